@@ -2,6 +2,11 @@ use std::env;
 use std::path::Path;
 
 fn main() {
+	// The binary floating point backend does not use the Intel library
+	if env::var_os("CARGO_FEATURE_BINARY64").is_some() {
+		return;
+	}
+
 	let source_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
 	let lib_path = Path::new(&source_dir).join("lib");
 
@@ -25,6 +30,22 @@ fn main() {
 			);
 			println!("cargo:rustc-link-lib=static=bid");
 		}
+		"wasm32-unknown-unknown" => {
+			// Not provided with the source. It must be built with a C compiler that
+			// targets WebAssembly and placed in this directory.
+			let lib_path = lib_path.join("wasm32");
+			if !lib_path.join("libbid.a").exists() {
+				panic!(
+					"Intel floating point library for WebAssembly not found in '{}'",
+					lib_path.to_str().unwrap()
+				);
+			}
+			println!(
+				"cargo:rustc-link-search=native={}",
+				lib_path.to_str().unwrap()
+			);
+			println!("cargo:rustc-link-lib=static=bid");
+		}
 		_ => panic!(
 			"Intel floating point library not pre-compiled for target '{}'",
 			target
@@ -0,0 +1,449 @@
+use crate::RoundingMode;
+use core::f64::consts::PI;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Coefficients of the Lanczos approximation used for the gamma functions, which
+/// the standard library does not provide
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS: [f64; 9] = [
+	0.99999999999980993,
+	676.5203681218851,
+	-1259.1392167224028,
+	771.32342877765313,
+	-176.61502916214059,
+	12.507343278686905,
+	-0.13857109526572012,
+	9.9843695780195716e-6,
+	1.5056327351493116e-7,
+];
+
+/// Number of terms used for the continued fraction of erfc
+const ERFC_TERMS: usize = 60;
+
+/// Selected rounding mode. Binary arithmetic always rounds to the nearest even
+/// value, so the mode is only stored. Displayed numbers are rounded with the mode
+/// of their format instead.
+static ROUNDING_MODE: AtomicU8 = AtomicU8::new(RoundingMode::NearestEven as u8);
+
+/// Binary floating point backend with the same API as the Intel decimal library.
+/// It is much faster on targets without a decimal library, but only has about 15
+/// digits of precision. The raw form holds the bits of the value in the first part,
+/// so saved values can't be moved between the two backends.
+#[derive(Clone)]
+pub struct Decimal {
+	parts: [u64; 2],
+}
+
+impl Decimal {
+	/// Significant digits that a binary64 value can hold without loss. Values are
+	/// rounded to this many digits when converted to text, so that a result such as
+	/// 0.1 + 0.2 is shown as the decimal number that was meant.
+	pub const DIGITS: usize = 15;
+
+	fn from_value(value: f64) -> Self {
+		Decimal {
+			parts: [value.to_bits(), 0],
+		}
+	}
+
+	fn value(&self) -> f64 {
+		f64::from_bits(self.parts[0])
+	}
+
+	pub fn new() -> Self {
+		0.into()
+	}
+
+	pub fn zero() -> Self {
+		0.into()
+	}
+
+	pub fn from_str(string: &str) -> Self {
+		Self::from_value(string.parse().unwrap_or(f64::NAN))
+	}
+
+	pub fn pi() -> Self {
+		Self::from_value(PI)
+	}
+
+	pub fn rounding_mode() -> RoundingMode {
+		RoundingMode::from_u8(ROUNDING_MODE.load(Ordering::Relaxed))
+			.unwrap_or(RoundingMode::NearestEven)
+	}
+
+	/// Stores the rounding mode without applying it to arithmetic, which always
+	/// rounds to the nearest even value
+	pub fn set_rounding_mode(mode: RoundingMode) {
+		ROUNDING_MODE.store(mode as u8, Ordering::Relaxed);
+	}
+
+	/// Converts to text in the same form as the Intel library, which is a sign
+	/// followed by the digits and the exponent of the last digit
+	pub fn to_string(&self) -> String {
+		let value = self.value();
+		if value.is_nan() {
+			return "+NaN".to_string();
+		}
+		let sign = if value.is_sign_negative() { "-" } else { "+" };
+		if value.is_infinite() {
+			return sign.to_string() + "Inf";
+		}
+
+		let text = format!("{:.*e}", Self::DIGITS - 1, value.abs());
+		let (mantissa, exponent) = text.split_at(text.find('e').unwrap());
+		let exponent: isize = exponent[1..].parse().unwrap();
+		let digits: String = mantissa.chars().filter(|ch| ch.is_ascii_digit()).collect();
+		let digits = digits.trim_end_matches('0');
+		if digits.len() == 0 {
+			return sign.to_string() + "0E+0";
+		}
+		format!(
+			"{}{}E{}",
+			sign,
+			digits,
+			exponent - (digits.len() as isize - 1)
+		)
+	}
+
+	pub fn sqrt(&self) -> Self {
+		Self::from_value(self.value().sqrt())
+	}
+
+	pub fn exp(&self) -> Self {
+		Self::from_value(self.value().exp())
+	}
+
+	pub fn ln(&self) -> Self {
+		Self::from_value(self.value().ln())
+	}
+
+	pub fn pow(&self, power: &Self) -> Self {
+		Self::from_value(self.value().powf(power.value()))
+	}
+
+	pub fn sin(&self) -> Self {
+		Self::from_value(self.value().sin())
+	}
+
+	pub fn cos(&self) -> Self {
+		Self::from_value(self.value().cos())
+	}
+
+	pub fn tan(&self) -> Self {
+		Self::from_value(self.value().tan())
+	}
+
+	pub fn asin(&self) -> Self {
+		Self::from_value(self.value().asin())
+	}
+
+	pub fn acos(&self) -> Self {
+		Self::from_value(self.value().acos())
+	}
+
+	pub fn atan(&self) -> Self {
+		Self::from_value(self.value().atan())
+	}
+
+	pub fn sinh(&self) -> Self {
+		Self::from_value(self.value().sinh())
+	}
+
+	pub fn cosh(&self) -> Self {
+		Self::from_value(self.value().cosh())
+	}
+
+	pub fn tanh(&self) -> Self {
+		Self::from_value(self.value().tanh())
+	}
+
+	pub fn asinh(&self) -> Self {
+		Self::from_value(self.value().asinh())
+	}
+
+	pub fn acosh(&self) -> Self {
+		Self::from_value(self.value().acosh())
+	}
+
+	pub fn atanh(&self) -> Self {
+		Self::from_value(self.value().atanh())
+	}
+
+	pub fn ln_1p(&self) -> Self {
+		Self::from_value(self.value().ln_1p())
+	}
+
+	pub fn exp_m1(&self) -> Self {
+		Self::from_value(self.value().exp_m1())
+	}
+
+	pub fn log10(&self) -> Self {
+		Self::from_value(self.value().log10())
+	}
+
+	pub fn log2(&self) -> Self {
+		Self::from_value(self.value().log2())
+	}
+
+	pub fn exp10(&self) -> Self {
+		Self::from_value(10.0f64.powf(self.value()))
+	}
+
+	pub fn exp2(&self) -> Self {
+		Self::from_value(self.value().exp2())
+	}
+
+	pub fn erf(&self) -> Self {
+		Self::from_value(erf(self.value()))
+	}
+
+	pub fn erfc(&self) -> Self {
+		Self::from_value(erfc(self.value()))
+	}
+
+	pub fn tgamma(&self) -> Self {
+		Self::from_value(gamma(self.value()))
+	}
+
+	pub fn lgamma(&self) -> Self {
+		Self::from_value(ln_gamma(self.value()))
+	}
+
+	pub fn cbrt(&self) -> Self {
+		Self::from_value(self.value().cbrt())
+	}
+
+	pub fn fract(&self) -> Self {
+		Self::from_value(self.value().fract())
+	}
+
+	pub fn trunc(&self) -> Self {
+		Self::from_value(self.value().trunc())
+	}
+
+	pub fn abs(&self) -> Self {
+		Self::from_value(self.value().abs())
+	}
+
+	pub fn classify(&self) -> core::num::FpCategory {
+		self.value().classify()
+	}
+
+	pub fn is_sign_negative(&self) -> bool {
+		self.value().is_sign_negative()
+	}
+
+	pub fn is_sign_positive(&self) -> bool {
+		self.value().is_sign_positive()
+	}
+
+	pub fn is_normal(&self) -> bool {
+		self.value().is_normal()
+	}
+
+	pub fn is_finite(&self) -> bool {
+		self.value().is_finite()
+	}
+
+	pub fn is_infinite(&self) -> bool {
+		self.value().is_infinite()
+	}
+
+	pub fn is_nan(&self) -> bool {
+		self.value().is_nan()
+	}
+
+	pub fn fma(x: &Self, y: &Self, z: &Self) -> Self {
+		Self::from_value(x.value().mul_add(y.value(), z.value()))
+	}
+
+	pub fn atan2(x: &Self, y: &Self) -> Self {
+		Self::from_value(x.value().atan2(y.value()))
+	}
+
+	pub fn hypot(x: &Self, y: &Self) -> Self {
+		Self::from_value(x.value().hypot(y.value()))
+	}
+
+	pub fn from_raw(value: [u64; 2]) -> Self {
+		Decimal { parts: value }
+	}
+
+	pub fn to_raw(&self) -> &[u64; 2] {
+		&self.parts
+	}
+}
+
+fn is_non_positive_integer(x: f64) -> bool {
+	x <= 0.0 && x == x.trunc()
+}
+
+fn lanczos_sum(x: f64) -> f64 {
+	let mut sum = LANCZOS[0];
+	for (i, coefficient) in LANCZOS.iter().enumerate().skip(1) {
+		sum += coefficient / (x + i as f64);
+	}
+	sum
+}
+
+fn gamma(x: f64) -> f64 {
+	if is_non_positive_integer(x) {
+		f64::NAN
+	} else if x < 0.5 {
+		// Reflection formula
+		PI / ((PI * x).sin() * gamma(1.0 - x))
+	} else {
+		let x = x - 1.0;
+		let t = x + LANCZOS_G + 0.5;
+		// The power is split in two so that it doesn't overflow before the
+		// exponential brings it back into range
+		let half_power = t.powf((x + 0.5) / 2.0);
+		(2.0 * PI).sqrt() * half_power * ((-t).exp() * half_power) * lanczos_sum(x)
+	}
+}
+
+fn ln_gamma(x: f64) -> f64 {
+	if is_non_positive_integer(x) {
+		f64::INFINITY
+	} else if x < 0.5 {
+		(PI / (PI * x).sin().abs()).ln() - ln_gamma(1.0 - x)
+	} else {
+		let x = x - 1.0;
+		let t = x + LANCZOS_G + 0.5;
+		0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + lanczos_sum(x).ln()
+	}
+}
+
+fn erf(x: f64) -> f64 {
+	if x.abs() >= 2.0 {
+		return x.signum() * (1.0 - erfc(x.abs()));
+	}
+
+	// Maclaurin series, which converges quickly for small values
+	let x2 = x * x;
+	let mut term = x;
+	let mut sum = x;
+	let mut n = 0.0;
+	loop {
+		n += 1.0;
+		term *= -x2 / n;
+		let next = term / (2.0 * n + 1.0);
+		sum += next;
+		if next.abs() <= 1e-17 * sum.abs() {
+			break;
+		}
+	}
+	sum * 2.0 / PI.sqrt()
+}
+
+fn erfc(x: f64) -> f64 {
+	if x.is_nan() {
+		return x;
+	}
+	if x < 2.0 {
+		return 1.0 - erf(x);
+	}
+
+	// Continued fraction, evaluated from the last term
+	let mut fraction = x;
+	for n in (1..=ERFC_TERMS).rev() {
+		fraction = x + (n as f64 / 2.0) / fraction;
+	}
+	(-x * x).exp() / (PI.sqrt() * fraction)
+}
+
+impl From<i32> for Decimal {
+	fn from(value: i32) -> Self {
+		Self::from_value(value as f64)
+	}
+}
+
+impl From<u32> for Decimal {
+	fn from(value: u32) -> Self {
+		Self::from_value(value as f64)
+	}
+}
+
+impl From<i64> for Decimal {
+	fn from(value: i64) -> Self {
+		Self::from_value(value as f64)
+	}
+}
+
+impl From<u64> for Decimal {
+	fn from(value: u64) -> Self {
+		Self::from_value(value as f64)
+	}
+}
+
+impl From<f32> for Decimal {
+	fn from(value: f32) -> Self {
+		Self::from_value(value as f64)
+	}
+}
+
+impl From<f64> for Decimal {
+	fn from(value: f64) -> Self {
+		Self::from_value(value)
+	}
+}
+
+macro_rules! binary_operator {
+	($trait: ident, $func: ident, $assign_trait: ident, $assign_func: ident, $op: tt) => {
+		impl core::ops::$trait for Decimal {
+			type Output = Self;
+
+			fn $func(self, rhs: Self) -> Self::Output {
+				Self::from_value(self.value() $op rhs.value())
+			}
+		}
+
+		impl core::ops::$trait for &Decimal {
+			type Output = Decimal;
+
+			fn $func(self, rhs: Self) -> Self::Output {
+				Decimal::from_value(self.value() $op rhs.value())
+			}
+		}
+
+		impl core::ops::$assign_trait for Decimal {
+			fn $assign_func(&mut self, rhs: Self) {
+				*self = Self::from_value(self.value() $op rhs.value());
+			}
+		}
+	};
+}
+
+binary_operator!(Add, add, AddAssign, add_assign, +);
+binary_operator!(Sub, sub, SubAssign, sub_assign, -);
+binary_operator!(Mul, mul, MulAssign, mul_assign, *);
+binary_operator!(Div, div, DivAssign, div_assign, /);
+binary_operator!(Rem, rem, RemAssign, rem_assign, %);
+
+impl core::ops::Neg for Decimal {
+	type Output = Self;
+
+	fn neg(self) -> Self::Output {
+		Self::from_value(-self.value())
+	}
+}
+
+impl core::ops::Neg for &Decimal {
+	type Output = Decimal;
+
+	fn neg(self) -> Self::Output {
+		Decimal::from_value(-self.value())
+	}
+}
+
+impl core::cmp::PartialEq for Decimal {
+	fn eq(&self, other: &Self) -> bool {
+		self.value() == other.value()
+	}
+}
+
+impl core::cmp::PartialOrd for Decimal {
+	fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+		self.value().partial_cmp(&other.value())
+	}
+}
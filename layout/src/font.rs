@@ -32,6 +32,27 @@ impl Font {
 	}
 }
 
+/// Fonts used to render stack entries. Values are laid out starting from the value
+/// font, which steps down to smaller fonts for fractions and alternate
+/// representations. Labels and stack messages use the label font.
+#[derive(Clone, PartialEq, Eq, Copy)]
+pub struct FontSet {
+	pub value: Font,
+	pub label: Font,
+}
+
+impl FontSet {
+	/// Font set for stack values rendered starting with `value`. Smaller value
+	/// fonts use smaller labels so that more stack levels fit on screen.
+	pub fn new(value: Font) -> Self {
+		let label = match value {
+			Font::Large | Font::Medium => Font::Small,
+			Font::Small | Font::Smallest => Font::Smallest,
+		};
+		FontSet { value, label }
+	}
+}
+
 pub trait FontMetrics {
 	fn width(&self, font: Font, text: &str) -> i32;
 	fn advance(&self, font: Font, text: &str) -> i32;
@@ -7,7 +7,7 @@ use alloc::string::String;
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Rect {
 	pub x: i32,
 	pub y: i32,
@@ -20,6 +20,10 @@ impl Rect {
 		Rect { x, y, w, h }
 	}
 
+	pub fn contains(&self, x: i32, y: i32) -> bool {
+		x >= self.x && y >= self.y && x < self.x + self.w && y < self.y + self.h
+	}
+
 	pub fn clipped_to(&self, area: &Rect) -> Self {
 		let Rect {
 			mut x,
@@ -103,7 +107,7 @@ pub trait LayoutRenderer {
 	fn set_selection_state(&mut self, selected: bool);
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Layout {
 	Text(String, Font, TokenType),
 	StaticText(&'static str, Font, TokenType),
@@ -114,6 +118,7 @@ pub enum Layout {
 	Vertical(Vec<Layout>),
 	Fraction(Box<Layout>, Box<Layout>, TokenType),
 	Power(Box<Layout>, Box<Layout>),
+	Overline(Box<Layout>, TokenType),
 	HorizontalSpace(i32),
 	VerticalSpace(i32),
 	HorizontalRule,
@@ -170,6 +175,7 @@ impl Layout {
 				core::cmp::max(numer.width(metrics), denom.width(metrics))
 			}
 			Layout::Power(base, power) => base.width(metrics) + power.width(metrics),
+			Layout::Overline(item, _) => item.width(metrics),
 			Layout::HorizontalSpace(width) => *width,
 			Layout::VerticalSpace(_) => 0,
 			Layout::HorizontalRule => 0,
@@ -205,6 +211,7 @@ impl Layout {
 				}
 				max_height
 			}
+			Layout::Overline(item, _) => item.height(metrics) + 2,
 			Layout::HorizontalSpace(_) => 0,
 			Layout::VerticalSpace(height) => *height,
 			Layout::HorizontalRule => 1,
@@ -403,6 +410,29 @@ impl Layout {
 					clip_rect,
 				);
 			}
+			Layout::Overline(item, token_type) => {
+				// Render the item below the line, leaving a gap between them
+				item.render(
+					renderer,
+					Rect {
+						x: rect.x,
+						y: rect.y + 2,
+						w: rect.w,
+						h: rect.h - 2,
+					},
+					clip_rect,
+				);
+				renderer.fill(
+					&Rect {
+						x: rect.x,
+						y: rect.y,
+						w: rect.w,
+						h: 1,
+					}
+					.clipped_to(clip_rect),
+					*token_type,
+				);
+			}
 			Layout::VerticalSpace(_) | Layout::HorizontalSpace(_) => (),
 			Layout::LeftAlign(item) => {
 				let width = item.width(renderer.metrics());
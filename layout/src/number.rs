@@ -2,7 +2,9 @@ use crate::font::{Font, FontMetrics};
 use crate::layout::{Layout, TokenType};
 use crate::string::StringLayout;
 use num_bigint::{BigInt, BigUint, ToBigInt};
-use rscalc_math::format::{Format, FormatMode, FormatResult, MAX_SHORT_DISPLAY_BITS};
+use rscalc_math::format::{
+	Format, FormatMode, FormatResult, FractionDisplay, OverflowMode, MAX_SHORT_DISPLAY_BITS,
+};
 use rscalc_math::number::Number;
 
 #[cfg(not(feature = "std"))]
@@ -10,8 +12,17 @@ use alloc::boxed::Box;
 #[cfg(not(feature = "std"))]
 use alloc::string::ToString;
 #[cfg(not(feature = "std"))]
+use alloc::vec;
+#[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 
+// Maximum number of lines an integer may be wrapped onto in a stack entry
+const MAX_WRAPPED_LINES: usize = 4;
+
+// Marker shown before a decimal that is not the exact value of the rational
+// number it displays
+const APPROXIMATE_MARKER: &str = "≈";
+
 pub trait NumberFormatResultToToken {
 	fn token_type(&self) -> TokenType;
 }
@@ -81,6 +92,68 @@ fn rational_layout(
 	}
 }
 
+/// Lays out a fraction with the numerator and denominator each wrapped onto
+/// more than one line, for fractions too wide to show otherwise
+fn wrapped_rational_layout(
+	num: &BigInt,
+	denom: &BigUint,
+	format: &Format,
+	font: Font,
+	metrics: &dyn FontMetrics,
+	max_width: i32,
+) -> Option<Layout> {
+	let max_lines = MAX_WRAPPED_LINES / 2;
+	let num_layout = format.format_bigint(num).wrapped_layout(
+		font,
+		TokenType::Integer,
+		metrics,
+		max_width,
+		max_lines,
+	)?;
+	let denom_layout = format
+		.format_bigint(&denom.to_bigint().unwrap())
+		.wrapped_layout(font, TokenType::Integer, metrics, max_width, max_lines)?;
+	Some(Layout::Fraction(
+		Box::new(num_layout),
+		Box::new(denom_layout),
+		TokenType::Integer,
+	))
+}
+
+/// Width taken by the approximation marker, or zero if the value is shown
+/// exactly
+fn approximate_marker_width(
+	value: &Number,
+	format: &Format,
+	font: Font,
+	metrics: &dyn FontMetrics,
+) -> i32 {
+	match value {
+		Number::Rational(num, denom) if !format.is_exact_decimal(num, denom) => {
+			metrics.width(font, APPROXIMATE_MARKER) + 2
+		}
+		_ => 0,
+	}
+}
+
+/// Adds the approximation marker before a layout if it has a width
+fn with_approximate_marker(layout: Layout, marker_width: i32, font: Font) -> Layout {
+	if marker_width == 0 {
+		return layout;
+	}
+	let items = vec![
+		Layout::StaticText(APPROXIMATE_MARKER, font, TokenType::Label),
+		Layout::HorizontalSpace(2),
+		layout,
+	];
+	Layout::Horizontal(items)
+}
+
+/// Whether rationals are laid out as fractions instead of decimals
+fn shows_fractions(format: &Format) -> bool {
+	format.mode == FormatMode::Rational && format.fraction_display != FractionDisplay::Decimal
+}
+
 pub trait NumberLayout {
 	fn single_line_layout(
 		&self,
@@ -110,8 +183,19 @@ impl NumberLayout for Number {
 		metrics: &dyn FontMetrics,
 		max_width: i32,
 	) -> Option<Layout> {
+		// Reduction may have been deferred by the calculation that gave the number
+		if !self.is_reduced() {
+			return self.reduced().single_line_layout(
+				format,
+				default_font,
+				small_font,
+				metrics,
+				max_width,
+			);
+		}
+
 		if let Number::Rational(num, denom) = self {
-			if format.mode == FormatMode::Rational {
+			if shows_fractions(format) {
 				// Rational number, try to lay out as a fraction
 				if let Some(layout) = rational_layout(
 					num,
@@ -128,15 +212,17 @@ impl NumberLayout for Number {
 		}
 
 		// Render full string of value and see if it fits
+		let marker_width = approximate_marker_width(self, format, small_font, metrics);
 		let format_result = format.format_number(self);
 		let token_type = format_result.token_type();
-		format_result.to_string().single_line_layout(
+		let layout = format_result.to_string().single_line_layout(
 			default_font,
 			token_type,
 			metrics,
-			max_width,
+			max_width - marker_width,
 			None,
-		)
+		)?;
+		Some(with_approximate_marker(layout, marker_width, small_font))
 	}
 
 	fn double_line_layout(
@@ -147,8 +233,18 @@ impl NumberLayout for Number {
 		metrics: &dyn FontMetrics,
 		max_width: i32,
 	) -> Option<(Layout, bool)> {
+		if !self.is_reduced() {
+			return self.reduced().double_line_layout(
+				format,
+				default_font,
+				small_font,
+				metrics,
+				max_width,
+			);
+		}
+
 		if let Number::Rational(num, denom) = self {
-			if format.mode == FormatMode::Rational {
+			if shows_fractions(format) {
 				// Rational number, try to lay out as a fraction
 				if let Some(layout) = rational_layout(
 					num,
@@ -161,23 +257,63 @@ impl NumberLayout for Number {
 				) {
 					return Some((layout, true));
 				}
+
+				// Fractions that are too wide are wrapped when the setting asks
+				// for them to always be shown
+				if format.fraction_display == FractionDisplay::Fraction {
+					if let Some(layout) = wrapped_rational_layout(
+						num,
+						denom,
+						format,
+						small_font.smaller(),
+						metrics,
+						max_width,
+					) {
+						return Some((layout, true));
+					}
+				}
 			}
 		}
 
 		// Render full string of value and see if it fits
+		let marker_width = approximate_marker_width(self, format, small_font, metrics);
 		let format_result = format.format_number(self);
 		let token_type = format_result.token_type();
-		if let Some(layout) = format_result.to_string().double_line_layout(
+		let is_integer = matches!(format_result, FormatResult::Integer(_));
+		let string = format_result.to_string();
+		if let Some(layout) = string.double_line_layout(
 			default_font,
 			small_font,
 			token_type,
 			metrics,
-			max_width,
+			max_width - marker_width,
 			None,
 		) {
-			Some((layout, false))
-		} else {
-			None
+			return Some((
+				with_approximate_marker(layout, marker_width, small_font),
+				false,
+			));
+		}
+
+		// Integers must show every digit, so if it is too long for two lines display
+		// it according to the overflow mode instead of converting to a decimal
+		if !is_integer {
+			return None;
+		}
+		match format.overflow {
+			OverflowMode::Wrap => string
+				.wrapped_layout(
+					small_font.smaller(),
+					token_type,
+					metrics,
+					max_width,
+					MAX_WRAPPED_LINES,
+				)
+				.map(|layout| (layout, false)),
+			OverflowMode::Ellipsis => Some((
+				string.ellipsis_layout(small_font, token_type, metrics, max_width),
+				false,
+			)),
 		}
 	}
 }
@@ -1,25 +1,25 @@
-use crate::font::Font;
+use crate::font::{Font, FontMetrics, FontSet};
 use crate::layout::{Layout, LayoutRenderer, Rect, TokenType};
 use crate::value::ValueLayout;
 use rscalc_math::format::Format;
 use rscalc_math::number::Number;
 use rscalc_math::stack::{Stack, StackEvent};
+use rscalc_math::unit::AngleUnit;
+use rscalc_math::value::{Value, ValueRef};
 
 #[cfg(feature = "std")]
 use std::cell::RefCell;
 #[cfg(feature = "std")]
-use std::collections::BTreeMap;
-#[cfg(feature = "std")]
 use std::rc::Rc;
 
 #[cfg(not(feature = "std"))]
 use alloc::boxed::Box;
 #[cfg(not(feature = "std"))]
-use alloc::collections::BTreeMap;
-#[cfg(not(feature = "std"))]
 use alloc::rc::Rc;
 #[cfg(not(feature = "std"))]
-use alloc::string::ToString;
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 #[cfg(not(feature = "std"))]
 use core::cell::RefCell;
 
@@ -28,21 +28,54 @@ struct CachedStackEntryLayout {
 	width: i32,
 	idx: usize,
 	bottom: i32,
-	layout: Layout,
+	layout: Rc<Layout>,
+	tag: Option<&'static str>,
+}
+
+/// Layout of a stored value, which stays valid for as long as the value and the
+/// format stay the same, wherever the value is on the stack
+struct CachedValueLayout {
+	value: ValueRef,
+	width: i32,
+	layout: Rc<Layout>,
+	tag: Option<&'static str>,
 }
 
+/// Draws the stack, reusing the layouts of entries that have not changed. Once
+/// the caches are filled, rendering an unchanged stack does not allocate: the
+/// caches and the label text are kept in buffers that are reused on each render,
+/// and layouts are shared rather than copied.
 pub struct StackRenderer {
-	render_cache: BTreeMap<usize, CachedStackEntryLayout>,
+	/// Layout of each stack entry as it was last drawn, indexed by stack position
+	render_cache: Vec<Option<CachedStackEntryLayout>>,
+	value_cache: Vec<CachedValueLayout>,
+	/// Storage for the value cache of the next render
+	spare_value_cache: Vec<CachedValueLayout>,
+	label_buffer: String,
+	/// Message shown in place of an empty stack, along with the font it is in
+	empty_message: Option<(Font, Layout)>,
+	value_cache_format: Option<Format>,
+	prev_render: Option<(Rect, usize)>,
 	prev_render_top: i32,
 	prev_render_area_top: i32,
+	prev_empty_message_bottom: Option<i32>,
+	cache_enabled: bool,
 }
 
 impl StackRenderer {
 	pub fn new(stack: &mut Stack) -> Rc<RefCell<StackRenderer>> {
 		let renderer = Rc::new(RefCell::new(StackRenderer {
-			render_cache: BTreeMap::new(),
+			render_cache: Vec::new(),
+			value_cache: Vec::new(),
+			spare_value_cache: Vec::new(),
+			label_buffer: String::new(),
+			empty_message: None,
+			value_cache_format: None,
+			prev_render: None,
 			prev_render_top: 0,
 			prev_render_area_top: 0,
+			prev_empty_message_bottom: None,
+			cache_enabled: true,
 		}));
 
 		// Register to get notifications for stack changes
@@ -55,40 +88,33 @@ impl StackRenderer {
 	}
 
 	fn event(&mut self, event: &StackEvent) {
+		self.prev_render = None;
+
 		// Update rendering cache for stack changes
 		match event {
-			StackEvent::ValuePushed => {
-				let mut new_cache = BTreeMap::new();
-				for (key, value) in &self.render_cache {
-					new_cache.insert(key + 1, value.clone());
-				}
-				self.render_cache = new_cache;
+			StackEvent::ValuePushed | StackEvent::RotateUp => {
+				self.render_cache.insert(0, None);
 			}
 			StackEvent::ValuePopped => {
-				let mut new_cache = BTreeMap::new();
-				for (key, value) in &self.render_cache {
-					if key > &0 {
-						new_cache.insert(key - 1, value.clone());
-					}
+				if !self.render_cache.is_empty() {
+					self.render_cache.remove(0);
 				}
-				self.render_cache = new_cache;
 			}
 			StackEvent::ValueChanged(idx) => {
-				self.render_cache.remove(idx);
-			}
-			StackEvent::TopReplacedWithEntries(count) => {
-				let mut new_cache = BTreeMap::new();
-				for (key, value) in &self.render_cache {
-					new_cache.insert(key + count - 1, value.clone());
+				if let Some(entry) = self.render_cache.get_mut(*idx) {
+					*entry = None;
 				}
-				self.render_cache = new_cache;
 			}
-			StackEvent::RotateUp => {
-				let mut new_cache = BTreeMap::new();
-				for (key, value) in &self.render_cache {
-					new_cache.insert(key + 1, value.clone());
+			StackEvent::TopReplacedWithEntries(count) => {
+				if *count == 0 {
+					if !self.render_cache.is_empty() {
+						self.render_cache.remove(0);
+					}
+				} else {
+					for _ in 1..*count {
+						self.render_cache.insert(0, None);
+					}
 				}
-				self.render_cache = new_cache;
 			}
 			StackEvent::Invalidate => {
 				self.render_cache.clear();
@@ -97,20 +123,88 @@ impl StackRenderer {
 	}
 
 	pub fn force_refresh(&mut self) {
-		for (_, value) in self.render_cache.iter_mut() {
+		self.prev_render = None;
+		for value in self.render_cache.iter_mut().flatten() {
 			// Set bottom coordinate to an invalid position to force
 			// rerendering the entry
 			value.bottom = i32::MAX;
 		}
 		self.prev_render_top = 0;
 		self.prev_render_area_top = 0;
+		self.prev_empty_message_bottom = None;
+	}
+
+	pub fn cache_enabled(&self) -> bool {
+		self.cache_enabled
+	}
+
+	/// Enables or disables reuse of previously rendered stack entries. With the cache
+	/// disabled, every entry is laid out and drawn again on each render.
+	pub fn set_cache_enabled(&mut self, value: bool) {
+		self.cache_enabled = value;
+		self.invalidate_rendering();
 	}
 
 	pub fn invalidate_rendering(&mut self) {
 		// Clear everything as values may have changed representation
 		self.render_cache.clear();
+		self.value_cache.clear();
+		self.spare_value_cache.clear();
+		self.value_cache_format = None;
+		self.prev_render = None;
 		self.prev_render_top = 0;
 		self.prev_render_area_top = 0;
+		self.prev_empty_message_bottom = None;
+	}
+
+	/// Returns true if the stack has not changed since it was last rendered into the
+	/// same area, so that drawing it again would leave the screen as it is
+	pub fn is_current(&self, area: &Rect, label_offset: usize) -> bool {
+		self.cache_enabled && self.prev_render == Some((area.clone(), label_offset))
+	}
+
+	/// Position on the stack of the entry that was last drawn at a point, if any
+	pub fn entry_at(&self, x: i32, y: i32, metrics: &dyn FontMetrics) -> Option<usize> {
+		let (area, _) = self.prev_render.as_ref()?;
+		if !area.contains(x, y) {
+			return None;
+		}
+		self.render_cache.iter().flatten().find_map(|entry| {
+			let height = entry.layout.height(metrics);
+			if y >= entry.bottom - height && y < entry.bottom {
+				Some(entry.idx)
+			} else {
+				None
+			}
+		})
+	}
+
+	/// Short text shown next to the label of a value that depends on a mode that
+	/// is not the default, so that values from before a mode was changed are not
+	/// misread
+	fn mode_tag(
+		stack: &Stack,
+		format: &Format,
+		value_ref: &ValueRef,
+		value: &Value,
+	) -> Option<&'static str> {
+		if !format.mode_tags {
+			return None;
+		}
+		match stack.angle_tag(value_ref) {
+			Some(AngleUnit::Radians) => return Some("Rad"),
+			Some(AngleUnit::Gradians) => return Some("Grad"),
+			_ => (),
+		}
+		match value {
+			Value::Number(Number::Integer(_)) => match format.integer_radix {
+				2 => Some("Bin"),
+				8 => Some("Oct"),
+				16 => Some("Hex"),
+				_ => None,
+			},
+			_ => None,
+		}
 	}
 
 	pub fn render(
@@ -118,44 +212,66 @@ impl StackRenderer {
 		stack: &Stack,
 		renderer: &mut dyn LayoutRenderer,
 		format: &Format,
-		base_font: Font,
+		fonts: &FontSet,
 		area: Rect,
 		label_offset: usize,
 	) {
 		let mut bottom = area.y + area.h;
-		let mut new_cache = BTreeMap::new();
+		let mut new_value_cache = core::mem::take(&mut self.spare_value_cache);
+		let mut label = core::mem::take(&mut self.label_buffer);
+		let mut rendered_entries = 0;
+		let mut empty_message_bottom = None;
+
+		// Formatted values can only be reused if they were laid out with the same format
+		if !self.cache_enabled || self.value_cache_format.as_ref() != Some(format) {
+			self.value_cache.clear();
+			self.value_cache_format = Some(format.clone());
+		}
 
 		if stack.len() == 0 && label_offset == 0 {
 			// Stack is empty, display a message instead of leaving the entire area blank
-			let layout = Layout::HorizontalCenter(Box::new(Layout::StaticText(
-				"⋘ Stack is empty ⋙",
-				Font::Small,
-				TokenType::Label,
-			)));
+			if self.empty_message.as_ref().map(|(font, _)| *font) != Some(fonts.label) {
+				self.empty_message = Some((
+					fonts.label,
+					Layout::HorizontalCenter(Box::new(Layout::StaticText(
+						"⋘ Stack is empty ⋙",
+						fonts.label,
+						TokenType::Label,
+					))),
+				));
+			}
+			let layout = &self.empty_message.as_ref().unwrap().1;
 
+			// Only draw the message if it is not already onscreen in the same position
 			let height = layout.height(renderer.metrics());
-			renderer.set_selection_state(false);
-			renderer.erase(
-				&Rect {
-					x: area.x,
-					y: bottom - height,
-					w: area.w,
-					h: height,
-				}
-				.clipped_to(&area),
-			);
+			if !self.cache_enabled
+				|| self.prev_empty_message_bottom != Some(bottom)
+				|| area.y != self.prev_render_area_top
+			{
+				renderer.set_selection_state(false);
+				renderer.erase(
+					&Rect {
+						x: area.x,
+						y: bottom - height,
+						w: area.w,
+						h: height,
+					}
+					.clipped_to(&area),
+				);
 
-			layout.render(
-				renderer,
-				Rect {
-					x: area.x,
-					y: bottom - height,
-					w: area.w,
-					h: height,
-				},
-				&area,
-			);
+				layout.render(
+					renderer,
+					Rect {
+						x: area.x,
+						y: bottom - height,
+						w: area.w,
+						h: height,
+					},
+					&area,
+				);
+			}
 
+			empty_message_bottom = Some(bottom);
 			bottom -= height;
 		}
 
@@ -165,23 +281,64 @@ impl StackRenderer {
 			}
 
 			renderer.set_selection_state(false);
+			rendered_entries = idx + 1;
 
 			// Construct and measure stack entry label
-			let label = if format.stack_xyz {
-				match idx + label_offset {
-					0 => "x".to_string(),
-					1 => "y".to_string(),
-					2 => "z".to_string(),
-					_ => Number::Integer((idx + label_offset + 1).into()).to_string(),
-				}
+			label.clear();
+			match idx + label_offset {
+				0 if format.stack_xyz => label.push('x'),
+				1 if format.stack_xyz => label.push('y'),
+				2 if format.stack_xyz => label.push('z'),
+				_ => format.write_count(&mut label, (idx + label_offset + 1) as u64),
+			}
+			label.push_str(": ");
+			let label_text_width = renderer.metrics().width(fonts.label, &label);
+			let metrics = renderer.metrics();
+			let label_width_with_tag = |tag: Option<&str>| match tag {
+				Some(tag) => 8 + label_text_width + metrics.width(Font::Smallest, tag),
+				None => 4 + label_text_width,
+			};
+
+			let value_ref = match stack.entry_ref(idx) {
+				Ok(value_ref) => value_ref.clone(),
+				Err(_) => continue,
+			};
+
+			// The tag of a value is kept along with its layout, as finding it needs the
+			// value itself
+			let cache = self.render_cache.get_mut(idx).and_then(Option::take);
+			let moved = if cache.is_none() && self.cache_enabled {
+				self.value_cache
+					.iter()
+					.find(|cache| cache.value.same_object(&value_ref))
 			} else {
-				Number::Integer((idx + label_offset + 1).into()).to_string()
+				None
+			};
+			let known_tag = match (&cache, moved) {
+				(Some(cache), _) => Some(cache.tag),
+				(None, Some(moved)) => Some(moved.tag),
+				(None, None) => None,
 			};
-			let label = label + ": ";
-			let label_width = 4 + renderer.metrics().width(Font::Small, &label);
+			let (tag, value) = match known_tag {
+				Some(tag) => (tag, None),
+				None => {
+					let entry = match value_ref.get() {
+						Ok(entry) => entry,
+						Err(_) => continue,
+					};
+					let entry = Stack::value_for_integer_mode(&format.integer_mode, entry);
+					(
+						Self::mode_tag(stack, format, &value_ref, &entry),
+						Some(entry),
+					)
+				}
+			};
+			let label_width = label_width_with_tag(tag);
 			let width = area.w - label_width - 8;
 
-			let layout = if let Some(cache) = self.render_cache.get(&idx) {
+			let layout = if !self.cache_enabled {
+				None
+			} else if let Some(cache) = cache {
 				// Check to see if this stack entry already been rendered to the screen in the
 				// correct position with the same index
 				let height = cache.layout.height(renderer.metrics());
@@ -192,7 +349,13 @@ impl StackRenderer {
 				{
 					// Entry is already onscreen, no need to rerender
 					bottom -= height;
-					new_cache.insert(idx, cache.clone());
+					new_value_cache.push(CachedValueLayout {
+						value: value_ref,
+						width: cache.width,
+						layout: cache.layout.clone(),
+						tag: cache.tag,
+					});
+					self.render_cache[idx] = Some(cache);
 					continue;
 				}
 
@@ -205,18 +368,38 @@ impl StackRenderer {
 				None
 			};
 
+			// Values that moved to a different place on the stack can keep their layout,
+			// as formatting large values is slow
+			let layout = layout.or_else(|| {
+				self.value_cache
+					.iter()
+					.find(|cache| cache.width == width && cache.value.same_object(&value_ref))
+					.map(|cache| cache.layout.clone())
+			});
+
 			let layout = if let Some(layout) = layout {
 				layout
 			} else {
 				// Render stack entry to a layout
-				let entry = match stack.entry(idx) {
-					Ok(entry) => entry,
-					Err(_) => continue,
+				let entry = match value {
+					Some(entry) => entry,
+					None => match value_ref.get() {
+						Ok(entry) => Stack::value_for_integer_mode(&format.integer_mode, entry),
+						Err(_) => continue,
+					},
 				};
-				let entry = Stack::value_for_integer_mode(&format.integer_mode, entry);
-				entry.layout(format, base_font, renderer.metrics(), width)
+				Rc::new(entry.layout(format, fonts.value, renderer.metrics(), width))
 			};
 
+			if self.cache_enabled {
+				new_value_cache.push(CachedValueLayout {
+					value: value_ref,
+					width,
+					layout: layout.clone(),
+					tag,
+				});
+			}
+
 			// Clear the area of the stack entry
 			let height = layout.height(renderer.metrics());
 			renderer.erase(
@@ -254,32 +437,52 @@ impl StackRenderer {
 			);
 
 			// Draw the label
-			let font_height = renderer.metrics().height(Font::Small);
+			let font_height = renderer.metrics().height(fonts.label);
 			renderer.draw_text(
 				4,
 				(bottom - height) + (height - font_height) / 2,
 				&label,
-				Font::Small,
+				fonts.label,
 				TokenType::Label,
 				&area,
 			);
+			if let Some(tag) = tag {
+				let tag_height = renderer.metrics().height(Font::Smallest);
+				renderer.draw_text(
+					8 + label_text_width,
+					(bottom - height) + (height - tag_height) / 2,
+					tag,
+					Font::Smallest,
+					TokenType::Label,
+					&area,
+				);
+			}
 
 			// Insert rendered entry into rendering cache so that it can be quickly rendered next
 			// time the screen is updated.
-			new_cache.insert(
+			if self.render_cache.len() <= idx {
+				self.render_cache.resize_with(idx + 1, || None);
+			}
+			self.render_cache[idx] = Some(CachedStackEntryLayout {
+				width,
 				idx,
-				CachedStackEntryLayout {
-					width,
-					idx,
-					bottom,
-					layout,
-				},
-			);
+				bottom,
+				layout,
+				tag,
+			});
 
 			bottom -= height;
 		}
 
-		self.render_cache = new_cache;
+		// Entries that were not drawn are no longer on screen
+		self.render_cache.truncate(rendered_entries);
+
+		// Keep the storage of both value caches for later renders
+		let mut old_value_cache = core::mem::replace(&mut self.value_cache, new_value_cache);
+		old_value_cache.clear();
+		self.spare_value_cache = old_value_cache;
+		self.label_buffer = label;
+		self.prev_empty_message_bottom = empty_message_bottom;
 
 		// If there is empty space above the stack, clear it now
 		if (bottom > area.y && bottom > self.prev_render_top) || area.y < self.prev_render_area_top
@@ -298,5 +501,6 @@ impl StackRenderer {
 		}
 		self.prev_render_top = bottom;
 		self.prev_render_area_top = area.y;
+		self.prev_render = Some((area, label_offset));
 	}
 }
@@ -25,6 +25,60 @@ pub trait StringLayout: Sized {
 		max_width: i32,
 		cursor: Option<usize>,
 	) -> Option<Layout>;
+
+	fn wrapped_layout(
+		&self,
+		font: Font,
+		token_type: TokenType,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+		max_lines: usize,
+	) -> Option<Layout>;
+
+	fn ellipsis_layout(
+		&self,
+		font: Font,
+		token_type: TokenType,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+	) -> Layout;
+}
+
+/// Determines how many characters from the end of `chars` fit within `max_width`.
+/// The split is moved to a nearby punctuation point if there is one.
+fn split_point(chars: &[char], font: Font, metrics: &dyn FontMetrics, max_width: i32) -> usize {
+	let mut split_point = 0;
+	let mut width = 0;
+	for i in 0..chars.len() {
+		let mut char_str = String::new();
+		char_str.push(chars[(chars.len() - 1) - i]);
+		split_point = i;
+		// Add in the width of this character
+		if i == 0 {
+			width += metrics.width(font, &char_str);
+		} else {
+			width += metrics.advance(font, &char_str);
+		}
+		if width > max_width {
+			break;
+		}
+	}
+
+	// Check for a puncuation point near the split point, and move the split
+	// there if there is one.
+	for i in 0..5 {
+		if i > split_point {
+			break;
+		}
+		match chars[(chars.len() - 1) - (split_point - i)] {
+			',' | '.' | 'x' | ' ' | '\'' | '_' | '\u{2009}' => {
+				split_point -= i;
+				break;
+			}
+			_ => (),
+		}
+	}
+	split_point
 }
 
 fn cursor_layout(string: &str, font: Font, token_type: TokenType, cursor: usize) -> Layout {
@@ -83,46 +137,29 @@ impl StringLayout for String {
 			return Some(layout);
 		}
 
-		// Check layout width for a single line in the smaller font
+		// Check layout width for a single line in the smaller font, then in the next
+		// size down, so that moderately long strings stay on one line
 		if let Some(layout) =
 			Self::single_line_layout(self, small_font, token_type, metrics, max_width, cursor)
 		{
 			return Some(layout);
 		}
-
-		// String does not fit, try to split it to two lines
-		let chars: Vec<char> = self.chars().collect();
-		let mut split_point = 0;
-		let mut width = 0;
-		for i in 0..chars.len() {
-			let mut char_str = String::new();
-			char_str.push(chars[(chars.len() - 1) - i]);
-			split_point = i;
-			// Add in the width of this character
-			if i == 0 {
-				width += metrics.width(small_font, &char_str);
-			} else {
-				width += metrics.advance(small_font, &char_str);
-			}
-			if width > max_width {
-				break;
+		if !small_font.is_smallest() {
+			if let Some(layout) = Self::single_line_layout(
+				self,
+				small_font.smaller(),
+				token_type,
+				metrics,
+				max_width,
+				cursor,
+			) {
+				return Some(layout);
 			}
 		}
 
-		// Check for a puncuation point near the split point, and move the split
-		// there if there is one.
-		for i in 0..5 {
-			if i > split_point {
-				break;
-			}
-			match chars[(chars.len() - 1) - (split_point - i)] {
-				',' | '.' | 'x' | ' ' | '\'' => {
-					split_point -= i;
-					break;
-				}
-				_ => (),
-			}
-		}
+		// String does not fit, try to split it to two lines
+		let chars: Vec<char> = self.chars().collect();
+		let split_point = split_point(&chars, small_font, metrics, max_width);
 
 		// Split the line into two lines
 		let (first, second) = chars.split_at(chars.len() - split_point);
@@ -154,4 +191,76 @@ impl StringLayout for String {
 			None
 		}
 	}
+
+	fn wrapped_layout(
+		&self,
+		font: Font,
+		token_type: TokenType,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+		max_lines: usize,
+	) -> Option<Layout> {
+		// Split lines off of the end of the string so that the first line holds the
+		// remaining partial line
+		let mut chars: Vec<char> = self.chars().collect();
+		let mut lines = Vec::new();
+		while !chars.is_empty() {
+			if lines.len() >= max_lines {
+				return None;
+			}
+
+			let remaining: String = chars.iter().collect();
+			if metrics.width(font, &remaining) <= max_width {
+				lines.push(Layout::Text(remaining, font, token_type));
+				break;
+			}
+
+			let split_point = split_point(&chars, font, metrics, max_width);
+			if split_point == 0 {
+				return None;
+			}
+			let line: String = chars.split_off(chars.len() - split_point).iter().collect();
+			lines.push(Layout::Text(line, font, token_type));
+		}
+
+		lines.reverse();
+		let layout = Layout::Vertical(lines);
+		if layout.width(metrics) <= max_width {
+			Some(layout)
+		} else {
+			None
+		}
+	}
+
+	fn ellipsis_layout(
+		&self,
+		font: Font,
+		token_type: TokenType,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+	) -> Layout {
+		if metrics.width(font, self) <= max_width {
+			return Layout::Text(self.clone(), font, token_type);
+		}
+
+		// Keep as many characters from the end of the string as will fit after the
+		// ellipsis, as the least significant digits are the ones at the end. The
+		// width is the sum of the advances plus the overhang of the last character,
+		// which is the same for every cut, so each character is measured once.
+		let chars: Vec<char> = self.chars().collect();
+		let overhang = metrics.width(font, self) - metrics.advance(font, self);
+		let mut width = metrics.advance(font, "…") + overhang;
+		let mut start = chars.len();
+		let mut encoded = [0; 4];
+		while start > 0 {
+			let advance = metrics.advance(font, chars[start - 1].encode_utf8(&mut encoded));
+			if width + advance > max_width {
+				break;
+			}
+			width += advance;
+			start -= 1;
+		}
+		let result = "…".to_string() + &chars[start..].iter().collect::<String>();
+		Layout::Text(result, font, token_type)
+	}
 }
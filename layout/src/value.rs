@@ -5,9 +5,12 @@ use crate::matrix::MatrixLayout;
 use crate::number::NumberLayout;
 use crate::string::StringLayout;
 use crate::unit::CompositeUnitLayout;
-use crate::vector::VectorLayout;
-use num_bigint::ToBigInt;
-use rscalc_math::format::{Format, FormatMode, MAX_SHORT_DISPLAY_BITS};
+use crate::vector::{VectorLayout, LIST_DELIMITERS, VECTOR_DELIMITERS};
+use num_bigint::{BigInt, BigUint};
+use rscalc_math::format::{
+	AlternateForm, AlternateValueClass, Format, FormatMode, MAX_SHORT_DISPLAY_BITS,
+};
+use rscalc_math::integer::IntegerValue;
 use rscalc_math::number::Number;
 use rscalc_math::value::Value;
 
@@ -19,8 +22,111 @@ use alloc::borrow::Cow;
 #[cfg(not(feature = "std"))]
 use alloc::boxed::Box;
 #[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+#[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 
+/// Layout of the decimal expansion of a rational if it repeats, with the
+/// repeating digits marked by a line above them
+fn repeating_decimal_layout(
+	format: &Format,
+	num: &BigInt,
+	denom: &BigUint,
+	font: Font,
+	metrics: &dyn FontMetrics,
+	max_width: i32,
+) -> Option<Layout> {
+	let (prefix, repetend) = format
+		.decimal_format()
+		.format_repeating_decimal(num, denom)?;
+
+	// Leave room above the leading digits to match the height of the line
+	let prefix_items = vec![
+		Layout::VerticalSpace(2),
+		Layout::Text(prefix, font, TokenType::Float),
+	];
+
+	let items = vec![
+		Layout::Vertical(prefix_items),
+		Layout::Overline(
+			Box::new(Layout::Text(repetend, font, TokenType::Float)),
+			TokenType::Float,
+		),
+	];
+	let layout = Layout::Horizontal(items);
+	if layout.width(metrics) > max_width {
+		None
+	} else {
+		Some(layout)
+	}
+}
+
+/// Strings of the alternate forms of a value that have been formatted. Each
+/// placement of the alternate form is tried in turn, and formatting large values
+/// is slow, so each form is formatted at most once.
+pub struct AlternateStrings {
+	strings: Vec<(AlternateForm, String)>,
+}
+
+impl AlternateStrings {
+	pub fn new() -> Self {
+		AlternateStrings {
+			strings: Vec::new(),
+		}
+	}
+
+	fn get<F: FnOnce() -> Option<String>>(
+		&mut self,
+		form: AlternateForm,
+		format_fn: F,
+	) -> Option<String> {
+		if let Some((_, string)) = self.strings.iter().find(|(cached, _)| *cached == form) {
+			return Some(string.clone());
+		}
+		let string = format_fn()?;
+		self.strings.push((form, string.clone()));
+		Some(string)
+	}
+}
+
+impl Default for AlternateStrings {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Returns a lower bound on the width of an integer with `bits` bits written in
+/// `radix`, so that integers that can't fit are never formatted
+fn min_integer_width(bits: u64, radix: u8, font: Font, metrics: &dyn FontMetrics) -> i32 {
+	// Thousandths of log2 of the radix, rounded up so that the number of digits is
+	// never overestimated
+	let log2_radix = match radix {
+		2 => 1000,
+		8 => 3000,
+		10 => 3322,
+		_ => 4000,
+	};
+	let digits = bits.saturating_sub(1) * 1000 / log2_radix + 1;
+
+	let digit_chars = if radix == 16 {
+		"0123456789ABCDEFabcdef"
+	} else {
+		&"0123456789"[..radix as usize]
+	};
+	let mut buf = [0; 4];
+	let digit_width = digit_chars
+		.chars()
+		.map(|ch| metrics.advance(font, ch.encode_utf8(&mut buf)))
+		.min()
+		.unwrap_or(0);
+	core::cmp::min(
+		digits.saturating_mul(digit_width.max(0) as u64),
+		i32::MAX as u64,
+	) as i32
+}
+
 pub enum AlternateLayoutType {
 	None,
 	Left,
@@ -63,20 +169,22 @@ pub trait ValueLayout {
 		max_width: i32,
 	) -> Layout;
 
-	fn alternate_hex_layout(
+	fn alternate_integer_layout(
 		&self,
 		format: &Format,
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		strings: &mut AlternateStrings,
 	) -> Option<Layout>;
 
-	fn alternate_float_layout(
+	fn alternate_real_layout(
 		&self,
 		format: &Format,
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		strings: &mut AlternateStrings,
 	) -> Option<Layout>;
 
 	fn add_alternate_layout(
@@ -86,8 +194,8 @@ pub trait ValueLayout {
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
-		alt_hex: bool,
-		alt_float: bool,
+		alt_integer: bool,
+		alt_real: bool,
 	) -> (Layout, AlternateLayoutType);
 }
 
@@ -148,7 +256,7 @@ impl ValueLayout for Value {
 							metrics,
 							max_width,
 							true,
-							is_rational,
+							is_rational || format.radicals,
 						)
 						.0;
 				}
@@ -178,44 +286,17 @@ impl ValueLayout for Value {
 				}
 			}
 			Value::Vector(vector) => {
-				// Vector, try to represent full form of vector entries in a single line. This is the
-				// preferred form because it can show rationals.
-				if let Some(layout) = vector.single_line_full_layout(
-					format,
-					base_font,
-					base_font.smaller(),
-					metrics,
-					max_width,
-				) {
-					return layout;
-				}
-
-				// Try a three line layout with full precision decimal form
 				if let Some(layout) =
-					vector.multi_line_layout(format, base_font.smaller(), metrics, max_width, 3)
+					vector.layout(format, base_font, metrics, max_width, VECTOR_DELIMITERS)
 				{
 					return layout;
 				}
-
-				// Try a three line layout with partial precision decimal form
-				if let Some(layout) = vector.multi_line_layout(
-					&format.with_max_precision(6),
-					base_font.smaller(),
-					metrics,
-					max_width,
-					3,
-				) {
-					return layout;
-				}
-
-				// Try a four line layout with smaller font
-				if let Some(layout) = vector.multi_line_layout(
-					&format.with_max_precision(6),
-					base_font.smaller().smaller(),
-					metrics,
-					max_width,
-					4,
-				) {
+			}
+			Value::List(list) => {
+				if let Some(layout) =
+					list.values()
+						.layout(format, base_font, metrics, max_width, LIST_DELIMITERS)
+				{
 					return layout;
 				}
 			}
@@ -459,96 +540,184 @@ impl ValueLayout for Value {
 		}
 	}
 
-	fn alternate_hex_layout(
+	fn alternate_integer_layout(
 		&self,
 		format: &Format,
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		strings: &mut AlternateStrings,
 	) -> Option<Layout> {
 		if max_width <= 0 {
 			return None;
 		}
-		match self.real_number() {
-			Ok(Number::Integer(int)) => {
-				// Integer, if number is ten or greater check for the
-				// hexadecimal alternate form
-				if format.show_alt_hex
-					&& (format.integer_radix != 10
-						|| format.mode == FormatMode::Normal
-						|| format.mode == FormatMode::Rational)
-					&& (int <= &-10.to_bigint().unwrap()
-						|| int >= &10.to_bigint().unwrap()
-						|| int <= &(-(format.integer_radix as i8)).to_bigint().unwrap()
-						|| int >= &(format.integer_radix as i8).to_bigint().unwrap())
-					&& int.bits() <= MAX_SHORT_DISPLAY_BITS
-				{
-					// There is an alternate form to display, try to generate a single
-					// line layout for it.
-					let string = if format.integer_radix == 10 {
-						self.format(&format.hex_format())
-					} else {
-						self.format(&format.decimal_format())
-					};
-					string.to_string().single_line_layout(
-						font,
-						TokenType::Integer,
-						metrics,
-						max_width,
-						None,
-					)
+		let int = match self.real_number() {
+			Ok(Number::Integer(int)) => int,
+			_ => return None,
+		};
+		if (format.integer_radix == 10
+			&& format.mode != FormatMode::Normal
+			&& format.mode != FormatMode::Rational)
+			|| int.bits() > MAX_SHORT_DISPLAY_BITS
+		{
+			return None;
+		}
+
+		for form in format.alt_forms.enabled_forms(AlternateValueClass::Integer) {
+			let radix = match form.radix() {
+				Some(radix) if radix != format.integer_radix => radix,
+				_ => continue,
+			};
+
+			// Small values look the same in both radixes, only show the alternate
+			// form when it is different
+			let min = IntegerValue::from(core::cmp::min(radix, format.integer_radix));
+			if int > &-&min && int < &min {
+				continue;
+			}
+
+			if min_integer_width(int.bits(), radix, font, metrics) > max_width {
+				continue;
+			}
+
+			let string = strings.get(form, || {
+				Some(if radix == 10 {
+					self.format(&format.decimal_format()).to_string()
 				} else {
-					None
-				}
+					let alt_format = Format {
+						integer_radix: radix,
+						..format.hex_format()
+					};
+					let string = self.format(&alt_format).to_string();
+					if radix == 2 {
+						// Binary has no prefix in the main display, add one so that it
+						// can't be mistaken for a decimal value
+						match string.strip_prefix('-') {
+							Some(digits) => "-0b".to_string() + digits,
+							None => "0b".to_string() + &string,
+						}
+					} else {
+						string
+					}
+				})
+			})?;
+			if let Some(layout) =
+				string.single_line_layout(font, TokenType::Integer, metrics, max_width, None)
+			{
+				return Some(layout);
 			}
-			_ => None,
 		}
+		None
 	}
 
-	fn alternate_float_layout(
+	fn alternate_real_layout(
 		&self,
 		format: &Format,
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		strings: &mut AlternateStrings,
 	) -> Option<Layout> {
 		if max_width <= 0 {
 			return None;
 		}
 		match self {
-			Value::Number(Number::Rational(_, _))
-			| Value::NumberWithUnit(Number::Rational(_, _), _) => {
+			Value::Number(number) | Value::NumberWithUnit(number, _) if !number.is_reduced() => {
+				// Reduction may have been deferred by the calculation that gave the
+				// number
+				Value::Number(number.reduced().into_owned())
+					.alternate_real_layout(format, font, metrics, max_width, strings)
+			}
+			Value::Number(Number::Rational(num, denom))
+			| Value::NumberWithUnit(Number::Rational(num, denom), _) => {
 				// Real number in rational form
-				if format.show_alt_float && format.mode == FormatMode::Rational {
-					if let Ok(number) = self.real_number() {
-						let string = format.decimal_format().format_decimal(&number.to_decimal());
+				for form in format
+					.alt_forms
+					.enabled_forms(AlternateValueClass::Rational)
+				{
+					let string = match form {
+						AlternateForm::Float if format.mode == FormatMode::Rational => {
+							if let Some(layout) = repeating_decimal_layout(
+								format, num, denom, font, metrics, max_width,
+							) {
+								return Some(layout);
+							}
+							match strings.get(form, || {
+								let number = self.real_number().ok()?;
+								Some(format.decimal_format().format_decimal(&number.to_decimal()))
+							}) {
+								Some(string) => string,
+								None => continue,
+							}
+						}
+						AlternateForm::Fraction if format.mode != FormatMode::Rational => {
+							let min_width = min_integer_width(num.bits(), 10, font, metrics)
+								.saturating_add(min_integer_width(denom.bits(), 10, font, metrics));
+							if min_width > max_width {
+								continue;
+							}
+							match strings.get(form, || {
+								let decimal_format = format.decimal_format();
+								Some(
+									decimal_format.format_bigint(num)
+										+ "/" + &decimal_format
+										.format_bigint(&BigInt::from(denom.clone())),
+								)
+							}) {
+								Some(string) => string,
+								None => continue,
+							}
+						}
+						_ => continue,
+					};
+					if let Some(layout) =
 						string.single_line_layout(font, TokenType::Float, metrics, max_width, None)
-					} else {
-						None
+					{
+						return Some(layout);
 					}
-				} else {
-					None
 				}
+				None
+			}
+			Value::Number(number @ Number::Decimal(_))
+			| Value::NumberWithUnit(number @ Number::Decimal(_), _)
+				if format.radicals =>
+			{
+				// Square root of an integer in simplified form
+				let (factor, radicand, divisor) = number.radical_form()?;
+				let mut string = match factor {
+					1 => String::new(),
+					-1 => "-".to_string(),
+					factor => factor.to_string(),
+				} + "√" + &radicand.to_string();
+				if divisor > 1 {
+					string.push('/');
+					string += &divisor.to_string();
+				}
+				string.single_line_layout(font, TokenType::Float, metrics, max_width, None)
 			}
 			Value::Complex(value) => {
-				if format.show_alt_float
+				if format
+					.alt_forms
+					.is_enabled(AlternateValueClass::Complex, AlternateForm::Float)
 					&& format.mode == FormatMode::Rational
 					&& (value.real_part().is_rational() || value.imaginary_part().is_rational())
 				{
 					// Complex number with at least one part in rational form
-					let real_part = value.real_part().to_decimal();
-					let imaginary_part = value.imaginary_part().to_decimal();
-					let string = if imaginary_part.is_sign_negative() {
-						format.with_max_precision(8).format_decimal(&real_part)
-							+ " - " + &format
-							.with_max_precision(8)
-							.format_decimal(&-&*imaginary_part)
-							+ "ℹ"
-					} else {
-						format.with_max_precision(8).format_decimal(&real_part)
-							+ " + " + &format.with_max_precision(8).format_decimal(&imaginary_part)
-							+ "ℹ"
-					};
+					let string = strings.get(AlternateForm::Float, || {
+						let real_part = value.real_part().to_decimal();
+						let imaginary_part = value.imaginary_part().to_decimal();
+						Some(if imaginary_part.is_sign_negative() {
+							format.with_max_precision(8).format_decimal(&real_part)
+								+ " - " + &format
+								.with_max_precision(8)
+								.format_decimal(&-&*imaginary_part)
+								+ "ℹ"
+						} else {
+							format.with_max_precision(8).format_decimal(&real_part)
+								+ " + " + &format.with_max_precision(8).format_decimal(&imaginary_part)
+								+ "ℹ"
+						})
+					})?;
 					string.single_line_layout(font, TokenType::Complex, metrics, max_width, None)
 				} else {
 					None
@@ -565,67 +734,45 @@ impl ValueLayout for Value {
 		font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
-		alt_hex: bool,
-		alt_float: bool,
+		alt_integer: bool,
+		alt_real: bool,
 	) -> (Layout, AlternateLayoutType) {
-		let left_alt_width = max_width - (layout.width(metrics) + 24);
-		if alt_hex {
-			if format.alt_mode.left_enabled() {
-				if let Some(alt_layout) =
-					self.alternate_hex_layout(format, font, metrics, left_alt_width)
-				{
-					let mut alt_layout_items = Vec::new();
-					alt_layout_items.push(Layout::LeftAlign(Box::new(alt_layout)));
-					alt_layout_items.push(Layout::HorizontalSpace(24));
-					alt_layout_items.push(layout);
-					return (
-						Layout::Horizontal(alt_layout_items),
-						AlternateLayoutType::Left,
-					);
-				}
+		// Left placement is tried first, so strings formatted for it are kept for the
+		// placement below the value
+		let mut strings = AlternateStrings::new();
+		let mut alternate_layout = |max_width| {
+			let mut result = None;
+			if alt_integer {
+				result =
+					self.alternate_integer_layout(format, font, metrics, max_width, &mut strings);
 			}
-			if format.alt_mode.bottom_enabled() {
-				if let Some(alt_layout) =
-					self.alternate_hex_layout(format, font, metrics, max_width)
-				{
-					let mut alt_layout_items = Vec::new();
-					alt_layout_items.push(layout);
-					alt_layout_items.push(alt_layout);
-					return (
-						Layout::Vertical(alt_layout_items),
-						AlternateLayoutType::Bottom,
-					);
-				}
+			if alt_real && result.is_none() {
+				result = self.alternate_real_layout(format, font, metrics, max_width, &mut strings);
 			}
-		}
+			result
+		};
 
-		if alt_float {
-			if format.alt_mode.left_enabled() {
-				if let Some(alt_layout) =
-					self.alternate_float_layout(format, font, metrics, left_alt_width)
-				{
-					let mut alt_layout_items = Vec::new();
-					alt_layout_items.push(Layout::LeftAlign(Box::new(alt_layout)));
-					alt_layout_items.push(Layout::HorizontalSpace(24));
-					alt_layout_items.push(layout);
-					return (
-						Layout::Horizontal(alt_layout_items),
-						AlternateLayoutType::Left,
-					);
-				}
+		if format.alt_mode.left_enabled() {
+			let left_alt_width = max_width - (layout.width(metrics) + 24);
+			if let Some(alt_layout) = alternate_layout(left_alt_width) {
+				let alt_layout_items = vec![
+					Layout::LeftAlign(Box::new(alt_layout)),
+					Layout::HorizontalSpace(24),
+					layout,
+				];
+				return (
+					Layout::Horizontal(alt_layout_items),
+					AlternateLayoutType::Left,
+				);
 			}
-			if format.alt_mode.bottom_enabled() {
-				if let Some(alt_layout) =
-					self.alternate_float_layout(format, font, metrics, max_width)
-				{
-					let mut alt_layout_items = Vec::new();
-					alt_layout_items.push(layout);
-					alt_layout_items.push(alt_layout);
-					return (
-						Layout::Vertical(alt_layout_items),
-						AlternateLayoutType::Bottom,
-					);
-				}
+		}
+		if format.alt_mode.bottom_enabled() {
+			if let Some(alt_layout) = alternate_layout(max_width) {
+				let alt_layout_items = vec![layout, alt_layout];
+				return (
+					Layout::Vertical(alt_layout_items),
+					AlternateLayoutType::Bottom,
+				);
 			}
 		}
 
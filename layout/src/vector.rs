@@ -7,7 +7,24 @@ use rscalc_math::vector::Vector;
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 
+/// Brackets around the elements of a vector
+pub const VECTOR_DELIMITERS: (&str, &str) = ("⦗", "⦘");
+
+/// Brackets around the elements of a list
+pub const LIST_DELIMITERS: (&str, &str) = ("{", "}");
+
 pub trait VectorLayout {
+	/// Lays out the elements between the given delimiters, trying forms with less
+	/// precision and smaller fonts until one fits
+	fn layout(
+		&self,
+		format: &Format,
+		base_font: Font,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+		delimiters: (&'static str, &'static str),
+	) -> Option<Layout>;
+
 	fn single_line_full_layout(
 		&self,
 		format: &Format,
@@ -15,6 +32,7 @@ pub trait VectorLayout {
 		small_font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		delimiters: (&'static str, &'static str),
 	) -> Option<Layout>;
 
 	fn multi_line_layout(
@@ -24,10 +42,67 @@ pub trait VectorLayout {
 		metrics: &dyn FontMetrics,
 		max_width: i32,
 		max_lines: usize,
+		delimiters: (&'static str, &'static str),
 	) -> Option<Layout>;
 }
 
 impl VectorLayout for Vector {
+	fn layout(
+		&self,
+		format: &Format,
+		base_font: Font,
+		metrics: &dyn FontMetrics,
+		max_width: i32,
+		delimiters: (&'static str, &'static str),
+	) -> Option<Layout> {
+		// Try to represent full form of vector entries in a single line. This is the
+		// preferred form because it can show rationals.
+		if let Some(layout) = self.single_line_full_layout(
+			format,
+			base_font,
+			base_font.smaller(),
+			metrics,
+			max_width,
+			delimiters,
+		) {
+			return Some(layout);
+		}
+
+		// Try a three line layout with full precision decimal form
+		if let Some(layout) = self.multi_line_layout(
+			format,
+			base_font.smaller(),
+			metrics,
+			max_width,
+			3,
+			delimiters,
+		) {
+			return Some(layout);
+		}
+
+		// Try a three line layout with partial precision decimal form
+		if let Some(layout) = self.multi_line_layout(
+			&format.with_max_precision(6),
+			base_font.smaller(),
+			metrics,
+			max_width,
+			3,
+			delimiters,
+		) {
+			return Some(layout);
+		}
+
+		// Try a four line layout with smaller font
+		self.multi_line_layout(
+			&format.with_max_precision(6),
+			base_font.smaller().smaller(),
+			metrics,
+			max_width,
+			4,
+			delimiters,
+		)
+	}
+
 	fn single_line_full_layout(
 		&self,
 		format: &Format,
@@ -35,10 +110,11 @@ impl VectorLayout for Vector {
 		small_font: Font,
 		metrics: &dyn FontMetrics,
 		max_width: i32,
+		delimiters: (&'static str, &'static str),
 	) -> Option<Layout> {
 		let mut horizontal_items = Vec::new();
-		let left_paren = Layout::StaticText("⦗", default_font, TokenType::Symbol);
-		let right_paren = Layout::StaticText("⦘", default_font, TokenType::Symbol);
+		let left_paren = Layout::StaticText(delimiters.0, default_font, TokenType::Symbol);
+		let right_paren = Layout::StaticText(delimiters.1, default_font, TokenType::Symbol);
 		let mut width = left_paren.width(metrics) + right_paren.width(metrics);
 		horizontal_items.push(left_paren);
 
@@ -85,11 +161,12 @@ impl VectorLayout for Vector {
 		metrics: &dyn FontMetrics,
 		max_width: i32,
 		max_lines: usize,
+		delimiters: (&'static str, &'static str),
 	) -> Option<Layout> {
 		let mut vertical_items = Vec::new();
 		let mut horizontal_items = Vec::new();
-		let left_paren = Layout::StaticText("⦗", font, TokenType::Symbol);
-		let right_paren = Layout::StaticText("⦘", font, TokenType::Symbol);
+		let left_paren = Layout::StaticText(delimiters.0, font, TokenType::Symbol);
+		let right_paren = Layout::StaticText(delimiters.1, font, TokenType::Symbol);
 		let mut width = left_paren.width(metrics) + right_paren.width(metrics);
 		horizontal_items.push(right_paren);
 
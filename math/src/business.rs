@@ -0,0 +1,174 @@
+use crate::error::{Error, Result};
+use crate::number::{Number, ToNumber};
+use crate::persist::{read_item, write_item};
+use crate::storage::{deserialize_portable, serialize_portable, DeserializeInput, SerializeOutput};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Quantity kept in one of the registers of the business menu
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BusinessVariable {
+	Cost,
+	Price,
+	/// Profit as a percentage of the price
+	Margin,
+	/// Profit as a percentage of the cost
+	Markup,
+	/// Percentage added to amounts by the tax functions
+	TaxRate,
+}
+
+impl BusinessVariable {
+	pub fn to_str(&self) -> &'static str {
+		match self {
+			BusinessVariable::Cost => "Cost",
+			BusinessVariable::Price => "Price",
+			BusinessVariable::Margin => "Mar%",
+			BusinessVariable::Markup => "MU%",
+			BusinessVariable::TaxRate => "Tax%",
+		}
+	}
+}
+
+/// Registers of the business menu. Cost, price and margin are related so that
+/// any one of them can be found from the other two. A markup is another way of
+/// giving the margin, so it is kept as the margin that it is equal to.
+#[derive(Clone)]
+pub struct BusinessRegisters {
+	cost: Option<Number>,
+	price: Option<Number>,
+	margin: Option<Number>,
+	tax_rate: Option<Number>,
+}
+
+impl BusinessRegisters {
+	pub fn new() -> Self {
+		BusinessRegisters {
+			cost: None,
+			price: None,
+			margin: None,
+			tax_rate: None,
+		}
+	}
+
+	pub fn get(&self, variable: BusinessVariable) -> Result<Number> {
+		match variable {
+			BusinessVariable::Cost => Self::defined(&self.cost),
+			BusinessVariable::Price => Self::defined(&self.price),
+			BusinessVariable::Margin => Self::defined(&self.margin),
+			BusinessVariable::Markup => {
+				// markup = 100·margin / (100 - margin)
+				let margin = Self::defined(&self.margin)?;
+				Self::checked_div(&(&margin * &hundred()), &(&hundred() - &margin))
+			}
+			BusinessVariable::TaxRate => Self::defined(&self.tax_rate),
+		}
+	}
+
+	pub fn set(&mut self, variable: BusinessVariable, value: Number) -> Result<()> {
+		match variable {
+			BusinessVariable::Cost => self.cost = Some(value),
+			BusinessVariable::Price => self.price = Some(value),
+			BusinessVariable::Margin => self.margin = Some(value),
+			BusinessVariable::Markup => {
+				// margin = 100·markup / (100 + markup)
+				self.margin = Some(Self::checked_div(
+					&(&value * &hundred()),
+					&(&hundred() + &value),
+				)?);
+			}
+			BusinessVariable::TaxRate => self.tax_rate = Some(value),
+		}
+		Ok(())
+	}
+
+	/// Finds a variable from the other two of cost, price and margin, and keeps
+	/// the result in its register. The tax rate is not related to the others,
+	/// so it is only read back.
+	pub fn solve(&mut self, variable: BusinessVariable) -> Result<Number> {
+		let result = match variable {
+			BusinessVariable::Cost => {
+				let price = Self::defined(&self.price)?;
+				let margin = Self::defined(&self.margin)?;
+				&(&price * &(&hundred() - &margin)) / &hundred()
+			}
+			BusinessVariable::Price => {
+				let cost = Self::defined(&self.cost)?;
+				let margin = Self::defined(&self.margin)?;
+				Self::checked_div(&(&cost * &hundred()), &(&hundred() - &margin))?
+			}
+			BusinessVariable::Margin => {
+				let cost = Self::defined(&self.cost)?;
+				let price = Self::defined(&self.price)?;
+				Self::checked_div(&(&(&price - &cost) * &hundred()), &price)?
+			}
+			BusinessVariable::Markup => {
+				let cost = Self::defined(&self.cost)?;
+				let price = Self::defined(&self.price)?;
+				Self::checked_div(&(&(&price - &cost) * &hundred()), &cost)?
+			}
+			BusinessVariable::TaxRate => return self.get(variable),
+		};
+		self.set(variable, result.clone())?;
+		Ok(result)
+	}
+
+	/// Factor that amounts are multiplied by to add tax at the stored rate
+	pub fn tax_factor(&self) -> Result<Number> {
+		let rate = Self::defined(&self.tax_rate)?;
+		Ok(&(&hundred() + &rate) / &hundred())
+	}
+
+	/// Writes each register, marking the ones that have not been set
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		for register in &[&self.cost, &self.price, &self.margin, &self.tax_rate] {
+			match register {
+				Some(value) => {
+					output.write_u8(1)?;
+					write_item(output, |output| serialize_portable(value, output))?;
+				}
+				None => output.write_u8(0)?,
+			}
+		}
+		Ok(())
+	}
+
+	pub fn restore(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		for register in &mut [
+			&mut self.cost,
+			&mut self.price,
+			&mut self.margin,
+			&mut self.tax_rate,
+		] {
+			**register = if input.read_u8()? != 0 {
+				Some(deserialize_portable(&mut read_item(input)?)?)
+			} else {
+				None
+			};
+		}
+		Ok(())
+	}
+
+	fn defined(register: &Option<Number>) -> Result<Number> {
+		register.clone().ok_or(Error::ValueNotDefined)
+	}
+
+	fn checked_div(num: &Number, denom: &Number) -> Result<Number> {
+		if denom.is_zero() {
+			Err(Error::DivideByZero)
+		} else {
+			Ok(num / denom)
+		}
+	}
+}
+
+impl Default for BusinessRegisters {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn hundred() -> Number {
+	100.to_number()
+}
@@ -0,0 +1,106 @@
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumKind {
+	/// Sum of the words, wrapped to the word width
+	Additive,
+	/// Exclusive or of the words
+	Xor,
+	/// One's complement of the one's complement sum of 16 bit words, as given
+	/// in RFC 1071
+	Internet,
+}
+
+/// Order of the bytes within each word of a checksum
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+	BigEndian,
+	LittleEndian,
+}
+
+impl ChecksumKind {
+	/// Returns true if the width of the words is taken from the stack
+	pub fn has_width(&self) -> bool {
+		*self != ChecksumKind::Internet
+	}
+
+	pub fn to_u8(&self) -> u8 {
+		match self {
+			ChecksumKind::Additive => 0,
+			ChecksumKind::Xor => 1,
+			ChecksumKind::Internet => 2,
+		}
+	}
+
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(ChecksumKind::Additive),
+			1 => Some(ChecksumKind::Xor),
+			2 => Some(ChecksumKind::Internet),
+			_ => None,
+		}
+	}
+}
+
+impl ByteOrder {
+	pub fn to_u8(&self) -> u8 {
+		match self {
+			ByteOrder::BigEndian => 0,
+			ByteOrder::LittleEndian => 1,
+		}
+	}
+
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(ByteOrder::BigEndian),
+			1 => Some(ByteOrder::LittleEndian),
+			_ => None,
+		}
+	}
+}
+
+/// Computes a checksum of `bytes` taken as words of `width` bits, which must be
+/// 8, 16, 32 or 64. A partial word at the end is padded with zero bytes.
+/// Internet checksums always use 16 bit words.
+pub fn checksum(kind: ChecksumKind, order: ByteOrder, bytes: &[u8], width: u32) -> Result<u64> {
+	let width = match kind {
+		ChecksumKind::Internet => 16,
+		_ => width,
+	};
+	let word_bytes = match width {
+		8 | 16 | 32 | 64 => (width / 8) as usize,
+		_ => return Err(Error::InvalidArgument),
+	};
+
+	let words = bytes.chunks(word_bytes).map(|chunk| {
+		let mut word = [0u8; 8];
+		word[..chunk.len()].copy_from_slice(chunk);
+		let word = &word[..word_bytes];
+		match order {
+			ByteOrder::BigEndian => word
+				.iter()
+				.fold(0u64, |acc, byte| (acc << 8) | *byte as u64),
+			ByteOrder::LittleEndian => word
+				.iter()
+				.rev()
+				.fold(0u64, |acc, byte| (acc << 8) | *byte as u64),
+		}
+	});
+
+	Ok(match kind {
+		ChecksumKind::Additive => {
+			let mask = u64::MAX >> (64 - width);
+			words.fold(0u64, |acc, word| acc.wrapping_add(word)) & mask
+		}
+		ChecksumKind::Xor => words.fold(0u64, |acc, word| acc ^ word),
+		ChecksumKind::Internet => {
+			// Carries are folded back in after the sum, which can't overflow
+			// for any input that fits in memory
+			let mut sum = words.sum::<u64>();
+			while sum > 0xffff {
+				sum = (sum & 0xffff) + (sum >> 16);
+			}
+			!sum & 0xffff
+		}
+	})
+}
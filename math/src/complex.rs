@@ -1,6 +1,8 @@
 use crate::format::Format;
 use crate::number::{Number, ToNumber};
 use intel_dfp::Decimal;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
 
 #[cfg(not(feature = "std"))]
 use alloc::string::String;
@@ -12,6 +14,10 @@ pub const MAX_COMPLEX_INTEGER_BITS: u64 = 1024;
 // 128-bit float available in fractional form.
 pub const MAX_COMPLEX_DENOMINATOR_BITS: u64 = 128;
 
+// Largest integer power, in bits, that is computed by repeated multiplication
+// instead of through the logarithm
+const MAX_INTEGER_POWER_BITS: u64 = 16;
+
 #[derive(Clone)]
 pub struct ComplexNumber {
 	real: Number,
@@ -37,6 +43,14 @@ impl ComplexNumber {
 		}
 	}
 
+	/// Fully reduces any rational parts of the number.
+	pub fn reduce(self) -> Self {
+		ComplexNumber {
+			real: self.real.reduce(),
+			imaginary: self.imaginary.reduce(),
+		}
+	}
+
 	pub fn i() -> Self {
 		ComplexNumber {
 			real: 0.into(),
@@ -159,10 +173,23 @@ impl ComplexNumber {
 		}
 	}
 
+	/// Principal argument of the number, which is greater than -π and at most π
+	pub fn principal_argument(&self) -> Number {
+		if self.real.is_zero() && self.imaginary.is_zero() {
+			0.to_number()
+		} else {
+			Number::Decimal(Decimal::atan2(
+				&self.imaginary.to_decimal(),
+				&self.real.to_decimal(),
+			))
+		}
+	}
+
+	/// Principal natural logarithm, with the imaginary part between -π and π
 	pub fn ln(&self) -> Self {
 		ComplexNumber {
 			real: self.magnitude().ln(),
-			imaginary: self.polar_angle(),
+			imaginary: self.principal_argument(),
 		}
 	}
 
@@ -174,10 +201,44 @@ impl ComplexNumber {
 		self.ln() / 10.to_number().ln().to_complex()
 	}
 
+	/// Principal value of the power. Integer powers are found by repeated
+	/// multiplication so that exact parts stay exact.
 	pub fn pow(&self, power: &ComplexNumber) -> Self {
+		if self.real.is_zero() && self.imaginary.is_zero() {
+			if power.real.is_zero() && power.imaginary.is_zero() {
+				return 1.to_complex();
+			} else if !power.real.is_negative() && !power.real.is_zero() {
+				return 0.to_complex();
+			}
+		}
+		if let (Number::Integer(exponent), true) = (&power.real, power.is_real()) {
+			if exponent.bits() <= MAX_INTEGER_POWER_BITS {
+				return self.integer_pow(&exponent.as_bigint());
+			}
+		}
 		(power * &self.ln()).exp()
 	}
 
+	fn integer_pow(&self, exponent: &BigInt) -> Self {
+		let mut remaining = exponent.magnitude().clone();
+		let mut square = self.clone();
+		let mut result = 1.to_complex();
+		while remaining.bits() != 0 {
+			if remaining.is_odd() {
+				result = &result * &square;
+			}
+			remaining >>= 1;
+			if remaining.bits() != 0 {
+				square = &square * &square;
+			}
+		}
+		if exponent.sign() == Sign::Minus {
+			1.to_complex() / result
+		} else {
+			result
+		}
+	}
+
 	pub fn sin(&self) -> Self {
 		ComplexNumber {
 			real: &self.real.sin() * &self.imaginary.cosh(),
@@ -229,7 +290,10 @@ impl ComplexNumber {
 	}
 
 	pub fn acosh(&self) -> Self {
-		(self + &(self * self - 1.to_complex()).sqrt()).ln()
+		// The square roots are kept separate so that the branch cut is only along
+		// the real axis below one
+		let root = (self + &1.to_complex()).sqrt() * (self - &1.to_complex()).sqrt();
+		(self + &root).ln()
 	}
 
 	pub fn atanh(&self) -> Self {
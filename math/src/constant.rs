@@ -17,6 +17,21 @@ impl Constant {
 		}
 	}
 
+	pub fn to_u16(&self) -> u16 {
+		match self {
+			Constant::Pi => 0,
+			Constant::SpeedOfLight => 1,
+		}
+	}
+
+	pub fn from_u16(value: u16) -> Option<Self> {
+		match value {
+			0 => Some(Constant::Pi),
+			1 => Some(Constant::SpeedOfLight),
+			_ => None,
+		}
+	}
+
 	pub fn value(&self) -> Value {
 		match self {
 			Constant::Pi => Value::Number(Number::Decimal(Decimal::pi())),
@@ -1,29 +1,54 @@
+use crate::business::BusinessRegisters;
+use crate::checksum::{self, ByteOrder, ChecksumKind};
 use crate::complex::ComplexNumber;
 use crate::constant::Constant;
+use crate::decibel::{self, DecibelScale, DEFAULT_REFERENCE_IMPEDANCE};
 use crate::error::{Error, Result};
-use crate::format::{DecimalPointMode, Format, FormatMode, IntegerMode};
+use crate::format::{
+	AlternateForm, AlternateValueClass, DecimalPointMode, DigitSeparator, Format, FormatMode,
+	FractionDisplay, IntegerMode, OverflowMode,
+};
+use crate::histogram::Histogram;
+use crate::interpolate;
+use crate::iso8601;
+use crate::list::List;
 use crate::matrix::Matrix;
-use crate::number::{Number, MAX_INTEGER_BITS};
+use crate::modes::ArithmeticModes;
+use crate::network;
+use crate::number::{Number, ToNumber, MAX_INTEGER_BITS};
+use crate::persist::{read_item, write_item, StateWriter};
+use crate::prime;
+use crate::probability::{Distribution, DistributionFunction, DistributionKind};
+use crate::quaternion::Quaternion;
 use crate::stack::Stack;
-use crate::storage::store;
+use crate::storage::{
+	deserialize_portable, serialize_portable, store, DeserializeInput, SerializeOutput,
+};
+use crate::text;
 use crate::time::Now;
-use crate::unit::{AngleUnit, Unit};
+use crate::undo::{pop_undo_action, push_undo_action, UndoAction};
+use crate::unit::{AngleUnit, CompositeUnit, PreferredUnit, Unit};
 use crate::value::{Value, ValueRef};
 use crate::vector::Vector;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use num_bigint::ToBigInt;
+use intel_dfp::RoundingMode;
+use num_bigint::{BigInt, Sign, ToBigInt};
 
 #[cfg(feature = "std")]
 use std::borrow::Cow;
 #[cfg(feature = "std")]
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 #[cfg(feature = "std")]
 use std::convert::TryFrom;
 
 #[cfg(not(feature = "std"))]
 use alloc::borrow::Cow;
 #[cfg(not(feature = "std"))]
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec;
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 #[cfg(not(feature = "std"))]
@@ -36,6 +61,64 @@ pub enum Location {
 	Variable(char),
 }
 
+impl Location {
+	/// Name of the location as shown to the user
+	pub fn to_string(&self) -> String {
+		match self {
+			Location::Integer(idx) => Number::from(*idx).to_string(),
+			Location::StackOffset(0) => "X".to_string(),
+			Location::StackOffset(1) => "Y".to_string(),
+			Location::StackOffset(2) => "Z".to_string(),
+			Location::StackOffset(offset) => {
+				".".to_string() + &Number::from(*offset + 1).to_string()
+			}
+			Location::Variable(name) => name.to_string(),
+		}
+	}
+
+	/// Writes the location into saved state
+	pub fn save<Out: SerializeOutput>(&self, output: &mut Out) -> Result<()> {
+		match self {
+			Location::Integer(idx) => {
+				output.write_u8(0)?;
+				output.write_u32(*idx as u32)
+			}
+			Location::StackOffset(offset) => {
+				output.write_u8(1)?;
+				output.write_u32(*offset as u32)
+			}
+			Location::Variable(name) => {
+				output.write_u8(2)?;
+				output.write_u32(*name as u32)
+			}
+		}
+	}
+
+	/// Reads a location written by `save`
+	pub fn restore(input: &mut DeserializeInput) -> Result<Self> {
+		let kind = input.read_u8()?;
+		let value = input.read_u32()?;
+		match kind {
+			0 => Ok(Location::Integer(value as usize)),
+			1 => Ok(Location::StackOffset(value as usize)),
+			2 => Ok(Location::Variable(
+				core::char::from_u32(value).ok_or(Error::CorruptData)?,
+			)),
+			_ => Err(Error::CorruptData),
+		}
+	}
+}
+
+// Tags of the records in saved state that hold the context
+const STATE_RECORD_STACK: u16 = 0x0001;
+const STATE_RECORD_MEMORY: u16 = 0x0002;
+const STATE_RECORD_LOCKED: u16 = 0x0003;
+const STATE_RECORD_FORMAT: u16 = 0x0004;
+const STATE_RECORD_MODES: u16 = 0x0005;
+const STATE_RECORD_PREFERRED_UNITS: u16 = 0x0006;
+const STATE_RECORD_BUSINESS: u16 = 0x0007;
+const STATE_RECORD_ELECTRONICS: u16 = 0x0008;
+
 pub struct Context {
 	stack: Stack,
 	format: Format,
@@ -43,6 +126,55 @@ pub struct Context {
 	prev_decimal_integer_mode: IntegerMode,
 	angle_mode: AngleUnit,
 	memory: BTreeMap<Location, ValueRef>,
+	locked: BTreeSet<Location>,
+	preferred_units: Vec<PreferredUnit>,
+	carry: bool,
+	out_of_range: bool,
+	integer_division: IntegerDivision,
+	deferred_reduction: bool,
+	complex_results: bool,
+	business: BusinessRegisters,
+	/// Impedance that levels in dBm are converted to voltages across
+	reference_impedance: Number,
+}
+
+/// Result of dividing two integers that don't divide evenly
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IntegerDivision {
+	/// Exact fraction
+	Rational,
+	/// Decimal approximation
+	Decimal,
+	/// Quotient rounded toward zero, followed by the remainder
+	QuotientRemainder,
+}
+
+impl IntegerDivision {
+	pub fn to_u8(&self) -> u8 {
+		match self {
+			IntegerDivision::Rational => 0,
+			IntegerDivision::Decimal => 1,
+			IntegerDivision::QuotientRemainder => 2,
+		}
+	}
+
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(IntegerDivision::Rational),
+			1 => Some(IntegerDivision::Decimal),
+			2 => Some(IntegerDivision::QuotientRemainder),
+			_ => None,
+		}
+	}
+}
+
+/// Integer arithmetic that sets the carry and out of range flags
+#[derive(Clone, Copy)]
+enum FlagOperation {
+	Add,
+	Sub,
+	Mul,
+	Div,
 }
 
 impl Context {
@@ -54,6 +186,15 @@ impl Context {
 			prev_decimal_integer_mode: IntegerMode::Float,
 			angle_mode: AngleUnit::Degrees,
 			memory: BTreeMap::new(),
+			locked: BTreeSet::new(),
+			preferred_units: PreferredUnit::defaults(),
+			carry: false,
+			out_of_range: false,
+			integer_division: IntegerDivision::Rational,
+			deferred_reduction: false,
+			complex_results: true,
+			business: BusinessRegisters::new(),
+			reference_impedance: DEFAULT_REFERENCE_IMPEDANCE.to_number(),
 		}
 	}
 
@@ -65,6 +206,38 @@ impl Context {
 			prev_decimal_integer_mode: IntegerMode::Float,
 			angle_mode: AngleUnit::Degrees,
 			memory: BTreeMap::new(),
+			locked: BTreeSet::new(),
+			preferred_units: PreferredUnit::defaults(),
+			carry: false,
+			out_of_range: false,
+			integer_division: IntegerDivision::Rational,
+			deferred_reduction: false,
+			complex_results: true,
+			business: BusinessRegisters::new(),
+			reference_impedance: DEFAULT_REFERENCE_IMPEDANCE.to_number(),
+		}
+	}
+
+	/// Copy of the modes and the top `depth` entries of the stack, without the
+	/// variables or undo, that a function can be run on to see its result
+	/// without changing anything
+	pub fn scratch_copy(&self, depth: usize) -> Self {
+		Context {
+			stack: self.stack.copy_top(depth),
+			format: self.format.clone(),
+			default_integer_format: self.default_integer_format,
+			prev_decimal_integer_mode: self.prev_decimal_integer_mode,
+			angle_mode: self.angle_mode,
+			memory: BTreeMap::new(),
+			locked: BTreeSet::new(),
+			preferred_units: self.preferred_units.clone(),
+			carry: self.carry,
+			out_of_range: self.out_of_range,
+			integer_division: self.integer_division,
+			deferred_reduction: self.deferred_reduction,
+			complex_results: self.complex_results,
+			business: self.business.clone(),
+			reference_impedance: self.reference_impedance.clone(),
 		}
 	}
 
@@ -80,40 +253,139 @@ impl Context {
 		&self.format
 	}
 
+	/// Gives access to the display format. Because the change can't be seen
+	/// here, the current format is always recorded for undo.
 	pub fn format_mut(&mut self) -> &mut Format {
+		self.push_modes_undo(self.save_modes());
 		self.stack.invalidate_caches();
 		&mut self.format
 	}
 
-	pub fn set_format_mode(&mut self, mode: FormatMode) {
-		self.format.mode = mode;
+	/// Display format and modes in the form kept in the undo buffer
+	fn save_modes(&self) -> Vec<u8> {
+		let mut output = Vec::new();
+		let _ = self.format.save(&mut output);
+		let _ = output.write_u16(Unit::Angle(self.angle_mode).to_u16());
+		let _ = self.default_integer_format.save(&mut output);
+		let _ = self.prev_decimal_integer_mode.save(&mut output);
+		output
+	}
+
+	fn restore_modes(&mut self, modes: &[u8]) -> Result<()> {
+		let mut input = DeserializeInput::new(modes);
+		self.format.restore(&mut input)?;
+		if let Some(Unit::Angle(unit)) = Unit::from_u16(input.read_u16()?) {
+			self.angle_mode = unit;
+		}
+		if let Some(mode) = IntegerMode::restore(&mut input)? {
+			self.default_integer_format = mode;
+		}
+		if let Some(mode) = IntegerMode::restore(&mut input)? {
+			self.prev_decimal_integer_mode = mode;
+		}
 		self.stack.invalidate_caches();
+		Ok(())
+	}
+
+	fn push_modes_undo(&self, modes: Vec<u8>) {
+		if self.stack.undo_enabled() {
+			push_undo_action(UndoAction::Modes(modes));
+		}
+	}
+
+	/// Makes a change to the display format or modes so that it can be undone.
+	/// Nothing is recorded when the settings end up the same as before.
+	fn change_modes<T>(&mut self, change: impl FnOnce(&mut Self) -> T) -> T {
+		let modes = self.save_modes();
+		let result = change(self);
+		if self.save_modes() != modes {
+			self.push_modes_undo(modes);
+			self.stack.invalidate_caches();
+		}
+		result
+	}
+
+	pub fn set_format_mode(&mut self, mode: FormatMode) {
+		self.change_modes(|context| {
+			context.format.mode = mode;
+		});
+	}
+
+	pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+		self.change_modes(|context| {
+			context.format.overflow = mode;
+		});
+	}
+
+	pub fn set_fraction_display(&mut self, mode: FractionDisplay) {
+		self.change_modes(|context| {
+			context.format.fraction_display = mode;
+		});
+	}
+
+	pub fn set_display_digits(&mut self, digits: Option<usize>) {
+		self.change_modes(|context| {
+			context.format.display_digits = digits;
+		});
+	}
+
+	pub fn toggle_si_prefix(&mut self) {
+		self.change_modes(|context| {
+			context.format.si_prefix = !context.format.si_prefix;
+		});
 	}
 
 	pub fn toggle_alt_hex(&mut self) {
-		self.format.show_alt_hex = !self.format.show_alt_hex;
-		self.stack.invalidate_caches();
+		self.change_modes(|context| {
+			context.format.alt_forms.toggle_integer_forms();
+		});
 	}
 
 	pub fn toggle_alt_float(&mut self) {
-		self.format.show_alt_float = !self.format.show_alt_float;
-		self.stack.invalidate_caches();
+		self.change_modes(|context| {
+			context.format.alt_forms.toggle_float_forms();
+		});
+	}
+
+	pub fn toggle_alternate_form(&mut self, class: AlternateValueClass, form: AlternateForm) {
+		self.change_modes(|context| {
+			context.format.alt_forms.toggle(class, form);
+		});
 	}
 
 	pub fn set_thousands_separator(&mut self, state: bool) {
-		self.format.thousands = state;
-		self.stack.invalidate_caches();
+		self.change_modes(|context| {
+			context.format.thousands = state;
+		});
+	}
+
+	/// Sets the rounding mode of displayed numbers and of the decimal arithmetic
+	/// of operations run by this context. The binary64 backend only uses it for
+	/// displayed numbers, as binary arithmetic always rounds to the nearest even
+	/// value.
+	pub fn set_rounding_mode(&mut self, mode: RoundingMode) {
+		self.change_modes(|context| {
+			context.format.rounding = mode;
+		});
+	}
+
+	pub fn set_digit_separator(&mut self, separator: DigitSeparator) {
+		self.change_modes(|context| {
+			context.format.digit_separator = separator;
+		});
 	}
 
 	pub fn set_decimal_point_mode(&mut self, mode: DecimalPointMode) {
-		self.format.decimal_point = mode;
-		self.stack.invalidate_caches();
+		self.change_modes(|context| {
+			context.format.decimal_point = mode;
+		});
 	}
 
 	pub fn set_float_mode(&mut self) -> Result<()> {
 		if self.format.integer_radix == 10 {
-			self.format.integer_mode = IntegerMode::Float;
-			self.stack.invalidate_caches();
+			self.change_modes(|context| {
+				context.format.integer_mode = IntegerMode::Float;
+			});
 			Ok(())
 		} else {
 			Err(Error::FloatRequiresDecimalMode)
@@ -121,25 +393,27 @@ impl Context {
 	}
 
 	pub fn set_integer_mode(&mut self, mode: IntegerMode) {
-		self.format.integer_mode = mode;
-		self.default_integer_format = mode;
-		self.stack.invalidate_caches();
+		self.change_modes(|context| {
+			context.format.integer_mode = mode;
+			context.default_integer_format = mode;
+		});
 	}
 
 	pub fn set_integer_radix(&mut self, radix: u8) {
-		if radix == 10 {
-			if self.format.integer_radix != 10 {
-				self.format.integer_mode = self.prev_decimal_integer_mode;
-			}
-			self.format.integer_radix = radix;
-		} else {
-			if self.format.integer_radix == 10 {
-				self.prev_decimal_integer_mode = self.format.integer_mode;
-				self.format.integer_mode = self.default_integer_format;
+		self.change_modes(|context| {
+			if radix == 10 {
+				if context.format.integer_radix != 10 {
+					context.format.integer_mode = context.prev_decimal_integer_mode;
+				}
+				context.format.integer_radix = radix;
+			} else {
+				if context.format.integer_radix == 10 {
+					context.prev_decimal_integer_mode = context.format.integer_mode;
+					context.format.integer_mode = context.default_integer_format;
+				}
+				context.format.integer_radix = radix;
 			}
-			self.format.integer_radix = radix;
-		}
-		self.stack.invalidate_caches();
+		});
 	}
 
 	pub fn toggle_integer_radix(&mut self) {
@@ -150,6 +424,21 @@ impl Context {
 		}
 	}
 
+	/// Returns the radix that follows the current one when cycling through the
+	/// common bases in the order decimal, hex, binary, octal.
+	pub fn next_integer_radix(&self) -> u8 {
+		match self.format.integer_radix {
+			10 => 16,
+			16 => 2,
+			2 => 8,
+			_ => 10,
+		}
+	}
+
+	pub fn cycle_integer_radix(&mut self) {
+		self.set_integer_radix(self.next_integer_radix());
+	}
+
 	pub fn default_integer_format(&self) -> &IntegerMode {
 		&self.default_integer_format
 	}
@@ -171,7 +460,68 @@ impl Context {
 	}
 
 	pub fn set_angle_mode(&mut self, unit: AngleUnit) {
-		self.angle_mode = unit;
+		self.change_modes(|context| {
+			context.angle_mode = unit;
+		});
+	}
+
+	pub fn integer_division(&self) -> IntegerDivision {
+		self.integer_division
+	}
+
+	pub fn set_integer_division(&mut self, mode: IntegerDivision) {
+		self.integer_division = mode;
+	}
+
+	/// Returns true if rational results skip GCD reduction until they are displayed
+	/// or grow too large.
+	pub fn deferred_reduction(&self) -> bool {
+		self.deferred_reduction
+	}
+
+	pub fn set_deferred_reduction(&mut self, value: bool) {
+		self.deferred_reduction = value;
+	}
+
+	/// Returns true if real functions outside of their real domain give complex
+	/// results. Otherwise they fail with an invalid argument error.
+	pub fn complex_results(&self) -> bool {
+		self.complex_results
+	}
+
+	pub fn set_complex_results(&mut self, value: bool) {
+		self.complex_results = value;
+	}
+
+	/// Modes that operations run by this context compute results with
+	pub fn arithmetic_modes(&self) -> ArithmeticModes {
+		ArithmeticModes {
+			deferred_reduction: self.deferred_reduction,
+			complex_results: self.complex_results,
+			rounding: self.format.rounding,
+		}
+	}
+
+	pub fn business(&self) -> &BusinessRegisters {
+		&self.business
+	}
+
+	pub fn business_mut(&mut self) -> &mut BusinessRegisters {
+		&mut self.business
+	}
+
+	pub fn reference_impedance(&self) -> &Number {
+		&self.reference_impedance
+	}
+
+	/// Sets the reference impedance to x, leaving x on the stack
+	pub fn set_reference_impedance(&mut self) -> Result<()> {
+		let impedance = self.top()?.real_number()?.clone();
+		if impedance.is_zero() || impedance.is_negative() {
+			return Err(Error::ValueOutOfRange);
+		}
+		self.reference_impedance = impedance;
+		Ok(())
 	}
 
 	pub fn stack_len(&self) -> usize {
@@ -218,6 +568,19 @@ impl Context {
 		self.stack.push(value)
 	}
 
+	/// Enters a typed number. The number replaces x instead of being pushed if
+	/// `enter` was the last change to the stack.
+	pub fn input_value(&mut self, value: Value) -> Result<()> {
+		let value = Stack::value_for_integer_mode(&self.format.integer_mode, value);
+		self.stack.input_value(value)
+	}
+
+	/// Copies x into y so that the next number entered replaces the copy in x,
+	/// as ENTER does on classic RPN calculators
+	pub fn enter(&mut self) -> Result<()> {
+		self.stack.enter()
+	}
+
 	pub fn push_constant(&mut self, constant: Constant) -> Result<()> {
 		self.push(constant.value())
 	}
@@ -241,10 +604,197 @@ impl Context {
 		self.stack.clear();
 	}
 
+	/// Copies the modes, display settings and memory from another context. Used to
+	/// run programs on a separate stack that otherwise behaves like the original.
+	pub fn copy_settings_from(&mut self, other: &Context) {
+		self.format = other.format.clone();
+		self.default_integer_format = other.default_integer_format;
+		self.prev_decimal_integer_mode = other.prev_decimal_integer_mode;
+		self.angle_mode = other.angle_mode;
+		self.memory = other.memory.clone();
+		self.locked = other.locked.clone();
+		self.preferred_units = other.preferred_units.clone();
+		self.business = other.business.clone();
+		self.reference_impedance = other.reference_impedance.clone();
+	}
+
 	pub fn clear_undo_buffer(&mut self) {
 		self.stack.clear_undo_buffer();
 	}
 
+	/// Writes the stack, memory and settings into saved state
+	pub fn save_state(&self, writer: &mut StateWriter) -> Result<()> {
+		writer.record(STATE_RECORD_STACK, |output| {
+			// Values are written from the bottom of the stack so that they can be
+			// pushed in order when restoring
+			let len = self.stack.len();
+			output.write_u32(len as u32)?;
+			for idx in (0..len).rev() {
+				let value = self.stack.entry(idx)?;
+				write_item(output, |output| serialize_portable(&value, output))?;
+			}
+			Ok(())
+		})?;
+		self.save_settings(writer)
+	}
+
+	/// Returns true if everything but the stack, such as the memory, modes, flags
+	/// and registers, is the same as in `other`
+	pub fn same_settings(&self, other: &Context) -> bool {
+		let saved = |context: &Context| {
+			let mut writer = StateWriter::new();
+			context.save_settings(&mut writer).map(|_| writer.finish())
+		};
+		match (saved(self), saved(other)) {
+			(Ok(settings), Ok(other_settings)) => settings == other_settings,
+			_ => false,
+		}
+	}
+
+	/// Writes everything but the stack into saved state
+	fn save_settings(&self, writer: &mut StateWriter) -> Result<()> {
+		writer.record(STATE_RECORD_MEMORY, |output| {
+			output.write_u32(self.memory.len() as u32)?;
+			for (location, value) in &self.memory {
+				location.save(output)?;
+				let value = value.get()?;
+				write_item(output, |output| serialize_portable(&value, output))?;
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_LOCKED, |output| {
+			output.write_u32(self.locked.len() as u32)?;
+			for location in &self.locked {
+				location.save(output)?;
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_FORMAT, |output| self.format.save(output))?;
+		writer.record(STATE_RECORD_MODES, |output| {
+			output.write_u16(Unit::Angle(self.angle_mode).to_u16())?;
+			self.default_integer_format.save(output)?;
+			self.prev_decimal_integer_mode.save(output)?;
+			output.write_u8(self.carry as u8)?;
+			output.write_u8(self.out_of_range as u8)?;
+			output.write_u8(self.integer_division.to_u8())?;
+			output.write_u8(self.complex_results as u8)?;
+			output.write_u8(self.deferred_reduction as u8)
+		})?;
+		writer.record(STATE_RECORD_PREFERRED_UNITS, |output| {
+			output.write_u32(self.preferred_units.len() as u32)?;
+			for preferred in &self.preferred_units {
+				output.write_u16(preferred.base().to_u16())?;
+				match preferred.target() {
+					Some(unit) => {
+						output.write_u8(1)?;
+						output.write_u16(unit.to_u16())?;
+					}
+					None => {
+						output.write_u8(0)?;
+						output.write_u16(0)?;
+					}
+				}
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_BUSINESS, |output| self.business.save(output))?;
+		writer.record(STATE_RECORD_ELECTRONICS, |output| {
+			write_item(output, |output| {
+				serialize_portable(&self.reference_impedance, output)
+			})
+		})
+	}
+
+	/// Restores a record written by `save_state`. Returns false if the record
+	/// does not belong to the context.
+	pub fn restore_state_record(&mut self, tag: u16, input: &mut DeserializeInput) -> Result<bool> {
+		match tag {
+			STATE_RECORD_STACK => {
+				self.stack.clear();
+				let count = input.read_u32()?;
+				for _ in 0..count {
+					// Values of types that are not known to this version are dropped
+					if let Ok(value) = deserialize_portable::<Value>(&mut read_item(input)?) {
+						self.stack.push(value)?;
+					}
+				}
+				self.stack.clear_undo_buffer();
+			}
+			STATE_RECORD_MEMORY => {
+				self.memory.clear();
+				let count = input.read_u32()?;
+				for _ in 0..count {
+					let location = Location::restore(input)?;
+					if let Ok(value) = deserialize_portable::<Value>(&mut read_item(input)?) {
+						self.memory.insert(location, store(value)?);
+					}
+				}
+			}
+			STATE_RECORD_LOCKED => {
+				self.locked.clear();
+				let count = input.read_u32()?;
+				for _ in 0..count {
+					self.locked.insert(Location::restore(input)?);
+				}
+			}
+			STATE_RECORD_FORMAT => {
+				self.format.restore(input)?;
+				self.stack.invalidate_caches();
+			}
+			STATE_RECORD_MODES => {
+				if let Some(Unit::Angle(unit)) = Unit::from_u16(input.read_u16()?) {
+					self.angle_mode = unit;
+				}
+				if let Some(mode) = IntegerMode::restore(input)? {
+					self.default_integer_format = mode;
+				}
+				if let Some(mode) = IntegerMode::restore(input)? {
+					self.prev_decimal_integer_mode = mode;
+				}
+				// Older versions did not save the integer flags
+				if input.remaining() > 0 {
+					self.carry = input.read_u8()? != 0;
+					self.out_of_range = input.read_u8()? != 0;
+				}
+				if input.remaining() > 0 {
+					if let Some(mode) = IntegerDivision::from_u8(input.read_u8()?) {
+						self.integer_division = mode;
+					}
+				}
+				if input.remaining() > 0 {
+					self.complex_results = input.read_u8()? != 0;
+				}
+				if input.remaining() > 0 {
+					self.deferred_reduction = input.read_u8()? != 0;
+				}
+			}
+			STATE_RECORD_PREFERRED_UNITS => {
+				let count = input.read_u32()?;
+				for _ in 0..count {
+					let base = Unit::from_u16(input.read_u16()?);
+					let has_target = input.read_u8()? != 0;
+					let target = Unit::from_u16(input.read_u16()?);
+					for preferred in &mut self.preferred_units {
+						if Some(preferred.base()) == base {
+							// Unknown target units leave the default in place
+							match (has_target, target) {
+								(false, _) => preferred.set_target(None),
+								(true, Some(unit)) => preferred.set_target(Some(unit)),
+								(true, None) => (),
+							}
+						}
+					}
+				}
+			}
+			STATE_RECORD_BUSINESS => self.business.restore(input)?,
+			STATE_RECORD_ELECTRONICS => {
+				self.reference_impedance = deserialize_portable(&mut read_item(input)?)?;
+			}
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
 	pub fn read<'a>(&'a self, location: &Location) -> Result<Value> {
 		match location {
 			Location::StackOffset(offset) => self.entry(*offset),
@@ -262,30 +812,234 @@ impl Context {
 		match location {
 			Location::StackOffset(offset) => self.set_entry(offset, value)?,
 			location => {
+				if self.locked.contains(&location) {
+					return Err(Error::Locked);
+				}
 				self.memory.insert(location, store(value)?);
 			}
 		}
 		Ok(())
 	}
 
+	/// Locations in memory that have a value stored, in order
+	pub fn memory_locations(&self) -> Vec<Location> {
+		self.memory.keys().cloned().collect()
+	}
+
+	pub fn is_locked(&self, location: &Location) -> bool {
+		self.locked.contains(location)
+	}
+
+	/// Locks or unlocks a memory location. Locked locations cannot be overwritten
+	/// until they are unlocked. Stack entries cannot be locked.
+	pub fn set_locked(&mut self, location: Location, locked: bool) -> Result<()> {
+		if let Location::StackOffset(_) = location {
+			return Err(Error::InvalidStackIndex);
+		}
+		if locked {
+			self.locked.insert(location);
+		} else {
+			self.locked.remove(&location);
+		}
+		Ok(())
+	}
+
 	pub fn undo(&mut self) -> Result<()> {
-		self.stack.undo()
+		if !self.stack.undo_enabled() {
+			return Err(Error::UndoBufferEmpty);
+		}
+		match pop_undo_action()? {
+			UndoAction::Modes(modes) => self.restore_modes(&modes),
+			action => self.stack.undo_action(action),
+		}
 	}
 
 	pub fn add(&mut self) -> Result<()> {
-		self.replace_entries(2, (self.entry(1)? + self.entry(0)?)?)
+		let flags = self.integer_flags(FlagOperation::Add)?;
+		self.replace_entries(2, (self.entry(1)? + self.entry(0)?)?)?;
+		self.set_integer_flags(flags);
+		Ok(())
 	}
 
 	pub fn sub(&mut self) -> Result<()> {
-		self.replace_entries(2, (self.entry(1)? - self.entry(0)?)?)
+		let flags = self.integer_flags(FlagOperation::Sub)?;
+		self.replace_entries(2, (self.entry(1)? - self.entry(0)?)?)?;
+		self.set_integer_flags(flags);
+		Ok(())
 	}
 
 	pub fn mul(&mut self) -> Result<()> {
-		self.replace_entries(2, (self.entry(1)? * self.entry(0)?)?)
+		let flags = self.integer_flags(FlagOperation::Mul)?;
+		let result = self.simplify_units((self.entry(1)? * self.entry(0)?)?);
+		self.replace_entries(2, result)?;
+		self.set_integer_flags(flags);
+		Ok(())
 	}
 
 	pub fn div(&mut self) -> Result<()> {
-		self.replace_entries(2, (self.entry(1)? / self.entry(0)?)?)
+		if self.inexact_integer_division()? {
+			return Ok(());
+		}
+		let flags = self.integer_flags(FlagOperation::Div)?;
+		let result = self.simplify_units((self.entry(1)? / self.entry(0)?)?);
+		self.replace_entries(2, result)?;
+		self.set_integer_flags(flags);
+		Ok(())
+	}
+
+	/// Divides two integers that don't divide evenly as selected by the integer
+	/// division setting. Returns false if the division is left to the normal
+	/// rules, which give an exact fraction.
+	fn inexact_integer_division(&mut self) -> Result<bool> {
+		if self.integer_division == IntegerDivision::Rational {
+			return Ok(false);
+		}
+		// Integer modes already truncate the quotient
+		match self.format.integer_mode {
+			IntegerMode::Float => (),
+			_ => return Ok(false),
+		}
+		let (y, x) = match (self.entry(1)?, self.entry(0)?) {
+			(Value::Number(Number::Integer(y)), Value::Number(Number::Integer(x))) => {
+				(y.into_bigint(), x.into_bigint())
+			}
+			_ => return Ok(false),
+		};
+		let zero = 0.to_bigint().unwrap();
+		if x == zero || &y % &x == zero {
+			return Ok(false);
+		}
+
+		match self.integer_division {
+			IntegerDivision::Decimal => {
+				let result = Number::bigint_to_decimal(&y) / Number::bigint_to_decimal(&x);
+				self.replace_entries(2, Value::Number(Number::Decimal(result)))?;
+			}
+			_ => {
+				let (quotient, remainder) = (&y / &x, &y % &x);
+				self.stack.replace_entries_with_multiple(
+					2,
+					[
+						Value::Number(Number::Integer(quotient.into())),
+						Value::Number(Number::Integer(remainder.into())),
+					]
+					.to_vec(),
+				)?;
+			}
+		}
+		Ok(true)
+	}
+
+	/// Carry flag of sized integer mode. As on the HP-16C, it is set when an
+	/// addition carries out of the word, a subtraction borrows, or a division
+	/// has a remainder.
+	pub fn carry(&self) -> bool {
+		self.carry
+	}
+
+	/// Out of range flag of sized integer mode, which is set when the exact
+	/// result of an operation does not fit in the word and was wrapped
+	pub fn out_of_range(&self) -> bool {
+		self.out_of_range
+	}
+
+	/// Pushes 1 if the carry flag is set, or 0 if it is clear
+	pub fn test_carry(&mut self) -> Result<()> {
+		self.push(Value::Number(Number::Integer((self.carry as u32).into())))
+	}
+
+	/// Pushes 1 if the out of range flag is set, or 0 if it is clear
+	pub fn test_out_of_range(&mut self) -> Result<()> {
+		self.push(Value::Number(Number::Integer(
+			(self.out_of_range as u32).into(),
+		)))
+	}
+
+	pub fn clear_integer_flags(&mut self) {
+		self.carry = false;
+		self.out_of_range = false;
+	}
+
+	fn set_integer_flags(&mut self, flags: Option<(Option<bool>, bool)>) {
+		if let Some((carry, out_of_range)) = flags {
+			if let Some(carry) = carry {
+				self.carry = carry;
+			}
+			self.out_of_range = out_of_range;
+		}
+	}
+
+	/// Computes the flags of an operation on the top two entries. Returns `None`
+	/// if the operation does not change the flags, which is the case outside of
+	/// sized integer mode. Multiplication does not change the carry flag.
+	fn integer_flags(&self, op: FlagOperation) -> Result<Option<(Option<bool>, bool)>> {
+		let (size, signed) = match self.format.integer_mode {
+			IntegerMode::SizedInteger(size, signed) => (size, signed),
+			_ => return Ok(None),
+		};
+		let (y, x) = match (self.entry(1)?, self.entry(0)?) {
+			(Value::Number(Number::Integer(y)), Value::Number(Number::Integer(x))) => {
+				(y.into_bigint(), x.into_bigint())
+			}
+			_ => return Ok(None),
+		};
+
+		let zero = 0.to_bigint().unwrap();
+		let modulus = 2.to_bigint().unwrap().pow(size as u32);
+		let unsigned = |value: &BigInt| {
+			if value < &zero {
+				value + &modulus
+			} else {
+				value.clone()
+			}
+		};
+
+		let (exact, carry) = match op {
+			FlagOperation::Add => (&y + &x, Some(unsigned(&y) + unsigned(&x) >= modulus)),
+			FlagOperation::Sub => (&y - &x, Some(unsigned(&y) < unsigned(&x))),
+			FlagOperation::Mul => (&y * &x, None),
+			FlagOperation::Div => {
+				if x == zero {
+					return Ok(None);
+				}
+				(&y / &x, Some(&y % &x != zero))
+			}
+		};
+
+		let (min, max) = if signed {
+			let half = 2.to_bigint().unwrap().pow(size as u32 - 1);
+			(-&half, half)
+		} else {
+			(zero, modulus)
+		};
+		Ok(Some((carry, exact < min || exact >= max)))
+	}
+
+	pub fn preferred_units(&self) -> &Vec<PreferredUnit> {
+		&self.preferred_units
+	}
+
+	pub fn cycle_preferred_unit(&mut self, idx: usize) {
+		if let Some(preferred) = self.preferred_units.get_mut(idx) {
+			preferred.cycle_target();
+		}
+	}
+
+	/// Replaces compound units in a result with the user's preferred named unit
+	/// when one is equivalent
+	fn simplify_units(&self, value: Value) -> Value {
+		if let Value::NumberWithUnit(num, unit) = &value {
+			if unit.units.len() > 1 {
+				for preferred in &self.preferred_units {
+					if let (Some(result), Some(target)) =
+						(preferred.simplify(num, unit), preferred.target())
+					{
+						return Value::NumberWithUnit(result, CompositeUnit::single_unit(target));
+					}
+				}
+			}
+		}
+		value
 	}
 
 	pub fn recip(&mut self) -> Result<()> {
@@ -306,6 +1060,75 @@ impl Context {
 		self.set_top(square)
 	}
 
+	pub fn ratio_to_db(&mut self, scale: DecibelScale) -> Result<()> {
+		let db = decibel::ratio_to_db(self.top()?.real_number()?, scale)?;
+		self.set_top(Value::Number(db))
+	}
+
+	pub fn db_to_ratio(&mut self, scale: DecibelScale) -> Result<()> {
+		let ratio = decibel::db_to_ratio(self.top()?.real_number()?, scale);
+		self.set_top(Value::Number(ratio))
+	}
+
+	pub fn dbm_to_watts(&mut self) -> Result<()> {
+		let watts = decibel::dbm_to_watts(self.top()?.real_number()?);
+		self.set_top(Value::Number(watts))
+	}
+
+	pub fn watts_to_dbm(&mut self) -> Result<()> {
+		let dbm = decibel::watts_to_dbm(self.top()?.real_number()?)?;
+		self.set_top(Value::Number(dbm))
+	}
+
+	/// Converts a level in dBm to an RMS voltage across the reference impedance
+	pub fn dbm_to_volts(&mut self) -> Result<()> {
+		let volts = decibel::dbm_to_volts(self.top()?.real_number()?, &self.reference_impedance);
+		self.set_top(Value::Number(volts))
+	}
+
+	/// Converts an RMS voltage across the reference impedance to a level in dBm
+	pub fn volts_to_dbm(&mut self) -> Result<()> {
+		let dbm = decibel::volts_to_dbm(self.top()?.real_number()?, &self.reference_impedance)?;
+		self.set_top(Value::Number(dbm))
+	}
+
+	/// Finds the y value at x on the line through the points (x₁, y₁) and
+	/// (x₂, y₂), which are given in the four entries above x
+	pub fn interpolate(&mut self) -> Result<()> {
+		let value = interpolate::linear_interpolate(
+			&self.entry(4)?,
+			&self.entry(3)?,
+			&self.entry(2)?,
+			&self.entry(1)?,
+			&self.entry(0)?,
+		)?;
+		self.replace_entries(5, value)
+	}
+
+	/// Finds the y value at x in a table given by a list of x values in z and a
+	/// list of y values in y
+	pub fn table_interpolate(&mut self) -> Result<()> {
+		match (self.entry(2)?, self.entry(1)?) {
+			(Value::List(xs), Value::List(ys)) => {
+				let value = interpolate::table_interpolate(&xs, &ys, &self.entry(0)?)?;
+				self.replace_entries(3, value)
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Adds tax at the rate in the business registers to x
+	pub fn add_tax(&mut self) -> Result<()> {
+		let factor = Value::Number(self.business.tax_factor()?);
+		self.set_top((self.top()? * factor)?)
+	}
+
+	/// Removes tax at the rate in the business registers from x
+	pub fn remove_tax(&mut self) -> Result<()> {
+		let factor = Value::Number(self.business.tax_factor()?);
+		self.set_top((self.top()? / factor)?)
+	}
+
 	pub fn percent(&mut self) -> Result<()> {
 		let factor = (self.entry(0)? / Value::Number(100.into()))?;
 		self.set_top((self.entry(1)? * factor)?)
@@ -328,15 +1151,30 @@ impl Context {
 	}
 
 	pub fn sin(&mut self) -> Result<()> {
-		self.set_top(self.top()?.sin(self.angle_mode)?)
+		self.set_trig_result(|value, angle_mode| value.sin(angle_mode))
 	}
 
 	pub fn cos(&mut self) -> Result<()> {
-		self.set_top(self.top()?.cos(self.angle_mode)?)
+		self.set_trig_result(|value, angle_mode| value.cos(angle_mode))
 	}
 
 	pub fn tan(&mut self) -> Result<()> {
-		self.set_top(self.top()?.tan(self.angle_mode)?)
+		self.set_trig_result(|value, angle_mode| value.tan(angle_mode))
+	}
+
+	/// Replaces the top of the stack with a trigonometric function of it. A plain
+	/// number is an angle in the angle mode, so the result is tagged with the
+	/// mode.
+	fn set_trig_result<F>(&mut self, func: F) -> Result<()>
+	where
+		F: Fn(&Value, AngleUnit) -> Result<Value>,
+	{
+		let value = self.top()?;
+		self.set_top(func(&value, self.angle_mode)?)?;
+		if let Value::Number(_) = value {
+			self.stack.tag_top_angle(self.angle_mode)?;
+		}
+		Ok(())
 	}
 
 	pub fn asin(&mut self) -> Result<()> {
@@ -377,27 +1215,27 @@ impl Context {
 
 	pub fn and(&mut self) -> Result<()> {
 		let value = Value::Number(Number::Integer(
-			&*self.entry(1)?.to_int()? & &*self.entry(0)?.to_int()?,
+			(&*self.entry(1)?.to_int()? & &*self.entry(0)?.to_int()?).into(),
 		));
 		self.replace_entries(2, value)
 	}
 
 	pub fn or(&mut self) -> Result<()> {
 		let value = Value::Number(Number::Integer(
-			&*self.entry(1)?.to_int()? | &*self.entry(0)?.to_int()?,
+			(&*self.entry(1)?.to_int()? | &*self.entry(0)?.to_int()?).into(),
 		));
 		self.replace_entries(2, value)
 	}
 
 	pub fn xor(&mut self) -> Result<()> {
 		let value = Value::Number(Number::Integer(
-			&*self.entry(1)?.to_int()? ^ &*self.entry(0)?.to_int()?,
+			(&*self.entry(1)?.to_int()? ^ &*self.entry(0)?.to_int()?).into(),
 		));
 		self.replace_entries(2, value)
 	}
 
 	pub fn not(&mut self) -> Result<()> {
-		let value = Number::Integer(!&*self.top()?.to_int()?);
+		let value = Number::Integer((!&*self.top()?.to_int()?).into());
 		self.set_top(Value::Number(value))
 	}
 
@@ -415,7 +1253,7 @@ impl Context {
 		if (y.bits() + x as u64) > MAX_INTEGER_BITS {
 			return Err(Error::ValueOutOfRange);
 		}
-		let value = Value::Number(Number::Integer(&*y << x));
+		let value = Value::Number(Number::Integer((&*y << x).into()));
 		self.replace_entries(2, value)
 	}
 
@@ -430,7 +1268,7 @@ impl Context {
 		let x = u32::try_from(&*x)?;
 		let y = self.entry(1)?;
 		let y = y.to_int()?;
-		let value = Value::Number(Number::Integer(&*y >> x));
+		let value = Value::Number(Number::Integer((&*y >> x).into()));
 		self.replace_entries(2, value)
 	}
 
@@ -445,7 +1283,7 @@ impl Context {
 				let y = self.entry(1)?;
 				let y = y.to_int()?;
 				let value = (&*y << x) | (&*y >> ((size as u32) - x));
-				self.replace_entries(2, Value::Number(Number::Integer(value)))
+				self.replace_entries(2, Value::Number(Number::Integer(value.into())))
 			} else {
 				Err(Error::ValueOutOfRange)
 			}
@@ -465,7 +1303,7 @@ impl Context {
 				let y = self.entry(1)?;
 				let y = y.to_int()?;
 				let value = (&*y >> x) | (&*y << ((size as u32) - x));
-				self.replace_entries(2, Value::Number(Number::Integer(value)))
+				self.replace_entries(2, Value::Number(Number::Integer(value.into())))
 			} else {
 				Err(Error::ValueOutOfRange)
 			}
@@ -474,10 +1312,190 @@ impl Context {
 		}
 	}
 
+	/// Number of bits that integers can hold in the current integer mode
+	fn word_bits(&self) -> usize {
+		match self.format.integer_mode {
+			IntegerMode::SizedInteger(size, _) => size,
+			_ => MAX_INTEGER_BITS as usize,
+		}
+	}
+
+	/// Bit position in the given stack entry, which must be within the word
+	fn bit_position(&self, offset: usize) -> Result<usize> {
+		let position = usize::try_from(&*self.entry(offset)?.to_int()?)?;
+		if position < self.word_bits() {
+			Ok(position)
+		} else {
+			Err(Error::ValueOutOfRange)
+		}
+	}
+
+	/// Position and unshifted mask of the bit field from the most significant
+	/// bit in y down to the least significant bit in x
+	fn bit_field(&self) -> Result<(usize, BigInt)> {
+		let msb = self.bit_position(1)?;
+		let lsb = self.bit_position(0)?;
+		if msb < lsb {
+			return Err(Error::InvalidArgument);
+		}
+		let mask = (1.to_bigint().unwrap() << (msb - lsb + 1)) - 1;
+		Ok((lsb, mask))
+	}
+
+	/// Replaces the value in z with its bits from the position in y down to the
+	/// position in x, shifted down to start at bit zero. Negative values are
+	/// taken as two's complement.
+	pub fn extract_bits(&mut self) -> Result<()> {
+		let (lsb, mask) = self.bit_field()?;
+		let value = (&*self.entry(2)?.to_int()? >> lsb) & mask;
+		self.replace_entries(3, Value::Number(Number::Integer(value.into())))
+	}
+
+	/// Replaces the bits of the value in t from the position in y down to the
+	/// position in x with the low bits of the field in z
+	pub fn insert_bits(&mut self) -> Result<()> {
+		let (lsb, mask) = self.bit_field()?;
+		let field = (&*self.entry(2)?.to_int()? & &mask) << lsb;
+		let value = (&*self.entry(3)?.to_int()? & !(mask << lsb)) | field;
+		self.replace_entries(4, Value::Number(Number::Integer(value.into())))
+	}
+
+	/// Replaces the count in x with a mask of that many low bits
+	pub fn bit_mask(&mut self) -> Result<()> {
+		let bits = usize::try_from(&*self.top()?.to_int()?)?;
+		if bits > self.word_bits() {
+			return Err(Error::ValueOutOfRange);
+		}
+		let mask: BigInt = (1.to_bigint().unwrap() << bits) - 1;
+		self.set_top(Value::Number(Number::Integer(mask.into())))
+	}
+
+	/// Integer in x as the bits of a word. In sized integer mode negative values
+	/// are taken as two's complement, and elsewhere they have no finite bits.
+	fn top_word(&self) -> Result<BigInt> {
+		let value = self.top()?.to_int()?.into_owned();
+		match self.format.integer_mode {
+			IntegerMode::SizedInteger(size, _) => {
+				Ok(value & ((1.to_bigint().unwrap() << size) - 1))
+			}
+			_ if value.sign() == Sign::Minus => Err(Error::ValueOutOfRange),
+			_ => Ok(value),
+		}
+	}
+
+	/// Replaces the integer in x with the number of bits set in it
+	pub fn population_count(&mut self) -> Result<()> {
+		let count = self.top_word()?.magnitude().count_ones();
+		self.set_top(Value::Number(Number::Integer(count.into())))
+	}
+
+	/// Replaces the integer in x with the number of zero bits above its highest
+	/// set bit in the word
+	pub fn count_leading_zeros(&mut self) -> Result<()> {
+		let size = match self.format.integer_mode {
+			IntegerMode::SizedInteger(size, _) => size as u64,
+			_ => return Err(Error::RequiresSizedIntegerMode),
+		};
+		let count = size - self.top_word()?.bits();
+		self.set_top(Value::Number(Number::Integer(count.into())))
+	}
+
+	/// Replaces the integer in x with the number of zero bits below its lowest
+	/// set bit. Zero has as many trailing zeros as the word has bits.
+	pub fn count_trailing_zeros(&mut self) -> Result<()> {
+		let value = self.top()?.to_int()?.into_owned();
+		let count = match (value.trailing_zeros(), self.format.integer_mode) {
+			(Some(count), IntegerMode::SizedInteger(size, _)) => core::cmp::min(count, size as u64),
+			(Some(count), _) => count,
+			(None, IntegerMode::SizedInteger(size, _)) => size as u64,
+			(None, _) => return Err(Error::ValueOutOfRange),
+		};
+		self.set_top(Value::Number(Number::Integer(count.into())))
+	}
+
+	/// Replaces the integer in x with the number of bits needed to write its
+	/// magnitude, which is zero for zero
+	pub fn bit_length(&mut self) -> Result<()> {
+		let bits = self.top()?.to_int()?.bits();
+		self.set_top(Value::Number(Number::Integer(bits.into())))
+	}
+
+	/// Replaces the top `count` entries with the low `bytes` bytes of `value` in
+	/// reverse order, taking negative values as two's complement. A sized integer
+	/// word narrower than the bytes is widened to hold the result.
+	fn replace_with_reversed_bytes(
+		&mut self,
+		count: usize,
+		value: &BigInt,
+		bytes: usize,
+	) -> Result<()> {
+		let bits = bytes * 8;
+		if bytes == 0 || bits as u64 > MAX_INTEGER_BITS {
+			return Err(Error::ValueOutOfRange);
+		}
+		let mask = (1.to_bigint().unwrap() << bits) - 1;
+		let mut reversed = (value & &mask).to_bytes_le().1;
+		reversed.resize(bytes, 0);
+		let reversed = BigInt::from_bytes_be(Sign::Plus, &reversed);
+
+		if let IntegerMode::SizedInteger(size, signed) = self.format.integer_mode {
+			if size < bits {
+				self.set_integer_mode(IntegerMode::SizedInteger(bits, signed));
+			}
+		}
+		self.replace_entries(count, Value::Number(Number::Integer(reversed.into())))
+	}
+
+	/// Reverses the order of the low `bytes` bytes of the integer in x
+	pub fn swap_bytes(&mut self, bytes: usize) -> Result<()> {
+		let value = self.top()?.to_int()?.into_owned();
+		self.replace_with_reversed_bytes(1, &value, bytes)
+	}
+
+	/// Reverses the order of the low x bytes of the integer in y
+	pub fn reverse_bytes(&mut self) -> Result<()> {
+		let bytes = usize::try_from(&*self.entry(0)?.to_int()?)?;
+		let value = self.entry(1)?.to_int()?.into_owned();
+		self.replace_with_reversed_bytes(2, &value, bytes)
+	}
+
 	pub fn now(&mut self) -> Result<()> {
 		self.push(Value::DateTime(NaiveDateTime::now()?))
 	}
 
+	/// Replaces the ISO 8601 text in x with the date, time of day, or date and
+	/// time that it holds
+	pub fn from_iso8601(&mut self) -> Result<()> {
+		match self.top()? {
+			Value::Text(text) => {
+				let text = core::str::from_utf8(&text).map_err(|_| Error::InvalidArgument)?;
+				self.set_top(iso8601::parse(text)?)
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Combines the value in y into the stack level given by x, counting y as
+	/// level 1 once x is removed. The value stays on the stack and the other
+	/// levels are left in place, so this is register arithmetic on a stack level.
+	pub fn level_operation(&mut self, op: fn(Value, Value) -> Result<Value>) -> Result<()> {
+		let level = usize::try_from(&*self.entry(0)?.to_int()?)?;
+		if level < 2 || level >= self.stack.len() {
+			return Err(Error::InvalidStackIndex);
+		}
+		let mut items = Vec::new();
+		items.push(op(self.entry(level)?, self.entry(1)?)?);
+		for idx in (1..level).rev() {
+			items.push(self.entry(idx)?);
+		}
+		self.stack.replace_entries_with_multiple(level + 1, items)
+	}
+
+	pub fn to_iso8601(&mut self) -> Result<()> {
+		let text = iso8601::format(&self.top()?)?;
+		self.set_top(Value::Text(text.into_bytes()))
+	}
+
 	pub fn date(&mut self) -> Result<()> {
 		if let Value::DateTime(dt) = self.top()? {
 			let date = dt.date();
@@ -497,8 +1515,7 @@ impl Context {
 			let time = dt.time();
 			self.set_top(Value::Time(time))
 		} else {
-			let nano = (self.entry(0)?
-				* Value::Number(Number::Integer(1_000_000_000.to_bigint().unwrap())))?;
+			let nano = (self.entry(0)? * Value::Number(Number::Integer(1_000_000_000.into())))?;
 			let hr = u8::try_from(&*self.entry(2)?.to_int()?)?;
 			let min = u8::try_from(&*self.entry(1)?.to_int()?)?;
 			let sec = u64::try_from(&*nano.to_int()?)?;
@@ -561,21 +1578,239 @@ impl Context {
 	}
 
 	pub fn sum(&mut self) -> Result<()> {
-		if let Value::Vector(vector) = self.top()? {
-			self.set_top(vector.sum()?)
-		} else {
-			Err(Error::DataTypeMismatch)
+		match self.top()? {
+			Value::Vector(vector) => self.set_top(vector.sum()?),
+			Value::List(list) => self.set_top(list.values().sum()?),
+			_ => Err(Error::DataTypeMismatch),
 		}
 	}
 
 	pub fn mean(&mut self) -> Result<()> {
-		if let Value::Vector(vector) = self.top()? {
-			self.set_top(vector.mean()?)
+		match self.top()? {
+			Value::Vector(vector) => self.set_top(vector.mean()?),
+			Value::List(list) => self.set_top(list.values().mean()?),
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Replaces the data set in y and the bin count in x with a vector of the
+	/// number of values in each bin
+	pub fn histogram(&mut self) -> Result<()> {
+		let bins =
+			usize::try_from(&*self.entry(0)?.to_int()?).map_err(|_| Error::InvalidArgument)?;
+		let histogram = Histogram::new(&Histogram::data_values(&self.entry(1)?)?, bins)?;
+		let mut counts = Vector::new()?;
+		for count in histogram.counts() {
+			counts.push(Value::Number(Number::Integer((*count).into())))?;
+		}
+		self.replace_entries(2, Value::Vector(counts))
+	}
+
+	/// Replaces x with the number of primes up to x
+	pub fn prime_count(&mut self) -> Result<()> {
+		let x = self.top()?.to_int()?.into_owned();
+		let count = match u64::try_from(&x) {
+			Ok(x) => prime::prime_count(x)?,
+			Err(_) if x < 0.into() => 0,
+			Err(_) => return Err(Error::ValueOutOfRange),
+		};
+		self.set_top(Value::Number(Number::Integer(count.into())))
+	}
+
+	/// Replaces x with the xth prime, counting 2 as the first
+	pub fn nth_prime(&mut self) -> Result<()> {
+		let n = u64::try_from(&*self.top()?.to_int()?).map_err(|_| Error::ValueOutOfRange)?;
+		let prime = prime::nth_prime(n)?;
+		self.set_top(Value::Number(Number::Integer(prime.into())))
+	}
+
+	/// Replaces the integer in x with the text of its bytes, most significant
+	/// first
+	pub fn to_text(&mut self) -> Result<()> {
+		let bytes = text::integer_to_bytes(&*self.top()?.to_int()?)?;
+		self.set_top(Value::Text(bytes))
+	}
+
+	/// Replaces the text in x with the integer made of its bytes
+	pub fn from_text(&mut self) -> Result<()> {
+		match self.top()? {
+			Value::Text(bytes) => self.set_top(Value::Number(Number::Integer(
+				text::bytes_to_integer(&bytes).into(),
+			))),
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Bytes of the text or integer in the given stack entry. In sized integer
+	/// mode an integer has all of the bytes of its two's complement word.
+	fn entry_bytes(&self, offset: usize) -> Result<Vec<u8>> {
+		let value = match self.entry(offset)? {
+			Value::Text(bytes) => return Ok(bytes),
+			value => value.to_int()?.into_owned(),
+		};
+		match self.format.integer_mode {
+			IntegerMode::SizedInteger(size, _) => {
+				let word = value & ((1.to_bigint().unwrap() << size) - 1);
+				let mut bytes = text::integer_to_bytes(&word)?;
+				let len = size.div_ceil(8);
+				if bytes.len() < len {
+					bytes.splice(0..0, core::iter::repeat_n(0, len - bytes.len()));
+				}
+				Ok(bytes)
+			}
+			_ => text::integer_to_bytes(&value),
+		}
+	}
+
+	/// Text of x, found with `decode` from the text in x
+	fn decode_top(&mut self, decode: fn(&[u8]) -> Result<Vec<u8>>) -> Result<()> {
+		match self.top()? {
+			Value::Text(encoded) => self.set_top(Value::Text(decode(&encoded)?)),
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	pub fn base64_encode(&mut self) -> Result<()> {
+		let bytes = self.entry_bytes(0)?;
+		self.set_top(Value::Text(text::base64_encode(&bytes)))
+	}
+
+	pub fn base64_decode(&mut self) -> Result<()> {
+		self.decode_top(text::base64_decode)
+	}
+
+	pub fn hex_encode(&mut self) -> Result<()> {
+		let bytes = self.entry_bytes(0)?;
+		self.set_top(Value::Text(text::hex_encode(&bytes)))
+	}
+
+	pub fn hex_decode(&mut self) -> Result<()> {
+		self.decode_top(text::hex_decode)
+	}
+
+	/// Checksum of the bytes of the text or integer in x, or in y with the word
+	/// width in bits in x for checksums that take a width
+	pub fn checksum(&mut self, kind: ChecksumKind, order: ByteOrder) -> Result<()> {
+		let (count, width) = if kind.has_width() {
+			(2, u32::try_from(&*self.entry(0)?.to_int()?)?)
+		} else {
+			(1, 16)
+		};
+		let bytes = self.entry_bytes(count - 1)?;
+		let sum = checksum::checksum(kind, order, &bytes, width)?;
+		self.replace_entries(count, Value::Number(Number::Integer(sum.into())))
+	}
+
+	/// Address in the given stack entry, either as text or as an integer. Returns
+	/// the address with its width in bits, and whether it was text. Integers
+	/// that fit in 32 bits are IPv4 addresses.
+	fn address_argument(&self, offset: usize) -> Result<(u128, u32, bool)> {
+		match self.entry(offset)? {
+			Value::Text(text) => {
+				let text = core::str::from_utf8(&text).map_err(|_| Error::InvalidArgument)?;
+				if text.contains(':') {
+					Ok((network::parse_ipv6(text)?, network::IPV6_BITS, true))
+				} else {
+					Ok((network::parse_ipv4(text)? as u128, network::IPV4_BITS, true))
+				}
+			}
+			value => {
+				let address = u128::try_from(&*value.to_int()?)?;
+				if address <= u32::MAX as u128 {
+					Ok((address, network::IPV4_BITS, false))
+				} else {
+					Ok((address, network::IPV6_BITS, false))
+				}
+			}
+		}
+	}
+
+	/// Value of an address, written as text if the argument was text
+	fn address_value(address: u128, bits: u32, text: bool) -> Value {
+		match (text, bits) {
+			(false, _) => Value::Number(Number::Integer(address.into())),
+			(true, network::IPV4_BITS) => {
+				Value::Text(network::format_ipv4(address as u32).into_bytes())
+			}
+			(true, _) => Value::Text(network::format_ipv6(address).into_bytes()),
+		}
+	}
+
+	/// Replaces the IPv4 or IPv6 address text in x with its integer value
+	pub fn address_to_integer(&mut self) -> Result<()> {
+		if let Value::Text(_) = self.top()? {
+			let (address, bits, _) = self.address_argument(0)?;
+			self.set_top(Self::address_value(address, bits, false))
 		} else {
 			Err(Error::DataTypeMismatch)
 		}
 	}
 
+	/// Replaces the integer in x with the text of the IPv4 address
+	pub fn integer_to_ipv4(&mut self) -> Result<()> {
+		let address = u32::try_from(&*self.top()?.to_int()?)?;
+		self.set_top(Self::address_value(
+			address as u128,
+			network::IPV4_BITS,
+			true,
+		))
+	}
+
+	/// Replaces the integer in x with the text of the IPv6 address
+	pub fn integer_to_ipv6(&mut self) -> Result<()> {
+		let address = u128::try_from(&*self.top()?.to_int()?)?;
+		self.set_top(Self::address_value(address, network::IPV6_BITS, true))
+	}
+
+	/// Replaces the address in y and prefix length in x with the first address
+	/// of the network
+	pub fn network_address(&mut self) -> Result<()> {
+		let prefix = u32::try_from(&*self.entry(0)?.to_int()?)?;
+		let (address, bits, text) = self.address_argument(1)?;
+		let address = network::network_address(address, bits, prefix)?;
+		self.replace_entries(2, Self::address_value(address, bits, text))
+	}
+
+	/// Replaces the address in y and prefix length in x with the broadcast
+	/// address of the network
+	pub fn broadcast_address(&mut self) -> Result<()> {
+		let prefix = u32::try_from(&*self.entry(0)?.to_int()?)?;
+		let (address, bits, text) = self.address_argument(1)?;
+		let address = network::broadcast_address(address, bits, prefix)?;
+		self.replace_entries(2, Self::address_value(address, bits, text))
+	}
+
+	/// Replaces the IPv4 prefix length in x with the text of its netmask
+	pub fn ipv4_netmask(&mut self) -> Result<()> {
+		let prefix = u32::try_from(&*self.top()?.to_int()?)?;
+		let mask = network::ipv4_netmask(prefix)?;
+		self.set_top(Self::address_value(mask as u128, network::IPV4_BITS, true))
+	}
+
+	/// Replaces the IPv4 prefix length in x with the number of usable hosts
+	pub fn ipv4_host_count(&mut self) -> Result<()> {
+		let prefix = u32::try_from(&*self.top()?.to_int()?)?;
+		let count = network::ipv4_host_count(prefix)?;
+		self.set_top(Value::Number(Number::Integer(count.into())))
+	}
+
+	/// Evaluates a function of a probability distribution. The parameters of the
+	/// distribution are taken from the stack above the argument.
+	pub fn distribution(
+		&mut self,
+		kind: DistributionKind,
+		func: DistributionFunction,
+	) -> Result<()> {
+		let count = kind.parameter_count() + 1;
+		let mut params = Vec::new();
+		for idx in (1..count).rev() {
+			params.push(self.entry(idx)?.real_number()?.to_decimal().into_owned());
+		}
+		let x = self.top()?.real_number()?.to_decimal().into_owned();
+		let result = Distribution::new(kind, params)?.evaluate(func, &x)?;
+		self.replace_entries(count, Value::Number(Number::Decimal(result)))
+	}
+
 	pub fn dot_product(&mut self) -> Result<()> {
 		let a = self.entry(1)?;
 		let b = self.entry(0)?;
@@ -604,11 +1839,21 @@ impl Context {
 		}
 	}
 
+	pub fn angle_between(&mut self) -> Result<()> {
+		match (self.entry(1)?, self.entry(0)?) {
+			(Value::Vector(a), Value::Vector(b)) => {
+				let angle = a.angle(&b, self.angle_mode)?;
+				self.replace_entries(2, angle)
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
 	pub fn magnitude(&mut self) -> Result<()> {
-		if let Value::Vector(vector) = self.top()? {
-			self.set_top(vector.magnitude()?)
-		} else {
-			Err(Error::DataTypeMismatch)
+		match self.top()? {
+			Value::Vector(vector) => self.set_top(vector.magnitude()?),
+			Value::Quaternion(value) => self.set_top(Value::Number(value.norm())),
+			_ => Err(Error::DataTypeMismatch),
 		}
 	}
 
@@ -787,6 +2032,35 @@ impl Context {
 		}
 	}
 
+	/// Solves the linear system with the coefficient matrix in y and the right
+	/// hand side in x. A vector on the right gives a solution vector, and a
+	/// matrix gives a solution for each of its columns.
+	pub fn solve_linear_system(&mut self) -> Result<()> {
+		let coefficients = match self.entry(1)? {
+			Value::Matrix(matrix) => matrix,
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		match self.top()? {
+			Value::Vector(vector) => {
+				let mut rhs = Matrix::new(vector.len(), 1)?;
+				for i in 0..vector.len() {
+					rhs.set(i, 0, vector.get(i)?)?;
+				}
+				let solution = coefficients.solve(&rhs)?;
+				let mut result = Vector::new()?;
+				for i in 0..solution.rows() {
+					result.push(solution.get(i, 0)?)?;
+				}
+				self.replace_entries(2, Value::Vector(result))
+			}
+			Value::Matrix(rhs) => {
+				let solution = coefficients.solve(&rhs)?;
+				self.replace_entries(2, Value::Matrix(solution))
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
 	pub fn identity_matrix(&mut self) -> Result<()> {
 		let size = usize::try_from(&*self.top()?.to_int()?)?;
 		if size == 0 {
@@ -863,6 +2137,101 @@ impl Context {
 		}
 	}
 
+	pub fn quaternion(&mut self) -> Result<()> {
+		let top = self.entry(0)?;
+		if let Value::Quaternion(value) = top {
+			// If a quaternion is on the top of the stack, break it into its
+			// four parts.
+			let mut items = Vec::new();
+			for part in value.parts().iter() {
+				items.push(store(Value::Number((*part).clone()))?);
+			}
+			self.replace_top_with_multiple(items)
+		} else {
+			// Take the four parts from the top four entries on the stack, with
+			// the real part the furthest from the top.
+			let w = self.entry(3)?.real_number()?.clone();
+			let x = self.entry(2)?.real_number()?.clone();
+			let y = self.entry(1)?.real_number()?.clone();
+			let z = top.real_number()?.clone();
+			self.replace_entries(
+				4,
+				Value::check_quaternion(Quaternion::from_parts(w, x, y, z))?,
+			)
+		}
+	}
+
+	pub fn conjugate(&mut self) -> Result<()> {
+		match self.top()? {
+			Value::Quaternion(value) => self.set_top(Value::Quaternion(value.conjugate())),
+			Value::Complex(value) => self.set_top(Value::Complex(ComplexNumber::from_parts(
+				value.real_part().clone(),
+				-value.imaginary_part(),
+			))),
+			Value::Number(_) | Value::NumberWithUnit(_, _) => Ok(()),
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	pub fn quaternion_to_rotation_matrix(&mut self) -> Result<()> {
+		let value = self.top()?.quaternion()?.into_owned();
+		self.set_top(Value::Matrix(value.to_rotation_matrix()?))
+	}
+
+	pub fn rotation_matrix_to_quaternion(&mut self) -> Result<()> {
+		if let Value::Matrix(matrix) = self.top()? {
+			self.set_top(Value::check_quaternion(Quaternion::from_rotation_matrix(
+				&matrix,
+			)?)?)
+		} else {
+			Err(Error::DataTypeMismatch)
+		}
+	}
+
+	/// Replaces a quaternion with the axis of its rotation and the angle
+	/// about it, in the angle mode
+	pub fn quaternion_to_axis_angle(&mut self) -> Result<()> {
+		let value = self.top()?.quaternion()?.into_owned();
+		let (axis, angle) = value.to_axis_angle(self.angle_mode)?;
+		let items = vec![store(Value::Vector(axis))?, store(Value::Number(angle))?];
+		self.replace_top_with_multiple(items)
+	}
+
+	/// Quaternion of the rotation about the axis in y by the angle in x
+	pub fn axis_angle_to_quaternion(&mut self) -> Result<()> {
+		let angle = self.entry(0)?;
+		if let Value::Vector(axis) = self.entry(1)? {
+			let value = Quaternion::from_axis_angle(&axis, angle.real_number()?, self.angle_mode)?;
+			self.replace_entries(2, Value::check_quaternion(value)?)
+		} else {
+			Err(Error::DataTypeMismatch)
+		}
+	}
+
+	pub fn to_angle(&mut self, unit: AngleUnit) -> Result<()> {
+		let value = self.top()?.to_angle(unit, self.angle_mode)?;
+		self.set_top(value)
+	}
+
+	pub fn rotate_2d(&mut self) -> Result<()> {
+		if let Value::Vector(point) = self.entry(1)? {
+			let value = point.rotate_2d(&self.entry(0)?, self.angle_mode)?;
+			self.replace_entries(2, Value::Vector(value))
+		} else {
+			Err(Error::DataTypeMismatch)
+		}
+	}
+
+	pub fn rotate_3d(&mut self) -> Result<()> {
+		match (self.entry(2)?, self.entry(1)?) {
+			(Value::Vector(point), Value::Vector(axis)) => {
+				let value = point.rotate_3d(&axis, &self.entry(0)?, self.angle_mode)?;
+				self.replace_entries(3, Value::Vector(value))
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
 	pub fn add_to_vector(&mut self) -> Result<()> {
 		let top = self.entry(0)?;
 		if let Value::Vector(existing_vector) = top {
@@ -905,13 +2274,20 @@ impl Context {
 				}
 			}
 			self.replace_top_with_multiple(values)
+		} else if let Value::List(list) = top {
+			// Top entry is a list. Break apart the list.
+			let mut values: Vec<ValueRef> = Vec::new();
+			for i in 0..list.len() {
+				values.push(list.values().get_ref(i)?);
+			}
+			self.replace_top_with_multiple(values)
 		} else {
 			// Batch create a vector from the entries on the stack. If there
 			// is a vector or matrix on the stack, stop there.
 			let mut vector = Vector::new()?;
 			for i in 0..self.stack_len() {
 				let value = self.entry(i)?;
-				if value.is_vector_or_matrix() {
+				if value.is_collection() {
 					break;
 				}
 				vector.insert(0, value)?;
@@ -922,4 +2298,44 @@ impl Context {
 			self.replace_entries(vector.len(), Value::Vector(vector))
 		}
 	}
+
+	/// Creates a list from the number of elements in x and the elements above it
+	pub fn to_list(&mut self) -> Result<()> {
+		let count = usize::try_from(&*self.entry(0)?.to_int()?)?;
+		let mut list = List::new()?;
+		for i in (1..=count).rev() {
+			list.push(self.entry(i)?)?;
+		}
+		self.replace_entries(count + 1, Value::List(list))
+	}
+
+	/// Gets the element of the list in y at the position in x, counting from one
+	pub fn list_get(&mut self) -> Result<()> {
+		match self.entry(1)? {
+			Value::List(list) => {
+				let idx = usize::try_from(&*self.entry(0)?.to_int()?)?;
+				if idx == 0 {
+					return Err(Error::IndexOutOfRange);
+				}
+				self.replace_entries(2, list.get(idx - 1)?)
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Appends x to the end of the list in y. A list in x is appended element by
+	/// element.
+	pub fn list_append(&mut self) -> Result<()> {
+		match (self.entry(1)?, self.entry(0)?) {
+			(Value::List(mut list), Value::List(other)) => {
+				list.extend_with(&other)?;
+				self.replace_entries(2, Value::List(list))
+			}
+			(Value::List(mut list), value) => {
+				list.push(value)?;
+				self.replace_entries(2, Value::List(list))
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
 }
@@ -0,0 +1,69 @@
+use crate::error::{Error, Result};
+use crate::number::{Number, ToNumber};
+
+/// Impedance that levels in dBm are converted to voltages across until another
+/// one is set, which is the usual impedance of radio frequency systems
+pub const DEFAULT_REFERENCE_IMPEDANCE: i64 = 50;
+
+/// Quantity compared by a ratio in decibels. A ratio of powers is ten times its
+/// logarithm, and a ratio of field quantities such as voltages is twenty times
+/// it, because power is proportional to their square.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DecibelScale {
+	Power,
+	Field,
+}
+
+impl DecibelScale {
+	pub fn to_u16(&self) -> u16 {
+		match self {
+			DecibelScale::Power => 0,
+			DecibelScale::Field => 1,
+		}
+	}
+
+	pub fn from_u16(value: u16) -> Option<Self> {
+		match value {
+			0 => Some(DecibelScale::Power),
+			1 => Some(DecibelScale::Field),
+			_ => None,
+		}
+	}
+
+	fn factor(&self) -> Number {
+		match self {
+			DecibelScale::Power => 10.to_number(),
+			DecibelScale::Field => 20.to_number(),
+		}
+	}
+}
+
+pub fn ratio_to_db(ratio: &Number, scale: DecibelScale) -> Result<Number> {
+	if ratio.is_zero() || ratio.is_negative() {
+		return Err(Error::ValueOutOfRange);
+	}
+	Ok(&scale.factor() * &ratio.log())
+}
+
+pub fn db_to_ratio(db: &Number, scale: DecibelScale) -> Number {
+	(db / &scale.factor()).exp10()
+}
+
+/// Power in watts of a level in dBm, which is decibels relative to a milliwatt
+pub fn dbm_to_watts(dbm: &Number) -> Number {
+	&db_to_ratio(dbm, DecibelScale::Power) / &1000.to_number()
+}
+
+pub fn watts_to_dbm(watts: &Number) -> Result<Number> {
+	ratio_to_db(&(watts * &1000.to_number()), DecibelScale::Power)
+}
+
+/// RMS voltage of a level in dBm across an impedance
+pub fn dbm_to_volts(dbm: &Number, impedance: &Number) -> Number {
+	(&dbm_to_watts(dbm) * impedance).sqrt()
+}
+
+/// Level in dBm of an RMS voltage across an impedance
+pub fn volts_to_dbm(volts: &Number, impedance: &Number) -> Result<Number> {
+	watts_to_dbm(&(&(volts * volts) / impedance))
+}
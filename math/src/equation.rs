@@ -0,0 +1,90 @@
+use crate::error::Result;
+use crate::script::Script;
+
+/// Formula of the built in equation library. The source is a user function
+/// that is zero when the formula holds, so that the solver can find any one of
+/// its variables from the others. Constants in the formulas are in SI units.
+pub struct LibraryEquation {
+	pub name: &'static str,
+	pub formula: &'static str,
+	source: &'static str,
+}
+
+impl LibraryEquation {
+	pub fn script(&self) -> Result<Script> {
+		Script::parse(self.source)
+	}
+}
+
+pub const EQUATION_LIBRARY: [LibraryEquation; 14] = [
+	LibraryEquation {
+		name: "Velocity",
+		formula: "v=u+a·t",
+		source: "« → v u a t « v u a t * + - » »",
+	},
+	LibraryEquation {
+		name: "Displacement",
+		formula: "s=u·t+a·t²/2",
+		source: "« → s u t a « s u t * a t SQ * 2 / + - » »",
+	},
+	LibraryEquation {
+		name: "Velocity and distance",
+		formula: "v²=u²+2·a·s",
+		source: "« → v u a s « v SQ u SQ 2 a * s * + - » »",
+	},
+	LibraryEquation {
+		name: "Newton's second law",
+		formula: "F=m·a",
+		source: "« → F m a « F m a * - » »",
+	},
+	LibraryEquation {
+		name: "Kinetic energy",
+		formula: "E=m·v²/2",
+		source: "« → E m v « E m v SQ * 2 / - » »",
+	},
+	LibraryEquation {
+		name: "Potential energy",
+		formula: "E=m·g·h",
+		source: "« → E m g h « E m g * h * - » »",
+	},
+	LibraryEquation {
+		name: "Pendulum period",
+		formula: "T=2·π·√(L/g)",
+		source: "« → T L g « T 2 PI * L g / √ * - » »",
+	},
+	LibraryEquation {
+		name: "Ohm's law",
+		formula: "V=I·R",
+		source: "« → V I R « V I R * - » »",
+	},
+	LibraryEquation {
+		name: "Electric power",
+		formula: "P=V·I",
+		source: "« → P V I « P V I * - » »",
+	},
+	LibraryEquation {
+		name: "Parallel resistors",
+		formula: "1/R=1/A+1/B",
+		source: "« → R A B « R INV A INV B INV + - » »",
+	},
+	LibraryEquation {
+		name: "Ideal gas",
+		formula: "P·V=n·R·T",
+		source: "« → P V n T « P V * n T * 8.314462618 * - » »",
+	},
+	LibraryEquation {
+		name: "Thin lens",
+		formula: "1/f=1/o+1/i",
+		source: "« → f o i « f INV o INV i INV + - » »",
+	},
+	LibraryEquation {
+		name: "Density",
+		formula: "ρ=m/V",
+		source: "« → ρ m V « ρ m V / - » »",
+	},
+	LibraryEquation {
+		name: "Wave speed",
+		formula: "v=f·λ",
+		source: "« → v f λ « v f λ * - » »",
+	},
+];
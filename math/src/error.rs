@@ -24,6 +24,23 @@ pub enum Error {
 	VectorTooLarge,
 	MatrixTooLarge,
 	DimensionMismatch,
+	SingularMatrix,
+	LabelNotFound,
+	NoProgram,
+	Locked,
+	NotSupported,
+	WriteFailed,
+	UnsupportedVersion,
+	NoSavedState,
+	SyntaxError,
+	UnknownName,
+	FileNotFound,
+	DivideByZero,
+	InvalidArgument,
+	ResultTooLarge,
+	Interrupted,
+	NoSolution,
+	ExamMode,
 }
 
 impl Error {
@@ -50,6 +67,23 @@ impl Error {
 			Error::VectorTooLarge => "Vector too large",
 			Error::MatrixTooLarge => "Matrix too large",
 			Error::DimensionMismatch => "Dimension mismatch",
+			Error::SingularMatrix => "Singular matrix",
+			Error::LabelNotFound => "Label not found",
+			Error::NoProgram => "No program recorded",
+			Error::Locked => "Locked",
+			Error::NotSupported => "Not supported",
+			Error::WriteFailed => "Write failed",
+			Error::UnsupportedVersion => "Unsupported version",
+			Error::NoSavedState => "No saved state",
+			Error::SyntaxError => "Syntax error",
+			Error::UnknownName => "Unknown name",
+			Error::FileNotFound => "File not found",
+			Error::DivideByZero => "Divide by zero",
+			Error::InvalidArgument => "Invalid argument",
+			Error::ResultTooLarge => "Result too large",
+			Error::Interrupted => "Interrupted",
+			Error::NoSolution => "No solution found",
+			Error::ExamMode => "Disabled in exam mode",
 		}
 	}
 }
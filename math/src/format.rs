@@ -1,5 +1,7 @@
-use crate::number::Number;
-use intel_dfp::Decimal;
+use crate::error::Result;
+use crate::number::{Number, GUARD_DIGITS, MAX_INTEGER_BITS};
+use crate::storage::{DeserializeInput, SerializeOutput};
+use intel_dfp::{Decimal, RoundingMode};
 use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
 
 #[cfg(feature = "std")]
@@ -15,12 +17,24 @@ use core::convert::TryInto;
 // Number of integer bits to attempt to render in short form (i.e. stack display)
 pub const MAX_SHORT_DISPLAY_BITS: u64 = 128;
 
+// Largest digit count that can be selected for fixed, scientific and engineering modes
+pub const MAX_DISPLAY_DIGITS: usize = 11;
+
+// Number of significant digits held by a decimal
+const MAX_DECIMAL_DIGITS: usize = 34;
+
+// Most significant digits displayed for a decimal, which leaves out the guard
+// digits at the end
+const MAX_SIGNIFICANT_DIGITS: usize = Decimal::DIGITS - GUARD_DIGITS;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum FormatMode {
 	Normal,
 	Rational,
+	Fixed,
 	Scientific,
 	Engineering,
+	Significant,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -29,6 +43,75 @@ pub enum DecimalPointMode {
 	Comma,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DigitSeparator {
+	Comma,
+	ThinSpace,
+	Apostrophe,
+	Underscore,
+}
+
+impl DigitSeparator {
+	pub fn to_str(&self) -> &'static str {
+		match self {
+			DigitSeparator::Comma => "Comma",
+			DigitSeparator::ThinSpace => "Space",
+			DigitSeparator::Apostrophe => "Apostrophe",
+			DigitSeparator::Underscore => "Underscore",
+		}
+	}
+
+	/// Name of the separator as it appears with the given decimal point
+	pub fn label(&self, decimal_point: DecimalPointMode) -> &'static str {
+		match (self, decimal_point) {
+			(DigitSeparator::Comma, DecimalPointMode::Comma) => "Period",
+			_ => self.to_str(),
+		}
+	}
+
+	/// Separator placed between groups of three digits in decimal numbers. When a
+	/// comma is the decimal point, a period is used in place of the comma separator.
+	pub fn decimal_char(&self, decimal_point: DecimalPointMode) -> char {
+		match self {
+			DigitSeparator::Comma => match decimal_point {
+				DecimalPointMode::Period => ',',
+				DecimalPointMode::Comma => '.',
+			},
+			DigitSeparator::ThinSpace => '\u{2009}',
+			DigitSeparator::Apostrophe => '\'',
+			DigitSeparator::Underscore => '_',
+		}
+	}
+
+	/// Separator placed between nibbles in hex and binary numbers. Commas are not
+	/// used here, so the comma separator groups nibbles with an apostrophe.
+	pub fn nibble_char(&self) -> char {
+		match self {
+			DigitSeparator::Comma | DigitSeparator::Apostrophe => '\'',
+			DigitSeparator::ThinSpace => '\u{2009}',
+			DigitSeparator::Underscore => '_',
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OverflowMode {
+	Wrap,
+	Ellipsis,
+}
+
+/// How rational numbers are shown in the rational format mode
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FractionDisplay {
+	/// Fractions that are too large to fit are shown as decimals
+	Auto,
+	/// Fractions are always shown, with parts that are too long wrapped onto
+	/// more lines
+	Fraction,
+	/// Rational numbers are always shown as decimals
+	Decimal,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum IntegerMode {
 	Float,
@@ -36,6 +119,40 @@ pub enum IntegerMode {
 	SizedInteger(usize, bool),
 }
 
+impl IntegerMode {
+	/// Writes the mode into saved state
+	pub fn save<Out: SerializeOutput>(&self, output: &mut Out) -> Result<()> {
+		match self {
+			IntegerMode::Float => output.write_u8(0)?,
+			IntegerMode::BigInteger => output.write_u8(1)?,
+			IntegerMode::SizedInteger(bits, signed) => {
+				output.write_u8(2)?;
+				output.write_u16(*bits as u16)?;
+				output.write_u8(*signed as u8)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads a mode written by `save`. Returns `None` if the mode is not valid.
+	pub fn restore(input: &mut DeserializeInput) -> Result<Option<Self>> {
+		Ok(match input.read_u8()? {
+			0 => Some(IntegerMode::Float),
+			1 => Some(IntegerMode::BigInteger),
+			2 => {
+				let bits = input.read_u16()? as usize;
+				let signed = input.read_u8()? != 0;
+				if bits > 0 && bits as u64 <= MAX_INTEGER_BITS {
+					Some(IntegerMode::SizedInteger(bits, signed))
+				} else {
+					None
+				}
+			}
+			_ => None,
+		})
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AlternateFormatMode {
 	Smart,
@@ -53,21 +170,218 @@ impl AlternateFormatMode {
 	}
 }
 
-#[derive(Clone)]
+/// Alternate representation that can be shown alongside a value
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AlternateForm {
+	Binary,
+	Octal,
+	Hex,
+	Decimal,
+	Float,
+	Fraction,
+}
+
+impl AlternateForm {
+	/// All alternate forms, in the order of preference when more than one can
+	/// be shown for a value
+	pub const ALL: [AlternateForm; 6] = [
+		AlternateForm::Binary,
+		AlternateForm::Octal,
+		AlternateForm::Hex,
+		AlternateForm::Decimal,
+		AlternateForm::Float,
+		AlternateForm::Fraction,
+	];
+
+	pub fn to_str(&self) -> &'static str {
+		match self {
+			AlternateForm::Binary => "Binary",
+			AlternateForm::Octal => "Octal",
+			AlternateForm::Hex => "Hex",
+			AlternateForm::Decimal => "Decimal",
+			AlternateForm::Float => "Float",
+			AlternateForm::Fraction => "Fraction",
+		}
+	}
+
+	/// Radix of the form if it is an integer representation
+	pub fn radix(&self) -> Option<u8> {
+		match self {
+			AlternateForm::Binary => Some(2),
+			AlternateForm::Octal => Some(8),
+			AlternateForm::Hex => Some(16),
+			AlternateForm::Decimal => Some(10),
+			AlternateForm::Float | AlternateForm::Fraction => None,
+		}
+	}
+
+	fn flag(&self) -> u8 {
+		1 << (*self as u8)
+	}
+}
+
+/// Class of value that alternate forms can be enabled for
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AlternateValueClass {
+	Integer,
+	Rational,
+	Complex,
+}
+
+impl AlternateValueClass {
+	pub const ALL: [AlternateValueClass; 3] = [
+		AlternateValueClass::Integer,
+		AlternateValueClass::Rational,
+		AlternateValueClass::Complex,
+	];
+
+	pub fn to_str(&self) -> &'static str {
+		match self {
+			AlternateValueClass::Integer => "Integer",
+			AlternateValueClass::Rational => "Rational",
+			AlternateValueClass::Complex => "Complex",
+		}
+	}
+
+	/// Returns true if the alternate form can be shown for this class of value
+	pub fn supports(&self, form: AlternateForm) -> bool {
+		match self {
+			AlternateValueClass::Integer => form.radix().is_some(),
+			AlternateValueClass::Rational => {
+				form == AlternateForm::Float || form == AlternateForm::Fraction
+			}
+			AlternateValueClass::Complex => form == AlternateForm::Float,
+		}
+	}
+}
+
+/// Set of alternate forms enabled for each class of value
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AlternateForms {
+	pub(crate) integer: u8,
+	pub(crate) rational: u8,
+	pub(crate) complex: u8,
+}
+
+impl AlternateForms {
+	/// Default forms, which show integers in hex (or decimal when the integer
+	/// radix is not decimal) and rationals in floating point
+	pub fn new() -> Self {
+		AlternateForms {
+			integer: Self::default_integer_flags(),
+			rational: AlternateForm::Float.flag(),
+			complex: AlternateForm::Float.flag(),
+		}
+	}
+
+	pub fn none() -> Self {
+		AlternateForms {
+			integer: 0,
+			rational: 0,
+			complex: 0,
+		}
+	}
+
+	fn default_integer_flags() -> u8 {
+		AlternateForm::Hex.flag() | AlternateForm::Decimal.flag()
+	}
+
+	fn flags(&self, class: AlternateValueClass) -> u8 {
+		match class {
+			AlternateValueClass::Integer => self.integer,
+			AlternateValueClass::Rational => self.rational,
+			AlternateValueClass::Complex => self.complex,
+		}
+	}
+
+	fn flags_mut(&mut self, class: AlternateValueClass) -> &mut u8 {
+		match class {
+			AlternateValueClass::Integer => &mut self.integer,
+			AlternateValueClass::Rational => &mut self.rational,
+			AlternateValueClass::Complex => &mut self.complex,
+		}
+	}
+
+	pub fn is_enabled(&self, class: AlternateValueClass, form: AlternateForm) -> bool {
+		self.flags(class) & form.flag() != 0
+	}
+
+	pub fn any_enabled(&self, class: AlternateValueClass) -> bool {
+		self.flags(class) != 0
+	}
+
+	pub fn toggle(&mut self, class: AlternateValueClass, form: AlternateForm) {
+		if class.supports(form) {
+			*self.flags_mut(class) ^= form.flag();
+		}
+	}
+
+	/// Enabled forms for the class of value, in order of preference
+	pub fn enabled_forms(
+		&self,
+		class: AlternateValueClass,
+	) -> impl Iterator<Item = AlternateForm> + '_ {
+		AlternateForm::ALL
+			.iter()
+			.copied()
+			.filter(move |form| self.is_enabled(class, *form))
+	}
+
+	/// Turns all integer forms off, or restores the default integer forms if
+	/// none are enabled
+	pub fn toggle_integer_forms(&mut self) {
+		self.integer = if self.integer != 0 {
+			0
+		} else {
+			Self::default_integer_flags()
+		};
+	}
+
+	/// Turns the floating point form of rationals and complex numbers on or off
+	pub fn toggle_float_forms(&mut self) {
+		let enable = !self.is_enabled(AlternateValueClass::Rational, AlternateForm::Float);
+		for class in &[AlternateValueClass::Rational, AlternateValueClass::Complex] {
+			if self.is_enabled(*class, AlternateForm::Float) != enable {
+				self.toggle(*class, AlternateForm::Float);
+			}
+		}
+	}
+}
+
+impl Default for AlternateForms {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[derive(PartialEq, Clone)]
 pub struct Format {
 	pub mode: FormatMode,
 	pub integer_mode: IntegerMode,
 	pub decimal_point: DecimalPointMode,
 	pub thousands: bool,
+	pub digit_separator: DigitSeparator,
+	pub si_prefix: bool,
+	pub display_digits: Option<usize>,
+	pub overflow: OverflowMode,
 	pub precision: usize,
 	pub trailing_zeros: bool,
 	pub integer_radix: u8,
-	pub show_alt_hex: bool,
-	pub show_alt_float: bool,
+	pub alt_forms: AlternateForms,
 	pub alt_mode: AlternateFormatMode,
 	pub limit_size: bool,
 	pub time_24_hour: bool,
 	pub stack_xyz: bool,
+	/// Marks values that depend on a mode that is not the default, such as
+	/// integers shown in hex or trigonometric results computed in radians
+	pub mode_tags: bool,
+	/// Shows decimals that are square roots of integers in simplified radical
+	/// form, such as 2√2, as an alternate form
+	pub radicals: bool,
+	/// Rounding of displayed digits, which is also used for the decimal
+	/// arithmetic of operations run by a context with this format
+	pub rounding: RoundingMode,
+	pub fraction_display: FractionDisplay,
 }
 
 pub enum FormatResult {
@@ -84,16 +398,153 @@ impl Format {
 			integer_mode: IntegerMode::Float,
 			decimal_point: DecimalPointMode::Period,
 			thousands: true,
+			digit_separator: DigitSeparator::Comma,
+			si_prefix: false,
+			display_digits: None,
+			overflow: OverflowMode::Wrap,
 			precision: 12,
 			trailing_zeros: false,
 			integer_radix: 10,
-			show_alt_hex: true,
-			show_alt_float: true,
+			alt_forms: AlternateForms::new(),
 			alt_mode: AlternateFormatMode::Smart,
 			limit_size: true,
 			time_24_hour: false,
 			stack_xyz: false,
+			mode_tags: false,
+			radicals: false,
+			rounding: RoundingMode::NearestEven,
+			fraction_display: FractionDisplay::Auto,
+		}
+	}
+
+	/// Writes the user selectable settings into saved state. New settings must
+	/// be added to the end so that older versions can still read the rest.
+	pub fn save<Out: SerializeOutput>(&self, output: &mut Out) -> Result<()> {
+		output.write_u8(match self.mode {
+			FormatMode::Normal => 0,
+			FormatMode::Rational => 1,
+			FormatMode::Fixed => 2,
+			FormatMode::Scientific => 3,
+			FormatMode::Engineering => 4,
+			FormatMode::Significant => 5,
+		})?;
+		self.integer_mode.save(output)?;
+		output.write_u8(match self.decimal_point {
+			DecimalPointMode::Period => 0,
+			DecimalPointMode::Comma => 1,
+		})?;
+		output.write_u8(self.thousands as u8)?;
+		output.write_u8(match self.digit_separator {
+			DigitSeparator::Comma => 0,
+			DigitSeparator::ThinSpace => 1,
+			DigitSeparator::Apostrophe => 2,
+			DigitSeparator::Underscore => 3,
+		})?;
+		output.write_u8(self.si_prefix as u8)?;
+		output.write_u8(match self.display_digits {
+			Some(digits) => digits as u8,
+			None => 0xff,
+		})?;
+		output.write_u8(match self.overflow {
+			OverflowMode::Wrap => 0,
+			OverflowMode::Ellipsis => 1,
+		})?;
+		output.write_u8(self.integer_radix)?;
+		output.write_u8(self.alt_forms.integer)?;
+		output.write_u8(self.alt_forms.rational)?;
+		output.write_u8(self.alt_forms.complex)?;
+		output.write_u8(match self.alt_mode {
+			AlternateFormatMode::Smart => 0,
+			AlternateFormatMode::Bottom => 1,
+			AlternateFormatMode::Left => 2,
+		})?;
+		output.write_u8(self.time_24_hour as u8)?;
+		output.write_u8(self.stack_xyz as u8)?;
+		output.write_u8(self.rounding as u8)?;
+		output.write_u8(match self.fraction_display {
+			FractionDisplay::Auto => 0,
+			FractionDisplay::Fraction => 1,
+			FractionDisplay::Decimal => 2,
+		})?;
+		output.write_u8(self.mode_tags as u8)?;
+		output.write_u8(self.radicals as u8)
+	}
+
+	/// Restores settings written by `save`. Settings with values that are not
+	/// known are left unchanged.
+	pub fn restore(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		match input.read_u8()? {
+			0 => self.mode = FormatMode::Normal,
+			1 => self.mode = FormatMode::Rational,
+			2 => self.mode = FormatMode::Fixed,
+			3 => self.mode = FormatMode::Scientific,
+			4 => self.mode = FormatMode::Engineering,
+			5 => self.mode = FormatMode::Significant,
+			_ => (),
+		}
+		if let Some(mode) = IntegerMode::restore(input)? {
+			self.integer_mode = mode;
+		}
+		match input.read_u8()? {
+			0 => self.decimal_point = DecimalPointMode::Period,
+			1 => self.decimal_point = DecimalPointMode::Comma,
+			_ => (),
+		}
+		self.thousands = input.read_u8()? != 0;
+		match input.read_u8()? {
+			0 => self.digit_separator = DigitSeparator::Comma,
+			1 => self.digit_separator = DigitSeparator::ThinSpace,
+			2 => self.digit_separator = DigitSeparator::Apostrophe,
+			3 => self.digit_separator = DigitSeparator::Underscore,
+			_ => (),
+		}
+		self.si_prefix = input.read_u8()? != 0;
+		match input.read_u8()? as usize {
+			0xff => self.display_digits = None,
+			digits if digits <= MAX_DISPLAY_DIGITS => self.display_digits = Some(digits),
+			_ => (),
+		}
+		match input.read_u8()? {
+			0 => self.overflow = OverflowMode::Wrap,
+			1 => self.overflow = OverflowMode::Ellipsis,
+			_ => (),
 		}
+		match input.read_u8()? {
+			radix @ 2 | radix @ 8 | radix @ 10 | radix @ 16 => self.integer_radix = radix,
+			_ => (),
+		}
+		self.alt_forms.integer = input.read_u8()?;
+		self.alt_forms.rational = input.read_u8()?;
+		self.alt_forms.complex = input.read_u8()?;
+		match input.read_u8()? {
+			0 => self.alt_mode = AlternateFormatMode::Smart,
+			1 => self.alt_mode = AlternateFormatMode::Bottom,
+			2 => self.alt_mode = AlternateFormatMode::Left,
+			_ => (),
+		}
+		self.time_24_hour = input.read_u8()? != 0;
+		self.stack_xyz = input.read_u8()? != 0;
+		// Older versions did not save the rounding mode
+		if input.remaining() > 0 {
+			if let Some(mode) = RoundingMode::from_u8(input.read_u8()?) {
+				self.rounding = mode;
+			}
+		}
+		if input.remaining() > 0 {
+			match input.read_u8()? {
+				0 => self.fraction_display = FractionDisplay::Auto,
+				1 => self.fraction_display = FractionDisplay::Fraction,
+				2 => self.fraction_display = FractionDisplay::Decimal,
+				_ => (),
+			}
+		}
+		if input.remaining() > 0 {
+			self.mode_tags = input.read_u8()? != 0;
+		}
+		if input.remaining() > 0 {
+			self.radicals = input.read_u8()? != 0;
+		}
+		Ok(())
 	}
 
 	pub fn exponent_format(&self) -> Self {
@@ -102,15 +553,22 @@ impl Format {
 			integer_mode: IntegerMode::BigInteger,
 			decimal_point: self.decimal_point,
 			thousands: false,
+			digit_separator: self.digit_separator,
+			si_prefix: false,
+			display_digits: None,
+			overflow: OverflowMode::Wrap,
 			precision: 4,
 			trailing_zeros: true,
 			integer_radix: 10,
-			show_alt_hex: false,
-			show_alt_float: false,
+			alt_forms: AlternateForms::none(),
 			alt_mode: AlternateFormatMode::Smart,
 			limit_size: true,
 			time_24_hour: false,
 			stack_xyz: false,
+			mode_tags: false,
+			radicals: false,
+			rounding: self.rounding,
+			fraction_display: self.fraction_display,
 		}
 	}
 
@@ -123,15 +581,22 @@ impl Format {
 			},
 			decimal_point: self.decimal_point,
 			thousands: self.thousands,
+			digit_separator: self.digit_separator,
+			si_prefix: self.si_prefix,
+			display_digits: self.display_digits,
+			overflow: self.overflow,
 			precision: self.precision,
 			trailing_zeros: self.trailing_zeros,
 			integer_radix: 16,
-			show_alt_hex: self.show_alt_hex,
-			show_alt_float: self.show_alt_float,
+			alt_forms: self.alt_forms,
 			alt_mode: self.alt_mode,
 			limit_size: self.limit_size,
 			time_24_hour: self.time_24_hour,
 			stack_xyz: self.stack_xyz,
+			mode_tags: self.mode_tags,
+			radicals: self.radicals,
+			rounding: self.rounding,
+			fraction_display: self.fraction_display,
 		}
 	}
 
@@ -141,15 +606,22 @@ impl Format {
 			integer_mode: self.integer_mode,
 			decimal_point: self.decimal_point,
 			thousands: self.thousands,
+			digit_separator: self.digit_separator,
+			si_prefix: self.si_prefix,
+			display_digits: self.display_digits,
+			overflow: self.overflow,
 			precision: self.precision,
 			trailing_zeros: self.trailing_zeros,
 			integer_radix: 10,
-			show_alt_hex: self.show_alt_hex,
-			show_alt_float: self.show_alt_float,
+			alt_forms: self.alt_forms,
 			alt_mode: self.alt_mode,
 			limit_size: self.limit_size,
 			time_24_hour: self.time_24_hour,
 			stack_xyz: self.stack_xyz,
+			mode_tags: self.mode_tags,
+			radicals: self.radicals,
+			rounding: self.rounding,
+			fraction_display: self.fraction_display,
 		}
 	}
 
@@ -159,15 +631,22 @@ impl Format {
 			integer_mode: self.integer_mode,
 			decimal_point: self.decimal_point,
 			thousands: self.thousands,
+			digit_separator: self.digit_separator,
+			si_prefix: self.si_prefix,
+			display_digits: self.display_digits,
+			overflow: self.overflow,
 			precision: core::cmp::min(self.precision, max_precision),
 			trailing_zeros: self.trailing_zeros,
 			integer_radix: self.integer_radix,
-			show_alt_hex: self.show_alt_hex,
-			show_alt_float: self.show_alt_float,
+			alt_forms: self.alt_forms,
 			alt_mode: self.alt_mode,
 			limit_size: self.limit_size,
 			time_24_hour: self.time_24_hour,
 			stack_xyz: self.stack_xyz,
+			mode_tags: self.mode_tags,
+			radicals: self.radicals,
+			rounding: self.rounding,
+			fraction_display: self.fraction_display,
 		}
 	}
 
@@ -178,16 +657,19 @@ impl Format {
 					if self.limit_size && int.bits() > MAX_SHORT_DISPLAY_BITS {
 						FormatResult::Float(self.format_decimal(&num.to_decimal()))
 					} else {
-						FormatResult::Integer(self.format_bigint(int))
+						FormatResult::Integer(self.format_bigint(&int.as_bigint()))
 					}
 				}
-				FormatMode::Scientific | FormatMode::Engineering => {
+				FormatMode::Fixed
+				| FormatMode::Scientific
+				| FormatMode::Engineering
+				| FormatMode::Significant => {
 					if self.integer_radix == 10
 						|| (self.limit_size && int.bits() > MAX_SHORT_DISPLAY_BITS)
 					{
 						FormatResult::Float(self.format_decimal(&num.to_decimal()))
 					} else {
-						FormatResult::Integer(self.format_bigint(int))
+						FormatResult::Integer(self.format_bigint(&int.as_bigint()))
 					}
 				}
 			},
@@ -196,6 +678,78 @@ impl Format {
 		}
 	}
 
+	/// Expands the rational `num / denom` into decimal digits. If the expansion
+	/// repeats within the display precision, returns the digits before the
+	/// repeating part (including the sign, integer part and decimal point) and
+	/// the repeating digits. Returns `None` for terminating expansions and for
+	/// expansions that take too many digits to start repeating.
+	pub fn format_repeating_decimal(
+		&self,
+		num: &BigInt,
+		denom: &BigUint,
+	) -> Option<(String, String)> {
+		if denom == &0.to_biguint().unwrap() {
+			return None;
+		}
+
+		let magnitude = num.magnitude();
+		let mut remainder = magnitude % denom;
+		let int_part = BigInt::from_biguint(Sign::Plus, magnitude / denom);
+
+		// Perform long division, remembering each remainder so that the start
+		// of the repeating part can be found when a remainder is seen again
+		let ten = 10.to_biguint().unwrap();
+		let mut remainders = Vec::new();
+		let mut digits = String::new();
+		for _ in 0..=self.precision {
+			if remainder == 0.to_biguint().unwrap() {
+				return None;
+			}
+			if let Some(start) = remainders.iter().position(|prev| prev == &remainder) {
+				let mut prefix = String::new();
+				if num.sign() == Sign::Minus {
+					prefix.push('-');
+				}
+				prefix += &self.decimal_format().format_bigint(&int_part);
+				prefix += match self.decimal_point {
+					DecimalPointMode::Period => ".",
+					DecimalPointMode::Comma => ",",
+				};
+				prefix += &digits[..start];
+				return Some((prefix, digits[start..].to_string()));
+			}
+			remainders.push(remainder.clone());
+			remainder *= &ten;
+			let digit: u8 = (&remainder / denom).try_into().ok()?;
+			digits.push(core::char::from_u32('0' as u32 + digit as u32)?);
+			remainder %= denom;
+		}
+		None
+	}
+
+	/// Appends a count or size in decimal to `out`, with the digits grouped in the
+	/// same way as `format_bigint`. Nothing is allocated other than to grow `out`,
+	/// so this is used for text that is drawn on every frame.
+	pub fn write_count(&self, out: &mut String, value: u64) {
+		let mut digits = [0u8; 20];
+		let mut len = 0;
+		let mut val = value;
+		loop {
+			digits[len] = b'0' + (val % 10) as u8;
+			len += 1;
+			val /= 10;
+			if val == 0 {
+				break;
+			}
+		}
+		for idx in (0..len).rev() {
+			out.push(digits[idx] as char);
+			if idx > 0 && idx % 3 == 0 && self.thousands {
+				out.push(self.digit_separator.decimal_char(self.decimal_point));
+			}
+		}
+	}
+
 	pub fn format_bigint(&self, int: &BigInt) -> String {
 		assert!(self.integer_radix > 1 && self.integer_radix <= 36);
 
@@ -214,12 +768,13 @@ impl Format {
 		while val != 0.to_biguint().unwrap() {
 			// Check for thousands separator
 			if digits % 3 == 0 && digits > 0 && self.integer_radix == 10 && self.thousands {
-				match self.decimal_point {
-					DecimalPointMode::Period => result.push(','),
-					DecimalPointMode::Comma => result.push('.'),
-				}
-			} else if digits % 4 == 0 && digits > 0 && self.integer_radix == 16 && self.thousands {
-				result.push('\'');
+				result.push(self.digit_separator.decimal_char(self.decimal_point));
+			} else if digits % 4 == 0
+				&& digits > 0
+				&& (self.integer_radix == 16 || self.integer_radix == 2)
+				&& self.thousands
+			{
+				result.push(self.digit_separator.nibble_char());
 			}
 
 			// Get the lowest digit for the current radix and push it
@@ -250,6 +805,10 @@ impl Format {
 		if self.integer_radix == 8 && result.len() > 1 {
 			result.push('0');
 		}
+		if self.integer_radix == 2 && result.len() > 1 {
+			result.push('b');
+			result.push('0');
+		}
 
 		// Add in sign
 		if int.sign() == Sign::Minus {
@@ -261,7 +820,12 @@ impl Format {
 		result.iter().collect()
 	}
 
-	fn format_decimal_post_round(&self, num: &Decimal, mode: FormatMode) -> String {
+	fn format_decimal_post_round(
+		&self,
+		num: &Decimal,
+		mut mode: FormatMode,
+		mut digits: Option<usize>,
+	) -> String {
 		let raw_str = num.to_string();
 
 		// Split string on the 'E' to decode parts of number. For non inf/NaN there
@@ -288,6 +852,15 @@ impl Format {
 			exponent = 0;
 		}
 
+		// Rounding to significant figures can carry into a new integer digit, which
+		// leaves more integer digits than significant figures
+		if let (FormatMode::Significant, Some(figures)) = (mode, digits) {
+			if digit_str.len() as isize + exponent > figures as isize {
+				mode = FormatMode::Scientific;
+				digits = Some(figures - 1);
+			}
+		}
+
 		let mut display_exponent = match mode {
 			FormatMode::Scientific => {
 				let new_exponent = 1 - digit_str.len() as isize;
@@ -348,23 +921,20 @@ impl Format {
 			let digit_bytes = digit_str.as_bytes();
 			for i in 0..integer_part_digits {
 				if digits > 0 && digits % 3 == 0 && self.thousands {
-					match self.decimal_point {
-						DecimalPointMode::Period => integer_digits.push(',' as u32 as u8),
-						DecimalPointMode::Comma => integer_digits.push('.' as u32 as u8),
-					}
+					integer_digits.push(self.digit_separator.decimal_char(self.decimal_point));
 				}
 				if ((integer_part_digits as usize - 1) - i as usize) < digit_bytes.len() {
 					integer_digits
-						.push(digit_bytes[(integer_part_digits as usize - 1) - i as usize]);
+						.push(digit_bytes[(integer_part_digits as usize - 1) - i as usize] as char);
 				} else {
-					integer_digits.push('0' as u32 as u8);
+					integer_digits.push('0');
 				}
 				digits += 1;
 			}
 
 			// Construct the final string
 			integer_digits.reverse();
-			String::from_utf8(integer_digits).unwrap()
+			integer_digits.iter().collect()
 		} else {
 			// There is no integer portion, so it is zero
 			"0".to_string()
@@ -386,10 +956,34 @@ impl Format {
 			display_exponent = 0;
 		}
 
+		// When a digit count is selected, always show that many digits by padding the
+		// fraction with trailing zeros
+		let mut fraction_str = fraction_str;
+		if let Some(digits) = digits {
+			let fraction_len = match mode {
+				FormatMode::Engineering => {
+					(digits + 1).saturating_sub(core::cmp::max(integer_part_digits, 1) as usize)
+				}
+				FormatMode::Significant => (digits as isize - integer_part_digits).max(0) as usize,
+				_ => digits,
+			};
+			while fraction_str.len() < fraction_len {
+				fraction_str.push('0');
+			}
+		}
+
 		// Construct final string
 		let sign_str = if sign { "-" } else { "" };
 
-		let exponent_str = if display_exponent != 0 {
+		let si_prefix = if self.si_prefix && mode == FormatMode::Engineering {
+			si_prefix_for_exponent(display_exponent)
+		} else {
+			None
+		};
+
+		let exponent_str = if let Some(prefix) = si_prefix {
+			prefix.to_string()
+		} else if display_exponent != 0 {
 			"ᴇ".to_string()
 				+ &self
 					.exponent_format()
@@ -400,7 +994,7 @@ impl Format {
 			"".to_string()
 		};
 
-		if fraction_digits.len() > 0 {
+		if !fraction_str.is_empty() {
 			let decimal = match self.decimal_point {
 				DecimalPointMode::Period => ".",
 				DecimalPointMode::Comma => ",",
@@ -412,6 +1006,47 @@ impl Format {
 	}
 
 	pub fn format_decimal(&self, num: &Decimal) -> String {
+		self.format_decimal_with_exactness(num).0
+	}
+
+	/// Whether the decimal display of the rational `num / denom` is exactly
+	/// its value. This is only the case when the expansion terminates within
+	/// the precision of a decimal and the displayed digits are not rounded.
+	pub fn is_exact_decimal(&self, num: &BigInt, denom: &BigUint) -> bool {
+		let two = 2.to_biguint().unwrap();
+		let five = 5.to_biguint().unwrap();
+		let zero = 0.to_biguint().unwrap();
+		let mut rest = denom.clone();
+		let mut places = 0;
+		while &rest % &two == zero {
+			rest /= &two;
+			places += 1;
+		}
+		let mut fives = 0;
+		while &rest % &five == zero {
+			rest /= &five;
+			fives += 1;
+		}
+		if rest != 1.to_biguint().unwrap() {
+			return false;
+		}
+		places = core::cmp::max(places, fives);
+
+		// The digits of the expansion must fit in a decimal for the conversion
+		// to be exact
+		let digits = num.magnitude() * 10.to_biguint().unwrap().pow(places) / denom;
+		if digits.to_string().trim_end_matches('0').len() > MAX_DECIMAL_DIGITS {
+			return false;
+		}
+		self.format_decimal_with_exactness(
+			&Number::Rational(num.clone(), denom.clone()).to_decimal(),
+		)
+		.1
+	}
+
+	/// Formats a decimal, also returning whether the displayed digits are the
+	/// exact value instead of a rounded one
+	fn format_decimal_with_exactness(&self, num: &Decimal) -> (String, bool) {
 		let raw_str = num.to_string();
 
 		// Split string on the 'E' to decode parts of number. For non inf/NaN there
@@ -420,9 +1055,9 @@ impl Format {
 		if parts.len() == 1 {
 			// Not a normal number, detect infinity vs. NaN
 			if &parts[0][1..] == "Inf" {
-				return raw_str[0..1].to_string() + "∞";
+				return (raw_str[0..1].to_string() + "∞", true);
 			} else {
-				return "NaN".to_string();
+				return ("NaN".to_string(), true);
 			}
 		}
 
@@ -437,9 +1072,45 @@ impl Format {
 		// Check to see if the number is too large or too small to display as a normal
 		// decimal number (or if the mode is not decimal), and determine the display
 		// mode according to this and the formatter settings.
+		let digits = match self.mode {
+			FormatMode::Fixed
+			| FormatMode::Scientific
+			| FormatMode::Engineering
+			| FormatMode::Significant => self
+				.display_digits
+				.map(|digits| core::cmp::min(digits, MAX_DISPLAY_DIGITS)),
+			_ => None,
+		};
+
+		// In significant figures mode the digit count is the number of significant
+		// figures, with at least one shown. Without a digit count the mode is the same
+		// as the normal mode.
+		let significant_figures = match (self.mode, digits) {
+			(FormatMode::Significant, Some(digits)) => Some(core::cmp::max(digits, 1)),
+			_ => None,
+		};
+
 		let mut mode =
 			if self.mode == FormatMode::Scientific || self.mode == FormatMode::Engineering {
 				self.mode
+			} else if let Some(figures) = significant_figures {
+				// Values with more integer digits than significant figures, or with many
+				// leading zeros, are displayed in scientific notation
+				if integer_part_digits > figures as isize || integer_part_digits < -4 {
+					FormatMode::Scientific
+				} else {
+					FormatMode::Significant
+				}
+			} else if let (FormatMode::Fixed, Some(digits)) = (self.mode, digits) {
+				// Values that are too large, or too small to show any digits in the fixed
+				// number of decimal places, are displayed in scientific notation
+				if integer_part_digits > self.precision as isize
+					|| integer_part_digits + (digits as isize) <= 0
+				{
+					FormatMode::Scientific
+				} else {
+					FormatMode::Fixed
+				}
 			} else if integer_part_digits > self.precision as isize
 				|| integer_part_digits < -4
 				|| integer_part_digits < -(self.precision as isize / 2)
@@ -449,11 +1120,28 @@ impl Format {
 				FormatMode::Normal
 			};
 
+		// Determine the number of significant digits to display. In fixed mode, this
+		// is the number of digits up to the selected decimal place.
+		let precision = match (mode, digits, significant_figures) {
+			(_, _, Some(figures)) => figures as isize,
+			(FormatMode::Fixed, Some(digits), _) => integer_part_digits + digits as isize,
+			(_, Some(digits), _) => digits as isize + 1,
+			_ => self.precision as isize,
+		};
+		let precision = core::cmp::min(precision, MAX_SIGNIFICANT_DIGITS as isize);
+
+		// Digits after the decimal point of the displayed form, or the number of
+		// significant figures when displayed in significant figures mode
+		let digits = match (mode, significant_figures) {
+			(FormatMode::Scientific, Some(figures)) => Some(figures - 1),
+			(_, Some(figures)) => Some(figures),
+			_ => digits,
+		};
+
 		// Check for rounding
-		if digit_str.len() > self.precision {
+		if digit_str.len() as isize > precision {
 			// More digits than desired precision, round at desired precision.
-			let mut round_exponent =
-				(exponent + digit_str.len() as isize) - self.precision as isize;
+			let mut round_exponent = (exponent + digit_str.len() as isize) - precision;
 			if round_exponent > 0 && mode == FormatMode::Normal {
 				// If rounding was in the middle of the integer portion, always display using
 				// scientific notation, as we must not display digits after the rounding point.
@@ -468,20 +1156,75 @@ impl Format {
 			// Perform rounding at the desired digit
 			let round_exponent_dec: Decimal = (round_exponent as i32).into();
 			let factor = round_exponent_dec.exp10();
-			let one: Decimal = 1.into();
-			let two: Decimal = 2.into();
-			let adjust = one / two;
-			let mut rounded = ((&num.abs() / &factor) + adjust).trunc() * factor;
+			let mut rounded = round_integral(
+				&(&num.abs() / &factor),
+				num.is_sign_negative(),
+				self.rounding,
+			) * factor;
 
 			if num.is_sign_negative() {
 				rounded = -rounded;
 			}
 
-			self.format_decimal_post_round(&rounded, mode)
+			(
+				self.format_decimal_post_round(&rounded, mode, digits),
+				&rounded == num,
+			)
 		} else {
 			// Number of digits is under the desired precision, convert to string directly
-			self.format_decimal_post_round(num, mode)
+			(self.format_decimal_post_round(num, mode, digits), true)
+		}
+	}
+}
+
+/// Rounds a positive value to an integer with the given rounding mode. The value
+/// is the magnitude of a number, so `negative` gives the direction of the modes
+/// that round up or down.
+fn round_integral(value: &Decimal, negative: bool, mode: RoundingMode) -> Decimal {
+	let zero = Decimal::zero();
+	let one: Decimal = 1.into();
+	let two: Decimal = 2.into();
+	let truncated = value.trunc();
+	let remainder = value - &truncated;
+	let half = &one / &two;
+	let increment = match mode {
+		RoundingMode::NearestAway => remainder >= half,
+		RoundingMode::NearestEven => {
+			remainder > half || (remainder == half && (&truncated / &two).fract() != zero)
 		}
+		RoundingMode::TowardZero => false,
+		RoundingMode::Up => !negative && remainder != zero,
+		RoundingMode::Down => negative && remainder != zero,
+	};
+	if increment {
+		truncated + one
+	} else {
+		truncated
+	}
+}
+
+/// SI prefix letter for an engineering notation exponent, if there is one. An
+/// exponent of zero has an empty prefix.
+fn si_prefix_for_exponent(exponent: isize) -> Option<&'static str> {
+	match exponent {
+		-24 => Some("y"),
+		-21 => Some("z"),
+		-18 => Some("a"),
+		-15 => Some("f"),
+		-12 => Some("p"),
+		-9 => Some("n"),
+		-6 => Some("μ"),
+		-3 => Some("m"),
+		0 => Some(""),
+		3 => Some("k"),
+		6 => Some("M"),
+		9 => Some("G"),
+		12 => Some("T"),
+		15 => Some("P"),
+		18 => Some("E"),
+		21 => Some("Z"),
+		24 => Some("Y"),
+		_ => None,
 	}
 }
 
@@ -1,19 +1,35 @@
+use crate::checksum::{ByteOrder, ChecksumKind};
 use crate::constant::Constant;
 use crate::context::Context;
+use crate::decibel::DecibelScale;
 use crate::error::Result;
-use crate::format::{DecimalPointMode, FormatMode, IntegerMode};
+use crate::format::{
+	AlternateForm, AlternateValueClass, DecimalPointMode, FormatMode, IntegerMode,
+};
+use crate::modes::ModeScope;
+use crate::probability::{DistributionFunction, DistributionKind};
+use crate::storage::{DeserializeInput, OperationBudget, SerializeOutput};
 use crate::unit::AngleUnit;
 use crate::unit::Unit;
 
 #[cfg(not(feature = "std"))]
 use alloc::string::{String, ToString};
 
+// Digit count used when fixed mode is selected without a digit count
+const DEFAULT_FIXED_DIGITS: usize = 4;
+
+// Significant figures used when that mode is selected without a digit count
+const DEFAULT_SIGNIFICANT_FIGURES: usize = 4;
+
 #[derive(PartialEq, Eq, Clone)]
 pub enum StackFunction {
 	NormalFormat,
 	RationalFormat,
+	FixedFormat,
 	ScientificFormat,
 	EngineeringFormat,
+	SignificantFormat,
+	SIPrefix,
 	AlternateHex,
 	AlternateFloat,
 	ThousandsSeparatorOff,
@@ -40,10 +56,14 @@ pub enum StackFunction {
 	ShiftRight,
 	RotateLeft,
 	RotateRight,
+	TestCarry,
+	TestOutOfRange,
+	ClearFlags,
 	Hex,
 	Octal,
 	Decimal,
 	BaseToggle,
+	BaseCycle,
 	Constant(Constant),
 	Now,
 	Date,
@@ -77,6 +97,7 @@ pub enum StackFunction {
 	Atanh,
 	Sum,
 	Mean,
+	Distribution(DistributionKind, DistributionFunction),
 	DotProduct,
 	CrossProduct,
 	Magnitude,
@@ -86,6 +107,66 @@ pub enum StackFunction {
 	ColsToMatrix,
 	IdentityMatrix,
 	Transpose,
+	LinearSolve,
+	AngleBetween,
+	ToList,
+	ListGet,
+	ListAppend,
+	PrimeCount,
+	NthPrime,
+	ToText,
+	FromText,
+	Base64Encode,
+	Base64Decode,
+	HexEncode,
+	HexDecode,
+	ByteSwap16,
+	ByteSwap32,
+	ByteSwap64,
+	ByteReverse,
+	ExtractBits,
+	InsertBits,
+	BitMask,
+	PopulationCount,
+	LeadingZeros,
+	TrailingZeros,
+	BitLength,
+	AddressToInteger,
+	IntegerToIpv4,
+	IntegerToIpv6,
+	NetworkAddress,
+	BroadcastAddress,
+	Netmask,
+	HostCount,
+	Checksum(ChecksumKind, ByteOrder),
+	FromIso8601,
+	ToIso8601,
+	LevelAdd,
+	LevelSub,
+	LevelMul,
+	LevelDiv,
+	Histogram,
+	Quaternion,
+	Conjugate,
+	QuaternionToRotation,
+	RotationToQuaternion,
+	QuaternionToAxisAngle,
+	AxisAngleToQuaternion,
+	AddTax,
+	RemoveTax,
+	Rotate2D,
+	Rotate3D,
+	/// Tags x as an angle in the unit, converting it if it is already tagged
+	ToAngle(AngleUnit),
+	RatioToDb(DecibelScale),
+	DbToRatio(DecibelScale),
+	DbmToWatts,
+	WattsToDbm,
+	DbmToVolts,
+	VoltsToDbm,
+	SetReferenceImpedance,
+	Interpolate,
+	TableInterpolate,
 }
 
 impl StackFunction {
@@ -105,6 +186,13 @@ impl StackFunction {
 					"Frac".to_string()
 				}
 			}
+			StackFunction::FixedFormat => {
+				if context.format().mode == FormatMode::Fixed {
+					"▪Fix".to_string()
+				} else {
+					"Fix".to_string()
+				}
+			}
 			StackFunction::ScientificFormat => {
 				if context.format().mode == FormatMode::Scientific {
 					"▪Sci".to_string()
@@ -119,15 +207,37 @@ impl StackFunction {
 					"Eng".to_string()
 				}
 			}
+			StackFunction::SignificantFormat => {
+				if context.format().mode == FormatMode::Significant {
+					"▪Sig".to_string()
+				} else {
+					"Sig".to_string()
+				}
+			}
+			StackFunction::SIPrefix => {
+				if context.format().si_prefix {
+					"▪SI".to_string()
+				} else {
+					"SI".to_string()
+				}
+			}
 			StackFunction::AlternateHex => {
-				if context.format().show_alt_hex {
+				if context
+					.format()
+					.alt_forms
+					.any_enabled(AlternateValueClass::Integer)
+				{
 					"▪↓Hex".to_string()
 				} else {
 					"↓Hex".to_string()
 				}
 			}
 			StackFunction::AlternateFloat => {
-				if context.format().show_alt_float {
+				if context
+					.format()
+					.alt_forms
+					.is_enabled(AlternateValueClass::Rational, AlternateForm::Float)
+				{
 					"▪↓Flt".to_string()
 				} else {
 					"↓Flt".to_string()
@@ -253,6 +363,9 @@ impl StackFunction {
 			StackFunction::ShiftRight => ">>".to_string(),
 			StackFunction::RotateLeft => "rol".to_string(),
 			StackFunction::RotateRight => "ror".to_string(),
+			StackFunction::TestCarry => "C?".to_string(),
+			StackFunction::TestOutOfRange => "G?".to_string(),
+			StackFunction::ClearFlags => "clrCG".to_string(),
 			StackFunction::Hex => {
 				if context.format().integer_radix == 16 {
 					"▪Hex".to_string()
@@ -275,6 +388,12 @@ impl StackFunction {
 				}
 			}
 			StackFunction::BaseToggle => "Hex≷Dec".to_string(),
+			StackFunction::BaseCycle => match context.next_integer_radix() {
+				16 => "▸Hex".to_string(),
+				2 => "▸Bin".to_string(),
+				8 => "▸Oct".to_string(),
+				_ => "▸Dec".to_string(),
+			},
 			StackFunction::Constant(constant) => constant.to_str().to_string(),
 			StackFunction::Now => "Now".to_string(),
 			StackFunction::Date => "Date".to_string(),
@@ -326,6 +445,20 @@ impl StackFunction {
 			StackFunction::Atanh => "atanh".to_string(),
 			StackFunction::Sum => "sum".to_string(),
 			StackFunction::Mean => "mean".to_string(),
+			StackFunction::Distribution(kind, func) => {
+				let name = match kind {
+					DistributionKind::Normal => "n",
+					DistributionKind::StudentT => "t",
+					DistributionKind::ChiSquare => "χ²",
+					DistributionKind::F => "F",
+				};
+				name.to_string()
+					+ match func {
+						DistributionFunction::Pdf => "PDF",
+						DistributionFunction::Cdf => "CDF",
+						DistributionFunction::InverseCdf => "Inv",
+					}
+			}
 			StackFunction::DotProduct => "dot".to_string(),
 			StackFunction::CrossProduct => "cross".to_string(),
 			StackFunction::Magnitude => "mag".to_string(),
@@ -335,10 +468,288 @@ impl StackFunction {
 			StackFunction::ColsToMatrix => "C▸Mat".to_string(),
 			StackFunction::IdentityMatrix => "ident".to_string(),
 			StackFunction::Transpose => "transp".to_string(),
+			StackFunction::LinearSolve => "linsol".to_string(),
+			StackFunction::AngleBetween => "angle".to_string(),
+			StackFunction::ToList => "→list".to_string(),
+			StackFunction::ListGet => "get".to_string(),
+			StackFunction::ListAppend => "append".to_string(),
+			StackFunction::PrimeCount => "π(x)".to_string(),
+			StackFunction::NthPrime => "prime".to_string(),
+			StackFunction::ToText => "→str".to_string(),
+			StackFunction::FromText => "str→".to_string(),
+			StackFunction::Base64Encode => "→b64".to_string(),
+			StackFunction::Base64Decode => "b64→".to_string(),
+			StackFunction::HexEncode => "→hexstr".to_string(),
+			StackFunction::HexDecode => "hexstr→".to_string(),
+			StackFunction::ByteSwap16 => "bswap16".to_string(),
+			StackFunction::ByteSwap32 => "bswap32".to_string(),
+			StackFunction::ByteSwap64 => "bswap64".to_string(),
+			StackFunction::ByteReverse => "brev".to_string(),
+			StackFunction::ExtractBits => "extract".to_string(),
+			StackFunction::InsertBits => "insert".to_string(),
+			StackFunction::BitMask => "mask".to_string(),
+			StackFunction::PopulationCount => "popcnt".to_string(),
+			StackFunction::LeadingZeros => "clz".to_string(),
+			StackFunction::TrailingZeros => "ctz".to_string(),
+			StackFunction::BitLength => "bitlen".to_string(),
+			StackFunction::AddressToInteger => "ip→".to_string(),
+			StackFunction::IntegerToIpv4 => "→ipv4".to_string(),
+			StackFunction::IntegerToIpv6 => "→ipv6".to_string(),
+			StackFunction::NetworkAddress => "net".to_string(),
+			StackFunction::BroadcastAddress => "bcast".to_string(),
+			StackFunction::Netmask => "netmask".to_string(),
+			StackFunction::HostCount => "hosts".to_string(),
+			StackFunction::Checksum(kind, order) => {
+				let name = match kind {
+					ChecksumKind::Additive => "addsum",
+					ChecksumKind::Xor => "xorsum",
+					ChecksumKind::Internet => "inetsum",
+				};
+				name.to_string()
+					+ match order {
+						ByteOrder::BigEndian => "",
+						ByteOrder::LittleEndian => "LE",
+					}
+			}
+			StackFunction::FromIso8601 => "ISO→".to_string(),
+			StackFunction::ToIso8601 => "→ISO".to_string(),
+			StackFunction::LevelAdd => "lvl+".to_string(),
+			StackFunction::LevelSub => "lvl−".to_string(),
+			StackFunction::LevelMul => "lvl×".to_string(),
+			StackFunction::LevelDiv => "lvl÷".to_string(),
+			StackFunction::Histogram => "hist".to_string(),
+			StackFunction::Quaternion => "quat".to_string(),
+			StackFunction::Conjugate => "conj".to_string(),
+			StackFunction::QuaternionToRotation => "q→rot".to_string(),
+			StackFunction::RotationToQuaternion => "rot→q".to_string(),
+			StackFunction::QuaternionToAxisAngle => "q→axa".to_string(),
+			StackFunction::AxisAngleToQuaternion => "axa→q".to_string(),
+			StackFunction::AddTax => "+tax".to_string(),
+			StackFunction::RemoveTax => "−tax".to_string(),
+			StackFunction::Rotate2D => "rot2".to_string(),
+			StackFunction::Rotate3D => "rot3".to_string(),
+			StackFunction::ToAngle(unit) => "→".to_string() + unit.to_str(),
+			StackFunction::RatioToDb(DecibelScale::Power) => "P→dB".to_string(),
+			StackFunction::RatioToDb(DecibelScale::Field) => "V→dB".to_string(),
+			StackFunction::DbToRatio(DecibelScale::Power) => "dB→P".to_string(),
+			StackFunction::DbToRatio(DecibelScale::Field) => "dB→V".to_string(),
+			StackFunction::DbmToWatts => "dBm→W".to_string(),
+			StackFunction::WattsToDbm => "W→dBm".to_string(),
+			StackFunction::DbmToVolts => "dBm→V".to_string(),
+			StackFunction::VoltsToDbm => "V→dBm".to_string(),
+			StackFunction::SetReferenceImpedance => "Zref".to_string(),
+			StackFunction::Interpolate => "interp".to_string(),
+			StackFunction::TableInterpolate => "tblint".to_string(),
+		}
+	}
+
+	/// Short description of the function's arguments and its effect on the stack
+	pub fn help(&self) -> Option<&'static str> {
+		match self {
+			StackFunction::And => Some("2 args: y, x → y AND x"),
+			StackFunction::Or => Some("2 args: y, x → y OR x"),
+			StackFunction::Xor => Some("2 args: y, x → y XOR x"),
+			StackFunction::Not => Some("1 arg: x → NOT x"),
+			StackFunction::ShiftLeft => Some("2 args: y, x → y shifted left by x bits"),
+			StackFunction::ShiftRight => Some("2 args: y, x → y shifted right by x bits"),
+			StackFunction::RotateLeft => Some("2 args: y, x → y rotated left by x bits"),
+			StackFunction::RotateRight => Some("2 args: y, x → y rotated right by x bits"),
+			StackFunction::TestCarry => Some("0 args: pushes 1 if the carry flag is set, else 0"),
+			StackFunction::TestOutOfRange => {
+				Some("0 args: pushes 1 if the last result was out of range, else 0")
+			}
+			StackFunction::ClearFlags => Some("0 args: clears the carry and out of range flags"),
+			StackFunction::Constant(_) => Some("0 args: pushes the constant"),
+			StackFunction::Now => Some("0 args: pushes the current date and time"),
+			StackFunction::Date => Some("0 args: pushes the current date"),
+			StackFunction::Time => Some("0 args: pushes the current time"),
+			StackFunction::ClearUnits => Some("1 arg: x → x without units"),
+			StackFunction::AddUnit(_)
+			| StackFunction::AddUnitSquared(_)
+			| StackFunction::AddUnitCubed(_) => Some("1 arg: x → x multiplied by the unit"),
+			StackFunction::AddInvUnit(_)
+			| StackFunction::AddInvUnitSquared(_)
+			| StackFunction::AddInvUnitCubed(_) => Some("1 arg: x → x divided by the unit"),
+			StackFunction::ConvertToUnit(_) => Some("1 arg: x → x converted to the unit"),
+			StackFunction::Log => Some("1 arg: x → base 10 logarithm of x"),
+			StackFunction::Exp10 => Some("1 arg: x → 10 to the power of x"),
+			StackFunction::Ln => Some("1 arg: x → natural logarithm of x"),
+			StackFunction::Exp => Some("1 arg: x → e to the power of x"),
+			StackFunction::Sin => Some("1 arg: x → sine of x"),
+			StackFunction::Cos => Some("1 arg: x → cosine of x"),
+			StackFunction::Tan => Some("1 arg: x → tangent of x"),
+			StackFunction::Asin => Some("1 arg: x → inverse sine of x"),
+			StackFunction::Acos => Some("1 arg: x → inverse cosine of x"),
+			StackFunction::Atan => Some("1 arg: x → inverse tangent of x"),
+			StackFunction::Sinh => Some("1 arg: x → hyperbolic sine of x"),
+			StackFunction::Cosh => Some("1 arg: x → hyperbolic cosine of x"),
+			StackFunction::Tanh => Some("1 arg: x → hyperbolic tangent of x"),
+			StackFunction::Asinh => Some("1 arg: x → inverse hyperbolic sine of x"),
+			StackFunction::Acosh => Some("1 arg: x → inverse hyperbolic cosine of x"),
+			StackFunction::Atanh => Some("1 arg: x → inverse hyperbolic tangent of x"),
+			StackFunction::Sum => Some("1 arg: vector or list x → sum of elements"),
+			StackFunction::Mean => Some("1 arg: vector or list x → mean of elements"),
+			StackFunction::Distribution(kind, func) => Some(match (kind, func) {
+				(DistributionKind::Normal, DistributionFunction::Pdf) => {
+					"1 arg: x → standard normal density at x"
+				}
+				(DistributionKind::Normal, DistributionFunction::Cdf) => {
+					"1 arg: x → probability of a standard normal value ≤ x"
+				}
+				(DistributionKind::Normal, DistributionFunction::InverseCdf) => {
+					"1 arg: probability x → standard normal value with that lower tail"
+				}
+				(DistributionKind::StudentT, DistributionFunction::Pdf) => {
+					"2 args: degrees of freedom y, x → t density at x"
+				}
+				(DistributionKind::StudentT, DistributionFunction::Cdf) => {
+					"2 args: degrees of freedom y, x → probability of a t value ≤ x"
+				}
+				(DistributionKind::StudentT, DistributionFunction::InverseCdf) => {
+					"2 args: degrees of freedom y, probability x → t value with that lower tail"
+				}
+				(DistributionKind::ChiSquare, DistributionFunction::Pdf) => {
+					"2 args: degrees of freedom y, x → χ² density at x"
+				}
+				(DistributionKind::ChiSquare, DistributionFunction::Cdf) => {
+					"2 args: degrees of freedom y, x → probability of a χ² value ≤ x"
+				}
+				(DistributionKind::ChiSquare, DistributionFunction::InverseCdf) => {
+					"2 args: degrees of freedom y, probability x → χ² value with that lower tail"
+				}
+				(DistributionKind::F, DistributionFunction::Pdf) => {
+					"3 args: degrees of freedom z, y, x → F density at x"
+				}
+				(DistributionKind::F, DistributionFunction::Cdf) => {
+					"3 args: degrees of freedom z, y, x → probability of an F value ≤ x"
+				}
+				(DistributionKind::F, DistributionFunction::InverseCdf) => {
+					"3 args: degrees of freedom z, y, probability x → F value with that lower tail"
+				}
+			}),
+			StackFunction::DotProduct => Some("2 args: vectors y, x → y ∙ x"),
+			StackFunction::CrossProduct => Some("2 args: vectors y, x → y × x"),
+			StackFunction::AngleBetween => Some("2 args: vectors y, x → angle between y and x"),
+			StackFunction::Magnitude => Some("1 arg: vector x → length of x"),
+			StackFunction::Normalize => Some("1 arg: vector x → unit vector of x"),
+			StackFunction::ToMatrix => Some("Elements, rows, cols → matrix"),
+			StackFunction::RowsToMatrix => Some("Row vectors, count → matrix"),
+			StackFunction::ColsToMatrix => Some("Column vectors, count → matrix"),
+			StackFunction::IdentityMatrix => Some("1 arg: size x → identity matrix"),
+			StackFunction::ToList => Some("Elements, count → list"),
+			StackFunction::ListGet => Some("2 args: list y, position x → element of y"),
+			StackFunction::ListAppend => Some("2 args: list y, x → y with x appended"),
+			StackFunction::PrimeCount => Some("1 arg: x → number of primes up to x"),
+			StackFunction::NthPrime => Some("1 arg: n → nth prime"),
+			StackFunction::ToText => Some("1 arg: integer x → text of its bytes"),
+			StackFunction::FromText => Some("1 arg: text x → integer of its bytes"),
+			StackFunction::Base64Encode => Some("1 arg: text or integer x → Base64 text"),
+			StackFunction::Base64Decode => Some("1 arg: Base64 text x → decoded text"),
+			StackFunction::HexEncode => Some("1 arg: text or integer x → hex text"),
+			StackFunction::HexDecode => Some("1 arg: hex text x → decoded text"),
+			StackFunction::ByteSwap16 => Some("1 arg: x → low 2 bytes of x reversed"),
+			StackFunction::ByteSwap32 => Some("1 arg: x → low 4 bytes of x reversed"),
+			StackFunction::ByteSwap64 => Some("1 arg: x → low 8 bytes of x reversed"),
+			StackFunction::ByteReverse => Some("2 args: y, x → low x bytes of y reversed"),
+			StackFunction::ExtractBits => Some("3 args: z, msb y, lsb x → bits y down to x of z"),
+			StackFunction::InsertBits => {
+				Some("4 args: t, field z, msb y, lsb x → t with bits y down to x set to z")
+			}
+			StackFunction::BitMask => Some("1 arg: n → mask of the low n bits"),
+			StackFunction::PopulationCount => Some("1 arg: x → number of bits set in x"),
+			StackFunction::LeadingZeros => Some("1 arg: x → leading zero bits of x in the word"),
+			StackFunction::TrailingZeros => Some("1 arg: x → trailing zero bits of x"),
+			StackFunction::BitLength => Some("1 arg: x → bits needed to write |x|"),
+			StackFunction::AddressToInteger => {
+				Some("1 arg: address text x → integer of the address")
+			}
+			StackFunction::IntegerToIpv4 => Some("1 arg: x → IPv4 address text"),
+			StackFunction::IntegerToIpv6 => Some("1 arg: x → IPv6 address text"),
+			StackFunction::NetworkAddress => Some("2 args: address y, prefix x → network address"),
+			StackFunction::BroadcastAddress => {
+				Some("2 args: address y, prefix x → broadcast address")
+			}
+			StackFunction::Netmask => Some("1 arg: prefix x → IPv4 netmask"),
+			StackFunction::HostCount => Some("1 arg: prefix x → usable IPv4 hosts"),
+			StackFunction::Checksum(kind, _) => Some(match kind {
+				ChecksumKind::Additive => "2 args: data y, width x → sum of the words of y",
+				ChecksumKind::Xor => "2 args: data y, width x → xor of the words of y",
+				ChecksumKind::Internet => "1 arg: data x → RFC 1071 checksum of x",
+			}),
+			StackFunction::FromIso8601 => Some("1 arg: ISO 8601 text x → date or time"),
+			StackFunction::ToIso8601 => Some("1 arg: date or time x → ISO 8601 text"),
+			StackFunction::LevelAdd => Some("2 args: value y, level x → adds y into level x"),
+			StackFunction::LevelSub => Some("2 args: value y, level x → subtracts y from level x"),
+			StackFunction::LevelMul => Some("2 args: value y, level x → multiplies level x by y"),
+			StackFunction::LevelDiv => Some("2 args: value y, level x → divides level x by y"),
+			StackFunction::Histogram => {
+				Some("2 args: data y, bins x → count of values in each bin")
+			}
+			StackFunction::Quaternion => {
+				Some("4 args: parts w, x, y, z → w + xi + yj + zk, or splits a quaternion")
+			}
+			StackFunction::Conjugate => Some("1 arg: quaternion or complex x → conjugate of x"),
+			StackFunction::QuaternionToRotation => {
+				Some("1 arg: quaternion x → 3×3 rotation matrix of x")
+			}
+			StackFunction::RotationToQuaternion => {
+				Some("1 arg: 3×3 rotation matrix x → unit quaternion of x")
+			}
+			StackFunction::QuaternionToAxisAngle => {
+				Some("1 arg: quaternion x → axis vector y, angle x")
+			}
+			StackFunction::AxisAngleToQuaternion => {
+				Some("2 args: axis vector y, angle x → unit quaternion")
+			}
+			StackFunction::AddTax => Some("1 arg: amount x → x with tax at the stored rate"),
+			StackFunction::RemoveTax => Some("1 arg: amount x → x before tax at the stored rate"),
+			StackFunction::Rotate2D => Some("2 args: 2D point y, angle x → y rotated by x"),
+			StackFunction::Rotate3D => {
+				Some("3 args: 3D point z, axis vector y, angle x → z rotated about y by x")
+			}
+			StackFunction::ToAngle(AngleUnit::Degrees) => {
+				Some("1 arg: angle x → x in degrees, untagged x is in the angle mode")
+			}
+			StackFunction::ToAngle(AngleUnit::Radians) => {
+				Some("1 arg: angle x → x in radians, untagged x is in the angle mode")
+			}
+			StackFunction::ToAngle(AngleUnit::Gradians) => {
+				Some("1 arg: angle x → x in gradians, untagged x is in the angle mode")
+			}
+			StackFunction::RatioToDb(DecibelScale::Power) => Some("1 arg: power ratio x → x in dB"),
+			StackFunction::RatioToDb(DecibelScale::Field) => {
+				Some("1 arg: voltage ratio x → x in dB")
+			}
+			StackFunction::DbToRatio(DecibelScale::Power) => Some("1 arg: dB x → power ratio"),
+			StackFunction::DbToRatio(DecibelScale::Field) => Some("1 arg: dB x → voltage ratio"),
+			StackFunction::DbmToWatts => Some("1 arg: level x in dBm → power in W"),
+			StackFunction::WattsToDbm => Some("1 arg: power x in W → level in dBm"),
+			StackFunction::DbmToVolts => {
+				Some("1 arg: level x in dBm → RMS voltage across the reference impedance")
+			}
+			StackFunction::VoltsToDbm => {
+				Some("1 arg: RMS voltage x across the reference impedance → level in dBm")
+			}
+			StackFunction::SetReferenceImpedance => {
+				Some("1 arg: impedance x in Ω → reference impedance for dBm and voltage")
+			}
+			StackFunction::Interpolate => {
+				Some("5 args: x₁, y₁, x₂, y₂, x → y on the line through the two points")
+			}
+			StackFunction::TableInterpolate => {
+				Some("3 args: list of increasing x values, list of y values, x → y between them")
+			}
+			StackFunction::Transpose => Some("1 arg: matrix x → transpose of x"),
+			StackFunction::LinearSolve => {
+				Some("2 args: matrix y, vector x → solution v of y ∙ v = x")
+			}
+			_ => None,
 		}
 	}
 
 	pub fn execute(&self, context: &mut Context) -> Result<()> {
+		let _budget = OperationBudget::begin();
+		let _modes = ModeScope::begin(context);
 		match self {
 			StackFunction::NormalFormat => {
 				context.set_format_mode(FormatMode::Normal);
@@ -352,10 +763,28 @@ impl StackFunction {
 				context.set_format_mode(FormatMode::Scientific);
 				Ok(())
 			}
+			StackFunction::FixedFormat => {
+				context.set_format_mode(FormatMode::Fixed);
+				if context.format().display_digits.is_none() {
+					context.set_display_digits(Some(DEFAULT_FIXED_DIGITS));
+				}
+				Ok(())
+			}
 			StackFunction::EngineeringFormat => {
 				context.set_format_mode(FormatMode::Engineering);
 				Ok(())
 			}
+			StackFunction::SignificantFormat => {
+				context.set_format_mode(FormatMode::Significant);
+				if context.format().display_digits.is_none() {
+					context.set_display_digits(Some(DEFAULT_SIGNIFICANT_FIGURES));
+				}
+				Ok(())
+			}
+			StackFunction::SIPrefix => {
+				context.toggle_si_prefix();
+				Ok(())
+			}
 			StackFunction::AlternateHex => {
 				context.toggle_alt_hex();
 				Ok(())
@@ -433,6 +862,12 @@ impl StackFunction {
 			StackFunction::ShiftRight => context.shr(),
 			StackFunction::RotateLeft => context.rotate_left(),
 			StackFunction::RotateRight => context.rotate_right(),
+			StackFunction::TestCarry => context.test_carry(),
+			StackFunction::TestOutOfRange => context.test_out_of_range(),
+			StackFunction::ClearFlags => {
+				context.clear_integer_flags();
+				Ok(())
+			}
 			StackFunction::Hex => {
 				context.set_integer_radix(16);
 				Ok(())
@@ -449,6 +884,10 @@ impl StackFunction {
 				context.toggle_integer_radix();
 				Ok(())
 			}
+			StackFunction::BaseCycle => {
+				context.cycle_integer_radix();
+				Ok(())
+			}
 			StackFunction::Constant(constant) => context.push_constant(*constant),
 			StackFunction::Now => context.now(),
 			StackFunction::Date => context.date(),
@@ -491,6 +930,7 @@ impl StackFunction {
 			StackFunction::Atanh => context.atanh(),
 			StackFunction::Sum => context.sum(),
 			StackFunction::Mean => context.mean(),
+			StackFunction::Distribution(kind, func) => context.distribution(*kind, *func),
 			StackFunction::DotProduct => context.dot_product(),
 			StackFunction::CrossProduct => context.cross_product(),
 			StackFunction::Magnitude => context.magnitude(),
@@ -500,6 +940,393 @@ impl StackFunction {
 			StackFunction::ColsToMatrix => context.cols_to_matrix(),
 			StackFunction::IdentityMatrix => context.identity_matrix(),
 			StackFunction::Transpose => context.transpose(),
+			StackFunction::LinearSolve => context.solve_linear_system(),
+			StackFunction::AngleBetween => context.angle_between(),
+			StackFunction::ToList => context.to_list(),
+			StackFunction::ListGet => context.list_get(),
+			StackFunction::ListAppend => context.list_append(),
+			StackFunction::PrimeCount => context.prime_count(),
+			StackFunction::NthPrime => context.nth_prime(),
+			StackFunction::ToText => context.to_text(),
+			StackFunction::FromText => context.from_text(),
+			StackFunction::Base64Encode => context.base64_encode(),
+			StackFunction::Base64Decode => context.base64_decode(),
+			StackFunction::HexEncode => context.hex_encode(),
+			StackFunction::HexDecode => context.hex_decode(),
+			StackFunction::ByteSwap16 => context.swap_bytes(2),
+			StackFunction::ByteSwap32 => context.swap_bytes(4),
+			StackFunction::ByteSwap64 => context.swap_bytes(8),
+			StackFunction::ByteReverse => context.reverse_bytes(),
+			StackFunction::ExtractBits => context.extract_bits(),
+			StackFunction::InsertBits => context.insert_bits(),
+			StackFunction::BitMask => context.bit_mask(),
+			StackFunction::PopulationCount => context.population_count(),
+			StackFunction::LeadingZeros => context.count_leading_zeros(),
+			StackFunction::TrailingZeros => context.count_trailing_zeros(),
+			StackFunction::BitLength => context.bit_length(),
+			StackFunction::AddressToInteger => context.address_to_integer(),
+			StackFunction::IntegerToIpv4 => context.integer_to_ipv4(),
+			StackFunction::IntegerToIpv6 => context.integer_to_ipv6(),
+			StackFunction::NetworkAddress => context.network_address(),
+			StackFunction::BroadcastAddress => context.broadcast_address(),
+			StackFunction::Netmask => context.ipv4_netmask(),
+			StackFunction::HostCount => context.ipv4_host_count(),
+			StackFunction::Checksum(kind, order) => context.checksum(*kind, *order),
+			StackFunction::FromIso8601 => context.from_iso8601(),
+			StackFunction::ToIso8601 => context.to_iso8601(),
+			StackFunction::LevelAdd => context.level_operation(|level, value| level + value),
+			StackFunction::LevelSub => context.level_operation(|level, value| level - value),
+			StackFunction::LevelMul => context.level_operation(|level, value| level * value),
+			StackFunction::LevelDiv => context.level_operation(|level, value| level / value),
+			StackFunction::Histogram => context.histogram(),
+			StackFunction::Quaternion => context.quaternion(),
+			StackFunction::Conjugate => context.conjugate(),
+			StackFunction::QuaternionToRotation => context.quaternion_to_rotation_matrix(),
+			StackFunction::RotationToQuaternion => context.rotation_matrix_to_quaternion(),
+			StackFunction::QuaternionToAxisAngle => context.quaternion_to_axis_angle(),
+			StackFunction::AxisAngleToQuaternion => context.axis_angle_to_quaternion(),
+			StackFunction::AddTax => context.add_tax(),
+			StackFunction::RemoveTax => context.remove_tax(),
+			StackFunction::Rotate2D => context.rotate_2d(),
+			StackFunction::Rotate3D => context.rotate_3d(),
+			StackFunction::ToAngle(unit) => context.to_angle(*unit),
+			StackFunction::RatioToDb(scale) => context.ratio_to_db(*scale),
+			StackFunction::DbToRatio(scale) => context.db_to_ratio(*scale),
+			StackFunction::DbmToWatts => context.dbm_to_watts(),
+			StackFunction::WattsToDbm => context.watts_to_dbm(),
+			StackFunction::DbmToVolts => context.dbm_to_volts(),
+			StackFunction::VoltsToDbm => context.volts_to_dbm(),
+			StackFunction::SetReferenceImpedance => context.set_reference_impedance(),
+			StackFunction::Interpolate => context.interpolate(),
+			StackFunction::TableInterpolate => context.table_interpolate(),
 		}
 	}
+	/// Code and payload identifying the function in saved state. Codes must never
+	/// be reused when functions are added or removed.
+	fn saved_code(&self) -> (u16, u16) {
+		match self {
+			StackFunction::NormalFormat => (0x0000, 0),
+			StackFunction::RationalFormat => (0x0001, 0),
+			StackFunction::FixedFormat => (0x0002, 0),
+			StackFunction::ScientificFormat => (0x0003, 0),
+			StackFunction::EngineeringFormat => (0x0004, 0),
+			StackFunction::SIPrefix => (0x0005, 0),
+			StackFunction::AlternateHex => (0x0006, 0),
+			StackFunction::AlternateFloat => (0x0007, 0),
+			StackFunction::ThousandsSeparatorOff => (0x0008, 0),
+			StackFunction::ThousandsSeparatorOn => (0x0009, 0),
+			StackFunction::DecimalPointPeriod => (0x000a, 0),
+			StackFunction::DecimalPointComma => (0x000b, 0),
+			StackFunction::Float => (0x000c, 0),
+			StackFunction::BigInteger => (0x000d, 0),
+			StackFunction::Signed8Bit => (0x000e, 0),
+			StackFunction::Signed16Bit => (0x000f, 0),
+			StackFunction::Signed32Bit => (0x0010, 0),
+			StackFunction::Signed64Bit => (0x0011, 0),
+			StackFunction::Signed128Bit => (0x0012, 0),
+			StackFunction::Unsigned8Bit => (0x0013, 0),
+			StackFunction::Unsigned16Bit => (0x0014, 0),
+			StackFunction::Unsigned32Bit => (0x0015, 0),
+			StackFunction::Unsigned64Bit => (0x0016, 0),
+			StackFunction::Unsigned128Bit => (0x0017, 0),
+			StackFunction::And => (0x0018, 0),
+			StackFunction::Or => (0x0019, 0),
+			StackFunction::Xor => (0x001a, 0),
+			StackFunction::Not => (0x001b, 0),
+			StackFunction::ShiftLeft => (0x001c, 0),
+			StackFunction::ShiftRight => (0x001d, 0),
+			StackFunction::RotateLeft => (0x001e, 0),
+			StackFunction::RotateRight => (0x001f, 0),
+			StackFunction::Hex => (0x0020, 0),
+			StackFunction::Octal => (0x0021, 0),
+			StackFunction::Decimal => (0x0022, 0),
+			StackFunction::BaseToggle => (0x0023, 0),
+			StackFunction::BaseCycle => (0x0024, 0),
+			StackFunction::Constant(constant) => (0x0025, constant.to_u16()),
+			StackFunction::Now => (0x0026, 0),
+			StackFunction::Date => (0x0027, 0),
+			StackFunction::Time => (0x0028, 0),
+			StackFunction::Degrees => (0x0029, 0),
+			StackFunction::Radians => (0x002a, 0),
+			StackFunction::Gradians => (0x002b, 0),
+			StackFunction::ClearUnits => (0x002c, 0),
+			StackFunction::AddUnit(unit) => (0x002d, unit.to_u16()),
+			StackFunction::AddUnitSquared(unit) => (0x002e, unit.to_u16()),
+			StackFunction::AddUnitCubed(unit) => (0x002f, unit.to_u16()),
+			StackFunction::AddInvUnit(unit) => (0x0030, unit.to_u16()),
+			StackFunction::AddInvUnitSquared(unit) => (0x0031, unit.to_u16()),
+			StackFunction::AddInvUnitCubed(unit) => (0x0032, unit.to_u16()),
+			StackFunction::ConvertToUnit(unit) => (0x0033, unit.to_u16()),
+			StackFunction::Log => (0x0034, 0),
+			StackFunction::Exp10 => (0x0035, 0),
+			StackFunction::Ln => (0x0036, 0),
+			StackFunction::Exp => (0x0037, 0),
+			StackFunction::Sin => (0x0038, 0),
+			StackFunction::Cos => (0x0039, 0),
+			StackFunction::Tan => (0x003a, 0),
+			StackFunction::Asin => (0x003b, 0),
+			StackFunction::Acos => (0x003c, 0),
+			StackFunction::Atan => (0x003d, 0),
+			StackFunction::Sinh => (0x003e, 0),
+			StackFunction::Cosh => (0x003f, 0),
+			StackFunction::Tanh => (0x0040, 0),
+			StackFunction::Asinh => (0x0041, 0),
+			StackFunction::Acosh => (0x0042, 0),
+			StackFunction::Atanh => (0x0043, 0),
+			StackFunction::Sum => (0x0044, 0),
+			StackFunction::Mean => (0x0045, 0),
+			StackFunction::DotProduct => (0x0046, 0),
+			StackFunction::CrossProduct => (0x0047, 0),
+			StackFunction::Magnitude => (0x0048, 0),
+			StackFunction::Normalize => (0x0049, 0),
+			StackFunction::ToMatrix => (0x004a, 0),
+			StackFunction::RowsToMatrix => (0x004b, 0),
+			StackFunction::ColsToMatrix => (0x004c, 0),
+			StackFunction::IdentityMatrix => (0x004d, 0),
+			StackFunction::Transpose => (0x004e, 0),
+			StackFunction::SignificantFormat => (0x004f, 0),
+			StackFunction::TestCarry => (0x0050, 0),
+			StackFunction::TestOutOfRange => (0x0051, 0),
+			StackFunction::ClearFlags => (0x0052, 0),
+			StackFunction::Distribution(kind, func) => {
+				(0x0053, ((kind.to_u8() as u16) << 8) | func.to_u8() as u16)
+			}
+			StackFunction::LinearSolve => (0x0054, 0),
+			StackFunction::AngleBetween => (0x0055, 0),
+			StackFunction::ToList => (0x0056, 0),
+			StackFunction::ListGet => (0x0057, 0),
+			StackFunction::ListAppend => (0x0058, 0),
+			StackFunction::PrimeCount => (0x0059, 0),
+			StackFunction::NthPrime => (0x005a, 0),
+			StackFunction::ToText => (0x005b, 0),
+			StackFunction::FromText => (0x005c, 0),
+			StackFunction::Base64Encode => (0x005d, 0),
+			StackFunction::Base64Decode => (0x005e, 0),
+			StackFunction::HexEncode => (0x005f, 0),
+			StackFunction::HexDecode => (0x0060, 0),
+			StackFunction::ByteSwap16 => (0x0061, 0),
+			StackFunction::ByteSwap32 => (0x0062, 0),
+			StackFunction::ByteSwap64 => (0x0063, 0),
+			StackFunction::ByteReverse => (0x0064, 0),
+			StackFunction::ExtractBits => (0x0065, 0),
+			StackFunction::InsertBits => (0x0066, 0),
+			StackFunction::BitMask => (0x0067, 0),
+			StackFunction::PopulationCount => (0x0068, 0),
+			StackFunction::LeadingZeros => (0x0069, 0),
+			StackFunction::TrailingZeros => (0x006a, 0),
+			StackFunction::BitLength => (0x006b, 0),
+			StackFunction::AddressToInteger => (0x006c, 0),
+			StackFunction::IntegerToIpv4 => (0x006d, 0),
+			StackFunction::IntegerToIpv6 => (0x006e, 0),
+			StackFunction::NetworkAddress => (0x006f, 0),
+			StackFunction::BroadcastAddress => (0x0070, 0),
+			StackFunction::Netmask => (0x0071, 0),
+			StackFunction::HostCount => (0x0072, 0),
+			StackFunction::Checksum(kind, order) => {
+				(0x0073, ((kind.to_u8() as u16) << 8) | order.to_u8() as u16)
+			}
+			StackFunction::FromIso8601 => (0x0074, 0),
+			StackFunction::ToIso8601 => (0x0075, 0),
+			StackFunction::LevelAdd => (0x0076, 0),
+			StackFunction::LevelSub => (0x0077, 0),
+			StackFunction::LevelMul => (0x0078, 0),
+			StackFunction::LevelDiv => (0x0079, 0),
+			StackFunction::Histogram => (0x007a, 0),
+			StackFunction::Quaternion => (0x007b, 0),
+			StackFunction::Conjugate => (0x007c, 0),
+			StackFunction::QuaternionToRotation => (0x007d, 0),
+			StackFunction::RotationToQuaternion => (0x007e, 0),
+			StackFunction::QuaternionToAxisAngle => (0x007f, 0),
+			StackFunction::AxisAngleToQuaternion => (0x0080, 0),
+			StackFunction::AddTax => (0x0081, 0),
+			StackFunction::RemoveTax => (0x0082, 0),
+			StackFunction::Rotate2D => (0x0083, 0),
+			StackFunction::Rotate3D => (0x0084, 0),
+			StackFunction::ToAngle(unit) => (0x0085, Unit::Angle(*unit).to_u16()),
+			StackFunction::RatioToDb(scale) => (0x0086, scale.to_u16()),
+			StackFunction::DbToRatio(scale) => (0x0087, scale.to_u16()),
+			StackFunction::DbmToWatts => (0x0088, 0),
+			StackFunction::WattsToDbm => (0x0089, 0),
+			StackFunction::DbmToVolts => (0x008a, 0),
+			StackFunction::VoltsToDbm => (0x008b, 0),
+			StackFunction::SetReferenceImpedance => (0x008c, 0),
+			StackFunction::Interpolate => (0x008d, 0),
+			StackFunction::TableInterpolate => (0x008e, 0),
+		}
+	}
+
+	/// Writes the function into saved state
+	pub fn save<Out: SerializeOutput>(&self, output: &mut Out) -> Result<()> {
+		let (code, payload) = self.saved_code();
+		output.write_u16(code)?;
+		output.write_u16(payload)
+	}
+
+	/// Reads a function written by `save`. Returns `None` for functions that are
+	/// not known to this version.
+	pub fn restore(input: &mut DeserializeInput) -> Result<Option<Self>> {
+		let code = input.read_u16()?;
+		let payload = input.read_u16()?;
+		Ok(match code {
+			0x0000 => Some(StackFunction::NormalFormat),
+			0x0001 => Some(StackFunction::RationalFormat),
+			0x0002 => Some(StackFunction::FixedFormat),
+			0x0003 => Some(StackFunction::ScientificFormat),
+			0x0004 => Some(StackFunction::EngineeringFormat),
+			0x0005 => Some(StackFunction::SIPrefix),
+			0x0006 => Some(StackFunction::AlternateHex),
+			0x0007 => Some(StackFunction::AlternateFloat),
+			0x0008 => Some(StackFunction::ThousandsSeparatorOff),
+			0x0009 => Some(StackFunction::ThousandsSeparatorOn),
+			0x000a => Some(StackFunction::DecimalPointPeriod),
+			0x000b => Some(StackFunction::DecimalPointComma),
+			0x000c => Some(StackFunction::Float),
+			0x000d => Some(StackFunction::BigInteger),
+			0x000e => Some(StackFunction::Signed8Bit),
+			0x000f => Some(StackFunction::Signed16Bit),
+			0x0010 => Some(StackFunction::Signed32Bit),
+			0x0011 => Some(StackFunction::Signed64Bit),
+			0x0012 => Some(StackFunction::Signed128Bit),
+			0x0013 => Some(StackFunction::Unsigned8Bit),
+			0x0014 => Some(StackFunction::Unsigned16Bit),
+			0x0015 => Some(StackFunction::Unsigned32Bit),
+			0x0016 => Some(StackFunction::Unsigned64Bit),
+			0x0017 => Some(StackFunction::Unsigned128Bit),
+			0x0018 => Some(StackFunction::And),
+			0x0019 => Some(StackFunction::Or),
+			0x001a => Some(StackFunction::Xor),
+			0x001b => Some(StackFunction::Not),
+			0x001c => Some(StackFunction::ShiftLeft),
+			0x001d => Some(StackFunction::ShiftRight),
+			0x001e => Some(StackFunction::RotateLeft),
+			0x001f => Some(StackFunction::RotateRight),
+			0x0020 => Some(StackFunction::Hex),
+			0x0021 => Some(StackFunction::Octal),
+			0x0022 => Some(StackFunction::Decimal),
+			0x0023 => Some(StackFunction::BaseToggle),
+			0x0024 => Some(StackFunction::BaseCycle),
+			0x0025 => Constant::from_u16(payload).map(StackFunction::Constant),
+			0x0026 => Some(StackFunction::Now),
+			0x0027 => Some(StackFunction::Date),
+			0x0028 => Some(StackFunction::Time),
+			0x0029 => Some(StackFunction::Degrees),
+			0x002a => Some(StackFunction::Radians),
+			0x002b => Some(StackFunction::Gradians),
+			0x002c => Some(StackFunction::ClearUnits),
+			0x002d => Unit::from_u16(payload).map(StackFunction::AddUnit),
+			0x002e => Unit::from_u16(payload).map(StackFunction::AddUnitSquared),
+			0x002f => Unit::from_u16(payload).map(StackFunction::AddUnitCubed),
+			0x0030 => Unit::from_u16(payload).map(StackFunction::AddInvUnit),
+			0x0031 => Unit::from_u16(payload).map(StackFunction::AddInvUnitSquared),
+			0x0032 => Unit::from_u16(payload).map(StackFunction::AddInvUnitCubed),
+			0x0033 => Unit::from_u16(payload).map(StackFunction::ConvertToUnit),
+			0x0034 => Some(StackFunction::Log),
+			0x0035 => Some(StackFunction::Exp10),
+			0x0036 => Some(StackFunction::Ln),
+			0x0037 => Some(StackFunction::Exp),
+			0x0038 => Some(StackFunction::Sin),
+			0x0039 => Some(StackFunction::Cos),
+			0x003a => Some(StackFunction::Tan),
+			0x003b => Some(StackFunction::Asin),
+			0x003c => Some(StackFunction::Acos),
+			0x003d => Some(StackFunction::Atan),
+			0x003e => Some(StackFunction::Sinh),
+			0x003f => Some(StackFunction::Cosh),
+			0x0040 => Some(StackFunction::Tanh),
+			0x0041 => Some(StackFunction::Asinh),
+			0x0042 => Some(StackFunction::Acosh),
+			0x0043 => Some(StackFunction::Atanh),
+			0x0044 => Some(StackFunction::Sum),
+			0x0045 => Some(StackFunction::Mean),
+			0x0046 => Some(StackFunction::DotProduct),
+			0x0047 => Some(StackFunction::CrossProduct),
+			0x0048 => Some(StackFunction::Magnitude),
+			0x0049 => Some(StackFunction::Normalize),
+			0x004a => Some(StackFunction::ToMatrix),
+			0x004b => Some(StackFunction::RowsToMatrix),
+			0x004c => Some(StackFunction::ColsToMatrix),
+			0x004d => Some(StackFunction::IdentityMatrix),
+			0x004e => Some(StackFunction::Transpose),
+			0x004f => Some(StackFunction::SignificantFormat),
+			0x0050 => Some(StackFunction::TestCarry),
+			0x0051 => Some(StackFunction::TestOutOfRange),
+			0x0052 => Some(StackFunction::ClearFlags),
+			0x0053 => match (
+				DistributionKind::from_u8((payload >> 8) as u8),
+				DistributionFunction::from_u8(payload as u8),
+			) {
+				(Some(kind), Some(func)) => Some(StackFunction::Distribution(kind, func)),
+				_ => None,
+			},
+			0x0054 => Some(StackFunction::LinearSolve),
+			0x0055 => Some(StackFunction::AngleBetween),
+			0x0056 => Some(StackFunction::ToList),
+			0x0057 => Some(StackFunction::ListGet),
+			0x0058 => Some(StackFunction::ListAppend),
+			0x0059 => Some(StackFunction::PrimeCount),
+			0x005a => Some(StackFunction::NthPrime),
+			0x005b => Some(StackFunction::ToText),
+			0x005c => Some(StackFunction::FromText),
+			0x005d => Some(StackFunction::Base64Encode),
+			0x005e => Some(StackFunction::Base64Decode),
+			0x005f => Some(StackFunction::HexEncode),
+			0x0060 => Some(StackFunction::HexDecode),
+			0x0061 => Some(StackFunction::ByteSwap16),
+			0x0062 => Some(StackFunction::ByteSwap32),
+			0x0063 => Some(StackFunction::ByteSwap64),
+			0x0064 => Some(StackFunction::ByteReverse),
+			0x0065 => Some(StackFunction::ExtractBits),
+			0x0066 => Some(StackFunction::InsertBits),
+			0x0067 => Some(StackFunction::BitMask),
+			0x0068 => Some(StackFunction::PopulationCount),
+			0x0069 => Some(StackFunction::LeadingZeros),
+			0x006a => Some(StackFunction::TrailingZeros),
+			0x006b => Some(StackFunction::BitLength),
+			0x006c => Some(StackFunction::AddressToInteger),
+			0x006d => Some(StackFunction::IntegerToIpv4),
+			0x006e => Some(StackFunction::IntegerToIpv6),
+			0x006f => Some(StackFunction::NetworkAddress),
+			0x0070 => Some(StackFunction::BroadcastAddress),
+			0x0071 => Some(StackFunction::Netmask),
+			0x0072 => Some(StackFunction::HostCount),
+			0x0073 => match (
+				ChecksumKind::from_u8((payload >> 8) as u8),
+				ByteOrder::from_u8(payload as u8),
+			) {
+				(Some(kind), Some(order)) => Some(StackFunction::Checksum(kind, order)),
+				_ => None,
+			},
+			0x0074 => Some(StackFunction::FromIso8601),
+			0x0075 => Some(StackFunction::ToIso8601),
+			0x0076 => Some(StackFunction::LevelAdd),
+			0x0077 => Some(StackFunction::LevelSub),
+			0x0078 => Some(StackFunction::LevelMul),
+			0x0079 => Some(StackFunction::LevelDiv),
+			0x007a => Some(StackFunction::Histogram),
+			0x007b => Some(StackFunction::Quaternion),
+			0x007c => Some(StackFunction::Conjugate),
+			0x007d => Some(StackFunction::QuaternionToRotation),
+			0x007e => Some(StackFunction::RotationToQuaternion),
+			0x007f => Some(StackFunction::QuaternionToAxisAngle),
+			0x0080 => Some(StackFunction::AxisAngleToQuaternion),
+			0x0081 => Some(StackFunction::AddTax),
+			0x0082 => Some(StackFunction::RemoveTax),
+			0x0083 => Some(StackFunction::Rotate2D),
+			0x0084 => Some(StackFunction::Rotate3D),
+			0x0085 => match Unit::from_u16(payload) {
+				Some(Unit::Angle(unit)) => Some(StackFunction::ToAngle(unit)),
+				_ => None,
+			},
+			0x0086 => DecibelScale::from_u16(payload).map(StackFunction::RatioToDb),
+			0x0087 => DecibelScale::from_u16(payload).map(StackFunction::DbToRatio),
+			0x0088 => Some(StackFunction::DbmToWatts),
+			0x0089 => Some(StackFunction::WattsToDbm),
+			0x008a => Some(StackFunction::DbmToVolts),
+			0x008b => Some(StackFunction::VoltsToDbm),
+			0x008c => Some(StackFunction::SetReferenceImpedance),
+			0x008d => Some(StackFunction::Interpolate),
+			0x008e => Some(StackFunction::TableInterpolate),
+			_ => None,
+		})
+	}
 }
@@ -0,0 +1,128 @@
+use crate::error::{Error, Result};
+use crate::number::Number;
+use crate::value::Value;
+use crate::vector::Vector;
+use core::convert::TryFrom;
+use intel_dfp::Decimal;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Largest number of bins in a histogram
+pub const MAX_BINS: usize = 100;
+
+/// Counts of the values of a data set that fall into each of a number of bins
+/// of the same width. The bins cover the range from the smallest to the largest
+/// value, and the largest value is counted in the last bin.
+pub struct Histogram {
+	min: Decimal,
+	max: Decimal,
+	counts: Vec<usize>,
+}
+
+impl Histogram {
+	pub fn new(values: &[Decimal], bins: usize) -> Result<Self> {
+		if bins == 0 || bins > MAX_BINS {
+			return Err(Error::InvalidArgument);
+		}
+		let mut range: Option<(Decimal, Decimal)> = None;
+		for value in values {
+			range = Some(match range {
+				Some((min, max)) => (
+					if value < &min { value.clone() } else { min },
+					if value > &max { value.clone() } else { max },
+				),
+				None => (value.clone(), value.clone()),
+			});
+		}
+		let (min, max) = match range {
+			Some((min, max)) if min == max => {
+				// All values are the same, center them in a bin of width one
+				let half = Decimal::from(1) / Decimal::from(2);
+				(&min - &half, &max + &half)
+			}
+			Some(range) => range,
+			None => return Err(Error::NotEnoughValues),
+		};
+
+		let mut histogram = Histogram {
+			min,
+			max,
+			counts: core::iter::repeat_n(0, bins).collect(),
+		};
+		for value in values {
+			let bin = histogram.bin_index(value);
+			histogram.counts[bin] += 1;
+		}
+		Ok(histogram)
+	}
+
+	/// Bin count given by Sturges' rule for a data set of `len` values
+	pub fn default_bins(len: usize) -> usize {
+		let mut bins = 1;
+		let mut remaining = len;
+		while remaining > 1 {
+			remaining = remaining.div_ceil(2);
+			bins += 1;
+		}
+		core::cmp::min(bins, MAX_BINS)
+	}
+
+	/// Real values of the elements of a vector or list, which are the data set
+	/// used by the statistics functions
+	pub fn data_values(value: &Value) -> Result<Vec<Decimal>> {
+		let vector: &Vector = match value {
+			Value::Vector(vector) => vector,
+			Value::List(list) => list.values(),
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		let mut values = Vec::new();
+		for idx in 0..vector.len() {
+			let value = vector.get(idx)?;
+			values.push(value.real_number()?.to_decimal().into_owned());
+		}
+		Ok(values)
+	}
+
+	fn bin_index(&self, value: &Decimal) -> usize {
+		let bins: Decimal = (self.counts.len() as u64).into();
+		let scaled = &(&(value - &self.min) * &bins) / &(&self.max - &self.min);
+		let bin = match Number::Decimal(scaled).to_int() {
+			Ok(int) => usize::try_from(&*int).unwrap_or(0),
+			Err(_) => 0,
+		};
+		core::cmp::min(bin, self.counts.len() - 1)
+	}
+
+	pub fn counts(&self) -> &[usize] {
+		&self.counts
+	}
+
+	pub fn bins(&self) -> usize {
+		self.counts.len()
+	}
+
+	pub fn min(&self) -> &Decimal {
+		&self.min
+	}
+
+	pub fn max(&self) -> &Decimal {
+		&self.max
+	}
+
+	/// Lower edge of a bin. The upper edge of the last bin is `max`.
+	pub fn bin_start(&self, bin: usize) -> Decimal {
+		let bins: Decimal = (self.counts.len() as u64).into();
+		let width = &(&self.max - &self.min) / &bins;
+		&self.min + &(&width * &(bin as u64).into())
+	}
+
+	/// Bin that `value` falls into, if it is within the range of the bins
+	pub fn bin_at(&self, value: &Decimal) -> Option<usize> {
+		if value < &self.min || value > &self.max {
+			None
+		} else {
+			Some(self.bin_index(value))
+		}
+	}
+}
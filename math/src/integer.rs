@@ -0,0 +1,231 @@
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use num_bigint::{BigInt, BigUint, Sign};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+#[cfg(not(feature = "std"))]
+use alloc::borrow::Cow;
+
+/// Value of an integer number. Most integers on the stack are small, so those
+/// that fit in 64 bits are kept inline and arithmetic on them does not need to
+/// allocate. Results that overflow are promoted to big integers, and big
+/// integers that become small enough are moved back inline, so each value has
+/// exactly one representation.
+#[derive(Clone, PartialEq, Eq)]
+pub enum IntegerValue {
+	Small(i64),
+	Big(BigInt),
+}
+
+impl IntegerValue {
+	pub fn zero() -> Self {
+		IntegerValue::Small(0)
+	}
+
+	pub fn as_bigint<'a>(&'a self) -> Cow<'a, BigInt> {
+		match self {
+			IntegerValue::Small(value) => Cow::Owned((*value).into()),
+			IntegerValue::Big(value) => Cow::Borrowed(value),
+		}
+	}
+
+	pub fn into_bigint(self) -> BigInt {
+		match self {
+			IntegerValue::Small(value) => value.into(),
+			IntegerValue::Big(value) => value,
+		}
+	}
+
+	pub fn to_i64(&self) -> Option<i64> {
+		match self {
+			IntegerValue::Small(value) => Some(*value),
+			IntegerValue::Big(_) => None,
+		}
+	}
+
+	/// Converts the magnitude to a primitive, if it fits
+	pub fn magnitude_to_u32(&self) -> Option<u32> {
+		match self {
+			IntegerValue::Small(value) => u32::try_from(value.unsigned_abs()).ok(),
+			IntegerValue::Big(value) => u32::try_from(value.magnitude()).ok(),
+		}
+	}
+
+	/// Number of bits needed for the magnitude, as given by `BigInt::bits`
+	pub fn bits(&self) -> u64 {
+		match self {
+			IntegerValue::Small(value) => 64 - value.unsigned_abs().leading_zeros() as u64,
+			IntegerValue::Big(value) => value.bits(),
+		}
+	}
+
+	pub fn sign(&self) -> Sign {
+		match self {
+			IntegerValue::Small(value) => match value.cmp(&0) {
+				Ordering::Less => Sign::Minus,
+				Ordering::Equal => Sign::NoSign,
+				Ordering::Greater => Sign::Plus,
+			},
+			IntegerValue::Big(value) => value.sign(),
+		}
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self == &IntegerValue::Small(0)
+	}
+
+	pub fn is_negative(&self) -> bool {
+		self.sign() == Sign::Minus
+	}
+
+	/// Divides by another integer if it divides evenly. Dividing by zero gives
+	/// `None`, like any other division that isn't exact.
+	pub fn exact_div(&self, rhs: &IntegerValue) -> Option<IntegerValue> {
+		match (self, rhs) {
+			(IntegerValue::Small(left), IntegerValue::Small(right)) => {
+				if *right != 0 && left.checked_rem(*right)? == 0 {
+					Some(IntegerValue::Small(left.checked_div(*right)?))
+				} else {
+					None
+				}
+			}
+			_ => None,
+		}
+	}
+}
+
+impl PartialOrd for IntegerValue {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for IntegerValue {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self, other) {
+			(IntegerValue::Small(left), IntegerValue::Small(right)) => left.cmp(right),
+			_ => self.as_bigint().cmp(&other.as_bigint()),
+		}
+	}
+}
+
+impl From<BigInt> for IntegerValue {
+	fn from(value: BigInt) -> Self {
+		match i64::try_from(&value) {
+			Ok(small) => IntegerValue::Small(small),
+			Err(_) => IntegerValue::Big(value),
+		}
+	}
+}
+
+impl From<BigUint> for IntegerValue {
+	fn from(value: BigUint) -> Self {
+		match i64::try_from(&value) {
+			Ok(small) => IntegerValue::Small(small),
+			Err(_) => IntegerValue::Big(value.into()),
+		}
+	}
+}
+
+macro_rules! small_from {
+	($($t:ty),*) => {
+		$(
+			impl From<$t> for IntegerValue {
+				fn from(value: $t) -> Self {
+					IntegerValue::Small(value.into())
+				}
+			}
+		)*
+	};
+}
+
+macro_rules! checked_from {
+	($($t:ty),*) => {
+		$(
+			impl From<$t> for IntegerValue {
+				fn from(value: $t) -> Self {
+					match i64::try_from(value) {
+						Ok(small) => IntegerValue::Small(small),
+						Err(_) => IntegerValue::Big(value.into()),
+					}
+				}
+			}
+		)*
+	};
+}
+
+small_from!(u8, i8, u16, i16, u32, i32, i64);
+checked_from!(u64, u128, i128, usize, isize);
+
+/// Implements an operator for every combination of owned and borrowed values.
+/// Small values use the checked operation on `i64`, and fall back to the big
+/// integer operation when it overflows.
+macro_rules! integer_op {
+	($op:ident, $method:ident, $checked:ident) => {
+		impl core::ops::$op<&IntegerValue> for &IntegerValue {
+			type Output = IntegerValue;
+
+			fn $method(self, rhs: &IntegerValue) -> IntegerValue {
+				if let (IntegerValue::Small(left), IntegerValue::Small(right)) = (self, rhs) {
+					if let Some(result) = left.$checked(*right) {
+						return IntegerValue::Small(result);
+					}
+				}
+				core::ops::$op::$method(&*self.as_bigint(), &*rhs.as_bigint()).into()
+			}
+		}
+
+		impl core::ops::$op<IntegerValue> for IntegerValue {
+			type Output = IntegerValue;
+
+			fn $method(self, rhs: IntegerValue) -> IntegerValue {
+				core::ops::$op::$method(&self, &rhs)
+			}
+		}
+
+		impl core::ops::$op<&IntegerValue> for IntegerValue {
+			type Output = IntegerValue;
+
+			fn $method(self, rhs: &IntegerValue) -> IntegerValue {
+				core::ops::$op::$method(&self, rhs)
+			}
+		}
+
+		impl core::ops::$op<IntegerValue> for &IntegerValue {
+			type Output = IntegerValue;
+
+			fn $method(self, rhs: IntegerValue) -> IntegerValue {
+				core::ops::$op::$method(self, &rhs)
+			}
+		}
+	};
+}
+
+integer_op!(Add, add, checked_add);
+integer_op!(Sub, sub, checked_sub);
+integer_op!(Mul, mul, checked_mul);
+integer_op!(Rem, rem, checked_rem);
+
+impl core::ops::Neg for &IntegerValue {
+	type Output = IntegerValue;
+
+	fn neg(self) -> IntegerValue {
+		match self {
+			IntegerValue::Small(value) => match value.checked_neg() {
+				Some(result) => IntegerValue::Small(result),
+				None => IntegerValue::from(-BigInt::from(*value)),
+			},
+			IntegerValue::Big(value) => IntegerValue::from(-value),
+		}
+	}
+}
+
+impl core::ops::Neg for IntegerValue {
+	type Output = IntegerValue;
+
+	fn neg(self) -> IntegerValue {
+		-&self
+	}
+}
@@ -0,0 +1,57 @@
+use crate::error::{Error, Result};
+use crate::list::List;
+use crate::value::Value;
+
+/// Value at `x` on the straight line through (x1, y1) and (x2, y2). Values of
+/// `x` outside of the two points are extrapolated along the same line.
+pub fn linear_interpolate(
+	x1: &Value,
+	y1: &Value,
+	x2: &Value,
+	y2: &Value,
+	x: &Value,
+) -> Result<Value> {
+	let run = (x2 - x1)?;
+	if run.real_number()?.is_zero() {
+		return Err(Error::DivideByZero);
+	}
+	let slope = ((y2 - y1)? / run)?;
+	y1 + &((x - x1)? * slope)?
+}
+
+/// Value at `x` of the piecewise straight line through the points of a table,
+/// given as a list of x values in increasing order and a list of the matching y
+/// values. Outside of the table the line through the nearest two points is
+/// extended.
+pub fn table_interpolate(xs: &List, ys: &List, x: &Value) -> Result<Value> {
+	if xs.len() != ys.len() {
+		return Err(Error::DimensionMismatch);
+	}
+	if xs.len() < 2 {
+		return Err(Error::NotEnoughValues);
+	}
+	for idx in 1..xs.len() {
+		if !is_less(&xs.get(idx - 1)?, &xs.get(idx)?)? {
+			return Err(Error::InvalidArgument);
+		}
+	}
+
+	let mut end = xs.len() - 1;
+	for idx in 1..xs.len() {
+		if !is_less(&xs.get(idx)?, x)? {
+			end = idx;
+			break;
+		}
+	}
+	linear_interpolate(
+		&xs.get(end - 1)?,
+		&ys.get(end - 1)?,
+		&xs.get(end)?,
+		&ys.get(end)?,
+		x,
+	)
+}
+
+fn is_less(a: &Value, b: &Value) -> Result<bool> {
+	Ok((a - b)?.real_number()?.is_negative())
+}
@@ -0,0 +1,182 @@
+use crate::error::{Error, Result};
+use crate::value::Value;
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+use core::fmt::Write;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+const NANOSECOND_DIGITS: usize = 9;
+
+/// Reads a date, a time of day, or a date and time in the extended or basic
+/// format of ISO 8601, such as `2024-02-29`, `13:45:30.25` or
+/// `20240229T134530`. A time zone designator is checked but not applied, as
+/// date and time values have no time zone.
+pub fn parse(text: &str) -> Result<Value> {
+	let text = text.trim();
+	// A time of day on its own has colons, or a `T` in front in the basic format
+	if let Some(time) = text.strip_prefix(|ch| ch == 'T' || ch == 't') {
+		return Ok(Value::Time(parse_time(time)?));
+	}
+	match text.find(['T', 't', ' ']) {
+		Some(idx) => {
+			let date = parse_date(&text[..idx])?;
+			let time = parse_time(&text[idx + 1..])?;
+			Ok(Value::DateTime(date.and_time(time)))
+		}
+		None if text.contains(':') => Ok(Value::Time(parse_time(text)?)),
+		None => Ok(Value::Date(parse_date(text)?)),
+	}
+}
+
+/// Writes a date or time value in the extended format of ISO 8601. Seconds
+/// only have a fraction if it is not zero.
+pub fn format(value: &Value) -> Result<String> {
+	let mut result = String::new();
+	match value {
+		Value::Date(date) => write_date(&mut result, date),
+		Value::Time(time) => write_time(&mut result, time),
+		Value::DateTime(date_time) => {
+			write_date(&mut result, &date_time.date());
+			result.push('T');
+			write_time(&mut result, &date_time.time());
+		}
+		_ => return Err(Error::DataTypeMismatch),
+	}
+	Ok(result)
+}
+
+/// Value of a run of decimal digits whose length is in the given range
+fn digits(text: &str, min: usize, max: usize) -> Option<u32> {
+	if text.len() < min || text.len() > max || !text.bytes().all(|ch| ch.is_ascii_digit()) {
+		return None;
+	}
+	text.parse().ok()
+}
+
+/// Reads `YYYY-MM-DD` or `YYYYMMDD`. Years beyond four digits must have a sign,
+/// as in the expanded format.
+fn parse_date(text: &str) -> Result<NaiveDate> {
+	let (negative, expanded, text) = match text.as_bytes().first() {
+		Some(b'+') => (false, true, &text[1..]),
+		Some(b'-') => (true, true, &text[1..]),
+		_ => (false, false, text),
+	};
+	let (year, month, day) = if text.contains('-') {
+		let mut parts = text.split('-');
+		match (parts.next(), parts.next(), parts.next(), parts.next()) {
+			(Some(year), Some(month), Some(day), None) => (year, month, day),
+			_ => return Err(Error::InvalidDate),
+		}
+	} else if text.len() >= 8 {
+		let len = text.len();
+		(&text[..len - 4], &text[len - 4..len - 2], &text[len - 2..])
+	} else {
+		return Err(Error::InvalidDate);
+	};
+
+	let max_year_digits = if expanded { 6 } else { 4 };
+	let year = digits(year, 4, max_year_digits).ok_or(Error::InvalidDate)? as i32;
+	let month = digits(month, 2, 2).ok_or(Error::InvalidDate)?;
+	let day = digits(day, 2, 2).ok_or(Error::InvalidDate)?;
+	let year = if negative { -year } else { year };
+	NaiveDate::from_ymd_opt(year, month, day).ok_or(Error::InvalidDate)
+}
+
+/// Reads `hh:mm[:ss[.fff]]` or `hhmm[ss[.fff]]`, followed by an optional time
+/// zone designator of `Z` or an offset from UTC
+fn parse_time(text: &str) -> Result<NaiveTime> {
+	let text = strip_time_zone(text)?;
+	let (text, fraction) = match text.find(['.', ',']) {
+		Some(idx) => (&text[..idx], Some(&text[idx + 1..])),
+		None => (text, None),
+	};
+
+	let parts: [Option<&str>; 3] = if text.contains(':') {
+		let mut parts = text.split(':');
+		let result = [parts.next(), parts.next(), parts.next()];
+		if parts.next().is_some() {
+			return Err(Error::InvalidTime);
+		}
+		result
+	} else {
+		match text.len() {
+			4 => [Some(&text[..2]), Some(&text[2..]), None],
+			6 => [Some(&text[..2]), Some(&text[2..4]), Some(&text[4..])],
+			_ => return Err(Error::InvalidTime),
+		}
+	};
+	let field = |part: Option<&str>| -> Result<u32> {
+		match part {
+			Some(part) => digits(part, 2, 2).ok_or(Error::InvalidTime),
+			None => Ok(0),
+		}
+	};
+	if parts[1].is_none() || (fraction.is_some() && parts[2].is_none()) {
+		return Err(Error::InvalidTime);
+	}
+
+	let nanoseconds = match fraction {
+		Some(fraction) => {
+			let value = digits(fraction, 1, NANOSECOND_DIGITS).ok_or(Error::InvalidTime)?;
+			value * 10u32.pow((NANOSECOND_DIGITS - fraction.len()) as u32)
+		}
+		None => 0,
+	};
+	NaiveTime::from_hms_nano_opt(
+		field(parts[0])?,
+		field(parts[1])?,
+		field(parts[2])?,
+		nanoseconds,
+	)
+	.ok_or(Error::InvalidTime)
+}
+
+/// Removes a time zone designator from the end of a time, checking that it is
+/// well formed
+fn strip_time_zone(text: &str) -> Result<&str> {
+	if let Some(text) = text.strip_suffix(|ch| ch == 'Z' || ch == 'z') {
+		return Ok(text);
+	}
+	let idx = match text.rfind(['+', '-']) {
+		Some(idx) => idx,
+		None => return Ok(text),
+	};
+	let offset = &text[idx + 1..];
+	let (hours, minutes) = match offset.len() {
+		2 => (offset, "00"),
+		4 => (&offset[..2], &offset[2..]),
+		5 if offset.as_bytes()[2] == b':' => (&offset[..2], &offset[3..]),
+		_ => return Err(Error::InvalidTime),
+	};
+	match (digits(hours, 2, 2), digits(minutes, 2, 2)) {
+		(Some(hours), Some(minutes)) if hours < 24 && minutes < 60 => Ok(&text[..idx]),
+		_ => Err(Error::InvalidTime),
+	}
+}
+
+fn write_date(result: &mut String, date: &NaiveDate) {
+	if (0..=9999).contains(&date.year()) {
+		let _ = write!(result, "{:04}", date.year());
+	} else {
+		let _ = write!(result, "{:+05}", date.year());
+	}
+	let _ = write!(result, "-{:02}-{:02}", date.month(), date.day());
+}
+
+fn write_time(result: &mut String, time: &NaiveTime) {
+	let _ = write!(
+		result,
+		"{:02}:{:02}:{:02}",
+		time.hour(),
+		time.minute(),
+		time.second()
+	);
+	let nanoseconds = time.nanosecond();
+	if nanoseconds != 0 {
+		let mut fraction = String::new();
+		let _ = write!(fraction, "{:09}", nanoseconds);
+		result.push('.');
+		result.push_str(fraction.trim_end_matches('0'));
+	}
+}
@@ -6,19 +6,40 @@ extern crate alloc;
 #[macro_use]
 extern crate lazy_static;
 
+pub mod business;
+pub mod checksum;
 pub mod complex;
 pub mod constant;
 pub mod context;
+pub mod decibel;
+pub mod equation;
 pub mod error;
 pub mod format;
 pub mod functions;
+pub mod histogram;
+pub mod integer;
+pub mod interpolate;
+pub mod iso8601;
+pub mod list;
 pub mod matrix;
+pub mod modes;
+pub mod network;
 pub mod number;
+pub mod persist;
+pub mod prime;
+pub mod probability;
+pub mod quaternion;
+pub mod script;
+pub mod solver;
 pub mod stack;
 pub mod storage;
+pub mod text;
 pub mod time;
 pub mod unit;
 pub mod value;
 pub mod vector;
 
 mod undo;
+
+#[cfg(feature = "serde")]
+mod serde_impl;
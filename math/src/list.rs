@@ -0,0 +1,73 @@
+use crate::error::{Error, Result};
+use crate::storage::{DeserializeInput, SerializeOutput, StorageObject, StorageRefSerializer};
+use crate::value::Value;
+use crate::vector::Vector;
+
+/// Ordered list of real numbers. A list keeps its elements in the same storage as
+/// a vector, but is not a vector: it has no vector arithmetic and is meant for
+/// applying a function to many numbers at once.
+#[derive(Clone)]
+pub struct List {
+	values: Vector,
+}
+
+impl List {
+	pub fn new() -> Result<Self> {
+		Ok(List {
+			values: Vector::new()?,
+		})
+	}
+
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.len() == 0
+	}
+
+	pub fn get(&self, idx: usize) -> Result<Value> {
+		self.values.get(idx)
+	}
+
+	/// Elements of the list as a vector, for operations that both share
+	pub fn values(&self) -> &Vector {
+		&self.values
+	}
+
+	pub fn push(&mut self, value: Value) -> Result<()> {
+		match value {
+			Value::Number(_) | Value::NumberWithUnit(_, _) => self.values.push(value),
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	pub fn extend_with(&mut self, other: &List) -> Result<()> {
+		self.values.extend_with(&other.values)
+	}
+
+	/// Deep copies all values in the list onto the non-reclaimable heap. This is used
+	/// when pulling values out of reclaimable memory.
+	pub fn deep_copy_values(&mut self) -> Result<()> {
+		self.values.deep_copy_values()
+	}
+}
+
+impl StorageObject for List {
+	fn serialize<Ref: StorageRefSerializer, Out: SerializeOutput>(
+		&self,
+		output: &mut Out,
+		storage_refs: &mut Ref,
+	) -> Result<()> {
+		self.values.serialize(output, storage_refs)
+	}
+
+	unsafe fn deserialize<T: StorageRefSerializer>(
+		input: &mut DeserializeInput,
+		storage_refs: &T,
+	) -> Result<Self> {
+		Ok(List {
+			values: Vector::deserialize(input, storage_refs)?,
+		})
+	}
+}
@@ -1,11 +1,23 @@
 use crate::error::{Error, Result};
+use crate::number::Number;
+use crate::script::computation_yield;
 use crate::storage::{
 	store, DeserializeInput, SerializeOutput, StorageObject, StorageRef, StorageRefArray,
 	StorageRefSerializer,
 };
 use crate::value::{Value, ValueRef};
+use intel_dfp::Decimal;
 
-const MAX_CAPACITY: usize = 1024;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Largest number of elements in a matrix
+pub const MAX_CAPACITY: usize = 1024;
+
+/// A decimal pivot is treated as zero when it shrinks to nothing next to the
+/// largest value of its column after being divided by this, which allows for
+/// the rounding of a few hundred operations
+const ROUNDING_PIVOT_SCALE: i32 = 1000;
 
 #[derive(Clone)]
 pub struct Matrix {
@@ -70,12 +82,113 @@ impl Matrix {
 		if row >= self.rows || col >= self.cols {
 			return Err(Error::IndexOutOfRange);
 		}
-		if value.is_vector_or_matrix() {
+		if value.is_collection() {
 			return Err(Error::DataTypeMismatch);
 		}
 		self.array.set((row * self.cols) + col, store(value)?)
 	}
 
+	/// Solves the linear system `self ∙ x = rhs` for x by Gaussian elimination.
+	/// Each column of `rhs` is solved separately. Exact values give an exact
+	/// solution, and decimals pick the largest pivot in each column to limit
+	/// rounding.
+	pub fn solve(&self, rhs: &Matrix) -> Result<Matrix> {
+		let size = self.rows;
+		if self.cols != size || rhs.rows != size {
+			return Err(Error::DimensionMismatch);
+		}
+
+		// Augmented matrix with the right hand side after the coefficients
+		let mut rows = Vec::new();
+		for row in 0..size {
+			let mut values = Vec::new();
+			for col in 0..size {
+				values.push(self.get(row, col)?);
+			}
+			for col in 0..rhs.cols {
+				values.push(rhs.get(row, col)?);
+			}
+			rows.push(values);
+		}
+
+		// Largest magnitude in each column of the coefficients
+		let mut scales = Vec::new();
+		for col in 0..size {
+			let mut scale = Decimal::zero();
+			for values in &rows {
+				let value_size = Self::magnitude(&values[col])?;
+				if value_size > scale {
+					scale = value_size;
+				}
+			}
+			scales.push(scale);
+		}
+
+		for col in 0..size {
+			computation_yield()?;
+
+			// Choose the pivot with the largest magnitude. A pivot that is zero,
+			// or a decimal pivot that is lost in rounding next to the original
+			// values of the column, means there is no single solution.
+			let mut pivot = col;
+			let mut pivot_size = Decimal::zero();
+			for (row, values) in rows.iter().enumerate().skip(col) {
+				let value_size = Self::magnitude(&values[col])?;
+				if value_size > pivot_size {
+					pivot = row;
+					pivot_size = value_size;
+				}
+			}
+			let rounded = match rows[pivot][col].real_number() {
+				Ok(Number::Decimal(_)) => {
+					&scales[col] + &(&pivot_size / &ROUNDING_PIVOT_SCALE.into()) == scales[col]
+				}
+				_ => false,
+			};
+			if pivot_size == Decimal::zero() || rounded {
+				return Err(Error::SingularMatrix);
+			}
+			rows.swap(col, pivot);
+
+			// Eliminate the column from the rows below
+			let (upper, lower) = rows.split_at_mut(col + 1);
+			let pivot_row = &upper[col];
+			for values in lower {
+				let factor = (&values[col] / &pivot_row[col])?;
+				for (value, pivot_value) in values.iter_mut().zip(pivot_row).skip(col) {
+					let product = (&factor * pivot_value)?;
+					*value = (&*value - &product)?;
+				}
+			}
+		}
+
+		// Substitute back from the last row
+		let mut result = Matrix::new(size, rhs.cols)?;
+		for rhs_col in 0..rhs.cols {
+			let mut solution: Vec<Value> = Vec::new();
+			for row in (0..size).rev() {
+				let mut value = rows[row][size + rhs_col].clone();
+				for (offset, known) in solution.iter().enumerate() {
+					let product = (&rows[row][size - 1 - offset] * known)?;
+					value = (&value - &product)?;
+				}
+				solution.push((&value / &rows[row][row])?.reduce());
+			}
+			for (offset, value) in solution.into_iter().enumerate() {
+				result.set(size - 1 - offset, rhs_col, value)?;
+			}
+		}
+		Ok(result)
+	}
+
+	fn magnitude(value: &Value) -> Result<Decimal> {
+		Ok(value
+			.complex_number()?
+			.magnitude()
+			.to_decimal()
+			.into_owned())
+	}
+
 	/// Deep copies all values in the matrix onto the non-reclaimable heap. This is used
 	/// when pulling values out of reclaimable memory.
 	pub fn deep_copy_values(&mut self) -> Result<()> {
@@ -0,0 +1,111 @@
+use crate::context::Context;
+use intel_dfp::{Decimal, RoundingMode};
+
+#[cfg(feature = "std")]
+use core::cell::Cell;
+#[cfg(not(feature = "std"))]
+use spin::Mutex;
+
+/// Modes of a context that change how the number and value types compute
+/// results. The types don't have access to the context, so the modes of the
+/// context running an operation are made active by a `ModeScope` for as long as
+/// the operation runs.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ArithmeticModes {
+	/// Rational results skip GCD reduction until they are displayed or grow too
+	/// large
+	pub deferred_reduction: bool,
+	/// Real functions outside of their real domain give complex results instead
+	/// of failing with an invalid argument error
+	pub complex_results: bool,
+	/// Rounding of decimal arithmetic. The binary64 backend always rounds to the
+	/// nearest even value, and only keeps the mode.
+	pub rounding: RoundingMode,
+}
+
+impl ArithmeticModes {
+	/// Modes used outside of any operation
+	pub const DEFAULT: ArithmeticModes = ArithmeticModes {
+		deferred_reduction: false,
+		complex_results: true,
+		rounding: RoundingMode::NearestEven,
+	};
+
+	/// Returns the modes of the operation that is running
+	pub fn active() -> Self {
+		#[cfg(feature = "std")]
+		return ACTIVE_MODES.with(|modes| modes.get());
+		#[cfg(not(feature = "std"))]
+		return *ACTIVE_MODES.lock();
+	}
+
+	fn set_active(modes: ArithmeticModes) {
+		Decimal::set_rounding_mode(modes.rounding);
+		#[cfg(feature = "std")]
+		ACTIVE_MODES.with(|active| active.set(modes));
+		#[cfg(not(feature = "std"))]
+		{
+			*ACTIVE_MODES.lock() = modes;
+		}
+	}
+}
+
+// Each thread runs its own operations, which keeps tests that use different
+// modes from seeing each other's modes
+#[cfg(feature = "std")]
+thread_local! {
+	static ACTIVE_MODES: Cell<ArithmeticModes> = const { Cell::new(ArithmeticModes::DEFAULT) };
+}
+
+#[cfg(not(feature = "std"))]
+static ACTIVE_MODES: Mutex<ArithmeticModes> = Mutex::new(ArithmeticModes::DEFAULT);
+
+/// Makes the modes of a context active while it is alive. The modes that were
+/// active before are restored when it is dropped, so operations started while
+/// another one is running use the modes of their own context.
+pub struct ModeScope {
+	previous: ArithmeticModes,
+}
+
+impl ModeScope {
+	pub fn begin(context: &Context) -> Self {
+		let previous = ArithmeticModes::active();
+		ArithmeticModes::set_active(context.arithmetic_modes());
+		ModeScope { previous }
+	}
+}
+
+impl Drop for ModeScope {
+	fn drop(&mut self) {
+		ArithmeticModes::set_active(self.previous);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ArithmeticModes, ModeScope};
+	use crate::context::Context;
+	use intel_dfp::{Decimal, RoundingMode};
+
+	#[test]
+	fn modes_of_the_context_are_active_inside_its_scope() {
+		let mut context = Context::new();
+		context.set_rounding_mode(RoundingMode::TowardZero);
+		context.set_complex_results(false);
+		{
+			let _modes = ModeScope::begin(&context);
+			assert!(ArithmeticModes::active() == context.arithmetic_modes());
+			assert_eq!(Decimal::rounding_mode(), RoundingMode::TowardZero);
+
+			// Operations started by another operation use their own modes
+			let inner = Context::new();
+			{
+				let _modes = ModeScope::begin(&inner);
+				assert!(ArithmeticModes::active() == inner.arithmetic_modes());
+			}
+			assert!(ArithmeticModes::active() == context.arithmetic_modes());
+		}
+		assert!(ArithmeticModes::active() == ArithmeticModes::DEFAULT);
+		assert_eq!(Decimal::rounding_mode(), RoundingMode::NearestEven);
+	}
+}
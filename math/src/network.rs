@@ -0,0 +1,190 @@
+use crate::error::{Error, Result};
+use core::fmt::Write;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+pub const IPV4_BITS: u32 = 32;
+pub const IPV6_BITS: u32 = 128;
+
+/// Reads an IPv4 address in dotted-quad notation
+pub fn parse_ipv4(text: &str) -> Result<u32> {
+	let mut result = 0u32;
+	let mut count = 0;
+	for part in text.split('.') {
+		// Leading zeros are rejected, as they are read as octal by some tools
+		if part.is_empty() || part.len() > 3 || (part.len() > 1 && part.starts_with('0')) {
+			return Err(Error::InvalidArgument);
+		}
+		if !part.chars().all(|ch| ch.is_ascii_digit()) {
+			return Err(Error::InvalidArgument);
+		}
+		let octet: u32 = part.parse().map_err(|_| Error::InvalidArgument)?;
+		if octet > 255 {
+			return Err(Error::InvalidArgument);
+		}
+		result = (result << 8) | octet;
+		count += 1;
+	}
+	if count != 4 {
+		return Err(Error::InvalidArgument);
+	}
+	Ok(result)
+}
+
+/// Reads an IPv6 address, which may compress a run of zero groups with `::`
+/// and end with an IPv4 address in dotted-quad notation
+pub fn parse_ipv6(text: &str) -> Result<u128> {
+	let (head, tail) = match text.find("::") {
+		Some(idx) => (&text[..idx], Some(&text[idx + 2..])),
+		None => (text, None),
+	};
+
+	let mut head_groups = [0u16; 8];
+	let head_count = parse_ipv6_groups(head, &mut head_groups)?;
+	let mut groups = [0u16; 8];
+	match tail {
+		Some(tail) => {
+			let mut tail_groups = [0u16; 8];
+			let tail_count = parse_ipv6_groups(tail, &mut tail_groups)?;
+			// The compressed run must stand for at least one group
+			if head_count + tail_count > 7 {
+				return Err(Error::InvalidArgument);
+			}
+			groups[..head_count].copy_from_slice(&head_groups[..head_count]);
+			groups[8 - tail_count..].copy_from_slice(&tail_groups[..tail_count]);
+		}
+		None => {
+			if head_count != 8 {
+				return Err(Error::InvalidArgument);
+			}
+			groups = head_groups;
+		}
+	}
+
+	Ok(groups
+		.iter()
+		.fold(0u128, |result, group| (result << 16) | *group as u128))
+}
+
+/// Reads colon separated groups into `groups`, returning how many were read
+fn parse_ipv6_groups(text: &str, groups: &mut [u16; 8]) -> Result<usize> {
+	if text.is_empty() {
+		return Ok(0);
+	}
+	let parts = text.split(':').count();
+	let mut count = 0;
+	for (idx, part) in text.split(':').enumerate() {
+		if idx == parts - 1 && part.contains('.') {
+			if count > 6 {
+				return Err(Error::InvalidArgument);
+			}
+			let ipv4 = parse_ipv4(part)?;
+			groups[count] = (ipv4 >> 16) as u16;
+			groups[count + 1] = ipv4 as u16;
+			count += 2;
+			continue;
+		}
+		if part.is_empty()
+			|| part.len() > 4
+			|| count >= 8
+			|| !part.chars().all(|ch| ch.is_ascii_hexdigit())
+		{
+			return Err(Error::InvalidArgument);
+		}
+		groups[count] = u16::from_str_radix(part, 16).map_err(|_| Error::InvalidArgument)?;
+		count += 1;
+	}
+	Ok(count)
+}
+
+pub fn format_ipv4(address: u32) -> String {
+	let mut result = String::new();
+	for idx in (0..4).rev() {
+		if idx != 3 {
+			result.push('.');
+		}
+		let _ = write!(result, "{}", (address >> (idx * 8)) as u8);
+	}
+	result
+}
+
+/// Writes an IPv6 address in the canonical form of RFC 5952: lowercase digits,
+/// and the longest run of two or more zero groups compressed with `::`
+pub fn format_ipv6(address: u128) -> String {
+	let mut groups = [0u16; 8];
+	for (idx, group) in groups.iter_mut().enumerate() {
+		*group = (address >> (16 * (7 - idx))) as u16;
+	}
+
+	// Find the first of the longest runs of zero groups
+	let mut run = (0, 0);
+	let mut idx = 0;
+	while idx < 8 {
+		if groups[idx] != 0 {
+			idx += 1;
+			continue;
+		}
+		let start = idx;
+		while idx < 8 && groups[idx] == 0 {
+			idx += 1;
+		}
+		if idx - start > run.1 {
+			run = (start, idx - start);
+		}
+	}
+
+	let mut result = String::new();
+	let mut idx = 0;
+	while idx < 8 {
+		if run.1 >= 2 && idx == run.0 {
+			result.push_str("::");
+			idx += run.1;
+			continue;
+		}
+		if idx != 0 && !result.ends_with(':') {
+			result.push(':');
+		}
+		let _ = write!(result, "{:x}", groups[idx]);
+		idx += 1;
+	}
+	result
+}
+
+/// Mask of the network part of an address with the given prefix length
+fn prefix_mask(bits: u32, prefix: u32) -> Result<u128> {
+	if prefix > bits {
+		return Err(Error::ValueOutOfRange);
+	}
+	let all = u128::MAX >> (IPV6_BITS - bits);
+	Ok(all & !all.checked_shr(prefix).unwrap_or(0))
+}
+
+/// First address of the network holding `address`
+pub fn network_address(address: u128, bits: u32, prefix: u32) -> Result<u128> {
+	Ok(address & prefix_mask(bits, prefix)?)
+}
+
+/// Last address of the network holding `address`, which is the broadcast
+/// address of IPv4 networks
+pub fn broadcast_address(address: u128, bits: u32, prefix: u32) -> Result<u128> {
+	let all = u128::MAX >> (IPV6_BITS - bits);
+	Ok(address | (all & !prefix_mask(bits, prefix)?))
+}
+
+/// Netmask of an IPv4 network with the given prefix length
+pub fn ipv4_netmask(prefix: u32) -> Result<u32> {
+	Ok(prefix_mask(IPV4_BITS, prefix)? as u32)
+}
+
+/// Number of usable host addresses in an IPv4 network. The network and
+/// broadcast addresses are not usable, except in the point to point networks
+/// of RFC 3021 with a 31 bit prefix and in single host networks.
+pub fn ipv4_host_count(prefix: u32) -> Result<u64> {
+	match prefix {
+		0..=30 => Ok((1u64 << (IPV4_BITS - prefix)) - 2),
+		31 => Ok(2),
+		32 => Ok(1),
+		_ => Err(Error::ValueOutOfRange),
+	}
+}
@@ -1,5 +1,7 @@
 use crate::error::{Error, Result};
 use crate::format::Format;
+use crate::integer::IntegerValue;
+use crate::modes::ArithmeticModes;
 use crate::storage::{DeserializeInput, SerializeOutput, StorageObject, StorageRefSerializer};
 use crate::unit::{AngleUnit, UnitConversion};
 use intel_dfp::Decimal;
@@ -33,9 +35,27 @@ pub const MAX_DENOMINATOR_BITS: u64 = 128;
 // Maximum numerator size is the maximum integer portion plus the range of the denominator.
 pub const MAX_NUMERATOR_BITS: u64 = MAX_INTEGER_BITS + MAX_DENOMINATOR_BITS;
 
+// When deferred reduction is enabled, rational results are not reduced by their GCD until
+// either the numerator or denominator grows past this size, or the value is explicitly
+// reduced (which happens when it is placed on the stack).
+pub const DEFERRED_REDUCTION_BITS: u64 = 512;
+
+// Largest integer under a square root that a decimal is recognized as the root
+// of when shown in radical form.
+pub const MAX_RADICAND: u32 = 1_000_000;
+
+// Largest integer that the root is divided by in radical form, which covers the
+// sines and tangents of multiples of 30 and 45 degrees
+pub const MAX_RADICAL_DIVISOR: u32 = 6;
+
+// Digits at the end of a decimal that are not displayed. They absorb the rounding
+// of the steps of a calculation, such as those inside a transcendental function,
+// so that the digits that are displayed are correctly rounded.
+pub const GUARD_DIGITS: usize = 3;
+
 #[derive(Clone)]
 pub enum Number {
-	Integer(BigInt),
+	Integer(IntegerValue),
 	Rational(BigInt, BigUint),
 	Decimal(Decimal),
 }
@@ -46,7 +66,7 @@ pub trait ToNumber {
 
 impl Number {
 	pub fn new() -> Self {
-		Number::Integer(0.into())
+		Number::Integer(IntegerValue::zero())
 	}
 
 	pub fn bigint_to_decimal(int: &BigInt) -> Decimal {
@@ -71,9 +91,16 @@ impl Number {
 		result
 	}
 
+	pub fn integer_to_decimal(int: &IntegerValue) -> Decimal {
+		match int {
+			IntegerValue::Small(value) => (*value).into(),
+			IntegerValue::Big(value) => Self::bigint_to_decimal(value),
+		}
+	}
+
 	pub fn to_decimal<'a>(&'a self) -> Cow<'a, Decimal> {
 		match self {
-			Number::Integer(int) => Cow::Owned(Self::bigint_to_decimal(&int)),
+			Number::Integer(int) => Cow::Owned(Self::integer_to_decimal(int)),
 			Number::Rational(num, denom) => Cow::Owned(
 				Self::bigint_to_decimal(&num)
 					/ Self::bigint_to_decimal(&denom.to_bigint().unwrap()),
@@ -84,7 +111,7 @@ impl Number {
 
 	pub fn to_int<'a>(&'a self) -> Result<Cow<'a, BigInt>> {
 		match self {
-			Number::Integer(int) => Ok(Cow::Borrowed(int)),
+			Number::Integer(int) => Ok(int.as_bigint()),
 			Number::Rational(num, denom) => Ok(Cow::Owned(num / denom.to_bigint().unwrap())),
 			Number::Decimal(num) => {
 				let num = num.trunc();
@@ -141,7 +168,7 @@ impl Number {
 
 	pub fn is_zero(&self) -> bool {
 		match self {
-			Number::Integer(value) => value == &0.to_bigint().unwrap(),
+			Number::Integer(value) => value.is_zero(),
 			Number::Rational(numerator, _) => numerator == &0.to_bigint().unwrap(),
 			Number::Decimal(value) => value == &Decimal::zero(),
 		}
@@ -149,7 +176,7 @@ impl Number {
 
 	pub fn is_negative(&self) -> bool {
 		match self {
-			Number::Integer(value) => value.sign() == Sign::Minus,
+			Number::Integer(value) => value.is_negative(),
 			Number::Rational(numerator, _) => numerator.sign() == Sign::Minus,
 			Number::Decimal(value) => value < &Decimal::zero(),
 		}
@@ -179,54 +206,167 @@ impl Number {
 	pub fn sqrt(&self) -> Number {
 		match &self {
 			Number::Integer(value) => {
-				if value < &0.to_bigint().unwrap() {
+				if value.is_negative() {
 					// Imaginary
 					return Number::Decimal(self.to_decimal().sqrt());
 				}
+				let value = value.as_bigint();
 				let result = value.sqrt();
 				if &result * &result == *value {
 					// Integer root
-					Number::Integer(result)
+					Number::Integer(result.into())
 				} else {
 					// Irrational root
 					Number::Decimal(self.to_decimal().sqrt())
 				}
 			}
-			Number::Rational(_, _) => Number::Decimal(self.to_decimal().sqrt()),
+			Number::Rational(_, _) => {
+				// Rationals with perfect squares for both parts have a rational root
+				match self.exact_pow(&Number::Rational(1.into(), 2.to_biguint().unwrap())) {
+					Some(result) => result,
+					None => Number::Decimal(self.to_decimal().sqrt()),
+				}
+			}
 			Number::Decimal(value) => Number::Decimal(value.sqrt()),
 		}
 	}
 
+	/// Simplified form `a√b/c` of a decimal that is the square root of an integer
+	/// no larger than `MAX_RADICAND` divided by an integer no larger than
+	/// `MAX_RADICAL_DIVISOR`, such as `2√2` for the square root of 8 or `√3/2`
+	/// for the sine of 60 degrees. Returns `a`, which has the sign of the value,
+	/// `b`, which has no square factors, and `c`, in lowest terms with `a`.
+	/// Values that are not irrational square roots give `None`.
+	pub fn radical_form(&self) -> Option<(i64, u32, u32)> {
+		let (value, negative) = match self {
+			Number::Decimal(value) if value.is_finite() => (value.abs(), value.is_sign_negative()),
+			_ => return None,
+		};
+		(1..=MAX_RADICAL_DIVISOR).find_map(|divisor| {
+			let (factor, radicand) = Self::root_of_integer(&(&value * &Decimal::from(divisor)))?;
+
+			// Rounding can leave a multiple of the smallest divisor as the first that
+			// is close enough, so the factor and divisor are brought to lowest terms
+			let common = factor.gcd(&divisor);
+			let factor = (factor / common) as i64;
+			Some((
+				if negative { -factor } else { factor },
+				radicand,
+				divisor / common,
+			))
+		})
+	}
+
+	/// Simplified form `a√b` of a positive decimal that is the square root of an
+	/// integer no larger than `MAX_RADICAND`
+	fn root_of_integer(value: &Decimal) -> Option<(u32, u32)> {
+		let square = value * value;
+		if square < 2.into() || square > MAX_RADICAND.into() {
+			return None;
+		}
+		let radicand: u32 = Number::Decimal(&square + &Decimal::from_str("0.5"))
+			.to_int()
+			.ok()?
+			.magnitude()
+			.try_into()
+			.ok()?;
+
+		// The value must be the root to all but the guard digits of the decimal
+		// backend, which allows for rounding in results such as 3 × √2
+		let root = Decimal::from(radicand).sqrt();
+		let tolerance = Decimal::from(GUARD_DIGITS as i32 - Decimal::DIGITS as i32).exp10();
+		if (value - &root).abs() > &root * &tolerance {
+			return None;
+		}
+
+		// Move the largest square factor out from under the root
+		let mut factor = 1;
+		let mut candidate = 2;
+		while candidate * candidate <= radicand {
+			if radicand.is_multiple_of(candidate * candidate) {
+				factor = candidate;
+			}
+			candidate += 1;
+		}
+		let remaining = radicand / (factor * factor);
+		if remaining == 1 {
+			return None;
+		}
+		Some((factor, remaining))
+	}
+
 	pub fn pow(&self, power: &Number) -> Number {
-		match &self {
-			Number::Integer(left) => match power {
-				Number::Integer(right) => {
-					if right < &0.to_bigint().unwrap() {
-						// Fractional power, use float
-						return Number::Decimal(self.to_decimal().pow(&power.to_decimal()));
-					}
-					if let Ok(int_power) = right.try_into() {
-						let left_bits = left.bits();
-						if left_bits > 0 && ((left_bits - 1) * int_power as u64) > MAX_INTEGER_BITS
-						{
-							Number::Decimal(self.to_decimal().pow(&power.to_decimal()))
-						} else {
-							Self::check_int_bounds(Number::Integer(left.pow(int_power)))
-						}
-					} else {
-						Number::Decimal(self.to_decimal().pow(&power.to_decimal()))
-					}
-				}
-				Number::Rational(_, _) => {
-					Number::Decimal(self.to_decimal().pow(&power.to_decimal()))
-				}
-				Number::Decimal(right) => Number::Decimal(self.to_decimal().pow(right)),
-			},
-			Number::Rational(_, _) => Number::Decimal(self.to_decimal().pow(&power.to_decimal())),
-			Number::Decimal(left) => Number::Decimal(left.pow(&power.to_decimal())),
+		match self.exact_pow(power) {
+			Some(result) => result,
+			None => Number::Decimal(self.to_decimal().pow(&power.to_decimal())),
 		}
 	}
 
+	/// Raises an integer or rational to an integer or rational power if the result
+	/// can be kept exact. A power of `p/q` takes the `q`th root first, which is only
+	/// exact when both parts of the base are perfect powers, and results that would
+	/// be too large for an integer or rational are left to the decimal calculation.
+	fn exact_pow(&self, power: &Number) -> Option<Number> {
+		// Roots are found from the parts in lowest terms
+		if !self.is_reduced() || !power.is_reduced() {
+			return self.reduced().exact_pow(&power.reduced());
+		}
+		let (num, denom) = match self {
+			Number::Integer(int) => (int.as_bigint(), Cow::Owned(1.to_biguint().unwrap())),
+			Number::Rational(num, denom) => (Cow::Borrowed(num), Cow::Borrowed(denom)),
+			Number::Decimal(_) => return None,
+		};
+		let (exponent, root): (u32, u32) = match power {
+			Number::Integer(int) => (int.magnitude_to_u32()?, 1),
+			Number::Rational(num, denom) => {
+				(num.magnitude().try_into().ok()?, denom.try_into().ok()?)
+			}
+			Number::Decimal(_) => return None,
+		};
+		let negative_power = power.is_negative();
+		if (num.sign() == Sign::NoSign && negative_power)
+			|| (num.sign() == Sign::Minus && root % 2 == 0)
+		{
+			return None;
+		}
+
+		// Take the root of each part, which must be exact
+		let num_root = num.nth_root(root);
+		let denom_root = denom.nth_root(root);
+		if num_root.pow(root) != *num || denom_root.pow(root) != *denom {
+			return None;
+		}
+
+		// Check the size of the result before computing it, as large powers can take
+		// a long time to compute
+		let too_large =
+			|bits: u64, max_bits: u64| bits > 0 && (bits - 1) * exponent as u64 > max_bits;
+		let (num_limit, denom_limit) = if negative_power {
+			(MAX_DENOMINATOR_BITS, MAX_NUMERATOR_BITS)
+		} else {
+			(MAX_NUMERATOR_BITS, MAX_DENOMINATOR_BITS)
+		};
+		if too_large(num_root.bits(), num_limit) || too_large(denom_root.bits(), denom_limit) {
+			return None;
+		}
+
+		let num_pow = num_root.pow(exponent);
+		let denom_pow = denom_root.pow(exponent);
+		let result = if negative_power {
+			// The sign of the base moves to the new numerator
+			let new_num = denom_pow.to_bigint().unwrap();
+			let new_num = if num_pow.sign() == Sign::Minus {
+				-new_num
+			} else {
+				new_num
+			};
+			Number::Rational(new_num, num_pow.magnitude().clone())
+		} else {
+			Number::Rational(num_pow, denom_pow)
+		};
+		Some(result.reduce())
+	}
+
 	pub fn sin(&self) -> Number {
 		Number::Decimal(self.to_decimal().sin())
 	}
@@ -264,7 +404,21 @@ impl Number {
 	}
 
 	pub fn asinh(&self) -> Number {
-		Number::Decimal(self.to_decimal().asinh())
+		// The library loses digits to cancellation for small arguments, so these are
+		// computed with ln_1p from asinh(x) = ln(1 + x + x²/(1 + √(1 + x²)))
+		let value = self.to_decimal();
+		let magnitude = value.abs();
+		let one = Decimal::from(1);
+		if magnitude >= one {
+			return Number::Decimal(value.asinh());
+		}
+		let square = &magnitude * &magnitude;
+		let result = (&magnitude + &(&square / &(&one + &(&square + &one).sqrt()))).ln_1p();
+		Number::Decimal(if value.is_sign_negative() {
+			-result
+		} else {
+			result
+		})
 	}
 
 	pub fn acosh(&self) -> Number {
@@ -275,11 +429,24 @@ impl Number {
 		Number::Decimal(self.to_decimal().atanh())
 	}
 
+	/// Converts an angle to radians. Angles in degrees and gradians are first
+	/// reduced to within one turn, which is exact, so that large angles don't lose
+	/// their fractional part to the rounding of the conversion.
 	pub fn angle_to_radians<'a>(&'a self, angle_mode: AngleUnit) -> Cow<'a, Number> {
-		match angle_mode {
-			AngleUnit::Radians => Cow::Borrowed(self),
-			_ => Cow::Owned(angle_mode.to_unit(self, &AngleUnit::Radians)),
-		}
+		let turn = match angle_mode {
+			AngleUnit::Radians => return Cow::Borrowed(self),
+			AngleUnit::Degrees => 360,
+			AngleUnit::Gradians => 400,
+		};
+		let angle = match self {
+			Number::Integer(int) => Number::Integer(int % IntegerValue::from(turn)),
+			Number::Rational(num, denom) => {
+				let turn = denom.to_bigint().unwrap() * turn;
+				Number::Rational(num % turn, denom.clone()).reduce()
+			}
+			Number::Decimal(value) => Number::Decimal(value % &turn.into()),
+		};
+		Cow::Owned(angle_mode.to_unit(&angle, &AngleUnit::Radians))
 	}
 
 	pub fn angle_from_radians<'a>(&'a self, angle_mode: AngleUnit) -> Cow<'a, Number> {
@@ -290,11 +457,38 @@ impl Number {
 	}
 
 	pub fn log(&self) -> Number {
-		Number::Decimal(self.to_decimal().log10())
+		match self.offset_from_one() {
+			Some(offset) => Number::Decimal(offset.ln_1p() / Decimal::from(10).ln()),
+			None => Number::Decimal(self.to_decimal().log10()),
+		}
 	}
 
 	pub fn ln(&self) -> Number {
-		Number::Decimal(self.to_decimal().ln())
+		match self.offset_from_one() {
+			Some(offset) => Number::Decimal(offset.ln_1p()),
+			None => Number::Decimal(self.to_decimal().ln()),
+		}
+	}
+
+	/// Exact difference from one of a fraction between 1/2 and 2. Logarithms of
+	/// these are computed from the difference, as converting the fraction itself
+	/// to decimal would round away the digits that the logarithm depends on.
+	fn offset_from_one(&self) -> Option<Decimal> {
+		match self {
+			Number::Rational(num, denom) => {
+				let denom_int = denom.to_bigint().unwrap();
+				if num * 2 > denom_int && num < &(&denom_int * 2) {
+					Some(
+						Number::Rational(num - &denom_int, denom.clone())
+							.to_decimal()
+							.into_owned(),
+					)
+				} else {
+					None
+				}
+			}
+			_ => None,
+		}
 	}
 
 	pub fn exp10(&self) -> Number {
@@ -306,6 +500,105 @@ impl Number {
 	}
 
 	fn simplify(self) -> Self {
+		if let Number::Rational(num, denom) = &self {
+			if ArithmeticModes::active().deferred_reduction
+				&& num.bits() <= DEFERRED_REDUCTION_BITS
+				&& denom.bits() <= DEFERRED_REDUCTION_BITS
+			{
+				return self.remove_common_twos().whole_to_integer();
+			}
+		}
+		self.reduce()
+	}
+
+	/// Converts a rational that is a whole number into an integer. This only needs
+	/// one division, so it is done even when the full reduction is deferred to keep
+	/// integer results usable by functions that take integers.
+	fn whole_to_integer(self) -> Self {
+		match self {
+			Number::Rational(num, denom) => {
+				let denom_int = denom.to_bigint().unwrap();
+				if num.is_multiple_of(&denom_int) {
+					Self::check_int_bounds(Number::Integer((num / denom_int).into()))
+				} else {
+					Number::Rational(num, denom)
+				}
+			}
+			_ => self,
+		}
+	}
+
+	/// Divides out the powers of two shared by the numerator and denominator. This
+	/// only needs a shift, so it is done even when the full reduction is deferred to
+	/// keep denominators such as those of binary fractions from growing.
+	fn remove_common_twos(self) -> Self {
+		match self {
+			Number::Rational(num, denom) => {
+				let shift = match (num.trailing_zeros(), denom.trailing_zeros()) {
+					(Some(num_zeros), Some(denom_zeros)) => num_zeros.min(denom_zeros),
+					_ => 0,
+				};
+				if shift == 0 {
+					Number::Rational(num, denom)
+				} else if denom.bits() == shift + 1 {
+					Self::check_int_bounds(Number::Integer((num >> shift).into()))
+				} else {
+					Number::Rational(num >> shift, denom >> shift)
+				}
+			}
+			_ => self,
+		}
+	}
+
+	/// Adds or subtracts two fractions. Fractions with the same denominator keep
+	/// it, so that a sum of values with a common denominator does not grow the
+	/// denominator while reduction is deferred.
+	fn rational_add_sub(
+		left_num: &BigInt,
+		left_denom: &BigUint,
+		right_num: &BigInt,
+		right_denom: &BigUint,
+		subtract: bool,
+	) -> Number {
+		if left_denom == right_denom {
+			let num = if subtract {
+				left_num - right_num
+			} else {
+				left_num + right_num
+			};
+			return Number::Rational(num, left_denom.clone()).simplify();
+		}
+		let left = left_num * right_denom.to_bigint().unwrap();
+		let right = right_num * left_denom.to_bigint().unwrap();
+		let num = if subtract { left - right } else { left + right };
+		Number::Rational(num, left_denom * right_denom).simplify()
+	}
+
+	/// Returns true unless the number is a rational that is not in lowest terms,
+	/// which is left by arithmetic while reduction is deferred
+	pub fn is_reduced(&self) -> bool {
+		match self {
+			Number::Rational(num, denom) => {
+				num.magnitude().gcd(denom) == 1.to_biguint().unwrap()
+					&& denom != &1.to_biguint().unwrap()
+			}
+			_ => true,
+		}
+	}
+
+	/// Completes any deferred reduction for display, borrowing numbers that are
+	/// already in lowest terms
+	pub fn reduced(&self) -> Cow<'_, Number> {
+		if self.is_reduced() {
+			Cow::Borrowed(self)
+		} else {
+			Cow::Owned(self.clone().reduce())
+		}
+	}
+
+	/// Fully reduces a rational number by the GCD of its numerator and denominator,
+	/// converting it to an integer if possible.
+	pub fn reduce(self) -> Self {
 		match self {
 			Number::Rational(num, denom) => {
 				let num_abs = if num.sign() == Sign::Minus {
@@ -317,7 +610,7 @@ impl Number {
 				let num = num / gcd.to_bigint().unwrap();
 				let denom = denom / gcd;
 				if denom == 1.to_biguint().unwrap() {
-					Self::check_int_bounds(Number::Integer(num))
+					Self::check_int_bounds(Number::Integer(num.into()))
 				} else {
 					Self::check_int_bounds(Number::Rational(num, denom))
 				}
@@ -371,21 +664,18 @@ impl Number {
 			Number::Integer(left) => match rhs {
 				Number::Integer(right) => Self::check_int_bounds(Number::Integer(left + right)),
 				Number::Rational(right_num, right_denom) => {
-					let num = left * right_denom.to_bigint().unwrap() + right_num;
+					let num = &*left.as_bigint() * right_denom.to_bigint().unwrap() + right_num;
 					Number::Rational(num, right_denom.clone()).simplify()
 				}
 				Number::Decimal(right) => Number::Decimal(&*self.to_decimal() + right),
 			},
 			Number::Rational(left_num, left_denom) => match rhs {
 				Number::Integer(right) => {
-					let num = left_num + right * left_denom.to_bigint().unwrap();
+					let num = left_num + &*right.as_bigint() * left_denom.to_bigint().unwrap();
 					Number::Rational(num, left_denom.clone()).simplify()
 				}
 				Number::Rational(right_num, right_denom) => {
-					let num = left_num * right_denom.to_bigint().unwrap()
-						+ right_num * left_denom.to_bigint().unwrap();
-					let denom = left_denom * right_denom;
-					Number::Rational(num, denom).simplify()
+					Self::rational_add_sub(left_num, left_denom, right_num, right_denom, false)
 				}
 				Number::Decimal(right) => Number::Decimal(&*self.to_decimal() + right),
 			},
@@ -398,21 +688,18 @@ impl Number {
 			Number::Integer(left) => match rhs {
 				Number::Integer(right) => Self::check_int_bounds(Number::Integer(left - right)),
 				Number::Rational(right_num, right_denom) => {
-					let num = left * right_denom.to_bigint().unwrap() - right_num;
+					let num = &*left.as_bigint() * right_denom.to_bigint().unwrap() - right_num;
 					Number::Rational(num, right_denom.clone()).simplify()
 				}
 				Number::Decimal(right) => Number::Decimal(&*self.to_decimal() - right),
 			},
 			Number::Rational(left_num, left_denom) => match rhs {
 				Number::Integer(right) => {
-					let num = left_num - right * left_denom.to_bigint().unwrap();
+					let num = left_num - &*right.as_bigint() * left_denom.to_bigint().unwrap();
 					Number::Rational(num, left_denom.clone()).simplify()
 				}
 				Number::Rational(right_num, right_denom) => {
-					let num = left_num * right_denom.to_bigint().unwrap()
-						- right_num * left_denom.to_bigint().unwrap();
-					let denom = left_denom * right_denom;
-					Number::Rational(num, denom).simplify()
+					Self::rational_add_sub(left_num, left_denom, right_num, right_denom, true)
 				}
 				Number::Decimal(right) => Number::Decimal(&*self.to_decimal() - right),
 			},
@@ -425,13 +712,13 @@ impl Number {
 			Number::Integer(left) => match rhs {
 				Number::Integer(right) => Self::check_int_bounds(Number::Integer(left * right)),
 				Number::Rational(right_num, right_denom) => {
-					Number::Rational(left * right_num, right_denom.clone()).simplify()
+					Number::Rational(&*left.as_bigint() * right_num, right_denom.clone()).simplify()
 				}
 				Number::Decimal(right) => Number::Decimal(&*self.to_decimal() * right),
 			},
 			Number::Rational(left_num, left_denom) => match rhs {
 				Number::Integer(right) => {
-					Number::Rational(left_num * right, left_denom.clone()).simplify()
+					Number::Rational(left_num * &*right.as_bigint(), left_denom.clone()).simplify()
 				}
 				Number::Rational(right_num, right_denom) => {
 					Number::Rational(left_num * right_num, left_denom * right_denom).simplify()
@@ -446,28 +733,31 @@ impl Number {
 		match &self {
 			Number::Integer(left) => match rhs {
 				Number::Integer(right) => {
-					if right == &0.to_bigint().unwrap() {
+					if right.is_zero() {
 						// Divide by zero, use float to get the right inf/NaN
 						return Number::Decimal(&*self.to_decimal() / &*rhs.to_decimal());
 					}
+					if let Some(result) = left.exact_div(right) {
+						return Number::Integer(result);
+					}
+					let left = left.as_bigint();
+					let right = right.as_bigint();
 					if right.sign() == Sign::Minus {
-						Number::Rational(-left.to_bigint().unwrap(), (-right).to_biguint().unwrap())
-							.simplify()
+						Number::Rational(-&*left, (-&*right).to_biguint().unwrap()).simplify()
 					} else {
-						Number::Rational(left.to_bigint().unwrap(), right.to_biguint().unwrap())
-							.simplify()
+						Number::Rational(left.into_owned(), right.to_biguint().unwrap()).simplify()
 					}
 				}
 				Number::Rational(right_num, right_denom) => {
 					if right_num.sign() == Sign::Minus {
 						Number::Rational(
-							left * -right_denom.to_bigint().unwrap(),
+							&*left.as_bigint() * -right_denom.to_bigint().unwrap(),
 							(-right_num).to_biguint().unwrap(),
 						)
 						.simplify()
 					} else {
 						Number::Rational(
-							left * right_denom.to_bigint().unwrap(),
+							&*left.as_bigint() * right_denom.to_bigint().unwrap(),
 							right_num.to_biguint().unwrap(),
 						)
 						.simplify()
@@ -477,9 +767,9 @@ impl Number {
 			},
 			Number::Rational(left_num, left_denom) => match rhs {
 				Number::Integer(right) => {
+					let right = right.as_bigint();
 					if right.sign() == Sign::Minus {
-						Number::Rational(-left_num, left_denom * right.to_biguint().unwrap())
-							.simplify()
+						Number::Rational(-left_num, left_denom * right.magnitude()).simplify()
 					} else {
 						Number::Rational(left_num.clone(), left_denom * right.to_biguint().unwrap())
 							.simplify()
@@ -615,13 +905,13 @@ impl From<Decimal> for Number {
 
 impl From<BigInt> for Number {
 	fn from(val: BigInt) -> Self {
-		Number::Integer(val)
+		Number::Integer(val.into())
 	}
 }
 
 impl From<BigUint> for Number {
 	fn from(val: BigUint) -> Self {
-		Number::Integer(val.to_bigint().unwrap())
+		Number::Integer(val.into())
 	}
 }
 
@@ -848,7 +1138,7 @@ impl StorageObject for Number {
 			Number::Integer(int) => {
 				output.write_u8(NUM_SERIALIZE_TYPE_INTEGER)?; // Type marker
 
-				let (sign, digits) = int.to_u32_digits();
+				let (sign, digits) = int.as_bigint().to_u32_digits();
 				// Output sign
 				output.write_u8(match sign {
 					Sign::NoSign => NUM_SERIALIZE_SIGN_NONE,
@@ -921,6 +1211,9 @@ impl StorageObject for Number {
 
 				// Decode size
 				let size = input.read_u32()? as usize;
+				if size > input.remaining() / 4 {
+					return Err(Error::CorruptData);
+				}
 
 				// Decode digits
 				let mut digits = Vec::new();
@@ -930,7 +1223,7 @@ impl StorageObject for Number {
 				}
 
 				// Create integer from parts
-				Ok(Number::Integer(BigInt::from_slice(sign, &digits)))
+				Ok(Number::Integer(BigInt::from_slice(sign, &digits).into()))
 			}
 			NUM_SERIALIZE_TYPE_RATIONAL => {
 				// Decode sign
@@ -980,3 +1273,531 @@ impl StorageObject for Number {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{Number, ToNumber, GUARD_DIGITS, MAX_DENOMINATOR_BITS};
+	use crate::context::Context;
+	use crate::format::{Format, FormatMode};
+	use crate::modes::ModeScope;
+	use crate::unit::AngleUnit;
+	use intel_dfp::Decimal;
+	use num_bigint::{BigInt, BigUint};
+
+	fn rational(num: i64, denom: u64) -> Number {
+		Number::Rational(BigInt::from(num), BigUint::from(denom))
+	}
+
+	fn assert_rational(value: &Number, num: i64, denom: u64) {
+		match value {
+			Number::Rational(value_num, value_denom) => {
+				assert_eq!(value_num, &BigInt::from(num));
+				assert_eq!(value_denom, &BigUint::from(denom));
+			}
+			_ => panic!("expected {}/{}, got {}", num, denom, value.to_string()),
+		}
+	}
+
+	fn assert_integer(value: &Number, expected: i64) {
+		match value {
+			Number::Integer(int) => assert_eq!(int.to_i64(), Some(expected)),
+			_ => panic!("expected {}, got {}", expected, value.to_string()),
+		}
+	}
+
+	// Results that can't be exact are only checked to the last kept digit
+	fn assert_decimal(value: &Number, expected: f64) {
+		match value {
+			Number::Decimal(value) => {
+				let actual: f64 = value.to_string().parse().unwrap();
+				let tolerance = 10f64.powi(1 - (Decimal::DIGITS - GUARD_DIGITS) as i32);
+				assert!(
+					((actual - expected) / expected).abs() < tolerance.max(1e-15),
+					"expected {}, got {}",
+					expected,
+					actual
+				);
+			}
+			_ => panic!("expected {}, got {}", expected, value.to_string()),
+		}
+	}
+
+	#[test]
+	fn negative_integer_power_is_rational() {
+		assert_rational(&Number::from(2).pow(&Number::from(-3)), 1, 8);
+		assert_rational(&Number::from(-2).pow(&Number::from(-3)), -1, 8);
+		assert_rational(&Number::from(-2).pow(&Number::from(-2)), 1, 4);
+		assert_rational(&rational(2, 3).pow(&Number::from(-2)), 9, 4);
+		assert_rational(&rational(-2, 3).pow(&Number::from(-3)), -27, 8);
+		assert_integer(&rational(1, 5).pow(&Number::from(-2)), 25);
+	}
+
+	#[test]
+	fn rational_power_of_perfect_power_is_exact() {
+		assert_integer(&Number::from(8).pow(&rational(2, 3)), 4);
+		assert_integer(&Number::from(-27).pow(&rational(1, 3)), -3);
+		assert_rational(&rational(8, 27).pow(&rational(2, 3)), 4, 9);
+		assert_rational(&Number::from(16).pow(&rational(-3, 4)), 1, 8);
+		assert_rational(&rational(4, 9).sqrt(), 2, 3);
+		assert_integer(&Number::from(144).sqrt(), 12);
+	}
+
+	#[test]
+	fn deferred_reduction_is_completed_for_display() {
+		let mut context = Context::new();
+		context.set_deferred_reduction(true);
+		let _modes = ModeScope::begin(&context);
+
+		// Sums with a common denominator keep it until the value is displayed
+		let sum = &rational(1, 9) + &rational(2, 9);
+		assert_rational(&sum, 3, 9);
+		assert!(!sum.is_reduced());
+		assert_rational(&sum.reduced(), 1, 3);
+		assert_rational(&(&sum * &sum).sqrt(), 1, 3);
+
+		// Whole numbers are still integers
+		assert_integer(&(&sum + &rational(6, 9)), 1);
+	}
+
+	#[test]
+	fn irrational_root_falls_back_to_decimal() {
+		assert_decimal(&Number::from(2).pow(&rational(1, 2)), 2f64.sqrt());
+		assert_decimal(
+			&Number::from(10).pow(&rational(2, 3)),
+			10f64.powf(2.0 / 3.0),
+		);
+		assert_decimal(&rational(8, 25).pow(&rational(1, 3)), 0.32f64.cbrt());
+		assert_decimal(&rational(1, 2).sqrt(), 0.5f64.sqrt());
+		match Number::from(-4).pow(&rational(1, 2)) {
+			Number::Rational(_, _) | Number::Integer(_) => panic!("even root of negative is exact"),
+			Number::Decimal(_) => (),
+		}
+	}
+
+	#[test]
+	fn results_past_size_limits_fall_back_to_decimal() {
+		// The integer limit is 8192 bits, so 3^10000 is too large
+		match Number::from(3).pow(&Number::from(10000)) {
+			Number::Decimal(_) => (),
+			value => panic!("expected a decimal, got {}", value.to_string()),
+		}
+
+		// Denominators are limited separately to keep the precision of decimals
+		let limit = MAX_DENOMINATOR_BITS as i32;
+		match Number::from(2).pow(&Number::from(1 - limit)) {
+			Number::Rational(_, _) => (),
+			value => panic!("expected a rational, got {}", value.to_string()),
+		}
+		assert_decimal(
+			&Number::from(2).pow(&Number::from(-limit)),
+			2f64.powi(-limit),
+		);
+		assert_decimal(&rational(1, 3).pow(&Number::from(200)), 3f64.powi(-200));
+	}
+
+	/// Checks that a result is displayed as the reference value, given to more
+	/// digits, correctly rounded to every digit that can be displayed. The
+	/// references were computed with mpmath at 60 digits.
+	fn assert_correctly_rounded(name: &str, result: &Number, reference: &str) {
+		let mut format = Format::new();
+		format.mode = FormatMode::Scientific;
+		format.precision = Decimal::DIGITS;
+		assert_eq!(
+			format.format_decimal(&result.to_decimal()),
+			format.format_decimal(&Decimal::from_str(reference)),
+			"{} gave {}, expected {}",
+			name,
+			result.to_decimal().to_string(),
+			reference
+		);
+	}
+
+	#[test]
+	fn transcendental_functions_are_correctly_rounded() {
+		type Case = (fn(&Number) -> Number, &'static str, &'static str);
+		let cases: [Case; 62] = [
+			(
+				Number::ln,
+				"2",
+				"6.931471805599453094172321214581765680755E-1",
+			),
+			(
+				Number::ln,
+				"10",
+				"2.302585092994045684017991454684364207601",
+			),
+			(
+				Number::ln,
+				"0.001",
+				"-6.907755278982137052053974364053092622803",
+			),
+			(
+				Number::ln,
+				"1E+100",
+				"2.302585092994045684017991454684364207601E+2",
+			),
+			(
+				Number::ln,
+				"1.25",
+				"2.231435513142097557662950903098345033746E-1",
+			),
+			(
+				Number::ln,
+				"123456.789",
+				"1.172364648718588098113995898391011158691E+1",
+			),
+			(
+				Number::log,
+				"2",
+				"3.010299956639811952137388947244930267682E-1",
+			),
+			(
+				Number::log,
+				"7",
+				"8.450980400142568307122162585926361934836E-1",
+			),
+			(
+				Number::log,
+				"0.5",
+				"-3.010299956639811952137388947244930267682E-1",
+			),
+			(
+				Number::log,
+				"3E-50",
+				"-4.95228787452803375627049720967448846908E+1",
+			),
+			(
+				Number::exp,
+				"1",
+				"2.718281828459045235360287471352662497757",
+			),
+			(
+				Number::exp,
+				"-1",
+				"3.678794411714423215955237701614608674458E-1",
+			),
+			(
+				Number::exp,
+				"0.5",
+				"1.648721270700128146848650787814163571654",
+			),
+			(
+				Number::exp,
+				"100",
+				"2.688117141816135448412625551580013587361E+43",
+			),
+			(
+				Number::exp,
+				"-100",
+				"3.720075976020835962959695803863118337359E-44",
+			),
+			(
+				Number::exp,
+				"1E-10",
+				"1.000000000100000000005000000000166666667",
+			),
+			(
+				Number::exp10,
+				"0.5",
+				"3.16227766016837933199889354443271853372",
+			),
+			(
+				Number::exp10,
+				"-3.3",
+				"5.011872336272722850015541868849457680605E-4",
+			),
+			(
+				Number::exp10,
+				"20.25",
+				"1.778279410038922801225421195192684844736E+20",
+			),
+			(
+				Number::sin,
+				"0.5",
+				"4.794255386042030002732879352155713880818E-1",
+			),
+			(
+				Number::sin,
+				"1",
+				"8.414709848078965066525023216302989996226E-1",
+			),
+			(
+				Number::sin,
+				"3",
+				"1.411200080598672221007448028081102798469E-1",
+			),
+			(
+				Number::sin,
+				"100",
+				"-5.06365641109758793656557610459785432065E-1",
+			),
+			(
+				Number::sin,
+				"1000000",
+				"-3.499935021712929521176524867807714690614E-1",
+			),
+			(Number::sin, "1E-20", "1.0E-20"),
+			(
+				Number::cos,
+				"0.5",
+				"8.775825618903727161162815826038296519916E-1",
+			),
+			(
+				Number::cos,
+				"1.5",
+				"7.073720166770291008818985143426870908509E-2",
+			),
+			(
+				Number::cos,
+				"3",
+				"-9.899924966004454572715727947312613023937E-1",
+			),
+			(
+				Number::cos,
+				"100",
+				"8.623188722876839341019385139508425355101E-1",
+			),
+			(
+				Number::cos,
+				"1000000",
+				"9.367521275331447869385325350749187757081E-1",
+			),
+			(
+				Number::tan,
+				"0.5",
+				"5.463024898437905132551794657802853832976E-1",
+			),
+			(
+				Number::tan,
+				"1.5",
+				"1.410141994717171938764608365198775644566E+1",
+			),
+			(
+				Number::tan,
+				"-2",
+				"2.185039863261518991643306102313682543432",
+			),
+			(
+				Number::tan,
+				"100",
+				"-5.872139151569290766778096356445878942588E-1",
+			),
+			(
+				Number::asin,
+				"0.5",
+				"5.235987755982988730771072305465838140329E-1",
+			),
+			(
+				Number::asin,
+				"-0.9",
+				"-1.119769514998634186686677055845399615895",
+			),
+			(
+				Number::asin,
+				"0.999",
+				"1.526071239626163187981625458968200372194",
+			),
+			(
+				Number::asin,
+				"1E-10",
+				"1.000000000000000000001666666666666666667E-10",
+			),
+			(
+				Number::acos,
+				"0.5",
+				"1.047197551196597746154214461093167628066",
+			),
+			(
+				Number::acos,
+				"-0.9",
+				"2.690565841793530805917998747485151057994",
+			),
+			(
+				Number::acos,
+				"0.999",
+				"4.472508716873343124969623267155106990418E-2",
+			),
+			(
+				Number::atan,
+				"0.5",
+				"4.636476090008061162142562314612144020285E-1",
+			),
+			(
+				Number::atan,
+				"10",
+				"1.471127674303734591852875571761730851855",
+			),
+			(
+				Number::atan,
+				"-100000",
+				"-1.57078632679489695256465500497308477686",
+			),
+			(
+				Number::sinh,
+				"0.5",
+				"5.210953054937473616224256264114915591059E-1",
+			),
+			(
+				Number::sinh,
+				"10",
+				"1.10132328747033933772365245548463644029E+4",
+			),
+			(
+				Number::sinh,
+				"-3",
+				"-1.001787492740990189897459361946582806018E+1",
+			),
+			(
+				Number::cosh,
+				"0.5",
+				"1.127625965206380785226225161402672012548",
+			),
+			(
+				Number::cosh,
+				"10",
+				"1.101323292010332313972137609043787996345E+4",
+			),
+			(
+				Number::cosh,
+				"-3",
+				"1.006766199577776584195393603511588983681E+1",
+			),
+			(
+				Number::tanh,
+				"0.5",
+				"4.621171572600097585023184836436725487303E-1",
+			),
+			(
+				Number::tanh,
+				"3",
+				"9.950547536867304513318801852554884750978E-1",
+			),
+			(
+				Number::tanh,
+				"-0.02",
+				"-1.999733375993093183028386901500650275169E-2",
+			),
+			(
+				Number::asinh,
+				"2",
+				"1.443635475178810342493276740273105269406",
+			),
+			(
+				Number::asinh,
+				"-0.001",
+				"-9.999998333334083332886905065723982627789E-4",
+			),
+			(
+				Number::asinh,
+				"1000",
+				"7.600902709541988611523289784664939633568",
+			),
+			(
+				Number::acosh,
+				"2",
+				"1.316957896924816708625046347307968444027",
+			),
+			(
+				Number::acosh,
+				"1.5",
+				"9.624236501192068949955178268487368462704E-1",
+			),
+			(
+				Number::acosh,
+				"1000",
+				"7.600902209541988611419123117998223748152",
+			),
+			(
+				Number::atanh,
+				"0.5",
+				"5.493061443340548456976226184612628523237E-1",
+			),
+			(
+				Number::atanh,
+				"-0.9",
+				"-1.472219489583220230004513715943926768619",
+			),
+			(
+				Number::atanh,
+				"0.001",
+				"1.000000333333533333476190587301678210755E-3",
+			),
+		];
+		for (func, argument, reference) in cases.iter() {
+			let result = func(&Number::Decimal(Decimal::from_str(argument)));
+			assert_correctly_rounded(argument, &result, reference);
+		}
+	}
+
+	#[test]
+	fn decimal_powers_are_correctly_rounded() {
+		let cases = [
+			("2", "0.5", "1.41421356237309504880168872420969807857"),
+			("10", "0.3333", "2.154269337404236599119889336556223513749"),
+			("1.5", "2.5", "2.755675960631075360471944584044127815962"),
+			(
+				"0.7",
+				"-40.1",
+				"1.627678381949496927510144578054043364823E+6",
+			),
+			(
+				"3",
+				"100.5",
+				"8.926600509867260257937586184844912024484E+47",
+			),
+		];
+		for (base, power, reference) in cases.iter() {
+			let base = Number::Decimal(Decimal::from_str(base));
+			let result = base.pow(&Number::Decimal(Decimal::from_str(power)));
+			assert_correctly_rounded(power, &result, reference);
+		}
+	}
+
+	#[test]
+	fn angles_in_degrees_are_reduced_exactly() {
+		let angle = 10.to_number().pow(&22.to_number());
+		assert_correctly_rounded(
+			"sin 1E22°",
+			&angle.angle_to_radians(AngleUnit::Degrees).sin(),
+			"-9.848077530122080593667430245895230136706E-1",
+		);
+		let angle = Number::Decimal(Decimal::from_str("123456789.5"));
+		assert_correctly_rounded(
+			"sin 123456789.5°",
+			&angle.angle_to_radians(AngleUnit::Degrees).sin(),
+			"-1.650476058606776483826337007884420974636E-1",
+		);
+		let angle = 1.to_number() / 3.to_number();
+		assert_correctly_rounded(
+			"cos 1/3°",
+			&angle.angle_to_radians(AngleUnit::Degrees).cos(),
+			"9.999830768577441888991732099403791605712E-1",
+		);
+	}
+
+	#[test]
+	fn square_roots_are_found_in_radical_form() {
+		let root = |value: u32| Number::Decimal(Decimal::from(value).sqrt());
+		assert_eq!(root(8).radical_form(), Some((2, 2, 1)));
+		assert_eq!(root(2).radical_form(), Some((1, 2, 1)));
+		assert_eq!(root(999_999).radical_form(), Some((3, 111_111, 1)));
+		let product = &Number::from(3) * &root(2);
+		assert_eq!(product.radical_form(), Some((3, 2, 1)));
+		assert_eq!((-root(12)).radical_form(), Some((-2, 3, 1)));
+
+		// Roots divided by an integer are kept in lowest terms
+		let quotient = |value: u32, divisor: u32| {
+			Number::Decimal(&Decimal::from(value).sqrt() / &Decimal::from(divisor))
+		};
+		assert_eq!(quotient(2, 2).radical_form(), Some((1, 2, 2)));
+		assert_eq!(quotient(3, 3).radical_form(), Some((1, 3, 3)));
+		assert_eq!(quotient(8, 6).radical_form(), Some((1, 2, 3)));
+		assert_eq!(quotient(50, 3).radical_form(), Some((5, 2, 3)));
+
+		// Perfect squares and values that only start like a root are left alone
+		assert_eq!(root(16).radical_form(), None);
+		let near = Number::Decimal(Decimal::from_str("1.41421356237"));
+		assert_eq!(near.radical_form(), None);
+		assert_eq!(Number::from(8).radical_form(), None);
+	}
+}
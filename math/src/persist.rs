@@ -0,0 +1,119 @@
+use crate::error::{Error, Result};
+use crate::storage::{DeserializeInput, SerializeOutput};
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Saved state starts with this magic value followed by the format version
+const STATE_MAGIC: [u8; 4] = *b"RSCS";
+
+/// Version of the saved state format. This only changes when the header or the
+/// record layout changes in a way that older versions can't skip over. Adding
+/// records, or adding fields to the end of a record, does not change it.
+pub const STATE_VERSION: u16 = 1;
+
+/// Writes saved state. The state is a header followed by a sequence of records,
+/// each with a 16-bit tag and a 32-bit length. Readers skip records they don't
+/// know and ignore fields past the ones they know at the end of a record, so
+/// state saved by one version can be restored by another.
+///
+/// Tags below 0x100 are used by the math library, the rest are used by the
+/// calculator.
+pub struct StateWriter {
+	data: Vec<u8>,
+}
+
+/// Reads records from saved state written by `StateWriter`
+pub struct StateReader<'a> {
+	input: DeserializeInput<'a>,
+}
+
+impl StateWriter {
+	pub fn new() -> Self {
+		let mut data = Vec::new();
+		data.extend_from_slice(&STATE_MAGIC);
+		data.extend_from_slice(&STATE_VERSION.to_le_bytes());
+		StateWriter { data }
+	}
+
+	/// Adds a record with the contents written by `func`
+	pub fn record<F: FnOnce(&mut Vec<u8>) -> Result<()>>(
+		&mut self,
+		tag: u16,
+		func: F,
+	) -> Result<()> {
+		let mut contents = Vec::new();
+		func(&mut contents)?;
+		self.data.write_u16(tag)?;
+		self.data.write_u32(contents.len() as u32)?;
+		self.data.write(&contents)
+	}
+
+	pub fn finish(self) -> Vec<u8> {
+		self.data
+	}
+}
+
+impl Default for StateWriter {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<'a> StateReader<'a> {
+	/// Checks the header of the saved state. Fails if the data is not saved state
+	/// or was written with a newer, incompatible format.
+	pub fn new(data: &'a [u8]) -> Result<Self> {
+		let mut input = DeserializeInput::new(data);
+		if input.read_slice(STATE_MAGIC.len())? != STATE_MAGIC {
+			return Err(Error::CorruptData);
+		}
+		if input.read_u16()? > STATE_VERSION {
+			return Err(Error::UnsupportedVersion);
+		}
+		Ok(StateReader { input })
+	}
+
+	/// Returns the tag and contents of the next record, or `None` at the end of
+	/// the saved state
+	pub fn next_record(&mut self) -> Result<Option<(u16, DeserializeInput<'a>)>> {
+		if self.input.remaining() == 0 {
+			return Ok(None);
+		}
+		let tag = self.input.read_u16()?;
+		let len = self.input.read_u32()? as usize;
+		let contents = self.input.read_slice(len)?;
+		Ok(Some((tag, DeserializeInput::new(contents))))
+	}
+}
+
+/// Writes an item with a length prefix, so that readers that don't understand
+/// the item can skip it
+pub fn write_item<F: FnOnce(&mut Vec<u8>) -> Result<()>>(
+	output: &mut Vec<u8>,
+	func: F,
+) -> Result<()> {
+	let mut contents = Vec::new();
+	func(&mut contents)?;
+	output.write_u32(contents.len() as u32)?;
+	output.write(&contents)
+}
+
+/// Reads an item written by `write_item`
+pub fn read_item<'a>(input: &mut DeserializeInput<'a>) -> Result<DeserializeInput<'a>> {
+	let len = input.read_u32()? as usize;
+	Ok(DeserializeInput::new(input.read_slice(len)?))
+}
+
+pub fn write_string(output: &mut Vec<u8>, value: &str) -> Result<()> {
+	output.write_u32(value.len() as u32)?;
+	output.write(value.as_bytes())
+}
+
+pub fn read_string(input: &mut DeserializeInput) -> Result<String> {
+	let len = input.read_u32()? as usize;
+	let data = input.read_slice(len)?;
+	String::from_utf8(data.to_vec()).map_err(|_| Error::CorruptData)
+}
@@ -0,0 +1,484 @@
+use crate::error::{Error, Result};
+use crate::number::Number;
+use crate::script::computation_yield;
+use core::convert::TryFrom;
+use intel_dfp::Decimal;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Largest argument of the prime counting function, and largest result of the
+/// nth prime function. Counting primes takes time that grows with about the two
+/// thirds power of the argument, which is kept to seconds on the calculator.
+pub const MAX_PRIME: u64 = 100_000_000_000;
+
+/// Primes below this are found with a single sieve and kept in a table. Its cube
+/// is larger than `MAX_PRIME`, so the table holds all of the primes used by
+/// Meissel's method.
+const SMALL_LIMIT: u64 = 1 << 15;
+
+/// Words of a sieve segment. Each bit is one number, so a segment covers 32768
+/// numbers in 4 KB.
+const SEGMENT_WORDS: usize = 512;
+const SEGMENT_SIZE: u64 = 64 * SEGMENT_WORDS as u64;
+
+/// Number of the smallest primes whose partial sieve function is read from a
+/// table of one period, which is the product of the primes
+const PHI_TABLE_PRIMES: usize = 5;
+const PHI_TABLE_PERIOD: u64 = 2 * 3 * 5 * 7 * 11;
+
+/// Calls of the partial sieve function between checks for an interruption
+const PHI_YIELD_INTERVAL: usize = 4096;
+
+fn isqrt(x: u64) -> u64 {
+	if x < 2 {
+		return x;
+	}
+	let mut result = x;
+	let mut next = (x / 2 + 1).min(1 << 32);
+	while next < result {
+		result = next;
+		next = (result + x / result) / 2;
+	}
+	result
+}
+
+fn icbrt(x: u64) -> u64 {
+	let mut result = 0;
+	for bit in (0..21).rev() {
+		let candidate = result | (1 << bit);
+		if candidate * candidate * candidate <= x {
+			result = candidate;
+		}
+	}
+	result
+}
+
+/// Primes of the small table, along with the counts of the partial sieve
+/// function for the first few primes over one period
+struct PrimeTables {
+	primes: Vec<u16>,
+	phi_counts: Vec<u16>,
+}
+
+impl PrimeTables {
+	fn new() -> Self {
+		let mut composite = Vec::new();
+		composite.resize(SMALL_LIMIT as usize, false);
+		let mut primes = Vec::new();
+		for n in 2..SMALL_LIMIT as usize {
+			if !composite[n] {
+				primes.push(n as u16);
+				for multiple in (n * n..SMALL_LIMIT as usize).step_by(n) {
+					composite[multiple] = true;
+				}
+			}
+		}
+
+		// Numbers up to each point of the period that have none of the first
+		// primes as a factor
+		let mut phi_counts = Vec::new();
+		let mut count = 0;
+		phi_counts.push(0);
+		for n in 1..=PHI_TABLE_PERIOD {
+			if primes[..PHI_TABLE_PRIMES]
+				.iter()
+				.all(|prime| n % *prime as u64 != 0)
+			{
+				count += 1;
+			}
+			phi_counts.push(count);
+		}
+		PrimeTables { primes, phi_counts }
+	}
+
+	fn prime(&self, idx: usize) -> u64 {
+		self.primes[idx] as u64
+	}
+
+	/// Number of primes up to `x`, which must be below `SMALL_LIMIT`
+	fn small_count(&self, x: u64) -> u64 {
+		self.primes.partition_point(|prime| *prime as u64 <= x) as u64
+	}
+
+	/// Calls `func` with each prime up to `limit` in increasing order. Primes
+	/// beyond the table are found by sieving segments.
+	fn for_each_prime(&self, limit: u64, func: &mut dyn FnMut(u64) -> Result<()>) -> Result<()> {
+		for prime in &self.primes {
+			if *prime as u64 > limit {
+				return Ok(());
+			}
+			func(*prime as u64)?;
+		}
+
+		let mut segment = Segment::new();
+		let mut low = SMALL_LIMIT;
+		while low <= limit {
+			segment.sieve(self, low)?;
+			for prime in segment.primes() {
+				if prime > limit {
+					return Ok(());
+				}
+				func(prime)?;
+			}
+			low += SEGMENT_SIZE;
+		}
+		Ok(())
+	}
+
+	/// Legendre's partial sieve function φ(y, c), the count of numbers up to `y`
+	/// that have none of the first `c` primes as a factor. The recursion
+	/// φ(y, c) = φ(y, c - 1) - φ(y / p_c, c - 1) is unrolled along its first term
+	/// so that its depth stays small, and is cut short where the count is known.
+	/// `c` is never below `PHI_TABLE_PRIMES`.
+	fn phi(&self, y: u64, c: usize, calls: &mut usize) -> Result<u64> {
+		*calls += 1;
+		if calls.is_multiple_of(PHI_YIELD_INTERVAL) {
+			computation_yield()?;
+		}
+		if c <= PHI_TABLE_PRIMES {
+			return Ok(self.phi_from_table(y));
+		}
+		if y <= self.prime(c - 1) {
+			return Ok(if y == 0 { 0 } else { 1 });
+		}
+
+		// Below the square of the next prime, only the primes after the first
+		// `c` are left unsieved along with one
+		let next = self.prime(c);
+		if y < SMALL_LIMIT && y < next * next {
+			return Ok(self.small_count(y) - c as u64 + 1);
+		}
+
+		let mut result = self.phi_from_table(y);
+		for j in PHI_TABLE_PRIMES + 1..=c {
+			let quotient = y / self.prime(j - 1);
+			if quotient <= self.prime(j - 2) {
+				// All of the remaining terms count only the number one
+				result -= (c - j + 1) as u64;
+				break;
+			}
+			result -= self.phi(quotient, j - 1, calls)?;
+		}
+		Ok(result)
+	}
+
+	fn phi_from_table(&self, y: u64) -> u64 {
+		let period_count = self.phi_counts[PHI_TABLE_PERIOD as usize] as u64;
+		(y / PHI_TABLE_PERIOD) * period_count
+			+ self.phi_counts[(y % PHI_TABLE_PERIOD) as usize] as u64
+	}
+}
+
+/// Segment of a sieve of Eratosthenes, where set bits mark the primes among
+/// the numbers from `low`
+struct Segment {
+	low: u64,
+	bits: Vec<u64>,
+}
+
+impl Segment {
+	fn new() -> Self {
+		let bits = vec![0; SEGMENT_WORDS];
+		Segment { low: 0, bits }
+	}
+
+	fn sieve(&mut self, tables: &PrimeTables, low: u64) -> Result<()> {
+		computation_yield()?;
+		self.low = low;
+		for word in self.bits.iter_mut() {
+			*word = !0;
+		}
+		for n in low..2.min(low + SEGMENT_SIZE) {
+			self.clear(n);
+		}
+
+		let high = low + SEGMENT_SIZE - 1;
+		let bits = &mut self.bits;
+		tables.for_each_prime(isqrt(high), &mut |prime| {
+			let first = low.div_ceil(prime).max(prime) * prime;
+			let mut multiple = first;
+			while multiple <= high {
+				let offset = (multiple - low) as usize;
+				bits[offset / 64] &= !(1 << (offset % 64));
+				multiple += prime;
+			}
+			Ok(())
+		})
+	}
+
+	fn clear(&mut self, n: u64) {
+		let offset = (n - self.low) as usize;
+		self.bits[offset / 64] &= !(1 << (offset % 64));
+	}
+
+	/// Primes of the segment in increasing order
+	fn primes<'a>(&'a self) -> impl Iterator<Item = u64> + 'a {
+		let low = self.low;
+		self.bits.iter().enumerate().flat_map(move |(idx, word)| {
+			let mut word = *word;
+			core::iter::from_fn(move || {
+				if word == 0 {
+					return None;
+				}
+				let bit = word.trailing_zeros() as u64;
+				word &= word - 1;
+				Some(low + idx as u64 * 64 + bit)
+			})
+		})
+	}
+
+	/// Primes of the segment in decreasing order
+	fn primes_rev<'a>(&'a self) -> impl Iterator<Item = u64> + 'a {
+		let low = self.low;
+		self.bits
+			.iter()
+			.enumerate()
+			.rev()
+			.flat_map(move |(idx, word)| {
+				let mut word = *word;
+				core::iter::from_fn(move || {
+					if word == 0 {
+						return None;
+					}
+					let bit = 63 - word.leading_zeros() as u64;
+					word &= !(1 << bit);
+					Some(low + idx as u64 * 64 + bit)
+				})
+			})
+	}
+
+	/// Number of primes in the segment up to `n`
+	fn count_to(&self, n: u64) -> u64 {
+		let offset = (n - self.low) as usize;
+		let mut count = 0;
+		for word in &self.bits[..offset / 64] {
+			count += word.count_ones() as u64;
+		}
+		let mask = !0u64 >> (63 - offset % 64);
+		count + (self.bits[offset / 64] & mask).count_ones() as u64
+	}
+}
+
+/// Counts primes up to numbers given in increasing order by sieving forward
+struct PrimeCounter<'a> {
+	tables: &'a PrimeTables,
+	segment: Segment,
+	/// Primes before the current segment
+	below: u64,
+}
+
+impl<'a> PrimeCounter<'a> {
+	fn new(tables: &'a PrimeTables) -> Result<Self> {
+		let mut segment = Segment::new();
+		segment.sieve(tables, 0)?;
+		Ok(PrimeCounter {
+			tables,
+			segment,
+			below: 0,
+		})
+	}
+
+	fn count_to(&mut self, n: u64) -> Result<u64> {
+		while n >= self.segment.low + SEGMENT_SIZE {
+			self.below += self.segment.count_to(self.segment.low + SEGMENT_SIZE - 1);
+			let next = self.segment.low + SEGMENT_SIZE;
+			self.segment.sieve(self.tables, next)?;
+		}
+		Ok(self.below + self.segment.count_to(n))
+	}
+}
+
+/// Number of primes up to `x`, found with Meissel's method. It sieves only up
+/// to the two thirds power of `x`, one segment at a time, so that it needs
+/// little memory.
+pub fn prime_count(x: u64) -> Result<u64> {
+	if x > MAX_PRIME {
+		return Err(Error::ValueOutOfRange);
+	}
+	let tables = PrimeTables::new();
+	if x < SMALL_LIMIT {
+		return Ok(tables.small_count(x));
+	}
+
+	// π(x) = φ(x, a) + a - 1 - P₂(x, a), where a counts the primes up to the
+	// cube root of x and P₂ counts the numbers up to x with exactly two prime
+	// factors larger than those
+	let a = tables.small_count(icbrt(x)) as usize;
+	let mut calls = 0;
+	let phi = tables.phi(x, a, &mut calls)?;
+
+	// P₂ is the sum of π(x / p) - π(p) + 1 over the primes p between the cube
+	// root and the square root of x. Taking p in decreasing order makes x / p
+	// increase, so both are found with sieves that move in one direction.
+	let root = isqrt(x);
+	let mut counter = PrimeCounter::new(&tables)?;
+	let mut index = counter.count_to(root)?;
+	let mut counter = PrimeCounter::new(&tables)?;
+	let mut second_order = 0;
+	let mut segment = Segment::new();
+	let mut low = root - root % SEGMENT_SIZE;
+	let cube_root = tables.prime(a - 1);
+	'segments: loop {
+		segment.sieve(&tables, low)?;
+		for prime in segment.primes_rev() {
+			if prime > root {
+				continue;
+			}
+			if prime <= cube_root {
+				break 'segments;
+			}
+			second_order += counter.count_to(x / prime)? + 1 - index;
+			index -= 1;
+		}
+		if low == 0 {
+			break;
+		}
+		low -= SEGMENT_SIZE;
+	}
+
+	Ok(phi + a as u64 - 1 - second_order)
+}
+
+/// Estimate of the nth prime from the asymptotic expansion
+/// n (ln n + ln ln n - 1 + (ln ln n - 2) / ln n), which is within a small
+/// fraction of a percent for the sizes used here
+fn nth_prime_estimate(n: u64) -> Result<u64> {
+	let n = Decimal::from(n);
+	let ln_n = n.ln();
+	let ln_ln_n = ln_n.ln();
+	let one = Decimal::from(1);
+	let two = Decimal::from(2);
+	let terms = &(&(&ln_n + &ln_ln_n) - &one) + &(&(&ln_ln_n - &two) / &ln_n);
+	let estimate = Number::Decimal(&n * &terms);
+	u64::try_from(&*estimate.to_int()?).map_err(|_| Error::ValueOutOfRange)
+}
+
+/// The nth prime, counting 2 as the first. The count of primes up to an
+/// estimate is corrected once with the density of primes there, and the
+/// remaining distance is covered by sieving.
+pub fn nth_prime(n: u64) -> Result<u64> {
+	if n == 0 {
+		return Err(Error::ValueOutOfRange);
+	}
+	let tables = PrimeTables::new();
+	if n <= tables.primes.len() as u64 {
+		return Ok(tables.prime(n as usize - 1));
+	}
+
+	let mut estimate = nth_prime_estimate(n)?.min(MAX_PRIME);
+	let mut count = prime_count(estimate)?;
+	if count != n {
+		let gap = Decimal::from(estimate).ln();
+		let difference = n.abs_diff(count);
+		let distance = Number::Decimal(&Decimal::from(difference) * &gap);
+		let distance = u64::try_from(&*distance.to_int()?).map_err(|_| Error::ValueOutOfRange)?;
+		estimate = if n > count {
+			estimate.saturating_add(distance).min(MAX_PRIME)
+		} else {
+			estimate.saturating_sub(distance).max(SMALL_LIMIT)
+		};
+		count = prime_count(estimate)?;
+	}
+
+	let mut segment = Segment::new();
+	let mut low = estimate - estimate % SEGMENT_SIZE;
+	if count >= n {
+		// Count back from the estimate to the nth prime
+		loop {
+			segment.sieve(&tables, low)?;
+			for prime in segment.primes_rev() {
+				if prime > estimate {
+					continue;
+				}
+				if count == n {
+					return Ok(prime);
+				}
+				count -= 1;
+			}
+			low -= SEGMENT_SIZE;
+		}
+	} else {
+		// Count forward from the estimate to the nth prime
+		loop {
+			segment.sieve(&tables, low)?;
+			for prime in segment.primes() {
+				if prime <= estimate {
+					continue;
+				}
+				if prime > MAX_PRIME {
+					return Err(Error::ValueOutOfRange);
+				}
+				count += 1;
+				if count == n {
+					return Ok(prime);
+				}
+			}
+			low += SEGMENT_SIZE;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{nth_prime, prime_count, MAX_PRIME, SEGMENT_SIZE, SMALL_LIMIT};
+
+	/// Limit of the plain sieve that the results are checked against. It reaches
+	/// past the small table and several sieve segments.
+	const SIEVE_LIMIT: usize = 2_000_000;
+
+	fn sieve() -> Vec<u64> {
+		let mut composite = vec![false; SIEVE_LIMIT + 1];
+		let mut primes = Vec::new();
+		for n in 2..=SIEVE_LIMIT {
+			if !composite[n] {
+				primes.push(n as u64);
+				for multiple in (n * n..=SIEVE_LIMIT).step_by(n) {
+					composite[multiple] = true;
+				}
+			}
+		}
+		primes
+	}
+
+	#[test]
+	fn prime_count_matches_sieve() {
+		let primes = sieve();
+		let mut points = vec![0, 1, 2, 3, 4, 100, SMALL_LIMIT - 1, SMALL_LIMIT];
+		points.extend((1..=SIEVE_LIMIT as u64 / SEGMENT_SIZE).map(|n| n * SEGMENT_SIZE));
+		points.extend(primes.iter().step_by(7919).flat_map(|p| [*p - 1, *p]));
+		points.push(SIEVE_LIMIT as u64);
+		for x in points {
+			let expected = primes.partition_point(|p| *p <= x) as u64;
+			assert_eq!(prime_count(x), Ok(expected), "π({})", x);
+		}
+	}
+
+	#[test]
+	fn nth_prime_matches_sieve() {
+		let primes = sieve();
+		let mut indices = vec![1, 2, 3, 3512, 3513, 3514];
+		indices.extend((1..primes.len()).step_by(4999));
+		indices.push(primes.len());
+		for n in indices {
+			assert_eq!(nth_prime(n as u64), Ok(primes[n - 1]), "p({})", n);
+		}
+	}
+
+	#[test]
+	fn prime_count_matches_known_values() {
+		assert_eq!(prime_count(1_000_000_000), Ok(50_847_534));
+		assert_eq!(prime_count(10_000_000_000), Ok(455_052_511));
+		assert_eq!(prime_count(MAX_PRIME), Ok(4_118_054_813));
+		assert!(prime_count(MAX_PRIME + 1).is_err());
+	}
+
+	#[test]
+	fn nth_prime_matches_known_values() {
+		assert_eq!(nth_prime(1_000_000), Ok(15_485_863));
+		assert_eq!(nth_prime(100_000_000), Ok(2_038_074_743));
+		assert!(nth_prime(0).is_err());
+	}
+}
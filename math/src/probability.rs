@@ -0,0 +1,854 @@
+use crate::error::{Error, Result};
+use intel_dfp::Decimal;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Maximum number of terms taken from a series or continued fraction. The
+/// iterations stop earlier once another term no longer changes the result.
+const MAX_TERMS: usize = 2000;
+
+/// Maximum number of steps when solving for the inverse of a distribution
+const MAX_SOLVER_STEPS: usize = 200;
+
+/// Maximum number of times the search range of an inverse is doubled
+const MAX_RANGE_DOUBLINGS: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DistributionKind {
+	Normal,
+	StudentT,
+	ChiSquare,
+	F,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DistributionFunction {
+	Pdf,
+	Cdf,
+	InverseCdf,
+}
+
+/// Continuous probability distribution with its parameters
+pub enum Distribution {
+	/// Standard normal distribution
+	Normal,
+	/// Student's t distribution with the given degrees of freedom
+	StudentT(Decimal),
+	/// Chi-square distribution with the given degrees of freedom
+	ChiSquare(Decimal),
+	/// F distribution with the degrees of freedom of the numerator and the
+	/// denominator
+	F(Decimal, Decimal),
+}
+
+impl DistributionKind {
+	/// Number of parameters the distribution takes from the stack
+	pub fn parameter_count(&self) -> usize {
+		match self {
+			DistributionKind::Normal => 0,
+			DistributionKind::StudentT | DistributionKind::ChiSquare => 1,
+			DistributionKind::F => 2,
+		}
+	}
+
+	pub fn to_u8(&self) -> u8 {
+		match self {
+			DistributionKind::Normal => 0,
+			DistributionKind::StudentT => 1,
+			DistributionKind::ChiSquare => 2,
+			DistributionKind::F => 3,
+		}
+	}
+
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(DistributionKind::Normal),
+			1 => Some(DistributionKind::StudentT),
+			2 => Some(DistributionKind::ChiSquare),
+			3 => Some(DistributionKind::F),
+			_ => None,
+		}
+	}
+}
+
+impl DistributionFunction {
+	pub fn to_u8(&self) -> u8 {
+		match self {
+			DistributionFunction::Pdf => 0,
+			DistributionFunction::Cdf => 1,
+			DistributionFunction::InverseCdf => 2,
+		}
+	}
+
+	pub fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(DistributionFunction::Pdf),
+			1 => Some(DistributionFunction::Cdf),
+			2 => Some(DistributionFunction::InverseCdf),
+			_ => None,
+		}
+	}
+}
+
+impl Distribution {
+	/// Creates a distribution from its parameters, which must number
+	/// `kind.parameter_count()`. Degrees of freedom must be positive.
+	pub fn new(kind: DistributionKind, params: Vec<Decimal>) -> Result<Self> {
+		if params.len() != kind.parameter_count() {
+			return Err(Error::InvalidArgument);
+		}
+		for param in &params {
+			if !param.is_finite() || *param <= Decimal::zero() {
+				return Err(Error::InvalidArgument);
+			}
+		}
+		let mut params = params.into_iter();
+		Ok(match kind {
+			DistributionKind::Normal => Distribution::Normal,
+			DistributionKind::StudentT => Distribution::StudentT(params.next().unwrap()),
+			DistributionKind::ChiSquare => Distribution::ChiSquare(params.next().unwrap()),
+			DistributionKind::F => {
+				let numerator = params.next().unwrap();
+				Distribution::F(numerator, params.next().unwrap())
+			}
+		})
+	}
+
+	pub fn evaluate(&self, func: DistributionFunction, x: &Decimal) -> Result<Decimal> {
+		if x.is_nan() {
+			return Err(Error::InvalidArgument);
+		}
+		match func {
+			DistributionFunction::Pdf => self.pdf(x),
+			DistributionFunction::Cdf => self.cdf(x),
+			DistributionFunction::InverseCdf => self.inverse_cdf(x),
+		}
+	}
+
+	/// Probability density at `x`
+	pub fn pdf(&self, x: &Decimal) -> Result<Decimal> {
+		let two: Decimal = 2.into();
+		match self {
+			Distribution::Normal => {
+				let exponent = -(x * x) / two.clone();
+				Ok(exponent.exp() / (Decimal::pi() * two).sqrt())
+			}
+			Distribution::StudentT(dof) => {
+				let half_dof = dof / &two;
+				let half_next = (dof + &1.into()) / two;
+				let log_density =
+					half_next.lgamma() - half_dof.lgamma() - &half_next * &(&(x * x) / dof).ln_1p();
+				Ok(log_density.exp() / (dof * &Decimal::pi()).sqrt())
+			}
+			Distribution::ChiSquare(dof) => {
+				if *x < Decimal::zero() {
+					return Ok(Decimal::zero());
+				}
+				if *x == Decimal::zero() {
+					return Self::density_at_zero(dof);
+				}
+				let half_dof = dof / &two;
+				let log_density = (&half_dof - &1.into()) * x.ln()
+					- x / &two - &half_dof * &two.ln()
+					- half_dof.lgamma();
+				Ok(log_density.exp())
+			}
+			Distribution::F(numerator, denominator) => {
+				if *x < Decimal::zero() {
+					return Ok(Decimal::zero());
+				}
+				if *x == Decimal::zero() {
+					return Self::density_at_zero(numerator).map(|density| {
+						// Only a numerator of two degrees of freedom has a finite value
+						// that isn't zero, which is one
+						if density == Decimal::zero() {
+							density
+						} else {
+							1.into()
+						}
+					});
+				}
+				let half_num = numerator / &two;
+				let half_denom = denominator / &two;
+				let log_density = &half_num * &(numerator / denominator).ln()
+					+ (&half_num - &1.into()) * x.ln()
+					- (&half_num + &half_denom) * (&(numerator * x) / denominator).ln_1p()
+					- Self::ln_beta(&half_num, &half_denom);
+				Ok(log_density.exp())
+			}
+		}
+	}
+
+	/// Cumulative probability of a value less than or equal to `x`
+	pub fn cdf(&self, x: &Decimal) -> Result<Decimal> {
+		let two: Decimal = 2.into();
+		match self {
+			Distribution::Normal => {
+				let scaled = -x / two.sqrt();
+				Ok(scaled.erfc() / two)
+			}
+			Distribution::StudentT(dof) => {
+				if x.is_infinite() {
+					return Ok(if x.is_sign_negative() {
+						Decimal::zero()
+					} else {
+						1.into()
+					});
+				}
+				let square = x * x;
+				let total = dof + &square;
+				let tail = incomplete_beta(
+					&(dof / &total),
+					&(&square / &total),
+					&(dof / &two),
+					&(&1.into() / &two),
+				)? / two;
+				if x.is_sign_negative() {
+					Ok(tail)
+				} else {
+					Ok(Decimal::from(1) - tail)
+				}
+			}
+			Distribution::ChiSquare(dof) => {
+				if *x <= Decimal::zero() {
+					return Ok(Decimal::zero());
+				}
+				incomplete_gamma(&(dof / &two), &(x / &two))
+			}
+			Distribution::F(numerator, denominator) => {
+				if *x <= Decimal::zero() {
+					return Ok(Decimal::zero());
+				}
+				let scaled = numerator * x;
+				let total = &scaled + denominator;
+				incomplete_beta(
+					&(&scaled / &total),
+					&(denominator / &total),
+					&(numerator / &two),
+					&(denominator / &two),
+				)
+			}
+		}
+	}
+
+	/// Value that has the cumulative probability `p`, which must be between
+	/// zero and one
+	pub fn inverse_cdf(&self, p: &Decimal) -> Result<Decimal> {
+		if *p <= Decimal::zero() || *p >= 1.into() {
+			return Err(Error::InvalidArgument);
+		}
+
+		// Find a range that holds the result, doubling it until it does. The
+		// normal and t distributions cover negative values too.
+		let two: Decimal = 2.into();
+		let (mut low, mut high) = match self {
+			Distribution::Normal | Distribution::StudentT(_) => {
+				(Decimal::from(-1), Decimal::from(1))
+			}
+			Distribution::ChiSquare(_) | Distribution::F(_, _) => {
+				(Decimal::zero(), Decimal::from(1))
+			}
+		};
+		let mut doublings = 0;
+		while low < Decimal::zero() && self.cdf(&low)? >= *p {
+			high = low.clone();
+			low *= two.clone();
+			doublings += 1;
+			if doublings > MAX_RANGE_DOUBLINGS {
+				return Err(Error::InvalidArgument);
+			}
+		}
+		while self.cdf(&high)? <= *p {
+			low = high.clone();
+			high *= two.clone();
+			doublings += 1;
+			if doublings > MAX_RANGE_DOUBLINGS {
+				return Err(Error::InvalidArgument);
+			}
+		}
+
+		// Newton's method, falling back to bisection whenever a step would leave
+		// the range or shrinks by less than half over two steps, which happens
+		// where the density is far from constant. The search stops once the range
+		// can't be split any further.
+		let mut x = (&low + &high) / two.clone();
+		let mut step = &high - &low;
+		let mut last_step = step.clone();
+		for _ in 0..MAX_SOLVER_STEPS {
+			let error = self.cdf(&x)? - p.clone();
+			if error == Decimal::zero() {
+				break;
+			}
+			if error < Decimal::zero() {
+				low = x.clone();
+			} else {
+				high = x.clone();
+			}
+
+			let density = self.pdf(&x)?;
+			let newton = if density > Decimal::zero() && density.is_finite() {
+				Some(error / density)
+			} else {
+				None
+			};
+			let next = match newton {
+				Some(newton) if newton.abs() * two.clone() <= last_step => {
+					let next = &x - &newton;
+					if next > low && next < high {
+						last_step = core::mem::replace(&mut step, newton.abs());
+						Some(next)
+					} else {
+						None
+					}
+				}
+				_ => None,
+			};
+			let next = match next {
+				Some(next) => next,
+				None => {
+					last_step = core::mem::replace(&mut step, (&high - &low) / two.clone());
+					(&low + &high) / two.clone()
+				}
+			};
+			if next == x || next <= low || next >= high {
+				break;
+			}
+			x = next;
+		}
+		Ok(x)
+	}
+
+	/// Density at zero of a distribution that behaves like a chi-square
+	/// distribution with the given degrees of freedom near zero
+	fn density_at_zero(dof: &Decimal) -> Result<Decimal> {
+		let two: Decimal = 2.into();
+		if *dof < two {
+			Err(Error::InvalidArgument)
+		} else if *dof == two {
+			Ok(Decimal::from(1) / two)
+		} else {
+			Ok(Decimal::zero())
+		}
+	}
+
+	fn ln_beta(a: &Decimal, b: &Decimal) -> Decimal {
+		a.lgamma() + b.lgamma() - (a + b).lgamma()
+	}
+}
+
+/// Smallest magnitude used in place of zero in continued fractions
+fn tiny() -> Decimal {
+	Decimal::from_str("1E-300")
+}
+
+/// Keeps a denominator of a continued fraction away from zero
+fn lentz_clamp(value: Decimal) -> Decimal {
+	if value.abs() < tiny() {
+		tiny()
+	} else {
+		value
+	}
+}
+
+/// Regularized lower incomplete gamma function P(a, x)
+fn incomplete_gamma(a: &Decimal, x: &Decimal) -> Result<Decimal> {
+	let one: Decimal = 1.into();
+	let prefix = (x.ln() * a.clone() - x.clone() - a.lgamma()).exp();
+
+	if *x < a + &one {
+		// Series, which converges quickly below the peak of the distribution
+		let mut term = &one / a;
+		let mut sum = term.clone();
+		let mut n = a.clone();
+		for _ in 0..MAX_TERMS {
+			n += one.clone();
+			term *= x / &n;
+			let next = &sum + &term;
+			if next == sum {
+				break;
+			}
+			sum = next;
+		}
+		Ok(sum * prefix)
+	} else {
+		// Continued fraction for the upper tail, evaluated with Lentz's method
+		let two: Decimal = 2.into();
+		let mut b = x + &one - a.clone();
+		let mut c = &one / &tiny();
+		let mut d = &one / &b;
+		let mut result = d.clone();
+		let mut i = Decimal::zero();
+		for _ in 0..MAX_TERMS {
+			i += one.clone();
+			let an = -(&i * &(&i - a));
+			b += two.clone();
+			d = lentz_clamp(&an * &d + b.clone());
+			c = lentz_clamp(&b + &(&an / &c));
+			d = &one / &d;
+			let next = &result * &(&d * &c);
+			if next == result {
+				break;
+			}
+			result = next;
+		}
+		Ok(one - result * prefix)
+	}
+}
+
+/// Regularized incomplete beta function I_x(a, b). The complement `1 - x` is
+/// passed separately so that it keeps its precision when x is close to one.
+fn incomplete_beta(x: &Decimal, complement: &Decimal, a: &Decimal, b: &Decimal) -> Result<Decimal> {
+	let one: Decimal = 1.into();
+	if *x <= Decimal::zero() {
+		return Ok(Decimal::zero());
+	}
+	if *complement <= Decimal::zero() {
+		return Ok(one);
+	}
+
+	let prefix = (a * &x.ln() + b * &complement.ln() - Distribution::ln_beta(a, b)).exp();
+	let two: Decimal = 2.into();
+	if *x < (a + &one) / (a + &(b + &two)) {
+		Ok(prefix * beta_fraction(x, a, b) / a.clone())
+	} else {
+		Ok(one - prefix * beta_fraction(complement, b, a) / b.clone())
+	}
+}
+
+/// Continued fraction of the incomplete beta function, evaluated with Lentz's
+/// method
+fn beta_fraction(x: &Decimal, a: &Decimal, b: &Decimal) -> Decimal {
+	let one: Decimal = 1.into();
+	let sum = a + b;
+	let a_next = a + &one;
+	let a_prev = a - &one;
+	let mut c = one.clone();
+	let mut d = lentz_clamp(&one - &(&(&sum * x) / &a_next));
+	d = &one / &d;
+	let mut result = d.clone();
+	let two: Decimal = 2.into();
+	let mut m = Decimal::zero();
+	for _ in 0..MAX_TERMS {
+		m += one.clone();
+		let twice = &m * &two;
+
+		// Even step
+		let numerator = &(&m * &(b - &m)) * x / (&a_prev + &twice) / (a + &twice);
+		d = lentz_clamp(&one + &(&numerator * &d));
+		c = lentz_clamp(&one + &(&numerator / &c));
+		d = &one / &d;
+		result = &result * &(&d * &c);
+
+		// Odd step
+		let numerator = -(&(a + &m) * &(&sum + &m)) * x.clone() / (a + &twice) / (&a_next + &twice);
+		d = lentz_clamp(&one + &(&numerator * &d));
+		c = lentz_clamp(&one + &(&numerator / &c));
+		d = &one / &d;
+		let next = &result * &(&d * &c);
+		if next == result {
+			break;
+		}
+		result = next;
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Distribution, DistributionFunction, DistributionKind};
+	use intel_dfp::Decimal;
+
+	/// Digits at the end of the decimal backend that can be off in the results.
+	/// Series and continued fractions stop once another term no longer changes the
+	/// result, so the rounding of each term adds up.
+	const INEXACT_DIGITS: i32 = 4;
+
+	/// Reference values computed with mpmath at 60 digits. The inverses were found
+	/// by bisection on the mpmath CDF.
+	#[test]
+	fn distributions_match_reference_values() {
+		let cases: [(DistributionKind, &[&str], DistributionFunction, &str, &str); 50] = [
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Pdf,
+				"0",
+				"3.989422804014326779399460599343818684759E-1",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Pdf,
+				"1.5",
+				"1.295175956658917276140995579547414911838E-1",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Pdf,
+				"-8",
+				"5.05227108353689228795018461551083466035E-15",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Cdf,
+				"0",
+				"5.0E-1",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Cdf,
+				"1.96",
+				"9.750021048517795658634157309591628099775E-1",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Cdf,
+				"-1.96",
+				"2.49978951482204341365842690408371900225E-2",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Cdf,
+				"-10",
+				"7.619853024160526065973343251599308363504E-24",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::Cdf,
+				"5",
+				"9.999997133484281208060883262476671253546E-1",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::InverseCdf,
+				"0.975",
+				"1.959963984540054235524594430520551527956",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::InverseCdf,
+				"1E-10",
+				"-6.361340902404056204695375828265221679204",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::InverseCdf,
+				"0.999",
+				"3.090232306167813541540399830107379205491",
+			),
+			(
+				DistributionKind::Normal,
+				&[],
+				DistributionFunction::InverseCdf,
+				"0.3",
+				"-5.244005127080407840382893250251225543254E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["1"],
+				DistributionFunction::Pdf,
+				"0",
+				"3.183098861837906715377675267450287240689E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["1"],
+				DistributionFunction::Pdf,
+				"2",
+				"6.366197723675813430755350534900574481378E-2",
+			),
+			(
+				DistributionKind::StudentT,
+				&["1"],
+				DistributionFunction::Cdf,
+				"-3",
+				"1.024163823495667258245989237752594740489E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["1"],
+				DistributionFunction::Cdf,
+				"100",
+				"9.968170072350917448508809862275167670899E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["1"],
+				DistributionFunction::InverseCdf,
+				"0.975",
+				"1.270620473617470464602167997884208746767E+1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["0.5"],
+				DistributionFunction::Cdf,
+				"2",
+				"7.772425549084343844067012766743931204761E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["0.5"],
+				DistributionFunction::Pdf,
+				"-1",
+				"1.183046570403926123438525567252248031472E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["0.5"],
+				DistributionFunction::InverseCdf,
+				"0.9",
+				"1.027032441023450614966686449646933682789E+1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["3"],
+				DistributionFunction::Cdf,
+				"2.353",
+				"9.499835058199950779267681105407930412113E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["3"],
+				DistributionFunction::InverseCdf,
+				"0.95",
+				"2.35336343480182387767122397889993260742",
+			),
+			(
+				DistributionKind::StudentT,
+				&["3"],
+				DistributionFunction::Pdf,
+				"-1",
+				"2.06748335783172018566747436867363540524E-1",
+			),
+			(
+				DistributionKind::StudentT,
+				&["30"],
+				DistributionFunction::Cdf,
+				"-4",
+				"1.909228180418784216184514350449330346568E-4",
+			),
+			(
+				DistributionKind::StudentT,
+				&["30"],
+				DistributionFunction::InverseCdf,
+				"1E-6",
+				"-5.871117120418882645581835531944843113414",
+			),
+			(
+				DistributionKind::StudentT,
+				&["30"],
+				DistributionFunction::Pdf,
+				"6",
+				"1.948677908397879716127013301462858221966E-6",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["1"],
+				DistributionFunction::Pdf,
+				"0.5",
+				"4.393912894677223970468619774122289491813E-1",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["1"],
+				DistributionFunction::Cdf,
+				"3.841458820694124",
+				"9.499999999999999416027191347695240392652E-1",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["1"],
+				DistributionFunction::Cdf,
+				"1E-6",
+				"7.978844278221251691806448886538825370065E-4",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["1"],
+				DistributionFunction::InverseCdf,
+				"0.95",
+				"3.841458820694125958361375437362596846213",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["0.5"],
+				DistributionFunction::Cdf,
+				"0.1",
+				"5.165553208304656273912782622259467612898E-1",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["0.5"],
+				DistributionFunction::Pdf,
+				"2",
+				"5.073345595415417902387466760462999132276E-2",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["0.5"],
+				DistributionFunction::InverseCdf,
+				"0.5",
+				"8.73476047057468207338859027212115088585E-2",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["10"],
+				DistributionFunction::Cdf,
+				"30",
+				"9.991433587892246996078892181016219506511E-1",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["10"],
+				DistributionFunction::Pdf,
+				"10",
+				"8.77336848839253528207818805097451074069E-2",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["10"],
+				DistributionFunction::InverseCdf,
+				"0.001",
+				"1.478743463835665033652184432797849729967",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["100"],
+				DistributionFunction::Cdf,
+				"150",
+				"9.99096067957645990914244355225170196215E-1",
+			),
+			(
+				DistributionKind::ChiSquare,
+				&["100"],
+				DistributionFunction::InverseCdf,
+				"0.5",
+				"9.933412923598845575421805917277470784817E+1",
+			),
+			(
+				DistributionKind::F,
+				&["1", "1"],
+				DistributionFunction::Cdf,
+				"1",
+				"5.0E-1",
+			),
+			(
+				DistributionKind::F,
+				&["1", "1"],
+				DistributionFunction::Pdf,
+				"2",
+				"7.502635967975883912959993258389504855538E-2",
+			),
+			(
+				DistributionKind::F,
+				&["1", "1"],
+				DistributionFunction::InverseCdf,
+				"0.9",
+				"3.986345818906140097284937164610780138503E+1",
+			),
+			(
+				DistributionKind::F,
+				&["2", "5"],
+				DistributionFunction::Cdf,
+				"3",
+				"8.607025077555284945771713478437131280808E-1",
+			),
+			(
+				DistributionKind::F,
+				&["2", "5"],
+				DistributionFunction::InverseCdf,
+				"0.95",
+				"5.786135043349967001171450360781604341325",
+			),
+			(
+				DistributionKind::F,
+				&["2", "5"],
+				DistributionFunction::Pdf,
+				"0.5",
+				"5.282817877171741063435279540902798359884E-1",
+			),
+			(
+				DistributionKind::F,
+				&["5", "2"],
+				DistributionFunction::Cdf,
+				"19.3",
+				"9.50008972606925682558058822208710879821E-1",
+			),
+			(
+				DistributionKind::F,
+				&["10", "20"],
+				DistributionFunction::Pdf,
+				"1",
+				"7.143568496192776216334805152754840490638E-1",
+			),
+			(
+				DistributionKind::F,
+				&["10", "20"],
+				DistributionFunction::Cdf,
+				"0.1",
+				"3.410973589131101174973884494187885303203E-4",
+			),
+			(
+				DistributionKind::F,
+				&["10", "20"],
+				DistributionFunction::InverseCdf,
+				"0.99",
+				"3.368186389188742682267620446299927664648",
+			),
+			(
+				DistributionKind::F,
+				&["0.5", "3"],
+				DistributionFunction::Cdf,
+				"0.2",
+				"4.833136135937381981880493956714190531289E-1",
+			),
+			(
+				DistributionKind::F,
+				&["0.5", "3"],
+				DistributionFunction::Pdf,
+				"0.2",
+				"5.770161455335270582771150897239765392076E-1",
+			),
+		];
+		let tolerance = Decimal::from(INEXACT_DIGITS - Decimal::DIGITS as i32).exp10();
+		for (kind, params, func, x, reference) in cases.iter() {
+			let params = params
+				.iter()
+				.map(|param| Decimal::from_str(param))
+				.collect();
+			let distribution = Distribution::new(*kind, params).unwrap();
+			let result = distribution.evaluate(*func, &Decimal::from_str(x)).unwrap();
+			let reference = Decimal::from_str(reference);
+			let error = ((&result - &reference) / reference.clone()).abs();
+			assert!(
+				error <= tolerance,
+				"{} gave {}, expected {}",
+				x,
+				result.to_string(),
+				reference.to_string()
+			);
+		}
+	}
+
+	#[test]
+	fn arguments_outside_the_domain_are_errors() {
+		let zero = Decimal::zero();
+		assert!(Distribution::new(DistributionKind::StudentT, [zero.clone()].to_vec()).is_err());
+		assert!(Distribution::new(DistributionKind::F, [1.into()].to_vec()).is_err());
+		assert!(Distribution::Normal.inverse_cdf(&zero).is_err());
+		assert!(Distribution::Normal.inverse_cdf(&1.into()).is_err());
+		assert!(Distribution::ChiSquare(1.into()).pdf(&zero).is_err());
+		assert!(Distribution::ChiSquare(3.into()).pdf(&zero).unwrap() == zero);
+		assert!(Distribution::ChiSquare(3.into()).cdf(&(-1).into()).unwrap() == zero);
+	}
+}
@@ -0,0 +1,358 @@
+use crate::complex::{ComplexNumber, MAX_COMPLEX_DENOMINATOR_BITS, MAX_COMPLEX_INTEGER_BITS};
+use crate::error::{Error, Result};
+use crate::format::Format;
+use crate::matrix::Matrix;
+use crate::number::{Number, ToNumber};
+use crate::unit::AngleUnit;
+use crate::value::Value;
+use crate::vector::Vector;
+use core::fmt;
+use intel_dfp::Decimal;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Quaternion `w + xi + yj + zk`, used for rotations in three dimensions. The
+/// parts are kept exactly like the parts of a complex number, so products of
+/// quaternions with integer parts stay exact.
+#[derive(Clone)]
+pub struct Quaternion {
+	w: Number,
+	x: Number,
+	y: Number,
+	z: Number,
+}
+
+impl Quaternion {
+	pub fn from_real(w: Number) -> Self {
+		Self::from_parts(w, 0.into(), 0.into(), 0.into())
+	}
+
+	pub fn from_complex(value: &ComplexNumber) -> Self {
+		Self::from_parts(
+			value.real_part().clone(),
+			value.imaginary_part().clone(),
+			0.into(),
+			0.into(),
+		)
+	}
+
+	pub fn from_parts(w: Number, x: Number, y: Number, z: Number) -> Self {
+		Quaternion {
+			w: Self::check_int_bounds(w),
+			x: Self::check_int_bounds(x),
+			y: Self::check_int_bounds(y),
+			z: Self::check_int_bounds(z),
+		}
+	}
+
+	fn check_int_bounds(value: Number) -> Number {
+		Number::check_int_bounds_with_bit_count(
+			value,
+			MAX_COMPLEX_INTEGER_BITS,
+			MAX_COMPLEX_DENOMINATOR_BITS,
+		)
+	}
+
+	/// Fully reduces any rational parts of the quaternion.
+	pub fn reduce(self) -> Self {
+		Quaternion {
+			w: self.w.reduce(),
+			x: self.x.reduce(),
+			y: self.y.reduce(),
+			z: self.z.reduce(),
+		}
+	}
+
+	/// Parts of the quaternion in the order `w`, `x`, `y`, `z`
+	pub fn parts(&self) -> [&Number; 4] {
+		[&self.w, &self.x, &self.y, &self.z]
+	}
+
+	pub fn is_real(&self) -> bool {
+		self.x.is_zero() && self.y.is_zero() && self.z.is_zero()
+	}
+
+	pub fn is_out_of_range(&self) -> bool {
+		self.parts()
+			.iter()
+			.any(|part| part.is_infinite() || part.is_nan())
+	}
+
+	pub fn take_real_part(self) -> Number {
+		self.w
+	}
+
+	pub fn format(&self, format: &Format) -> String {
+		let mut result = format.format_number(&self.w).to_string();
+		for (part, name) in self.parts()[1..].iter().zip(["i", "j", "k"].iter()) {
+			if part.is_negative() {
+				result += " - ";
+				result += format.format_number(&-*part).to_str();
+			} else {
+				result += " + ";
+				result += format.format_number(part).to_str();
+			}
+			result += *name;
+		}
+		result
+	}
+
+	pub fn conjugate(&self) -> Self {
+		Quaternion {
+			w: self.w.clone(),
+			x: -&self.x,
+			y: -&self.y,
+			z: -&self.z,
+		}
+	}
+
+	fn norm_squared(&self) -> Number {
+		&(&(&self.w * &self.w) + &(&self.x * &self.x))
+			+ &(&(&self.y * &self.y) + &(&self.z * &self.z))
+	}
+
+	pub fn norm(&self) -> Number {
+		self.norm_squared().sqrt()
+	}
+
+	pub fn inverse(&self) -> Result<Self> {
+		let norm_squared = self.norm_squared();
+		if norm_squared.is_zero() {
+			return Err(Error::DivideByZero);
+		}
+		let conjugate = self.conjugate();
+		Ok(Self::from_parts(
+			&conjugate.w / &norm_squared,
+			&conjugate.x / &norm_squared,
+			&conjugate.y / &norm_squared,
+			&conjugate.z / &norm_squared,
+		))
+	}
+
+	/// Quaternion of the same direction with a norm of one
+	fn normalize(&self) -> Result<Self> {
+		let norm = self.norm();
+		if norm.is_zero() {
+			return Err(Error::DivideByZero);
+		}
+		Ok(Self::from_parts(
+			&self.w / &norm,
+			&self.x / &norm,
+			&self.y / &norm,
+			&self.z / &norm,
+		))
+	}
+
+	/// Rotation matrix of the rotation given by the quaternion, which is
+	/// normalized first
+	pub fn to_rotation_matrix(&self) -> Result<Matrix> {
+		let norm_squared = self.norm_squared();
+		if norm_squared.is_zero() {
+			return Err(Error::DivideByZero);
+		}
+		let s = &2.to_number() / &norm_squared;
+		let (w, x, y, z) = (&self.w, &self.x, &self.y, &self.z);
+		let one = 1.to_number();
+		let diagonal = |a: &Number, b: &Number| &one - &(&s * &(&(a * a) + &(b * b)));
+		let sum = |a: &Number, b: &Number, c: &Number, d: &Number| &s * &(&(a * b) + &(c * d));
+		let difference =
+			|a: &Number, b: &Number, c: &Number, d: &Number| &s * &(&(a * b) - &(c * d));
+		let elements = [
+			[diagonal(y, z), difference(x, y, w, z), sum(x, z, w, y)],
+			[sum(x, y, w, z), diagonal(x, z), difference(y, z, w, x)],
+			[difference(x, z, w, y), sum(y, z, w, x), diagonal(x, y)],
+		];
+		let mut matrix = Matrix::new(3, 3)?;
+		for (row, cols) in elements.iter().enumerate() {
+			for (col, element) in cols.iter().enumerate() {
+				matrix.set(row, col, Value::Number(element.clone()))?;
+			}
+		}
+		Ok(matrix)
+	}
+
+	/// Unit quaternion of the rotation given by a 3×3 rotation matrix. The
+	/// largest of the parts is found first, which keeps the others accurate.
+	pub fn from_rotation_matrix(matrix: &Matrix) -> Result<Self> {
+		if matrix.rows() != 3 || matrix.cols() != 3 {
+			return Err(Error::DimensionMismatch);
+		}
+		let mut m = Vec::new();
+		for row in 0..3 {
+			let mut cols = Vec::new();
+			for col in 0..3 {
+				cols.push(matrix.get(row, col)?.real_number()?.clone());
+			}
+			m.push(cols);
+		}
+
+		let one = 1.to_number();
+		let half = |value: &Number| value / &2.to_number();
+		let trace = &(&m[0][0] + &m[1][1]) + &m[2][2];
+		let diagonal = [&m[0][0], &m[1][1], &m[2][2]];
+		let largest_diagonal = (0..3)
+			.max_by(|a, b| {
+				diagonal[*a]
+					.to_decimal()
+					.partial_cmp(&diagonal[*b].to_decimal())
+					.unwrap_or(core::cmp::Ordering::Equal)
+			})
+			.unwrap_or(0);
+
+		let result = if *trace.to_decimal() > Decimal::zero() {
+			let r = (&one + &trace).sqrt();
+			let f = &one / &(&r * &2.to_number());
+			Self::from_parts(
+				half(&r),
+				&(&m[2][1] - &m[1][2]) * &f,
+				&(&m[0][2] - &m[2][0]) * &f,
+				&(&m[1][0] - &m[0][1]) * &f,
+			)
+		} else {
+			// One of x, y and z is the largest part, taken from the largest
+			// element of the diagonal
+			let i = largest_diagonal;
+			let j = (i + 1) % 3;
+			let k = (i + 2) % 3;
+			let r = (&(&one + &m[i][i]) - &(&m[j][j] + &m[k][k])).sqrt();
+			let f = &one / &(&r * &2.to_number());
+			let mut vector = [0.to_number(), 0.to_number(), 0.to_number()];
+			vector[i] = half(&r);
+			vector[j] = &(&m[j][i] + &m[i][j]) * &f;
+			vector[k] = &(&m[k][i] + &m[i][k]) * &f;
+			let [x, y, z] = vector;
+			Self::from_parts(&(&m[k][j] - &m[j][k]) * &f, x, y, z)
+		};
+		if result.is_out_of_range() {
+			Err(Error::ValueOutOfRange)
+		} else {
+			Ok(result)
+		}
+	}
+
+	/// Unit axis and angle of the rotation given by the quaternion. A rotation
+	/// by zero has no axis, so the x axis is given for it.
+	pub fn to_axis_angle(&self, angle_mode: AngleUnit) -> Result<(Vector, Number)> {
+		let unit = self.normalize()?;
+		let sin_half =
+			(&(&(&unit.x * &unit.x) + &(&unit.y * &unit.y)) + &(&unit.z * &unit.z)).sqrt();
+		let angle = Number::Decimal(Decimal::atan2(&sin_half.to_decimal(), &unit.w.to_decimal()))
+			* 2.to_number();
+
+		let mut axis = Vector::new()?;
+		if sin_half.is_zero() {
+			axis.push(Value::Number(1.into()))?;
+			axis.push(Value::Number(0.into()))?;
+			axis.push(Value::Number(0.into()))?;
+		} else {
+			for part in &[&unit.x, &unit.y, &unit.z] {
+				axis.push(Value::Number(*part / &sin_half))?;
+			}
+		}
+		Ok((axis, angle.angle_from_radians(angle_mode).into_owned()))
+	}
+
+	/// Unit quaternion of the rotation by `angle` about `axis`, which must have
+	/// three elements and does not need to be of unit length
+	pub fn from_axis_angle(axis: &Vector, angle: &Number, angle_mode: AngleUnit) -> Result<Self> {
+		if axis.len() != 3 {
+			return Err(Error::DimensionMismatch);
+		}
+		let parts = [
+			axis.get(0)?.real_number()?.clone(),
+			axis.get(1)?.real_number()?.clone(),
+			axis.get(2)?.real_number()?.clone(),
+		];
+		let length = (&(&(&parts[0] * &parts[0]) + &(&parts[1] * &parts[1]))
+			+ &(&parts[2] * &parts[2]))
+			.sqrt();
+		if length.is_zero() {
+			return Err(Error::DivideByZero);
+		}
+
+		let half_angle = &*angle.angle_to_radians(angle_mode) / &2.to_number();
+		let scale = &half_angle.sin() / &length;
+		let [x, y, z] = parts;
+		Ok(Self::from_parts(
+			half_angle.cos(),
+			&x * &scale,
+			&y * &scale,
+			&z * &scale,
+		))
+	}
+
+	fn quaternion_add(&self, rhs: &Self) -> Self {
+		Self::from_parts(
+			&self.w + &rhs.w,
+			&self.x + &rhs.x,
+			&self.y + &rhs.y,
+			&self.z + &rhs.z,
+		)
+	}
+
+	fn quaternion_sub(&self, rhs: &Self) -> Self {
+		Self::from_parts(
+			&self.w - &rhs.w,
+			&self.x - &rhs.x,
+			&self.y - &rhs.y,
+			&self.z - &rhs.z,
+		)
+	}
+
+	/// Hamilton product, which does not commute
+	fn quaternion_mul(&self, rhs: &Self) -> Self {
+		let (a, b) = (self, rhs);
+		Self::from_parts(
+			&(&(&a.w * &b.w) - &(&a.x * &b.x)) - &(&(&a.y * &b.y) + &(&a.z * &b.z)),
+			&(&(&a.w * &b.x) + &(&a.x * &b.w)) + &(&(&a.y * &b.z) - &(&a.z * &b.y)),
+			&(&(&a.w * &b.y) - &(&a.x * &b.z)) + &(&(&a.y * &b.w) + &(&a.z * &b.x)),
+			&(&(&a.w * &b.z) + &(&a.x * &b.y)) + &(&(&a.z * &b.w) - &(&a.y * &b.x)),
+		)
+	}
+
+	/// Product with the inverse of `rhs` on the right
+	pub fn divide(&self, rhs: &Self) -> Result<Self> {
+		Ok(self.quaternion_mul(&rhs.inverse()?))
+	}
+}
+
+impl fmt::Display for Quaternion {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.w.to_string())?;
+		for (part, name) in self.parts()[1..].iter().zip(["i", "j", "k"].iter()) {
+			if part.is_negative() {
+				write!(f, " - {}{}", (-*part).to_string(), name)?;
+			} else {
+				write!(f, " + {}{}", part.to_string(), name)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl core::ops::Add for &Quaternion {
+	type Output = Quaternion;
+
+	fn add(self, rhs: Self) -> Self::Output {
+		self.quaternion_add(rhs)
+	}
+}
+
+impl core::ops::Sub for &Quaternion {
+	type Output = Quaternion;
+
+	fn sub(self, rhs: Self) -> Self::Output {
+		self.quaternion_sub(rhs)
+	}
+}
+
+impl core::ops::Mul for &Quaternion {
+	type Output = Quaternion;
+
+	fn mul(self, rhs: Self) -> Self::Output {
+		self.quaternion_mul(rhs)
+	}
+}
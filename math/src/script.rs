@@ -0,0 +1,893 @@
+use crate::checksum::{ByteOrder, ChecksumKind};
+use crate::constant::Constant;
+use crate::context::Context;
+use crate::decibel::DecibelScale;
+use crate::error::{Error, Result};
+use crate::functions::StackFunction;
+use crate::list::List;
+use crate::modes::ModeScope;
+use crate::number::Number;
+use crate::probability::{DistributionFunction, DistributionKind};
+use crate::storage::OperationBudget;
+use crate::text::unquote;
+use crate::unit::AngleUnit;
+use crate::value::Value;
+use intel_dfp::Decimal;
+use num_bigint::BigInt;
+use spin::Mutex;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+static YIELD_HOOK: Mutex<Option<fn() -> bool>> = Mutex::new(None);
+
+/// Sets a function that is called on each pass through a loop of a script, so
+/// that the platform can service its hardware during long computations. The
+/// script stops with `Error::Interrupted` when the function returns true.
+pub fn set_yield_hook(hook: fn() -> bool) {
+	*YIELD_HOOK.lock() = Some(hook);
+}
+
+/// Passes through loops left to computations before they are stopped, while a
+/// `YieldLimit` is alive
+static YIELDS_LEFT: Mutex<Option<usize>> = Mutex::new(None);
+
+/// Stops computations with `Error::Interrupted` once they have passed through
+/// their loops more than a number of times, for running functions where only a
+/// quick result is of use. Functions that finish without a loop are not limited.
+pub struct YieldLimit {
+	previous: Option<usize>,
+}
+
+impl YieldLimit {
+	pub fn begin(passes: usize) -> Self {
+		let previous = YIELDS_LEFT.lock().replace(passes);
+		YieldLimit { previous }
+	}
+}
+
+impl Drop for YieldLimit {
+	fn drop(&mut self) {
+		*YIELDS_LEFT.lock() = self.previous;
+	}
+}
+
+pub(crate) fn computation_yield() -> Result<()> {
+	if let Some(left) = YIELDS_LEFT.lock().as_mut() {
+		if *left == 0 {
+			return Err(Error::Interrupted);
+		}
+		*left -= 1;
+	}
+	let hook = *YIELD_HOOK.lock();
+	match hook {
+		Some(hook) if hook() => Err(Error::Interrupted),
+		_ => Ok(()),
+	}
+}
+
+/// Way that the terms of a series are combined
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Series {
+	/// `Σ`, the sum of the terms
+	Sum,
+	/// `∏`, the product of the terms
+	Product,
+}
+
+/// Words that operate directly on the stack instead of through a stack function
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Builtin {
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Pow,
+	Neg,
+	Recip,
+	Sqrt,
+	Square,
+	Dup,
+	Drop,
+	Swap,
+	Over,
+	Clear,
+	Equal,
+	NotEqual,
+	Less,
+	LessEqual,
+	Greater,
+	GreaterEqual,
+}
+
+#[derive(Clone)]
+enum Word {
+	Number(Number),
+	Builtin(Builtin),
+	Function(StackFunction),
+	Local(String),
+	/// Text in quotes, pushed as it is
+	Text(Vec<u8>),
+	/// `IF cond THEN body ELSE body END`, where the else branch may be empty
+	If(Vec<Word>, Vec<Word>, Vec<Word>),
+	/// `start end FOR name body NEXT`, or `STEP` in place of `NEXT` to take the
+	/// increment from the stack
+	For(String, Vec<Word>, bool),
+	/// `start end START body NEXT`, or `STEP` in place of `NEXT`
+	Start(Vec<Word>, bool),
+	/// `→ name... « body »` binds values from the stack to local names
+	Locals(Vec<String>, Vec<Word>),
+	/// `{ numbers }` pushes a list
+	List(Vec<Number>),
+	/// `« body » MAP` runs the body on each element of the list in x
+	Map(Vec<Word>),
+	/// `« body » REDUCE` combines the elements of the list in x with the body
+	Reduce(Vec<Word>),
+	/// `start end « body » Σ` adds the results of the body for each index from
+	/// start to end, or multiplies them with `∏`
+	Series(Series, Vec<Word>),
+}
+
+/// Program written in a small stack based language in the style of RPL. A script
+/// is either a program object enclosed in `« »`, which is kept to be run later,
+/// or a command line that is run once.
+///
+/// A program object followed by `'NAME' STO` defines a user function. User
+/// functions take their arguments from the stack, given by the local variables
+/// of a body in the form `« → x y « ... » »`. Functions of one or two arguments
+/// can be graphed and applied to lists, and those of any number of arguments
+/// can be solved for one of them.
+///
+/// Lists are written as `{ 1 2 3 }`. A program written inside a script must be
+/// followed by `MAP` or `REDUCE`, which apply it to the elements of a list, or
+/// by `Σ` or `∏`, which evaluate it over a range of indexes.
+#[derive(Clone)]
+pub struct Script {
+	source: String,
+	words: Vec<Word>,
+	program: bool,
+	name: Option<String>,
+}
+
+impl Script {
+	/// Parses the text of a script. All names must be known when the script is
+	/// parsed, so mistakes are caught before anything is run.
+	pub fn parse(source: &str) -> Result<Self> {
+		let mut tokens = tokenize(source);
+		let name = definition_name(&tokens);
+		if name.is_some() {
+			tokens.truncate(tokens.len() - 2);
+		}
+		let program = tokens.first().map(|token| token.as_str()) == Some("«")
+			&& tokens.last().map(|token| token.as_str()) == Some("»");
+		let mut parser = Parser {
+			tokens: &tokens,
+			pos: 0,
+			locals: Vec::new(),
+		};
+
+		// Strip the outer program delimiters, which do not create a new scope
+		let words = if program {
+			parser.pos = 1;
+			let (words, end) = parser.block(&["»"])?;
+			if end != "»" || parser.pos != tokens.len() {
+				return Err(Error::SyntaxError);
+			}
+			words
+		} else {
+			let (words, _) = parser.block(&[])?;
+			words
+		};
+
+		let script = Script {
+			source: source.trim().to_string(),
+			words,
+			program,
+			name,
+		};
+		if script.name.is_some() {
+			match (script.program, script.arguments().map(|args| args.len())) {
+				(true, Some(_)) => (),
+				_ => return Err(Error::SyntaxError),
+			}
+		}
+		Ok(script)
+	}
+
+	pub fn source(&self) -> &str {
+		&self.source
+	}
+
+	/// Returns true if the script is a program object that should be kept
+	pub fn is_program(&self) -> bool {
+		self.program
+	}
+
+	/// Name of the user function defined by the script, if it defines one
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// Names of the arguments taken from the stack, if the whole program binds
+	/// them to local variables. The last argument is taken from the top of the
+	/// stack.
+	pub fn arguments(&self) -> Option<&[String]> {
+		match self.words.as_slice() {
+			[Word::Locals(names, _)] => Some(names),
+			_ => None,
+		}
+	}
+
+	pub fn execute(&self, context: &mut Context) -> Result<()> {
+		let _budget = OperationBudget::begin();
+		let _modes = ModeScope::begin(context);
+		let mut locals = Vec::new();
+		run(&self.words, context, &mut locals)
+	}
+
+	/// Runs the script on each element of the list in x, replacing the list with
+	/// the list of results
+	pub fn map(&self, context: &mut Context) -> Result<()> {
+		let _budget = OperationBudget::begin();
+		let _modes = ModeScope::begin(context);
+		let mut locals = Vec::new();
+		map_list(context, &mut |context| {
+			run(&self.words, context, &mut locals)
+		})
+	}
+
+	/// Replaces the list in x with the result of combining its elements in order
+	/// with the script, which takes two arguments
+	pub fn reduce(&self, context: &mut Context) -> Result<()> {
+		let _budget = OperationBudget::begin();
+		let _modes = ModeScope::begin(context);
+		let mut locals = Vec::new();
+		reduce_list(context, &mut |context| {
+			run(&self.words, context, &mut locals)
+		})
+	}
+
+	/// Evaluates the script for each integer index from the start in y to the end
+	/// in x, replacing them with the sum or product of the results
+	pub fn series(&self, context: &mut Context, series: Series) -> Result<()> {
+		let _budget = OperationBudget::begin();
+		let _modes = ModeScope::begin(context);
+		let mut locals = Vec::new();
+		evaluate_series(context, series, &mut |context| {
+			run(&self.words, context, &mut locals)
+		})
+	}
+}
+
+/// Returns the name given by a trailing `'NAME' STO`
+fn definition_name(tokens: &[String]) -> Option<String> {
+	if tokens.len() < 3 || tokens[tokens.len() - 1].to_uppercase() != "STO" {
+		return None;
+	}
+	let quoted = &tokens[tokens.len() - 2];
+	let name = quoted.strip_prefix('\'')?.strip_suffix('\'')?;
+	if name.starts_with(|ch: char| ch.is_alphabetic()) {
+		Some(name.to_string())
+	} else {
+		None
+	}
+}
+
+fn tokenize(source: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut current = String::new();
+	let mut chars = source.chars();
+	while let Some(ch) = chars.next() {
+		if ch == '"' && current.is_empty() {
+			// Text is a single token with its quotes, and may hold spaces. A
+			// backslash escapes the next character, so escaped quotes do not end
+			// the text.
+			current.push(ch);
+			while let Some(ch) = chars.next() {
+				current.push(ch);
+				match ch {
+					'"' => break,
+					'\\' => current.extend(chars.next()),
+					_ => (),
+				}
+			}
+			tokens.push(core::mem::take(&mut current));
+		} else if ch.is_whitespace() || ch == '«' || ch == '»' {
+			if !current.is_empty() {
+				tokens.push(core::mem::take(&mut current));
+			}
+			if !ch.is_whitespace() {
+				tokens.push(ch.to_string());
+			}
+		} else {
+			current.push(ch);
+		}
+	}
+	if !current.is_empty() {
+		tokens.push(current);
+	}
+
+	// Accept the plain text spellings of the delimiters as well
+	for token in tokens.iter_mut() {
+		match token.as_str() {
+			"<<" => *token = "«".to_string(),
+			">>" => *token = "»".to_string(),
+			"->" => *token = "→".to_string(),
+			_ => (),
+		}
+	}
+	tokens
+}
+
+struct Parser<'a> {
+	tokens: &'a [String],
+	pos: usize,
+	locals: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+	fn next(&mut self) -> Option<&'a str> {
+		let token = self.tokens.get(self.pos)?;
+		self.pos += 1;
+		Some(token.as_str())
+	}
+
+	/// Parses words until one of the `terminators` is reached, returning the
+	/// words along with the terminator. With no terminators the block extends to
+	/// the end of the script.
+	fn block(&mut self, terminators: &[&'static str]) -> Result<(Vec<Word>, &'static str)> {
+		let mut words = Vec::new();
+		loop {
+			let token = match self.next() {
+				Some(token) => token,
+				None if terminators.is_empty() => return Ok((words, "")),
+				None => return Err(Error::SyntaxError),
+			};
+			if let Some(text) = token.strip_prefix('"') {
+				let text = text.strip_suffix('"').ok_or(Error::SyntaxError)?;
+				words.push(Word::Text(unquote(text)?));
+				continue;
+			}
+
+			let keyword = token.to_uppercase();
+			if let Some(end) = terminators.iter().find(|end| **end == keyword) {
+				return Ok((words, end));
+			}
+
+			words.push(match keyword.as_str() {
+				"IF" => {
+					let (condition, _) = self.block(&["THEN"])?;
+					let (then, end) = self.block(&["ELSE", "END"])?;
+					let otherwise = if end == "ELSE" {
+						self.block(&["END"])?.0
+					} else {
+						Vec::new()
+					};
+					Word::If(condition, then, otherwise)
+				}
+				"FOR" => {
+					let name = self.name()?;
+					self.locals.push(name.clone());
+					let result = self.block(&["NEXT", "STEP"]);
+					self.locals.pop();
+					let (body, end) = result?;
+					Word::For(name, body, end == "STEP")
+				}
+				"START" => {
+					let (body, end) = self.block(&["NEXT", "STEP"])?;
+					Word::Start(body, end == "STEP")
+				}
+				"→" => {
+					let mut names = Vec::new();
+					loop {
+						match self.next() {
+							Some("«") => break,
+							Some(_) => {
+								self.pos -= 1;
+								names.push(self.name()?);
+							}
+							None => return Err(Error::SyntaxError),
+						}
+					}
+					if names.is_empty() {
+						return Err(Error::SyntaxError);
+					}
+					let scope = self.locals.len();
+					self.locals.extend(names.iter().cloned());
+					let result = self.block(&["»"]);
+					self.locals.truncate(scope);
+					Word::Locals(names, result?.0)
+				}
+				"{" => {
+					let mut numbers = Vec::new();
+					loop {
+						match self.next() {
+							Some("}") => break,
+							Some(token) => {
+								numbers.push(parse_number(token).ok_or(Error::SyntaxError)?)
+							}
+							None => return Err(Error::SyntaxError),
+						}
+					}
+					Word::List(numbers)
+				}
+				"«" => {
+					let (body, _) = self.block(&["»"])?;
+					match self.next().map(|token| token.to_uppercase()).as_deref() {
+						Some("MAP") => Word::Map(body),
+						Some("REDUCE") => Word::Reduce(body),
+						Some("Σ") | Some("SIGMA") => Word::Series(Series::Sum, body),
+						Some("∏") | Some("Π") | Some("PRODUCT") => {
+							Word::Series(Series::Product, body)
+						}
+						_ => return Err(Error::SyntaxError),
+					}
+				}
+				_ => self.word(token, &keyword)?,
+			});
+		}
+	}
+
+	/// Reads the name of a local variable
+	fn name(&mut self) -> Result<String> {
+		match self.next() {
+			Some(token) if token.starts_with(|ch: char| ch.is_alphabetic()) => {
+				Ok(token.to_string())
+			}
+			_ => Err(Error::SyntaxError),
+		}
+	}
+
+	fn word(&self, token: &str, keyword: &str) -> Result<Word> {
+		if self.locals.iter().any(|name| name == token) {
+			return Ok(Word::Local(token.to_string()));
+		}
+		if let Some(number) = parse_number(token) {
+			return Ok(Word::Number(number));
+		}
+		if let Some(builtin) = builtin_word(keyword) {
+			return Ok(Word::Builtin(builtin));
+		}
+		if let Some(func) = function_word(keyword) {
+			return Ok(Word::Function(func));
+		}
+		match keyword {
+			"THEN" | "ELSE" | "END" | "NEXT" | "STEP" | "»" | "}" | "MAP" | "REDUCE" | "Σ"
+			| "∏" => Err(Error::SyntaxError),
+			_ => Err(Error::UnknownName),
+		}
+	}
+}
+
+fn parse_number(token: &str) -> Option<Number> {
+	let digits = token.strip_prefix('-').unwrap_or(token);
+	if digits.is_empty() {
+		return None;
+	}
+	if digits.chars().all(|ch| ch.is_ascii_digit()) {
+		return BigInt::parse_bytes(token.as_bytes(), 10).map(Number::from);
+	}
+
+	// Decimal numbers have digits with an optional fraction, then an optional
+	// exponent
+	let (mantissa, exponent) = match digits.find(['E', 'e']) {
+		Some(idx) => (&digits[..idx], Some(&digits[idx + 1..])),
+		None => (digits, None),
+	};
+	let mut parts = mantissa.splitn(2, '.');
+	let integer = parts.next().unwrap_or("");
+	let fraction = parts.next().unwrap_or("");
+	if integer.len() + fraction.len() == 0
+		|| !integer.chars().all(|ch| ch.is_ascii_digit())
+		|| !fraction.chars().all(|ch| ch.is_ascii_digit())
+	{
+		return None;
+	}
+	if let Some(exponent) = exponent {
+		let exponent = exponent.strip_prefix('-').unwrap_or(exponent);
+		if exponent.is_empty() || !exponent.chars().all(|ch| ch.is_ascii_digit()) {
+			return None;
+		}
+	}
+	Some(Number::Decimal(Decimal::from_str(token)))
+}
+
+fn builtin_word(name: &str) -> Option<Builtin> {
+	Some(match name {
+		"+" => Builtin::Add,
+		"-" | "−" => Builtin::Sub,
+		"*" | "×" => Builtin::Mul,
+		"/" | "÷" => Builtin::Div,
+		"^" => Builtin::Pow,
+		"NEG" => Builtin::Neg,
+		"INV" => Builtin::Recip,
+		"SQRT" | "√" => Builtin::Sqrt,
+		"SQ" => Builtin::Square,
+		"DUP" => Builtin::Dup,
+		"DROP" => Builtin::Drop,
+		"SWAP" => Builtin::Swap,
+		"OVER" => Builtin::Over,
+		"CLEAR" => Builtin::Clear,
+		"==" => Builtin::Equal,
+		"≠" | "!=" => Builtin::NotEqual,
+		"<" => Builtin::Less,
+		"≤" | "<=" => Builtin::LessEqual,
+		">" => Builtin::Greater,
+		"≥" | ">=" => Builtin::GreaterEqual,
+		_ => return None,
+	})
+}
+
+fn function_word(name: &str) -> Option<StackFunction> {
+	Some(match name {
+		"LOG" => StackFunction::Log,
+		"ALOG" => StackFunction::Exp10,
+		"LN" => StackFunction::Ln,
+		"EXP" => StackFunction::Exp,
+		"SIN" => StackFunction::Sin,
+		"COS" => StackFunction::Cos,
+		"TAN" => StackFunction::Tan,
+		"ASIN" => StackFunction::Asin,
+		"ACOS" => StackFunction::Acos,
+		"ATAN" => StackFunction::Atan,
+		"SINH" => StackFunction::Sinh,
+		"COSH" => StackFunction::Cosh,
+		"TANH" => StackFunction::Tanh,
+		"ASINH" => StackFunction::Asinh,
+		"ACOSH" => StackFunction::Acosh,
+		"ATANH" => StackFunction::Atanh,
+		"AND" => StackFunction::And,
+		"OR" => StackFunction::Or,
+		"XOR" => StackFunction::Xor,
+		"NOT" => StackFunction::Not,
+		"SL" => StackFunction::ShiftLeft,
+		"SR" => StackFunction::ShiftRight,
+		"RL" => StackFunction::RotateLeft,
+		"RR" => StackFunction::RotateRight,
+		"BSWAP16" => StackFunction::ByteSwap16,
+		"BSWAP32" => StackFunction::ByteSwap32,
+		"BSWAP64" => StackFunction::ByteSwap64,
+		"BREV" => StackFunction::ByteReverse,
+		"EXTRACT" => StackFunction::ExtractBits,
+		"INSERT" | "DEPOSIT" => StackFunction::InsertBits,
+		"MASK" => StackFunction::BitMask,
+		"POPCOUNT" | "POPCNT" => StackFunction::PopulationCount,
+		"CLZ" => StackFunction::LeadingZeros,
+		"CTZ" => StackFunction::TrailingZeros,
+		"BITLEN" => StackFunction::BitLength,
+		"IP→" => StackFunction::AddressToInteger,
+		"→IPV4" => StackFunction::IntegerToIpv4,
+		"→IPV6" => StackFunction::IntegerToIpv6,
+		"NETADDR" => StackFunction::NetworkAddress,
+		"BCAST" => StackFunction::BroadcastAddress,
+		"NETMASK" => StackFunction::Netmask,
+		"HOSTS" => StackFunction::HostCount,
+		"ADDSUM" => StackFunction::Checksum(ChecksumKind::Additive, ByteOrder::BigEndian),
+		"ADDSUMLE" => StackFunction::Checksum(ChecksumKind::Additive, ByteOrder::LittleEndian),
+		"XORSUM" => StackFunction::Checksum(ChecksumKind::Xor, ByteOrder::BigEndian),
+		"XORSUMLE" => StackFunction::Checksum(ChecksumKind::Xor, ByteOrder::LittleEndian),
+		"INETSUM" => StackFunction::Checksum(ChecksumKind::Internet, ByteOrder::BigEndian),
+		"INETSUMLE" => StackFunction::Checksum(ChecksumKind::Internet, ByteOrder::LittleEndian),
+		"ISO→" => StackFunction::FromIso8601,
+		"→ISO" => StackFunction::ToIso8601,
+		"LVL+" => StackFunction::LevelAdd,
+		"LVL-" | "LVL−" => StackFunction::LevelSub,
+		"LVL*" | "LVL×" => StackFunction::LevelMul,
+		"LVL/" | "LVL÷" => StackFunction::LevelDiv,
+		"C?" => StackFunction::TestCarry,
+		"G?" => StackFunction::TestOutOfRange,
+		"CLRCG" => StackFunction::ClearFlags,
+		"SUM" => StackFunction::Sum,
+		"MEAN" => StackFunction::Mean,
+		"+TAX" => StackFunction::AddTax,
+		"-TAX" | "−TAX" => StackFunction::RemoveTax,
+		"HIST" => StackFunction::Histogram,
+		"NPDF" => StackFunction::Distribution(DistributionKind::Normal, DistributionFunction::Pdf),
+		"NCDF" => StackFunction::Distribution(DistributionKind::Normal, DistributionFunction::Cdf),
+		"NINV" => {
+			StackFunction::Distribution(DistributionKind::Normal, DistributionFunction::InverseCdf)
+		}
+		"TPDF" => {
+			StackFunction::Distribution(DistributionKind::StudentT, DistributionFunction::Pdf)
+		}
+		"TCDF" => {
+			StackFunction::Distribution(DistributionKind::StudentT, DistributionFunction::Cdf)
+		}
+		"TINV" => StackFunction::Distribution(
+			DistributionKind::StudentT,
+			DistributionFunction::InverseCdf,
+		),
+		"CHI2PDF" => {
+			StackFunction::Distribution(DistributionKind::ChiSquare, DistributionFunction::Pdf)
+		}
+		"CHI2CDF" => {
+			StackFunction::Distribution(DistributionKind::ChiSquare, DistributionFunction::Cdf)
+		}
+		"CHI2INV" => StackFunction::Distribution(
+			DistributionKind::ChiSquare,
+			DistributionFunction::InverseCdf,
+		),
+		"FPDF" => StackFunction::Distribution(DistributionKind::F, DistributionFunction::Pdf),
+		"FCDF" => StackFunction::Distribution(DistributionKind::F, DistributionFunction::Cdf),
+		"FINV" => {
+			StackFunction::Distribution(DistributionKind::F, DistributionFunction::InverseCdf)
+		}
+		"DOT" => StackFunction::DotProduct,
+		"CROSS" => StackFunction::CrossProduct,
+		"ANGLE" => StackFunction::AngleBetween,
+		"ROT2" => StackFunction::Rotate2D,
+		"ROT3" => StackFunction::Rotate3D,
+		"→DEG" => StackFunction::ToAngle(AngleUnit::Degrees),
+		"→RAD" => StackFunction::ToAngle(AngleUnit::Radians),
+		"→GRAD" => StackFunction::ToAngle(AngleUnit::Gradians),
+		"P→DB" => StackFunction::RatioToDb(DecibelScale::Power),
+		"V→DB" => StackFunction::RatioToDb(DecibelScale::Field),
+		"DB→P" => StackFunction::DbToRatio(DecibelScale::Power),
+		"DB→V" => StackFunction::DbToRatio(DecibelScale::Field),
+		"DBM→W" => StackFunction::DbmToWatts,
+		"W→DBM" => StackFunction::WattsToDbm,
+		"DBM→V" => StackFunction::DbmToVolts,
+		"V→DBM" => StackFunction::VoltsToDbm,
+		"ZREF" => StackFunction::SetReferenceImpedance,
+		"INTERP" => StackFunction::Interpolate,
+		"TBLINT" => StackFunction::TableInterpolate,
+		"QUAT" => StackFunction::Quaternion,
+		"CONJ" => StackFunction::Conjugate,
+		"Q→ROT" => StackFunction::QuaternionToRotation,
+		"ROT→Q" => StackFunction::RotationToQuaternion,
+		"Q→AXA" => StackFunction::QuaternionToAxisAngle,
+		"AXA→Q" => StackFunction::AxisAngleToQuaternion,
+		"LINSOLVE" => StackFunction::LinearSolve,
+		"→LIST" => StackFunction::ToList,
+		"GET" => StackFunction::ListGet,
+		"APPEND" => StackFunction::ListAppend,
+		"PRIMEPI" => StackFunction::PrimeCount,
+		"NTHPRIME" => StackFunction::NthPrime,
+		"→STR" => StackFunction::ToText,
+		"STR→" => StackFunction::FromText,
+		"→B64" => StackFunction::Base64Encode,
+		"B64→" => StackFunction::Base64Decode,
+		"→HEXSTR" => StackFunction::HexEncode,
+		"HEXSTR→" => StackFunction::HexDecode,
+		"TRN" => StackFunction::Transpose,
+		"PI" | "π" => StackFunction::Constant(Constant::Pi),
+		"DEG" => StackFunction::Degrees,
+		"RAD" => StackFunction::Radians,
+		"GRAD" => StackFunction::Gradians,
+		_ => return None,
+	})
+}
+
+fn run(words: &[Word], context: &mut Context, locals: &mut Vec<(String, Value)>) -> Result<()> {
+	for word in words {
+		match word {
+			Word::Number(number) => context.push(Value::Number(number.clone()))?,
+			Word::Text(text) => context.push(Value::Text(text.clone()))?,
+			Word::Builtin(builtin) => run_builtin(*builtin, context)?,
+			Word::Function(func) => func.execute(context)?,
+			Word::Local(name) => {
+				let value = match locals.iter().rev().find(|(local, _)| local == name) {
+					Some((_, value)) => value.clone(),
+					None => return Err(Error::UnknownName),
+				};
+				context.push(value)?;
+			}
+			Word::If(condition, then, otherwise) => {
+				run(condition, context, locals)?;
+				if !context.pop()?.real_number()?.is_zero() {
+					run(then, context, locals)?;
+				} else {
+					run(otherwise, context, locals)?;
+				}
+			}
+			Word::For(name, body, step) => {
+				run_loop(Some(name), body, *step, context, locals)?;
+			}
+			Word::Start(body, step) => run_loop(None, body, *step, context, locals)?,
+			Word::Locals(names, body) => {
+				if context.stack_len() < names.len() {
+					return Err(Error::NotEnoughValues);
+				}
+
+				// The last name is bound to the top of the stack
+				let scope = locals.len();
+				for (idx, name) in names.iter().enumerate() {
+					locals.push((name.clone(), context.entry(names.len() - idx - 1)?));
+				}
+				for _ in 0..names.len() {
+					context.pop()?;
+				}
+				let result = run(body, context, locals);
+				locals.truncate(scope);
+				result?;
+			}
+			Word::List(numbers) => {
+				let mut list = List::new()?;
+				for number in numbers {
+					list.push(Value::Number(number.clone()))?;
+				}
+				context.push(Value::List(list))?;
+			}
+			Word::Map(body) => map_list(context, &mut |context| run(body, context, locals))?,
+			Word::Reduce(body) => reduce_list(context, &mut |context| run(body, context, locals))?,
+			Word::Series(series, body) => {
+				evaluate_series(context, *series, &mut |context| run(body, context, locals))?
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Returns the list in x, which is the argument of `MAP` and `REDUCE`
+fn list_argument(context: &Context) -> Result<List> {
+	match context.top()? {
+		Value::List(list) => Ok(list),
+		_ => Err(Error::DataTypeMismatch),
+	}
+}
+
+/// Checks that a function run on the elements of a list left one value in place
+/// of the values it was given
+fn check_result_depth(context: &Context, depth: usize) -> Result<()> {
+	if context.stack_len() != depth + 1 {
+		return Err(Error::InvalidArgument);
+	}
+	Ok(())
+}
+
+fn map_list(context: &mut Context, func: &mut dyn FnMut(&mut Context) -> Result<()>) -> Result<()> {
+	let list = list_argument(context)?;
+	let depth = context.stack_len();
+	let mut result = List::new()?;
+	for i in 0..list.len() {
+		computation_yield()?;
+		context.push(list.get(i)?)?;
+		func(context)?;
+		check_result_depth(context, depth)?;
+		result.push(context.pop()?)?;
+	}
+	context.set_top(Value::List(result))
+}
+
+fn reduce_list(
+	context: &mut Context,
+	func: &mut dyn FnMut(&mut Context) -> Result<()>,
+) -> Result<()> {
+	let list = list_argument(context)?;
+	if list.is_empty() {
+		return Err(Error::NotEnoughValues);
+	}
+	let depth = context.stack_len();
+	context.push(list.get(0)?)?;
+	for i in 1..list.len() {
+		computation_yield()?;
+		context.push(list.get(i)?)?;
+		func(context)?;
+		check_result_depth(context, depth)?;
+	}
+	let result = context.pop()?;
+	context.set_top(result)
+}
+
+/// Combines the results of a function for each index between the limits in y and
+/// x. Integer and rational terms are accumulated exactly. A range that is empty
+/// gives zero for a sum and one for a product.
+fn evaluate_series(
+	context: &mut Context,
+	series: Series,
+	func: &mut dyn FnMut(&mut Context) -> Result<()>,
+) -> Result<()> {
+	let mut index = context.entry(1)?.to_int()?.into_owned();
+	let end = context.entry(0)?.to_int()?.into_owned();
+	let depth = context.stack_len();
+	let mut result = Value::Number(match series {
+		Series::Sum => 0.into(),
+		Series::Product => 1.into(),
+	});
+	while index <= end {
+		computation_yield()?;
+		context.push(Value::Number(Number::Integer(index.clone().into())))?;
+		func(context)?;
+		check_result_depth(context, depth)?;
+		let term = context.pop()?;
+		result = match series {
+			Series::Sum => (result + term)?,
+			Series::Product => (result * term)?,
+		};
+		index += 1;
+	}
+	context.replace_entries(2, result)
+}
+
+/// Runs the body of a counted loop. Like RPL, the body always runs at least
+/// once and the loop ends when the counter passes the end value.
+fn run_loop(
+	name: Option<&String>,
+	body: &[Word],
+	step: bool,
+	context: &mut Context,
+	locals: &mut Vec<(String, Value)>,
+) -> Result<()> {
+	let end = context.pop()?;
+	let mut counter = context.pop()?;
+	loop {
+		computation_yield()?;
+		if let Some(name) = name {
+			locals.push((name.clone(), counter.clone()));
+		}
+		let result = run(body, context, locals);
+		if name.is_some() {
+			locals.pop();
+		}
+		result?;
+
+		let increment = if step {
+			context.pop()?
+		} else {
+			Value::Number(1.into())
+		};
+		let descending = {
+			let increment = increment.real_number()?;
+			if increment.is_zero() {
+				return Err(Error::ValueOutOfRange);
+			}
+			increment.is_negative()
+		};
+		counter = (&counter + &increment)?;
+
+		let remaining = (&end - &counter)?;
+		let remaining = remaining.real_number()?;
+		let done = if descending {
+			!remaining.is_negative() && !remaining.is_zero()
+		} else {
+			remaining.is_negative()
+		};
+		if done {
+			return Ok(());
+		}
+	}
+}
+
+fn run_builtin(builtin: Builtin, context: &mut Context) -> Result<()> {
+	match builtin {
+		Builtin::Add => context.add(),
+		Builtin::Sub => context.sub(),
+		Builtin::Mul => context.mul(),
+		Builtin::Div => context.div(),
+		Builtin::Pow => context.pow(),
+		Builtin::Neg => context.set_top((-context.top()?)?),
+		Builtin::Recip => context.recip(),
+		Builtin::Sqrt => context.sqrt(),
+		Builtin::Square => context.square(),
+		Builtin::Dup => context.push(context.top()?),
+		Builtin::Drop => context.pop().map(|_| ()),
+		Builtin::Swap => context.swap(0, 1),
+		Builtin::Over => context.push(context.entry(1)?),
+		Builtin::Clear => {
+			context.clear_stack();
+			Ok(())
+		}
+		Builtin::Equal
+		| Builtin::NotEqual
+		| Builtin::Less
+		| Builtin::LessEqual
+		| Builtin::Greater
+		| Builtin::GreaterEqual => {
+			// Compare y with x using the sign of their difference
+			let difference = (context.entry(1)? - context.entry(0)?)?;
+			let difference = difference.real_number()?;
+			let (zero, negative) = (difference.is_zero(), difference.is_negative());
+			let result = match builtin {
+				Builtin::Equal => zero,
+				Builtin::NotEqual => !zero,
+				Builtin::Less => negative,
+				Builtin::LessEqual => negative || zero,
+				Builtin::Greater => !negative && !zero,
+				_ => !negative,
+			};
+			context.replace_entries(2, Value::Number((result as i32).into()))
+		}
+	}
+}
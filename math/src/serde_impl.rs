@@ -0,0 +1,755 @@
+use crate::complex::ComplexNumber;
+use crate::error::Error;
+use crate::format::{
+	AlternateFormatMode, DecimalPointMode, DigitSeparator, Format, FormatMode, FractionDisplay,
+	IntegerMode, OverflowMode, MAX_DISPLAY_DIGITS,
+};
+use crate::iso8601;
+use crate::list::List;
+use crate::matrix::Matrix;
+use crate::number::{Number, MAX_INTEGER_BITS};
+use crate::quaternion::Quaternion;
+use crate::stack::Stack;
+use crate::unit::{AngleUnit, CompositeUnit, Unit, UnitType};
+use crate::value::Value;
+use crate::vector::Vector;
+use core::fmt;
+use intel_dfp::{Decimal, RoundingMode};
+use num_bigint::{BigInt, BigUint};
+use serde::de::{
+	self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+// Integers can be larger than the numbers of most formats, and decimals have more
+// precision than a double, so the digits of numbers are written as strings. A
+// number is written as one of `{"Integer": "12"}`, `{"Rational": ["1", "3"]}` or
+// `{"Decimal": "+15E-1"}`.
+
+const NUMBER_VARIANTS: &[&str] = &["Integer", "Rational", "Decimal"];
+
+impl Serialize for Number {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Number::Integer(int) => serializer.serialize_newtype_variant(
+				"Number",
+				0,
+				"Integer",
+				&int.as_bigint().to_string(),
+			),
+			Number::Rational(num, denom) => serializer.serialize_newtype_variant(
+				"Number",
+				1,
+				"Rational",
+				&(num.to_string(), denom.to_string()),
+			),
+			Number::Decimal(value) => {
+				serializer.serialize_newtype_variant("Number", 2, "Decimal", &value.to_string())
+			}
+		}
+	}
+}
+
+fn parse_int<E: de::Error>(digits: &str) -> Result<BigInt, E> {
+	BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(|| E::custom("invalid integer"))
+}
+
+/// The decimal library reads text that is not a number as NaN, so NaN is only
+/// accepted when it is spelled out
+fn parse_decimal<E: de::Error>(text: &str) -> Result<Decimal, E> {
+	let value = Decimal::from_str(text);
+	let unsigned = text.trim_start_matches(['+', '-']);
+	if value.is_nan() && !unsigned.eq_ignore_ascii_case("nan") {
+		Err(E::custom("invalid decimal"))
+	} else {
+		Ok(value)
+	}
+}
+
+struct NumberVisitor;
+
+impl<'de> Visitor<'de> for NumberVisitor {
+	type Value = Number;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a number")
+	}
+
+	fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Number, A::Error> {
+		let (variant, access): (String, _) = data.variant()?;
+		match variant.as_str() {
+			"Integer" => Ok(Number::Integer(
+				parse_int(&access.newtype_variant::<String>()?)?.into(),
+			)),
+			"Rational" => {
+				let (num, denom) = access.newtype_variant::<(String, String)>()?;
+				let denom = BigUint::parse_bytes(denom.as_bytes(), 10)
+					.filter(|denom| *denom != BigUint::from(0u32))
+					.ok_or_else(|| de::Error::custom("invalid denominator"))?;
+				Ok(Number::Rational(parse_int(&num)?, denom).reduce())
+			}
+			"Decimal" => Ok(Number::Decimal(parse_decimal(
+				&access.newtype_variant::<String>()?,
+			)?)),
+			_ => Err(de::Error::unknown_variant(&variant, NUMBER_VARIANTS)),
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Number {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_enum("Number", NUMBER_VARIANTS, NumberVisitor)
+	}
+}
+
+// Other values are written as a map with one entry, named for the kind of value:
+//
+// - `{"Number": number}`
+// - `{"NumberWithUnit": [number, [["m", 1], ["s", -2]]]}`, with the symbol and
+//   power of each unit
+// - `{"Complex": [real, imaginary]}` and `{"Quaternion": [w, x, y, z]}`
+// - `{"DateTime": "2024-02-29T13:45:30"}`, `{"Date": "2024-02-29"}` and
+//   `{"Time": "13:45:30.25"}` in the extended format of ISO 8601
+// - `{"Vector": [values]}`, `{"List": [values]}` and `{"Matrix": [[values]]}`
+//   with a sequence of values for each row of a matrix
+// - `{"Text": [bytes]}`, as text is not always valid UTF-8
+
+const VALUE_VARIANTS: &[&str] = &[
+	"Number",
+	"NumberWithUnit",
+	"Complex",
+	"Quaternion",
+	"DateTime",
+	"Date",
+	"Time",
+	"Vector",
+	"Matrix",
+	"List",
+	"Text",
+];
+
+const UNIT_TYPES: [UnitType; 11] = [
+	UnitType::Angle,
+	UnitType::Area,
+	UnitType::Distance,
+	UnitType::Energy,
+	UnitType::Force,
+	UnitType::Mass,
+	UnitType::Power,
+	UnitType::Pressure,
+	UnitType::Temperature,
+	UnitType::Time,
+	UnitType::Volume,
+];
+
+/// Units are written as their symbols, which are different for every unit
+impl Serialize for Unit {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.to_str())
+	}
+}
+
+impl<'de> Deserialize<'de> for Unit {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let symbol = String::deserialize(deserializer)?;
+		UNIT_TYPES
+			.iter()
+			.flat_map(|unit_type| unit_type.units().iter())
+			.find(|unit| unit.to_str() == symbol)
+			.copied()
+			.ok_or_else(|| de::Error::custom("unknown unit"))
+	}
+}
+
+/// A composite unit is written as a sequence of the units in it with their powers
+impl Serialize for CompositeUnit {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(self.units.len()))?;
+		for unit in self.units.values() {
+			seq.serialize_element(unit)?;
+		}
+		seq.end()
+	}
+}
+
+impl<'de> Deserialize<'de> for CompositeUnit {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let units = Vec::<(Unit, i32)>::deserialize(deserializer)?;
+		let result = CompositeUnit::from_units(&units);
+		// Each kind of unit can only be in a composite unit once
+		if result.units.len() != units.len() || units.iter().any(|(_, power)| *power == 0) {
+			return Err(de::Error::custom("invalid composite unit"));
+		}
+		Ok(result)
+	}
+}
+
+/// Elements of a collection, which are read back into a vector
+struct Elements<'a>(&'a Vector);
+
+impl<'a> Serialize for Elements<'a> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+		for idx in 0..self.0.len() {
+			seq.serialize_element(&self.0.get(idx).map_err(ser_error)?)?;
+		}
+		seq.end()
+	}
+}
+
+struct MatrixRows<'a>(&'a Matrix);
+
+impl<'a> Serialize for MatrixRows<'a> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let matrix = self.0;
+		let mut seq = serializer.serialize_seq(Some(matrix.rows()))?;
+		for row in 0..matrix.rows() {
+			let mut values = Vec::new();
+			for col in 0..matrix.cols() {
+				values.push(matrix.get(row, col).map_err(ser_error)?);
+			}
+			seq.serialize_element(&values)?;
+		}
+		seq.end()
+	}
+}
+
+fn ser_error<E: ser::Error>(error: Error) -> E {
+	E::custom(error.to_str())
+}
+
+fn de_error<E: de::Error>(error: Error) -> E {
+	E::custom(error.to_str())
+}
+
+impl Serialize for Value {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Value::Number(num) => serializer.serialize_newtype_variant("Value", 0, "Number", num),
+			Value::NumberWithUnit(num, unit) => {
+				serializer.serialize_newtype_variant("Value", 1, "NumberWithUnit", &(num, unit))
+			}
+			Value::Complex(value) => serializer.serialize_newtype_variant(
+				"Value",
+				2,
+				"Complex",
+				&(value.real_part(), value.imaginary_part()),
+			),
+			Value::Quaternion(value) => {
+				serializer.serialize_newtype_variant("Value", 3, "Quaternion", &value.parts())
+			}
+			Value::DateTime(_) | Value::Date(_) | Value::Time(_) => {
+				let (index, variant) = match self {
+					Value::DateTime(_) => (4, "DateTime"),
+					Value::Date(_) => (5, "Date"),
+					_ => (6, "Time"),
+				};
+				let text = iso8601::format(self).map_err(ser_error)?;
+				serializer.serialize_newtype_variant("Value", index, variant, &text)
+			}
+			Value::Vector(vector) => {
+				serializer.serialize_newtype_variant("Value", 7, "Vector", &Elements(vector))
+			}
+			Value::Matrix(matrix) => {
+				serializer.serialize_newtype_variant("Value", 8, "Matrix", &MatrixRows(matrix))
+			}
+			Value::List(list) => {
+				serializer.serialize_newtype_variant("Value", 9, "List", &Elements(list.values()))
+			}
+			Value::Text(text) => serializer.serialize_newtype_variant("Value", 10, "Text", text),
+		}
+	}
+}
+
+/// Reads a date or time in ISO 8601 form, which must be the kind of value named
+/// by the variant
+fn parse_date_time<E: de::Error>(variant: &str, text: &str) -> Result<Value, E> {
+	let value = iso8601::parse(text).map_err(de_error)?;
+	match (variant, &value) {
+		("DateTime", Value::DateTime(_)) | ("Date", Value::Date(_)) | ("Time", Value::Time(_)) => {
+			Ok(value)
+		}
+		_ => Err(E::custom("invalid date or time")),
+	}
+}
+
+fn matrix_from_rows<E: de::Error>(rows: Vec<Vec<Value>>) -> Result<Matrix, E> {
+	let cols = rows.first().map(|row| row.len()).unwrap_or(0);
+	if rows.iter().any(|row| row.len() != cols) {
+		return Err(E::custom("matrix rows have different lengths"));
+	}
+	let mut matrix = Matrix::new(rows.len(), cols).map_err(de_error)?;
+	for (row, values) in rows.into_iter().enumerate() {
+		for (col, value) in values.into_iter().enumerate() {
+			matrix.set(row, col, value).map_err(de_error)?;
+		}
+	}
+	Ok(matrix)
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+	type Value = Value;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a value")
+	}
+
+	fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+		let (variant, access): (String, _) = data.variant()?;
+		match variant.as_str() {
+			"Number" => Ok(Value::Number(access.newtype_variant()?)),
+			"NumberWithUnit" => {
+				let (num, unit) = access.newtype_variant::<(Number, CompositeUnit)>()?;
+				if unit.unitless() {
+					Ok(Value::Number(num))
+				} else {
+					Ok(Value::NumberWithUnit(num, unit))
+				}
+			}
+			"Complex" => {
+				let (real, imaginary) = access.newtype_variant::<(Number, Number)>()?;
+				Value::check_complex(ComplexNumber::from_parts(real, imaginary)).map_err(de_error)
+			}
+			"Quaternion" => {
+				let [w, x, y, z] = access.newtype_variant::<[Number; 4]>()?;
+				Value::check_quaternion(Quaternion::from_parts(w, x, y, z)).map_err(de_error)
+			}
+			"DateTime" | "Date" | "Time" => {
+				parse_date_time(&variant, &access.newtype_variant::<String>()?)
+			}
+			"Vector" => {
+				let mut vector = Vector::new().map_err(de_error)?;
+				for value in access.newtype_variant::<Vec<Value>>()? {
+					vector.push(value).map_err(de_error)?;
+				}
+				Ok(Value::Vector(vector))
+			}
+			"Matrix" => Ok(Value::Matrix(matrix_from_rows(
+				access.newtype_variant::<Vec<Vec<Value>>>()?,
+			)?)),
+			"List" => {
+				let mut list = List::new().map_err(de_error)?;
+				for value in access.newtype_variant::<Vec<Value>>()? {
+					list.push(value).map_err(de_error)?;
+				}
+				Ok(Value::List(list))
+			}
+			"Text" => Ok(Value::Text(access.newtype_variant()?)),
+			_ => Err(de::Error::unknown_variant(&variant, VALUE_VARIANTS)),
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Value {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_enum("Value", VALUE_VARIANTS, ValueVisitor)
+	}
+}
+
+/// The stack is written as a sequence of its values from the bottom of the stack
+/// to the top, in the order they would be entered
+impl Serialize for Stack {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(self.len()))?;
+		for idx in (0..self.len()).rev() {
+			let value = self
+				.entry(idx)
+				.map_err(|error| ser::Error::custom(error.to_str()))?;
+			seq.serialize_element(&value)?;
+		}
+		seq.end()
+	}
+}
+
+struct StackVisitor;
+
+impl<'de> Visitor<'de> for StackVisitor {
+	type Value = Stack;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a sequence of values")
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Stack, A::Error> {
+		let mut stack = Stack::new();
+		while let Some(value) = seq.next_element::<Value>()? {
+			stack
+				.push(value)
+				.map_err(|error| de::Error::custom(error.to_str()))?;
+		}
+		Ok(stack)
+	}
+}
+
+impl<'de> Deserialize<'de> for Stack {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_seq(StackVisitor)
+	}
+}
+
+/// Implements serialization of settings that are a choice of plain variants as
+/// the names of the variants
+macro_rules! named_variants {
+	($name:ident, [$(($variant:ident, $label:expr)),*]) => {
+		impl Serialize for $name {
+			fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				serializer.serialize_str(match self {
+					$($name::$variant => $label,)*
+				})
+			}
+		}
+
+		impl<'de> Deserialize<'de> for $name {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				let label = String::deserialize(deserializer)?;
+				match label.as_str() {
+					$($label => Ok($name::$variant),)*
+					_ => Err(de::Error::unknown_variant(&label, &[$($label),*])),
+				}
+			}
+		}
+	};
+}
+
+named_variants!(
+	FormatMode,
+	[
+		(Normal, "Normal"),
+		(Rational, "Rational"),
+		(Fixed, "Fixed"),
+		(Scientific, "Scientific"),
+		(Engineering, "Engineering"),
+		(Significant, "Significant")
+	]
+);
+named_variants!(DecimalPointMode, [(Period, "Period"), (Comma, "Comma")]);
+named_variants!(
+	DigitSeparator,
+	[
+		(Comma, "Comma"),
+		(ThinSpace, "ThinSpace"),
+		(Apostrophe, "Apostrophe"),
+		(Underscore, "Underscore")
+	]
+);
+named_variants!(OverflowMode, [(Wrap, "Wrap"), (Ellipsis, "Ellipsis")]);
+named_variants!(
+	FractionDisplay,
+	[(Auto, "Auto"), (Fraction, "Fraction"), (Decimal, "Decimal")]
+);
+named_variants!(
+	AlternateFormatMode,
+	[(Smart, "Smart"), (Bottom, "Bottom"), (Left, "Left")]
+);
+named_variants!(
+	AngleUnit,
+	[
+		(Degrees, "Degrees"),
+		(Radians, "Radians"),
+		(Gradians, "Gradians")
+	]
+);
+
+/// Integer modes are written as `Float`, `BigInteger`, or the size of the integer
+/// with `i` for signed or `u` for unsigned, such as `i64`
+impl Serialize for IntegerMode {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			IntegerMode::Float => serializer.serialize_str("Float"),
+			IntegerMode::BigInteger => serializer.serialize_str("BigInteger"),
+			IntegerMode::SizedInteger(bits, signed) => {
+				let prefix = if *signed { "i" } else { "u" };
+				serializer.serialize_str(&(prefix.to_string() + &bits.to_string()))
+			}
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for IntegerMode {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let label = String::deserialize(deserializer)?;
+		let sized = |signed: bool, bits: &str| match bits.parse::<usize>() {
+			Ok(bits) if bits > 0 && bits as u64 <= MAX_INTEGER_BITS => {
+				Ok(IntegerMode::SizedInteger(bits, signed))
+			}
+			_ => Err(de::Error::custom("invalid integer mode")),
+		};
+		match label.as_str() {
+			"Float" => Ok(IntegerMode::Float),
+			"BigInteger" => Ok(IntegerMode::BigInteger),
+			_ if label.starts_with('i') => sized(true, &label[1..]),
+			_ if label.starts_with('u') => sized(false, &label[1..]),
+			_ => Err(de::Error::custom("invalid integer mode")),
+		}
+	}
+}
+
+// The user selectable display settings are written as a map with an entry for
+// each setting, the same settings that are kept in saved state. Settings that
+// are missing or not known are left at their defaults when reading.
+
+const FORMAT_FIELDS: &[&str] = &[
+	"mode",
+	"integer_mode",
+	"decimal_point",
+	"thousands",
+	"digit_separator",
+	"si_prefix",
+	"display_digits",
+	"overflow",
+	"integer_radix",
+	"alt_integer",
+	"alt_rational",
+	"alt_complex",
+	"alt_mode",
+	"time_24_hour",
+	"stack_xyz",
+	"mode_tags",
+	"radicals",
+	"rounding",
+	"fraction_display",
+];
+
+// The rounding mode belongs to the decimal library, so it is written through these
+// names instead of implementing the serde traits for it
+const ROUNDING_MODES: &[(RoundingMode, &str)] = &[
+	(RoundingMode::NearestEven, "NearestEven"),
+	(RoundingMode::Down, "Down"),
+	(RoundingMode::Up, "Up"),
+	(RoundingMode::TowardZero, "TowardZero"),
+	(RoundingMode::NearestAway, "NearestAway"),
+];
+
+fn rounding_mode_name(mode: RoundingMode) -> &'static str {
+	ROUNDING_MODES
+		.iter()
+		.find(|(value, _)| *value == mode)
+		.map(|(_, name)| *name)
+		.unwrap()
+}
+
+impl Serialize for Format {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut map = serializer.serialize_map(Some(FORMAT_FIELDS.len()))?;
+		map.serialize_entry("mode", &self.mode)?;
+		map.serialize_entry("integer_mode", &self.integer_mode)?;
+		map.serialize_entry("decimal_point", &self.decimal_point)?;
+		map.serialize_entry("thousands", &self.thousands)?;
+		map.serialize_entry("digit_separator", &self.digit_separator)?;
+		map.serialize_entry("si_prefix", &self.si_prefix)?;
+		map.serialize_entry("display_digits", &self.display_digits)?;
+		map.serialize_entry("overflow", &self.overflow)?;
+		map.serialize_entry("integer_radix", &self.integer_radix)?;
+		map.serialize_entry("alt_integer", &self.alt_forms.integer)?;
+		map.serialize_entry("alt_rational", &self.alt_forms.rational)?;
+		map.serialize_entry("alt_complex", &self.alt_forms.complex)?;
+		map.serialize_entry("alt_mode", &self.alt_mode)?;
+		map.serialize_entry("time_24_hour", &self.time_24_hour)?;
+		map.serialize_entry("stack_xyz", &self.stack_xyz)?;
+		map.serialize_entry("mode_tags", &self.mode_tags)?;
+		map.serialize_entry("radicals", &self.radicals)?;
+		map.serialize_entry("rounding", rounding_mode_name(self.rounding))?;
+		map.serialize_entry("fraction_display", &self.fraction_display)?;
+		map.end()
+	}
+}
+
+struct FormatVisitor;
+
+impl<'de> Visitor<'de> for FormatVisitor {
+	type Value = Format;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a map of display settings")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Format, A::Error> {
+		let mut format = Format::new();
+		while let Some(key) = map.next_key::<String>()? {
+			match key.as_str() {
+				"mode" => format.mode = map.next_value()?,
+				"integer_mode" => format.integer_mode = map.next_value()?,
+				"decimal_point" => format.decimal_point = map.next_value()?,
+				"thousands" => format.thousands = map.next_value()?,
+				"digit_separator" => format.digit_separator = map.next_value()?,
+				"si_prefix" => format.si_prefix = map.next_value()?,
+				"display_digits" => match map.next_value::<Option<usize>>()? {
+					Some(digits) if digits > MAX_DISPLAY_DIGITS => {
+						return Err(de::Error::custom("too many display digits"));
+					}
+					digits => format.display_digits = digits,
+				},
+				"overflow" => format.overflow = map.next_value()?,
+				"fraction_display" => format.fraction_display = map.next_value()?,
+				"integer_radix" => match map.next_value::<u8>()? {
+					radix @ 2 | radix @ 8 | radix @ 10 | radix @ 16 => format.integer_radix = radix,
+					_ => return Err(de::Error::custom("invalid integer radix")),
+				},
+				"alt_integer" => format.alt_forms.integer = map.next_value()?,
+				"alt_rational" => format.alt_forms.rational = map.next_value()?,
+				"alt_complex" => format.alt_forms.complex = map.next_value()?,
+				"alt_mode" => format.alt_mode = map.next_value()?,
+				"time_24_hour" => format.time_24_hour = map.next_value()?,
+				"stack_xyz" => format.stack_xyz = map.next_value()?,
+				"mode_tags" => format.mode_tags = map.next_value()?,
+				"radicals" => format.radicals = map.next_value()?,
+				"rounding" => {
+					let name = map.next_value::<String>()?;
+					match ROUNDING_MODES.iter().find(|(_, label)| *label == name) {
+						Some((mode, _)) => format.rounding = *mode,
+						None => return Err(de::Error::custom("invalid rounding mode")),
+					}
+				}
+				_ => {
+					map.next_value::<de::IgnoredAny>()?;
+				}
+			}
+		}
+		Ok(format)
+	}
+}
+
+impl<'de> Deserialize<'de> for Format {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_map(FormatVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::complex::ComplexNumber;
+	use crate::format::{Format, FormatMode, IntegerMode};
+	use crate::iso8601;
+	use crate::matrix::Matrix;
+	use crate::number::{Number, ToNumber};
+	use crate::stack::Stack;
+	use crate::unit::{CompositeUnit, DistanceUnit, TimeUnit, Unit};
+	use crate::value::Value;
+	use crate::vector::Vector;
+	use intel_dfp::Decimal;
+	use num_bigint::{BigInt, BigUint};
+	use serde::de::DeserializeOwned;
+	use serde::Serialize;
+
+	/// Writes a value, reads it back and checks that it is written the same way
+	/// again
+	fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> String {
+		let json = serde_json::to_string(value).unwrap();
+		let read: T = serde_json::from_str(&json).unwrap();
+		assert_eq!(serde_json::to_string(&read).unwrap(), json);
+		json
+	}
+
+	#[test]
+	fn numbers_round_trip() {
+		assert_eq!(round_trip(&12.to_number()), r#"{"Integer":"12"}"#);
+		let big = Number::Integer((BigInt::from(1) << 100usize).into());
+		assert_eq!(
+			round_trip(&big),
+			r#"{"Integer":"1267650600228229401496703205376"}"#
+		);
+		let third = Number::Rational(BigInt::from(-1), BigUint::from(3u32));
+		assert_eq!(round_trip(&third), r#"{"Rational":["-1","3"]}"#);
+		let decimal = Number::Decimal(Decimal::from_str("1.5"));
+		let read: Number = serde_json::from_str(&round_trip(&decimal)).unwrap();
+		assert!(read.to_decimal().into_owned() == Decimal::from_str("1.5"));
+	}
+
+	#[test]
+	fn invalid_numbers_are_errors() {
+		assert!(serde_json::from_str::<Number>(r#"{"Decimal":"1.5x"}"#).is_err());
+		assert!(serde_json::from_str::<Number>(r#"{"Decimal":""}"#).is_err());
+		assert!(serde_json::from_str::<Number>(r#"{"Integer":"12a"}"#).is_err());
+		assert!(serde_json::from_str::<Number>(r#"{"Rational":["1","0"]}"#).is_err());
+		let nan: Number = serde_json::from_str(r#"{"Decimal":"NaN"}"#).unwrap();
+		assert!(nan.is_nan());
+	}
+
+	#[test]
+	fn values_round_trip() {
+		let speed = Value::NumberWithUnit(
+			3.to_number(),
+			CompositeUnit::ratio_unit(
+				Unit::Distance(DistanceUnit::Meters),
+				Unit::Time(TimeUnit::Seconds),
+			),
+		);
+		assert_eq!(
+			round_trip(&speed),
+			r#"{"NumberWithUnit":[{"Integer":"3"},[["m",1],["sec",-1]]]}"#
+		);
+
+		let complex = Value::Complex(ComplexNumber::from_parts(1.to_number(), 2.to_number()));
+		assert_eq!(
+			round_trip(&complex),
+			r#"{"Complex":[{"Integer":"1"},{"Integer":"2"}]}"#
+		);
+
+		let date_time = iso8601::parse("2024-02-29T13:45:30.25").unwrap();
+		assert_eq!(
+			round_trip(&date_time),
+			r#"{"DateTime":"2024-02-29T13:45:30.25"}"#
+		);
+		let date = iso8601::parse("2024-02-29").unwrap();
+		assert_eq!(round_trip(&date), r#"{"Date":"2024-02-29"}"#);
+
+		let mut vector = Vector::new().unwrap();
+		vector.push(Value::Number(1.to_number())).unwrap();
+		vector.push(complex).unwrap();
+		assert_eq!(
+			round_trip(&Value::Vector(vector)),
+			r#"{"Vector":[{"Number":{"Integer":"1"}},{"Complex":[{"Integer":"1"},{"Integer":"2"}]}]}"#
+		);
+
+		let mut matrix = Matrix::new(2, 1).unwrap();
+		matrix.set(1, 0, Value::Number(5.to_number())).unwrap();
+		assert_eq!(
+			round_trip(&Value::Matrix(matrix)),
+			r#"{"Matrix":[[{"Number":{"Integer":"0"}}],[{"Number":{"Integer":"5"}}]]}"#
+		);
+
+		assert_eq!(
+			round_trip(&Value::Text(b"hi".to_vec())),
+			r#"{"Text":[104,105]}"#
+		);
+	}
+
+	#[test]
+	fn invalid_values_are_errors() {
+		let ragged = r#"{"Matrix":[[{"Number":{"Integer":"1"}}],[]]}"#;
+		assert!(serde_json::from_str::<Value>(ragged).is_err());
+		let nested = r#"{"Vector":[{"Vector":[]}]}"#;
+		assert!(serde_json::from_str::<Value>(nested).is_err());
+		let repeated = r#"{"NumberWithUnit":[{"Integer":"1"},[["m",1],["km",1]]]}"#;
+		assert!(serde_json::from_str::<Value>(repeated).is_err());
+		let unknown = r#"{"NumberWithUnit":[{"Integer":"1"},[["parsec",1]]]}"#;
+		assert!(serde_json::from_str::<Value>(unknown).is_err());
+		let wrong_kind = r#"{"Date":"13:45:30"}"#;
+		assert!(serde_json::from_str::<Value>(wrong_kind).is_err());
+	}
+
+	#[test]
+	fn stack_and_format_round_trip() {
+		let mut stack = Stack::new();
+		stack.push(Value::Number(1.to_number())).unwrap();
+		stack.push(Value::Text(b"x".to_vec())).unwrap();
+		assert_eq!(
+			round_trip(&stack),
+			r#"[{"Number":{"Integer":"1"}},{"Text":[120]}]"#
+		);
+
+		let mut format = Format::new();
+		format.mode = FormatMode::Engineering;
+		format.integer_mode = IntegerMode::SizedInteger(16, true);
+		format.display_digits = Some(6);
+		let json = round_trip(&format);
+		assert!(serde_json::from_str::<Format>(&json).unwrap() == format);
+	}
+}
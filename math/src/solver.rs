@@ -0,0 +1,178 @@
+use crate::context::{Context, Location};
+use crate::error::{Error, Result};
+use crate::number::Number;
+use crate::script::{computation_yield, Script};
+use crate::value::Value;
+use intel_dfp::Decimal;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Maximum number of times the equation is evaluated while solving
+const MAX_SOLVER_STEPS: usize = 200;
+
+/// Relative size of the change in the unknown at which steps are considered to
+/// have converged
+const SOLVER_TOLERANCE: &str = "1e-32";
+
+/// Maximum number of times a step that went too far is shortened
+const MAX_BACKTRACKS: usize = 30;
+
+/// Relative distance from the first guess to the second point of the search
+const FIRST_STEP: &str = "0.001";
+
+/// Variable in memory that holds the value of an argument of an equation. Only
+/// arguments named by a single character have one.
+pub fn argument_location(name: &str) -> Result<Location> {
+	let mut chars = name.chars();
+	match (chars.next(), chars.next()) {
+		(Some(ch), None) => Ok(Location::Variable(ch)),
+		_ => Err(Error::InvalidEntry),
+	}
+}
+
+/// Solves an equation given by a user function for the argument at `unknown`,
+/// finding the value that makes the function return zero. The other arguments
+/// are taken from the variables of the same name, and the search starts from
+/// the value stored for the unknown, or from zero if there is none.
+pub fn solve_equation(script: &Script, unknown: usize, context: &Context) -> Result<Decimal> {
+	let names = script.arguments().ok_or(Error::ValueNotDefined)?;
+	if unknown >= names.len() {
+		return Err(Error::InvalidArgument);
+	}
+	let mut args = Vec::new();
+	for (idx, name) in names.iter().enumerate() {
+		let location = argument_location(name)?;
+		args.push(if idx == unknown {
+			None
+		} else {
+			Some(context.read(&location)?)
+		});
+	}
+	let guess = match context.read(&argument_location(&names[unknown])?) {
+		Ok(value) => value.real_number()?.to_decimal().into_owned(),
+		Err(_) => Decimal::zero(),
+	};
+
+	// Each evaluation runs on a separate stack so that the stack of the
+	// calculator is left alone
+	let mut evaluator = Context::new();
+	evaluator.copy_settings_from(context);
+	find_root(
+		&mut |x| {
+			evaluator.clear_stack();
+			for arg in &args {
+				evaluator.push(match arg {
+					Some(value) => value.clone(),
+					None => Value::Number(Number::Decimal(x.clone())),
+				})?;
+			}
+			script.execute(&mut evaluator)?;
+			Ok(evaluator.top()?.real_number()?.to_decimal().into_owned())
+		},
+		&guess,
+	)
+}
+
+/// Finds a value where `f` is zero, starting the search at `guess`. Steps are
+/// taken with the secant method, and once the function has changed sign they
+/// are kept within the range where it does, falling back to bisection whenever
+/// a step would leave it.
+pub fn find_root(
+	f: &mut dyn FnMut(&Decimal) -> Result<Decimal>,
+	guess: &Decimal,
+) -> Result<Decimal> {
+	let zero = Decimal::zero();
+	let two: Decimal = 2.into();
+	let one: Decimal = 1.into();
+	let tolerance = Decimal::from_str(SOLVER_TOLERANCE);
+	let first_step = Decimal::from_str(FIRST_STEP);
+
+	let mut prev = guess.clone();
+	let mut prev_value = f(&prev)?;
+	if prev_value == zero {
+		return Ok(prev);
+	}
+	let mut x = if prev == zero {
+		first_step
+	} else {
+		&prev + &(&prev * &first_step)
+	};
+	let mut value = f(&x)?;
+
+	// Points where the function is negative and positive, once both are known
+	let mut bracket: Option<(Decimal, Decimal)> = None;
+	for _ in 0..MAX_SOLVER_STEPS {
+		computation_yield()?;
+		if value == zero {
+			return Ok(x);
+		}
+		bracket = match bracket {
+			Some((_, positive)) if value < zero => Some((x.clone(), positive)),
+			Some((negative, _)) => Some((negative, x.clone())),
+			None if (value < zero) != (prev_value < zero) => {
+				if value < zero {
+					Some((x.clone(), prev.clone()))
+				} else {
+					Some((prev.clone(), x.clone()))
+				}
+			}
+			None => None,
+		};
+
+		let secant = if value != prev_value {
+			let next = &x - &(&(&value * &(&x - &prev)) / &(&value - &prev_value));
+			if next.is_finite() {
+				Some(next)
+			} else {
+				None
+			}
+		} else {
+			None
+		};
+		let from_secant = secant.is_some();
+		let mut next = match (&bracket, secant) {
+			(Some((negative, positive)), Some(next))
+				if (&next > negative && &next < positive)
+					|| (&next < negative && &next > positive) =>
+			{
+				next
+			}
+			(Some((negative, positive)), _) => &(negative + positive) / &two,
+			(None, Some(next)) => next,
+			// The function is flat here, so search further away
+			(None, None) => &x + &(&(&x - &prev) * &two),
+		};
+
+		let scale = if x.abs() > one { x.abs() } else { one.clone() };
+		let limit = &tolerance * &scale;
+		let converged = match &bracket {
+			Some((negative, positive)) => (negative - positive).abs() <= limit,
+			None => false,
+		} || (&next - &x).abs() <= limit;
+		if converged {
+			return Ok(next);
+		}
+
+		// A step that makes the function larger without changing its sign has
+		// gone past the curve, so it is shortened until it improves on the last
+		let mut next_value = f(&next)?;
+		let mut backtracks = 0;
+		while from_secant
+			&& bracket.is_none()
+			&& (next_value < zero) == (value < zero)
+			&& next_value.abs() > value.abs()
+			&& backtracks < MAX_BACKTRACKS
+		{
+			next = &(&x + &next) / &two;
+			next_value = f(&next)?;
+			backtracks += 1;
+		}
+
+		prev = x;
+		prev_value = value;
+		x = next;
+		value = next_value;
+	}
+	Err(Error::NoSolution)
+}
@@ -2,7 +2,8 @@ use crate::error::{Error, Result};
 use crate::format::IntegerMode;
 use crate::number::Number;
 use crate::storage::store;
-use crate::undo::{clear_undo_buffer, pop_undo_action, push_undo_action, UndoAction};
+use crate::undo::{clear_undo_buffer, push_undo_action, UndoAction};
+use crate::unit::AngleUnit;
 use crate::value::{Value, ValueRef};
 use num_bigint::ToBigInt;
 
@@ -14,12 +15,18 @@ use alloc::vec::Vec;
 #[cfg(feature = "limited_heap")]
 const MAX_STACK_ENTRIES: usize = 1024;
 
+/// Most values that are remembered as computed in an angle mode
+const MAX_ANGLE_TAGS: usize = 16;
+
 pub struct Stack {
 	entries: Vec<ValueRef>,
 	push_new_entry: bool,
 	empty: bool,
 	undo: bool,
 	notifications: Vec<Box<dyn Fn(&StackEvent)>>,
+	/// Values on the stack that were computed using an angle mode that is not
+	/// the default
+	angle_tags: Vec<(ValueRef, AngleUnit)>,
 }
 
 pub enum StackEvent {
@@ -35,7 +42,7 @@ macro_rules! push_undo_action {
 	($undo: expr, $action: expr) => {
 		if $undo {
 			push_undo_action($action);
-			}
+		}
 	};
 }
 
@@ -47,6 +54,7 @@ impl Stack {
 			empty: true,
 			undo: false,
 			notifications: Vec::new(),
+			angle_tags: Vec::new(),
 		}
 	}
 
@@ -57,6 +65,21 @@ impl Stack {
 			empty: true,
 			undo: true,
 			notifications: Vec::new(),
+			angle_tags: Vec::new(),
+		}
+	}
+
+	/// Copy of the top `depth` entries, without undo or notifications, for
+	/// working out results without changing this stack
+	pub fn copy_top(&self, depth: usize) -> Self {
+		let start = self.entries.len() - core::cmp::min(depth, self.entries.len());
+		Stack {
+			entries: self.entries[start..].to_vec(),
+			push_new_entry: self.push_new_entry,
+			empty: self.empty,
+			undo: false,
+			notifications: Vec::new(),
+			angle_tags: Vec::new(),
 		}
 	}
 
@@ -97,7 +120,7 @@ impl Stack {
 							int = -((int ^ mask) + 1.to_bigint().unwrap());
 						}
 					}
-					Value::Number(Number::Integer(int))
+					Value::Number(Number::Integer(int.into()))
 				} else {
 					value
 				}
@@ -130,7 +153,7 @@ impl Stack {
 		Ok(value_ref.get()?)
 	}
 
-	fn entry_ref(&self, idx: usize) -> Result<&ValueRef> {
+	pub fn entry_ref(&self, idx: usize) -> Result<&ValueRef> {
 		if idx >= self.entries.len() {
 			return Err(Error::NotEnoughValues);
 		}
@@ -227,6 +250,33 @@ impl Stack {
 		Ok(())
 	}
 
+	/// Replaces the top `count` entries with several new values. The first value
+	/// ends up deepest in the stack.
+	pub fn replace_entries_with_multiple(&mut self, count: usize, items: Vec<Value>) -> Result<()> {
+		if count > self.entries.len() {
+			return Err(Error::NotEnoughValues);
+		}
+		let old_values = self.entries[self.entries.len() - count..].to_vec();
+		let item_count = items.len();
+		let mut items = items.into_iter();
+		match items.next() {
+			Some(first) => self.replace_entries_internal(count, first)?,
+			None => {
+				for _ in 0..count {
+					self.pop_internal()?;
+				}
+			}
+		}
+		for item in items {
+			self.push_internal(item)?;
+		}
+		push_undo_action!(
+			self.undo,
+			UndoAction::ReplaceWithMultiple(item_count, old_values)
+		);
+		Ok(())
+	}
+
 	pub fn replace_top_with_multiple(&mut self, items: Vec<ValueRef>) -> Result<()> {
 		let old_value = self.top_ref()?.clone();
 		if items.len() == 0 {
@@ -255,6 +305,7 @@ impl Stack {
 		match self.entries.pop() {
 			Some(value) => {
 				self.notify(StackEvent::ValuePopped);
+				self.push_new_entry = true;
 				Ok(value)
 			}
 			None => Err(Error::NotEnoughValues),
@@ -309,19 +360,52 @@ impl Stack {
 	pub fn clear(&mut self) {
 		push_undo_action!(self.undo, UndoAction::Clear(self.entries.clone()));
 		self.entries.clear();
+		self.angle_tags.clear();
 		self.notify(StackEvent::Invalidate);
 		self.push_new_entry = false;
 		self.empty = true;
 	}
 
 	pub fn enter(&mut self) -> Result<()> {
+		let tag = self.angle_tag(self.top_ref()?);
 		self.push(self.top()?.clone())?;
 		self.push_new_entry = false;
+		if let Some(unit) = tag {
+			self.tag_top_angle(unit)?;
+		}
+		Ok(())
+	}
+
+	/// Remembers that the value on top of the stack was computed in an angle
+	/// mode, so that it can be marked when it is shown. Degrees are the default
+	/// and are not remembered. Values that have left the stack are forgotten,
+	/// and only the most recent values are kept.
+	pub fn tag_top_angle(&mut self, unit: AngleUnit) -> Result<()> {
+		if unit == AngleUnit::Degrees {
+			return Ok(());
+		}
+		let top = self.top_ref()?.clone();
+		let entries = &self.entries;
+		self.angle_tags
+			.retain(|(value, _)| entries.iter().any(|entry| entry.same_object(value)));
+		if self.angle_tags.len() >= MAX_ANGLE_TAGS {
+			self.angle_tags.remove(0);
+		}
+		self.angle_tags.push((top, unit));
 		Ok(())
 	}
 
+	/// Angle mode that a value on the stack was computed in, if it was not the
+	/// default
+	pub fn angle_tag(&self, value: &ValueRef) -> Option<AngleUnit> {
+		self.angle_tags
+			.iter()
+			.find(|(tagged, _)| tagged.same_object(value))
+			.map(|(_, unit)| *unit)
+	}
+
 	pub fn input_value(&mut self, value: Value) -> Result<()> {
-		if self.push_new_entry {
+		if self.push_new_entry || self.entries.is_empty() {
 			self.push(value)
 		} else {
 			self.set_top(value)
@@ -334,9 +418,15 @@ impl Stack {
 		}
 	}
 
-	pub fn undo(&mut self) -> Result<()> {
+	pub fn undo_enabled(&self) -> bool {
+		self.undo
+	}
+
+	/// Reverses an action taken from the undo buffer. Actions that change the
+	/// context rather than the stack are handled by the context.
+	pub fn undo_action(&mut self, action: UndoAction) -> Result<()> {
 		if self.undo {
-			match pop_undo_action()? {
+			match action {
 				UndoAction::Push => {
 					self.pop_internal()?;
 				}
@@ -383,6 +473,24 @@ impl Stack {
 				UndoAction::ReplaceTopWithMultiple(count, value) => {
 					self.replace_entries_internal(count, value.get()?)?;
 				}
+				UndoAction::ReplaceWithMultiple(count, values) => {
+					for _ in 1..count {
+						self.pop_internal()?;
+					}
+					if count == 0 {
+						for value in &values {
+							self.push_internal(value.get()?)?;
+						}
+					} else if values.is_empty() {
+						self.pop_internal()?;
+					} else {
+						self.set_top_internal(values[0].get()?)?;
+						for value in &values[1..] {
+							self.push_internal(value.get()?)?;
+						}
+					}
+				}
+				UndoAction::Modes(_) => return Err(Error::InvalidArgument),
 			}
 			Ok(())
 		} else {
@@ -12,6 +12,10 @@ use alloc::boxed::Box;
 use alloc::vec::Vec;
 
 const STORAGE_SIZE: usize = 65536;
+
+/// Storage that a single operation may use by default. The rest is kept for the
+/// values already on the stack and the undo buffer.
+const DEFAULT_OPERATION_BUDGET: usize = STORAGE_SIZE / 2;
 type OffsetType = u16;
 type ReferenceType = u16;
 
@@ -122,6 +126,10 @@ struct NormalStorageRefSerializer {
 
 struct DropStorageRefSerializer;
 
+/// Serializes references by writing the referenced objects inline, so that the output does
+/// not depend on the contents of storage. Deserializing places the objects back into storage.
+struct PortableStorageRefSerializer;
+
 struct ReclaimableStorageRefSerializer {
 	cleanup: Vec<Box<dyn FnOnce()>>,
 }
@@ -208,14 +216,41 @@ impl SerializeOutput for SerializeSizer {
 	}
 }
 
+impl SerializeOutput for Vec<u8> {
+	fn size_only(&self) -> bool {
+		false
+	}
+
+	fn write(&mut self, data: &[u8]) -> Result<()> {
+		self.extend_from_slice(data);
+		Ok(())
+	}
+}
+
 impl<'a> DeserializeInput<'a> {
-	fn new(slice: &'a [u8]) -> Self {
+	pub fn new(slice: &'a [u8]) -> Self {
 		DeserializeInput {
 			buffer: slice,
 			offset: 0,
 		}
 	}
 
+	/// Number of bytes that have not been read yet
+	pub fn remaining(&self) -> usize {
+		self.buffer.len() - self.offset
+	}
+
+	/// Reads the next `len` bytes without copying them
+	pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8]> {
+		if len > self.remaining() {
+			return Err(Error::CorruptData);
+		}
+
+		let result = &self.buffer[self.offset..self.offset + len];
+		self.offset += len;
+		Ok(result)
+	}
+
 	pub fn read(&mut self, data: &mut [u8]) -> Result<()> {
 		if (self.offset + data.len()) > self.buffer.len() {
 			return Err(Error::CorruptData);
@@ -283,6 +318,12 @@ impl<T: StorageObject> StorageRef<T> {
 		Ok(result)
 	}
 
+	/// Returns true if both references point to the same stored object. Stored objects
+	/// are never modified, so this also means that the values are the same.
+	pub fn same_object(&self, other: &Self) -> bool {
+		self.offset == other.offset
+	}
+
 	fn deserialize<Ref: StorageRefSerializer>(&self, storage_ref: &Ref) -> Result<T> {
 		let (header, data) = obj_data(self.offset);
 		unsafe {
@@ -459,6 +500,26 @@ impl<T: StorageObject> StorageRefArray<T> {
 		})
 	}
 
+	/// Creates an array holding the given references
+	fn from_refs(refs: &[StorageRef<T>]) -> Result<Self> {
+		let size = core::mem::size_of::<OffsetType>() * refs.len();
+		let (buffer, _alloc_size, _used_size) = alloc_obj(size, false)?;
+
+		let array_buffer = (buffer.as_ptr() as usize + core::mem::size_of::<StorageObjectHeader>())
+			as *mut OffsetType;
+		let array_slice = unsafe { core::slice::from_raw_parts_mut(array_buffer, refs.len()) };
+		for (i, value) in refs.iter().enumerate() {
+			array_slice[i] = value.offset;
+			value.add_ref();
+		}
+
+		Ok(StorageRefArray {
+			offset: (buffer.as_ptr() as usize - HEAP.lock().bottom()) as OffsetType,
+			len: refs.len(),
+			_type: PhantomData,
+		})
+	}
+
 	pub fn len(&self) -> usize {
 		self.len
 	}
@@ -862,15 +923,113 @@ lazy_static! {
 		Mutex::new(Heap::new(backing_mem as usize, STORAGE_SIZE))
 	};
 	static ref RECLAIMABLE: Mutex<usize> = Mutex::new(0);
+	static ref PEAK_USED: Mutex<usize> = Mutex::new(0);
+	static ref OPERATION_BUDGET: Mutex<usize> = Mutex::new(DEFAULT_OPERATION_BUDGET);
+	static ref OPERATION_LIMIT: Mutex<Option<usize>> = Mutex::new(None);
+}
+
+/// Limits the storage used by an operation while it is alive. An operation that
+/// would make storage grow by more than the budget fails with `ResultTooLarge`
+/// instead of using up the memory, and as it fails before its result is placed on
+/// the stack, the stack is left as it was. Operations started while another one is
+/// running share the budget of the first.
+pub struct OperationBudget {
+	outermost: bool,
+}
+
+impl OperationBudget {
+	pub fn begin() -> Self {
+		let mut limit = OPERATION_LIMIT.lock();
+		if limit.is_some() {
+			return OperationBudget { outermost: false };
+		}
+		*limit = Some(used_bytes() + *OPERATION_BUDGET.lock());
+		OperationBudget { outermost: true }
+	}
+}
+
+impl Drop for OperationBudget {
+	fn drop(&mut self) {
+		if self.outermost {
+			*OPERATION_LIMIT.lock() = None;
+		}
+	}
+}
+
+/// Sets the number of bytes of storage that a single operation may use
+pub fn set_operation_budget(bytes: usize) {
+	*OPERATION_BUDGET.lock() = bytes;
+}
+
+pub fn operation_budget() -> usize {
+	*OPERATION_BUDGET.lock()
+}
+
+impl StorageRefSerializer for PortableStorageRefSerializer {
+	fn serialize<T: StorageObject, Out: SerializeOutput>(
+		&mut self,
+		value: &StorageRef<T>,
+		output: &mut Out,
+	) -> Result<()> {
+		value.get()?.serialize(output, self)
+	}
+
+	fn serialize_array<T: StorageObject, Out: SerializeOutput>(
+		&mut self,
+		value: &StorageRefArray<T>,
+		output: &mut Out,
+	) -> Result<()> {
+		output.write_u32(value.len as u32)?;
+		for i in 0..value.len {
+			self.serialize(&value.get(i)?, output)?;
+		}
+		Ok(())
+	}
+
+	unsafe fn deserialize<T: StorageObject>(
+		&self,
+		input: &mut DeserializeInput,
+	) -> Result<StorageRef<T>> {
+		store(T::deserialize(input, self)?)
+	}
+
+	unsafe fn deserialize_array<T: StorageObject>(
+		&self,
+		input: &mut DeserializeInput,
+	) -> Result<StorageRefArray<T>> {
+		let len = input.read_u32()? as usize;
+		if len > input.remaining() {
+			// Every element takes at least one byte, the length must be corrupt
+			return Err(Error::CorruptData);
+		}
+
+		let mut refs = Vec::new();
+		for _ in 0..len {
+			refs.push(self.deserialize(input)?);
+		}
+		StorageRefArray::from_refs(&refs)
+	}
 }
 
 fn alloc_result(layout: Layout) -> Result<(NonNull<u8>, usize)> {
+	let limit = *OPERATION_LIMIT.lock();
+	if let Some(limit) = limit {
+		if used_bytes() + layout.size() > limit {
+			return Err(Error::ResultTooLarge);
+		}
+	}
+
 	loop {
 		// Try allocating
 		let prev_used_bytes = used_bytes();
 		let result = HEAP.lock().allocate_first_fit(layout);
 		match result {
-			Ok(ptr) => return Ok((ptr, used_bytes() - prev_used_bytes)),
+			Ok(ptr) => {
+				let used = used_bytes();
+				let mut peak = PEAK_USED.lock();
+				*peak = core::cmp::max(*peak, used);
+				return Ok((ptr, used - prev_used_bytes));
+			}
 			Err(_) => (),
 		};
 
@@ -987,6 +1146,20 @@ pub fn store<T: StorageObject>(value: T) -> Result<StorageRef<T>> {
 	store_obj(value, false)
 }
 
+/// Serializes an object into a standalone buffer with all referenced objects written inline.
+/// The result does not depend on storage and can be saved across restarts.
+pub fn serialize_portable<T: StorageObject>(value: &T, output: &mut Vec<u8>) -> Result<()> {
+	value.serialize(output, &mut PortableStorageRefSerializer)
+}
+
+/// Deserializes an object written by `serialize_portable`, placing any referenced objects
+/// into storage.
+pub fn deserialize_portable<T: StorageObject>(input: &mut DeserializeInput) -> Result<T> {
+	// Portable data contains no storage offsets, so malformed input can only produce
+	// a deserialization error.
+	unsafe { T::deserialize(input, &PortableStorageRefSerializer) }
+}
+
 pub fn store_reclaimable<T: StorageObject>(value: T) -> Result<StorageRef<T>> {
 	store_obj(value, true)
 }
@@ -1006,3 +1179,34 @@ pub fn free_bytes() -> usize {
 pub fn available_bytes() -> usize {
 	free_bytes() + reclaimable_bytes()
 }
+
+/// Most storage that has been in use since the peak was last reset
+pub fn peak_used_bytes() -> usize {
+	core::cmp::max(*PEAK_USED.lock(), used_bytes())
+}
+
+pub fn reset_peak_used_bytes() {
+	*PEAK_USED.lock() = used_bytes();
+}
+
+/// Size of the largest object that can be stored without reclaiming memory.
+/// This is less than the free space when the free space is fragmented. The
+/// heap doesn't keep track of it, so it is found by trying allocations.
+pub fn largest_free_block() -> usize {
+	let mut heap = HEAP.lock();
+	let align = core::mem::align_of::<StorageObjectHeader>();
+	let mut low = 0;
+	let mut high = heap.free() + 1;
+	while low + 1 < high {
+		let size = low + (high - low) / 2;
+		let layout = Layout::from_size_align(size, align).unwrap();
+		match heap.allocate_first_fit(layout) {
+			Ok(ptr) => {
+				unsafe { heap.deallocate(ptr, layout) };
+				low = size;
+			}
+			Err(_) => high = size,
+		}
+	}
+	low
+}
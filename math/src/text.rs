@@ -0,0 +1,153 @@
+use crate::error::{Error, Result};
+use num_bigint::{BigInt, Sign};
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+const BASE64_ALPHABET: &[u8; 64] =
+	b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_PADDING: u8 = b'=';
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Bytes of a non-negative integer with the most significant byte first. Zero
+/// is a single zero byte.
+pub fn integer_to_bytes(value: &BigInt) -> Result<Vec<u8>> {
+	match value.to_bytes_be() {
+		(Sign::Minus, _) => Err(Error::ValueOutOfRange),
+		(_, bytes) => Ok(bytes),
+	}
+}
+
+/// Integer with the given bytes, most significant first
+pub fn bytes_to_integer(bytes: &[u8]) -> BigInt {
+	BigInt::from_bytes_be(Sign::Plus, bytes)
+}
+
+pub fn base64_encode(bytes: &[u8]) -> Vec<u8> {
+	let mut result = Vec::new();
+	for chunk in bytes.chunks(3) {
+		let mut group = 0u32;
+		for (idx, byte) in chunk.iter().enumerate() {
+			group |= (*byte as u32) << (16 - 8 * idx);
+		}
+		for idx in 0..4 {
+			if idx <= chunk.len() {
+				let digit = (group >> (18 - 6 * idx)) & 0x3f;
+				result.push(BASE64_ALPHABET[digit as usize]);
+			} else {
+				result.push(BASE64_PADDING);
+			}
+		}
+	}
+	result
+}
+
+/// Decodes Base64 text. Padding at the end is optional, and any other character
+/// outside of the alphabet is an invalid argument.
+pub fn base64_decode(text: &[u8]) -> Result<Vec<u8>> {
+	let end = text
+		.iter()
+		.rposition(|ch| *ch != BASE64_PADDING)
+		.map_or(0, |idx| idx + 1);
+	if text.len() - end > 2 {
+		return Err(Error::InvalidArgument);
+	}
+
+	let mut result = Vec::new();
+	let mut group = 0u32;
+	let mut bits = 0;
+	for ch in &text[..end] {
+		let digit = BASE64_ALPHABET
+			.iter()
+			.position(|digit| digit == ch)
+			.ok_or(Error::InvalidArgument)?;
+		group = (group << 6) | digit as u32;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			result.push((group >> bits) as u8);
+			group &= (1 << bits) - 1;
+		}
+	}
+
+	// A single leftover digit can't hold a whole byte
+	if bits >= 6 {
+		return Err(Error::InvalidArgument);
+	}
+	Ok(result)
+}
+
+pub fn hex_encode(bytes: &[u8]) -> Vec<u8> {
+	let mut result = Vec::new();
+	for byte in bytes {
+		result.push(HEX_DIGITS[(byte >> 4) as usize]);
+		result.push(HEX_DIGITS[(byte & 0xf) as usize]);
+	}
+	result
+}
+
+/// Decodes text made of pairs of hex digits in either case
+pub fn hex_decode(text: &[u8]) -> Result<Vec<u8>> {
+	if !text.len().is_multiple_of(2) {
+		return Err(Error::InvalidArgument);
+	}
+	let digit = |ch: u8| -> Result<u8> {
+		(ch as char)
+			.to_digit(16)
+			.map(|digit| digit as u8)
+			.ok_or(Error::InvalidArgument)
+	};
+	let mut result = Vec::new();
+	for pair in text.chunks(2) {
+		result.push((digit(pair[0])? << 4) | digit(pair[1])?);
+	}
+	Ok(result)
+}
+
+/// Shows text in quotes. Bytes that are not printable ASCII are written as
+/// `\xNN` escapes, along with backslashes and quotes, so that the text can be
+/// read back by a script.
+pub fn quoted(text: &[u8]) -> String {
+	let mut result = String::new();
+	result.push('"');
+	for byte in text {
+		match byte {
+			b'"' => result.push_str("\\\""),
+			b'\\' => result.push_str("\\\\"),
+			0x20..=0x7e => result.push(*byte as char),
+			_ => {
+				result.push_str("\\x");
+				result.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+				result.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+			}
+		}
+	}
+	result.push('"');
+	result
+}
+
+/// Reads text written by `quoted`, without the quotes. Characters beyond ASCII
+/// are kept as their UTF-8 bytes.
+pub fn unquote(text: &str) -> Result<Vec<u8>> {
+	let mut result = Vec::new();
+	let mut bytes = text.bytes();
+	while let Some(byte) = bytes.next() {
+		if byte != b'\\' {
+			result.push(byte);
+			continue;
+		}
+		match bytes.next() {
+			Some(b'x') => {
+				let high = bytes.next().ok_or(Error::SyntaxError)?;
+				let low = bytes.next().ok_or(Error::SyntaxError)?;
+				let value = hex_decode(&[high, low]).map_err(|_| Error::SyntaxError)?;
+				result.extend_from_slice(&value);
+			}
+			Some(ch @ b'"') | Some(ch @ b'\\') => result.push(ch),
+			_ => return Err(Error::SyntaxError),
+		}
+	}
+	Ok(result)
+}
@@ -1,18 +1,29 @@
 use crate::error::Result;
 use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use spin::Mutex;
 
 #[cfg(feature = "std")]
-use chrono::{DateTime, Local};
+use chrono::DateTime;
+
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+use chrono::Local;
 
 #[cfg(not(feature = "std"))]
 use alloc::string::{String, ToString};
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 
-#[cfg(not(feature = "std"))]
-#[cfg(not(feature = "dm42"))]
+#[cfg(any(not(feature = "std"), target_arch = "wasm32"))]
 use crate::error::Error;
 
+// The web page provides the local time, as there is no clock in WebAssembly
+#[cfg(feature = "std")]
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+	fn rscalc_local_time_ms() -> f64;
+}
+
 pub trait Now: Sized {
 	/// Gets the current date and time in the local timezone.
 	fn now() -> Result<Self>;
@@ -35,58 +46,51 @@ pub trait SimpleDateTimeToString {
 	fn simple_format(&self, format: &SimpleDateTimeFormat) -> String;
 }
 
-#[cfg(feature = "dm42")]
-#[repr(C)]
-struct dt_t {
-	year: u16,
-	month: u8,
-	day: u8,
+/// Real time clock of the platform. Platforms without a clock in the standard
+/// library, such as calculator hardware, provide theirs with `set_clock`.
+pub trait Clock: Sync {
+	/// Gets the current date and time in the local timezone.
+	fn now(&self) -> Result<NaiveDateTime>;
 }
 
-#[cfg(feature = "dm42")]
-#[repr(C)]
-struct tm_t {
-	hour: u8,
-	min: u8,
-	sec: u8,
-	csec: u8,
-	dow: u8,
+static CLOCK: Mutex<Option<&'static dyn Clock>> = Mutex::new(None);
+
+/// Sets the clock that gives the current time, in place of the clock of the
+/// standard library if there is one
+pub fn set_clock(clock: &'static dyn Clock) {
+	*CLOCK.lock() = Some(clock);
 }
 
 impl Now for NaiveDateTime {
-	#[cfg(feature = "dm42")]
 	fn now() -> Result<Self> {
-		unsafe {
-			const LIBRARY_BASE: usize = 0x8000201;
-			let func_ptr: usize = LIBRARY_BASE + 204;
-			let func: extern "C" fn(time: *mut tm_t, date: *mut dt_t) =
-				core::mem::transmute(func_ptr);
-			let mut date = core::mem::MaybeUninit::<dt_t>::uninit();
-			let mut time = core::mem::MaybeUninit::<tm_t>::uninit();
-			func(time.as_mut_ptr(), date.as_mut_ptr());
-			let date = date.assume_init();
-			let time = time.assume_init();
-			let date = NaiveDate::from_ymd(date.year as i32, date.month as u32, date.day as u32);
-			let time = NaiveTime::from_hms_milli(
-				time.hour as u32,
-				time.min as u32,
-				time.sec as u32,
-				time.csec as u32 * 10,
-			);
-			Ok(NaiveDateTime::new(date, time))
+		let clock = *CLOCK.lock();
+		match clock {
+			Some(clock) => clock.now(),
+			None => system_now(),
 		}
 	}
+}
 
-	#[cfg(not(feature = "std"))]
-	#[cfg(not(feature = "dm42"))]
-	fn now() -> Result<Self> {
-		Err(Error::ValueNotDefined)
-	}
+#[cfg(not(feature = "std"))]
+fn system_now() -> Result<NaiveDateTime> {
+	Err(Error::ValueNotDefined)
+}
 
-	#[cfg(feature = "std")]
-	fn now() -> Result<Self> {
-		let result: DateTime<Local> = Local::now();
-		Ok(result.naive_local())
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+fn system_now() -> Result<NaiveDateTime> {
+	let result: DateTime<Local> = Local::now();
+	Ok(result.naive_local())
+}
+
+#[cfg(feature = "std")]
+#[cfg(target_arch = "wasm32")]
+fn system_now() -> Result<NaiveDateTime> {
+	// The page gives the local time as milliseconds since the epoch
+	let ms = unsafe { rscalc_local_time_ms() };
+	match DateTime::from_timestamp_millis(ms as i64) {
+		Some(time) => Ok(time.naive_utc()),
+		None => Err(Error::InvalidTime),
 	}
 }
 
@@ -20,6 +20,10 @@ pub enum UndoAction {
 	RotateDown,
 	SetStackEntry(usize, ValueRef),
 	ReplaceTopWithMultiple(usize, ValueRef),
+	ReplaceWithMultiple(usize, Vec<ValueRef>),
+	/// Display format and modes as they were before a setting was changed, in
+	/// the form written by the context
+	Modes(Vec<u8>),
 }
 
 type UndoActionRef = StorageRef<UndoAction>;
@@ -36,6 +40,8 @@ const UNDO_SERIALIZE_TYPE_CLEAR: u8 = 4;
 const UNDO_SERIALIZE_TYPE_ROTATE_DOWN: u8 = 5;
 const UNDO_SERIALIZE_TYPE_SET_STACK_ENTRY: u8 = 6;
 const UNDO_SERIALIZE_TYPE_REPLACE_TOP_WITH_MULTIPLE: u8 = 7;
+const UNDO_SERIALIZE_TYPE_REPLACE_WITH_MULTIPLE: u8 = 8;
+const UNDO_SERIALIZE_TYPE_MODES: u8 = 9;
 
 impl StorageObject for UndoAction {
 	fn serialize<Ref: StorageRefSerializer, Out: SerializeOutput>(
@@ -83,6 +89,19 @@ impl StorageObject for UndoAction {
 				output.write_u32(*count as u32)?;
 				storage_refs.serialize(value, output)?;
 			}
+			UndoAction::ReplaceWithMultiple(count, values) => {
+				output.write_u8(UNDO_SERIALIZE_TYPE_REPLACE_WITH_MULTIPLE)?;
+				output.write_u32(*count as u32)?;
+				output.write_u32(values.len() as u32)?;
+				for value in values {
+					storage_refs.serialize(value, output)?;
+				}
+			}
+			UndoAction::Modes(modes) => {
+				output.write_u8(UNDO_SERIALIZE_TYPE_MODES)?;
+				output.write_u32(modes.len() as u32)?;
+				output.write(modes)?;
+			}
 		}
 		Ok(())
 	}
@@ -128,6 +147,19 @@ impl StorageObject for UndoAction {
 				let value = storage_refs.deserialize(input)?;
 				Ok(UndoAction::ReplaceTopWithMultiple(count, value))
 			}
+			UNDO_SERIALIZE_TYPE_REPLACE_WITH_MULTIPLE => {
+				let count = input.read_u32()? as usize;
+				let value_count = input.read_u32()? as usize;
+				let mut values = Vec::with_capacity(value_count);
+				for _ in 0..value_count {
+					values.push(storage_refs.deserialize(input)?);
+				}
+				Ok(UndoAction::ReplaceWithMultiple(count, values))
+			}
+			UNDO_SERIALIZE_TYPE_MODES => {
+				let len = input.read_u32()? as usize;
+				Ok(UndoAction::Modes(input.read_slice(len)?.to_vec()))
+			}
 			_ => Err(Error::CorruptData),
 		}
 	}
@@ -205,6 +237,12 @@ pub fn pop_undo_action() -> Result<UndoAction> {
 		UndoAction::ReplaceTopWithMultiple(count, value) => {
 			UndoAction::ReplaceTopWithMultiple(count, Value::deep_copy_value(value)?)
 		}
+		UndoAction::ReplaceWithMultiple(count, mut values) => {
+			for value in &mut values {
+				*value = Value::deep_copy_value(value.clone())?;
+			}
+			UndoAction::ReplaceWithMultiple(count, values)
+		}
 		entry => entry,
 	})
 }
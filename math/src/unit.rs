@@ -12,6 +12,10 @@ use std::collections::BTreeMap;
 use alloc::borrow::Cow;
 #[cfg(not(feature = "std"))]
 use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum AngleUnit {
@@ -1232,6 +1236,14 @@ impl CompositeUnit {
 		CompositeUnit { units }
 	}
 
+	pub fn from_units(units: &[(Unit, i32)]) -> Self {
+		let mut result = CompositeUnit::new();
+		for (unit, power) in units {
+			result.units.insert(unit.unit_type(), (*unit, *power));
+		}
+		result
+	}
+
 	pub fn unitless(&self) -> bool {
 		self.units.len() == 0
 	}
@@ -1596,6 +1608,117 @@ impl CompositeUnit {
 	}
 }
 
+const KILOGRAMS: Unit = Unit::Mass(MassUnit::Kilograms);
+const METERS: Unit = Unit::Distance(DistanceUnit::Meters);
+const SECONDS: Unit = Unit::Time(TimeUnit::Seconds);
+const JOULES: Unit = Unit::Energy(EnergyUnit::Joules);
+const NEWTONS: Unit = Unit::Force(ForceUnit::Newton);
+const WATTS: Unit = Unit::Power(PowerUnit::Watts);
+const PASCALS: Unit = Unit::Pressure(PressureUnit::Pascals);
+
+/// Named unit that compound unit results are simplified into. Each entry lists the
+/// compound forms, in SI units, that are equivalent to the named SI unit. The
+/// target unit is the user's preferred unit of that type for displaying results,
+/// or `None` if simplification into this unit is disabled.
+#[derive(Clone)]
+pub struct PreferredUnit {
+	base: Unit,
+	compound_forms: &'static [&'static [(Unit, i32)]],
+	target: Option<Unit>,
+}
+
+impl PreferredUnit {
+	pub fn defaults() -> Vec<PreferredUnit> {
+		vec![
+			PreferredUnit {
+				base: WATTS,
+				compound_forms: &[
+					&[(KILOGRAMS, 1), (METERS, 2), (SECONDS, -3)],
+					&[(JOULES, 1), (SECONDS, -1)],
+					&[(NEWTONS, 1), (METERS, 1), (SECONDS, -1)],
+				],
+				target: Some(WATTS),
+			},
+			PreferredUnit {
+				base: JOULES,
+				compound_forms: &[
+					&[(KILOGRAMS, 1), (METERS, 2), (SECONDS, -2)],
+					&[(NEWTONS, 1), (METERS, 1)],
+					&[(WATTS, 1), (SECONDS, 1)],
+				],
+				target: Some(JOULES),
+			},
+			PreferredUnit {
+				base: NEWTONS,
+				compound_forms: &[
+					&[(KILOGRAMS, 1), (METERS, 1), (SECONDS, -2)],
+					&[(JOULES, 1), (METERS, -1)],
+					&[(PASCALS, 1), (METERS, 2)],
+				],
+				target: Some(NEWTONS),
+			},
+			PreferredUnit {
+				base: PASCALS,
+				compound_forms: &[
+					&[(KILOGRAMS, 1), (METERS, -1), (SECONDS, -2)],
+					&[(NEWTONS, 1), (METERS, -2)],
+					&[(JOULES, 1), (METERS, -3)],
+				],
+				target: Some(PASCALS),
+			},
+		]
+	}
+
+	pub fn unit_type(&self) -> UnitType {
+		self.base.unit_type()
+	}
+
+	pub fn base(&self) -> Unit {
+		self.base
+	}
+
+	pub fn target(&self) -> Option<Unit> {
+		self.target
+	}
+
+	/// Sets the preferred unit. Units of a different type are ignored.
+	pub fn set_target(&mut self, target: Option<Unit>) {
+		match target {
+			Some(unit) if unit.unit_type() != self.unit_type() => (),
+			target => self.target = target,
+		}
+	}
+
+	/// Moves to the next unit of this type as the preferred unit. After the last
+	/// unit, simplification is disabled.
+	pub fn cycle_target(&mut self) {
+		let units = self.unit_type().units();
+		self.target = match self.target {
+			Some(target) => match units.iter().position(|unit| *unit == target) {
+				Some(idx) => units.get(idx + 1).cloned(),
+				None => None,
+			},
+			None => units.first().cloned(),
+		};
+	}
+
+	/// Converts a value with compound units into the preferred unit, if the units are
+	/// equivalent to it.
+	pub fn simplify(&self, value: &Number, unit: &CompositeUnit) -> Option<Number> {
+		let target = self.target?;
+		for form in self.compound_forms {
+			let form = CompositeUnit::from_units(form);
+			if let Ok(value) = unit.coerce_to_other(value, &form) {
+				// The compound form is in coherent SI units, so the value is the same
+				// when expressed in the named SI unit.
+				let mut result_unit = CompositeUnit::single_unit(self.base);
+				return result_unit.convert_single_unit(&value, target).ok();
+			}
+		}
+		None
+	}
+}
+
 impl StorageObject for CompositeUnit {
 	fn serialize<Ref: StorageRefSerializer, Out: SerializeOutput>(
 		&self,
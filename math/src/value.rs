@@ -1,17 +1,22 @@
 use crate::complex::ComplexNumber;
 use crate::error::{Error, Result};
 use crate::format::{Format, FormatResult};
+use crate::list::List;
 use crate::matrix::Matrix;
+use crate::modes::ArithmeticModes;
 use crate::number::{Number, ToNumber};
+use crate::quaternion::Quaternion;
 use crate::storage::{
 	store, DeserializeInput, SerializeOutput, StorageObject, StorageRef, StorageRefSerializer,
 };
+use crate::text::quoted;
 use crate::time::{SimpleDateTimeFormat, SimpleDateTimeToString};
-use crate::unit::{AngleUnit, CompositeUnit, TimeUnit, Unit};
+use crate::unit::{AngleUnit, CompositeUnit, TimeUnit, Unit, UnitConversion};
 use crate::vector::Vector;
 use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use core::ops::Add;
-use num_bigint::BigInt;
+use intel_dfp::Decimal;
+use num_bigint::{BigInt, ToBigInt};
 
 #[cfg(feature = "std")]
 use std::borrow::Cow;
@@ -23,6 +28,8 @@ use alloc::borrow::Cow;
 #[cfg(not(feature = "std"))]
 use alloc::string::{String, ToString};
 #[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
 use core::convert::TryFrom;
 
 #[derive(Clone)]
@@ -30,15 +37,26 @@ pub enum Value {
 	Number(Number),
 	NumberWithUnit(Number, CompositeUnit),
 	Complex(ComplexNumber),
+	Quaternion(Quaternion),
 	DateTime(NaiveDateTime),
 	Date(NaiveDate),
 	Time(NaiveTime),
 	Vector(Vector),
 	Matrix(Matrix),
+	List(List),
+	/// Text made of bytes, such as the bytes of an integer shown as ASCII
+	Text(Vec<u8>),
 }
 
 pub type ValueRef = StorageRef<Value>;
 
+#[derive(Clone, Copy)]
+enum TrigFunction {
+	Sin,
+	Cos,
+	Tan,
+}
+
 impl Value {
 	/// Deep copies a value onto the non-reclaimable heap. This is used when pulling values out
 	/// of reclaimable memory.
@@ -47,6 +65,7 @@ impl Value {
 		match &mut value {
 			Value::Vector(vector) => vector.deep_copy_values()?,
 			Value::Matrix(matrix) => matrix.deep_copy_values()?,
+			Value::List(list) => list.deep_copy_values()?,
 			_ => (),
 		};
 		store(value)
@@ -69,6 +88,18 @@ impl Value {
 		}
 	}
 
+	/// Fully reduces any rational numbers in the value. Used to complete reduction
+	/// that was deferred during a chain of calculations.
+	pub fn reduce(self) -> Self {
+		match self {
+			Value::Number(num) => Value::Number(num.reduce()),
+			Value::NumberWithUnit(num, unit) => Value::NumberWithUnit(num.reduce(), unit),
+			Value::Complex(value) => Value::Complex(value.reduce()),
+			Value::Quaternion(value) => Value::Quaternion(value.reduce()),
+			_ => self,
+		}
+	}
+
 	pub fn to_int<'a>(&'a self) -> Result<Cow<'a, BigInt>> {
 		match self {
 			Value::Number(num) => num.to_int(),
@@ -82,10 +113,10 @@ impl Value {
 			Value::Number(Number::Integer(_)) => Ok(Cow::Borrowed(self)),
 			Value::NumberWithUnit(Number::Integer(_), _) => Ok(Cow::Borrowed(self)),
 			Value::Number(num) => Ok(Cow::Owned(Value::Number(Number::Integer(
-				num.to_int()?.into_owned(),
+				num.to_int()?.into_owned().into(),
 			)))),
 			Value::NumberWithUnit(num, unit) => Ok(Cow::Owned(Value::NumberWithUnit(
-				Number::Integer(num.to_int()?.into_owned()),
+				Number::Integer(num.to_int()?.into_owned().into()),
 				unit.clone(),
 			))),
 			_ => Err(Error::NotARealNumber),
@@ -97,6 +128,7 @@ impl Value {
 			Value::Number(num) => num.to_string(),
 			Value::NumberWithUnit(num, _) => num.to_string(),
 			Value::Complex(num) => num.to_string(),
+			Value::Quaternion(num) => num.to_string(),
 			Value::DateTime(dt) => dt.simple_format(&SimpleDateTimeFormat::full(false)),
 			Value::Date(date) => date.simple_format(&SimpleDateTimeFormat::date(false)),
 			Value::Time(time) => time.simple_format(&SimpleDateTimeFormat::time(false)),
@@ -109,6 +141,10 @@ impl Value {
 					+ "×" + &matrix.cols().to_number().to_string()
 					+ " matrix⟫"
 			}
+			Value::List(list) => {
+				"⟪".to_string() + &list.len().to_number().to_string() + " elem list⟫"
+			}
+			Value::Text(text) => quoted(text),
 		}
 	}
 
@@ -117,6 +153,7 @@ impl Value {
 			Value::Number(num) => format.format_number(num),
 			Value::NumberWithUnit(num, _) => format.format_number(num),
 			Value::Complex(num) => FormatResult::Complex(num.format(format)),
+			Value::Quaternion(num) => FormatResult::Object(num.format(format)),
 			Value::DateTime(dt) => FormatResult::Object(
 				dt.simple_format(&SimpleDateTimeFormat::full(format.time_24_hour)),
 			),
@@ -126,24 +163,88 @@ impl Value {
 			Value::Time(time) => FormatResult::Object(
 				time.simple_format(&SimpleDateTimeFormat::time(format.time_24_hour)),
 			),
+			Value::List(list) => {
+				let mut result = "{".to_string();
+				for i in 0..list.len() {
+					match list.get(i) {
+						Ok(value) => {
+							result += &(" ".to_string() + &value.format(format).to_string())
+						}
+						Err(_) => return FormatResult::Object(self.to_string()),
+					}
+				}
+				FormatResult::Object(result + " }")
+			}
 			_ => FormatResult::Object(self.to_string()),
 		}
 	}
 
-	pub fn is_vector_or_matrix(&self) -> bool {
+	/// Returns true for vectors, matrices and lists, which can't be elements of
+	/// each other
+	pub fn is_collection(&self) -> bool {
 		match self {
-			Value::Vector(_) | Value::Matrix(_) => true,
+			Value::Vector(_) | Value::Matrix(_) | Value::List(_) => true,
 			_ => false,
 		}
 	}
 
+	/// Result of a real function for an argument outside of its real domain
+	fn outside_real_domain<F: FnOnce(&ComplexNumber) -> ComplexNumber>(
+		&self,
+		func: F,
+	) -> Result<Value> {
+		if ArithmeticModes::active().complex_results {
+			Self::check_complex(func(&*self.complex_number()?))
+		} else {
+			Err(Error::InvalidArgument)
+		}
+	}
+
+	/// Checks the result of a real function for a real argument. Results that are
+	/// not a number come from arguments the function is not defined for.
+	fn check_real(value: Number) -> Result<Value> {
+		if value.is_nan() {
+			Err(Error::InvalidArgument)
+		} else {
+			Ok(Value::Number(value))
+		}
+	}
+
+	/// Raises to a power. Zero to the power of zero is one, and zero to a negative
+	/// power is a division by zero.
 	pub fn pow(&self, power: &Value) -> Result<Value> {
 		if let Value::Complex(value) = self {
 			Self::check_complex(value.pow(&*power.complex_number()?))
 		} else if let Value::Complex(power) = power {
+			if self.real_number()?.is_zero() {
+				// Zero to a power with a positive real part is zero, and has no value
+				// for other complex powers
+				let real = power.real_part();
+				return if real.is_negative() || real.is_zero() {
+					Err(Error::DivideByZero)
+				} else {
+					Ok(Value::Number(0.into()))
+				};
+			}
 			Self::check_complex(self.complex_number()?.pow(power))
 		} else {
-			Ok(Value::Number(self.real_number()?.pow(power.real_number()?)))
+			let base = self.real_number()?;
+			let power_num = power.real_number()?;
+			if base.is_zero() && power_num.is_negative() {
+				Err(Error::DivideByZero)
+			} else if base.is_negative() && !Self::is_integral(power_num) {
+				let power = power.complex_number()?;
+				self.outside_real_domain(|value| value.pow(&power))
+			} else {
+				Self::check_real(base.pow(power_num))
+			}
+		}
+	}
+
+	fn is_integral(num: &Number) -> bool {
+		match num {
+			Number::Integer(_) => true,
+			_ => num.to_decimal().fract() == Decimal::zero(),
 		}
 	}
 
@@ -153,9 +254,9 @@ impl Value {
 		} else {
 			let value = self.real_number()?;
 			if value.is_negative() {
-				Self::check_complex(ComplexNumber::from_real(value.clone()).sqrt())
+				self.outside_real_domain(|value| value.sqrt())
 			} else {
-				Ok(Value::Number(self.real_number()?.sqrt()))
+				Self::check_real(value.sqrt())
 			}
 		}
 	}
@@ -163,10 +264,13 @@ impl Value {
 	pub fn log(&self) -> Result<Value> {
 		if let Value::Complex(value) = self {
 			Self::check_complex(value.log())
+		} else if self.real_number()?.is_zero() {
+			// The logarithm of zero is infinite
+			Err(Error::InvalidArgument)
 		} else if self.real_number()?.is_negative() {
-			Self::check_complex(self.complex_number()?.log())
+			self.outside_real_domain(|value| value.log())
 		} else {
-			Ok(Value::Number(self.real_number()?.log()))
+			Self::check_real(self.real_number()?.log())
 		}
 	}
 
@@ -181,10 +285,12 @@ impl Value {
 	pub fn ln(&self) -> Result<Value> {
 		if let Value::Complex(value) = self {
 			Self::check_complex(value.ln())
+		} else if self.real_number()?.is_zero() {
+			Err(Error::InvalidArgument)
 		} else if self.real_number()?.is_negative() {
-			Self::check_complex(self.complex_number()?.ln())
+			self.outside_real_domain(|value| value.ln())
 		} else {
-			Ok(Value::Number(self.real_number()?.ln()))
+			Self::check_real(self.real_number()?.ln())
 		}
 	}
 
@@ -197,56 +303,126 @@ impl Value {
 	}
 
 	pub fn sin(&self, angle_mode: AngleUnit) -> Result<Value> {
+		if let Some(result) = self.exact_trig(angle_mode, TrigFunction::Sin)? {
+			return Ok(Value::Number(result));
+		}
 		match self {
-			Value::NumberWithUnit(num, unit) => {
-				match unit
-					.clone()
-					.convert_single_unit(num, AngleUnit::Radians.into())
-				{
-					Ok(value) => Ok(Value::Number(value.sin())),
-					_ => Ok(Value::Number(num.angle_to_radians(angle_mode).sin())),
-				}
-			}
 			Value::Complex(value) => Self::check_complex(value.sin()),
-			_ => Ok(Value::Number(
-				self.real_number()?.angle_to_radians(angle_mode).sin(),
-			)),
+			_ => Ok(Value::Number(self.radians(angle_mode)?.sin())),
 		}
 	}
 
 	pub fn cos(&self, angle_mode: AngleUnit) -> Result<Value> {
+		if let Some(result) = self.exact_trig(angle_mode, TrigFunction::Cos)? {
+			return Ok(Value::Number(result));
+		}
 		match self {
-			Value::NumberWithUnit(num, unit) => {
-				match unit
-					.clone()
-					.convert_single_unit(num, AngleUnit::Radians.into())
-				{
-					Ok(value) => Ok(Value::Number(value.cos())),
-					_ => Ok(Value::Number(num.angle_to_radians(angle_mode).cos())),
-				}
-			}
 			Value::Complex(value) => Self::check_complex(value.cos()),
-			_ => Ok(Value::Number(
-				self.real_number()?.angle_to_radians(angle_mode).cos(),
-			)),
+			_ => Ok(Value::Number(self.radians(angle_mode)?.cos())),
 		}
 	}
 
 	pub fn tan(&self, angle_mode: AngleUnit) -> Result<Value> {
+		if let Some(result) = self.exact_trig(angle_mode, TrigFunction::Tan)? {
+			return Ok(Value::Number(result));
+		}
 		match self {
-			Value::NumberWithUnit(num, unit) => {
-				match unit
-					.clone()
-					.convert_single_unit(num, AngleUnit::Radians.into())
-				{
-					Ok(value) => Ok(Value::Number(value.tan())),
-					_ => Ok(Value::Number(num.angle_to_radians(angle_mode).tan())),
-				}
-			}
 			Value::Complex(value) => Self::check_complex(value.tan()),
-			_ => Ok(Value::Number(
-				self.real_number()?.angle_to_radians(angle_mode).tan(),
+			_ => Ok(Value::Number(self.radians(angle_mode)?.tan())),
+		}
+	}
+
+	/// Angle tagged with `unit`. Angles that are already tagged are converted,
+	/// and numbers without a unit are taken to be in the angle mode.
+	pub fn to_angle(&self, unit: AngleUnit, angle_mode: AngleUnit) -> Result<Value> {
+		match self {
+			Value::Number(num) => Ok(Value::NumberWithUnit(
+				angle_mode.to_unit(num, &unit),
+				CompositeUnit::single_unit(unit.into()),
 			)),
+			Value::NumberWithUnit(_, _) => self.convert_single_unit(unit.into()),
+			_ => Err(Error::NotARealNumber),
+		}
+	}
+
+	/// Real angle in radians, using the angle unit of the value if it has one
+	fn radians(&self, angle_mode: AngleUnit) -> Result<Number> {
+		if let Value::NumberWithUnit(num, unit) = self {
+			match unit.units.values().next() {
+				Some((Unit::Angle(angle), 1)) if unit.units.len() == 1 => {
+					return Ok(num.angle_to_radians(*angle).into_owned());
+				}
+				_ => {
+					if let Ok(value) = unit
+						.clone()
+						.convert_single_unit(num, AngleUnit::Radians.into())
+					{
+						return Ok(value);
+					}
+				}
+			}
+		}
+		Ok(self
+			.real_number()?
+			.angle_to_radians(angle_mode)
+			.into_owned())
+	}
+
+	/// Exact angle in degrees, if the value is an exact rational angle in degrees
+	/// or gradians. Angles in radians are never exact because π is not.
+	fn exact_degrees(&self, angle_mode: AngleUnit) -> Option<Number> {
+		let (num, unit) = match self {
+			Value::Number(num) => (num, angle_mode),
+			Value::NumberWithUnit(num, unit) => match unit.units.values().next() {
+				Some((Unit::Angle(angle), 1)) if unit.units.len() == 1 => (num, *angle),
+				_ => return None,
+			},
+			_ => return None,
+		};
+		match (num, unit) {
+			(Number::Decimal(_), _) | (_, AngleUnit::Radians) => None,
+			_ => Some(unit.to_unit(num, &AngleUnit::Degrees)),
+		}
+	}
+
+	/// Result of a trigonometric function for angles that are a multiple of 30 or
+	/// 45 degrees and have a rational result, such as the sine of 30 degrees being
+	/// exactly 1/2. Returns `None` for other angles, which are computed in decimal.
+	fn exact_trig(&self, angle_mode: AngleUnit, func: TrigFunction) -> Result<Option<Number>> {
+		let degrees = match self.exact_degrees(angle_mode) {
+			Some(degrees) => degrees,
+			None => return Ok(None),
+		};
+
+		// Find the angle in steps of 15 degrees within one turn
+		let steps = match (degrees / 15.to_number()).reduce() {
+			Number::Integer(steps) => steps.into_bigint(),
+			_ => return Ok(None),
+		};
+		let turn = 24.to_bigint().unwrap();
+		let step = i32::try_from(((steps % &turn) + &turn) % &turn)?;
+
+		// The sine of each multiple of 30 degrees is rational
+		let sin = |step: i32| match step % 24 {
+			0 | 12 => Some(0.to_number()),
+			2 | 10 => Some(1.to_number() / 2.to_number()),
+			6 => Some(1.to_number()),
+			14 | 22 => Some(-1.to_number() / 2.to_number()),
+			18 => Some(-1.to_number()),
+			_ => None,
+		};
+		match func {
+			TrigFunction::Sin => Ok(sin(step)),
+			// Cosine is the sine a quarter turn later
+			TrigFunction::Cos => Ok(sin(step + 6)),
+			TrigFunction::Tan => match step {
+				0 | 12 => Ok(Some(0.to_number())),
+				3 | 15 => Ok(Some(1.to_number())),
+				9 | 21 => Ok(Some(-1.to_number())),
+				// The tangent is infinite at a quarter turn
+				6 | 18 => Err(Error::InvalidArgument),
+				_ => Ok(None),
+			},
 		}
 	}
 
@@ -256,7 +432,7 @@ impl Value {
 		} else {
 			let result = self.real_number()?.asin();
 			if result.is_nan() {
-				Self::check_complex(self.complex_number()?.asin())
+				self.outside_real_domain(|value| value.asin())
 			} else {
 				Ok(Value::NumberWithUnit(
 					result.angle_from_radians(angle_mode).into_owned(),
@@ -272,7 +448,7 @@ impl Value {
 		} else {
 			let result = self.real_number()?.acos();
 			if result.is_nan() {
-				Self::check_complex(self.complex_number()?.acos())
+				self.outside_real_domain(|value| value.acos())
 			} else {
 				Ok(Value::NumberWithUnit(
 					result.angle_from_radians(angle_mode).into_owned(),
@@ -288,7 +464,7 @@ impl Value {
 		} else {
 			let result = self.real_number()?.atan();
 			if result.is_nan() {
-				Self::check_complex(self.complex_number()?.atan())
+				self.outside_real_domain(|value| value.atan())
 			} else {
 				Ok(Value::NumberWithUnit(
 					result.angle_from_radians(angle_mode).into_owned(),
@@ -325,7 +501,7 @@ impl Value {
 			_ => {
 				let result = self.real_number()?.asinh();
 				if result.is_nan() {
-					Self::check_complex(self.complex_number()?.asinh())
+					self.outside_real_domain(|value| value.asinh())
 				} else {
 					Ok(Value::Number(result))
 				}
@@ -339,7 +515,7 @@ impl Value {
 			_ => {
 				let result = self.real_number()?.acosh();
 				if result.is_nan() {
-					Self::check_complex(self.complex_number()?.acosh())
+					self.outside_real_domain(|value| value.acosh())
 				} else {
 					Ok(Value::Number(result))
 				}
@@ -353,7 +529,10 @@ impl Value {
 			_ => {
 				let result = self.real_number()?.atanh();
 				if result.is_nan() {
-					Self::check_complex(self.complex_number()?.atanh())
+					self.outside_real_domain(|value| value.atanh())
+				} else if result.to_decimal().is_infinite() {
+					// The inverse hyperbolic tangent of one is infinite
+					Err(Error::InvalidArgument)
 				} else {
 					Ok(Value::Number(result))
 				}
@@ -442,6 +621,37 @@ impl Value {
 		}
 	}
 
+	pub fn check_quaternion(value: Quaternion) -> Result<Value> {
+		if value.is_out_of_range() {
+			Err(Error::ValueOutOfRange)
+		} else if value.is_real() {
+			Ok(Value::Number(value.take_real_part()))
+		} else {
+			Ok(Value::Quaternion(value))
+		}
+	}
+
+	/// Quaternion with the value of a real or complex number or quaternion
+	pub fn quaternion<'a>(&'a self) -> Result<Cow<'a, Quaternion>> {
+		match self {
+			Value::Quaternion(value) => Ok(Cow::Borrowed(value)),
+			Value::Complex(value) => Ok(Cow::Owned(Quaternion::from_complex(value))),
+			_ => Ok(Cow::Owned(Quaternion::from_real(
+				self.real_number()?.clone(),
+			))),
+		}
+	}
+
+	/// Result of an arithmetic operation where one side is a quaternion and
+	/// the other is a real or complex number or a quaternion
+	fn quaternion_operation(
+		&self,
+		rhs: &Value,
+		op: fn(&Quaternion, &Quaternion) -> Result<Quaternion>,
+	) -> Result<Value> {
+		Self::check_quaternion(op(&*self.quaternion()?, &*rhs.quaternion()?)?)
+	}
+
 	fn value_add(&self, rhs: &Value) -> Result<Value> {
 		match self {
 			Value::Number(left) => match rhs {
@@ -455,6 +665,7 @@ impl Value {
 				Value::DateTime(right) => self.datetime_add_secs(right, left),
 				Value::Date(right) => self.date_add_days(right, left),
 				Value::Time(right) => self.time_add_secs(right, left),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a + b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::NumberWithUnit(left, left_unit) => match rhs {
@@ -487,6 +698,7 @@ impl Value {
 						&CompositeUnit::single_unit(TimeUnit::Seconds.into()),
 					)?,
 				),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a + b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Complex(left) => match rhs {
@@ -497,6 +709,7 @@ impl Value {
 					Self::check_complex(left + &ComplexNumber::from_real(right.clone()))
 				}
 				Value::Complex(right) => Self::check_complex(left + right),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a + b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::DateTime(left) => match rhs {
@@ -573,6 +786,8 @@ impl Value {
 				}
 				_ => Err(Error::DataTypeMismatch),
 			},
+			Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a + b)),
+			Value::List(_) | Value::Text(_) => Err(Error::DataTypeMismatch),
 		}
 	}
 
@@ -586,6 +801,7 @@ impl Value {
 				Value::Complex(right) => {
 					Self::check_complex(&ComplexNumber::from_real(left.clone()) - right)
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a - b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::NumberWithUnit(left, left_unit) => match rhs {
@@ -597,6 +813,7 @@ impl Value {
 				Value::Complex(right) => {
 					Self::check_complex(&ComplexNumber::from_real(left.clone()) - right)
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a - b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Complex(left) => match rhs {
@@ -607,6 +824,7 @@ impl Value {
 					Self::check_complex(left - &ComplexNumber::from_real(right.clone()))
 				}
 				Value::Complex(right) => Self::check_complex(left - right),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a - b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::DateTime(left) => match rhs {
@@ -702,6 +920,8 @@ impl Value {
 				}
 				_ => Err(Error::DataTypeMismatch),
 			},
+			Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a - b)),
+			Value::List(_) | Value::Text(_) => Err(Error::DataTypeMismatch),
 		}
 	}
 
@@ -757,6 +977,7 @@ impl Value {
 					}
 					Ok(Value::Matrix(result))
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a * b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::NumberWithUnit(left, left_unit) => match rhs {
@@ -787,6 +1008,7 @@ impl Value {
 					}
 					Ok(Value::Matrix(result))
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a * b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Complex(left) => match rhs {
@@ -797,6 +1019,7 @@ impl Value {
 					Self::check_complex(left * &ComplexNumber::from_real(right.clone()))
 				}
 				Value::Complex(right) => Self::check_complex(left * right),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a * b)),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Vector(left) => match rhs {
@@ -874,11 +1097,21 @@ impl Value {
 				}
 				_ => Err(Error::DataTypeMismatch),
 			},
+			Value::Quaternion(_) => self.quaternion_operation(rhs, |a, b| Ok(a * b)),
 			_ => Err(Error::DataTypeMismatch),
 		}
 	}
 
 	fn value_div(&self, rhs: &Value) -> Result<Value> {
+		// Dividing by zero is an error instead of an infinite result, so that the
+		// stack is left as it was
+		match rhs {
+			Value::Number(right) | Value::NumberWithUnit(right, _) if right.is_zero() => {
+				return Err(Error::DivideByZero);
+			}
+			_ => (),
+		}
+
 		match self {
 			Value::Number(left) => match rhs {
 				Value::Number(right) => Ok(Value::Number(left / right)),
@@ -888,6 +1121,7 @@ impl Value {
 				Value::Complex(right) => {
 					Self::check_complex(&ComplexNumber::from_real(left.clone()) / right)
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, Quaternion::divide),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::NumberWithUnit(left, left_unit) => match rhs {
@@ -900,6 +1134,7 @@ impl Value {
 				Value::Complex(right) => {
 					Self::check_complex(&ComplexNumber::from_real(left.clone()) / right)
 				}
+				Value::Quaternion(_) => self.quaternion_operation(rhs, Quaternion::divide),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Complex(left) => match rhs {
@@ -910,6 +1145,7 @@ impl Value {
 					Self::check_complex(left / &ComplexNumber::from_real(right.clone()))
 				}
 				Value::Complex(right) => Self::check_complex(left / right),
+				Value::Quaternion(_) => self.quaternion_operation(rhs, Quaternion::divide),
 				_ => Err(Error::DataTypeMismatch),
 			},
 			Value::Vector(left) => match rhs {
@@ -936,6 +1172,7 @@ impl Value {
 				}
 				_ => Err(Error::DataTypeMismatch),
 			},
+			Value::Quaternion(_) => self.quaternion_operation(rhs, Quaternion::divide),
 			_ => Err(Error::DataTypeMismatch),
 		}
 	}
@@ -1113,6 +1350,9 @@ const VALUE_SERIALIZE_TYPE_DATE: u8 = 4;
 const VALUE_SERIALIZE_TYPE_TIME: u8 = 5;
 const VALUE_SERIALIZE_TYPE_VECTOR: u8 = 6;
 const VALUE_SERIALIZE_TYPE_MATRIX: u8 = 7;
+const VALUE_SERIALIZE_TYPE_LIST: u8 = 8;
+const VALUE_SERIALIZE_TYPE_TEXT: u8 = 9;
+const VALUE_SERIALIZE_TYPE_QUATERNION: u8 = 10;
 
 impl StorageObject for Value {
 	fn serialize<Ref: StorageRefSerializer, Out: SerializeOutput>(
@@ -1166,6 +1406,21 @@ impl StorageObject for Value {
 				output.write_u8(VALUE_SERIALIZE_TYPE_MATRIX)?;
 				vector.serialize(output, storage_refs)?;
 			}
+			Value::List(list) => {
+				output.write_u8(VALUE_SERIALIZE_TYPE_LIST)?;
+				list.serialize(output, storage_refs)?;
+			}
+			Value::Text(text) => {
+				output.write_u8(VALUE_SERIALIZE_TYPE_TEXT)?;
+				output.write_u32(text.len() as u32)?;
+				output.write(text)?;
+			}
+			Value::Quaternion(num) => {
+				output.write_u8(VALUE_SERIALIZE_TYPE_QUATERNION)?;
+				for part in num.parts().iter() {
+					part.serialize(output, storage_refs)?;
+				}
+			}
 		}
 		Ok(())
 	}
@@ -1225,7 +1480,248 @@ impl StorageObject for Value {
 				let matrix = Matrix::deserialize(input, storage_refs)?;
 				Ok(Value::Matrix(matrix))
 			}
+			VALUE_SERIALIZE_TYPE_LIST => {
+				let list = List::deserialize(input, storage_refs)?;
+				Ok(Value::List(list))
+			}
+			VALUE_SERIALIZE_TYPE_TEXT => {
+				let len = input.read_u32()? as usize;
+				Ok(Value::Text(input.read_slice(len)?.to_vec()))
+			}
+			VALUE_SERIALIZE_TYPE_QUATERNION => {
+				let w = Number::deserialize(input, storage_refs)?;
+				let x = Number::deserialize(input, storage_refs)?;
+				let y = Number::deserialize(input, storage_refs)?;
+				let z = Number::deserialize(input, storage_refs)?;
+				Ok(Value::Quaternion(Quaternion::from_parts(w, x, y, z)))
+			}
 			_ => Err(Error::CorruptData),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::Value;
+	use crate::context::Context;
+	use crate::error::{Error, Result};
+	use crate::modes::ModeScope;
+	use crate::number::{Number, ToNumber, GUARD_DIGITS};
+	use crate::unit::AngleUnit;
+	use intel_dfp::Decimal;
+
+	fn num(text: &str) -> Value {
+		Value::Number(Number::Decimal(Decimal::from_str(text)))
+	}
+
+	fn int(value: i64) -> Value {
+		Value::Number(value.to_number())
+	}
+
+	fn to_f64(value: &Number) -> f64 {
+		value.to_decimal().to_string().parse().unwrap()
+	}
+
+	fn assert_close(actual: f64, expected: f64) {
+		let error = if expected == 0.0 {
+			actual.abs()
+		} else {
+			((actual - expected) / expected).abs()
+		};
+		// Results are only checked to the last digit kept by the decimal backend
+		let tolerance = 10f64.powi(1 - (Decimal::DIGITS - GUARD_DIGITS) as i32);
+		assert!(
+			error < tolerance.max(1e-15),
+			"expected {}, got {}",
+			expected,
+			actual
+		);
+	}
+
+	fn assert_real(result: Result<Value>, expected: f64) {
+		match result {
+			Ok(Value::Number(value)) | Ok(Value::NumberWithUnit(value, _)) => {
+				assert_close(to_f64(&value), expected)
+			}
+			Ok(value) => panic!("expected {}, got {}", expected, value.to_string()),
+			Err(error) => panic!("expected {}, got {:?}", expected, error),
+		}
+	}
+
+	fn assert_complex(result: Result<Value>, real: f64, imaginary: f64) {
+		match result {
+			Ok(Value::Complex(value)) => {
+				assert_close(to_f64(value.real_part()), real);
+				assert_close(to_f64(value.imaginary_part()), imaginary);
+			}
+			Ok(value) => panic!("expected a complex result, got {}", value.to_string()),
+			Err(error) => panic!("expected a complex result, got {:?}", error),
+		}
+	}
+
+	#[test]
+	fn real_functions_inside_their_domain() {
+		let radians = AngleUnit::Radians;
+		assert_real(int(16).sqrt(), 4.0);
+		assert_real(num("0.5").sqrt(), 0.5f64.sqrt());
+		assert_real(int(1000).log(), 3.0);
+		assert_real(num("2").ln(), 2f64.ln());
+		assert_real(num("0.5").exp(), 0.5f64.exp());
+		assert_real(int(3).exp10(), 1000.0);
+		assert_real(num("0.5").sin(radians), 0.5f64.sin());
+		assert_real(num("0.5").cos(radians), 0.5f64.cos());
+		assert_real(num("0.5").tan(radians), 0.5f64.tan());
+		assert_real(num("0.5").sinh(), 0.5f64.sinh());
+		assert_real(num("0.5").cosh(), 0.5f64.cosh());
+		assert_real(num("0.5").tanh(), 0.5f64.tanh());
+		assert_real(num("0.5").asin(radians), 0.5f64.asin());
+		assert_real(int(-1).acos(radians), core::f64::consts::PI);
+		assert_real(int(100).atan(radians), 100f64.atan());
+		assert_real(int(-2).asinh(), (-2f64).asinh());
+		assert_real(int(1).acosh(), 0.0);
+		assert_real(num("-0.5").atanh(), (-0.5f64).atanh());
+		assert_real(int(-2).pow(&int(3)), -8.0);
+		assert_real(int(-8).pow(&int(-1)), -0.125);
+		assert_real(int(2).pow(&num("0.5")), 2f64.sqrt());
+	}
+
+	/// Checks that a result is exactly the rational `num / den`
+	fn assert_exact(result: Result<Value>, num: i64, den: i64) {
+		match result {
+			Ok(Value::Number(value)) => {
+				assert!(
+					!matches!(value, Number::Decimal(_)),
+					"expected {}/{}, got {}",
+					num,
+					den,
+					value.to_decimal().to_string()
+				);
+				let expected = num.to_number() / den.to_number();
+				assert!((&value - &expected).is_zero(), "expected {}/{}", num, den);
+			}
+			Ok(value) => panic!("expected {}/{}, got {}", num, den, value.to_string()),
+			Err(error) => panic!("expected {}/{}, got {:?}", num, den, error),
+		}
+	}
+
+	fn rational(num: i64, den: i64) -> Value {
+		Value::Number(num.to_number() / den.to_number())
+	}
+
+	#[test]
+	fn tabulated_angles_give_exact_results() {
+		let degrees = AngleUnit::Degrees;
+		assert_exact(int(0).sin(degrees), 0, 1);
+		assert_exact(int(30).sin(degrees), 1, 2);
+		assert_exact(int(90).sin(degrees), 1, 1);
+		assert_exact(int(150).sin(degrees), 1, 2);
+		assert_exact(int(210).sin(degrees), -1, 2);
+		assert_exact(int(-90).sin(degrees), -1, 1);
+		assert_exact(int(60).cos(degrees), 1, 2);
+		assert_exact(int(180).cos(degrees), -1, 1);
+		assert_exact(int(240).cos(degrees), -1, 2);
+		assert_exact(int(-300).cos(degrees), 1, 2);
+		assert_exact(int(45).tan(degrees), 1, 1);
+		assert_exact(int(135).tan(degrees), -1, 1);
+		assert_exact(int(720).tan(degrees), 0, 1);
+
+		let gradians = AngleUnit::Gradians;
+		assert_exact(rational(100, 3).sin(gradians), 1, 2);
+		assert_exact(int(100).sin(gradians), 1, 1);
+		assert_exact(int(-300).sin(gradians), 1, 1);
+		assert_exact(rational(200, 3).cos(gradians), 1, 2);
+		assert_exact(int(200).cos(gradians), -1, 1);
+		assert_exact(int(50).tan(gradians), 1, 1);
+		assert_exact(int(350).tan(gradians), -1, 1);
+		assert_eq!(int(300).tan(gradians).err(), Some(Error::InvalidArgument));
+
+		// Angles tagged with a unit use it instead of the angle mode
+		let tagged = int(30).to_angle(degrees, degrees).unwrap();
+		assert_exact(tagged.sin(AngleUnit::Radians), 1, 2);
+	}
+
+	#[test]
+	fn other_angles_are_computed_in_decimal() {
+		let degrees = AngleUnit::Degrees;
+		for value in [
+			int(1).sin(degrees),
+			rational(15, 2).sin(degrees),
+			int(1).sin(AngleUnit::Radians),
+		] {
+			assert!(matches!(value, Ok(Value::Number(Number::Decimal(_)))));
+		}
+	}
+
+	#[test]
+	fn irrational_sines_are_shown_as_radicals() {
+		let radical = |value: Result<Value>| match value {
+			Ok(Value::Number(value)) => value.radical_form(),
+			_ => None,
+		};
+		assert_eq!(radical(int(45).sin(AngleUnit::Degrees)), Some((1, 2, 2)));
+		assert_eq!(radical(int(60).sin(AngleUnit::Degrees)), Some((1, 3, 2)));
+		assert_eq!(radical(int(-30).cos(AngleUnit::Degrees)), Some((1, 3, 2)));
+		assert_eq!(radical(int(30).tan(AngleUnit::Degrees)), Some((1, 3, 3)));
+		assert_eq!(radical(int(120).tan(AngleUnit::Degrees)), Some((-1, 3, 1)));
+		assert_eq!(radical(int(150).sin(AngleUnit::Gradians)), Some((1, 2, 2)));
+	}
+
+	#[test]
+	fn zero_to_the_power_of_zero_is_one() {
+		assert_real(int(0).pow(&int(0)), 1.0);
+		assert_real(num("0").pow(&num("0")), 1.0);
+		assert_real(int(0).pow(&int(2)), 0.0);
+	}
+
+	#[test]
+	fn singularities_are_errors() {
+		assert_eq!(int(0).pow(&int(-1)).err(), Some(Error::DivideByZero));
+		assert_eq!(num("0").pow(&num("-0.5")).err(), Some(Error::DivideByZero));
+		assert_eq!(int(0).log().err(), Some(Error::InvalidArgument));
+		assert_eq!(int(0).ln().err(), Some(Error::InvalidArgument));
+		assert_eq!(int(1).atanh().err(), Some(Error::InvalidArgument));
+		assert_eq!(int(-1).atanh().err(), Some(Error::InvalidArgument));
+		assert_eq!(
+			int(90).tan(AngleUnit::Degrees).err(),
+			Some(Error::InvalidArgument)
+		);
+	}
+
+	#[test]
+	fn outside_real_domain_fails_without_complex_results() {
+		type Case = (&'static str, fn() -> Result<Value>);
+		let outside: [Case; 8] = [
+			("sqrt", || int(-4).sqrt()),
+			("log", || int(-100).log()),
+			("ln", || int(-1).ln()),
+			("pow", || int(-8).pow(&num("0.5"))),
+			("asin", || int(2).asin(AngleUnit::Radians)),
+			("acos", || int(2).acos(AngleUnit::Radians)),
+			("acosh", || num("0.5").acosh()),
+			("atanh", || int(2).atanh()),
+		];
+
+		let mut context = Context::new();
+		context.set_complex_results(false);
+		let _modes = ModeScope::begin(&context);
+		for (name, func) in outside.iter() {
+			assert_eq!(func().err(), Some(Error::InvalidArgument), "{}", name);
+		}
+	}
+
+	#[test]
+	fn outside_real_domain_gives_complex_results() {
+		let context = Context::new();
+		let _modes = ModeScope::begin(&context);
+		let radians = AngleUnit::Radians;
+		let pi = core::f64::consts::PI;
+		assert_complex(int(-4).sqrt(), 0.0, 2.0);
+		assert_complex(int(-100).log(), 2.0, pi / 10f64.ln());
+		assert_complex(int(-1).ln(), 0.0, pi);
+		assert_complex(int(-8).pow(&num("0.5")), 0.0, 8f64.sqrt());
+		assert_complex(int(2).asin(radians), pi / 2.0, -(2f64.acosh()));
+		assert_complex(int(2).acos(radians), 0.0, 2f64.acosh());
+		assert_complex(num("0.5").acosh(), 0.0, 0.5f64.acos());
+		assert_complex(int(2).atanh(), 3f64.ln() / 2.0, pi / 2.0);
+	}
+}
@@ -4,9 +4,11 @@ use crate::storage::{
 	store, DeserializeInput, SerializeOutput, StorageObject, StorageRef, StorageRefArray,
 	StorageRefSerializer,
 };
+use crate::unit::AngleUnit;
 use crate::value::{Value, ValueRef};
 
-const MAX_CAPACITY: usize = 1000;
+/// Largest number of elements in a vector
+pub const MAX_CAPACITY: usize = 1000;
 const EXTRA_CAPACITY: usize = 4;
 
 #[derive(Clone)]
@@ -53,7 +55,7 @@ impl Vector {
 		if idx >= self.len {
 			return Err(Error::IndexOutOfRange);
 		}
-		if value.is_vector_or_matrix() {
+		if value.is_collection() {
 			return Err(Error::DataTypeMismatch);
 		}
 		self.array.set(idx, store(value)?)
@@ -66,7 +68,7 @@ impl Vector {
 		if (self.len + 1) > MAX_CAPACITY {
 			return Err(Error::VectorTooLarge);
 		}
-		if value.is_vector_or_matrix() {
+		if value.is_collection() {
 			return Err(Error::DataTypeMismatch);
 		}
 
@@ -183,6 +185,28 @@ impl Vector {
 		Ok(result)
 	}
 
+	/// Angle between two vectors in the given angle unit. Vectors of zero length
+	/// have no direction and give an invalid argument error.
+	pub fn angle(&self, other: &Vector, angle_mode: AngleUnit) -> Result<Value> {
+		let lengths = (self.magnitude()? * other.magnitude()?)?;
+		if lengths.real_number()?.is_zero() {
+			return Err(Error::InvalidArgument);
+		}
+		let cosine = (self.dot(other)? / lengths)?;
+
+		// Rounding can move the cosine of parallel vectors just past one
+		let limit = cosine.real_number()?.to_decimal().abs();
+		if limit > 1.into() {
+			let one = if cosine.real_number()?.is_negative() {
+				-1
+			} else {
+				1
+			};
+			return Value::Number(one.into()).acos(angle_mode);
+		}
+		cosine.acos(angle_mode)
+	}
+
 	pub fn cross(&self, other: &Vector) -> Result<Vector> {
 		if self.len() != 3 || other.len() != 3 {
 			return Err(Error::DimensionMismatch);
@@ -199,6 +223,47 @@ impl Vector {
 		)?;
 		Ok(result)
 	}
+
+	/// Rotates a 2D point counterclockwise about the origin
+	pub fn rotate_2d(&self, angle: &Value, angle_mode: AngleUnit) -> Result<Vector> {
+		if self.len() != 2 {
+			return Err(Error::DimensionMismatch);
+		}
+		let cos = angle.cos(angle_mode)?;
+		let sin = angle.sin(angle_mode)?;
+		let x = self.get(0)?;
+		let y = self.get(1)?;
+		let mut result = Vector::new()?;
+		result.push(((&x * &cos)? - (&y * &sin)?)?)?;
+		result.push(((&x * &sin)? + (&y * &cos)?)?)?;
+		Ok(result)
+	}
+
+	/// Rotates a 3D point about an axis through the origin. The rotation is
+	/// counterclockwise when looking back along the axis.
+	pub fn rotate_3d(&self, axis: &Vector, angle: &Value, angle_mode: AngleUnit) -> Result<Vector> {
+		if self.len() != 3 || axis.len() != 3 {
+			return Err(Error::DimensionMismatch);
+		}
+		if axis.magnitude()?.real_number()?.is_zero() {
+			return Err(Error::InvalidArgument);
+		}
+
+		// Rodrigues' formula: v·cos θ + (k×v)·sin θ + k·(k·v)·(1 - cos θ)
+		let axis = axis.normalize()?;
+		let cos = angle.cos(angle_mode)?;
+		let sin = angle.sin(angle_mode)?;
+		let cross = axis.cross(self)?;
+		let along_axis = (axis.dot(self)? * (Value::Number(1.into()) - cos.clone())?)?;
+		let mut result = Vector::new()?;
+		for i in 0..3 {
+			result.push(
+				(((&self.get(i)? * &cos)? + (&cross.get(i)? * &sin)?)?
+					+ (&axis.get(i)? * &along_axis)?)?,
+			)?;
+		}
+		Ok(result)
+	}
 }
 
 impl StorageObject for Vector {
@@ -1,55 +1,70 @@
 #[cfg(feature = "dm42")]
 pub mod device;
 
-#[cfg(not(feature = "dm42"))]
+#[cfg(feature = "gtk")]
 pub mod simulated;
 
+#[cfg(feature = "desktop")]
+pub mod desktop;
+
+#[cfg(feature = "web")]
+pub mod web;
+
+#[cfg(not(feature = "embedded"))]
+pub mod fuzz;
+
+#[cfg(not(feature = "embedded"))]
+pub mod golden;
+
+#[cfg(all(not(feature = "embedded"), not(target_arch = "wasm32")))]
+mod clipboard;
+
+#[cfg(not(feature = "embedded"))]
+pub mod repl;
+
+#[cfg(any(feature = "gtk", feature = "desktop"))]
+mod host;
+
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+mod screenshot;
+
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+mod main_loop;
+
+mod alarm;
+mod animation;
+mod answers;
+mod calendar;
 mod catalog;
+mod currency;
+mod diagnostics;
+mod dialog;
+mod display;
 mod edit;
+mod feedback;
+mod files;
+mod flags;
 mod font;
 mod functions;
+mod graph;
 mod input;
+mod listing;
+mod locale;
+mod matrix_editor;
 mod menu;
+mod pane;
+pub mod platform;
+mod print;
+mod save;
 mod screen;
+mod selftest;
+mod settings;
 mod state;
+mod table;
+mod tape;
+mod toast;
+mod transfer;
 mod unit;
 
-use input::{InputQueue, KeyEvent};
-use screen::Screen;
-use state::{InputResult, State};
-
-pub fn calc_main<ScreenT: Screen, InputT: InputQueue>(mut screen: ScreenT, mut input: InputT) {
-	screen.clear();
-
-	let mut state = State::new();
-	state.render(&mut screen);
-
-	loop {
-		if let Some(input_event) = state.wait_for_input(&mut input) {
-			match state.handle_input(input_event, &screen) {
-				Ok(InputResult::Normal) => (),
-				Ok(InputResult::Suspend) => input.suspend(),
-				Err(error) => {
-					state.show_error(error);
-					state.render(&mut screen);
-
-					for _ in 0..30 {
-						#[cfg(feature = "dm42")]
-						device::sys_delay(100);
-						#[cfg(not(feature = "dm42"))]
-						std::thread::sleep(std::time::Duration::from_millis(100));
-
-						if let Some(KeyEvent::Press(_)) = input.pop_raw() {
-							break;
-						}
-					}
-
-					state.hide_error();
-				}
-			}
-			state.render(&mut screen);
-		} else {
-			state.update_header(&mut screen);
-		}
-	}
-}
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+pub use main_loop::calc_main;
@@ -0,0 +1,129 @@
+use chrono::{DateTime, NaiveDateTime};
+use rscalc_math::error::{Error, Result};
+use rscalc_math::persist::{read_string, write_string};
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+use rscalc_math::time::{SimpleDateTimeFormat, SimpleDateTimeToString};
+
+#[cfg(feature = "embedded")]
+use alloc::string::String;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Largest number of alarms that can be pending at once
+const MAX_ALARMS: usize = 16;
+
+/// Time that an alarm rings for before it is dismissed by itself
+pub const ALARM_RING_MS: u32 = 60000;
+
+/// Time between beeps of a ringing alarm
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+pub const ALARM_BEEP_INTERVAL_MS: u32 = 2000;
+
+/// Time that an alarm goes off, with a message to show when it does
+#[derive(Clone)]
+pub struct Alarm {
+	time: NaiveDateTime,
+	message: Option<String>,
+}
+
+impl Alarm {
+	pub fn new(time: NaiveDateTime, message: Option<String>) -> Self {
+		Alarm { time, message }
+	}
+
+	pub fn message(&self) -> Option<&str> {
+		self.message.as_deref()
+	}
+
+	pub fn time_string(&self, time_24_hour: bool) -> String {
+		self.time
+			.simple_format(&SimpleDateTimeFormat::full(time_24_hour))
+	}
+
+	fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_i64(self.time.and_utc().timestamp())?;
+		match &self.message {
+			Some(message) => {
+				output.write_u8(1)?;
+				write_string(output, message)
+			}
+			None => output.write_u8(0),
+		}
+	}
+
+	fn restore(input: &mut DeserializeInput) -> Result<Self> {
+		let time = DateTime::from_timestamp(input.read_i64()?, 0)
+			.ok_or(Error::CorruptData)?
+			.naive_utc();
+		let message = if input.read_u8()? != 0 {
+			Some(read_string(input)?)
+		} else {
+			None
+		};
+		Ok(Alarm { time, message })
+	}
+}
+
+/// Alarms that have not gone off yet, kept in the order that they go off
+pub struct AlarmList {
+	alarms: Vec<Alarm>,
+}
+
+impl AlarmList {
+	pub fn new() -> Self {
+		AlarmList { alarms: Vec::new() }
+	}
+
+	pub fn alarms(&self) -> &[Alarm] {
+		&self.alarms
+	}
+
+	pub fn add(&mut self, alarm: Alarm) -> Result<()> {
+		if self.alarms.len() >= MAX_ALARMS {
+			return Err(Error::ValueOutOfRange);
+		}
+		let idx = self
+			.alarms
+			.iter()
+			.position(|existing| existing.time > alarm.time)
+			.unwrap_or(self.alarms.len());
+		self.alarms.insert(idx, alarm);
+		Ok(())
+	}
+
+	pub fn remove(&mut self, idx: usize) -> Result<()> {
+		if idx >= self.alarms.len() {
+			return Err(Error::IndexOutOfRange);
+		}
+		self.alarms.remove(idx);
+		Ok(())
+	}
+
+	/// Removes and returns the earliest alarm if it is due at `now`
+	pub fn take_due(&mut self, now: &NaiveDateTime) -> Option<Alarm> {
+		match self.alarms.first() {
+			Some(alarm) if alarm.time <= *now => Some(self.alarms.remove(0)),
+			_ => None,
+		}
+	}
+
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_u32(self.alarms.len() as u32)?;
+		for alarm in &self.alarms {
+			alarm.save(output)?;
+		}
+		Ok(())
+	}
+
+	/// Reads alarms written by `save`, including any that went off while the
+	/// calculator was not running. Those ring as soon as it checks for alarms.
+	pub fn restore(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		let count = input.read_u32()?;
+		let mut alarms = AlarmList::new();
+		for _ in 0..count {
+			alarms.add(Alarm::restore(input)?)?;
+		}
+		*self = alarms;
+		Ok(())
+	}
+}
@@ -0,0 +1,197 @@
+use crate::dm42::screen::{theme, Color, RenderMode, Screen, ScreenLayoutRenderer};
+use rscalc_layout::layout::Rect;
+use rscalc_math::error::Result;
+
+/// Time between animation frames in milliseconds
+pub const FRAME_TIME_MS: u32 = 33;
+
+const MENU_SLIDE_IN_FRAMES: u32 = 8;
+const FADE_IN_FRAMES: u32 = 5;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AnimationType {
+	MenuSlideIn,
+	FadeIn,
+}
+
+pub struct Animation {
+	animation_type: AnimationType,
+	frame: u32,
+	frame_count: u32,
+}
+
+impl Animation {
+	pub fn new(animation_type: AnimationType) -> Self {
+		Animation {
+			animation_type,
+			frame: 0,
+			frame_count: match animation_type {
+				AnimationType::MenuSlideIn => MENU_SLIDE_IN_FRAMES,
+				AnimationType::FadeIn => FADE_IN_FRAMES,
+			},
+		}
+	}
+
+	pub fn animation_type(&self) -> AnimationType {
+		self.animation_type
+	}
+
+	/// Advances the animation to the next frame. Returns false when the animation
+	/// has completed.
+	pub fn tick(&mut self) -> bool {
+		if self.frame < self.frame_count {
+			self.frame += 1;
+		}
+		self.frame < self.frame_count
+	}
+
+	/// Progress of the animation scaled to the range 0 to `max`. Uses an ease out
+	/// curve so that the animation slows down as it reaches its final state.
+	pub fn progress(&self, max: i32) -> i32 {
+		let remaining = (self.frame_count - self.frame) as i32;
+		let total = self.frame_count as i32;
+		max - (max * remaining * remaining) / (total * total)
+	}
+}
+
+/// Fades the contents of `rect` in by erasing a dithered portion of it. At a
+/// progress of zero the area is fully erased, and at `max` it is untouched.
+pub fn fade_rect(screen: &mut dyn Screen, rect: &Rect, progress: i32, max: i32) {
+	let rect = rect.clipped_to(&screen.screen_rect());
+	let level = if max > 0 { (progress * 4) / max } else { 4 };
+	for y in rect.y..rect.y + rect.h {
+		let pattern = match (level, y & 1) {
+			(0, _) => 0xf,
+			(1, 0) => 0xf,
+			(1, _) => 0xa,
+			(2, 0) => 0xa,
+			(2, _) => 0x5,
+			(3, 0) => 0xa,
+			_ => continue,
+		};
+		screen.horizontal_pattern(
+			rect.x,
+			rect.w,
+			y,
+			pattern,
+			4,
+			theme().color(Color::ContentBackground),
+		);
+	}
+}
+
+/// Screen that renders onto another screen with a horizontal offset. Anything
+/// that falls outside of the underlying screen is clipped. Used to slide content
+/// onto the screen.
+pub struct OffsetScreen<'a> {
+	screen: &'a mut dyn Screen,
+	x_offset: i32,
+}
+
+impl<'a> OffsetScreen<'a> {
+	pub fn new(screen: &'a mut dyn Screen, x_offset: i32) -> Self {
+		OffsetScreen { screen, x_offset }
+	}
+}
+
+impl<'a> Screen for OffsetScreen<'a> {
+	fn width(&self) -> i32 {
+		self.screen.width()
+	}
+
+	fn height(&self) -> i32 {
+		self.screen.height()
+	}
+
+	fn clear(&mut self) {
+		let rect = Rect {
+			x: self.x_offset,
+			y: 0,
+			w: self.screen.width() - self.x_offset,
+			h: self.screen.height(),
+		};
+		let rect = rect.clipped_to(&self.screen.screen_rect());
+		self.screen
+			.fill(&rect, theme().color(Color::ContentBackground));
+	}
+
+	fn refresh(&mut self) {
+		self.screen.refresh();
+	}
+
+	fn refresh_rows(&mut self, top: i32, bottom: i32) {
+		self.screen.refresh_rows(top, bottom);
+	}
+
+	fn begin_frame(&mut self) {
+		self.screen.begin_frame();
+	}
+
+	fn end_frame(&mut self) {
+		self.screen.end_frame();
+	}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		let rect = Rect {
+			x: rect.x + self.x_offset,
+			y: rect.y,
+			w: rect.w,
+			h: rect.h,
+		};
+		let rect = rect.clipped_to(&self.screen.screen_rect());
+		if rect.w > 0 && rect.h > 0 {
+			self.screen.fill(&rect, color);
+		}
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		let mut x = x + self.x_offset;
+		let mut bits = bits;
+		let mut width = width as i32;
+
+		// Clip bits that are off the left edge
+		if x < 0 {
+			width += x;
+			if width <= 0 {
+				return;
+			}
+			bits &= (1 << width) - 1;
+			x = 0;
+		}
+
+		// Clip bits that are off the right edge
+		let overflow = (x + width) - self.screen.width();
+		if overflow > 0 {
+			width -= overflow;
+			if width <= 0 {
+				return;
+			}
+			bits >>= overflow;
+		}
+
+		self.screen.draw_bits(x, y, bits, width as u8, color);
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+
+	fn supports_animation(&self) -> bool {
+		self.screen.supports_animation()
+	}
+
+	fn supports_grayscale(&self) -> bool {
+		self.screen.supports_grayscale()
+	}
+
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		let x = x + self.x_offset;
+		if x >= 0 && x < self.screen.width() {
+			self.screen.blend_pixel(x, y, color, alpha);
+		}
+	}
+
+	fn capture(&self) -> Result<()> {
+		self.screen.capture()
+	}
+}
@@ -0,0 +1,38 @@
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Number of results kept in the answer history. When it is full the oldest
+/// results are discarded.
+const MAX_ANSWERS: usize = 50;
+
+/// Results of the most recent operations, kept apart from the stack so that a
+/// result can be brought back exactly after it has been dropped from the stack
+pub struct AnswerHistory {
+	answers: Vec<Value>,
+}
+
+impl AnswerHistory {
+	pub fn new() -> Self {
+		AnswerHistory {
+			answers: Vec::new(),
+		}
+	}
+
+	pub fn push(&mut self, value: Value) {
+		if self.answers.len() >= MAX_ANSWERS {
+			self.answers.remove(0);
+		}
+		self.answers.push(value);
+	}
+
+	/// Results from the oldest to the most recent
+	pub fn answers(&self) -> &[Value] {
+		&self.answers
+	}
+
+	pub fn get(&self, idx: usize) -> Option<&Value> {
+		self.answers.get(idx)
+	}
+}
@@ -0,0 +1,198 @@
+use crate::dm42::screen::{theme, Color, RenderMode, Screen};
+use chrono::{Datelike, Months, NaiveDate};
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
+use rscalc_math::number::ToNumber;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+
+const MONTH_NAMES: [&str; 12] = [
+	"January",
+	"February",
+	"March",
+	"April",
+	"May",
+	"June",
+	"July",
+	"August",
+	"September",
+	"October",
+	"November",
+	"December",
+];
+
+/// Weeks start on Sunday
+const WEEKDAY_NAMES: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+
+/// Most weeks that a month can touch
+const MAX_WEEKS: i32 = 6;
+
+/// Month view with a selected day, which moves between months as the selection
+/// does
+pub struct Calendar {
+	selected: NaiveDate,
+	today: Option<NaiveDate>,
+}
+
+impl Calendar {
+	pub fn new(selected: NaiveDate, today: Option<NaiveDate>) -> Self {
+		Calendar { selected, today }
+	}
+
+	pub fn selected(&self) -> NaiveDate {
+		self.selected
+	}
+
+	/// Moves the selection by a number of days. The selection stays where it is
+	/// at the ends of the range of dates.
+	pub fn move_days(&mut self, days: i64) {
+		if let Some(date) = self
+			.selected
+			.checked_add_signed(chrono::Duration::days(days))
+		{
+			self.selected = date;
+		}
+	}
+
+	/// Moves the selection by a number of months, keeping the day of the month.
+	/// Days past the end of the new month select its last day.
+	pub fn move_months(&mut self, months: i32) {
+		let date = if months < 0 {
+			self.selected
+				.checked_sub_months(Months::new(months.unsigned_abs()))
+		} else {
+			self.selected.checked_add_months(Months::new(months as u32))
+		};
+		if let Some(date) = date {
+			self.selected = date;
+		}
+	}
+
+	pub fn select_today(&mut self) {
+		if let Some(today) = self.today {
+			self.selected = today;
+		}
+	}
+
+	fn title(&self) -> String {
+		MONTH_NAMES[self.selected.month0() as usize].to_string()
+			+ " " + &self.selected.year().to_number().to_string()
+	}
+
+	/// Draws the month of the selected day into `area`. The selected day is
+	/// drawn inverted and today has a frame around it.
+	pub fn render(&self, screen: &mut dyn Screen, area: &Rect) {
+		let metrics = screen.metrics();
+		let title_height = metrics.height(Font::Small) + 4;
+		let header_height = metrics.height(Font::Smallest) + 2;
+		let row_height = (area.h - title_height - header_height) / MAX_WEEKS;
+		let col_width = area.w / 7;
+		let grid_x = area.x + (area.w - col_width * 7) / 2;
+
+		let first = self.selected.with_day(1).unwrap();
+		let days = match first.checked_add_months(Months::new(1)) {
+			Some(next) => (next - first).num_days() as u32,
+			None => 31,
+		};
+		let offset = first.weekday().num_days_from_sunday() as i32;
+
+		let mut renderer = screen.renderer(RenderMode::Normal);
+		renderer.erase(area);
+		Layout::HorizontalCenter(Box::new(Layout::Text(
+			self.title(),
+			Font::Small,
+			TokenType::Label,
+		)))
+		.render(
+			&mut renderer,
+			Rect {
+				x: area.x,
+				y: area.y,
+				w: area.w,
+				h: title_height,
+			},
+			area,
+		);
+		for (idx, name) in WEEKDAY_NAMES.iter().enumerate() {
+			Layout::HorizontalCenter(Box::new(Layout::StaticText(
+				name,
+				Font::Smallest,
+				TokenType::Label,
+			)))
+			.render(
+				&mut renderer,
+				Rect {
+					x: grid_x + idx as i32 * col_width,
+					y: area.y + title_height,
+					w: col_width,
+					h: header_height,
+				},
+				area,
+			);
+		}
+
+		for day in 1..=days {
+			let cell = offset + day as i32 - 1;
+			let rect = Rect {
+				x: grid_x + (cell % 7) * col_width,
+				y: area.y + title_height + header_height + (cell / 7) * row_height,
+				w: col_width,
+				h: row_height,
+			};
+			let date = first.with_day(day).unwrap();
+			let mut renderer = screen.renderer(if date == self.selected {
+				RenderMode::Selected
+			} else {
+				RenderMode::Normal
+			});
+			if date == self.selected {
+				renderer.erase(&rect);
+			}
+			Layout::HorizontalCenter(Box::new(Layout::Text(
+				day.to_number().to_string(),
+				Font::Small,
+				TokenType::Text,
+			)))
+			.render(&mut renderer, rect.clone(), area);
+
+			if Some(date) == self.today && date != self.selected {
+				draw_frame(screen, &rect, theme().color(Color::ContentText));
+			}
+		}
+	}
+}
+
+fn draw_frame(screen: &mut dyn Screen, rect: &Rect, color: bool) {
+	let edges = [
+		Rect {
+			x: rect.x,
+			y: rect.y,
+			w: rect.w,
+			h: 1,
+		},
+		Rect {
+			x: rect.x,
+			y: rect.y + rect.h - 1,
+			w: rect.w,
+			h: 1,
+		},
+		Rect {
+			x: rect.x,
+			y: rect.y,
+			w: 1,
+			h: rect.h,
+		},
+		Rect {
+			x: rect.x + rect.w - 1,
+			y: rect.y,
+			w: 1,
+			h: rect.h,
+		},
+	];
+	for edge in &edges {
+		screen.fill(edge, color);
+	}
+}
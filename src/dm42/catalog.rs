@@ -1,47 +1,225 @@
-use crate::dm42::functions::Function;
+use crate::dm42::functions::{CustomSlot, Function};
+use crate::dm42::input::InputEvent;
 use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::screen::Screen;
+use crate::dm42::state::{State, PROGRAM_SLOTS};
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, TokenType};
+use rscalc_layout::string::StringLayout;
+use rscalc_layout::value::ValueLayout;
+use rscalc_math::checksum::{ByteOrder, ChecksumKind};
 use rscalc_math::constant::Constant;
+use rscalc_math::decibel::DecibelScale;
+use rscalc_math::equation::EQUATION_LIBRARY;
+use rscalc_math::error::{Error, Result};
 use rscalc_math::functions::StackFunction;
+use rscalc_math::number::Number;
+use rscalc_math::probability::{DistributionFunction, DistributionKind};
+use rscalc_math::script::{Series, YieldLimit};
+use rscalc_math::unit::AngleUnit;
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::boxed::Box;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum CatalogPage {
+	Alarms,
+	Answers,
+	Bits,
+	Checksums,
 	Constants,
+	Coordinates,
+	Electronics,
+	Equations,
+	List,
+	Network,
+	NumberTheory,
+	Probability,
 	Stats,
+	Text,
 	Time,
 	Transcendental,
+	Scripts,
+	Series,
+	Stack,
 	Units,
+	User,
+	Variables,
 	Vector,
 }
 
+const CATALOG_PAGES: [CatalogPage; 22] = [
+	CatalogPage::Answers,
+	CatalogPage::Bits,
+	CatalogPage::Checksums,
+	CatalogPage::Constants,
+	CatalogPage::Coordinates,
+	CatalogPage::Electronics,
+	CatalogPage::Equations,
+	CatalogPage::List,
+	CatalogPage::Network,
+	CatalogPage::NumberTheory,
+	CatalogPage::Probability,
+	CatalogPage::Stats,
+	CatalogPage::Text,
+	CatalogPage::Time,
+	CatalogPage::Transcendental,
+	CatalogPage::Scripts,
+	CatalogPage::Series,
+	CatalogPage::Stack,
+	CatalogPage::Units,
+	CatalogPage::User,
+	CatalogPage::Variables,
+	CatalogPage::Vector,
+];
+
 impl CatalogPage {
 	pub fn to_str(&self) -> &'static str {
 		match self {
+			CatalogPage::Alarms => "Alarms",
+			CatalogPage::Answers => "Answers",
+			CatalogPage::Bits => "Bits",
+			CatalogPage::Checksums => "Checksums",
 			CatalogPage::Constants => "Constants",
+			CatalogPage::Coordinates => "Coordinates",
+			CatalogPage::Electronics => "Electronics",
+			CatalogPage::Equations => "Equations",
+			CatalogPage::List => "Lists",
+			CatalogPage::Network => "Network",
+			CatalogPage::NumberTheory => "Number Theory",
+			CatalogPage::Probability => "Probability",
 			CatalogPage::Stats => "Statistics",
+			CatalogPage::Text => "Text",
 			CatalogPage::Time => "Time",
 			CatalogPage::Transcendental => "Transcendental",
+			CatalogPage::Scripts => "Scripts",
+			CatalogPage::Series => "Series",
+			CatalogPage::Stack => "Stack",
 			CatalogPage::Units => "Units",
-			CatalogPage::Vector => "Vector",
+			CatalogPage::User => "User",
+			CatalogPage::Variables => "Variables",
+			CatalogPage::Vector => "Vectors",
 		}
 	}
 
 	pub fn menu(
 		&self,
-		_page: &dyn Fn(CatalogPage) -> Function,
+		state: &State,
+		page: &dyn Fn(CatalogPage) -> Function,
 		func: &dyn Fn(Function) -> Function,
-	) -> Menu {
-		match self {
+	) -> Result<Menu> {
+		Ok(match self {
+			CatalogPage::Alarms => alarm_catalog_menu(state, func)?,
+			CatalogPage::Answers => answers_catalog_menu(state, func)?,
+			CatalogPage::Bits => bits_catalog_menu(func),
+			CatalogPage::Checksums => checksum_catalog_menu(func),
 			CatalogPage::Constants => constant_catalog_menu(func),
+			CatalogPage::Coordinates => coordinate_catalog_menu(func),
+			CatalogPage::Electronics => electronics_catalog_menu(func),
+			CatalogPage::Equations => equation_catalog_menu(func),
+			CatalogPage::List => list_catalog_menu(state, func),
+			CatalogPage::Network => network_catalog_menu(func),
+			CatalogPage::NumberTheory => number_theory_catalog_menu(func),
+			CatalogPage::Probability => probability_catalog_menu(func),
 			CatalogPage::Stats => stats_catalog_menu(func),
-			CatalogPage::Time => time_catalog_menu(func),
+			CatalogPage::Text => text_catalog_menu(func),
+			CatalogPage::Time => time_catalog_menu(page, func),
 			CatalogPage::Transcendental => transcendental_catalog_menu(func),
+			CatalogPage::Scripts => scripts_catalog_menu(state, func)?,
+			CatalogPage::Series => series_catalog_menu(state, func)?,
+			CatalogPage::Stack => stack_catalog_menu(func),
 			CatalogPage::Units => main_unit_catalog_menu(func),
+			CatalogPage::User => user_catalog_menu(state, func)?,
+			CatalogPage::Variables => variables_catalog_menu(state, func)?,
 			CatalogPage::Vector => vector_catalog_menu(func),
+		})
+	}
+}
+
+/// Number of stack entries copied to preview the result of a function. Functions
+/// that use all of them might have used more, so they are not previewed.
+const PREVIEW_DEPTH: usize = 8;
+
+/// Passes through the loops of a long computation, such as counting primes,
+/// that a function may make before its preview is given up
+const PREVIEW_PASSES: usize = 32;
+
+/// One line preview of what a catalog function would do with the values on the
+/// stack, such as `3 nCr 2 → 3`. The function is run on a copy of the stack.
+/// Entries at the bottom of the copy that still hold the same values were not
+/// used, and the entries above them are the results. Returns `None` if the
+/// function fails on these values, takes too long, leaves nothing on the stack
+/// or changes anything other than the stack, such as a setting or a flag.
+pub fn stack_effect_preview(
+	state: &State,
+	function: &MenuItemFunction,
+	screen: &dyn Screen,
+) -> Option<String> {
+	let func = match function {
+		MenuItemFunction::Action(Function::Stack(func)) if func.help().is_some() => func,
+		_ => return None,
+	};
+	let context = state.context();
+	let depth = core::cmp::min(context.stack_len(), PREVIEW_DEPTH);
+	let mut scratch = context.scratch_copy(depth);
+	{
+		let _limit = YieldLimit::begin(PREVIEW_PASSES);
+		func.execute(&mut scratch).ok()?;
+	}
+	if !scratch.same_settings(&context.scratch_copy(0)) {
+		return None;
+	}
+
+	let len = scratch.stack_len();
+	let mut kept = 0;
+	while kept < core::cmp::min(depth, len) {
+		let before = context.stack().entry_ref(depth - 1 - kept).ok()?;
+		let after = scratch.stack().entry_ref(len - 1 - kept).ok()?;
+		if !before.same_object(after) {
+			break;
+		}
+		kept += 1;
+	}
+	let used = depth - kept;
+	if used == PREVIEW_DEPTH || len == kept {
+		return None;
+	}
+
+	let format = context.format().with_max_precision(8);
+	let mut results = Vec::new();
+	for idx in (0..len - kept).rev() {
+		results.push(scratch.entry(idx).ok()?.format(&format).to_string());
+	}
+	let result = results.join(", ");
+	let mut args = Vec::new();
+	for idx in (0..used).rev() {
+		args.push(context.entry(idx).ok()?.format(&format).to_string());
+	}
+	let name = func.to_string(context);
+	let preview = match args.len() {
+		0 => name,
+		1 => name + " " + &args[0],
+		2 => args[0].clone() + " " + &name + " " + &args[1],
+		_ => name + " " + &args.join(", "),
+	} + " → "
+		+ &result;
+
+	// Leave out the values used when they don't fit
+	let max_width = screen.width() - 8;
+	if screen.metrics().width(Font::Small, &preview) <= max_width {
+		Some(preview)
+	} else {
+		let preview = "→ ".to_string() + &result;
+		if screen.metrics().width(Font::Small, &preview) <= max_width {
+			Some(preview)
+		} else {
+			None
 		}
 	}
 }
@@ -52,6 +230,7 @@ fn create_parent_items(items: &[(&'static str, Function)]) -> Vec<MenuItem> {
 		result.push(MenuItem {
 			layout: MenuItemLayout::Static(MenuItem::static_string_layout(item.0)),
 			function: MenuItemFunction::InMenuAction(item.1.clone()),
+			help: None,
 		});
 	}
 	result
@@ -63,23 +242,78 @@ fn create_action_items(items: &[(&'static str, Function)]) -> Vec<MenuItem> {
 		result.push(MenuItem {
 			layout: MenuItemLayout::Static(MenuItem::static_string_layout(item.0)),
 			function: MenuItemFunction::Action(item.1.clone()),
+			help: item.1.help(),
 		});
 	}
 	result
 }
 
+fn catalog_page_items(func: &dyn Fn(CatalogPage) -> Function) -> Vec<(&'static str, Function)> {
+	CATALOG_PAGES
+		.iter()
+		.map(|page| (page.to_str(), func(*page)))
+		.collect()
+}
+
 pub fn catalog_menu(func: &dyn Fn(CatalogPage) -> Function) -> Menu {
-	Menu::new(
-		"Catalog",
-		create_parent_items(&[
-			("Constants", func(CatalogPage::Constants)),
-			("Statistics", func(CatalogPage::Stats)),
-			("Time", func(CatalogPage::Time)),
-			("Transcendental", func(CatalogPage::Transcendental)),
-			("Units", func(CatalogPage::Units)),
-			("Vector", func(CatalogPage::Vector)),
-		]),
-	)
+	let mut items = create_parent_items(&catalog_page_items(func));
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Search")),
+		function: MenuItemFunction::Action(Function::CatalogSearch),
+		help: Some("Finds the functions of every page with a name containing the typed text"),
+	});
+	Menu::new("Catalog", items)
+}
+
+/// Menu of the functions on the catalog pages with a name that contains `text`,
+/// ignoring case, or `None` if there are none. Pages within pages are not
+/// searched, and neither are pages that have nothing on them.
+pub fn catalog_search_menu(state: &State, text: &str) -> Option<Menu> {
+	let text = text.to_lowercase();
+	let mut items = Vec::new();
+	for page in CATALOG_PAGES.iter() {
+		let menu = match page.menu(state, &|page| Function::CatalogPage(page), &|func| func) {
+			Ok(menu) => menu,
+			Err(_) => continue,
+		};
+		for item in menu.into_items() {
+			let matches = match (&item.function, item.label()) {
+				(MenuItemFunction::Action(_), Some(label)) => label.to_lowercase().contains(&text),
+				_ => false,
+			};
+			if matches {
+				items.push(item);
+			}
+		}
+	}
+	if items.is_empty() {
+		return None;
+	}
+	let mut menu = Menu::new("Search", items);
+	menu.show_stack_effects();
+	Some(menu)
+}
+
+pub fn assign_catalog_menu(slot: CustomSlot) -> Menu {
+	// Catalog for assigning functions also allows creating a sub-menu, switching
+	// banks, keeping the value in x or recording a macro in the slot
+	let mut items = catalog_page_items(&|page| Function::AssignCatalogPage(slot, page));
+	items.push(("New Sub-menu", Function::AssignNewCustomMenu(slot)));
+	items.push(("Value in x", Function::AssignValue(slot)));
+	items.push((
+		"Next Bank",
+		Function::AssignCatalogItem(slot, Box::new(Function::NextCustomBank)),
+	));
+	let mut items = create_parent_items(&items);
+
+	// Recording a macro closes the menus so that the keys pressed next are
+	// recorded into the slot
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Record Macro")),
+		function: MenuItemFunction::Action(Function::RecordMacro(slot)),
+		help: Some("Records keys until Prgm is pressed"),
+	});
+	Menu::new("Catalog", items)
 }
 
 fn constant_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
@@ -94,27 +328,426 @@ fn constant_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	)
 }
 
-fn stats_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+/// Lists the equations of the library. Selecting one opens the solver with a
+/// soft key for each of its variables.
+fn equation_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	let mut items = Vec::new();
+	for (idx, equation) in EQUATION_LIBRARY.iter().enumerate() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::string_layout(
+				equation.name.to_string() + ": " + equation.formula,
+			)),
+			function: MenuItemFunction::Action(func(Function::SolveLibraryEquation(idx))),
+			help: None,
+		});
+	}
+	Menu::new("Equations", items)
+}
+
+fn bits_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	Menu::new(
-		"Statistics",
+		"Bits",
 		create_action_items(&[
-			("sum", func(Function::Stack(StackFunction::Sum))),
-			("mean", func(Function::Stack(StackFunction::Mean))),
+			(
+				"popcnt - bits set",
+				func(Function::Stack(StackFunction::PopulationCount)),
+			),
+			(
+				"clz - leading zeros",
+				func(Function::Stack(StackFunction::LeadingZeros)),
+			),
+			(
+				"ctz - trailing zeros",
+				func(Function::Stack(StackFunction::TrailingZeros)),
+			),
+			(
+				"Bit length",
+				func(Function::Stack(StackFunction::BitLength)),
+			),
+			(
+				"Extract field",
+				func(Function::Stack(StackFunction::ExtractBits)),
+			),
+			(
+				"Insert field",
+				func(Function::Stack(StackFunction::InsertBits)),
+			),
+			(
+				"Low bit mask",
+				func(Function::Stack(StackFunction::BitMask)),
+			),
+			(
+				"Byte swap 16",
+				func(Function::Stack(StackFunction::ByteSwap16)),
+			),
+			(
+				"Byte swap 32",
+				func(Function::Stack(StackFunction::ByteSwap32)),
+			),
+			(
+				"Byte swap 64",
+				func(Function::Stack(StackFunction::ByteSwap64)),
+			),
+			(
+				"Byte reverse",
+				func(Function::Stack(StackFunction::ByteReverse)),
+			),
+		]),
+	)
+}
+
+fn network_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Network",
+		create_action_items(&[
+			(
+				"Address → integer",
+				func(Function::Stack(StackFunction::AddressToInteger)),
+			),
+			(
+				"Integer → IPv4",
+				func(Function::Stack(StackFunction::IntegerToIpv4)),
+			),
+			(
+				"Integer → IPv6",
+				func(Function::Stack(StackFunction::IntegerToIpv6)),
+			),
+			(
+				"Network address",
+				func(Function::Stack(StackFunction::NetworkAddress)),
+			),
+			(
+				"Broadcast address",
+				func(Function::Stack(StackFunction::BroadcastAddress)),
+			),
+			("Netmask", func(Function::Stack(StackFunction::Netmask))),
+			(
+				"Host count",
+				func(Function::Stack(StackFunction::HostCount)),
+			),
+		]),
+	)
+}
+
+fn checksum_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Checksums",
+		create_action_items(&[
+			(
+				"Additive",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Additive,
+					ByteOrder::BigEndian,
+				))),
+			),
+			(
+				"Additive LE",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Additive,
+					ByteOrder::LittleEndian,
+				))),
+			),
+			(
+				"XOR",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Xor,
+					ByteOrder::BigEndian,
+				))),
+			),
+			(
+				"XOR LE",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Xor,
+					ByteOrder::LittleEndian,
+				))),
+			),
+			(
+				"Internet",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Internet,
+					ByteOrder::BigEndian,
+				))),
+			),
+			(
+				"Internet LE",
+				func(Function::Stack(StackFunction::Checksum(
+					ChecksumKind::Internet,
+					ByteOrder::LittleEndian,
+				))),
+			),
 		]),
 	)
 }
 
-fn time_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+fn number_theory_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Number Theory",
+		create_action_items(&[
+			("nth prime", func(Function::Stack(StackFunction::NthPrime))),
+			(
+				"π(x) - prime count",
+				func(Function::Stack(StackFunction::PrimeCount)),
+			),
+		]),
+	)
+}
+
+fn text_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Text",
+		create_action_items(&[
+			("Enter text", func(Function::EnterText)),
+			(
+				"Integer → text",
+				func(Function::Stack(StackFunction::ToText)),
+			),
+			(
+				"Text → integer",
+				func(Function::Stack(StackFunction::FromText)),
+			),
+			(
+				"Base64 encode",
+				func(Function::Stack(StackFunction::Base64Encode)),
+			),
+			(
+				"Base64 decode",
+				func(Function::Stack(StackFunction::Base64Decode)),
+			),
+			(
+				"Hex encode",
+				func(Function::Stack(StackFunction::HexEncode)),
+			),
+			(
+				"Hex decode",
+				func(Function::Stack(StackFunction::HexDecode)),
+			),
+		]),
+	)
+}
+
+fn probability_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	let mut menu = Menu::new(
+		"Probability",
+		create_action_items(&[
+			(
+				"nPDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::Normal,
+					DistributionFunction::Pdf,
+				))),
+			),
+			(
+				"nCDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::Normal,
+					DistributionFunction::Cdf,
+				))),
+			),
+			(
+				"nInv",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::Normal,
+					DistributionFunction::InverseCdf,
+				))),
+			),
+			(
+				"tPDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::StudentT,
+					DistributionFunction::Pdf,
+				))),
+			),
+			(
+				"tCDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::StudentT,
+					DistributionFunction::Cdf,
+				))),
+			),
+			(
+				"tInv",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::StudentT,
+					DistributionFunction::InverseCdf,
+				))),
+			),
+			(
+				"χ²PDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::ChiSquare,
+					DistributionFunction::Pdf,
+				))),
+			),
+			(
+				"χ²CDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::ChiSquare,
+					DistributionFunction::Cdf,
+				))),
+			),
+			(
+				"χ²Inv",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::ChiSquare,
+					DistributionFunction::InverseCdf,
+				))),
+			),
+			(
+				"FPDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::F,
+					DistributionFunction::Pdf,
+				))),
+			),
+			(
+				"FCDF",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::F,
+					DistributionFunction::Cdf,
+				))),
+			),
+			(
+				"FInv",
+				func(Function::Stack(StackFunction::Distribution(
+					DistributionKind::F,
+					DistributionFunction::InverseCdf,
+				))),
+			),
+		]),
+	);
+	menu.set_columns(3);
+	menu
+}
+
+fn stats_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	Menu::new(
-		"Time",
+		"Statistics",
 		create_action_items(&[
-			("Now", func(Function::Stack(StackFunction::Now))),
-			("Date", func(Function::Stack(StackFunction::Date))),
-			("Time", func(Function::Stack(StackFunction::Time))),
+			("sum", func(Function::Stack(StackFunction::Sum))),
+			("mean", func(Function::Stack(StackFunction::Mean))),
+			("edit data", func(Function::EditData)),
+			(
+				"interpolate",
+				func(Function::Stack(StackFunction::Interpolate)),
+			),
+			(
+				"table interpolate",
+				func(Function::Stack(StackFunction::TableInterpolate)),
+			),
 		]),
 	)
 }
 
+fn time_catalog_menu(
+	page: &dyn Fn(CatalogPage) -> Function,
+	func: &dyn Fn(Function) -> Function,
+) -> Menu {
+	let mut items = create_action_items(&[
+		("Now", func(Function::Stack(StackFunction::Now))),
+		("Date", func(Function::Stack(StackFunction::Date))),
+		("Time", func(Function::Stack(StackFunction::Time))),
+		(
+			"ISO 8601 to date",
+			func(Function::Stack(StackFunction::FromIso8601)),
+		),
+		(
+			"Date to ISO 8601",
+			func(Function::Stack(StackFunction::ToIso8601)),
+		),
+		("Set alarm", func(Function::SetAlarm)),
+		(
+			"Set alarm with message",
+			func(Function::SetAlarmWithMessage),
+		),
+		("Calendar", func(Function::ShowCalendar)),
+	]);
+	items.append(&mut create_parent_items(&[(
+		"Alarms",
+		page(CatalogPage::Alarms),
+	)]));
+	Menu::new("Time", items)
+}
+
+/// Pending alarms, which are cancelled when selected
+fn alarm_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	if state.alarms().is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+
+	let time_24_hour = state.context().format().time_24_hour;
+	let mut items = Vec::new();
+	for (idx, alarm) in state.alarms().iter().enumerate() {
+		let text = match alarm.message() {
+			Some(message) => alarm.time_string(time_24_hour) + " " + message,
+			None => alarm.time_string(time_24_hour),
+		};
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |_state, screen| {
+				Layout::LeftAlign(Box::new(text.ellipsis_layout(
+					Font::Small,
+					TokenType::Text,
+					screen.metrics(),
+					screen.width() - 16,
+				)))
+			})),
+			function: MenuItemFunction::Action(func(Function::CancelAlarm(idx))),
+			help: Some("Cancels the alarm"),
+		});
+	}
+	Ok(Menu::new("Alarms", items))
+}
+
+/// Conversions between ratios and decibels, and between levels in dBm and
+/// power or voltage. The last item sets the impedance that voltages are across.
+fn electronics_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	let mut items = create_action_items(&[
+		(
+			"power ratio → dB",
+			func(Function::Stack(StackFunction::RatioToDb(
+				DecibelScale::Power,
+			))),
+		),
+		(
+			"dB → power ratio",
+			func(Function::Stack(StackFunction::DbToRatio(
+				DecibelScale::Power,
+			))),
+		),
+		(
+			"voltage ratio → dB",
+			func(Function::Stack(StackFunction::RatioToDb(
+				DecibelScale::Field,
+			))),
+		),
+		(
+			"dB → voltage ratio",
+			func(Function::Stack(StackFunction::DbToRatio(
+				DecibelScale::Field,
+			))),
+		),
+		("dBm → W", func(Function::Stack(StackFunction::DbmToWatts))),
+		("W → dBm", func(Function::Stack(StackFunction::WattsToDbm))),
+		("dBm → V", func(Function::Stack(StackFunction::DbmToVolts))),
+		("V → dBm", func(Function::Stack(StackFunction::VoltsToDbm))),
+	]);
+	let set_impedance = Function::Stack(StackFunction::SetReferenceImpedance);
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::string_layout(
+				"Reference   ".to_string()
+					+ &state
+						.context()
+						.format()
+						.format_number(state.context().reference_impedance())
+						.to_string() + " Ω",
+			)
+		})),
+		help: set_impedance.help(),
+		function: MenuItemFunction::Action(func(set_impedance)),
+	});
+	Menu::new("Electronics", items)
+}
+
 fn transcendental_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	let mut menu = Menu::new(
 		"Transcendental",
@@ -135,6 +768,18 @@ fn transcendental_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 			("asinh", func(Function::Stack(StackFunction::Asinh))),
 			("acosh", func(Function::Stack(StackFunction::Acosh))),
 			("atanh", func(Function::Stack(StackFunction::Atanh))),
+			(
+				"→ degrees",
+				func(Function::Stack(StackFunction::ToAngle(AngleUnit::Degrees))),
+			),
+			(
+				"→ radians",
+				func(Function::Stack(StackFunction::ToAngle(AngleUnit::Radians))),
+			),
+			(
+				"→ gradians",
+				func(Function::Stack(StackFunction::ToAngle(AngleUnit::Gradians))),
+			),
 		]),
 	);
 	menu.set_columns(2);
@@ -152,36 +797,534 @@ fn main_unit_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	)
 }
 
+/// Lists the list functions, followed by the user functions that can be mapped
+/// over a list or used to reduce one
+fn list_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Menu {
+	let mut items = create_action_items(&[
+		("→list", func(Function::Stack(StackFunction::ToList))),
+		("get", func(Function::Stack(StackFunction::ListGet))),
+		("append", func(Function::Stack(StackFunction::ListAppend))),
+		("sum", func(Function::Stack(StackFunction::Sum))),
+		("mean", func(Function::Stack(StackFunction::Mean))),
+	]);
+	items.append(&mut user_function_items(state, Some(1), "Map ", &|idx| {
+		func(Function::MapList(idx))
+	}));
+	items.append(&mut user_function_items(
+		state,
+		Some(2),
+		"Reduce ",
+		&|idx| func(Function::ReduceList(idx)),
+	));
+	Menu::new("Lists", items)
+}
+
+fn stack_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Stack",
+		create_action_items(&[
+			(
+				"add into level",
+				func(Function::Stack(StackFunction::LevelAdd)),
+			),
+			(
+				"subtract from level",
+				func(Function::Stack(StackFunction::LevelSub)),
+			),
+			(
+				"multiply level",
+				func(Function::Stack(StackFunction::LevelMul)),
+			),
+			(
+				"divide level",
+				func(Function::Stack(StackFunction::LevelDiv)),
+			),
+		]),
+	)
+}
+
+fn coordinate_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
+	Menu::new(
+		"Coordinates",
+		create_action_items(&[
+			("rotate 2D", func(Function::Stack(StackFunction::Rotate2D))),
+			("rotate 3D", func(Function::Stack(StackFunction::Rotate3D))),
+			("translate", func(Function::Input(InputEvent::Add))),
+		]),
+	)
+}
+
 fn vector_catalog_menu(func: &dyn Fn(Function) -> Function) -> Menu {
 	Menu::new(
-		"Vector",
+		"Vectors",
 		create_action_items(&[
 			("dot", func(Function::Stack(StackFunction::DotProduct))),
 			("cross", func(Function::Stack(StackFunction::CrossProduct))),
 			("magnitude", func(Function::Stack(StackFunction::Magnitude))),
 			("normalize", func(Function::Stack(StackFunction::Normalize))),
+			("angle", func(Function::Stack(StackFunction::AngleBetween))),
+			(
+				"quaternion",
+				func(Function::Stack(StackFunction::Quaternion)),
+			),
+			("conjugate", func(Function::Stack(StackFunction::Conjugate))),
+			(
+				"quaternion to rotation",
+				func(Function::Stack(StackFunction::QuaternionToRotation)),
+			),
+			(
+				"rotation to quaternion",
+				func(Function::Stack(StackFunction::RotationToQuaternion)),
+			),
+			(
+				"quaternion to axis-angle",
+				func(Function::Stack(StackFunction::QuaternionToAxisAngle)),
+			),
+			(
+				"axis-angle to quaternion",
+				func(Function::Stack(StackFunction::AxisAngleToQuaternion)),
+			),
 		]),
 	)
 }
 
-pub fn assign_menu() -> Menu {
+/// Lists the stored variables and registers along with their values. Selecting
+/// one recalls its value onto the stack.
+fn variables_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	let locations = state.context().memory_locations();
+	if locations.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+
+	let mut items = Vec::new();
+	for location in locations {
+		let name = location.to_string();
+		let function = MenuItemFunction::Action(func(Function::RecallVariable(location.clone())));
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, screen| {
+				let label = Layout::Text(name.clone() + " = ", Font::Small, TokenType::Label);
+				let width = screen.width() - label.width(screen.metrics()) - 16;
+				let value = match state.context().read(&location) {
+					Ok(value) => value.layout(
+						state.context().format(),
+						Font::Small,
+						screen.metrics(),
+						width,
+					),
+					Err(error) => Layout::StaticText(error.to_str(), Font::Small, TokenType::Error),
+				};
+				Layout::LeftAlign(Box::new(Layout::Horizontal(vec![label, value])))
+			})),
+			function,
+			help: None,
+		});
+	}
+	Ok(Menu::new("Variables", items))
+}
+
+/// Results of the most recent operations, newest first. Selecting one pushes
+/// it back onto the stack.
+fn answers_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	let count = state.answers().answers().len();
+	if count == 0 {
+		return Err(Error::ValueNotDefined);
+	}
+
+	let mut items = Vec::new();
+	for idx in (0..count).rev() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, screen| {
+				match state.answers().get(idx) {
+					Some(value) => Layout::LeftAlign(Box::new(value.layout(
+						state.context().format(),
+						Font::Small,
+						screen.metrics(),
+						screen.width() - 16,
+					))),
+					None => MenuItem::static_string_layout(""),
+				}
+			})),
+			function: MenuItemFunction::Action(func(Function::RecallAnswer(idx))),
+			help: None,
+		});
+	}
+	Ok(Menu::new("Answers", items))
+}
+
+/// Lists the stored script programs. Selecting one runs it.
+fn scripts_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	if state.scripts().is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+
+	let mut items = Vec::new();
+	for (idx, script) in state.scripts().iter().enumerate() {
+		let function = MenuItemFunction::Action(func(Function::RunScript(idx)));
+		let name = Function::RunScript(idx).to_string(state) + ": ";
+		let source = script.source().to_string();
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |_state, screen| {
+				let label = Layout::Text(name.clone(), Font::Small, TokenType::Label);
+				let width = screen.width() - label.width(screen.metrics()) - 16;
+				let items = vec![
+					label,
+					source.ellipsis_layout(Font::Small, TokenType::Text, screen.metrics(), width),
+				];
+				Layout::LeftAlign(Box::new(Layout::Horizontal(items)))
+			})),
+			function,
+			help: None,
+		});
+	}
+	Ok(Menu::new("Scripts", items))
+}
+
+/// Name of a user function along with its arguments, such as `F(x, y)`
+fn user_function_signature(name: &str, args: &[String]) -> String {
+	let mut result = name.to_string() + "(";
+	for (idx, arg) in args.iter().enumerate() {
+		if idx != 0 {
+			result += ", ";
+		}
+		result += arg;
+	}
+	result + ")"
+}
+
+/// Lists the user functions that take `arg_count` arguments, or all of them,
+/// with `prefix` before each name
+fn user_function_items(
+	state: &State,
+	arg_count: Option<usize>,
+	prefix: &str,
+	func: &dyn Fn(usize) -> Function,
+) -> Vec<MenuItem> {
+	let mut items = Vec::new();
+	for (idx, script) in state.scripts().iter().enumerate() {
+		if let (Some(name), Some(args)) = (script.name(), script.arguments()) {
+			if arg_count.map(|count| count == args.len()) == Some(false) {
+				continue;
+			}
+			items.push(MenuItem {
+				layout: MenuItemLayout::Static(MenuItem::string_layout(
+					prefix.to_string() + &user_function_signature(name, args),
+				)),
+				function: MenuItemFunction::Action(func(idx)),
+				help: None,
+			});
+		}
+	}
+	items
+}
+
+/// Lists the user functions. Selecting one runs it on the arguments on the
+/// stack.
+fn user_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	let items = user_function_items(state, None, "", &|idx| func(Function::RunScript(idx)));
+	if items.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+	Ok(Menu::new("User Functions", items))
+}
+
+/// Lists the sum and product over each user function of one argument. The
+/// limits of the index are taken from the stack.
+fn series_catalog_menu(state: &State, func: &dyn Fn(Function) -> Function) -> Result<Menu> {
+	let mut items = user_function_items(state, Some(1), "Σ ", &|idx| {
+		func(Function::Series(Series::Sum, idx))
+	});
+	items.append(&mut user_function_items(state, Some(1), "Π ", &|idx| {
+		func(Function::Series(Series::Product, idx))
+	}));
+	if items.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+	Ok(Menu::new("Series", items))
+}
+
+/// Lists the user functions so that one can be solved for any of its arguments
+pub fn solver_menu(state: &State) -> Result<Menu> {
+	let items = user_function_items(state, None, "", &Function::SolveEquation);
+	if items.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+	Ok(Menu::new("Solver", items))
+}
+
+/// Lists the user functions of one argument so that one can be graphed
+pub fn graph_user_function_menu(state: &State) -> Result<Menu> {
+	let items = user_function_items(state, Some(1), "", &Function::GraphScript);
+	if items.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+	Ok(Menu::new("Graph User Function", items))
+}
+
+pub fn overlay_user_function_menu(state: &State) -> Result<Menu> {
+	let items = user_function_items(state, Some(1), "", &Function::OverlayScript);
+	if items.is_empty() {
+		return Err(Error::ValueNotDefined);
+	}
+	Ok(Menu::new("Overlay User Function", items))
+}
+
+pub fn assign_menu(state: &State, menu: Option<usize>) -> Menu {
 	let mut items = Vec::new();
-	for i in 0..18 {
+	for i in 0..state.custom_slots() {
+		let slot = CustomSlot::new(menu, i);
 		items.push(MenuItem {
 			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
-				if let Some(func) = state.custom_function(i) {
-					MenuItem::string_layout(func.to_string(state))
+				if let Some(func) = state.custom_function(slot) {
+					if state.function_keys().is_slot_locked(slot) {
+						MenuItem::string_layout("[L] ".to_string() + &func.to_string(state))
+					} else {
+						MenuItem::string_layout(func.to_string(state))
+					}
 				} else {
 					MenuItem::static_string_layout("(None)")
 				}
 			})),
 			function: MenuItemFunction::InMenuActionWithDelete(
-				Function::AssignCatalogMenu(i),
-				Function::RemoveCustomAssign(i),
+				Function::AssignCatalogMenu(slot),
+				Function::RemoveCustomAssign(slot),
 			),
+			help: None,
 		});
 	}
-	let mut menu = Menu::new("Assign Custom Functions", items);
+	let mut menu = match menu {
+		Some(menu) => Menu::new(
+			&("Assign ".to_string() + state.function_keys().custom_menu_name(menu)),
+			items,
+		),
+		None => {
+			// Banks are listed after the slots so that slots keep their positions
+			items.push(MenuItem {
+				layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+					let keys = state.function_keys();
+					MenuItem::string_layout(
+						"Bank [".to_string()
+							+ keys.custom_bank_name(keys.active_custom_bank())
+							+ "] >",
+					)
+				})),
+				function: MenuItemFunction::InMenuAction(Function::CustomBanksMenu),
+				help: Some("Switches between sets of functions assigned to the custom menu"),
+			});
+			Menu::new("Assign Custom Functions", items)
+		}
+	};
 	menu.set_columns(3);
 	menu
 }
+
+/// Lists the banks of custom functions. Picking a bank shows it in the custom
+/// menu, and the active bank can be renamed.
+pub fn custom_banks_menu(state: &State) -> Menu {
+	let mut items = Vec::new();
+	let keys = state.function_keys();
+	for bank in 0..keys.custom_bank_count() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				let keys = state.function_keys();
+				let name = keys.custom_bank_name(bank);
+				if bank == keys.active_custom_bank() {
+					MenuItem::string_layout(name.to_string() + "   [Active]")
+				} else {
+					MenuItem::string_layout(name.to_string())
+				}
+			})),
+			function: MenuItemFunction::InMenuActionWithDelete(
+				Function::SelectCustomBank(bank),
+				Function::DeleteCustomBank(bank),
+			),
+			help: Some("Press ← to delete the bank"),
+		});
+	}
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("New Bank")),
+		function: MenuItemFunction::InMenuAction(Function::NewCustomBank),
+		help: None,
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Rename Active Bank")),
+		function: MenuItemFunction::InMenuAction(Function::RenameCustomBank),
+		help: None,
+	});
+	Menu::new("Custom Banks", items)
+}
+
+/// Menu of the program slots, with the number of steps in each. Choosing a slot
+/// makes its program the one that is graphed and run.
+pub fn program_slots_menu() -> Menu {
+	let mut items = Vec::new();
+	for slot in 0..PROGRAM_SLOTS {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				let mut text = state.program_slot_name(slot).to_string();
+				match state.program_slot_steps(slot) {
+					Some(steps) => {
+						text += "   ";
+						text += &Number::Integer(steps.len().into()).to_string();
+						text += if steps.len() == 1 { " step" } else { " steps" };
+					}
+					None => text += "   Empty",
+				}
+				if slot == state.active_program_slot() {
+					text += "   [Active]";
+				}
+				MenuItem::string_layout(text)
+			})),
+			function: MenuItemFunction::InMenuActionWithDelete(
+				Function::SelectProgramSlot(slot),
+				Function::ClearProgramSlot(slot),
+			),
+			help: Some("Press ← to clear the program"),
+		});
+	}
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Rename Active Slot")),
+		function: MenuItemFunction::InMenuAction(Function::RenameProgramSlot),
+		help: None,
+	});
+	Menu::new("Program Slots", items)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{stack_effect_preview, CATALOG_PAGES};
+	use crate::dm42::functions::Function;
+	use crate::dm42::golden::RecordingScreen;
+	use crate::dm42::menu::MenuItemFunction;
+	use crate::dm42::state::State;
+	use rscalc_math::format::IntegerMode;
+	use rscalc_math::functions::StackFunction;
+	use rscalc_math::number::{Number, ToNumber};
+	use rscalc_math::quaternion::Quaternion;
+	use rscalc_math::value::Value;
+
+	fn is_nan(value: &Value) -> bool {
+		match value {
+			Value::Number(value) | Value::NumberWithUnit(value, _) => value.is_nan(),
+			Value::Complex(value) => value.real_part().is_nan() || value.imaginary_part().is_nan(),
+			_ => false,
+		}
+	}
+
+	/// Runs every function of the catalog on arguments at and around the edges of
+	/// the real domains. Each must give a number or fail with an error, and never
+	/// leave a value that is not a number on the stack.
+	#[test]
+	fn catalog_functions_give_numbers_or_errors() {
+		let arguments = [
+			(-2).to_number(),
+			(-1).to_number(),
+			0.to_number(),
+			1.to_number() / 2.to_number(),
+			1.to_number(),
+			2.to_number(),
+			1000.to_number(),
+		];
+		let screen = RecordingScreen::new(400, 240);
+		let state = State::new();
+		for page in CATALOG_PAGES.iter() {
+			// Pages that list what the user has made can't be shown in a new state
+			let menu = match page.menu(&state, &|page| Function::CatalogPage(page), &|func| func) {
+				Ok(menu) => menu,
+				Err(_) => continue,
+			};
+			for idx in 0..menu.item_count() {
+				let func = match menu.item_function(idx) {
+					Some(MenuItemFunction::Action(func)) => func,
+					Some(MenuItemFunction::InMenuAction(func)) => func,
+					_ => continue,
+				};
+				for argument in arguments.iter() {
+					let mut state = State::new();
+					for _ in 0..4 {
+						let value = Value::Number(Number::clone(argument));
+						state.context_mut().stack_mut().push(value).unwrap();
+					}
+					if func.execute(&mut state, &screen).is_err() {
+						continue;
+					}
+					let stack = state.context().stack();
+					for entry in 0..stack.len() {
+						assert!(
+							!is_nan(&stack.entry(entry).unwrap()),
+							"{} of {} in {}",
+							func.to_string(&state),
+							argument.to_string(),
+							page.to_str()
+						);
+					}
+				}
+			}
+		}
+	}
+
+	fn preview(state: &State, func: StackFunction) -> Option<String> {
+		let screen = RecordingScreen::new(400, 240);
+		stack_effect_preview(
+			state,
+			&MenuItemFunction::Action(Function::Stack(func)),
+			&screen,
+		)
+	}
+
+	fn push_numbers(state: &mut State, values: &[i32]) {
+		for value in values {
+			let value = Value::Number(value.to_number());
+			state.context_mut().stack_mut().push(value).unwrap();
+		}
+	}
+
+	#[test]
+	fn preview_shows_only_the_values_used() {
+		let mut state = State::new();
+		push_numbers(&mut state, &[7, 3, 2]);
+		assert_eq!(
+			preview(&state, StackFunction::ShiftLeft).unwrap(),
+			"3 << 2 → 12"
+		);
+		push_numbers(&mut state, &[10]);
+		assert_eq!(
+			preview(&state, StackFunction::NthPrime).unwrap(),
+			"prime 10 → 29"
+		);
+	}
+
+	#[test]
+	fn preview_shows_every_result() {
+		let mut state = State::new();
+		let value =
+			Quaternion::from_parts(1.to_number(), 2.to_number(), 3.to_number(), 4.to_number());
+		state
+			.context_mut()
+			.stack_mut()
+			.push(Value::Quaternion(value))
+			.unwrap();
+		let preview = preview(&state, StackFunction::Quaternion).unwrap();
+		assert!(preview.ends_with("→ 1, 2, 3, 4"), "{}", preview);
+	}
+
+	#[test]
+	fn no_preview_of_functions_with_side_effects() {
+		let mut state = State::new();
+		state
+			.context_mut()
+			.set_integer_mode(IntegerMode::SizedInteger(8, false));
+		push_numbers(&mut state, &[200, 100]);
+		state.context_mut().add().unwrap();
+		assert!(state.context().carry());
+		assert_eq!(preview(&state, StackFunction::ClearFlags), None);
+		assert!(state.context().carry());
+	}
+
+	#[test]
+	fn no_preview_of_long_computations() {
+		let mut state = State::new();
+		let value = Value::Number(10.to_number().pow(&10.to_number()));
+		state.context_mut().stack_mut().push(value).unwrap();
+		assert_eq!(preview(&state, StackFunction::NthPrime), None);
+	}
+}
@@ -0,0 +1,70 @@
+use rscalc_math::error::{Error, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// The window libraries of the simulators don't give access to the clipboard, so
+// the tools of the system are used instead. Each command is tried in order until
+// one of them is installed.
+
+#[cfg(target_os = "macos")]
+const COPY_COMMANDS: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(target_os = "macos")]
+const PASTE_COMMANDS: &[&[&str]] = &[&["pbpaste"]];
+
+#[cfg(target_os = "windows")]
+const COPY_COMMANDS: &[&[&str]] = &[&["clip"]];
+#[cfg(target_os = "windows")]
+const PASTE_COMMANDS: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]];
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const COPY_COMMANDS: &[&[&str]] = &[
+	&["wl-copy"],
+	&["xclip", "-selection", "clipboard"],
+	&["xsel", "--clipboard", "--input"],
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const PASTE_COMMANDS: &[&[&str]] = &[
+	&["wl-paste", "--no-newline"],
+	&["xclip", "-selection", "clipboard", "-o"],
+	&["xsel", "--clipboard", "--output"],
+];
+
+fn command(args: &[&str]) -> Command {
+	let mut command = Command::new(args[0]);
+	command.args(&args[1..]).stderr(Stdio::null());
+	command
+}
+
+pub fn copy(text: &str) -> Result<()> {
+	for args in COPY_COMMANDS {
+		let mut child = match command(args)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::null())
+			.spawn()
+		{
+			Ok(child) => child,
+			Err(_) => continue,
+		};
+		let written = match child.stdin.take() {
+			Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+			None => false,
+		};
+		if let Ok(status) = child.wait() {
+			if written && status.success() {
+				return Ok(());
+			}
+		}
+	}
+	Err(Error::NotSupported)
+}
+
+pub fn paste() -> Result<String> {
+	for args in PASTE_COMMANDS {
+		if let Ok(output) = command(args).stdin(Stdio::null()).output() {
+			if output.status.success() {
+				return String::from_utf8(output.stdout).map_err(|_| Error::InvalidEntry);
+			}
+		}
+	}
+	Err(Error::NotSupported)
+}
@@ -0,0 +1,169 @@
+use crate::dm42::functions::Function;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use rscalc_math::error::{Error, Result};
+use rscalc_math::number::{Number, ToNumber};
+use rscalc_math::persist::{read_item, write_item};
+use rscalc_math::storage::{
+	deserialize_portable, serialize_portable, DeserializeInput, SerializeOutput,
+};
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::ToString;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Currencies that rates can be entered for. Saved rates refer to currencies by
+/// their position, so new currencies must be added to the end.
+pub const CURRENCY_CODES: [&str; 11] = [
+	"USD", "EUR", "GBP", "JPY", "CNY", "CAD", "AUD", "CHF", "INR", "MXN", "KRW",
+];
+
+/// Exchange rates entered by the user. Each rate is the number of units of the
+/// currency that one unit of the home currency buys, so the home currency
+/// itself always has a rate of one.
+pub struct CurrencyRates {
+	home: usize,
+	rates: Vec<Option<Number>>,
+}
+
+impl CurrencyRates {
+	pub fn new() -> Self {
+		CurrencyRates {
+			home: 0,
+			rates: CURRENCY_CODES.iter().map(|_| None).collect(),
+		}
+	}
+
+	pub fn home(&self) -> usize {
+		self.home
+	}
+
+	pub fn rate(&self, currency: usize) -> Result<Number> {
+		if currency == self.home {
+			return Ok(1.into());
+		}
+		self.rates
+			.get(currency)
+			.ok_or(Error::InvalidArgument)?
+			.clone()
+			.ok_or(Error::ValueNotDefined)
+	}
+
+	pub fn set_rate(&mut self, currency: usize, rate: Number) -> Result<()> {
+		if currency == self.home {
+			return Err(Error::InvalidArgument);
+		}
+		if rate.is_zero() || rate.is_negative() {
+			return Err(Error::ValueOutOfRange);
+		}
+		*self.rates.get_mut(currency).ok_or(Error::InvalidArgument)? = Some(rate);
+		Ok(())
+	}
+
+	/// Converts an amount between two currencies through the home currency
+	pub fn convert(&self, amount: &Number, from: usize, to: usize) -> Result<Number> {
+		Ok(&(amount * &self.rate(to)?) / &self.rate(from)?)
+	}
+
+	/// Makes the next currency that has a rate the home currency. The rates of
+	/// the others are changed to be relative to it, so conversions give the same
+	/// results as before.
+	pub fn next_home(&mut self) -> Result<()> {
+		let count = CURRENCY_CODES.len();
+		let new_home = (1..count)
+			.map(|offset| (self.home + offset) % count)
+			.find(|currency| self.rates[*currency].is_some())
+			.ok_or(Error::ValueNotDefined)?;
+		let new_home_rate = self.rate(new_home)?;
+		for currency in 0..count {
+			self.rates[currency] = if currency == new_home {
+				None
+			} else if currency == self.home {
+				Some(&1.to_number() / &new_home_rate)
+			} else {
+				self.rates[currency]
+					.as_ref()
+					.map(|rate| rate / &new_home_rate)
+			};
+		}
+		self.home = new_home;
+		Ok(())
+	}
+
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_u8(self.home as u8)?;
+		output.write_u32(self.rates.len() as u32)?;
+		for rate in &self.rates {
+			match rate {
+				Some(rate) => {
+					output.write_u8(1)?;
+					write_item(output, |output| serialize_portable(rate, output))?;
+				}
+				None => output.write_u8(0)?,
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads rates written by `save`. Rates of currencies that this version does
+	/// not know about are dropped.
+	pub fn restore(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		let mut rates = CurrencyRates::new();
+		let home = input.read_u8()? as usize;
+		if home >= CURRENCY_CODES.len() {
+			return Err(Error::CorruptData);
+		}
+		rates.home = home;
+		let count = input.read_u32()? as usize;
+		for currency in 0..count {
+			if input.read_u8()? != 0 {
+				let rate: Number = deserialize_portable(&mut read_item(input)?)?;
+				if currency < CURRENCY_CODES.len() && currency != home {
+					rates.rates[currency] = Some(rate);
+				}
+			}
+		}
+		*self = rates;
+		Ok(())
+	}
+}
+
+/// Menu for updating the stored rates. Picking a currency sets its rate to x.
+pub fn currency_rates_menu() -> Menu {
+	let mut items = Vec::new();
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::string_layout(
+				"Home   [".to_string() + CURRENCY_CODES[state.currencies().home()] + "]",
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::CurrencyHomeCycle),
+		help: Some("Currency that rates are given against. Changing it keeps the stored rates"),
+	});
+	for (currency, &code) in CURRENCY_CODES.iter().enumerate() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				let rates = state.currencies();
+				let rate = if currency == rates.home() {
+					"[Home]".to_string()
+				} else {
+					match rates.rate(currency) {
+						Ok(rate) => Value::Number(rate)
+							.format(state.context().format())
+							.to_string(),
+						Err(_) => "—".to_string(),
+					}
+				};
+				MenuItem::string_layout(code.to_string() + "   " + &rate)
+			})),
+			function: MenuItemFunction::InMenuAction(Function::SetCurrencyRate(currency)),
+			help: Some("Sets the rate to x units per unit of the home currency"),
+		});
+	}
+	let mut menu = Menu::new("Currency Rates", items);
+	menu.set_columns(2);
+	menu
+}
@@ -0,0 +1,173 @@
+use crate::dm42::animation::FRAME_TIME_MS;
+use crate::dm42::calc_main;
+use crate::dm42::host::{Refresh, VirtualDM42Screen, VirtualKeyboard, HEIGHT, SET_PIXEL, WIDTH};
+use crate::dm42::input::{Key, KeyEvent, Keyboard};
+use crate::dm42::platform::interrupt_computation;
+use minifb::{Key as WindowKey, KeyRepeat, MouseButton, MouseMode, Scale, Window, WindowOptions};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Maps a key of the desktop keyboard to a calculator key. Letters press the key
+/// that types them in alpha mode, as on the calculator keyboard. Tab acts as the
+/// shift key of the calculator, as the desktop shift key is needed to type
+/// symbols.
+fn calculator_key(key: WindowKey, shift: bool) -> Option<Key> {
+	match key {
+		WindowKey::Key0 | WindowKey::NumPad0 => Some(Key::Zero),
+		WindowKey::Key1 | WindowKey::NumPad1 => Some(Key::One),
+		WindowKey::Key2 | WindowKey::NumPad2 => Some(Key::Two),
+		WindowKey::Key3 | WindowKey::NumPad3 => Some(Key::Three),
+		WindowKey::Key4 | WindowKey::NumPad4 => Some(Key::Four),
+		WindowKey::Key5 | WindowKey::NumPad5 => Some(Key::Five),
+		WindowKey::Key6 | WindowKey::NumPad6 => Some(Key::Six),
+		WindowKey::Key7 | WindowKey::NumPad7 => Some(Key::Seven),
+		WindowKey::Key8 if shift => Some(Key::Mul),
+		WindowKey::Key8 | WindowKey::NumPad8 => Some(Key::Eight),
+		WindowKey::Key9 | WindowKey::NumPad9 => Some(Key::Nine),
+		WindowKey::Period | WindowKey::NumPadDot => Some(Key::Dot),
+		WindowKey::Equal if shift => Some(Key::Add),
+		WindowKey::NumPadPlus => Some(Key::Add),
+		WindowKey::Minus | WindowKey::NumPadMinus => Some(Key::Sub),
+		WindowKey::NumPadAsterisk => Some(Key::Mul),
+		WindowKey::Slash | WindowKey::NumPadSlash => Some(Key::Div),
+		WindowKey::Enter | WindowKey::NumPadEnter => Some(Key::Enter),
+		WindowKey::Backspace => Some(Key::Backspace),
+		WindowKey::Escape => Some(Key::Exit),
+		WindowKey::Tab => Some(Key::Shift),
+		WindowKey::Up if shift => Some(Key::ShiftUp),
+		WindowKey::Up => Some(Key::Up),
+		WindowKey::Down if shift => Some(Key::ShiftDown),
+		WindowKey::Down => Some(Key::Down),
+		WindowKey::F1 => Some(Key::F1),
+		WindowKey::F2 => Some(Key::F2),
+		WindowKey::F3 => Some(Key::F3),
+		WindowKey::F4 => Some(Key::F4),
+		WindowKey::F5 => Some(Key::F5),
+		WindowKey::F6 => Some(Key::F6),
+		WindowKey::F12 => Some(Key::Screenshot),
+		WindowKey::A => Some(Key::Sigma),
+		WindowKey::B => Some(Key::Recip),
+		WindowKey::C => Some(Key::Sqrt),
+		WindowKey::D => Some(Key::Log),
+		WindowKey::E => Some(Key::Ln),
+		WindowKey::F => Some(Key::Xeq),
+		WindowKey::G => Some(Key::Sto),
+		WindowKey::H => Some(Key::Rcl),
+		WindowKey::I => Some(Key::RotateDown),
+		WindowKey::J => Some(Key::Sin),
+		WindowKey::K => Some(Key::Cos),
+		WindowKey::L => Some(Key::Tan),
+		WindowKey::M => Some(Key::Swap),
+		WindowKey::N => Some(Key::Neg),
+		WindowKey::O => Some(Key::E),
+		WindowKey::P => Some(Key::Seven),
+		WindowKey::Q => Some(Key::Eight),
+		WindowKey::R => Some(Key::Nine),
+		WindowKey::S => Some(Key::Div),
+		WindowKey::T => Some(Key::Four),
+		WindowKey::U => Some(Key::Five),
+		WindowKey::V => Some(Key::Six),
+		WindowKey::W => Some(Key::Mul),
+		WindowKey::X => Some(Key::One),
+		WindowKey::Y => Some(Key::Two),
+		WindowKey::Z => Some(Key::Three),
+		WindowKey::Space => Some(Key::Run),
+		_ => None,
+	}
+}
+
+/// Converts the simulated screen to the pixel format of the window
+fn window_pixels(screen: &VirtualDM42Screen) -> Vec<u32> {
+	let mut pixels = Vec::with_capacity((WIDTH * HEIGHT) as usize);
+	for y in 0..HEIGHT {
+		for x in 0..WIDTH {
+			let value = (SET_PIXEL - screen.intensity(x, y)) as u32;
+			pixels.push((value << 16) | (value << 8) | value);
+		}
+	}
+	pixels
+}
+
+/// Runs the calculator in a desktop window that is driven from the keyboard.
+/// The calculator runs on its own thread, and the window shows the latest frame
+/// it has drawn.
+pub fn run() {
+	let refresh = Arc::new(Mutex::new(Refresh { screen: None }));
+	let input_queue = Arc::new(Mutex::new(Vec::new()));
+	let input_event = Arc::new(Condvar::new());
+	let screen = VirtualDM42Screen::new(refresh.clone());
+	let input = Keyboard::new(VirtualKeyboard::new(
+		input_queue.clone(),
+		input_event.clone(),
+	));
+	let mut pixels = window_pixels(&screen);
+	thread::spawn(move || {
+		calc_main(screen, input);
+		std::process::exit(0);
+	});
+
+	let options = WindowOptions {
+		scale: Scale::X2,
+		..WindowOptions::default()
+	};
+	let mut window = match Window::new("rscalc", WIDTH as usize, HEIGHT as usize, options) {
+		Ok(window) => window,
+		Err(error) => {
+			eprintln!("failed to open window: {}", error);
+			std::process::exit(1);
+		}
+	};
+	window.limit_update_rate(Some(Duration::from_millis(FRAME_TIME_MS as u64)));
+
+	let mut mouse_down = false;
+	while window.is_open() {
+		// Clicks on the screen are taps, in the pixels of the calculator screen as
+		// the window is scaled
+		let down = window.get_mouse_down(MouseButton::Left);
+		if down && !mouse_down {
+			if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+				let mut queue = input_queue.lock().unwrap();
+				queue.push(KeyEvent::Touch(x as i32, y as i32));
+				input_event.notify_one();
+			}
+		}
+		mouse_down = down;
+
+		if let Some(keys) = window.get_keys_pressed(KeyRepeat::Yes) {
+			let shift = window.is_key_down(WindowKey::LeftShift)
+				|| window.is_key_down(WindowKey::RightShift);
+			let control =
+				window.is_key_down(WindowKey::LeftCtrl) || window.is_key_down(WindowKey::RightCtrl);
+			let mut queue = input_queue.lock().unwrap();
+			for key in keys {
+				let key = match key {
+					// Control with C or V copies x to or pastes a number from the clipboard
+					WindowKey::C if control => Some(Key::Copy),
+					WindowKey::V if control => Some(Key::Paste),
+					_ if control => None,
+					_ => calculator_key(key, shift),
+				};
+				if key == Some(Key::Exit) && interrupt_computation() {
+					// EXIT stops a running computation instead of being queued
+					continue;
+				}
+				if let Some(key) = key {
+					queue.push(KeyEvent::Press(key));
+					queue.push(KeyEvent::Release);
+					input_event.notify_one();
+				}
+			}
+		}
+
+		if let Some(screen) = refresh.lock().unwrap().screen.take() {
+			pixels = window_pixels(&screen);
+		}
+		if window
+			.update_with_buffer(&pixels, WIDTH as usize, HEIGHT as usize)
+			.is_err()
+		{
+			break;
+		}
+	}
+}
@@ -1,11 +1,22 @@
 use crate::dm42::calc_main;
+use crate::dm42::files::{check_file_name, open_file_store, FileStore};
 use crate::dm42::font;
-use crate::dm42::input::{InputQueue, Key, KeyEvent};
+use crate::dm42::input::{Key, KeyEvent, Keyboard, KeyboardDriver, RawKeyEvent};
+use crate::dm42::platform::{Platform, PowerStatus};
 use crate::dm42::screen::{RenderMode, Screen, ScreenLayoutRenderer};
+use crate::dm42::screenshot::encode_bmp;
 use alloc::alloc::Layout;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use core::alloc::GlobalAlloc;
 use rscalc_layout::layout::Rect;
+use rscalc_math::error::{Error, Result};
 use rscalc_math::format::Format;
+use rscalc_math::time::Clock;
 use spin::Mutex;
 
 struct Heap;
@@ -33,6 +44,9 @@ const STAT_CLK_WKUP_FLAG: u32 = 1 << 12;
 const STAT_CLK24: u32 = 1 << 14;
 const STAT_POWER_CHANGE: u32 = 1 << 15;
 
+/// Key code of EXIT in the key buffer
+const KEY_EXIT: i32 = 33;
+
 const MENU_RESET: i32 = 0;
 const MI_MSC: u8 = 196;
 const MI_SYSTEM_ENTER: u8 = 200;
@@ -272,6 +286,22 @@ fn bitblt24(x: u32, dx: u32, y: u32, val: u32, blt_op: i32, fill: i32) {
 	}
 }
 
+/// Line of the frame buffer, where cleared bits are set pixels. The panel is
+/// mounted upside down, so the first byte holds the rightmost pixels.
+fn lcd_line_addr(y: i32) -> *const u8 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 40;
+		let func: extern "C" fn(y: i32) -> *const u8 = core::mem::transmute(func_ptr);
+		func(y)
+	}
+}
+
+fn lcd_pixel(x: i32, y: i32) -> bool {
+	let x_from_right = WIDTH - 1 - x;
+	let byte = unsafe { *lcd_line_addr(y).offset((x_from_right / 8) as isize) };
+	byte & (1 << (x % 8)) == 0
+}
+
 fn lcd_clear_buf() {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 44;
@@ -296,6 +326,15 @@ fn lcd_forced_refresh() {
 	}
 }
 
+/// Sends only the given lines of the frame buffer to the panel
+fn lcd_refresh_lines(y: i32, count: i32) {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 56;
+		let func: extern "C" fn(y: i32, count: i32) = core::mem::transmute(func_ptr);
+		func(y, count);
+	}
+}
+
 fn lcd_fill_rect(x: u32, y: u32, dx: u32, dy: u32, val: i32) {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 60;
@@ -329,7 +368,7 @@ fn lcd_get_buf_cleared() -> bool {
 	}
 }
 
-pub fn rtc_updated() -> bool {
+fn rtc_updated() -> bool {
 	*CLOCK_CHANGED.lock()
 }
 
@@ -341,7 +380,7 @@ fn rtc_wakeup_delay() {
 	}
 }
 
-pub fn read_power_voltage() -> u32 {
+fn read_power_voltage() -> u32 {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 232;
 		let func: extern "C" fn() -> u32 = core::mem::transmute(func_ptr);
@@ -349,6 +388,12 @@ pub fn read_power_voltage() -> u32 {
 	}
 }
 
+/// Estimated remaining battery charge in percent, derived from the power voltage
+fn battery_percent() -> u32 {
+	let voltage = read_power_voltage() as i32;
+	((voltage - 2190) * 100 / 750).clamp(0, 100) as u32
+}
+
 pub fn set_reset_magic(magic: u32) {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 272;
@@ -357,7 +402,7 @@ pub fn set_reset_magic(magic: u32) {
 	}
 }
 
-pub fn usb_powered() -> bool {
+fn usb_powered() -> bool {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 288;
 		let func: extern "C" fn() -> i32 = core::mem::transmute(func_ptr);
@@ -403,7 +448,7 @@ fn reset_auto_off() {
 	}
 }
 
-pub fn sys_delay(ms_delay: u32) {
+fn sys_delay(ms_delay: u32) {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 516;
 		let func: extern "C" fn(u32) = core::mem::transmute(func_ptr);
@@ -419,7 +464,7 @@ fn sys_sleep() {
 	}
 }
 
-pub fn sys_free_mem() -> usize {
+fn sys_free_mem() -> usize {
 	unsafe {
 		let func_ptr: usize = LIBRARY_BASE + 540;
 		let func: extern "C" fn() -> u32 = core::mem::transmute(func_ptr);
@@ -464,7 +509,7 @@ fn clear_state(bit: u32) {
 	}
 }
 
-pub fn show_system_setup_menu() {
+fn show_system_setup_menu() {
 	unsafe {
 		set_state(STAT_CLK_WKUP_SECONDS);
 
@@ -483,11 +528,11 @@ pub fn show_system_setup_menu() {
 	}
 }
 
-pub fn time_24_hour() -> bool {
+fn time_24_hour() -> bool {
 	state(STAT_CLK24)
 }
 
-pub fn set_time_24_hour(value: bool) {
+fn set_time_24_hour(value: bool) {
 	if value {
 		set_state(STAT_CLK24);
 	} else {
@@ -495,6 +540,405 @@ pub fn set_time_24_hour(value: bool) {
 	}
 }
 
+#[repr(C)]
+struct dt_t {
+	year: u16,
+	month: u8,
+	day: u8,
+}
+
+#[repr(C)]
+struct tm_t {
+	hour: u8,
+	min: u8,
+	sec: u8,
+	csec: u8,
+	dow: u8,
+}
+
+/// Real time clock of the calculator, read through the firmware
+struct DM42Clock;
+
+impl Clock for DM42Clock {
+	fn now(&self) -> Result<NaiveDateTime> {
+		unsafe {
+			let func_ptr: usize = LIBRARY_BASE + 204;
+			let func: extern "C" fn(time: *mut tm_t, date: *mut dt_t) =
+				core::mem::transmute(func_ptr);
+			let mut date = core::mem::MaybeUninit::<dt_t>::uninit();
+			let mut time = core::mem::MaybeUninit::<tm_t>::uninit();
+			func(time.as_mut_ptr(), date.as_mut_ptr());
+			let date = date.assume_init();
+			let time = time.assume_init();
+			let date =
+				NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32)
+					.ok_or(Error::InvalidDate)?;
+			let time = NaiveTime::from_hms_milli_opt(
+				time.hour as u32,
+				time.min as u32,
+				time.sec as u32,
+				time.csec as u32 * 10,
+			)
+			.ok_or(Error::InvalidTime)?;
+			Ok(NaiveDateTime::new(date, time))
+		}
+	}
+}
+
+/// Directory of the flash disk that the files of the calculator are kept in. The
+/// disk is the FAT filesystem of the firmware, which is left alone when a program
+/// is loaded and can be reached over USB.
+const DISK_DIR: &str = "/RSCALC";
+
+const FR_OK: i32 = 0;
+const FR_NO_FILE: i32 = 4;
+const FR_NO_PATH: i32 = 5;
+const FR_EXIST: i32 = 8;
+
+const FA_READ: u8 = 0x01;
+const FA_WRITE: u8 = 0x02;
+const FA_CREATE_ALWAYS: u8 = 0x08;
+
+const AM_DIR: u8 = 0x10;
+
+/// Longest long file name that the filesystem of the firmware reads
+const MAX_LFN: usize = 255;
+
+/// File and directory objects of the filesystem, which are only used through its
+/// functions. They are kept larger than the objects of the firmware.
+#[repr(C, align(8))]
+struct FatFile([u8; 1024]);
+
+#[repr(C, align(8))]
+struct FatDir([u8; 256]);
+
+#[repr(C)]
+struct FileInfo {
+	size: u32,
+	date: u16,
+	time: u16,
+	attributes: u8,
+	short_name: [u8; 13],
+	name: [u8; MAX_LFN + 1],
+}
+
+impl FileInfo {
+	fn new() -> Self {
+		FileInfo {
+			size: 0,
+			date: 0,
+			time: 0,
+			attributes: 0,
+			short_name: [0; 13],
+			name: [0; MAX_LFN + 1],
+		}
+	}
+
+	fn name(&self) -> Option<&str> {
+		let len = self.name.iter().position(|byte| *byte == 0)?;
+		core::str::from_utf8(&self.name[..len]).ok()
+	}
+}
+
+fn sys_disk_write_enable(value: bool) {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 568;
+		let func: extern "C" fn(i32) = core::mem::transmute(func_ptr);
+		func(value as i32);
+	}
+}
+
+fn f_open(file: &mut FatFile, path: &[u8], mode: u8) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 572;
+		let func: extern "C" fn(*mut FatFile, *const u8, u8) -> i32 =
+			core::mem::transmute(func_ptr);
+		func(file, path.as_ptr(), mode)
+	}
+}
+
+fn f_close(file: &mut FatFile) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 576;
+		let func: extern "C" fn(*mut FatFile) -> i32 = core::mem::transmute(func_ptr);
+		func(file)
+	}
+}
+
+fn f_read(file: &mut FatFile, data: &mut [u8]) -> Result<usize> {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 580;
+		let func: extern "C" fn(*mut FatFile, *mut u8, u32, *mut u32) -> i32 =
+			core::mem::transmute(func_ptr);
+		let mut len = 0;
+		match func(file, data.as_mut_ptr(), data.len() as u32, &mut len) {
+			FR_OK => Ok(len as usize),
+			_ => Err(Error::CorruptData),
+		}
+	}
+}
+
+fn f_write(file: &mut FatFile, data: &[u8]) -> Result<usize> {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 584;
+		let func: extern "C" fn(*mut FatFile, *const u8, u32, *mut u32) -> i32 =
+			core::mem::transmute(func_ptr);
+		let mut len = 0;
+		match func(file, data.as_ptr(), data.len() as u32, &mut len) {
+			FR_OK => Ok(len as usize),
+			_ => Err(Error::WriteFailed),
+		}
+	}
+}
+
+fn f_opendir(dir: &mut FatDir, path: &[u8]) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 588;
+		let func: extern "C" fn(*mut FatDir, *const u8) -> i32 = core::mem::transmute(func_ptr);
+		func(dir, path.as_ptr())
+	}
+}
+
+fn f_closedir(dir: &mut FatDir) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 592;
+		let func: extern "C" fn(*mut FatDir) -> i32 = core::mem::transmute(func_ptr);
+		func(dir)
+	}
+}
+
+fn f_readdir(dir: &mut FatDir, info: &mut FileInfo) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 596;
+		let func: extern "C" fn(*mut FatDir, *mut FileInfo) -> i32 =
+			core::mem::transmute(func_ptr);
+		func(dir, info)
+	}
+}
+
+fn f_stat(path: &[u8], info: &mut FileInfo) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 600;
+		let func: extern "C" fn(*const u8, *mut FileInfo) -> i32 = core::mem::transmute(func_ptr);
+		func(path.as_ptr(), info)
+	}
+}
+
+fn f_unlink(path: &[u8]) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 604;
+		let func: extern "C" fn(*const u8) -> i32 = core::mem::transmute(func_ptr);
+		func(path.as_ptr())
+	}
+}
+
+fn f_mkdir(path: &[u8]) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 608;
+		let func: extern "C" fn(*const u8) -> i32 = core::mem::transmute(func_ptr);
+		func(path.as_ptr())
+	}
+}
+
+fn f_rename(from: &[u8], to: &[u8]) -> i32 {
+	unsafe {
+		let func_ptr: usize = LIBRARY_BASE + 612;
+		let func: extern "C" fn(*const u8, *const u8) -> i32 = core::mem::transmute(func_ptr);
+		func(from.as_ptr(), to.as_ptr())
+	}
+}
+
+/// Null terminated path of a file in the directory of the calculator
+fn disk_path(name: &str) -> Result<Vec<u8>> {
+	check_file_name(name)?;
+	let mut path = Vec::with_capacity(DISK_DIR.len() + name.len() + 2);
+	path.extend_from_slice(DISK_DIR.as_bytes());
+	path.push(b'/');
+	path.extend_from_slice(name.as_bytes());
+	path.push(0);
+	Ok(path)
+}
+
+fn disk_dir_path() -> Vec<u8> {
+	let mut path = Vec::with_capacity(DISK_DIR.len() + 1);
+	path.extend_from_slice(DISK_DIR.as_bytes());
+	path.push(0);
+	path
+}
+
+/// Files kept on the flash disk of the firmware. Writes to the disk have to be
+/// enabled while a file is being changed, and are disabled again afterwards so
+/// that the disk can be used over USB.
+struct DiskFileStore;
+
+impl DiskFileStore {
+	fn exists(&self, path: &[u8]) -> bool {
+		f_stat(path, &mut FileInfo::new()) == FR_OK
+	}
+
+	fn with_writes_enabled<T>(&mut self, func: impl FnOnce() -> Result<T>) -> Result<T> {
+		sys_disk_write_enable(true);
+		let result = func();
+		sys_disk_write_enable(false);
+		result
+	}
+}
+
+impl FileStore for DiskFileStore {
+	fn list(&self) -> Result<Vec<String>> {
+		let mut dir = Box::new(FatDir([0; 256]));
+		match f_opendir(&mut dir, &disk_dir_path()) {
+			FR_OK => (),
+			// The directory is created when the first file is written
+			FR_NO_FILE | FR_NO_PATH => return Ok(Vec::new()),
+			_ => return Err(Error::CorruptData),
+		}
+		let mut names = Vec::new();
+		let mut info = Box::new(FileInfo::new());
+		while f_readdir(&mut dir, &mut info) == FR_OK && info.name[0] != 0 {
+			if info.attributes & AM_DIR != 0 {
+				continue;
+			}
+			if let Some(name) = info.name() {
+				if check_file_name(name).is_ok() {
+					names.push(name.to_string());
+				}
+			}
+		}
+		f_closedir(&mut dir);
+		names.sort();
+		Ok(names)
+	}
+
+	fn read(&self, name: &str) -> Result<Vec<u8>> {
+		let path = disk_path(name)?;
+		let mut info = Box::new(FileInfo::new());
+		if f_stat(&path, &mut info) != FR_OK {
+			return Err(Error::FileNotFound);
+		}
+		let mut file = Box::new(FatFile([0; 1024]));
+		if f_open(&mut file, &path, FA_READ) != FR_OK {
+			return Err(Error::FileNotFound);
+		}
+		let mut data = vec![0; info.size as usize];
+		let result = f_read(&mut file, &mut data);
+		f_close(&mut file);
+		if result? != data.len() {
+			return Err(Error::CorruptData);
+		}
+		Ok(data)
+	}
+
+	fn write(&mut self, name: &str, data: &[u8]) -> Result<()> {
+		let path = disk_path(name)?;
+		self.with_writes_enabled(|| {
+			match f_mkdir(&disk_dir_path()) {
+				FR_OK | FR_EXIST => (),
+				_ => return Err(Error::WriteFailed),
+			}
+			let mut file = Box::new(FatFile([0; 1024]));
+			if f_open(&mut file, &path, FA_WRITE | FA_CREATE_ALWAYS) != FR_OK {
+				return Err(Error::WriteFailed);
+			}
+			let result = f_write(&mut file, data);
+			// Closing the file writes out the rest of its data
+			let closed = f_close(&mut file);
+			if result? != data.len() || closed != FR_OK {
+				return Err(Error::WriteFailed);
+			}
+			Ok(())
+		})
+	}
+
+	fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+		let from = disk_path(from)?;
+		let to = disk_path(to)?;
+		if !self.exists(&from) {
+			return Err(Error::FileNotFound);
+		}
+		if self.exists(&to) {
+			return Err(Error::InvalidEntry);
+		}
+		self.with_writes_enabled(|| match f_rename(&from, &to) {
+			FR_OK => Ok(()),
+			_ => Err(Error::WriteFailed),
+		})
+	}
+
+	fn delete(&mut self, name: &str) -> Result<()> {
+		let path = disk_path(name)?;
+		self.with_writes_enabled(|| match f_unlink(&path) {
+			FR_OK => Ok(()),
+			FR_NO_FILE | FR_NO_PATH => Err(Error::FileNotFound),
+			_ => Err(Error::WriteFailed),
+		})
+	}
+}
+
+/// Minutes without a key press before the calculator turns itself off. The
+/// firmware also has a timer, which each key press resets, but it turns off
+/// without giving the calculator a chance to save its state.
+const AUTO_OFF_MINUTES: i64 = 5;
+
+pub struct DM42Platform;
+
+impl Platform for DM42Platform {
+	fn delay(&self, ms: u32) {
+		sys_delay(ms);
+	}
+
+	fn clock_minute_updated(&self) -> bool {
+		rtc_updated()
+	}
+
+	fn power_status(&self) -> Option<PowerStatus> {
+		Some(PowerStatus {
+			voltage: read_power_voltage(),
+			percent: battery_percent(),
+			usb: usb_powered(),
+		})
+	}
+
+	fn free_system_memory(&self) -> Option<usize> {
+		Some(sys_free_mem())
+	}
+
+	fn computation_yield(&self) -> bool {
+		// The key buffer can't be looked at without taking keys from it, so keys
+		// pressed during a computation are dropped, except for EXIT which stops it
+		while !key_empty() {
+			if key_pop() == KEY_EXIT {
+				return true;
+			}
+		}
+		false
+	}
+
+	fn file_store(&self) -> Box<dyn FileStore> {
+		Box::new(DiskFileStore)
+	}
+
+	fn auto_off_minutes(&self) -> Option<i64> {
+		Some(AUTO_OFF_MINUTES)
+	}
+
+	fn has_system_menu(&self) -> bool {
+		true
+	}
+
+	fn show_system_menu(&self) {
+		show_system_setup_menu();
+	}
+
+	fn time_24_hour(&self) -> Option<bool> {
+		Some(time_24_hour())
+	}
+
+	fn set_time_24_hour(&self, value: bool) {
+		set_time_24_hour(value);
+	}
+}
+
 pub struct DM42Screen;
 
 impl Screen for DM42Screen {
@@ -514,6 +958,14 @@ impl Screen for DM42Screen {
 		lcd_refresh_dma();
 	}
 
+	fn refresh_rows(&mut self, top: i32, bottom: i32) {
+		if top == 0 && bottom == HEIGHT {
+			lcd_refresh_dma();
+		} else {
+			lcd_refresh_lines(top, bottom - top);
+		}
+	}
+
 	fn fill(&mut self, rect: &Rect, color: bool) {
 		let rect = rect.clipped_to(&Rect {
 			x: 0,
@@ -549,16 +1001,34 @@ impl Screen for DM42Screen {
 	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer {
 		ScreenLayoutRenderer::new(self, render_mode)
 	}
+
+	/// Writes the frame buffer to a BMP file in the file store, named after the
+	/// current time
+	fn capture(&self) -> Result<()> {
+		let now = DM42Clock.now()?;
+		let name = format!(
+			"rscalc-{:04}{:02}{:02}-{:02}{:02}{:02}.bmp",
+			now.year(),
+			now.month(),
+			now.day(),
+			now.hour(),
+			now.minute(),
+			now.second()
+		);
+		let image = encode_bmp(self, &lcd_pixel);
+		open_file_store().write(&name, &image)
+	}
 }
 
-pub struct DM42InputQueue;
+/// Keys from the key buffer of the firmware, which debounces them
+pub struct DM42Keyboard;
 
-impl InputQueue for DM42InputQueue {
-	fn has_input(&self) -> bool {
+impl KeyboardDriver for DM42Keyboard {
+	fn has_event(&self) -> bool {
 		!key_empty()
 	}
 
-	fn pop_raw(&mut self) -> Option<KeyEvent> {
+	fn poll_event(&mut self) -> Option<RawKeyEvent> {
 		if key_empty() {
 			None
 		} else {
@@ -597,7 +1067,7 @@ impl InputQueue for DM42InputQueue {
 					30 => Key::Two,
 					31 => Key::Three,
 					32 => Key::Sub,
-					33 => Key::Exit,
+					KEY_EXIT => Key::Exit,
 					34 => Key::Zero,
 					35 => Key::Dot,
 					36 => Key::Run,
@@ -614,17 +1084,17 @@ impl InputQueue for DM42InputQueue {
 					99 => Key::DoubleRelease,
 					_ => return None,
 				};
-				Some(KeyEvent::Press(key))
+				Some(RawKeyEvent::from_key_event(KeyEvent::Press(key)))
 			} else if key == 0 {
-				Some(KeyEvent::Release)
+				Some(RawKeyEvent::from_key_event(KeyEvent::Release))
 			} else {
 				None
 			}
 		}
 	}
 
-	fn wait_raw(&mut self) -> Option<KeyEvent> {
-		if let Some(key) = self.pop_raw() {
+	fn wait_event(&mut self) -> Option<RawKeyEvent> {
+		if let Some(key) = self.poll_event() {
 			reset_auto_off();
 			return Some(key);
 		}
@@ -680,7 +1150,7 @@ impl InputQueue for DM42InputQueue {
 				changes = true;
 			}
 
-			if let Some(key) = self.pop_raw() {
+			if let Some(key) = self.poll_event() {
 				reset_auto_off();
 				return Some(key);
 			} else if changes {
@@ -713,8 +1183,9 @@ extern "C" fn program_entry() -> ! {
 }
 
 pub fn program_main() {
+	rscalc_math::time::set_clock(&DM42Clock);
 	let screen = DM42Screen;
-	let input_queue = DM42InputQueue;
+	let input_queue = Keyboard::new(DM42Keyboard);
 	calc_main(screen, input_queue);
 }
 
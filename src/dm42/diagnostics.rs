@@ -0,0 +1,269 @@
+use crate::dm42::functions::Function;
+use crate::dm42::graph::ProgramStep;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::platform::platform;
+use crate::dm42::state::{State, PROGRAM_SLOTS};
+use chrono::NaiveDateTime;
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, TokenType};
+use rscalc_math::number::ToNumber;
+use rscalc_math::storage::{
+	free_bytes, largest_free_block, peak_used_bytes, reclaimable_bytes, serialize_portable,
+	used_bytes,
+};
+use rscalc_math::time::Now;
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Minimum time a session must run before a battery drain estimate is shown
+const MIN_BATTERY_ESTIMATE_MINUTES: i64 = 10;
+
+/// Usage statistics for the current session, shown on the diagnostics screen
+pub struct SessionStats {
+	start: Option<NaiveDateTime>,
+	operations: u64,
+	/// Battery charge at the start of the session, if the platform has a battery
+	start_battery: Option<u32>,
+}
+
+impl SessionStats {
+	pub fn new() -> Self {
+		SessionStats {
+			start: NaiveDateTime::now().ok(),
+			operations: 0,
+			start_battery: platform().power_status().map(|power| power.percent),
+		}
+	}
+
+	/// Restarts the session timer and clears the counters
+	pub fn reset(&mut self) {
+		*self = SessionStats::new();
+	}
+
+	pub fn record_operation(&mut self) {
+		self.operations += 1;
+	}
+
+	pub fn operations(&self) -> u64 {
+		self.operations
+	}
+
+	pub fn uptime_minutes(&self) -> Option<i64> {
+		let start = self.start?;
+		let now = NaiveDateTime::now().ok()?;
+		Some((now - start).num_minutes())
+	}
+
+	pub fn uptime_string(&self) -> String {
+		match self.uptime_minutes() {
+			Some(minutes) => {
+				(minutes / 60).to_number().to_string()
+					+ "h " + &(minutes % 60).to_number().to_string()
+					+ "m"
+			}
+			None => "--".to_string(),
+		}
+	}
+
+	/// Estimated battery usage in percent per hour over this session. Not available
+	/// while on USB power or until the session has run long enough to measure.
+	pub fn battery_drain_per_hour(&self) -> Option<i64> {
+		let minutes = self.uptime_minutes()?;
+		let power = platform().power_status()?;
+		if power.usb || minutes < MIN_BATTERY_ESTIMATE_MINUTES {
+			return None;
+		}
+		let used = self.start_battery? as i64 - power.percent as i64;
+		Some(core::cmp::max(0, used) * 60 / minutes)
+	}
+}
+
+pub fn diagnostics_menu() -> Menu {
+	let mut items = Vec::new();
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Reset Counters")),
+		function: MenuItemFunction::InMenuAction(Function::ResetSessionStats),
+		help: None,
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Self Test")),
+		function: MenuItemFunction::InMenuAction(Function::SelfTest),
+		help: Some("Checks the clock, storage and math library"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Benchmark")),
+		function: MenuItemFunction::InMenuAction(Function::Benchmark),
+		help: Some("Times math and drawing, to compare versions and hardware"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Display Test")),
+		function: MenuItemFunction::InMenuAction(Function::DisplayTest),
+		help: Some("Shows test patterns, advancing with each key"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Keyboard Test")),
+		function: MenuItemFunction::InMenuAction(Function::KeyboardTest),
+		help: Some("Shows each key as it is pressed"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Memory Status >")),
+		function: MenuItemFunction::InMenuAction(Function::MemoryStatus),
+		help: Some("Shows how the memory is used"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Screenshot")),
+		function: MenuItemFunction::InMenuAction(Function::Screenshot),
+		help: None,
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Save State")),
+		function: MenuItemFunction::InMenuAction(Function::SaveState),
+		help: None,
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Restore State")),
+		function: MenuItemFunction::Action(Function::RestoreState),
+		help: None,
+	});
+	#[cfg(not(feature = "embedded"))]
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Export over Serial")),
+		function: MenuItemFunction::InMenuAction(Function::ExportState),
+		help: None,
+	});
+	#[cfg(not(feature = "embedded"))]
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Import over Serial")),
+		function: MenuItemFunction::Action(Function::ImportState),
+		help: None,
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("View Tape")),
+		function: MenuItemFunction::Action(Function::ShowTape),
+		help: Some("Shows the operations of this session"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Export Tape")),
+		function: MenuItemFunction::InMenuAction(Function::ExportTape),
+		help: Some("Saves the operations of this session to tape.txt"),
+	});
+
+	Menu::new_with_bottom(
+		"Diagnostics",
+		items,
+		Box::new(|state, _screen| {
+			let stats = state.session_stats();
+			let mut bottom_items = Vec::new();
+			bottom_items.push(stat_layout("Uptime: ".to_string() + &stats.uptime_string()));
+			bottom_items.push(stat_layout(
+				"Operations: ".to_string() + &stats.operations().to_number().to_string(),
+			));
+
+			if let Some(power) = platform().power_status() {
+				let drain = match stats.battery_drain_per_hour() {
+					Some(drain) => drain.to_number().to_string() + "% per hour",
+					None => "-- per hour".to_string(),
+				};
+				bottom_items.push(stat_layout(
+					"Battery: ".to_string()
+						+ &power.percent.to_number().to_string()
+						+ "%, " + &drain,
+				));
+			}
+
+			Layout::Vertical(bottom_items)
+		}),
+	)
+}
+
+/// Use of the memory that values are stored in. The sizes of the parts of the
+/// calculator are estimated from the size of their saved form, as values may
+/// share storage.
+pub fn memory_status_menu() -> Menu {
+	let mut items = Vec::new();
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Reset Peak")),
+		function: MenuItemFunction::InMenuAction(Function::ResetPeakMemory),
+		help: Some("Starts measuring the peak from the memory used now"),
+	});
+
+	Menu::new_with_bottom(
+		"Memory Status",
+		items,
+		Box::new(|state, _screen| {
+			let mut bottom_items = vec![
+				stat_layout(bytes_text("Used: ", used_bytes())),
+				stat_layout(bytes_text("Peak: ", peak_used_bytes())),
+				stat_layout(
+					bytes_text("Free: ", free_bytes())
+						+ &bytes_text(", largest block ", largest_free_block()),
+				),
+				stat_layout(bytes_text("Stack: ~", stack_bytes(state))),
+				stat_layout(bytes_text("Programs: ~", program_bytes(state))),
+				stat_layout(
+					bytes_text("History: ~", values_bytes(state.answers().answers()))
+						+ &bytes_text(", undo ", reclaimable_bytes()),
+				),
+			];
+			if let Some(free) = platform().free_system_memory() {
+				bottom_items.push(stat_layout(bytes_text("System free: ", free)));
+			}
+			Layout::Vertical(bottom_items)
+		}),
+	)
+}
+
+fn bytes_text(label: &str, bytes: usize) -> String {
+	label.to_string() + &bytes.to_number().to_string() + " bytes"
+}
+
+fn values_bytes<'a>(values: impl IntoIterator<Item = &'a Value>) -> usize {
+	let mut total = 0;
+	for value in values {
+		let mut data = Vec::new();
+		if serialize_portable(value, &mut data).is_ok() {
+			total += data.len();
+		}
+	}
+	total
+}
+
+fn stack_bytes(state: &State) -> usize {
+	let context = state.context();
+	let entries: Vec<Value> = (0..context.stack_len())
+		.filter_map(|idx| context.entry(idx).ok())
+		.collect();
+	values_bytes(&entries)
+}
+
+fn program_bytes(state: &State) -> usize {
+	let mut total = 0;
+	let programs = (0..PROGRAM_SLOTS)
+		.filter_map(|slot| state.program_slot_steps(slot))
+		.chain(
+			state
+				.function_keys()
+				.macros()
+				.iter()
+				.map(|steps| steps.as_slice()),
+		);
+	for steps in programs {
+		let mut data = Vec::new();
+		if ProgramStep::save_steps(steps, &mut data).is_ok() {
+			total += data.len();
+		}
+	}
+	total
+}
+
+fn stat_layout(text: String) -> Layout {
+	Layout::LeftAlign(Box::new(Layout::Text(text, Font::Small, TokenType::Text)))
+}
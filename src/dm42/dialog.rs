@@ -0,0 +1,62 @@
+use crate::dm42::functions::Function;
+use crate::dm42::input::InputEvent;
+
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DialogAnswer {
+	Yes,
+	No,
+}
+
+/// Yes or no question asked before an action that destroys data. While it is
+/// shown it takes all of the input, and only the soft key under Yes carries out
+/// the action, so that a single stray key press can't do it by accident.
+pub struct ConfirmDialog {
+	title: &'static str,
+	message: &'static str,
+	action: Function,
+}
+
+impl ConfirmDialog {
+	pub fn new(title: &'static str, message: &'static str, action: Function) -> Self {
+		ConfirmDialog {
+			title,
+			message,
+			action,
+		}
+	}
+
+	pub fn title(&self) -> &'static str {
+		self.title
+	}
+
+	pub fn message(&self) -> &'static str {
+		self.message
+	}
+
+	pub fn into_action(self) -> Function {
+		self.action
+	}
+
+	/// Answer given by an input, or `None` for inputs that don't answer the
+	/// question and are ignored
+	pub fn answer(input: InputEvent) -> Option<DialogAnswer> {
+		match input {
+			InputEvent::FunctionKey(1, _) => Some(DialogAnswer::Yes),
+			InputEvent::FunctionKey(6, _) | InputEvent::Exit => Some(DialogAnswer::No),
+			_ => None,
+		}
+	}
+
+	/// Labels of the soft keys while the dialog is shown
+	pub fn key_labels() -> Vec<String> {
+		["Yes", "", "", "", "", "No"]
+			.iter()
+			.map(|label| label.to_string())
+			.collect()
+	}
+}
@@ -0,0 +1,97 @@
+use crate::dm42::functions::Function;
+use crate::dm42::locale::tr;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::state::State;
+use rscalc_math::number::ToNumber;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Brightness and contrast go from 0 up to this level
+pub const DISPLAY_LEVEL_MAX: u8 = 10;
+
+/// Choices for the seconds without a key press after which the display is
+/// dimmed, where zero never dims it
+pub const AUTO_DIM_SECONDS: [u16; 5] = [0, 15, 30, 60, 120];
+
+/// Setting of the display that is changed with a slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayControl {
+	Brightness,
+	Contrast,
+}
+
+impl DisplayControl {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			DisplayControl::Brightness => "Brightness",
+			DisplayControl::Contrast => "Contrast",
+		}
+	}
+
+	pub fn default_level(&self) -> u8 {
+		match self {
+			DisplayControl::Brightness => DISPLAY_LEVEL_MAX,
+			DisplayControl::Contrast => DISPLAY_LEVEL_MAX / 2,
+		}
+	}
+}
+
+/// Brightness used while the display is dimmed. It stays bright enough to see
+/// that the calculator is still on.
+pub fn dimmed_brightness(brightness: u8) -> u8 {
+	core::cmp::min(brightness, 1)
+}
+
+/// Text of a slider showing a level out of `DISPLAY_LEVEL_MAX`
+fn slider_string(level: u8) -> String {
+	let mut result = "[".to_string();
+	for step in 0..DISPLAY_LEVEL_MAX {
+		result.push(if step < level { '▪' } else { '∙' });
+	}
+	result + "]"
+}
+
+fn auto_dim_string(seconds: u16) -> String {
+	if seconds == 0 {
+		"Off".to_string()
+	} else {
+		seconds.to_number().to_string() + " s"
+	}
+}
+
+fn slider_item(control: DisplayControl) -> MenuItem {
+	MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(move |state: &State, _screen| {
+			MenuItem::string_layout(
+				tr(control.to_str()).to_string()
+					+ "   " + &slider_string(state.display_level(control)),
+			)
+		})),
+		function: MenuItemFunction::ConversionAction(
+			Function::DisplayLevelUp(control),
+			Function::DisplayLevelDown(control),
+			Function::DisplayLevelReset(control),
+		),
+		help: Some("+ and - move the slider, and x≷y puts back the default level"),
+	}
+}
+
+/// Menu with the sliders for the display and the time before it dims
+pub fn brightness_menu() -> Menu {
+	let mut items = Vec::new();
+	items.push(slider_item(DisplayControl::Brightness));
+	items.push(slider_item(DisplayControl::Contrast));
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Auto Dim", &auto_dim_string(state.auto_dim_seconds()))
+		})),
+		function: MenuItemFunction::InMenuAction(Function::AutoDimCycle),
+		help: Some("Dims the display when no key has been pressed for this long"),
+	});
+	Menu::new("Brightness", items)
+}
@@ -1,3 +1,4 @@
+use crate::dm42::input::InputEvent;
 use intel_dfp::Decimal;
 use num_bigint::{BigInt, ToBigInt};
 use rscalc_layout::layout::TokenType;
@@ -5,14 +6,28 @@ use rscalc_math::error::{Error, Result};
 use rscalc_math::format::{DecimalPointMode, Format};
 use rscalc_math::number::Number;
 
-#[cfg(feature = "dm42")]
-use alloc::string::String;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
 
 const MAX_FRACTION_DIGITS: usize = 34;
 const MAX_EXPONENT: i32 = 9999;
 
+/// Power of ten of an SI prefix typed after a number. Only milli and mega need
+/// the case of the letter, so the others are accepted in either case.
+fn si_prefix_power(ch: char) -> Option<i32> {
+	match ch {
+		'p' | 'P' => Some(-12),
+		'n' | 'N' => Some(-9),
+		'u' | 'U' | 'µ' => Some(-6),
+		'm' => Some(-3),
+		'k' | 'K' => Some(3),
+		'M' => Some(6),
+		_ => None,
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum NumberEditorState {
 	Integer,
@@ -27,6 +42,7 @@ pub struct NumberEditor {
 	exponent_sign: bool,
 	exponent: Option<i32>,
 	radix: u8,
+	decimal_point: DecimalPointMode,
 	state: NumberEditorState,
 }
 
@@ -39,10 +55,14 @@ impl NumberEditor {
 			exponent_sign: false,
 			exponent: None,
 			radix: format.integer_radix,
+			decimal_point: format.decimal_point,
 			state: NumberEditorState::Integer,
 		}
 	}
 
+	/// Adds a digit to the part being entered. Digits that aren't valid in the
+	/// radix and digits past the precision or the largest exponent are refused,
+	/// leaving the number as it was.
 	fn push_digit(&mut self, digit: u8) -> Result<()> {
 		if digit >= self.radix {
 			return Err(Error::InvalidEntry);
@@ -53,18 +73,20 @@ impl NumberEditor {
 				self.integer += digit;
 			}
 			NumberEditorState::Fraction => {
-				if self.fraction_digits.len() < MAX_FRACTION_DIGITS {
-					self.fraction_digits.push(digit);
+				if self.fraction_digits.len() >= MAX_FRACTION_DIGITS {
+					return Err(Error::ValueOutOfRange);
 				}
+				self.fraction_digits.push(digit);
 			}
 			NumberEditorState::Exponent => {
 				let new_exponent = match self.exponent {
 					Some(exponent) => (exponent * 10) + digit as i32,
 					None => digit as i32,
 				};
-				if new_exponent <= MAX_EXPONENT {
-					self.exponent = Some(new_exponent);
+				if new_exponent > MAX_EXPONENT {
+					return Err(Error::ValueOutOfRange);
 				}
+				self.exponent = Some(new_exponent);
 			}
 		}
 		Ok(())
@@ -75,7 +97,9 @@ impl NumberEditor {
 			'0'..='9' => self.push_digit(ch as u32 as u8 - '0' as u32 as u8),
 			'A'..='Z' => self.push_digit(ch as u32 as u8 - 'A' as u32 as u8 + 10),
 			'a'..='z' => self.push_digit(ch as u32 as u8 - 'a' as u32 as u8 + 10),
-			'.' => {
+			// The decimal point key always enters a decimal point, and a comma is
+			// also accepted when it is the decimal point
+			'.' | ',' if ch == '.' || self.decimal_point == DecimalPointMode::Comma => {
 				if self.state == NumberEditorState::Integer && self.radix == 10 {
 					self.state = NumberEditorState::Fraction;
 					Ok(())
@@ -87,10 +111,33 @@ impl NumberEditor {
 		}
 	}
 
-	pub fn exponent(&mut self) {
-		if self.state != NumberEditorState::Exponent && self.radix == 10 {
-			self.state = NumberEditorState::Exponent;
+	/// Multiplies the decimal number being entered by the power of ten of an SI
+	/// prefix, such as the k of 4.7k, by entering the power as the exponent. Fails
+	/// for characters that are not a prefix and once an exponent has been started.
+	pub fn push_si_prefix(&mut self, ch: char) -> Result<()> {
+		let power = si_prefix_power(ch).ok_or(Error::InvalidEntry)?;
+		if self.radix != 10 || self.state == NumberEditorState::Exponent {
+			return Err(Error::InvalidEntry);
+		}
+		self.exponent()?;
+		self.exponent_sign = power < 0;
+		self.exponent = Some(power.abs());
+		Ok(())
+	}
+
+	/// Starts entering the exponent. If no mantissa has been typed, the mantissa
+	/// is one, so that a power of ten can be entered with the exponent alone.
+	/// Fails if the exponent has already been started or the radix isn't
+	/// decimal.
+	pub fn exponent(&mut self) -> Result<()> {
+		if self.state == NumberEditorState::Exponent || self.radix != 10 {
+			return Err(Error::InvalidEntry);
 		}
+		if self.state == NumberEditorState::Integer && self.integer == 0.to_bigint().unwrap() {
+			self.integer = 1.into();
+		}
+		self.state = NumberEditorState::Exponent;
+		Ok(())
 	}
 
 	pub fn neg(&mut self) {
@@ -127,6 +174,9 @@ impl NumberEditor {
 					} else {
 						self.exponent = Some(new_exponent);
 					}
+				} else if self.exponent_sign {
+					// The sign of the exponent goes before leaving the exponent
+					self.exponent_sign = false;
 				} else if self.fraction_digits.len() == 0 {
 					self.state = NumberEditorState::Integer;
 				} else {
@@ -173,41 +223,34 @@ impl NumberEditor {
 	pub fn number(&self) -> Number {
 		if self.state == NumberEditorState::Integer {
 			if self.sign {
-				return Number::check_int_bounds(Number::Integer(-self.integer.clone()));
+				return Number::check_int_bounds(Number::Integer((-self.integer.clone()).into()));
 			} else {
-				return Number::check_int_bounds(Number::Integer(self.integer.clone()));
+				return Number::check_int_bounds(Number::Integer(self.integer.clone().into()));
 			}
 		}
 
-		let mut result = Number::bigint_to_decimal(&self.integer);
-
-		let one: Decimal = 1.into();
-		let ten: Decimal = 10.into();
-		let mut factor = &one / &ten;
+		// Convert the digits as typed in a single step, so that the result is the
+		// nearest decimal to the number entered even when the mantissa has more
+		// digits than a decimal holds or the exponent is near the ends of the range
+		let mut text = String::new();
+		if self.sign {
+			text.push('-');
+		}
+		text += self.integer.to_string().as_str();
+		if !self.fraction_digits.is_empty() {
+			text.push('.');
+		}
 		for digit in &self.fraction_digits {
-			let digit: Decimal = (*digit as u32).into();
-			result += &digit * &factor;
-			factor = &factor / &ten;
+			text.push((b'0' + digit) as char);
 		}
-
-		let exponent: Decimal = match self.exponent {
-			Some(exponent) => {
-				if self.exponent_sign {
-					-exponent
-				} else {
-					exponent
-				}
+		if let Some(exponent) = self.exponent {
+			text.push('E');
+			if self.exponent_sign {
+				text.push('-');
 			}
-			None => 0,
-		}
-		.into();
-
-		result *= exponent.exp10();
-		if self.sign {
-			Number::Decimal(-result)
-		} else {
-			Number::Decimal(result)
+			text += exponent.to_string().as_str();
 		}
+		Number::Decimal(Decimal::from_str(&text))
 	}
 
 	pub fn token_type(&self) -> TokenType {
@@ -217,3 +260,54 @@ impl NumberEditor {
 		}
 	}
 }
+
+/// Converts a number written as text into the inputs that type it, so that text
+/// pasted from other programs is entered the same way as a number typed on the
+/// keyboard. Spaces, underscores and the digit separator that is not the
+/// decimal point are skipped. Returns an error if the text is not a number.
+pub fn number_inputs(text: &str, format: &Format) -> Result<Vec<InputEvent>> {
+	let radix = format.integer_radix as u32;
+	let (point, separator) = match format.decimal_point {
+		DecimalPointMode::Comma => (',', '.'),
+		_ => ('.', ','),
+	};
+
+	let mut inputs = Vec::new();
+	let mut negative = false;
+	let mut exponent = false;
+	let mut digits = false;
+	for ch in text.trim().chars() {
+		match ch {
+			'-' | '−' | '+' if !digits => {
+				if ch != '+' {
+					negative = !negative;
+				}
+			}
+			'e' | 'E' | 'ᴇ' if radix == 10 && digits && !exponent => {
+				// The sign of the mantissa is entered before moving to the exponent
+				if negative {
+					inputs.push(InputEvent::Neg);
+				}
+				inputs.push(InputEvent::E);
+				negative = false;
+				exponent = true;
+				digits = false;
+			}
+			_ if ch == point && !exponent => inputs.push(InputEvent::Character('.')),
+			' ' | '_' | '\'' | '\u{2009}' | '\u{202f}' => (),
+			_ if ch == separator && !exponent => (),
+			_ if ch.is_digit(radix) => {
+				inputs.push(InputEvent::Character(ch));
+				digits = true;
+			}
+			_ => return Err(Error::InvalidEntry),
+		}
+	}
+	if !digits {
+		return Err(Error::InvalidEntry);
+	}
+	if negative {
+		inputs.push(InputEvent::Neg);
+	}
+	Ok(inputs)
+}
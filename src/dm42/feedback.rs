@@ -0,0 +1,122 @@
+use crate::dm42::functions::Function;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::platform::platform;
+use rscalc_math::error::{Error, Result};
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Something that the calculator can give feedback for, with a sound or with
+/// vibration on platforms that have it. Each kind can be turned off on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackEvent {
+	KeyClick,
+	Error,
+	Alarm,
+}
+
+pub const FEEDBACK_EVENTS: [FeedbackEvent; 3] = [
+	FeedbackEvent::KeyClick,
+	FeedbackEvent::Error,
+	FeedbackEvent::Alarm,
+];
+
+impl FeedbackEvent {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			FeedbackEvent::KeyClick => "Key Click",
+			FeedbackEvent::Error => "Error Buzz",
+			FeedbackEvent::Alarm => "Alarm Tone",
+		}
+	}
+
+	/// Tone played for the event on platforms that give feedback with sound
+	pub fn tone(&self) -> Tone {
+		match self {
+			FeedbackEvent::KeyClick => Tone {
+				frequency_hz: 4000,
+				duration_ms: 3,
+			},
+			FeedbackEvent::Error => Tone {
+				frequency_hz: 180,
+				duration_ms: 150,
+			},
+			FeedbackEvent::Alarm => Tone {
+				frequency_hz: 1000,
+				duration_ms: 250,
+			},
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tone {
+	pub frequency_hz: u32,
+	pub duration_ms: u32,
+}
+
+impl Tone {
+	pub fn play(&self) {
+		platform().tone(self.frequency_hz, self.duration_ms);
+	}
+}
+
+/// Frequencies of the tones that programs play with TONE, rising from tone 0
+/// to tone 9
+const PROGRAM_TONE_FREQUENCIES: [u32; 10] = [220, 247, 262, 294, 330, 349, 392, 440, 494, 523];
+
+const PROGRAM_TONE_MS: u32 = 250;
+
+/// Tones played in turn by BEEP
+const BEEP_TONES: [usize; 4] = [5, 7, 9, 7];
+
+const BEEP_TONE_MS: u32 = 125;
+
+/// Plays one of the tones numbered from 0 to 9 that programs can use
+pub fn play_program_tone(tone: usize) -> Result<()> {
+	let frequency_hz = *PROGRAM_TONE_FREQUENCIES
+		.get(tone)
+		.ok_or(Error::ValueOutOfRange)?;
+	Tone {
+		frequency_hz,
+		duration_ms: PROGRAM_TONE_MS,
+	}
+	.play();
+	Ok(())
+}
+
+/// Plays the short run of tones that programs use to get attention
+pub fn play_beep() {
+	for tone in &BEEP_TONES {
+		Tone {
+			frequency_hz: PROGRAM_TONE_FREQUENCIES[*tone],
+			duration_ms: BEEP_TONE_MS,
+		}
+		.play();
+	}
+}
+
+/// Menu for turning each kind of feedback on and off
+pub fn sound_settings_menu() -> Menu {
+	let mut items = Vec::new();
+	for event in &FEEDBACK_EVENTS {
+		let event = *event;
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				MenuItem::setting_layout(
+					event.to_str(),
+					if state.feedback_enabled(event) {
+						"On"
+					} else {
+						"Off"
+					},
+				)
+			})),
+			function: MenuItemFunction::InMenuAction(Function::FeedbackToggle(event)),
+			help: None,
+		});
+	}
+	Menu::new("Sounds", items)
+}
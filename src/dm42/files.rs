@@ -0,0 +1,205 @@
+use crate::dm42::functions::Function;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::platform::platform;
+use rscalc_math::error::{Error, Result};
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Longest file name that can be stored, in bytes
+pub const MAX_FILE_NAME_LEN: usize = 32;
+
+/// File that the calculator state is saved to from the file browser
+pub const STATE_FILE_NAME: &str = "calculator.state";
+
+/// Kind of data held in a file, given by the extension of its name
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+	State,
+	Program,
+	Constants,
+	Keymap,
+	/// Keystroke program written as a text listing
+	Listing,
+	Data,
+}
+
+impl FileKind {
+	pub fn of(name: &str) -> FileKind {
+		match name.rfind('.').map(|idx| &name[idx + 1..]) {
+			Some("state") => FileKind::State,
+			Some("rpl") => FileKind::Program,
+			Some("const") => FileKind::Constants,
+			Some("keys") => FileKind::Keymap,
+			Some("prg") => FileKind::Listing,
+			_ => FileKind::Data,
+		}
+	}
+
+	pub fn to_str(self) -> &'static str {
+		match self {
+			FileKind::State => "State",
+			FileKind::Program => "Program",
+			FileKind::Constants => "Constants",
+			FileKind::Keymap => "Keymap",
+			FileKind::Listing => "Listing",
+			FileKind::Data => "Data",
+		}
+	}
+}
+
+/// Storage of named files. The simulator keeps files in a directory on the host,
+/// and the device keeps them on the flash disk of the firmware.
+pub trait FileStore {
+	/// Names of the stored files in sorted order
+	fn list(&self) -> Result<Vec<String>>;
+	fn read(&self, name: &str) -> Result<Vec<u8>>;
+	/// Creates a file or replaces its contents
+	fn write(&mut self, name: &str, data: &[u8]) -> Result<()>;
+	/// Gives a file a new name. Fails if a file with the new name exists.
+	fn rename(&mut self, from: &str, to: &str) -> Result<()>;
+	fn delete(&mut self, name: &str) -> Result<()>;
+}
+
+/// File names must fit in the store and can't contain path separators
+pub fn check_file_name(name: &str) -> Result<()> {
+	if name.is_empty()
+		|| name.len() > MAX_FILE_NAME_LEN
+		|| name == "."
+		|| name == ".."
+		|| name.contains(['/', '\\', '\0'])
+	{
+		Err(Error::InvalidEntry)
+	} else {
+		Ok(())
+	}
+}
+
+/// Opens the file store of the platform
+pub fn open_file_store() -> Box<dyn FileStore> {
+	platform().file_store()
+}
+
+/// Lists the stored files. Selecting a file shows what can be done with it.
+pub fn file_browser_menu(names: Vec<String>) -> Menu {
+	let mut items = Vec::new();
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Save State")),
+		function: MenuItemFunction::InMenuAction(Function::SaveStateFile),
+		help: Some("Saves the calculator state to calculator.state"),
+	});
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Save Scripts")),
+		function: MenuItemFunction::InMenuAction(Function::SaveScriptFiles),
+		help: Some("Saves each kept script to a .rpl file"),
+	});
+	for name in names {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::string_layout(name.clone())),
+			function: MenuItemFunction::InMenuActionWithDelete(
+				Function::FileMenu(name.clone()),
+				Function::DeleteFile(name),
+			),
+			help: Some("Press ← to delete the file"),
+		});
+	}
+	Menu::new("Files", items)
+}
+
+pub fn file_menu(name: &str) -> Menu {
+	let items = vec![
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Open")),
+			function: MenuItemFunction::Action(Function::OpenFile(name.to_string())),
+			help: Some("Restores state, keeps the script in a program file or loads a listing"),
+		},
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Rename")),
+			function: MenuItemFunction::Action(Function::RenameFile(name.to_string())),
+			help: None,
+		},
+	];
+	Menu::new(
+		&(FileKind::of(name).to_str().to_string() + ": " + name),
+		items,
+	)
+}
+
+/// Files of the simulator are kept in the directory given by `RSCALC_FILES_DIR`,
+/// or `rscalc_files` in the current directory if it is not set
+#[cfg(not(feature = "embedded"))]
+pub struct HostFileStore {
+	dir: std::path::PathBuf,
+}
+
+#[cfg(not(feature = "embedded"))]
+impl HostFileStore {
+	pub fn new() -> Self {
+		let dir = match std::env::var_os("RSCALC_FILES_DIR") {
+			Some(dir) => std::path::PathBuf::from(dir),
+			None => std::path::PathBuf::from("rscalc_files"),
+		};
+		HostFileStore { dir }
+	}
+
+	fn path(&self, name: &str) -> Result<std::path::PathBuf> {
+		check_file_name(name)?;
+		Ok(self.dir.join(name))
+	}
+}
+
+#[cfg(not(feature = "embedded"))]
+impl FileStore for HostFileStore {
+	fn list(&self) -> Result<Vec<String>> {
+		let entries = match std::fs::read_dir(&self.dir) {
+			Ok(entries) => entries,
+			// The directory is created when the first file is written
+			Err(_) => return Ok(Vec::new()),
+		};
+		let mut names = Vec::new();
+		for entry in entries.flatten() {
+			if !entry.path().is_file() {
+				continue;
+			}
+			if let Ok(name) = entry.file_name().into_string() {
+				if check_file_name(&name).is_ok() {
+					names.push(name);
+				}
+			}
+		}
+		names.sort();
+		Ok(names)
+	}
+
+	fn read(&self, name: &str) -> Result<Vec<u8>> {
+		std::fs::read(self.path(name)?).map_err(|_| Error::FileNotFound)
+	}
+
+	fn write(&mut self, name: &str, data: &[u8]) -> Result<()> {
+		let path = self.path(name)?;
+		std::fs::create_dir_all(&self.dir).map_err(|_| Error::WriteFailed)?;
+		std::fs::write(path, data).map_err(|_| Error::WriteFailed)
+	}
+
+	fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+		let from = self.path(from)?;
+		let to = self.path(to)?;
+		if !from.is_file() {
+			return Err(Error::FileNotFound);
+		}
+		if to.exists() {
+			return Err(Error::InvalidEntry);
+		}
+		std::fs::rename(from, to).map_err(|_| Error::WriteFailed)
+	}
+
+	fn delete(&mut self, name: &str) -> Result<()> {
+		std::fs::remove_file(self.path(name)?).map_err(|_| Error::FileNotFound)
+	}
+}
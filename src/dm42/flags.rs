@@ -0,0 +1,158 @@
+use crate::dm42::functions::Function;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use rscalc_math::error::{Error, Result};
+use rscalc_math::number::ToNumber;
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::String;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Flags are numbered from 00 to 99, as on HP calculators
+pub const FLAG_COUNT: usize = 100;
+
+/// Number of digits typed to choose a flag
+pub const FLAG_DIGITS: usize = 2;
+
+/// Flags 36 to 80 belong to the calculator and show its modes. They change
+/// along with the modes, and can only be tested by programs.
+pub fn is_system_flag(flag: usize) -> bool {
+	(36..=80).contains(&flag)
+}
+
+/// Name of the mode shown by a system flag, using the flag numbers of the
+/// HP-42S so that programs written for it can be ported
+pub fn system_flag_name(flag: usize) -> Option<&'static str> {
+	match flag {
+		40 => Some("Fix"),
+		41 => Some("Eng"),
+		42 => Some("Grad"),
+		43 => Some("Rad"),
+		52 => Some("Prgm"),
+		53 => Some("Input"),
+		_ => None,
+	}
+}
+
+/// What is done with the flag chosen after the function
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FlagOperation {
+	Set,
+	Clear,
+	/// Runs the next step of a program only if the flag is set
+	TestSet,
+	/// Runs the next step of a program only if the flag is clear
+	TestClear,
+}
+
+impl FlagOperation {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			FlagOperation::Set => "SF",
+			FlagOperation::Clear => "CF",
+			FlagOperation::TestSet => "FS?",
+			FlagOperation::TestClear => "FC?",
+		}
+	}
+
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_u8(match self {
+			FlagOperation::Set => 0,
+			FlagOperation::Clear => 1,
+			FlagOperation::TestSet => 2,
+			FlagOperation::TestClear => 3,
+		})
+	}
+
+	pub fn restore(input: &mut DeserializeInput) -> Result<Option<FlagOperation>> {
+		Ok(match input.read_u8()? {
+			0 => Some(FlagOperation::Set),
+			1 => Some(FlagOperation::Clear),
+			2 => Some(FlagOperation::TestSet),
+			3 => Some(FlagOperation::TestClear),
+			_ => None,
+		})
+	}
+}
+
+/// Flags that are set by the user and by programs
+#[derive(Clone)]
+pub struct Flags {
+	bits: u128,
+}
+
+impl Flags {
+	pub fn new() -> Self {
+		Flags { bits: 0 }
+	}
+
+	pub fn get(&self, flag: usize) -> bool {
+		flag < FLAG_COUNT && self.bits & (1 << flag) != 0
+	}
+
+	pub fn set(&mut self, flag: usize, value: bool) -> Result<()> {
+		if flag >= FLAG_COUNT {
+			return Err(Error::IndexOutOfRange);
+		}
+		if is_system_flag(flag) {
+			return Err(Error::NotSupported);
+		}
+		if value {
+			self.bits |= 1 << flag;
+		} else {
+			self.bits &= !(1 << flag);
+		}
+		Ok(())
+	}
+
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_u64(self.bits as u64)?;
+		output.write_u64((self.bits >> 64) as u64)
+	}
+
+	pub fn restore(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		let low = input.read_u64()? as u128;
+		let high = input.read_u64()? as u128;
+		self.bits = low | (high << 64);
+		Ok(())
+	}
+}
+
+/// Two digit number of a flag as it is shown to the user
+pub fn flag_string(flag: usize) -> String {
+	let mut result = String::new();
+	if flag < 10 {
+		result.push('0');
+	}
+	result + &flag.to_number().to_string()
+}
+
+/// Lists all of the flags along with whether they are set. Picking a user flag
+/// toggles it.
+pub fn flags_menu() -> Menu {
+	let mut items = Vec::new();
+	for flag in 0..FLAG_COUNT {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				let mut text = flag_string(flag);
+				if let Some(name) = system_flag_name(flag) {
+					text = text + " " + name;
+				}
+				let set = state.flag(flag).unwrap_or(false);
+				MenuItem::string_layout(text + if set { "   [On]" } else { "   [Off]" })
+			})),
+			function: MenuItemFunction::InMenuAction(Function::FlagToggle(flag)),
+			help: if is_system_flag(flag) {
+				Some("Follows the mode of the calculator")
+			} else {
+				None
+			},
+		});
+	}
+	let mut menu = Menu::new("Flags", items);
+	menu.set_columns(3);
+	menu
+}
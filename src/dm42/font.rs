@@ -3,11 +3,157 @@ mod sans_16;
 mod sans_20;
 mod sans_24;
 
+use crate::dm42::files::FileStore;
+use crate::dm42::screen::BitmapFont;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use rscalc_layout::font::Font;
+use rscalc_math::error::{Error, Result};
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
 pub use sans_13::FONT as SANS_13;
 pub use sans_16::FONT as SANS_16;
 pub use sans_20::FONT as SANS_20;
 pub use sans_24::FONT as SANS_24;
 
+/// Identifies a custom font file
+const CUSTOM_FONT_MAGIC: &[u8] = b"RSCF";
+const CUSTOM_FONT_VERSION: u8 = 1;
+const CUSTOM_FONT_HEADER_SIZE: usize = 8;
+
+/// Names of the files that custom fonts are read from for each size
+pub const CUSTOM_FONT_FILES: [(Font, &str); 4] = [
+	(Font::Smallest, "smallest.rscf"),
+	(Font::Small, "small.rscf"),
+	(Font::Medium, "medium.rscf"),
+	(Font::Large, "large.rscf"),
+];
+
+static CUSTOM_SMALLEST: AtomicPtr<BitmapFont> = AtomicPtr::new(core::ptr::null_mut());
+static CUSTOM_SMALL: AtomicPtr<BitmapFont> = AtomicPtr::new(core::ptr::null_mut());
+static CUSTOM_MEDIUM: AtomicPtr<BitmapFont> = AtomicPtr::new(core::ptr::null_mut());
+static CUSTOM_LARGE: AtomicPtr<BitmapFont> = AtomicPtr::new(core::ptr::null_mut());
+
+fn custom_font_slot(font: Font) -> &'static AtomicPtr<BitmapFont> {
+	match font {
+		Font::Smallest => &CUSTOM_SMALLEST,
+		Font::Small => &CUSTOM_SMALL,
+		Font::Medium => &CUSTOM_MEDIUM,
+		Font::Large => &CUSTOM_LARGE,
+	}
+}
+
+/// Returns the bitmap font used to draw `font`. This is the custom font loaded
+/// for that size, if there is one, or the built in font otherwise.
+pub fn bitmap_font(font: Font) -> &'static BitmapFont {
+	let custom = custom_font_slot(font).load(Ordering::Acquire);
+	if !custom.is_null() {
+		// Custom fonts are leaked when loaded and are never freed
+		return unsafe { &*custom };
+	}
+	match font {
+		Font::Smallest => &SANS_13,
+		Font::Small => &SANS_16,
+		Font::Medium => &SANS_20,
+		Font::Large => &SANS_24,
+	}
+}
+
+/// Loads a custom font from `data` and uses it in place of the built in font
+/// for `font`. The font is kept for the life of the program, so fonts should be
+/// loaded once at startup.
+///
+/// Custom fonts use a simple binary format, with all multibyte values stored
+/// in little endian:
+///
+/// * The magic bytes `RSCF`
+/// * Format version as a byte, currently 1
+/// * Height of the font in pixels as a byte
+/// * Number of glyphs as a 16-bit value, in the order given by `char_to_idx`.
+///   This can't be more than the number of glyphs in the built in fonts. Fonts
+///   made before later glyphs were added have fewer, and the rest are blank.
+///
+/// The header is followed by each glyph in turn:
+///
+/// * Width of the glyph's bitmap in pixels as a byte
+/// * Distance to advance to the next glyph in pixels as a byte
+/// * Bitmap of the glyph, one row at a time from top to bottom. Each row is
+///   `(width + 7) / 8` bytes. Pixels are stored from the most significant bit
+///   of each byte, except that the last byte of a row holds its remaining
+///   pixels in the least significant bits.
+pub fn load_font(font: Font, data: &[u8]) -> Result<()> {
+	let custom = parse_font(data)?;
+	custom_font_slot(font).store(Box::leak(Box::new(custom)), Ordering::Release);
+	Ok(())
+}
+
+/// Loads the custom fonts kept in a file store. Sizes without a file, or with
+/// a file that can't be read, keep the built in font.
+pub fn load_custom_fonts(store: &dyn FileStore) {
+	for (font, name) in &CUSTOM_FONT_FILES {
+		if let Ok(data) = store.read(name) {
+			let _ = load_font(*font, &data);
+		}
+	}
+}
+
+fn parse_font(data: &[u8]) -> Result<BitmapFont> {
+	if data.len() < CUSTOM_FONT_HEADER_SIZE
+		|| &data[0..4] != CUSTOM_FONT_MAGIC
+		|| data[4] != CUSTOM_FONT_VERSION
+	{
+		return Err(Error::CorruptData);
+	}
+	let height = data[5] as usize;
+	let glyph_count = u16::from_le_bytes([data[6], data[7]]) as usize;
+	if height == 0 || glyph_count > SANS_16.chars.len() {
+		return Err(Error::CorruptData);
+	}
+
+	let mut chars = Vec::with_capacity(glyph_count);
+	let mut width = Vec::with_capacity(glyph_count);
+	let mut advance = Vec::with_capacity(glyph_count);
+	let mut offset = CUSTOM_FONT_HEADER_SIZE;
+	for _ in 0..glyph_count {
+		if offset + 2 > data.len() {
+			return Err(Error::CorruptData);
+		}
+		let glyph_width = data[offset];
+		let glyph_size = height * (glyph_width as usize).div_ceil(8);
+		offset += 2;
+		if offset + glyph_size > data.len() {
+			return Err(Error::CorruptData);
+		}
+		width.push(glyph_width);
+		advance.push(data[offset - 1]);
+		let glyph: &'static [u8] = Box::leak(
+			data[offset..offset + glyph_size]
+				.to_vec()
+				.into_boxed_slice(),
+		);
+		chars.push(glyph);
+		offset += glyph_size;
+	}
+	if offset != data.len() {
+		return Err(Error::CorruptData);
+	}
+	while chars.len() < SANS_16.chars.len() {
+		chars.push(&[]);
+		width.push(0);
+		advance.push(0);
+	}
+
+	Ok(BitmapFont {
+		height: height as i32,
+		chars: Box::leak(chars.into_boxed_slice()),
+		width: Box::leak(width.into_boxed_slice()),
+		advance: Box::leak(advance.into_boxed_slice()),
+	})
+}
+
 pub fn char_to_idx(ch: char) -> Option<usize> {
 	match ch {
 		' '..='~' => Some(ch as u32 as usize - ' ' as u32 as usize),
@@ -89,6 +235,61 @@ pub fn char_to_idx(ch: char) -> Option<usize> {
 		'‒' => Some(0xaa),
 		'⋘' => Some(0xab),
 		'⋙' => Some(0xac),
+		'√' => Some(0xad),
+		// Thin space, used to group digits
+		'\u{2009}' => Some(0xae),
 		_ => None,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_font, SANS_16};
+
+	#[test]
+	fn custom_fonts_are_parsed() {
+		let data = [
+			b'R',
+			b'S',
+			b'C',
+			b'F',
+			1,
+			2,
+			1,
+			0,
+			3,
+			4,
+			0b1010_0000,
+			0b0100_0000,
+		];
+		let font = parse_font(&data).unwrap();
+		assert_eq!(font.height, 2);
+		assert_eq!(font.chars.len(), SANS_16.chars.len());
+		assert_eq!(font.chars[0], &[0b1010_0000, 0b0100_0000]);
+		assert_eq!(font.width[0], 3);
+		assert_eq!(font.advance[0], 4);
+		assert!(font.chars[1].is_empty());
+	}
+
+	#[test]
+	fn corrupt_fonts_are_errors() {
+		let data = [
+			b'R',
+			b'S',
+			b'C',
+			b'F',
+			1,
+			2,
+			1,
+			0,
+			3,
+			4,
+			0b1010_0000,
+			0b0100_0000,
+		];
+		assert!(parse_font(&data[..data.len() - 1]).is_err());
+		assert!(parse_font(&[&data[..], &[0]].concat()).is_err());
+		assert!(parse_font(&[&b"RSCG"[..], &data[4..]].concat()).is_err());
+		assert!(parse_font(&[&data[..4], &[2], &data[5..]].concat()).is_err());
+	}
+}
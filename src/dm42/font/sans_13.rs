@@ -175,6 +175,8 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x7f,0x3,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0xc1,0x43,0x3,0xc7,0xf,0xf,0x1c,0x3c,0x3c,0x71,0x70,0x70,0xc3,0x0,0x38,0xe3,0x60,0xe,0x38,0x78,0x3,0x8f,0xe,0x0,0xc3,0x43,0x0,0x0,0x40,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x60,0x83,0x0,0x38,0xe3,0x60,0xe,0x38,0x78,0x3,0x8f,0x1e,0x0,0xc3,0x43,0x3,0xc7,0xf,0xf,0x1c,0x38,0x3c,0x71,0x60,0x70,0xc3,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x7,0x0,0x6,0x0,0xc,0x0,0xc,0x0,0xc,0x1,0x8,0x1,0x8,0xe1,0x8,0x33,0x0,0x3b,0x0,0x1f,0x0,0xe,0x0,0x6,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[],
     ],
     width: &[
         0,5,6,13,11,15,13,3,5,5,8,14,4,5,4,6,10,10,10,9,10,9,10,9,10,10,4,4,13,13,13,8,
@@ -182,7 +184,7 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         5,9,10,8,9,10,7,9,10,4,4,11,4,16,10,10,10,9,7,9,7,10,11,14,10,11,8,9,3,10,13,8,
         13,12,12,13,7,12,14,14,14,9,8,13,13,13,12,12,13,11,13,11,12,14,10,10,10,9,11,10,9,7,8,10,
         12,10,6,10,11,10,11,9,10,9,11,10,11,10,11,10,10,13,11,9,13,11,12,13,16,10,9,9,9,9,13,13,
-        14,6,6,7,6,7,9,8,9,7,10,23,23,
+        14,6,6,7,6,7,9,8,9,7,10,23,23,12,0,
     ],
     advance: &[
         5,7,8,14,11,16,13,5,7,7,9,14,5,6,5,6,11,11,11,11,11,11,11,11,11,11,6,6,14,14,14,9,
@@ -190,6 +192,6 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         9,10,10,8,10,10,6,10,11,5,5,10,5,17,11,10,10,10,7,8,7,11,10,15,10,10,8,11,6,11,14,8,
         14,14,14,14,9,12,15,15,15,11,9,14,14,14,14,14,14,14,14,14,14,14,11,11,10,10,12,11,10,8,9,11,
         13,10,7,10,12,11,11,10,12,10,13,11,12,11,12,10,11,14,12,9,14,12,13,14,17,12,9,9,9,9,14,14,
-        15,7,7,8,7,6,9,9,10,10,11,24,24,
+        15,7,7,8,7,6,9,9,10,10,11,24,24,13,1,
     ],
 };
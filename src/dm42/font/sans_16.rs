@@ -175,6 +175,8 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x7f,0xf,0x7f,0xf,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x8,0x0,0x0,0x0,0x38,0x30,0x6,0x0,0xf0,0xf0,0x1e,0x3,0xc3,0xc3,0x18,0xf,0xf,0x1f,0x0,0x3c,0x3c,0x7c,0x0,0x30,0x70,0x70,0x0,0x3c,0x3c,0x3c,0x0,0xf,0xf,0xf,0x0,0x3,0xc3,0xc3,0x18,0x0,0xf8,0xf0,0x1e,0x0,0x38,0x30,0x6,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x40,0x40,0x0,0x30,0x70,0x70,0x0,0x3c,0x3c,0x3c,0x0,0xf,0xf,0xf,0x0,0x3,0xc3,0xc3,0x18,0x0,0xf0,0xf0,0x1e,0x0,0x38,0x30,0x6,0x0,0xf0,0xf0,0x1e,0x3,0xc3,0xc7,0x18,0xf,0xf,0x1f,0x0,0x3c,0x7c,0x78,0x0,0x30,0x70,0x60,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0xf,0x0,0xc,0x0,0x1c,0x0,0x18,0x0,0x18,0x0,0x18,0x0,0x30,0x0,0x30,0xe0,0x30,0x30,0x30,0x39,0x20,0x19,0x20,0xd,0x20,0xf,0x20,0x7,0x0,0x3,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[],
     ],
     width: &[
         0,5,8,16,13,19,15,4,7,7,10,16,4,7,4,7,12,11,12,12,12,12,12,12,12,12,4,4,15,15,15,10,
@@ -182,7 +184,7 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         7,11,12,10,11,12,8,11,12,4,4,12,4,18,12,12,12,11,9,11,8,13,11,16,12,12,10,11,5,11,15,10,
         16,15,15,16,9,15,17,17,17,10,10,15,15,15,16,16,16,14,16,14,14,17,13,12,11,12,14,12,11,8,10,12,
         15,12,7,12,14,12,13,12,11,11,14,12,13,12,13,11,11,15,13,12,15,13,15,15,18,13,11,11,11,11,15,16,
-        17,7,7,9,7,8,10,10,11,9,12,29,29,
+        17,7,7,9,7,8,10,10,11,9,12,29,29,14,0,
     ],
     advance: &[
         7,8,10,18,13,20,16,6,8,8,11,18,7,8,7,7,13,13,13,13,13,13,13,13,13,13,7,7,18,18,18,11,
@@ -190,6 +192,6 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         11,13,13,12,13,13,7,13,13,6,6,12,6,20,14,13,13,13,9,11,8,14,11,17,13,13,11,13,7,13,18,10,
         18,18,18,18,11,14,18,18,18,13,11,18,18,18,17,17,18,18,18,18,18,18,14,13,12,13,14,13,13,10,11,14,
         16,13,8,13,14,13,13,12,13,12,16,12,14,14,14,12,12,16,14,13,16,14,16,16,21,14,11,11,11,11,18,18,
-        19,8,8,9,8,8,12,12,13,13,13,30,30,
+        19,8,8,9,8,8,12,12,13,13,13,30,30,15,2,
     ],
 };
@@ -175,6 +175,8 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x7f,0xff,0x7f,0xff,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x3,0x0,0xc0,0x6,0x0,0xf,0x3,0xc0,0x1e,0x0,0x3f,0xf,0xc3,0x1e,0x0,0xfc,0x3f,0xf,0x18,0x3,0xf0,0xfc,0x3f,0x0,0xf,0xc3,0xf0,0xfc,0x0,0x3e,0xf,0xc3,0xf0,0x0,0x38,0xf,0x3,0xc0,0x0,0x3e,0xf,0x83,0xf0,0x0,0xf,0x83,0xe0,0xfc,0x0,0x3,0xe0,0xfc,0x3f,0x0,0x0,0xf8,0x3f,0xf,0x18,0x0,0x3f,0xf,0xc3,0x1e,0x0,0xf,0x3,0xc0,0xe,0x0,0x3,0x0,0xc0,0x2,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x30,0xc,0x3,0x0,0x0,0x3c,0xf,0x3,0xc0,0x0,0x3f,0x7,0xc1,0xf0,0x0,0xf,0xc1,0xf0,0x7c,0x0,0x1,0xf0,0x7c,0x1f,0x0,0x0,0x7c,0x1f,0x7,0x1c,0x0,0x1f,0x7,0xc1,0x1e,0x0,0x7,0x1,0xc0,0xe,0x0,0x1f,0x7,0xc1,0x1e,0x0,0x7c,0x1f,0x7,0x18,0x1,0xf0,0x7c,0x1f,0x0,0x7,0xc1,0xf0,0x7c,0x0,0x1f,0x7,0xc1,0xf0,0x0,0x3c,0xf,0x3,0xc0,0x0,0x30,0xc,0x3,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0xf,0x1,0x0,0xf,0x1,0x0,0x1e,0x0,0x0,0x1c,0x0,0x0,0x1c,0x0,0x0,0x1c,0x0,0x0,0x3c,0x0,0x0,0x38,0x0,0x0,0x38,0x0,0x0,0x38,0x0,0x0,0x78,0x0,0xf0,0x70,0x0,0x78,0x70,0x0,0x3c,0x70,0x0,0x1c,0xf0,0x0,0x1e,0xe0,0x0,0xf,0xe0,0x0,0x7,0xe0,0x0,0x7,0xe0,0x0,0x3,0xc0,0x0,0x1,0xc0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[],
     ],
     width: &[
         0,7,10,20,16,24,21,5,8,8,13,19,6,8,6,9,16,15,15,15,16,15,16,15,16,16,6,6,20,20,20,13,
@@ -182,7 +184,7 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         9,14,15,14,15,16,11,15,14,5,5,15,5,23,14,16,15,15,11,13,10,14,15,21,15,15,13,14,5,14,20,12,
         20,19,20,19,11,19,22,22,22,13,13,20,20,20,20,20,21,17,22,17,18,22,17,15,16,14,18,16,13,11,13,14,
         20,16,9,15,18,15,16,15,15,13,18,15,16,15,17,16,14,19,17,15,19,16,20,21,24,16,14,14,14,14,20,20,
-        22,9,9,11,9,10,13,13,13,11,16,37,37,
+        22,9,9,11,9,10,13,13,13,11,16,37,37,17,0,
     ],
     advance: &[
         9,11,12,23,17,26,21,7,11,11,14,23,9,10,9,9,17,17,17,17,17,17,17,17,17,17,9,9,23,23,23,14,
@@ -190,6 +192,6 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         14,17,17,15,17,17,10,17,17,8,8,16,8,26,17,17,17,17,11,14,11,17,16,22,16,16,14,17,9,17,23,13,
         22,23,23,23,14,18,24,24,24,17,14,23,23,23,22,22,23,23,23,23,23,23,18,17,16,15,18,18,17,12,14,16,
         22,18,10,16,18,16,17,15,18,15,21,16,18,17,18,17,16,21,19,16,20,18,22,23,27,18,14,14,14,14,23,23,
-        24,11,11,12,11,10,15,15,16,16,17,38,38,
+        24,11,11,12,11,10,15,15,16,16,17,38,38,18,2,
     ],
 };
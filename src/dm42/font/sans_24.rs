@@ -175,6 +175,8 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x3f,0xff,0x7,0x3f,0xff,0x7,0x3f,0xff,0x7,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x60,0x6,0x0,0x6,0x0,0x1,0xe0,0x1e,0x1,0xe,0x0,0x7,0xe0,0x7e,0x7,0xe,0x0,0x1f,0xc1,0xfc,0x1f,0xc,0x0,0x7f,0x7,0xf0,0x7f,0x0,0x1,0xf8,0x1f,0x81,0xf8,0x0,0x7,0xe0,0x7e,0x7,0xe0,0x0,0x1f,0x81,0xf8,0x1f,0x80,0x0,0x3e,0x3,0xe0,0x3e,0x0,0x0,0x3e,0x3,0xe0,0x3e,0x0,0x0,0x1f,0x81,0xf8,0x1f,0x80,0x0,0x7,0xe0,0x7e,0x7,0xe0,0x0,0x1,0xfc,0x1f,0xc1,0xfc,0x0,0x0,0x7f,0x7,0xf0,0x7f,0x0,0x0,0x1f,0xc1,0xfc,0x1f,0xc,0x0,0x7,0xe0,0x7e,0x7,0xe,0x0,0x1,0xe0,0x1e,0x1,0xe,0x0,0x0,0x20,0x2,0x0,0x2,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
         &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x30,0x3,0x0,0x30,0x0,0x0,0x3c,0x3,0xc0,0x3c,0x0,0x0,0x3f,0x3,0xf8,0x3f,0x80,0x0,0xf,0xe0,0xfe,0xf,0xe0,0x0,0x3,0xf8,0x3f,0x83,0xf8,0x0,0x0,0xfe,0xf,0xe0,0xfe,0x0,0x0,0x3f,0x83,0xf8,0x3f,0x8,0x0,0xf,0xe0,0xfe,0xf,0xe,0x0,0x3,0xe0,0x3e,0x3,0xe,0x0,0x3,0xe0,0x3e,0x3,0xe,0x0,0xf,0xc0,0xfc,0xf,0xc,0x0,0x3f,0x3,0xf0,0x3f,0x0,0x0,0xfc,0xf,0xc0,0xfc,0x0,0x3,0xf0,0x3f,0x3,0xf0,0x0,0xf,0xc0,0xfc,0xf,0xc0,0x0,0x3f,0x3,0xf0,0x3f,0x0,0x0,0x3c,0x3,0xc0,0x3c,0x0,0x0,0x30,0x3,0x0,0x30,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x3,0xf,0x0,0x3,0xf,0x0,0x7,0x8,0x0,0x7,0x0,0x0,0x7,0x0,0x0,0x7,0x0,0x0,0xf,0x0,0x0,0xe,0x0,0x0,0xe,0x0,0x0,0xe,0x0,0x0,0x1c,0x0,0x0,0x1c,0x0,0xf0,0x1c,0x0,0x78,0x1c,0x0,0x3c,0x38,0x0,0x1c,0x38,0x0,0x1e,0x38,0x0,0xe,0x78,0x0,0x7,0x70,0x0,0x7,0xf0,0x0,0x3,0xf0,0x0,0x3,0xf0,0x0,0x1,0xe0,0x0,0x0,0xe0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,],
+        &[],
     ],
     width: &[
         0,8,12,24,19,29,24,6,10,10,15,24,7,10,7,11,18,17,18,17,19,17,18,17,18,18,7,7,23,23,23,14,
@@ -182,7 +184,7 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         10,17,19,16,18,18,12,18,18,6,6,19,6,28,18,18,19,18,13,16,12,18,18,24,18,18,15,17,7,17,23,15,
         24,22,23,24,13,22,26,26,26,15,15,23,23,23,24,24,25,21,25,21,21,26,20,18,17,18,21,18,16,13,17,18,
         24,18,10,18,21,18,20,16,17,16,21,18,19,18,20,18,17,23,19,18,23,19,24,25,29,19,16,16,16,16,23,24,
-        26,10,12,13,10,12,16,15,16,14,19,44,44,
+        26,10,12,13,10,12,16,15,16,14,19,44,44,20,0,
     ],
     advance: &[
         10,13,15,27,20,30,25,9,12,12,16,27,10,12,10,11,20,20,20,20,20,20,20,20,20,20,11,11,27,27,27,17,
@@ -190,6 +192,6 @@ pub const FONT: crate::dm42::screen::BitmapFont = crate::dm42::screen::BitmapFon
         16,20,20,18,20,20,11,20,20,9,9,19,9,31,20,20,20,20,13,17,13,20,19,26,19,19,17,20,11,20,27,16,
         27,27,27,27,16,22,28,28,28,20,17,27,27,27,26,26,27,27,27,27,27,27,22,20,18,19,22,20,20,15,19,21,
         26,20,11,19,22,19,20,18,20,18,24,19,21,20,21,19,19,25,21,19,25,21,26,27,32,22,16,16,16,16,27,27,
-        29,13,13,14,13,12,18,18,19,19,20,46,46,
+        29,13,13,14,13,12,18,18,19,19,20,46,46,21,2,
     ],
 };
@@ -1,31 +1,70 @@
-use crate::dm42::catalog::{assign_menu, catalog_menu, CatalogPage};
+use crate::dm42::catalog::{
+	assign_catalog_menu, assign_menu, custom_banks_menu, graph_user_function_menu,
+	overlay_user_function_menu, program_slots_menu, solver_menu, CatalogPage,
+};
+use crate::dm42::currency::{currency_rates_menu, CURRENCY_CODES};
+use crate::dm42::diagnostics::{diagnostics_menu, memory_status_menu};
+use crate::dm42::dialog::ConfirmDialog;
+use crate::dm42::display::{brightness_menu, DisplayControl, DISPLAY_LEVEL_MAX};
+use crate::dm42::feedback::{play_beep, sound_settings_menu, FeedbackEvent};
+use crate::dm42::files::{file_menu, FileKind};
+use crate::dm42::flags::{flag_string, flags_menu, FlagOperation};
+use crate::dm42::graph::ProgramStep;
 use crate::dm42::input::InputEvent;
-use crate::dm42::menu::settings_menu;
-use crate::dm42::screen::{RenderMode, Screen};
-use crate::dm42::state::{State, StatusBarLeftDisplayType};
-use crate::dm42::unit::{unit_catalog_menu, unit_catalog_menu_of_type, unit_menu_of_type};
+use crate::dm42::locale::LANGUAGES;
+use crate::dm42::matrix_editor::MatrixEditAction;
+use crate::dm42::menu::{
+	advanced_settings_menu, alternate_forms_menu, display_digits_menu, display_settings_menu,
+	number_format_settings_menu, settings_menu,
+};
+use crate::dm42::platform::platform;
+use crate::dm42::print::{print_menu, print_programs, print_stack, print_x, reset_printer};
+use crate::dm42::save::{read_saved_state, receive_transfer, send_transfer, write_saved_state};
+use crate::dm42::screen::{RenderMode, Screen, Theme};
+use crate::dm42::selftest::SelfTest;
+use crate::dm42::settings::{EnterMode, StartupMode, StatusBarLeftDisplayType, CUSTOM_SLOT_COUNTS};
+use crate::dm42::state::State;
+use crate::dm42::transfer::encode_transfer;
+use crate::dm42::unit::{
+	preferred_units_menu, unit_catalog_menu, unit_catalog_menu_of_type, unit_menu_of_type,
+};
+use intel_dfp::RoundingMode;
 use rscalc_layout::font::Font;
 use rscalc_layout::layout::{LayoutRenderer, Rect, TokenType};
-use rscalc_math::error::Result;
-use rscalc_math::format::{AlternateFormatMode, Format, IntegerMode};
+use rscalc_math::business::BusinessVariable;
+use rscalc_math::context::{IntegerDivision, Location};
+use rscalc_math::equation::EQUATION_LIBRARY;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::format::{
+	AlternateForm, AlternateFormatMode, AlternateValueClass, DecimalPointMode, DigitSeparator,
+	Format, FormatMode, FractionDisplay, IntegerMode, OverflowMode,
+};
 use rscalc_math::functions::StackFunction;
-use rscalc_math::unit::UnitType;
+use rscalc_math::modes::ModeScope;
+use rscalc_math::number::{Number, ToNumber};
+use rscalc_math::persist::{read_item, read_string, write_item, write_string, StateWriter};
+use rscalc_math::script::Series;
+use rscalc_math::storage::{
+	deserialize_portable, reset_peak_used_bytes, serialize_portable, DeserializeInput,
+	SerializeOutput,
+};
+use rscalc_math::unit::{AngleUnit, UnitType};
+use rscalc_math::value::Value;
 
-#[cfg(not(feature = "dm42"))]
+#[cfg(not(feature = "embedded"))]
 use std::cell::RefCell;
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::boxed::Box;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::string::{String, ToString};
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use core::cell::RefCell;
 
-#[cfg(feature = "dm42")]
-use crate::dm42::device::{set_time_24_hour, time_24_hour};
-
 #[derive(PartialEq, Eq, Clone)]
 #[allow(dead_code)]
 pub enum Function {
@@ -40,27 +79,178 @@ pub enum Function {
 	AddInvUnitCatalogPage(UnitType),
 	ConvertUnitCatalogMenu,
 	ConvertUnitCatalogPage(UnitType),
-	AssignCatalogMenu(usize),
-	AssignCatalogPage(usize, CatalogPage),
-	AssignAddUnitCatalogMenu(usize),
-	AssignAddUnitCatalogPage(usize, UnitType),
-	AssignAddInvUnitCatalogMenu(usize),
-	AssignAddInvUnitCatalogPage(usize, UnitType),
-	AssignConvertUnitCatalogMenu(usize),
-	AssignConvertUnitCatalogPage(usize, UnitType),
-	AssignCatalogFunction(usize, Box<Function>),
-	RemoveCustomAssign(usize),
+	AssignCatalogMenu(CustomSlot),
+	AssignCatalogPage(CustomSlot, CatalogPage),
+	AssignAddUnitCatalogMenu(CustomSlot),
+	AssignAddUnitCatalogPage(CustomSlot, UnitType),
+	AssignAddInvUnitCatalogMenu(CustomSlot),
+	AssignAddInvUnitCatalogPage(CustomSlot, UnitType),
+	AssignConvertUnitCatalogMenu(CustomSlot),
+	AssignConvertUnitCatalogPage(CustomSlot, UnitType),
+	AssignCatalogItem(CustomSlot, Box<Function>),
+	AssignNewCustomMenu(CustomSlot),
+	RemoveCustomAssign(CustomSlot),
+	CustomMenu(usize),
+	Macro(usize),
+	RecordMacro(CustomSlot),
+	/// Pushes a value kept with the custom assignments
+	PushValue(usize),
+	AssignValue(CustomSlot),
 	UnitMenu(UnitType),
 	SettingsMenu,
+	DisplaySettingsMenu,
+	BrightnessMenu,
+	DisplayLevelUp(DisplayControl),
+	DisplayLevelDown(DisplayControl),
+	DisplayLevelReset(DisplayControl),
+	AutoDimCycle,
+	AutoSaveCycle,
+	NumberFormatSettingsMenu,
+	AdvancedSettingsMenu,
+	PreferredUnitsMenu,
+	PreferredUnitCycle(usize),
 	SystemMenu,
 	Time24HourToggle,
+	LanguageCycle,
 	StatusBarLeftDisplayToggle,
 	StackLabelXYZToggle,
+	ModeTagsToggle,
+	RadicalsToggle,
 	ShowEmptySoftKeyToggle,
 	StatusBarToggle,
 	FontSizeToggle,
 	AlternateFormatModeToggle,
+	StackRenderCacheToggle,
+	ReducedMotionToggle,
+	DeferredReductionToggle,
+	ComplexResultsToggle,
+	EnterModeToggle,
+	StartupModeCycle,
+	CustomSlotCountCycle,
+	ExamModeToggle,
+	EnterExamMode,
+	ExitExamMode,
+	ClearStack,
+	ResetToDefaults,
+	IntegerDivisionCycle,
+	ThemeToggle,
+	DigitSeparatorToggle,
+	RoundingModeCycle,
+	DecimalPointToggle,
+	DisplayModeCycle,
+	IntegerRadixCycle,
+	AngleModeCycle,
+	OverflowModeToggle,
+	FractionDisplayCycle,
+	SplitPaneCycle,
+	SplitPaneResize,
+	DisplayDigitsMenu,
+	DisplayDigits(Option<usize>),
+	AlternateFormsMenu,
+	AlternateFormToggle(AlternateValueClass, AlternateForm),
+	DiagnosticsMenu,
+	ResetSessionStats,
+	MemoryStatus,
+	ResetPeakMemory,
+	DisplayTest,
+	KeyboardTest,
+	SelfTest,
+	Benchmark,
+	Screenshot,
+	RecallVariable(Location),
+	SaveState,
+	RestoreState,
+	ExportState,
+	ImportState,
+	EditScript,
+	EnterText,
+	CatalogSearch,
+	LoadScript,
+	RunScript(usize),
+	RecallAnswer(usize),
+	ValueTable,
+	TableValue(usize),
+	GraphUserFunctionMenu,
+	GraphScript(usize),
+	OverlayUserFunctionMenu,
+	OverlayScript(usize),
+	SolverMenu,
+	SolveEquation(usize),
+	SolveLibraryEquation(usize),
+	SolverVariable(usize),
+	BusinessMenu,
+	Business(BusinessVariable),
+	CurrencyMenu,
+	Currency(usize),
+	CurrencyRatesMenu,
+	SetCurrencyRate(usize),
+	CurrencyHomeCycle,
+	MapList(usize),
+	ReduceList(usize),
+	Series(Series, usize),
+	SetAlarm,
+	SetAlarmWithMessage,
+	CancelAlarm(usize),
+	ScriptText(&'static str),
+	FileBrowser,
+	FileMenu(String),
+	OpenFile(String),
+	RenameFile(String),
+	DeleteFile(String),
+	SaveStateFile,
+	SaveScriptFiles,
+	ShowTape,
+	ExportTape,
+	ShowCalendar,
+	CalendarMonths(i32),
+	CalendarToday,
+	CalendarPushDate,
+	PrintMenu,
+	PrintX,
+	PrintStack,
+	PrintPrograms,
+	ResetPrinter,
 	NewMatrix,
+	EditMatrix,
+	EditData,
+	MatrixEdit(MatrixEditAction),
+	ProgramLabel(ProgramMenuKey),
+	Graph,
+	GraphRange,
+	ScatterPlot,
+	HistogramPlot,
+	LockLocation,
+	ProgramLockToggle,
+	/// Stops the program to ask for the value of a variable
+	ProgramInput,
+	/// Stops the program to show x until a key is pressed
+	ProgramView,
+	/// Marks a place in a program that GTO can continue at
+	Label,
+	/// Shows a soft key for each label in the recorded program
+	ProgramMenu,
+	Flag(FlagOperation),
+	FlagToggle(usize),
+	FlagsMenu,
+	/// Plays the tone numbered by x
+	Tone,
+	Beep,
+	FeedbackToggle(FeedbackEvent),
+	SoundSettingsMenu,
+	CustomSlotLockToggle(CustomSlot),
+	CustomBanksMenu,
+	SelectCustomBank(usize),
+	DeleteCustomBank(usize),
+	NewCustomBank,
+	RenameCustomBank,
+	NextCustomBank,
+	ExportProgram,
+	CopyProgram,
+	PasteProgram,
+	ProgramSlotsMenu,
+	SelectProgramSlot(usize),
+	ClearProgramSlot(usize),
+	RenameProgramSlot,
 }
 
 impl Function {
@@ -95,23 +285,293 @@ impl Function {
 			Function::AssignConvertUnitCatalogPage(_, unit_type) => {
 				"▸".to_string() + unit_type.to_str()
 			}
-			Function::AssignCatalogFunction(_, func) => func.to_string(state),
+			Function::AssignCatalogItem(_, func) => func.to_string(state),
+			Function::AssignNewCustomMenu(_) => "New Menu".to_string(),
 			Function::RemoveCustomAssign(_) => "(None)".to_string(),
+			Function::CustomMenu(menu) => state.function_keys().custom_menu_name(*menu).to_string(),
+			Function::Macro(idx) => "M".to_string() + &(*idx + 1).to_number().to_string(),
+			Function::RecordMacro(_) => "Rec".to_string(),
+			Function::PushValue(idx) => match state.function_keys().custom_value(*idx) {
+				Some(value) => value.format(state.context().format()).to_string(),
+				None => "?".to_string(),
+			},
+			Function::AssignValue(_) => "Value".to_string(),
+			Function::ProgramLabel(key) => key.label.clone(),
 			Function::UnitMenu(unit_type) => unit_type.to_str().to_string(),
 			Function::SettingsMenu => "Settings".to_string(),
+			Function::DisplaySettingsMenu => "Display".to_string(),
+			Function::BrightnessMenu => "Bright".to_string(),
+			Function::DisplayLevelUp(control) => control.to_str().to_string() + "+",
+			Function::DisplayLevelDown(control) => control.to_str().to_string() + "-",
+			Function::DisplayLevelReset(control) => control.to_str().to_string(),
+			Function::AutoDimCycle => "Dim".to_string(),
+			Function::AutoSaveCycle => "AutoSave".to_string(),
+			Function::NumberFormatSettingsMenu => "NumFmt".to_string(),
+			Function::AdvancedSettingsMenu => "Adv".to_string(),
+			Function::PreferredUnitsMenu => "PrefUnit".to_string(),
+			Function::PreferredUnitCycle(idx) => {
+				match state.context().preferred_units().get(*idx) {
+					Some(preferred) => preferred.unit_type().to_str().to_string(),
+					None => "".to_string(),
+				}
+			}
 			Function::SystemMenu => "Sys".to_string(),
 			Function::Time24HourToggle => "24Hr".to_string(),
+			Function::LanguageCycle => "Lang".to_string(),
 			Function::StatusBarLeftDisplayToggle => "StatusDisp".to_string(),
 			Function::StackLabelXYZToggle => "xyz".to_string(),
+			Function::ModeTagsToggle => "Tags".to_string(),
+			Function::RadicalsToggle => "Radicals".to_string(),
 			Function::ShowEmptySoftKeyToggle => "Empty".to_string(),
 			Function::StatusBarToggle => "StatusBar".to_string(),
 			Function::FontSizeToggle => "Font".to_string(),
 			Function::AlternateFormatModeToggle => "Alt".to_string(),
+			Function::StackRenderCacheToggle => "Cache".to_string(),
+			Function::ReducedMotionToggle => "Motion".to_string(),
+			Function::DeferredReductionToggle => "Reduce".to_string(),
+			Function::ComplexResultsToggle => "Cplx".to_string(),
+			Function::EnterModeToggle => "Enter".to_string(),
+			Function::StartupModeCycle => "Start".to_string(),
+			Function::CustomSlotCountCycle => "Slots".to_string(),
+			Function::ExamModeToggle => "Exam".to_string(),
+			Function::EnterExamMode => "Exam".to_string(),
+			Function::ExitExamMode => "EndExm".to_string(),
+			Function::ResetToDefaults => "Reset".to_string(),
+			Function::ClearStack => "CLST".to_string(),
+			Function::IntegerDivisionCycle => "IDiv".to_string(),
+			Function::ThemeToggle => "Theme".to_string(),
+			Function::DigitSeparatorToggle => "Sep".to_string(),
+			Function::RoundingModeCycle => "Round".to_string(),
+			Function::DecimalPointToggle => match state.context().format().decimal_point {
+				DecimalPointMode::Period => "0.5".to_string(),
+				DecimalPointMode::Comma => "0,5".to_string(),
+			},
+			Function::DisplayModeCycle => "Mode".to_string(),
+			Function::IntegerRadixCycle => "Radix".to_string(),
+			Function::AngleModeCycle => "Angle".to_string(),
+			Function::OverflowModeToggle => "Long".to_string(),
+			Function::FractionDisplayCycle => "Frac".to_string(),
+			Function::SplitPaneCycle => "Split".to_string(),
+			Function::SplitPaneResize => "SplitSz".to_string(),
+			Function::DisplayDigitsMenu => "Digits".to_string(),
+			Function::DisplayDigits(digits) => match digits {
+				Some(digits) => digits.to_number().to_string(),
+				None => "All".to_string(),
+			},
+			Function::AlternateFormsMenu => "AltForm".to_string(),
+			Function::AlternateFormToggle(class, form) => {
+				if state.context().format().alt_forms.is_enabled(*class, *form) {
+					"▪".to_string() + form.to_str()
+				} else {
+					form.to_str().to_string()
+				}
+			}
+			Function::DiagnosticsMenu => "Diag".to_string(),
+			Function::ResetSessionStats => "Reset".to_string(),
+			Function::MemoryStatus => "Memory".to_string(),
+			Function::ResetPeakMemory => "Reset Peak".to_string(),
+			Function::DisplayTest => "DispT".to_string(),
+			Function::KeyboardTest => "KeyT".to_string(),
+			Function::SelfTest => "Test".to_string(),
+			Function::Benchmark => "Bench".to_string(),
+			Function::Screenshot => "Shot".to_string(),
+			Function::RecallVariable(location) => location.to_string(),
+			Function::SaveState => "Save".to_string(),
+			Function::RestoreState => "Restore".to_string(),
+			Function::ExportState => "Export".to_string(),
+			Function::ImportState => "Import".to_string(),
+			Function::EditScript => "Script".to_string(),
+			Function::EnterText => "Text".to_string(),
+			Function::CatalogSearch => "Search".to_string(),
+			Function::LoadScript => "Load".to_string(),
+			Function::RunScript(idx) => {
+				match state.scripts().get(*idx).and_then(|script| script.name()) {
+					Some(name) => name.to_string(),
+					None => "S".to_string() + &(*idx + 1).to_number().to_string(),
+				}
+			}
+			Function::RecallAnswer(_) => "Ans".to_string(),
+			Function::ValueTable => "Table".to_string(),
+			Function::TableValue(_) => "f(x)".to_string(),
+			Function::GraphUserFunctionMenu => "PlotFn".to_string(),
+			Function::GraphScript(idx) => Function::RunScript(*idx).to_string(state),
+			Function::OverlayUserFunctionMenu => "PlotF2".to_string(),
+			Function::OverlayScript(idx) => Function::RunScript(*idx).to_string(state),
+			Function::SolverMenu => "Solver".to_string(),
+			Function::SolveEquation(idx) => Function::RunScript(*idx).to_string(state),
+			Function::SolveLibraryEquation(idx) => match EQUATION_LIBRARY.get(*idx) {
+				Some(equation) => equation.formula.to_string(),
+				None => "".to_string(),
+			},
+			Function::SolverVariable(arg) => state.solver_argument(*arg).unwrap_or("").to_string(),
+			Function::BusinessMenu => "Bus".to_string(),
+			Function::Business(variable) => variable.to_str().to_string(),
+			Function::CurrencyMenu => "Curr".to_string(),
+			Function::Currency(currency) | Function::SetCurrencyRate(currency) => {
+				CURRENCY_CODES.get(*currency).unwrap_or(&"").to_string()
+			}
+			Function::CurrencyRatesMenu => "Rates".to_string(),
+			Function::CurrencyHomeCycle => "Home".to_string(),
+			Function::MapList(idx) => {
+				"Map ".to_string() + &Function::RunScript(*idx).to_string(state)
+			}
+			Function::ReduceList(idx) => {
+				"Reduce ".to_string() + &Function::RunScript(*idx).to_string(state)
+			}
+			Function::Series(Series::Sum, idx) => {
+				"Σ".to_string() + &Function::RunScript(*idx).to_string(state)
+			}
+			Function::Series(Series::Product, idx) => {
+				"Π".to_string() + &Function::RunScript(*idx).to_string(state)
+			}
+			Function::SetAlarm => "Alarm".to_string(),
+			Function::SetAlarmWithMessage => "AlarmM".to_string(),
+			Function::CancelAlarm(idx) => {
+				"Cancel".to_string() + &(*idx + 1).to_number().to_string()
+			}
+			Function::ScriptText(text) => text.to_string(),
+			Function::FileBrowser => "Files".to_string(),
+			Function::FileMenu(name) => name.clone(),
+			Function::OpenFile(_) => "Open".to_string(),
+			Function::RenameFile(_) => "Rename".to_string(),
+			Function::DeleteFile(_) => "Delete".to_string(),
+			Function::SaveStateFile => "SaveSt".to_string(),
+			Function::SaveScriptFiles => "SaveScr".to_string(),
+			Function::ShowTape => "Tape".to_string(),
+			Function::ExportTape => "Export".to_string(),
+			Function::ShowCalendar => "Cal".to_string(),
+			Function::CalendarMonths(months) => match months {
+				-12 => "-Year".to_string(),
+				12 => "+Year".to_string(),
+				months if *months < 0 => "-Mon".to_string(),
+				_ => "+Mon".to_string(),
+			},
+			Function::CalendarToday => "Today".to_string(),
+			Function::CalendarPushDate => "→Date".to_string(),
+			Function::PrintMenu => "Print".to_string(),
+			Function::PrintX => "PRX".to_string(),
+			Function::PrintStack => "PRSTK".to_string(),
+			Function::PrintPrograms => "PRPRG".to_string(),
+			Function::ResetPrinter => "PRRST".to_string(),
 			Function::NewMatrix => "New".to_string(),
+			Function::EditMatrix => "Edit".to_string(),
+			Function::EditData => "Data".to_string(),
+			Function::MatrixEdit(action) => match action {
+				MatrixEditAction::Left => "◀".to_string(),
+				MatrixEditAction::Right => "▶".to_string(),
+				MatrixEditAction::InsertRow => "+Row".to_string(),
+				MatrixEditAction::InsertCol => "+Col".to_string(),
+				MatrixEditAction::DeleteRow => "-Row".to_string(),
+				MatrixEditAction::DeleteCol => "-Col".to_string(),
+			},
+			Function::Graph => "Graph".to_string(),
+			Function::GraphRange => "Range".to_string(),
+			Function::ScatterPlot => "Plot".to_string(),
+			Function::HistogramPlot => "Hist".to_string(),
+			Function::LockLocation => "Lock V".to_string(),
+			Function::ProgramInput => "Input".to_string(),
+			Function::ProgramView => "View".to_string(),
+			Function::Label => "Lbl".to_string(),
+			Function::ProgramMenu => "Menu".to_string(),
+			Function::ExportProgram => "Export".to_string(),
+			Function::CopyProgram => "Copy P".to_string(),
+			Function::PasteProgram => "Paste P".to_string(),
+			Function::ProgramSlotsMenu => "Slots".to_string(),
+			Function::SelectProgramSlot(slot) => state.program_slot_name(*slot).to_string(),
+			Function::ClearProgramSlot(_) => "Clear".to_string(),
+			Function::RenameProgramSlot => "Rename".to_string(),
+			Function::Flag(operation) => operation.to_str().to_string(),
+			Function::FlagToggle(flag) => flag_string(*flag),
+			Function::FlagsMenu => "Flags".to_string(),
+			Function::Tone => "Tone".to_string(),
+			Function::Beep => "Beep".to_string(),
+			Function::FeedbackToggle(event) => event.to_str().to_string(),
+			Function::SoundSettingsMenu => "Sounds".to_string(),
+			Function::ProgramLockToggle => {
+				if state.program_locked() {
+					"Unlk P".to_string()
+				} else {
+					"Lock P".to_string()
+				}
+			}
+			Function::CustomSlotLockToggle(_) => "Lock".to_string(),
+			Function::CustomBanksMenu => "Banks".to_string(),
+			Function::SelectCustomBank(bank) => {
+				state.function_keys().custom_bank_name(*bank).to_string()
+			}
+			Function::DeleteCustomBank(_) => "Delete".to_string(),
+			Function::NewCustomBank => "New Bank".to_string(),
+			Function::RenameCustomBank => "Rename".to_string(),
+			Function::NextCustomBank => "Bank▸".to_string(),
+		}
+	}
+
+	pub fn help(&self) -> Option<&'static str> {
+		match self {
+			Function::Stack(func) => func.help(),
+			Function::AssignCatalogItem(_, func) => func.help(),
+			Function::EnterText => {
+				Some("Types text with the alpha keys and pushes it onto the stack")
+			}
+			_ => None,
 		}
 	}
 
+	/// Returns the function that toggles the lock on the item removed by this
+	/// delete function, if that item can be locked
+	pub fn lock_toggle(&self) -> Option<Function> {
+		match self {
+			Function::RemoveCustomAssign(slot) => Some(Function::CustomSlotLockToggle(*slot)),
+			_ => None,
+		}
+	}
+
+	/// Title and message of the question asked before running this function,
+	/// for functions that destroy data
+	pub fn confirmation(&self) -> Option<(&'static str, &'static str)> {
+		match self {
+			Function::DeleteFile(_) => Some(("Delete File?", "The file can't be recovered")),
+			Function::DeleteCustomBank(_) => Some((
+				"Delete Bank?",
+				"The functions assigned in the bank are removed",
+			)),
+			Function::ClearProgramSlot(_) => Some((
+				"Clear Program?",
+				"The steps recorded in the slot are removed",
+			)),
+			Function::RestoreState | Function::ImportState => Some(REPLACE_STATE_CONFIRMATION),
+			Function::OpenFile(name) if FileKind::of(name) == FileKind::State => {
+				Some(REPLACE_STATE_CONFIRMATION)
+			}
+			Function::ResetToDefaults => Some((
+				"Reset to Defaults?",
+				"All stored data and settings are erased",
+			)),
+			Function::EnterExamMode => {
+				Some(("Enter Exam Mode?", "Programs and stored data are put away"))
+			}
+			Function::ExitExamMode => {
+				Some(("Leave Exam Mode?", "Everything done in exam mode is lost"))
+			}
+			_ => None,
+		}
+	}
+
+	/// Runs the function, first asking for confirmation if it destroys data
 	pub fn execute(&self, state: &mut State, screen: &dyn Screen) -> Result<()> {
+		if let Some((title, message)) = self.confirmation() {
+			if state.confirms_actions() {
+				state.show_dialog(ConfirmDialog::new(title, message, self.clone()));
+				return Ok(());
+			}
+		}
+		self.execute_confirmed(state, screen)
+	}
+
+	/// Runs the function without asking for confirmation
+	pub fn execute_confirmed(&self, state: &mut State, screen: &dyn Screen) -> Result<()> {
+		let _modes = ModeScope::begin(state.context());
+		state.advance_generation();
 		match self {
 			Function::Stack(func) => {
 				state.end_edit()?;
@@ -131,7 +591,10 @@ impl Function {
 					.show_menu(FunctionMenu::UnsignedInteger);
 			}
 			Function::CatalogPage(page) => {
-				state.show_menu(page.menu(&|page| Function::CatalogPage(page), &|func| func))?;
+				let mut menu =
+					page.menu(state, &|page| Function::CatalogPage(page), &|func| func)?;
+				menu.show_stack_effects();
+				state.show_menu(menu)?;
 			}
 			Function::AddUnitCatalogMenu => {
 				state.show_menu(unit_catalog_menu("Assign Unit", &|unit_type| {
@@ -176,12 +639,19 @@ impl Function {
 				))?;
 			}
 			Function::AssignCatalogMenu(idx) => {
-				state.show_menu(catalog_menu(&|page| {
-					Function::AssignCatalogPage(*idx, page)
-				}))?;
+				if let Some(Function::CustomMenu(menu)) = state.custom_function(*idx) {
+					// Slot contains a sub-menu, edit the functions inside it
+					let menu = assign_menu(state, Some(menu));
+					state.show_menu(menu)?;
+				} else if state.function_keys().is_slot_locked(*idx) {
+					return Err(Error::Locked);
+				} else {
+					state.show_menu(assign_catalog_menu(*idx))?;
+				}
 			}
 			Function::AssignCatalogPage(idx, page) => {
-				state.show_menu(page.menu(
+				let menu = page.menu(
+					state,
 					&|page| Function::AssignCatalogPage(*idx, page),
 					&|func| match func {
 						Function::AddUnitCatalogMenu => Function::AssignAddUnitCatalogMenu(*idx),
@@ -191,9 +661,10 @@ impl Function {
 						Function::ConvertUnitCatalogMenu => {
 							Function::AssignConvertUnitCatalogMenu(*idx)
 						}
-						_ => Function::AssignCatalogFunction(*idx, Box::new(func)),
+						_ => Function::AssignCatalogItem(*idx, Box::new(func)),
 					},
-				))?;
+				)?;
+				state.show_menu(menu)?;
 			}
 			Function::AssignAddUnitCatalogMenu(idx) => {
 				state.show_menu(unit_catalog_menu("Assign Unit", &|unit_type| {
@@ -205,19 +676,19 @@ impl Function {
 					*unit_type,
 					"",
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddUnit(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddUnitSquared(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddUnitCubed(unit))),
 						)
@@ -234,19 +705,19 @@ impl Function {
 					*unit_type,
 					"/",
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddInvUnit(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddInvUnitSquared(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::AddInvUnitCubed(unit))),
 						)
@@ -263,71 +734,286 @@ impl Function {
 					*unit_type,
 					"▸",
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::ConvertToUnit(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::ConvertToUnit(unit))),
 						)
 					},
 					&|unit| {
-						Function::AssignCatalogFunction(
+						Function::AssignCatalogItem(
 							*idx,
 							Box::new(Function::Stack(StackFunction::ConvertToUnit(unit))),
 						)
 					},
 				))?;
 			}
-			Function::AssignCatalogFunction(idx, func) => {
-				state.set_custom_function(*idx, Some(func.as_ref().clone()));
-				let mut menu = assign_menu();
-				menu.set_selection(*idx);
+			Function::AssignCatalogItem(idx, func) => {
+				state.set_custom_function(*idx, Some(func.as_ref().clone()))?;
+				let mut menu = assign_menu(state, idx.menu);
+				menu.set_selection(idx.idx);
+				state.show_menu(menu)?;
+			}
+			Function::AssignNewCustomMenu(idx) => {
+				let menu = state.function_keys_mut().new_custom_menu();
+				state.set_custom_function(*idx, Some(Function::CustomMenu(menu)))?;
+				let menu = assign_menu(state, Some(menu));
 				state.show_menu(menu)?;
 			}
+			Function::AssignValue(idx) => {
+				state.assign_custom_value(*idx)?;
+				let mut menu = assign_menu(state, idx.menu);
+				menu.set_selection(idx.idx);
+				state.show_menu(menu)?;
+			}
+			Function::PushValue(idx) => {
+				state.end_edit()?;
+				let value = state
+					.function_keys()
+					.custom_value(*idx)
+					.ok_or(Error::ValueNotDefined)?
+					.clone();
+				state.context_mut().push(value)?;
+			}
 			Function::RemoveCustomAssign(idx) => {
-				state.set_custom_function(*idx, None);
+				state.set_custom_function(*idx, None)?;
 			}
+			Function::Macro(idx) => state.run_macro(*idx, screen)?,
+			Function::RecordMacro(idx) => state.start_macro_recording(*idx)?,
 			Function::UnitMenu(unit_type) => {
 				let menu = unit_menu_of_type(*unit_type);
 				state.show_menu(menu)?;
 			}
+			Function::DiagnosticsMenu => {
+				let menu = diagnostics_menu();
+				state.show_menu(menu)?;
+			}
+			Function::ResetSessionStats => {
+				state.session_stats_mut().reset();
+			}
+			Function::MemoryStatus => state.show_menu(memory_status_menu())?,
+			Function::ResetPeakMemory => reset_peak_used_bytes(),
+			Function::DisplayTest => state.start_self_test(SelfTest::display())?,
+			Function::KeyboardTest => state.start_self_test(SelfTest::keyboard())?,
+			Function::SelfTest => state.start_self_test(SelfTest::checks())?,
+			Function::Benchmark => state.start_self_test(SelfTest::benchmark())?,
+			Function::Screenshot => {
+				screen.capture()?;
+			}
+			Function::RecallVariable(location) => {
+				state.end_edit()?;
+				let value = state.context().read(location)?;
+				state.context_mut().push(value)?;
+			}
+			Function::SaveState => {
+				state.end_edit()?;
+				write_saved_state(&state.save_state()?)?;
+			}
+			Function::RestoreState => {
+				let data = read_saved_state().ok_or(Error::NoSavedState)?;
+				state.restore_saved_state(&data)?;
+			}
+			Function::ExportState => {
+				state.end_edit()?;
+				send_transfer(&encode_transfer(&state.save_state()?))?;
+			}
+			Function::ImportState => {
+				let data = receive_transfer()?;
+				state.restore_saved_state(&data)?;
+			}
+			Function::EditScript => state.start_script_entry()?,
+			Function::EnterText => state.start_text_entry()?,
+			Function::CatalogSearch => state.start_catalog_search()?,
+			Function::LoadScript => state.load_script()?,
+			Function::RunScript(idx) => state.run_script(*idx)?,
+			Function::RecallAnswer(idx) => state.recall_answer(*idx)?,
+			Function::ValueTable => state.show_function_table(screen)?,
+			Function::TableValue(idx) => state.push_table_value(*idx)?,
+			Function::GraphUserFunctionMenu => {
+				let menu = graph_user_function_menu(state)?;
+				state.show_menu(menu)?;
+			}
+			Function::GraphScript(idx) => state.show_user_function_graph(*idx, screen)?,
+			Function::OverlayUserFunctionMenu => {
+				let menu = overlay_user_function_menu(state)?;
+				state.show_menu(menu)?;
+			}
+			Function::OverlayScript(idx) => {
+				state.overlay_user_function_graph(*idx, screen)?
+			}
+			Function::SolverMenu => {
+				let menu = solver_menu(state)?;
+				state.show_menu(menu)?;
+			}
+			Function::SolveEquation(idx) => state.start_solver(*idx)?,
+			Function::SolveLibraryEquation(idx) => state.start_library_solver(*idx)?,
+			Function::SolverVariable(arg) => state.solver_variable(*arg)?,
+			Function::BusinessMenu => {
+				state.function_keys_mut().show_menu(FunctionMenu::Business);
+			}
+			Function::Business(variable) => state.business_variable(*variable)?,
+			Function::CurrencyMenu => {
+				state.function_keys_mut().show_menu(FunctionMenu::Currency);
+			}
+			Function::Currency(currency) => state.currency_key(*currency)?,
+			Function::CurrencyRatesMenu => state.show_menu(currency_rates_menu())?,
+			Function::SetCurrencyRate(currency) => state.set_currency_rate(*currency)?,
+			Function::CurrencyHomeCycle => state.next_home_currency()?,
+			Function::MapList(idx) => state.map_list(*idx)?,
+			Function::ReduceList(idx) => state.reduce_list(*idx)?,
+			Function::Series(series, idx) => state.evaluate_series(*idx, *series)?,
+			Function::SetAlarm => state.set_alarm(false)?,
+			Function::SetAlarmWithMessage => state.set_alarm(true)?,
+			Function::CancelAlarm(idx) => state.cancel_alarm(*idx)?,
+			Function::ScriptText(text) => state.insert_script_word(text),
+			Function::FileBrowser => state.show_file_browser()?,
+			Function::FileMenu(name) => state.show_menu(file_menu(name))?,
+			Function::OpenFile(name) => state.open_file(name)?,
+			Function::RenameFile(name) => state.start_rename_file(name)?,
+			Function::DeleteFile(name) => state.delete_file(name)?,
+			Function::SaveStateFile => state.save_state_file()?,
+			Function::SaveScriptFiles => state.save_script_files()?,
+			Function::ShowTape => state.show_tape()?,
+			Function::ExportTape => state.export_tape()?,
+			Function::ShowCalendar => state.show_calendar()?,
+			Function::CalendarMonths(months) => state.move_calendar_months(*months)?,
+			Function::CalendarToday => state.select_calendar_today()?,
+			Function::CalendarPushDate => state.push_calendar_date()?,
+			Function::PrintMenu => state.show_menu(print_menu())?,
+			Function::PrintX => print_x(state)?,
+			Function::PrintStack => print_stack(state)?,
+			Function::PrintPrograms => print_programs(state)?,
+			Function::ResetPrinter => reset_printer()?,
 			Function::SettingsMenu => {
 				let menu = settings_menu();
 				state.show_menu(menu)?;
 			}
+			Function::DisplaySettingsMenu => {
+				let menu = display_settings_menu();
+				state.show_menu(menu)?;
+			}
+			Function::BrightnessMenu => state.show_menu(brightness_menu())?,
+			Function::DisplayLevelUp(control) => {
+				let level = state.display_level(*control);
+				if level < DISPLAY_LEVEL_MAX {
+					state.set_display_level(*control, level + 1);
+				}
+			}
+			Function::DisplayLevelDown(control) => {
+				let level = state.display_level(*control);
+				if level > 0 {
+					state.set_display_level(*control, level - 1);
+				}
+			}
+			Function::DisplayLevelReset(control) => {
+				state.set_display_level(*control, control.default_level());
+			}
+			Function::AutoDimCycle => state.next_auto_dim(),
+			Function::AutoSaveCycle => state.next_auto_save(),
+			Function::NumberFormatSettingsMenu => {
+				let menu = number_format_settings_menu();
+				state.show_menu(menu)?;
+			}
+			Function::AdvancedSettingsMenu => {
+				let menu = advanced_settings_menu();
+				state.show_menu(menu)?;
+			}
+			Function::DisplayModeCycle => {
+				// Go through the format functions so that modes that need extra
+				// setup, such as fixed digits, are configured the same way
+				let func = match state.context().format().mode {
+					FormatMode::Normal => StackFunction::RationalFormat,
+					FormatMode::Rational => StackFunction::FixedFormat,
+					FormatMode::Fixed => StackFunction::ScientificFormat,
+					FormatMode::Scientific => StackFunction::EngineeringFormat,
+					FormatMode::Engineering => StackFunction::SignificantFormat,
+					FormatMode::Significant => StackFunction::NormalFormat,
+				};
+				func.execute(state.context_mut())?;
+			}
+			Function::IntegerRadixCycle => {
+				state.context_mut().cycle_integer_radix();
+			}
+			Function::AngleModeCycle => {
+				let unit = match state.context().angle_mode() {
+					AngleUnit::Degrees => AngleUnit::Radians,
+					AngleUnit::Radians => AngleUnit::Gradians,
+					AngleUnit::Gradians => AngleUnit::Degrees,
+				};
+				state.context_mut().set_angle_mode(unit);
+			}
+			Function::PreferredUnitsMenu => {
+				let menu = preferred_units_menu(state);
+				state.show_menu(menu)?;
+			}
+			Function::DisplayDigitsMenu => {
+				let menu = display_digits_menu(state);
+				state.show_menu(menu)?;
+			}
+			Function::AlternateFormsMenu => {
+				let menu = alternate_forms_menu();
+				state.show_menu(menu)?;
+			}
+			Function::AlternateFormToggle(class, form) => {
+				state.context_mut().toggle_alternate_form(*class, *form);
+			}
+			Function::DisplayDigits(digits) => {
+				state.context_mut().set_display_digits(*digits);
+			}
+			Function::PreferredUnitCycle(idx) => {
+				state.context_mut().cycle_preferred_unit(*idx);
+			}
 			Function::SystemMenu => {
 				state.show_system_setup_menu();
 			}
 			Function::Time24HourToggle => {
-				#[cfg(feature = "dm42")]
-				{
-					set_time_24_hour(!time_24_hour());
-					state.context_mut().format_mut().time_24_hour = time_24_hour();
-				}
-
-				#[cfg(not(feature = "dm42"))]
-				{
-					let value = !state.context().format().time_24_hour;
-					state.context_mut().format_mut().time_24_hour = value;
+				// The clock format is a system setting on some platforms
+				match platform().time_24_hour() {
+					Some(value) => {
+						platform().set_time_24_hour(!value);
+						state.context_mut().format_mut().time_24_hour = !value;
+					}
+					None => {
+						let value = !state.context().format().time_24_hour;
+						state.context_mut().format_mut().time_24_hour = value;
+					}
 				}
 			}
+			Function::LanguageCycle => {
+				let next = (state.language().index() + 1) % LANGUAGES.len();
+				state.set_language(LANGUAGES[next]);
+				// Items with fixed text are translated when the menu is built, so
+				// the menu is built again in the new language
+				state.replace_menu(settings_menu());
+			}
 			Function::StatusBarLeftDisplayToggle => {
 				state.set_status_bar_left_display(match state.status_bar_left_display() {
 					StatusBarLeftDisplayType::CurrentTime => StatusBarLeftDisplayType::FreeMemory,
-					StatusBarLeftDisplayType::FreeMemory => StatusBarLeftDisplayType::CurrentTime,
+					StatusBarLeftDisplayType::FreeMemory => StatusBarLeftDisplayType::FrameTime,
+					StatusBarLeftDisplayType::FrameTime => StatusBarLeftDisplayType::Battery,
+					StatusBarLeftDisplayType::Battery => StatusBarLeftDisplayType::CurrentTime,
 				});
 			}
 			Function::StackLabelXYZToggle => {
 				let value = !state.context().format().stack_xyz;
 				state.context_mut().format_mut().stack_xyz = value;
 			}
+			Function::ModeTagsToggle => {
+				let value = !state.context().format().mode_tags;
+				state.context_mut().format_mut().mode_tags = value;
+			}
+			Function::RadicalsToggle => {
+				let value = !state.context().format().radicals;
+				state.context_mut().format_mut().radicals = value;
+			}
 			Function::ShowEmptySoftKeyToggle => {
-				let value = !state.function_keys().show_empty();
-				state.function_keys_mut().set_show_empty(value);
+				let value = !state.show_empty_soft_keys();
+				state.set_show_empty_soft_keys(value);
 			}
 			Function::StatusBarToggle => {
 				let value = !state.status_bar_enabled();
@@ -338,7 +1024,7 @@ impl Function {
 					Font::Smallest => Font::Small,
 					Font::Small => Font::Medium,
 					Font::Medium => Font::Large,
-					Font::Large => Font::Small,
+					Font::Large => Font::Smallest,
 				};
 				state.set_base_font(value);
 			}
@@ -350,12 +1036,293 @@ impl Function {
 				};
 				state.context_mut().format_mut().alt_mode = value;
 			}
+			Function::DigitSeparatorToggle => {
+				let value = match state.context().format().digit_separator {
+					DigitSeparator::Comma => DigitSeparator::ThinSpace,
+					DigitSeparator::ThinSpace => DigitSeparator::Apostrophe,
+					DigitSeparator::Apostrophe => DigitSeparator::Underscore,
+					DigitSeparator::Underscore => DigitSeparator::Comma,
+				};
+				state.context_mut().set_digit_separator(value);
+			}
+			Function::RoundingModeCycle => {
+				let value = match state.context().format().rounding {
+					RoundingMode::NearestAway => RoundingMode::NearestEven,
+					RoundingMode::NearestEven => RoundingMode::TowardZero,
+					RoundingMode::TowardZero => RoundingMode::Up,
+					RoundingMode::Up => RoundingMode::Down,
+					RoundingMode::Down => RoundingMode::NearestAway,
+				};
+				state.context_mut().set_rounding_mode(value);
+			}
+			Function::SplitPaneCycle => {
+				state.split_mut().cycle_pane();
+			}
+			Function::SplitPaneResize => {
+				state.split_mut().resize();
+			}
+			Function::DecimalPointToggle => {
+				let value = match state.context().format().decimal_point {
+					DecimalPointMode::Period => DecimalPointMode::Comma,
+					DecimalPointMode::Comma => DecimalPointMode::Period,
+				};
+				state.context_mut().set_decimal_point_mode(value);
+			}
+			Function::OverflowModeToggle => {
+				let value = match state.context().format().overflow {
+					OverflowMode::Wrap => OverflowMode::Ellipsis,
+					OverflowMode::Ellipsis => OverflowMode::Wrap,
+				};
+				state.context_mut().set_overflow_mode(value);
+			}
+			Function::FractionDisplayCycle => {
+				let value = match state.context().format().fraction_display {
+					FractionDisplay::Auto => FractionDisplay::Fraction,
+					FractionDisplay::Fraction => FractionDisplay::Decimal,
+					FractionDisplay::Decimal => FractionDisplay::Auto,
+				};
+				state.context_mut().set_fraction_display(value);
+			}
+			Function::StackRenderCacheToggle => {
+				let value = !state.stack_render_cache_enabled();
+				state.set_stack_render_cache_enabled(value);
+			}
+			Function::ReducedMotionToggle => {
+				let value = !state.reduced_motion();
+				state.set_reduced_motion(value);
+			}
+			Function::DeferredReductionToggle => {
+				let value = !state.context().deferred_reduction();
+				state.context_mut().set_deferred_reduction(value);
+			}
+			Function::ComplexResultsToggle => {
+				let value = !state.context().complex_results();
+				state.context_mut().set_complex_results(value);
+			}
+			Function::EnterModeToggle => {
+				let value = match state.enter_mode() {
+					EnterMode::Terminate => EnterMode::Duplicate,
+					EnterMode::Duplicate => EnterMode::Terminate,
+				};
+				state.set_enter_mode(value);
+			}
+			Function::StartupModeCycle => {
+				let value = match state.startup_mode() {
+					StartupMode::Restore => StartupMode::ClearStack,
+					StartupMode::ClearStack => StartupMode::RunProgram,
+					StartupMode::RunProgram => StartupMode::Restore,
+				};
+				state.set_startup_mode(value);
+			}
+			Function::CustomSlotCountCycle => {
+				let count = state.custom_slots();
+				let next = CUSTOM_SLOT_COUNTS
+					.iter()
+					.find(|value| **value as usize > count)
+					.unwrap_or(&CUSTOM_SLOT_COUNTS[0]);
+				state.set_custom_slots(*next);
+			}
+			Function::ExamModeToggle => {
+				if state.exam_mode() {
+					Function::ExitExamMode.execute(state, screen)?;
+				} else {
+					Function::EnterExamMode.execute(state, screen)?;
+				}
+			}
+			Function::EnterExamMode => state.enter_exam_mode()?,
+			Function::ExitExamMode => state.exit_exam_mode()?,
+			Function::ResetToDefaults => state.reset_to_defaults()?,
+			Function::ClearStack => {
+				state.end_edit()?;
+				state.context_mut().clear_stack();
+			}
+			Function::IntegerDivisionCycle => {
+				let value = match state.context().integer_division() {
+					IntegerDivision::Rational => IntegerDivision::Decimal,
+					IntegerDivision::Decimal => IntegerDivision::QuotientRemainder,
+					IntegerDivision::QuotientRemainder => IntegerDivision::Rational,
+				};
+				state.context_mut().set_integer_division(value);
+			}
+			Function::ThemeToggle => {
+				state.set_theme(match state.theme() {
+					Theme::Light => Theme::Dark,
+					Theme::Dark => Theme::Light,
+				});
+			}
 			Function::NewMatrix => state.function_keys_mut().show_menu(FunctionMenu::NewMatrix),
+			Function::EditMatrix => state.start_matrix_edit()?,
+			Function::EditData => state.start_data_edit()?,
+			Function::MatrixEdit(action) => state.apply_matrix_edit(*action)?,
+			Function::Graph => state.show_graph(screen)?,
+			Function::GraphRange => {
+				// Range is taken from the stack with the minimum in Y and maximum in X
+				state.end_edit()?;
+				let min = state
+					.context()
+					.entry(1)?
+					.real_number()?
+					.to_decimal()
+					.into_owned();
+				let max = state
+					.context()
+					.entry(0)?
+					.real_number()?
+					.to_decimal()
+					.into_owned();
+				state.set_graph_range(min, max)?;
+			}
+			Function::ScatterPlot => state.show_scatter_plot(screen)?,
+			Function::HistogramPlot => state.show_histogram(screen)?,
+			Function::LockLocation => state.start_lock_entry()?,
+			Function::ProgramInput => state.start_program_input()?,
+			Function::ProgramView => state.view_x()?,
+			Function::Label => state.start_label_entry()?,
+			Function::ProgramMenu => state.show_program_labels_menu()?,
+			Function::ExportProgram => state.export_program()?,
+			Function::CopyProgram => state.copy_program()?,
+			Function::PasteProgram => state.paste_program()?,
+			Function::ProgramSlotsMenu => state.show_menu(program_slots_menu())?,
+			Function::SelectProgramSlot(slot) => state.select_program_slot(*slot)?,
+			Function::ClearProgramSlot(slot) => state.clear_program_slot(*slot)?,
+			Function::RenameProgramSlot => state.start_rename_program_slot()?,
+			Function::Flag(operation) => state.start_flag_entry(*operation)?,
+			Function::FlagToggle(flag) => {
+				let value = !state.flag(*flag)?;
+				state.set_flag(*flag, value)?;
+			}
+			Function::FlagsMenu => state.show_menu(flags_menu())?,
+			Function::Tone => state.play_tone()?,
+			Function::Beep => play_beep(),
+			Function::FeedbackToggle(event) => {
+				let enabled = !state.feedback_enabled(*event);
+				state.set_feedback_enabled(*event, enabled);
+			}
+			Function::SoundSettingsMenu => state.show_menu(sound_settings_menu())?,
+			Function::ProgramLockToggle => {
+				let locked = !state.program_locked();
+				state.set_program_locked(locked);
+			}
+			Function::CustomSlotLockToggle(slot) => {
+				let locked = !state.function_keys().is_slot_locked(*slot);
+				state.function_keys_mut().set_slot_locked(*slot, locked);
+			}
+			Function::CustomBanksMenu => state.show_menu(custom_banks_menu(state))?,
+			Function::SelectCustomBank(bank) => state.select_custom_bank(*bank),
+			Function::DeleteCustomBank(bank) => state.delete_custom_bank(*bank)?,
+			Function::NewCustomBank => state.new_custom_bank()?,
+			Function::RenameCustomBank => state.start_rename_custom_bank()?,
+			Function::NextCustomBank => state.next_custom_bank(),
+			Function::CustomMenu(menu) => state
+				.function_keys_mut()
+				.show_menu(FunctionMenu::CustomMenu(*menu)),
+			Function::ProgramLabel(key) => state.goto_label(key.target.clone(), screen)?,
 		}
 		Ok(())
 	}
+
+	/// Writes an optional function into saved state. Only functions that can be
+	/// assigned to keys or recorded in programs are saved, others are written as
+	/// empty.
+	pub fn save(func: Option<&Function>, output: &mut Vec<u8>) -> Result<()> {
+		write_item(output, |output| match func {
+			Some(Function::Stack(func)) => {
+				output.write_u8(SAVED_FUNCTION_STACK)?;
+				func.save(output)
+			}
+			Some(Function::Input(input)) => {
+				output.write_u8(SAVED_FUNCTION_INPUT)?;
+				input.save(output)
+			}
+			Some(Function::CustomMenu(menu)) => {
+				output.write_u8(SAVED_FUNCTION_CUSTOM_MENU)?;
+				output.write_u32(*menu as u32)
+			}
+			Some(Function::RecallVariable(location)) => {
+				output.write_u8(SAVED_FUNCTION_RECALL_VARIABLE)?;
+				location.save(output)
+			}
+			Some(Function::Macro(idx)) => {
+				output.write_u8(SAVED_FUNCTION_MACRO)?;
+				output.write_u32(*idx as u32)
+			}
+			Some(Function::NextCustomBank) => output.write_u8(SAVED_FUNCTION_NEXT_CUSTOM_BANK),
+			Some(Function::PushValue(idx)) => {
+				output.write_u8(SAVED_FUNCTION_VALUE)?;
+				output.write_u32(*idx as u32)
+			}
+			Some(Function::ProgramInput) => output.write_u8(SAVED_FUNCTION_PROGRAM_INPUT),
+			Some(Function::ProgramView) => output.write_u8(SAVED_FUNCTION_PROGRAM_VIEW),
+			Some(Function::Label) => output.write_u8(SAVED_FUNCTION_LABEL),
+			Some(Function::Tone) => output.write_u8(SAVED_FUNCTION_TONE),
+			Some(Function::Beep) => output.write_u8(SAVED_FUNCTION_BEEP),
+			Some(Function::ProgramMenu) => output.write_u8(SAVED_FUNCTION_PROGRAM_MENU),
+			Some(Function::Flag(operation)) => {
+				output.write_u8(SAVED_FUNCTION_FLAG)?;
+				operation.save(output)
+			}
+			_ => output.write_u8(SAVED_FUNCTION_NONE),
+		})
+	}
+
+	/// Reads a function written by `save`. Functions that are not known to this
+	/// version are restored as empty.
+	pub fn restore(input: &mut DeserializeInput) -> Result<Option<Function>> {
+		let mut input = read_item(input)?;
+		Ok(match input.read_u8()? {
+			SAVED_FUNCTION_STACK => StackFunction::restore(&mut input)?.map(Function::Stack),
+			SAVED_FUNCTION_INPUT => InputEvent::restore(&mut input)?.map(Function::Input),
+			SAVED_FUNCTION_CUSTOM_MENU => Some(Function::CustomMenu(input.read_u32()? as usize)),
+			SAVED_FUNCTION_RECALL_VARIABLE => {
+				Some(Function::RecallVariable(Location::restore(&mut input)?))
+			}
+			SAVED_FUNCTION_MACRO => Some(Function::Macro(input.read_u32()? as usize)),
+			SAVED_FUNCTION_NEXT_CUSTOM_BANK => Some(Function::NextCustomBank),
+			SAVED_FUNCTION_VALUE => Some(Function::PushValue(input.read_u32()? as usize)),
+			SAVED_FUNCTION_PROGRAM_INPUT => Some(Function::ProgramInput),
+			SAVED_FUNCTION_PROGRAM_VIEW => Some(Function::ProgramView),
+			SAVED_FUNCTION_LABEL => Some(Function::Label),
+			SAVED_FUNCTION_TONE => Some(Function::Tone),
+			SAVED_FUNCTION_BEEP => Some(Function::Beep),
+			SAVED_FUNCTION_PROGRAM_MENU => Some(Function::ProgramMenu),
+			SAVED_FUNCTION_FLAG => FlagOperation::restore(&mut input)?.map(Function::Flag),
+			_ => None,
+		})
+	}
 }
 
+// Kinds of functions in saved state
+/// Question asked before loading a state over the current one
+const REPLACE_STATE_CONFIRMATION: (&str, &str) = (
+	"Replace State?",
+	"The stack, memory and programs are replaced",
+);
+
+const SAVED_FUNCTION_NONE: u8 = 0;
+const SAVED_FUNCTION_STACK: u8 = 1;
+const SAVED_FUNCTION_INPUT: u8 = 2;
+const SAVED_FUNCTION_CUSTOM_MENU: u8 = 3;
+const SAVED_FUNCTION_RECALL_VARIABLE: u8 = 4;
+const SAVED_FUNCTION_MACRO: u8 = 5;
+const SAVED_FUNCTION_NEXT_CUSTOM_BANK: u8 = 6;
+const SAVED_FUNCTION_VALUE: u8 = 7;
+const SAVED_FUNCTION_PROGRAM_INPUT: u8 = 8;
+const SAVED_FUNCTION_PROGRAM_VIEW: u8 = 9;
+const SAVED_FUNCTION_FLAG: u8 = 10;
+const SAVED_FUNCTION_LABEL: u8 = 11;
+const SAVED_FUNCTION_TONE: u8 = 12;
+const SAVED_FUNCTION_BEEP: u8 = 13;
+const SAVED_FUNCTION_PROGRAM_MENU: u8 = 14;
+
+// Tag of the saved state record holding custom assignments
+const STATE_RECORD_CUSTOM: u16 = 0x0101;
+
+/// Words of the script language that can't be typed directly on the keyboard
+const SCRIPT_WORDS: [&str; 21] = [
+	"«", "»", "→", "IF", "THEN", "ELSE", "END", "FOR", "NEXT", "START", "STEP", "DUP", "DROP",
+	"SWAP", "OVER", "==", "≠", "<", ">", "≤", "≥",
+];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum FunctionMenu {
 	Custom,
@@ -368,17 +1335,33 @@ pub enum FunctionMenu {
 	Stats,
 	Matrix,
 	NewMatrix,
+	MatrixEditor,
+	DataEditor,
+	/// Arguments of the equation being solved, given by their count
+	Solver(usize),
+	Business,
+	Currency,
+	CustomMenu(usize),
+	Program,
+	Graph,
+	Script,
+	Tape,
+	Calendar,
 }
 
 impl FunctionMenu {
 	pub fn functions(&self, state: &FunctionKeyState) -> Vec<Option<Function>> {
 		match self {
-			FunctionMenu::Custom => state.custom_functions.clone(),
+			FunctionMenu::Custom => state.banks[state.active_bank].functions.clone(),
 			FunctionMenu::Disp => [
 				Some(Function::Stack(StackFunction::NormalFormat)),
 				Some(Function::Stack(StackFunction::RationalFormat)),
+				Some(Function::Stack(StackFunction::FixedFormat)),
 				Some(Function::Stack(StackFunction::ScientificFormat)),
 				Some(Function::Stack(StackFunction::EngineeringFormat)),
+				Some(Function::Stack(StackFunction::SignificantFormat)),
+				Some(Function::DisplayDigitsMenu),
+				Some(Function::Stack(StackFunction::SIPrefix)),
 				Some(Function::Stack(StackFunction::AlternateHex)),
 				Some(Function::Stack(StackFunction::AlternateFloat)),
 				Some(Function::Stack(StackFunction::ThousandsSeparatorOff)),
@@ -400,6 +1383,7 @@ impl FunctionMenu {
 				Some(Function::Stack(StackFunction::Float)),
 				Some(Function::SignedInteger),
 				Some(Function::UnsignedInteger),
+				Some(Function::Stack(StackFunction::BaseCycle)),
 			]
 			.to_vec(),
 			FunctionMenu::SignedInteger => [
@@ -429,20 +1413,58 @@ impl FunctionMenu {
 				Some(Function::Stack(StackFunction::ShiftRight)),
 				Some(Function::Stack(StackFunction::RotateLeft)),
 				Some(Function::Stack(StackFunction::RotateRight)),
+				Some(Function::Stack(StackFunction::ByteSwap16)),
+				Some(Function::Stack(StackFunction::ByteSwap32)),
+				Some(Function::Stack(StackFunction::ByteSwap64)),
+				Some(Function::Stack(StackFunction::ByteReverse)),
+				Some(Function::Stack(StackFunction::ExtractBits)),
+				Some(Function::Stack(StackFunction::InsertBits)),
+				Some(Function::Stack(StackFunction::BitMask)),
+				Some(Function::Stack(StackFunction::TestCarry)),
+				Some(Function::Stack(StackFunction::TestOutOfRange)),
+				Some(Function::Stack(StackFunction::ClearFlags)),
 			]
 			.to_vec(),
 			FunctionMenu::Stats => [
 				Some(Function::Stack(StackFunction::Sum)),
 				Some(Function::Stack(StackFunction::Mean)),
+				Some(Function::EditData),
+				Some(Function::ScatterPlot),
+				Some(Function::HistogramPlot),
+				Some(Function::Stack(StackFunction::Histogram)),
+				Some(Function::BusinessMenu),
+			]
+			.to_vec(),
+			FunctionMenu::Business => [
+				Some(Function::Business(BusinessVariable::Cost)),
+				Some(Function::Business(BusinessVariable::Price)),
+				Some(Function::Business(BusinessVariable::Margin)),
+				Some(Function::Business(BusinessVariable::Markup)),
+				Some(Function::Business(BusinessVariable::TaxRate)),
+				Some(Function::Stack(StackFunction::AddTax)),
+				Some(Function::Stack(StackFunction::RemoveTax)),
 			]
 			.to_vec(),
+			FunctionMenu::Currency => (0..CURRENCY_CODES.len())
+				.map(|currency| Some(Function::Currency(currency)))
+				.chain(core::iter::once(Some(Function::CurrencyRatesMenu)))
+				.collect(),
 			FunctionMenu::Matrix => [
 				Some(Function::NewMatrix),
+				Some(Function::EditMatrix),
 				Some(Function::Stack(StackFunction::Transpose)),
 				Some(Function::Stack(StackFunction::DotProduct)),
 				Some(Function::Stack(StackFunction::CrossProduct)),
 				Some(Function::Stack(StackFunction::Magnitude)),
 				Some(Function::Stack(StackFunction::Normalize)),
+				Some(Function::Stack(StackFunction::AngleBetween)),
+				Some(Function::Stack(StackFunction::LinearSolve)),
+				Some(Function::Stack(StackFunction::Quaternion)),
+				Some(Function::Stack(StackFunction::Conjugate)),
+				Some(Function::Stack(StackFunction::QuaternionToRotation)),
+				Some(Function::Stack(StackFunction::RotationToQuaternion)),
+				Some(Function::Stack(StackFunction::QuaternionToAxisAngle)),
+				Some(Function::Stack(StackFunction::AxisAngleToQuaternion)),
 			]
 			.to_vec(),
 			FunctionMenu::NewMatrix => [
@@ -452,6 +1474,121 @@ impl FunctionMenu {
 				Some(Function::Stack(StackFunction::IdentityMatrix)),
 			]
 			.to_vec(),
+			FunctionMenu::MatrixEditor => [
+				Some(Function::MatrixEdit(MatrixEditAction::Left)),
+				Some(Function::MatrixEdit(MatrixEditAction::Right)),
+				Some(Function::MatrixEdit(MatrixEditAction::InsertRow)),
+				Some(Function::MatrixEdit(MatrixEditAction::InsertCol)),
+				Some(Function::MatrixEdit(MatrixEditAction::DeleteRow)),
+				Some(Function::MatrixEdit(MatrixEditAction::DeleteCol)),
+			]
+			.to_vec(),
+			FunctionMenu::DataEditor => [
+				Some(Function::MatrixEdit(MatrixEditAction::Left)),
+				Some(Function::MatrixEdit(MatrixEditAction::Right)),
+				Some(Function::MatrixEdit(MatrixEditAction::InsertRow)),
+				Some(Function::MatrixEdit(MatrixEditAction::DeleteRow)),
+			]
+			.to_vec(),
+			FunctionMenu::Solver(count) => (0..*count)
+				.map(|arg| Some(Function::SolverVariable(arg)))
+				.collect(),
+			FunctionMenu::CustomMenu(menu) => match state.custom_menus.get(*menu) {
+				Some(menu) => menu.functions.clone(),
+				None => Vec::new(),
+			},
+			FunctionMenu::Program => state.program_menu.clone(),
+			FunctionMenu::Graph => [
+				Some(Function::Graph),
+				Some(Function::GraphRange),
+				Some(Function::ValueTable),
+				Some(Function::Input(InputEvent::Program)),
+				Some(Function::ProgramLockToggle),
+				Some(Function::ProgramSlotsMenu),
+				Some(Function::LockLocation),
+				Some(Function::ProgramInput),
+				Some(Function::ProgramView),
+				Some(Function::Label),
+				Some(Function::Input(InputEvent::Gto)),
+				Some(Function::ProgramMenu),
+				Some(Function::Flag(FlagOperation::Set)),
+				Some(Function::Flag(FlagOperation::Clear)),
+				Some(Function::Flag(FlagOperation::TestSet)),
+				Some(Function::Flag(FlagOperation::TestClear)),
+				Some(Function::FlagsMenu),
+				Some(Function::Tone),
+				Some(Function::Beep),
+				Some(Function::ExportProgram),
+				Some(Function::CopyProgram),
+				Some(Function::PasteProgram),
+				Some(Function::EditScript),
+				Some(Function::LoadScript),
+				Some(Function::GraphUserFunctionMenu),
+				Some(Function::OverlayUserFunctionMenu),
+				Some(Function::SolverMenu),
+			]
+			.to_vec(),
+			FunctionMenu::Script => SCRIPT_WORDS
+				.iter()
+				.map(|word| Some(Function::ScriptText(word)))
+				.collect(),
+			FunctionMenu::Tape => [Some(Function::ExportTape)].to_vec(),
+			FunctionMenu::Calendar => [
+				Some(Function::CalendarMonths(-12)),
+				Some(Function::CalendarMonths(-1)),
+				Some(Function::CalendarToday),
+				Some(Function::CalendarMonths(1)),
+				Some(Function::CalendarMonths(12)),
+				Some(Function::CalendarPushDate),
+			]
+			.to_vec(),
+		}
+	}
+}
+
+/// Location of a user assigned function. Functions are either assigned to the
+/// top level custom menu or to one of the user defined sub-menus.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CustomSlot {
+	pub menu: Option<usize>,
+	pub idx: usize,
+}
+
+impl CustomSlot {
+	pub fn new(menu: Option<usize>, idx: usize) -> Self {
+		CustomSlot { menu, idx }
+	}
+}
+
+/// Soft key defined by a user program. Pressing the key runs the program starting
+/// at the given label.
+#[derive(PartialEq, Eq, Clone)]
+pub struct ProgramMenuKey {
+	pub label: String,
+	pub target: Location,
+}
+
+struct CustomMenu {
+	name: String,
+	functions: Vec<Option<Function>>,
+}
+
+/// Set of functions assigned to the top level custom menu. Only one bank is
+/// shown at a time, so that keys can be assigned for different kinds of work
+/// and switched between. Sub-menus are shared by all of the banks.
+struct CustomBank {
+	name: String,
+	functions: Vec<Option<Function>>,
+	/// Slots of the bank that are locked
+	locked: Vec<usize>,
+}
+
+impl CustomBank {
+	fn new(name: String) -> Self {
+		CustomBank {
+			name,
+			functions: Vec::new(),
+			locked: Vec::new(),
 		}
 	}
 }
@@ -462,20 +1599,36 @@ pub struct FunctionKeyState {
 	page: usize,
 	menu_stack: Vec<(Option<FunctionMenu>, usize)>,
 	quick_functions: Vec<Option<Function>>,
-	custom_functions: Vec<Option<Function>>,
+	banks: Vec<CustomBank>,
+	active_bank: usize,
+	custom_menus: Vec<CustomMenu>,
+	macros: Vec<Vec<ProgramStep>>,
+	/// Values pushed by custom keys, such as constants used in the lab
+	values: Vec<Value>,
+	program_menu: Vec<Option<Function>>,
+	/// Locked slots of the sub-menus. Locks of the top level slots are kept
+	/// with the bank they are in.
+	locked_slots: Vec<CustomSlot>,
 	menu_strings: RefCell<Vec<String>>,
 	show_empty: bool,
 }
 
 impl FunctionKeyState {
 	pub fn new() -> Self {
+		let banks = vec![CustomBank::new(bank_name(0))];
 		FunctionKeyState {
 			menu: None,
 			functions: Vec::new(),
 			page: 0,
 			menu_stack: Vec::new(),
 			quick_functions: Vec::new(),
-			custom_functions: Vec::new(),
+			banks,
+			active_bank: 0,
+			custom_menus: Vec::new(),
+			macros: Vec::new(),
+			values: Vec::new(),
+			program_menu: Vec::new(),
+			locked_slots: Vec::new(),
 			menu_strings: RefCell::new(Vec::new()),
 			show_empty: false,
 		}
@@ -587,92 +1740,214 @@ impl FunctionKeyState {
 		self.functions.len() > 6
 	}
 
-	pub fn custom_function(&self, idx: usize) -> Option<Function> {
-		if let Some(func) = self.custom_functions.get(idx) {
+	fn custom_functions(&self, menu: Option<usize>) -> Option<&Vec<Option<Function>>> {
+		match menu {
+			Some(menu) => self.custom_menus.get(menu).map(|menu| &menu.functions),
+			None => Some(&self.banks[self.active_bank].functions),
+		}
+	}
+
+	fn custom_functions_mut(&mut self, menu: Option<usize>) -> Option<&mut Vec<Option<Function>>> {
+		match menu {
+			Some(menu) => self
+				.custom_menus
+				.get_mut(menu)
+				.map(|menu| &mut menu.functions),
+			None => Some(&mut self.banks[self.active_bank].functions),
+		}
+	}
+
+	pub fn is_slot_locked(&self, slot: CustomSlot) -> bool {
+		match slot.menu {
+			Some(_) => self.locked_slots.contains(&slot),
+			None => self.banks[self.active_bank].locked.contains(&slot.idx),
+		}
+	}
+
+	/// Locks or unlocks a custom function slot. Locked slots cannot be reassigned
+	/// or removed until they are unlocked.
+	pub fn set_slot_locked(&mut self, slot: CustomSlot, locked: bool) {
+		if slot.menu.is_none() {
+			let bank = &mut self.banks[self.active_bank].locked;
+			if !locked {
+				bank.retain(|idx| *idx != slot.idx);
+			} else if !bank.contains(&slot.idx) {
+				bank.push(slot.idx);
+			}
+		} else if !locked {
+			self.locked_slots.retain(|locked_slot| *locked_slot != slot);
+		} else if !self.locked_slots.contains(&slot) {
+			self.locked_slots.push(slot);
+		}
+	}
+
+	pub fn custom_bank_count(&self) -> usize {
+		self.banks.len()
+	}
+
+	pub fn active_custom_bank(&self) -> usize {
+		self.active_bank
+	}
+
+	pub fn custom_bank_name(&self, bank: usize) -> &str {
+		match self.banks.get(bank) {
+			Some(bank) => &bank.name,
+			None => "",
+		}
+	}
+
+	pub fn set_custom_bank_name(&mut self, name: String) {
+		self.banks[self.active_bank].name = name;
+	}
+
+	/// Shows the functions of another bank in the custom menu
+	pub fn select_custom_bank(&mut self, bank: usize) {
+		if bank < self.banks.len() {
+			self.active_bank = bank;
+			self.refresh_custom_menu();
+		}
+	}
+
+	pub fn next_custom_bank(&mut self) {
+		self.select_custom_bank((self.active_bank + 1) % self.banks.len());
+	}
+
+	/// Adds an empty bank and makes it the active one
+	pub fn new_custom_bank(&mut self) {
+		let name = bank_name(self.banks.len());
+		self.banks.push(CustomBank::new(name));
+		self.select_custom_bank(self.banks.len() - 1);
+	}
+
+	/// Removes a bank along with its assignments. There is always at least one
+	/// bank, and banks with locked slots are kept until they are unlocked.
+	pub fn delete_custom_bank(&mut self, bank: usize) -> Result<()> {
+		let locked = match self.banks.get(bank) {
+			Some(bank) => !bank.locked.is_empty(),
+			None => return Err(Error::InvalidArgument),
+		};
+		if locked {
+			return Err(Error::Locked);
+		}
+		if self.banks.len() == 1 {
+			self.banks[0] = CustomBank::new(bank_name(0));
+		} else {
+			self.banks.remove(bank);
+			if self.active_bank > bank || self.active_bank == self.banks.len() {
+				self.active_bank -= 1;
+			}
+		}
+		self.refresh_custom_menu();
+		Ok(())
+	}
+
+	/// Updates the soft keys if they show the bank that was changed
+	fn refresh_custom_menu(&mut self) {
+		if self.menu == Some(FunctionMenu::Custom) {
+			self.functions = FunctionMenu::Custom.functions(self);
+			let page_count = self.functions.len().div_ceil(6);
+			if self.page >= page_count {
+				self.page = 0;
+			}
+		}
+	}
+
+	pub fn custom_function(&self, slot: CustomSlot) -> Option<Function> {
+		if let Some(func) = self
+			.custom_functions(slot.menu)
+			.and_then(|functions| functions.get(slot.idx))
+		{
 			func.clone()
 		} else {
 			None
 		}
 	}
 
-	pub fn set_custom_function(&mut self, idx: usize, func: Option<Function>) {
-		if let Some(dest) = self.custom_functions.get_mut(idx) {
+	pub fn set_custom_function(&mut self, slot: CustomSlot, func: Option<Function>) {
+		let functions = match self.custom_functions_mut(slot.menu) {
+			Some(functions) => functions,
+			None => return,
+		};
+		if let Some(dest) = functions.get_mut(slot.idx) {
 			*dest = func;
-			while let Some(None) = self.custom_functions.last() {
-				self.custom_functions.pop();
+			while let Some(None) = functions.last() {
+				functions.pop();
 			}
 		} else if func.is_some() {
-			while self.custom_functions.len() < idx {
-				self.custom_functions.push(None);
+			while functions.len() < slot.idx {
+				functions.push(None);
 			}
-			self.custom_functions.push(func);
+			functions.push(func);
 		}
 	}
 
-	pub fn render(&self, screen: &mut dyn Screen) {
-		let top = screen.height() - screen.metrics().height(Font::Smallest);
+	/// Displays a menu defined by a user program on the soft keys. Each key invokes
+	/// a label within the program, and empty entries leave the key unassigned.
+	pub fn show_program_menu(&mut self, keys: Vec<Option<ProgramMenuKey>>) {
+		self.program_menu = keys
+			.into_iter()
+			.map(|key| key.map(Function::ProgramLabel))
+			.collect();
+		self.show_toplevel_menu(FunctionMenu::Program);
+	}
 
-		// Clear menu area
-		let screen_width = screen.width();
-		let mut renderer = screen.renderer(RenderMode::Normal);
-		renderer.erase(&Rect {
-			x: 0,
-			y: top - 1,
-			w: screen_width,
-			h: renderer.metrics().height(Font::Smallest) + 1,
+	/// Creates a new empty user defined sub-menu and returns its index
+	pub fn new_custom_menu(&mut self) -> usize {
+		let idx = self.custom_menus.len();
+		self.custom_menus.push(CustomMenu {
+			name: "Menu ".to_string() + &Number::Integer((idx + 1).into()).to_string(),
+			functions: Vec::new(),
 		});
+		idx
+	}
 
-		// Render each function key display
-		let mut renderer = screen.renderer(RenderMode::FunctionKeys);
-		for i in 0..6 {
-			let min_x = (screen_width - 1) * i / 6;
-			let max_x = (screen_width - 1) * (i + 1) / 6;
-
-			// Render key background
-			renderer.erase(&Rect {
-				x: min_x + 2,
-				y: top,
-				w: max_x - min_x - 3,
-				h: 1,
-			});
-			renderer.erase(&Rect {
-				x: min_x + 1,
-				y: top + 1,
-				w: max_x - min_x - 1,
-				h: renderer.metrics().height(Font::Smallest) - 1,
-			});
-
-			// Render key text if there is one
-			if let Some(string) = self.menu_strings.borrow().get(i as usize) {
-				let mut string = string.clone();
-
-				// Trim string until it fits
-				let mut width = renderer.metrics().width(Font::Smallest, &string);
-				while string.len() > 1 {
-					if width > max_x - min_x {
-						string.pop();
-						width = renderer.metrics().width(Font::Smallest, &string);
-					} else {
-						break;
-					}
-				}
+	/// Keeps a recorded macro and returns the index used to run it. If `slot`
+	/// already holds a macro it is replaced in place.
+	pub fn store_macro(&mut self, slot: CustomSlot, steps: Vec<ProgramStep>) -> usize {
+		if let Some(Function::Macro(idx)) = self.custom_function(slot) {
+			if let Some(dest) = self.macros.get_mut(idx) {
+				*dest = steps;
+				return idx;
+			}
+		}
+		self.macros.push(steps);
+		self.macros.len() - 1
+	}
 
-				// Draw key text centered in button
-				renderer.draw_text(
-					(min_x + max_x) / 2 - (width / 2),
-					top,
-					&string,
-					Font::Smallest,
-					TokenType::Text,
-					&Rect {
-						x: min_x + 1,
-						y: top,
-						w: max_x - min_x - 1,
-						h: renderer.metrics().height(Font::Smallest),
-					},
-				);
+	pub fn macros(&self) -> &[Vec<ProgramStep>] {
+		&self.macros
+	}
+
+	pub fn macro_steps(&self, idx: usize) -> Option<&[ProgramStep]> {
+		self.macros.get(idx).map(|steps| steps.as_slice())
+	}
+
+	/// Keeps a value to push from a custom key and returns its index. If `slot`
+	/// already pushes a value it is replaced in place.
+	pub fn store_value(&mut self, slot: CustomSlot, value: Value) -> usize {
+		if let Some(Function::PushValue(idx)) = self.custom_function(slot) {
+			if let Some(dest) = self.values.get_mut(idx) {
+				*dest = value;
+				return idx;
 			}
 		}
+		self.values.push(value);
+		self.values.len() - 1
+	}
+
+	pub fn custom_value(&self, idx: usize) -> Option<&Value> {
+		self.values.get(idx)
+	}
+
+	pub fn custom_menu_name(&self, menu: usize) -> &str {
+		match self.custom_menus.get(menu) {
+			Some(menu) => &menu.name,
+			None => "",
+		}
+	}
+
+	pub fn render(&self, screen: &mut dyn Screen) {
+		render_function_key_labels(&self.menu_strings.borrow(), screen);
 	}
 
 	pub fn height(&self, screen: &dyn Screen) -> i32 {
@@ -696,8 +1971,120 @@ impl FunctionKeyState {
 		}
 	}
 
-	pub fn show_empty(&self) -> bool {
-		self.show_empty
+	/// Writes the custom assignments, sub-menus, slot locks, macros and values
+	/// into saved state. The first bank is written where older versions expect
+	/// the custom assignments, and all of the banks follow the macros.
+	pub fn save_state(&self, writer: &mut StateWriter) -> Result<()> {
+		writer.record(STATE_RECORD_CUSTOM, |output| {
+			save_functions(&self.banks[0].functions, output)?;
+			output.write_u32(self.custom_menus.len() as u32)?;
+			for menu in &self.custom_menus {
+				write_string(output, &menu.name)?;
+				save_functions(&menu.functions, output)?;
+			}
+			output.write_u32((self.locked_slots.len() + self.banks[0].locked.len()) as u32)?;
+			for idx in &self.banks[0].locked {
+				output.write_u32(u32::MAX)?;
+				output.write_u32(*idx as u32)?;
+			}
+			for slot in &self.locked_slots {
+				output.write_u32(match slot.menu {
+					Some(menu) => menu as u32,
+					None => u32::MAX,
+				})?;
+				output.write_u32(slot.idx as u32)?;
+			}
+			output.write_u32(self.macros.len() as u32)?;
+			for steps in &self.macros {
+				ProgramStep::save_steps(steps, output)?;
+			}
+			output.write_u32(self.active_bank as u32)?;
+			output.write_u32(self.banks.len() as u32)?;
+			for bank in &self.banks {
+				write_string(output, &bank.name)?;
+				save_functions(&bank.functions, output)?;
+				output.write_u32(bank.locked.len() as u32)?;
+				for idx in &bank.locked {
+					output.write_u32(*idx as u32)?;
+				}
+			}
+			output.write_u32(self.values.len() as u32)?;
+			for value in &self.values {
+				write_item(output, |output| serialize_portable(value, output))?;
+			}
+			Ok(())
+		})
+	}
+
+	/// Restores a record written by `save_state`. Returns false if the record
+	/// does not hold custom assignments.
+	pub fn restore_state_record(&mut self, tag: u16, input: &mut DeserializeInput) -> Result<bool> {
+		if tag != STATE_RECORD_CUSTOM {
+			return Ok(false);
+		}
+
+		let mut bank = CustomBank::new(bank_name(0));
+		bank.functions = restore_functions(input)?;
+		self.custom_menus.clear();
+		let count = input.read_u32()?;
+		for _ in 0..count {
+			let name = read_string(input)?;
+			let functions = restore_functions(input)?;
+			self.custom_menus.push(CustomMenu { name, functions });
+		}
+		self.locked_slots.clear();
+		let count = input.read_u32()?;
+		for _ in 0..count {
+			let menu = match input.read_u32()? {
+				u32::MAX => None,
+				menu => Some(menu as usize),
+			};
+			let idx = input.read_u32()? as usize;
+			match menu {
+				Some(_) => self.locked_slots.push(CustomSlot::new(menu, idx)),
+				None => bank.locked.push(idx),
+			}
+		}
+		// Macros are at the end of the record and are missing from older state
+		self.macros.clear();
+		if input.remaining() > 0 {
+			let count = input.read_u32()?;
+			for _ in 0..count {
+				self.macros.push(ProgramStep::restore_steps(input)?);
+			}
+		}
+		// Older state only has the first bank
+		self.banks.clear();
+		self.active_bank = 0;
+		if input.remaining() > 0 {
+			let active = input.read_u32()? as usize;
+			let count = input.read_u32()?;
+			for _ in 0..count {
+				let mut bank = CustomBank::new(read_string(input)?);
+				bank.functions = restore_functions(input)?;
+				let locked = input.read_u32()?;
+				for _ in 0..locked {
+					bank.locked.push(input.read_u32()? as usize);
+				}
+				self.banks.push(bank);
+			}
+			if active < self.banks.len() {
+				self.active_bank = active;
+			}
+		}
+		if self.banks.is_empty() {
+			self.banks.push(bank);
+		}
+		self.values.clear();
+		if input.remaining() > 0 {
+			let count = input.read_u32()?;
+			for _ in 0..count {
+				self.values
+					.push(deserialize_portable(&mut read_item(input)?)?);
+			}
+		}
+		*self.menu_strings.borrow_mut() = Vec::new();
+		Ok(true)
 	}
 
 	pub fn set_show_empty(&mut self, value: bool) {
@@ -707,3 +2094,105 @@ impl FunctionKeyState {
 		*self.menu_strings.borrow_mut() = Vec::new();
 	}
 }
+
+/// Name given to a new bank of custom functions
+fn bank_name(idx: usize) -> String {
+	"Bank ".to_string() + &Number::Integer((idx + 1).into()).to_string()
+}
+
+fn save_functions(functions: &[Option<Function>], output: &mut Vec<u8>) -> Result<()> {
+	output.write_u32(functions.len() as u32)?;
+	for func in functions {
+		Function::save(func.as_ref(), output)?;
+	}
+	Ok(())
+}
+
+fn restore_functions(input: &mut DeserializeInput) -> Result<Vec<Option<Function>>> {
+	let count = input.read_u32()?;
+	let mut result = Vec::new();
+	for _ in 0..count {
+		result.push(Function::restore(input)?);
+	}
+	Ok(result)
+}
+
+/// Number of the soft key whose label is drawn at a point on the screen, from 1
+/// to 6 as in `InputEvent::FunctionKey`
+pub fn function_key_at(x: i32, y: i32, screen: &dyn Screen) -> Option<u8> {
+	let top = screen.height() - screen.metrics().height(Font::Smallest);
+	if y < top - 1 || y >= screen.height() || x < 0 || x >= screen.width() {
+		return None;
+	}
+	Some((x * 6 / screen.width()) as u8 + 1)
+}
+
+/// Draws the labels of the soft keys along the bottom of the screen. Keys past
+/// the end of `labels` are drawn blank.
+pub fn render_function_key_labels(labels: &[String], screen: &mut dyn Screen) {
+	let top = screen.height() - screen.metrics().height(Font::Smallest);
+
+	// Clear menu area
+	let screen_width = screen.width();
+	let mut renderer = screen.renderer(RenderMode::Normal);
+	renderer.erase(&Rect {
+		x: 0,
+		y: top - 1,
+		w: screen_width,
+		h: renderer.metrics().height(Font::Smallest) + 1,
+	});
+
+	// Render each function key display
+	let mut renderer = screen.renderer(RenderMode::FunctionKeys);
+	for i in 0..6 {
+		let min_x = (screen_width - 1) * i / 6;
+		let max_x = (screen_width - 1) * (i + 1) / 6;
+
+		// Render key background
+		renderer.erase(&Rect {
+			x: min_x + 2,
+			y: top,
+			w: max_x - min_x - 3,
+			h: 1,
+		});
+		renderer.erase(&Rect {
+			x: min_x + 1,
+			y: top + 1,
+			w: max_x - min_x - 1,
+			h: renderer.metrics().height(Font::Smallest) - 1,
+		});
+
+		// Render key text if there is one
+		if let Some(string) = labels.get(i as usize) {
+			let mut string = string.as_str();
+
+			// Trim string until it fits
+			let mut width = renderer.metrics().width(Font::Smallest, string);
+			while string.len() > 1 {
+				if width > max_x - min_x {
+					if let Some((end, _)) = string.char_indices().last() {
+						string = &string[..end];
+					}
+					width = renderer.metrics().width(Font::Smallest, string);
+				} else {
+					break;
+				}
+			}
+
+			// Draw key text centered in button
+			renderer.draw_text(
+				(min_x + max_x) / 2 - (width / 2),
+				top,
+				string,
+				Font::Smallest,
+				TokenType::Text,
+				&Rect {
+					x: min_x + 1,
+					y: top,
+					w: max_x - min_x - 1,
+					h: renderer.metrics().height(Font::Smallest),
+				},
+			);
+		}
+	}
+}
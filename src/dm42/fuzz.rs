@@ -0,0 +1,306 @@
+use crate::dm42::edit::{number_inputs, NumberEditor};
+use crate::dm42::feedback::FEEDBACK_EVENTS;
+use crate::dm42::golden::RecordingScreen;
+use crate::dm42::input::InputEvent;
+use crate::dm42::screen::Screen;
+use crate::dm42::state::State;
+use intel_dfp::Decimal;
+use num_bigint::BigInt;
+use rscalc_math::format::{DecimalPointMode, Format, FormatMode};
+use rscalc_math::number::{Number, ToNumber};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Number of random pairs of numbers that the arithmetic properties are checked
+/// on
+const NUMBER_CASES: u64 = 2000;
+
+/// Number of random key sequences run against a fresh state
+const KEY_SEQUENCES: u64 = 300;
+
+const KEYS_PER_SEQUENCE: usize = 80;
+
+/// Keys pressed by the key sequences. Keys that write files, use the clipboard,
+/// play sounds or run programs are left out so that runs don't depend on the
+/// host and finish quickly.
+const FUZZ_KEYS: &[InputEvent] = &[
+	InputEvent::Character('0'),
+	InputEvent::Character('1'),
+	InputEvent::Character('2'),
+	InputEvent::Character('3'),
+	InputEvent::Character('5'),
+	InputEvent::Character('7'),
+	InputEvent::Character('9'),
+	InputEvent::Character('.'),
+	InputEvent::E,
+	InputEvent::Neg,
+	InputEvent::Enter,
+	InputEvent::Backspace,
+	InputEvent::Swap,
+	InputEvent::RotateDown,
+	InputEvent::Undo,
+	InputEvent::Clear,
+	InputEvent::Add,
+	InputEvent::Sub,
+	InputEvent::Mul,
+	InputEvent::Div,
+	InputEvent::Percent,
+	InputEvent::Recip,
+	InputEvent::Pow,
+	InputEvent::Sqrt,
+	InputEvent::Square,
+	InputEvent::Log,
+	InputEvent::TenX,
+	InputEvent::Ln,
+	InputEvent::EX,
+	InputEvent::Pi,
+	InputEvent::Sin,
+	InputEvent::Asin,
+	InputEvent::Cos,
+	InputEvent::Acos,
+	InputEvent::Tan,
+	InputEvent::Atan,
+	InputEvent::Complex,
+	InputEvent::Sto,
+	InputEvent::Rcl,
+	InputEvent::Base,
+	InputEvent::Disp,
+	InputEvent::Modes,
+	InputEvent::Logic,
+	InputEvent::Convert,
+	InputEvent::Up,
+	InputEvent::Down,
+	InputEvent::ShiftUp,
+	InputEvent::ShiftDown,
+	InputEvent::FunctionKey(1, false),
+	InputEvent::FunctionKey(2, false),
+	InputEvent::FunctionKey(3, false),
+	InputEvent::FunctionKey(4, false),
+	InputEvent::FunctionKey(5, false),
+	InputEvent::FunctionKey(6, false),
+	InputEvent::FunctionKey(1, true),
+	InputEvent::FunctionKey(4, true),
+	InputEvent::Exit,
+];
+
+/// Generator of pseudo-random numbers (xorshift64*), so that a failure can be
+/// repeated from its seed
+struct Random(u64);
+
+impl Random {
+	fn new(seed: u64) -> Self {
+		Random(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+	}
+
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+	}
+
+	fn below(&mut self, count: u64) -> u64 {
+		self.next() % count
+	}
+
+	fn range(&mut self, low: i64, high: i64) -> i64 {
+		low + self.below((high - low + 1) as u64) as i64
+	}
+}
+
+/// Makes a number of a random kind. Sizes are kept well below the limits where
+/// exact numbers turn into decimals, so that exact results of one operation are
+/// expected to stay exact.
+fn random_number(random: &mut Random) -> Number {
+	match random.below(5) {
+		0 => random.range(-1000, 1000).to_number(),
+		1 => {
+			let int: BigInt = (random.next() as i64).into();
+			(int * BigInt::from(random.next() as u32)).to_number()
+		}
+		2 => {
+			let denom = random.range(1, 10000).to_number();
+			&random.range(-100000, 100000).to_number() / &denom
+		}
+		_ => {
+			let text = random.range(-999_999_999_999, 999_999_999_999).to_string()
+				+ "E" + &random.range(-40, 40).to_string();
+			Decimal::from_str(&text).to_number()
+		}
+	}
+}
+
+fn is_exact(num: &Number) -> bool {
+	match num {
+		Number::Integer(_) | Number::Rational(_, _) => true,
+		Number::Decimal(_) => false,
+	}
+}
+
+/// True if two numbers have the same value. Text doesn't tell whether a number
+/// was exact, so a decimal is the same as an exact number of equal value.
+fn same(a: &Number, b: &Number) -> bool {
+	if is_exact(a) && is_exact(b) {
+		(a - b).is_zero()
+	} else {
+		let (a, b) = (a.to_decimal(), b.to_decimal());
+		*a == *b || (a.is_nan() && b.is_nan())
+	}
+}
+
+fn check(condition: bool, message: &dyn Fn() -> String) -> Result<(), String> {
+	if condition {
+		Ok(())
+	} else {
+		Err(message())
+	}
+}
+
+fn check_arithmetic(a: &Number, b: &Number) -> Result<(), String> {
+	let show = |op: &str| format!("{} {} {}", a.to_string(), op, b.to_string());
+
+	check(same(&(a + b), &(b + a)), &|| {
+		show("+") + " is not commutative"
+	})?;
+	check(same(&(a * b), &(b * a)), &|| {
+		show("×") + " is not commutative"
+	})?;
+
+	if is_exact(a) && is_exact(b) {
+		let sum = a + b;
+		let difference = a - b;
+		let product = a * b;
+		check(is_exact(&sum), &|| show("+") + " is not exact")?;
+		check(is_exact(&difference), &|| show("-") + " is not exact")?;
+		check(is_exact(&product), &|| show("×") + " is not exact")?;
+		check(same(&(&sum - b), a), &|| {
+			show("+") + " does not undo with -"
+		})?;
+		if !b.is_zero() {
+			let quotient = a / b;
+			check(is_exact(&quotient), &|| show("÷") + " is not exact")?;
+			check(same(&(&quotient * b), a), &|| {
+				show("÷") + " does not undo with ×"
+			})?;
+		}
+	}
+	Ok(())
+}
+
+/// Enters formatted text the way a paste does, through the keys that type it
+fn parse(text: &str, format: &Format) -> Result<Number, String> {
+	let inputs = number_inputs(text, format).map_err(|error| error.to_str().to_string())?;
+	let mut editor = NumberEditor::new(format);
+	for input in inputs {
+		match input {
+			InputEvent::Character(ch) => editor
+				.push_char(ch)
+				.map_err(|error| error.to_str().to_string())?,
+			InputEvent::Neg => editor.neg(),
+			InputEvent::E => editor
+				.exponent()
+				.map_err(|error| error.to_str().to_string())?,
+			_ => return Err(format!("unexpected input {:?}", input)),
+		}
+	}
+	Ok(editor.number())
+}
+
+/// Formats exact integers and decimals shown with all of their digits, and
+/// checks that typing the text back in gives the same number
+fn check_round_trip(num: &Number, decimal_point: DecimalPointMode) -> Result<(), String> {
+	let mut format = Format::new();
+	format.mode = FormatMode::Normal;
+	format.precision = 34;
+	format.decimal_point = decimal_point;
+	let num = match num {
+		Number::Rational(_, _) => return Ok(()),
+		num => num,
+	};
+	let text = format.format_number(num).to_string();
+	let parsed = parse(&text, &format).map_err(|error| {
+		format!(
+			"{} formatted as {} fails to parse: {}",
+			num.to_string(),
+			text,
+			error
+		)
+	})?;
+	check(same(&parsed, num), &|| {
+		format!(
+			"{} formatted as {} parses as {}",
+			num.to_string(),
+			text,
+			parsed.to_string()
+		)
+	})
+}
+
+fn check_numbers(random: &mut Random) -> Result<(), String> {
+	let a = random_number(random);
+	let b = random_number(random);
+	check_arithmetic(&a, &b)?;
+	check_round_trip(&a, DecimalPointMode::Period)?;
+	check_round_trip(&b, DecimalPointMode::Comma)
+}
+
+/// Presses the keys on a fresh state, drawing each frame so that rendering is
+/// checked along with the stack
+fn run_keys(keys: &[InputEvent]) {
+	let mut screen = RecordingScreen::new(400, 240);
+	screen.clear_to_background();
+	let mut state = State::new();
+	state.set_animation_supported(false);
+	for event in FEEDBACK_EVENTS.iter() {
+		state.set_feedback_enabled(*event, false);
+	}
+	for key in keys {
+		if let Err(error) = state.handle_input(*key, &screen) {
+			state.show_error(error);
+			state.hide_toast();
+		}
+		state.render(&mut screen);
+	}
+}
+
+/// Checks properties of the arithmetic on random numbers, then presses random
+/// keys looking for panics. Each failure is printed with the seed that repeats
+/// it. Returns true if nothing failed.
+pub fn run(seed: u64) -> bool {
+	let mut passed = true;
+
+	for case in 0..NUMBER_CASES {
+		let case_seed = seed.wrapping_add(case);
+		let mut random = Random::new(case_seed);
+		match catch_unwind(AssertUnwindSafe(|| check_numbers(&mut random))) {
+			Ok(Ok(())) => (),
+			Ok(Err(message)) => {
+				println!("numbers, seed {}: {}", case_seed, message);
+				passed = false;
+			}
+			Err(_) => {
+				println!("numbers, seed {}: panicked", case_seed);
+				passed = false;
+			}
+		}
+	}
+
+	for sequence in 0..KEY_SEQUENCES {
+		let sequence_seed = seed.wrapping_add(sequence);
+		let mut random = Random::new(sequence_seed ^ 0x6b65_7973);
+		let keys: Vec<InputEvent> = (0..KEYS_PER_SEQUENCE)
+			.map(|_| FUZZ_KEYS[random.below(FUZZ_KEYS.len() as u64) as usize])
+			.collect();
+		if catch_unwind(AssertUnwindSafe(|| run_keys(&keys))).is_err() {
+			println!("keys, seed {}: panicked on {:?}", sequence_seed, keys);
+			passed = false;
+		}
+	}
+
+	if passed {
+		println!(
+			"{} number cases and {} key sequences passed",
+			NUMBER_CASES, KEY_SEQUENCES
+		);
+	}
+	passed
+}
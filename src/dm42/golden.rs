@@ -0,0 +1,431 @@
+use crate::dm42::input::InputEvent;
+use crate::dm42::locale::Language;
+use crate::dm42::screen::{RenderMode, RotatedScreen, Rotation, Screen, ScreenLayoutRenderer};
+use crate::dm42::state::State;
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::Rect;
+use std::path::PathBuf;
+
+/// Size and mounting of the panel that a scene is drawn on
+struct Panel {
+	width: i32,
+	height: i32,
+	rotation: Rotation,
+}
+
+/// Panel of the DM42
+const STANDARD: Panel = Panel {
+	width: 400,
+	height: 240,
+	rotation: Rotation::None,
+};
+
+/// Smaller panel, to check that layouts fit themselves to the screen
+const SMALL: Panel = Panel {
+	width: 320,
+	height: 200,
+	rotation: Rotation::None,
+};
+
+/// Portrait panel that is narrower than the menus of the DM42
+const NARROW: Panel = Panel {
+	width: 240,
+	height: 320,
+	rotation: Rotation::None,
+};
+
+/// Panel of the DM42 mounted on its side, which shows the same picture turned
+const SIDEWAYS: Panel = Panel {
+	width: 240,
+	height: 400,
+	rotation: Rotation::Clockwise,
+};
+
+/// Panel of the DM42 mounted on its other side
+const SIDEWAYS_LEFT: Panel = Panel {
+	width: 240,
+	height: 400,
+	rotation: Rotation::CounterClockwise,
+};
+
+/// Screen that rasterizes into memory so that frames can be compared. It only
+/// has the capabilities of the device screen, so renders are the same as on the
+/// calculator.
+pub(crate) struct RecordingScreen {
+	width: i32,
+	height: i32,
+	pixels: Vec<bool>,
+}
+
+impl RecordingScreen {
+	pub(crate) fn new(width: i32, height: i32) -> Self {
+		RecordingScreen {
+			width,
+			height,
+			pixels: vec![false; (width * height) as usize],
+		}
+	}
+
+	fn pixel(&self, x: i32, y: i32) -> bool {
+		if x < 0 || x >= self.width || y < 0 || y >= self.height {
+			return false;
+		}
+		self.pixels[(y * self.width + x) as usize]
+	}
+
+	fn set(&mut self, x: i32, y: i32, color: bool) {
+		if x < 0 || x >= self.width || y < 0 || y >= self.height {
+			return;
+		}
+		self.pixels[(y * self.width + x) as usize] = color;
+	}
+
+	/// Encodes the frame as a plain PBM image, which can be viewed with most
+	/// image tools and compared as text
+	fn to_pbm(&self) -> String {
+		let mut result = format!("P1\n{} {}\n", self.width, self.height);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				result.push(if self.pixel(x, y) { '1' } else { '0' });
+			}
+			result.push('\n');
+		}
+		result
+	}
+}
+
+impl Screen for RecordingScreen {
+	fn width(&self) -> i32 {
+		self.width
+	}
+
+	fn height(&self) -> i32 {
+		self.height
+	}
+
+	fn clear(&mut self) {
+		for pixel in self.pixels.iter_mut() {
+			*pixel = false;
+		}
+	}
+
+	fn refresh(&mut self) {}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		let rect = rect.clipped_to(&self.screen_rect());
+		for y in rect.y..rect.y + rect.h {
+			for x in rect.x..rect.x + rect.w {
+				self.set(x, y, color);
+			}
+		}
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		for i in 0..width {
+			if bits & (1 << ((width - 1) - i)) != 0 {
+				self.set(x + i as i32, y, color);
+			}
+		}
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+}
+
+/// Name of each golden image, the panel it is drawn on and the input that
+/// leads to it, starting from a fresh state
+const SCENES: &[(&str, &Panel, &[InputEvent])] = &[
+	("empty", &STANDARD, &[]),
+	(
+		"stack",
+		&STANDARD,
+		&[
+			InputEvent::Character('2'),
+			InputEvent::Enter,
+			InputEvent::Character('3'),
+			InputEvent::Add,
+			InputEvent::Character('2'),
+			InputEvent::Sqrt,
+			InputEvent::Character('1'),
+			InputEvent::Character('5'),
+			InputEvent::Enter,
+			InputEvent::Character('7'),
+			InputEvent::Div,
+		],
+	),
+	(
+		"editor",
+		&STANDARD,
+		&[
+			InputEvent::Character('1'),
+			InputEvent::Enter,
+			InputEvent::Character('1'),
+			InputEvent::Character('2'),
+			InputEvent::Character('3'),
+			InputEvent::Character('.'),
+			InputEvent::Character('4'),
+			InputEvent::Character('5'),
+			InputEvent::E,
+			InputEvent::Character('6'),
+		],
+	),
+	("menu", &STANDARD, &[InputEvent::Setup]),
+	(
+		"function_keys",
+		&STANDARD,
+		&[
+			InputEvent::Character('4'),
+			InputEvent::Enter,
+			InputEvent::Base,
+		],
+	),
+	(
+		"small_stack",
+		&SMALL,
+		&[
+			InputEvent::Character('2'),
+			InputEvent::Sqrt,
+			InputEvent::Character('3'),
+			InputEvent::Base,
+		],
+	),
+	("narrow_menu", &NARROW, &[InputEvent::Setup]),
+	(
+		"sideways_stack",
+		&SIDEWAYS,
+		&[
+			InputEvent::Character('2'),
+			InputEvent::Sqrt,
+			InputEvent::Character('3'),
+			InputEvent::Base,
+		],
+	),
+	("sideways_left_menu", &SIDEWAYS_LEFT, &[InputEvent::Setup]),
+	(
+		"touch_stack",
+		&STANDARD,
+		&[
+			InputEvent::Character('1'),
+			InputEvent::Enter,
+			InputEvent::Character('2'),
+			InputEvent::Enter,
+			InputEvent::Character('3'),
+			InputEvent::Touch(200, 145),
+		],
+	),
+	(
+		"touch_menu",
+		&STANDARD,
+		&[InputEvent::Setup, InputEvent::Touch(100, 30)],
+	),
+	(
+		"german_settings",
+		&STANDARD,
+		&[
+			InputEvent::Setup,
+			InputEvent::Character('1'),
+			InputEvent::Character('5'),
+		],
+	),
+	(
+		"data_editor",
+		&STANDARD,
+		&[
+			InputEvent::Stat,
+			InputEvent::FunctionKey(3, false),
+			InputEvent::Character('3'),
+			InputEvent::Enter,
+			InputEvent::Character('4'),
+			InputEvent::Enter,
+			InputEvent::FunctionKey(3, false),
+			InputEvent::Character('5'),
+		],
+	),
+	(
+		"function_table",
+		&STANDARD,
+		&[
+			InputEvent::Character('3'),
+			InputEvent::Program,
+			InputEvent::Square,
+			InputEvent::Character('2'),
+			InputEvent::Sub,
+			InputEvent::Program,
+			InputEvent::Character('1'),
+			InputEvent::ProgramFunc,
+			InputEvent::FunctionKey(3, false),
+			InputEvent::Down,
+		],
+	),
+	(
+		"program_slots",
+		&STANDARD,
+		&[
+			InputEvent::Character('3'),
+			InputEvent::Program,
+			InputEvent::Square,
+			InputEvent::Program,
+			InputEvent::ProgramFunc,
+			InputEvent::FunctionKey(6, false),
+			InputEvent::Down,
+			InputEvent::Enter,
+		],
+	),
+	(
+		"mode_tags",
+		&STANDARD,
+		&[
+			InputEvent::Setup,
+			InputEvent::Character('1'),
+			InputEvent::Character('1'),
+			InputEvent::Character('6'),
+			InputEvent::Exit,
+			InputEvent::Exit,
+			InputEvent::Exit,
+			InputEvent::Character('1'),
+			InputEvent::Modes,
+			InputEvent::FunctionKey(2, false),
+			InputEvent::Sin,
+			InputEvent::Character('2'),
+			InputEvent::Character('6'),
+			InputEvent::Base,
+			InputEvent::FunctionKey(3, false),
+		],
+	),
+	(
+		"radicals",
+		&STANDARD,
+		&[
+			InputEvent::Setup,
+			InputEvent::Character('1'),
+			InputEvent::Character('1'),
+			InputEvent::Character('7'),
+			InputEvent::Exit,
+			InputEvent::Exit,
+			InputEvent::Exit,
+			InputEvent::Character('8'),
+			InputEvent::Sqrt,
+			InputEvent::Character('1'),
+			InputEvent::Character('2'),
+			InputEvent::Sqrt,
+			InputEvent::Character('3'),
+			InputEvent::Sqrt,
+			InputEvent::Character('2'),
+			InputEvent::Sqrt,
+			InputEvent::Character('3'),
+			InputEvent::Mul,
+		],
+	),
+	(
+		"stack_effect_preview",
+		&STANDARD,
+		&[
+			InputEvent::Character('2'),
+			InputEvent::Character('5'),
+			InputEvent::Character('5'),
+			InputEvent::Enter,
+			InputEvent::Character('4'),
+			InputEvent::Catalog,
+			InputEvent::Character('2'),
+			InputEvent::Down,
+			InputEvent::Down,
+			InputEvent::Down,
+			InputEvent::Down,
+			InputEvent::Down,
+			InputEvent::Down,
+		],
+	),
+	(
+		"entry_rejection",
+		&STANDARD,
+		&[
+			InputEvent::Character('1'),
+			InputEvent::Character('.'),
+			InputEvent::Character('5'),
+			InputEvent::Character('.'),
+		],
+	),
+];
+
+fn golden_dir() -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+/// Renders that differ from their golden image are written to a directory in
+/// the system's temporary directory, so that failed checks leave nothing in the
+/// source tree
+fn actual_dir() -> PathBuf {
+	std::env::temp_dir().join("rscalc-golden")
+}
+
+fn render_scene(panel: &Panel, inputs: &[InputEvent]) -> String {
+	let mut screen = RotatedScreen::new(
+		RecordingScreen::new(panel.width, panel.height),
+		panel.rotation,
+	);
+	screen.clear_to_background();
+	let mut state = State::new();
+	state.set_animation_supported(false);
+	// The language is global, so a scene that changes it must not change the
+	// ones after it
+	state.set_language(Language::English);
+	state.render(&mut screen);
+	for input in inputs {
+		if let Err(error) = state.handle_input(*input, &screen) {
+			state.show_error(error);
+		}
+		state.render(&mut screen);
+	}
+
+	// The status bar shows the time, so it would never match
+	let status_bar = Rect {
+		x: 0,
+		y: 0,
+		w: screen.width(),
+		h: screen.metrics().height(Font::Smallest) + 1,
+	};
+	screen.fill(&status_bar, false);
+	screen.panel().to_pbm()
+}
+
+/// Renders each scene and compares it to its golden image in the `golden`
+/// directory. When `update` is set the golden images are written instead. A
+/// scene that does not match is written to `actual_dir`. Returns true if all
+/// scenes matched.
+pub fn run(update: bool) -> bool {
+	let dir = golden_dir();
+	let mut passed = true;
+	for (name, panel, inputs) in SCENES {
+		let frame = render_scene(panel, inputs);
+		let path = dir.join(name.to_string() + ".pbm");
+		let actual_path = actual_dir().join(name.to_string() + ".pbm");
+		if update {
+			if let Err(error) =
+				std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &frame))
+			{
+				eprintln!("{}: failed to write golden image: {}", name, error);
+				passed = false;
+			}
+			continue;
+		}
+
+		match std::fs::read_to_string(&path) {
+			Ok(golden) if golden == frame => {
+				let _ = std::fs::remove_file(&actual_path);
+				println!("{}: ok", name);
+			}
+			Ok(_) => {
+				let _ = std::fs::create_dir_all(actual_dir())
+					.and_then(|_| std::fs::write(&actual_path, &frame));
+				println!("{}: differs, see {}", name, actual_path.display());
+				passed = false;
+			}
+			Err(_) => {
+				println!("{}: no golden image, run with --update-golden", name);
+				passed = false;
+			}
+		}
+	}
+	passed
+}
@@ -0,0 +1,915 @@
+use crate::dm42::flags::FLAG_DIGITS;
+use crate::dm42::font::bitmap_font;
+use crate::dm42::functions::Function;
+use crate::dm42::input::InputEvent;
+use crate::dm42::screen::{theme, Color, Screen};
+use crate::dm42::state::{State, MAX_MEMORY_INDEX_DIGITS};
+use core::convert::TryFrom;
+use intel_dfp::Decimal;
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::Rect;
+use rscalc_math::context::Location;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::format::Format;
+use rscalc_math::histogram::{Histogram, MAX_BINS};
+use rscalc_math::number::{Number, ToNumber};
+use rscalc_math::persist::{read_item, write_item};
+use rscalc_math::solver::find_root;
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Number of pixels the trace cursor moves when shift is held
+const TRACE_FAST_STEP: i32 = 8;
+
+/// Size of the step used to find the slope of a curve, relative to the width
+/// of a column of the plot
+const SLOPE_STEP: &str = "1e-6";
+
+/// Point of a plotted function that can be searched for near the trace cursor
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GraphAnalysis {
+	Root,
+	Minimum,
+	Maximum,
+	/// Point where the function crosses the function overlaid on it
+	Intersection,
+}
+
+/// Analyses run by the function keys F1 to F4 in the graph view
+pub const GRAPH_ANALYSES: [GraphAnalysis; 4] = [
+	GraphAnalysis::Root,
+	GraphAnalysis::Minimum,
+	GraphAnalysis::Maximum,
+	GraphAnalysis::Intersection,
+];
+
+impl GraphAnalysis {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			GraphAnalysis::Root => "Root",
+			GraphAnalysis::Minimum => "Min",
+			GraphAnalysis::Maximum => "Max",
+			GraphAnalysis::Intersection => "Intersect",
+		}
+	}
+}
+
+/// A single recorded step of a keystroke program. Function keys are recorded
+/// as the function they were mapped to at the time of recording, so that the
+/// program does not depend on the function key menu state when it is run.
+#[derive(Clone)]
+pub enum ProgramStep {
+	Input(InputEvent),
+	Function(Function),
+}
+
+impl ProgramStep {
+	/// Writes a list of steps into saved state
+	pub fn save_steps(steps: &[ProgramStep], output: &mut Vec<u8>) -> Result<()> {
+		output.write_u32(steps.len() as u32)?;
+		for step in steps {
+			write_item(output, |output| match step {
+				ProgramStep::Input(input) => {
+					output.write_u8(0)?;
+					input.save(output)
+				}
+				ProgramStep::Function(func) => {
+					output.write_u8(1)?;
+					Function::save(Some(func), output)
+				}
+			})?;
+		}
+		Ok(())
+	}
+
+	/// Reads a list of steps written by `save_steps`. Steps that are not known to
+	/// this version are left out.
+	pub fn restore_steps(input: &mut DeserializeInput) -> Result<Vec<ProgramStep>> {
+		let count = input.read_u32()?;
+		let mut steps = Vec::new();
+		for _ in 0..count {
+			let mut step = read_item(input)?;
+			let step = match step.read_u8()? {
+				0 => InputEvent::restore(&mut step)?.map(ProgramStep::Input),
+				1 => Function::restore(&mut step)?.map(ProgramStep::Function),
+				_ => None,
+			};
+			if let Some(step) = step {
+				steps.push(step);
+			}
+		}
+		Ok(steps)
+	}
+
+	/// Number of steps that make up the instruction at the start of `steps`,
+	/// which are skipped together when a test fails. Numbers are recorded as a
+	/// step for each key, and the location or flag that a function works on is
+	/// recorded as the keys that chose it.
+	pub fn instruction_len(steps: &[ProgramStep]) -> usize {
+		match steps.first() {
+			None => 0,
+			Some(ProgramStep::Input(InputEvent::Character(ch)))
+				if ch.is_ascii_digit() || *ch == '.' =>
+			{
+				Self::number_len(steps)
+			}
+			Some(ProgramStep::Input(InputEvent::E)) => Self::number_len(steps),
+			Some(ProgramStep::Input(InputEvent::Sto)) => {
+				// An arithmetic key before the location selects register arithmetic
+				let operation = match steps.get(1) {
+					Some(ProgramStep::Input(InputEvent::Add))
+					| Some(ProgramStep::Input(InputEvent::Sub))
+					| Some(ProgramStep::Input(InputEvent::Mul))
+					| Some(ProgramStep::Input(InputEvent::Div)) => 1,
+					_ => 0,
+				};
+				1 + operation + Self::location_len(&steps[1 + operation..])
+			}
+			Some(ProgramStep::Input(InputEvent::Rcl))
+			| Some(ProgramStep::Input(InputEvent::Gto))
+			| Some(ProgramStep::Function(Function::LockLocation))
+			| Some(ProgramStep::Function(Function::ProgramInput))
+			| Some(ProgramStep::Function(Function::Label)) => 1 + Self::location_len(&steps[1..]),
+			Some(ProgramStep::Function(Function::Flag(_))) => 1 + Self::flag_len(&steps[1..]),
+			Some(_) => 1,
+		}
+	}
+
+	/// Finds the label in `steps`, giving the position of the step after it
+	pub fn find_label(steps: &[ProgramStep], label: &Location) -> Option<usize> {
+		let mut idx = 0;
+		while idx < steps.len() {
+			let len = Self::instruction_len(&steps[idx..]);
+			if let ProgramStep::Function(Function::Label) = &steps[idx] {
+				if Self::label_location(&steps[idx + 1..idx + len]).as_ref() == Some(label) {
+					return Some(idx + len);
+				}
+			}
+			idx += len;
+		}
+		None
+	}
+
+	/// Labels in `steps`, in the order that they appear
+	pub fn labels(steps: &[ProgramStep]) -> Vec<Location> {
+		let mut labels = Vec::new();
+		let mut idx = 0;
+		while idx < steps.len() {
+			let len = Self::instruction_len(&steps[idx..]);
+			if let ProgramStep::Function(Function::Label) = &steps[idx] {
+				if let Some(label) = Self::label_location(&steps[idx + 1..idx + len]) {
+					if !labels.contains(&label) {
+						labels.push(label);
+					}
+				}
+			}
+			idx += len;
+		}
+		labels
+	}
+
+	/// Name of a label from the keys that were recorded after it
+	fn label_location(steps: &[ProgramStep]) -> Option<Location> {
+		let mut digits: Vec<usize> = Vec::new();
+		for step in steps {
+			match step {
+				ProgramStep::Input(InputEvent::Character(ch)) if ch.is_ascii_digit() => {
+					digits.push(*ch as usize - '0' as usize);
+					if digits.len() >= MAX_MEMORY_INDEX_DIGITS {
+						break;
+					}
+				}
+				ProgramStep::Input(InputEvent::Character(ch)) if digits.is_empty() => {
+					return Some(Location::Variable(*ch));
+				}
+				ProgramStep::Input(InputEvent::Backspace) => {
+					digits.pop();
+				}
+				ProgramStep::Input(InputEvent::Enter) => break,
+				_ => return None,
+			}
+		}
+		if digits.is_empty() {
+			return None;
+		}
+		Some(Location::Integer(
+			digits.iter().fold(0, |value, digit| value * 10 + digit),
+		))
+	}
+
+	/// Number of steps at the start of `steps` that type a number
+	pub fn number_len(steps: &[ProgramStep]) -> usize {
+		steps
+			.iter()
+			.take_while(|step| match step {
+				ProgramStep::Input(InputEvent::Character(ch)) => ch.is_ascii_digit() || *ch == '.',
+				ProgramStep::Input(InputEvent::E)
+				| ProgramStep::Input(InputEvent::Neg)
+				| ProgramStep::Input(InputEvent::Backspace) => true,
+				_ => false,
+			})
+			.count()
+	}
+
+	/// Number of steps used to choose a location, following the rules of the
+	/// location entry of the state
+	fn location_len(steps: &[ProgramStep]) -> usize {
+		let mut stack = false;
+		let mut digits = 0;
+		for (idx, step) in steps.iter().enumerate() {
+			match step {
+				ProgramStep::Input(InputEvent::Character(ch)) if ch.is_ascii_digit() => {
+					digits += 1;
+					if !stack && digits >= MAX_MEMORY_INDEX_DIGITS {
+						return idx + 1;
+					}
+				}
+				ProgramStep::Input(InputEvent::Character('.')) => stack = true,
+				// Rcl before the location makes the entry indirect
+				ProgramStep::Input(InputEvent::Rcl) if digits == 0 && !stack => (),
+				ProgramStep::Input(InputEvent::Backspace) => (),
+				_ => return idx + 1,
+			}
+		}
+		steps.len()
+	}
+
+	fn flag_len(steps: &[ProgramStep]) -> usize {
+		let mut digits = 0;
+		for (idx, step) in steps.iter().enumerate() {
+			match step {
+				ProgramStep::Input(InputEvent::Character(ch)) if ch.is_ascii_digit() => {
+					digits += 1;
+					if digits >= FLAG_DIGITS {
+						return idx + 1;
+					}
+				}
+				ProgramStep::Input(InputEvent::Backspace) => (),
+				_ => return idx + 1,
+			}
+		}
+		steps.len()
+	}
+}
+
+/// Data that is plotted by a graph
+enum GraphSource {
+	/// Recorded program y=f(x)
+	Program(Vec<ProgramStep>),
+
+	/// Data points along with the slope and intercept of the least squares
+	/// regression line through them, if there is one
+	Scatter(Vec<(Decimal, Decimal)>, Option<(Decimal, Decimal)>),
+
+	/// Data set along with the counts of its values in each bin
+	Histogram(Vec<Decimal>, Histogram),
+}
+
+/// Plot of a recorded program y=f(x) over a range of x values, of a set of
+/// data points and the line fitted to them, or of a histogram of a data set
+pub struct Graph {
+	source: GraphSource,
+	/// Second program plotted along with a program, so that the points where
+	/// they cross can be found
+	overlay: Option<Vec<ProgramStep>>,
+	x_min: Decimal,
+	x_max: Decimal,
+	y_min: Decimal,
+	y_max: Decimal,
+	auto_scale: bool,
+	samples: Vec<Option<Decimal>>,
+	overlay_samples: Vec<Option<Decimal>>,
+	trace: i32,
+	/// Point found by the last analysis, shown until the trace cursor moves
+	found: Option<(GraphAnalysis, Decimal, Decimal)>,
+}
+
+impl Graph {
+	pub fn new(program: Vec<ProgramStep>, x_min: Decimal, x_max: Decimal) -> Self {
+		Graph {
+			source: GraphSource::Program(program),
+			overlay: None,
+			x_min,
+			x_max,
+			y_min: (-1).into(),
+			y_max: 1.into(),
+			auto_scale: true,
+			samples: Vec::new(),
+			overlay_samples: Vec::new(),
+			trace: 0,
+			found: None,
+		}
+	}
+
+	/// Creates a scatter plot of `points`, with the range set to fit all of them
+	pub fn new_scatter(points: Vec<(Decimal, Decimal)>) -> Self {
+		let fit = Self::linear_fit(&points);
+		let (x_min, x_max) = match Self::range(points.iter().map(|point| &point.0)) {
+			Some((min, max)) => Self::with_margin(min, max),
+			None => ((-1).into(), 1.into()),
+		};
+		Graph {
+			source: GraphSource::Scatter(points, fit),
+			overlay: None,
+			x_min,
+			x_max,
+			y_min: (-1).into(),
+			y_max: 1.into(),
+			auto_scale: true,
+			samples: Vec::new(),
+			overlay_samples: Vec::new(),
+			trace: 0,
+			found: None,
+		}
+	}
+
+	/// Creates a histogram of `values` with the given number of bins, with the
+	/// range set to fit all of the bins
+	pub fn new_histogram(values: Vec<Decimal>, bins: usize) -> Result<Self> {
+		let histogram = Histogram::new(&values, bins)?;
+		let (x_min, x_max) = Self::with_margin(histogram.min().clone(), histogram.max().clone());
+		Ok(Graph {
+			source: GraphSource::Histogram(values, histogram),
+			overlay: None,
+			x_min,
+			x_max,
+			y_min: (-1).into(),
+			y_max: 1.into(),
+			auto_scale: true,
+			samples: Vec::new(),
+			overlay_samples: Vec::new(),
+			trace: 0,
+			found: None,
+		})
+	}
+
+	/// Changes the number of bins of a histogram by `delta`, staying within the
+	/// allowed range. Other graphs are left as they are.
+	pub fn change_bins(&mut self, delta: isize) -> Result<()> {
+		if let GraphSource::Histogram(values, histogram) = &mut self.source {
+			let bins = histogram.bins() as isize + delta;
+			if bins >= 1 && bins <= MAX_BINS as isize {
+				*histogram = Histogram::new(values, bins as usize)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Computes the slope and intercept of the least squares regression line
+	fn linear_fit(points: &[(Decimal, Decimal)]) -> Option<(Decimal, Decimal)> {
+		let mut sum_x = Decimal::zero();
+		let mut sum_y = Decimal::zero();
+		let mut sum_xy = Decimal::zero();
+		let mut sum_xx = Decimal::zero();
+		for (x, y) in points {
+			sum_x += x.clone();
+			sum_y += y.clone();
+			sum_xy += x * y;
+			sum_xx += x * x;
+		}
+
+		let n: Decimal = (points.len() as u64).into();
+		let denom = &(&n * &sum_xx) - &(&sum_x * &sum_x);
+		if points.len() < 2 || denom == Decimal::zero() {
+			return None;
+		}
+
+		let slope = &(&(&n * &sum_xy) - &(&sum_x * &sum_y)) / &denom;
+		let intercept = &(&sum_y - &(&slope * &sum_x)) / &n;
+		Some((slope, intercept))
+	}
+
+	/// Evaluates the plotted function for every column of the plot, then updates
+	/// the vertical range if auto scaling is enabled. The evaluator is used to run
+	/// recorded programs.
+	pub fn sample(&mut self, evaluator: &mut State, screen: &dyn Screen) {
+		let width = screen.width();
+		let step = &(&self.x_max - &self.x_min) / &(width - 1).into();
+
+		self.samples.clear();
+		self.overlay_samples.clear();
+		self.found = None;
+		for col in 0..width {
+			let x = &self.x_min + &(&step * &col.into());
+			if let Some(overlay) = &self.overlay {
+				let y = Self::evaluate(overlay, &x, evaluator, screen).ok();
+				self.overlay_samples.push(y.filter(|y| y.is_finite()));
+			}
+			let y = match &self.source {
+				GraphSource::Program(program) => {
+					Self::evaluate(program, &x, evaluator, screen).ok()
+				}
+				GraphSource::Scatter(_, Some((slope, intercept))) => {
+					Some(&(slope * &x) + intercept)
+				}
+				GraphSource::Scatter(_, None) => None,
+				GraphSource::Histogram(_, histogram) => histogram
+					.bin_at(&x)
+					.map(|bin| (histogram.counts()[bin] as u64).into()),
+			};
+			self.samples.push(y.filter(|y| y.is_finite()));
+		}
+
+		if self.trace >= width {
+			self.trace = width / 2;
+		}
+
+		if self.auto_scale {
+			self.auto_scale_range();
+		}
+	}
+
+	/// Runs a plotted program for the value `x`, failing if it doesn't give a
+	/// number
+	fn evaluate(
+		program: &[ProgramStep],
+		x: &Decimal,
+		evaluator: &mut State,
+		screen: &dyn Screen,
+	) -> Result<Decimal> {
+		match evaluator.run_program(program, Value::Number(Number::Decimal(x.clone())), screen)? {
+			Value::Number(result) | Value::NumberWithUnit(result, _) => {
+				Ok(result.to_decimal().into_owned())
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Plots a second program along with the program of this graph, replacing
+	/// any that was plotted before. Other graphs can't have one.
+	pub fn set_overlay(
+		&mut self,
+		program: Vec<ProgramStep>,
+		evaluator: &mut State,
+		screen: &dyn Screen,
+	) -> Result<()> {
+		match self.source {
+			GraphSource::Program(_) => {
+				self.overlay = Some(program);
+				self.sample(evaluator, screen);
+				Ok(())
+			}
+			_ => Err(Error::DataTypeMismatch),
+		}
+	}
+
+	/// Searches for a point of the plotted program near the trace cursor, moving
+	/// the cursor to it and returning its coordinates. The samples of the plot
+	/// are used to find where to start the solver, so that the nearest point is
+	/// found rather than whichever one the solver reaches first.
+	pub fn analyze(
+		&mut self,
+		analysis: GraphAnalysis,
+		evaluator: &mut State,
+		screen: &dyn Screen,
+	) -> Result<(Decimal, Decimal)> {
+		let program = match &self.source {
+			GraphSource::Program(program) => program.clone(),
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		let start = self.trace as usize;
+		let (x, y) = match analysis {
+			GraphAnalysis::Root => {
+				let col = Self::nearest_sign_change(&self.samples, start).unwrap_or(start);
+				let x = find_root(
+					&mut |x| Self::evaluate(&program, x, evaluator, screen),
+					&self.column_x(col),
+				)?;
+				let y = Self::evaluate(&program, &x, evaluator, screen)?;
+				(x, y)
+			}
+			GraphAnalysis::Minimum | GraphAnalysis::Maximum => {
+				let maximum = analysis == GraphAnalysis::Maximum;
+				let col = Self::nearest_extremum(&self.samples, start, maximum)
+					.ok_or(Error::NoSolution)?;
+				let step =
+					&(&self.column_x(1) - &self.column_x(0)) * &Decimal::from_str(SLOPE_STEP);
+				let two: Decimal = 2.into();
+				let x = find_root(
+					&mut |x| {
+						let after = Self::evaluate(&program, &(x + &step), evaluator, screen)?;
+						let before = Self::evaluate(&program, &(x - &step), evaluator, screen)?;
+						Ok(&(&after - &before) / &(&step * &two))
+					},
+					&self.column_x(col),
+				)?;
+				// The slope is also zero at other extremes and at inflections, so
+				// the solver must stay between the columns next to the extreme
+				if x < self.column_x(col - 1) || x > self.column_x(col + 1) {
+					return Err(Error::NoSolution);
+				}
+				let y = Self::evaluate(&program, &x, evaluator, screen)?;
+				(x, y)
+			}
+			GraphAnalysis::Intersection => {
+				let overlay = self.overlay.clone().ok_or(Error::ValueNotDefined)?;
+				let differences: Vec<Option<Decimal>> = self
+					.samples
+					.iter()
+					.zip(self.overlay_samples.iter())
+					.map(|samples| match samples {
+						(Some(a), Some(b)) => Some(a - b),
+						_ => None,
+					})
+					.collect();
+				let col = Self::nearest_sign_change(&differences, start).unwrap_or(start);
+				let x = find_root(
+					&mut |x| {
+						let a = Self::evaluate(&program, x, evaluator, screen)?;
+						let b = Self::evaluate(&overlay, x, evaluator, screen)?;
+						Ok(&a - &b)
+					},
+					&self.column_x(col),
+				)?;
+				let y = Self::evaluate(&program, &x, evaluator, screen)?;
+				(x, y)
+			}
+		};
+
+		if x >= self.x_min && x <= self.x_max {
+			if let Some(col) =
+				Self::to_pixel(&x, &self.x_min, &self.x_max, self.samples.len() as i32)
+			{
+				self.trace = col;
+			}
+		}
+		self.found = Some((analysis, x.clone(), y.clone()));
+		Ok((x, y))
+	}
+
+	/// Column where the values change sign that is nearest to `start`
+	fn nearest_sign_change(values: &[Option<Decimal>], start: usize) -> Option<usize> {
+		let zero = Decimal::zero();
+		let changes_sign = |col: usize| match (values.get(col), values.get(col + 1)) {
+			(Some(Some(a)), Some(Some(b))) => (a < &zero) != (b < &zero) || a == &zero,
+			_ => false,
+		};
+		(0..values.len())
+			.flat_map(|offset| {
+				core::iter::once(start + offset)
+					.chain(core::iter::once(start.wrapping_sub(offset + 1)))
+			})
+			.find(|col| changes_sign(*col))
+	}
+
+	/// Column of the lowest or highest sample reached by moving from `start`
+	/// towards lower or higher samples. There is none if the samples keep going
+	/// down (or up) to the edge of the plot.
+	fn nearest_extremum(values: &[Option<Decimal>], start: usize, maximum: bool) -> Option<usize> {
+		let better = |a: &Decimal, b: &Decimal| if maximum { a > b } else { a < b };
+		let mut col = start;
+		loop {
+			let value = values.get(col)?.as_ref()?;
+			let neighbors = [col.wrapping_sub(1), col + 1];
+			let next = neighbors
+				.iter()
+				.filter_map(|next| Some((*next, values.get(*next)?.as_ref()?)))
+				.filter(|(_, next_value)| better(next_value, value))
+				.fold(None, |best: Option<(usize, &Decimal)>, next| match best {
+					Some(best) if !better(next.1, best.1) => Some(best),
+					_ => Some(next),
+				});
+			match next {
+				Some((next, _)) if next == 0 || next + 1 == values.len() => return None,
+				Some((next, _)) => col = next,
+				None if col == 0 || col + 1 == values.len() => return None,
+				None => return Some(col),
+			}
+		}
+	}
+
+	fn column_x(&self, col: usize) -> Decimal {
+		let columns = core::cmp::max(1, self.samples.len() as i32 - 1);
+		let step = &(&self.x_max - &self.x_min) / &columns.into();
+		&self.x_min + &(&step * &(col as i32).into())
+	}
+
+	/// Finds the minimum and maximum of a set of values
+	fn range<'a>(values: impl Iterator<Item = &'a Decimal>) -> Option<(Decimal, Decimal)> {
+		let mut range: Option<(Decimal, Decimal)> = None;
+		for value in values {
+			range = Some(match range {
+				Some((min, max)) => (
+					if value < &min { value.clone() } else { min },
+					if value > &max { value.clone() } else { max },
+				),
+				None => (value.clone(), value.clone()),
+			});
+		}
+		range
+	}
+
+	/// Expands a range by a small margin on each side so that values at the
+	/// edges remain visible
+	fn with_margin(min: Decimal, max: Decimal) -> (Decimal, Decimal) {
+		if min == max {
+			// Single value, center it on the screen
+			(&min - &1.into(), &max + &1.into())
+		} else {
+			let margin = &(&max - &min) / &10.into();
+			(&min - &margin, &max + &margin)
+		}
+	}
+
+	fn auto_scale_range(&mut self) {
+		// Scatter plots always include all of the data points in the range
+		let range = match &self.source {
+			GraphSource::Program(_) => Self::range(self.samples.iter().flatten()),
+			GraphSource::Scatter(points, _) => Self::range(
+				self.samples
+					.iter()
+					.flatten()
+					.chain(points.iter().map(|point| &point.1)),
+			),
+			// Bars start at zero, so zero is always in the range
+			GraphSource::Histogram(_, _) => {
+				let zero = Decimal::zero();
+				Self::range(self.samples.iter().flatten().chain(core::iter::once(&zero)))
+			}
+		};
+
+		let (min, max) = match range {
+			Some((min, max)) => Self::with_margin(min, max),
+			None => ((-1).into(), 1.into()),
+		};
+		self.y_min = min;
+		self.y_max = max;
+	}
+
+	/// Scales the visible range by `factor` around its center
+	pub fn zoom(&mut self, factor: Decimal) {
+		Self::zoom_range(&mut self.x_min, &mut self.x_max, &factor);
+		if !self.auto_scale {
+			Self::zoom_range(&mut self.y_min, &mut self.y_max, &factor);
+		}
+	}
+
+	fn zoom_range(min: &mut Decimal, max: &mut Decimal, factor: &Decimal) {
+		let center = &(&*min + &*max) / &2.into();
+		let half = &(&(&*max - &*min) / &2.into()) * factor;
+		*min = &center - &half;
+		*max = &center + &half;
+	}
+
+	/// Moves the horizontal range by a quarter of its width in the given direction
+	pub fn pan_x(&mut self, direction: i32) {
+		let offset = &(&(&self.x_max - &self.x_min) / &4.into()) * &direction.into();
+		self.x_min = &self.x_min + &offset;
+		self.x_max = &self.x_max + &offset;
+	}
+
+	/// Moves the vertical range by a quarter of its height in the given direction.
+	/// This disables auto scaling until it is reset.
+	pub fn pan_y(&mut self, direction: i32) {
+		let offset = &(&(&self.y_max - &self.y_min) / &4.into()) * &direction.into();
+		self.y_min = &self.y_min + &offset;
+		self.y_max = &self.y_max + &offset;
+		self.auto_scale = false;
+	}
+
+	pub fn reset_scale(&mut self) {
+		self.auto_scale = true;
+		self.auto_scale_range();
+	}
+
+	pub fn move_trace(&mut self, offset: i32, fast: bool, screen: &dyn Screen) {
+		let offset = if fast {
+			offset * TRACE_FAST_STEP
+		} else {
+			offset
+		};
+		self.trace = core::cmp::max(0, core::cmp::min(screen.width() - 1, self.trace + offset));
+		self.found = None;
+	}
+
+	fn trace_x(&self) -> Decimal {
+		self.column_x(self.trace as usize)
+	}
+
+	/// Value of the curve at the trace cursor, if it is defined
+	pub fn trace_value(&self) -> Option<Decimal> {
+		self.samples.get(self.trace as usize).cloned().flatten()
+	}
+
+	fn to_pixel(value: &Decimal, min: &Decimal, max: &Decimal, size: i32) -> Option<i32> {
+		let scaled = &(&(value - min) * &(size - 1).into()) / &(max - min);
+		let scaled = Number::Decimal(scaled);
+		let int = scaled.to_int().ok()?;
+		i32::try_from(&*int).ok()
+	}
+
+	fn row(&self, value: &Decimal, plot: &Rect) -> Option<i32> {
+		let row = Self::to_pixel(value, &self.y_min, &self.y_max, plot.h)?;
+		Some(plot.y + plot.h - 1 - row)
+	}
+
+	/// Draws the samples of a curve, connecting adjacent samples with vertical
+	/// segments so that steep sections remain continuous. A dotted curve is drawn
+	/// in every other column, so that it can be told apart from a solid one.
+	fn draw_curve(
+		&self,
+		screen: &mut dyn Screen,
+		samples: &[Option<Decimal>],
+		plot: &Rect,
+		dotted: bool,
+	) {
+		let color = theme().color(Color::ContentText);
+		let mut prev_row: Option<i32> = None;
+		for (col, sample) in samples.iter().enumerate() {
+			let row = match sample {
+				Some(value) => self
+					.row(value, plot)
+					.map(|row| core::cmp::max(plot.y - 1, core::cmp::min(plot.y + plot.h, row))),
+				None => None,
+			};
+			match row {
+				Some(row) if !dotted || col % 2 == 0 => {
+					let (top, bottom) = match prev_row {
+						Some(prev) => (core::cmp::min(prev, row), core::cmp::max(prev, row)),
+						None => (row, row),
+					};
+					let segment = Rect {
+						x: plot.x + col as i32,
+						y: top,
+						w: 1,
+						h: bottom - top + 1,
+					}
+					.clipped_to(plot);
+					if segment.w > 0 && segment.h > 0 {
+						screen.fill(&segment, color);
+					}
+				}
+				_ => (),
+			}
+			prev_row = row;
+		}
+	}
+
+	pub fn render(&self, format: &Format, screen: &mut dyn Screen) {
+		let rect = screen.screen_rect();
+		self.render_area(format, screen, &rect);
+		screen.refresh();
+	}
+
+	/// Draws the graph and trace coordinates into `rect` of the screen
+	pub fn render_area(&self, format: &Format, screen: &mut dyn Screen, rect: &Rect) {
+		screen.fill(rect, theme().color(Color::ContentBackground));
+		let color = theme().color(Color::ContentText);
+
+		let label_height = bitmap_font(Font::Smallest).height + 2;
+		let plot = Rect {
+			x: rect.x,
+			y: rect.y,
+			w: rect.w,
+			h: rect.h - label_height,
+		};
+
+		// Draw axes when they are within the visible range
+		if let Some(row) = self.row(&Decimal::zero(), &plot) {
+			if row >= plot.y && row < plot.y + plot.h {
+				screen.horizontal_pattern(plot.x, plot.w, row, 0xa, 4, color);
+			}
+		}
+		if let Some(col) = Self::to_pixel(&Decimal::zero(), &self.x_min, &self.x_max, plot.w) {
+			let col = plot.x + col;
+			if col >= plot.x && col < plot.x + plot.w {
+				for y in (plot.y..plot.y + plot.h).step_by(2) {
+					screen.set_pixel(col, y, color);
+				}
+			}
+		}
+
+		self.draw_curve(screen, &self.samples, &plot, false);
+		self.draw_curve(screen, &self.overlay_samples, &plot, true);
+
+		// Draw the outlines of the bars of a histogram, with a gap between adjacent
+		// bars. The bars are not filled so that the trace cursor stays visible.
+		if let GraphSource::Histogram(_, histogram) = &self.source {
+			let zero_row = self.row(&Decimal::zero(), &plot);
+			for (bin, count) in histogram.counts().iter().enumerate() {
+				let end = if bin + 1 == histogram.bins() {
+					histogram.max().clone()
+				} else {
+					histogram.bin_start(bin + 1)
+				};
+				let left =
+					Self::to_pixel(&histogram.bin_start(bin), &self.x_min, &self.x_max, plot.w);
+				let right = Self::to_pixel(&end, &self.x_min, &self.x_max, plot.w);
+				let top = self.row(&(*count as u64).into(), &plot);
+				if let (Some(left), Some(right), Some(top), Some(bottom)) =
+					(left, right, top, zero_row)
+				{
+					let (x, w, h) = (plot.x + left + 1, right - left - 1, bottom - top + 1);
+					let edges = [
+						Rect { x, y: top, w, h: 1 },
+						Rect { x, y: top, w: 1, h },
+						Rect {
+							x: x + w - 1,
+							y: top,
+							w: 1,
+							h,
+						},
+					];
+					for edge in &edges {
+						let edge = edge.clipped_to(&plot);
+						if edge.w > 0 && edge.h > 0 {
+							screen.fill(&edge, color);
+						}
+					}
+				}
+			}
+		}
+
+		// Draw the data points of a scatter plot as small crosses
+		if let GraphSource::Scatter(points, _) = &self.source {
+			for (x, y) in points {
+				if let (Some(col), Some(row)) = (
+					Self::to_pixel(x, &self.x_min, &self.x_max, plot.w),
+					self.row(y, &plot),
+				) {
+					let col = plot.x + col;
+					for (dx, dy) in &[
+						(0, 0),
+						(-1, 0),
+						(1, 0),
+						(0, -1),
+						(0, 1),
+						(-2, 0),
+						(2, 0),
+						(0, -2),
+						(0, 2),
+					] {
+						let (x, y) = (col + dx, row + dy);
+						if x >= plot.x && x < plot.x + plot.w && y >= plot.y && y < plot.y + plot.h
+						{
+							screen.set_pixel(x, y, color);
+						}
+					}
+				}
+			}
+		}
+
+		// Draw the trace cursor
+		for y in (plot.y..plot.y + plot.h).step_by(4) {
+			screen.set_pixel(plot.x + self.trace, y, color);
+		}
+		let trace_value = self.trace_value();
+		if let Some(value) = &trace_value {
+			if let Some(row) = self.row(value, &plot) {
+				let marker = Rect {
+					x: plot.x + self.trace - 2,
+					y: row - 2,
+					w: 5,
+					h: 5,
+				}
+				.clipped_to(&plot);
+				if marker.w > 0 && marker.h > 0 {
+					screen.fill(&marker, color);
+				}
+			}
+		}
+
+		// Draw the coordinates of the trace cursor below the plot, or of the point
+		// found by an analysis
+		let (mut label, x, y) = match (&self.source, &self.found) {
+			(_, Some((analysis, x, y))) => (
+				analysis.to_str().to_string() + "   ",
+				x.clone(),
+				Some(y.clone()),
+			),
+			(GraphSource::Histogram(_, histogram), None) => (
+				histogram.bins().to_number().to_string() + " bins   ",
+				self.trace_x(),
+				trace_value,
+			),
+			_ => (String::new(), self.trace_x(), trace_value),
+		};
+		label += "x: ";
+		label += &format.format_number(&Number::Decimal(x)).to_string();
+		label += "   y: ";
+		match y {
+			Some(value) => label += &format.format_number(&Number::Decimal(value)).to_string(),
+			None => label += "undefined",
+		}
+		let label_rect = Rect {
+			x: rect.x,
+			y: plot.y + plot.h,
+			w: rect.w,
+			h: label_height,
+		};
+		screen.fill(&Rect { h: 1, ..label_rect }, color);
+		bitmap_font(Font::Smallest).draw(
+			screen,
+			&label_rect,
+			label_rect.x + 4,
+			label_rect.y + 2,
+			&label,
+			color,
+		);
+	}
+}
@@ -0,0 +1,184 @@
+use crate::dm42::input::{KeyEvent, KeyboardDriver, RawKeyEvent};
+use crate::dm42::screen::{RenderMode, Screen, ScreenLayoutRenderer};
+use crate::dm42::screenshot::encode_bmp;
+use rscalc_layout::layout::Rect;
+use rscalc_math::error::{Error, Result};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const WIDTH: i32 = 400;
+pub const HEIGHT: i32 = 240;
+
+/// Latest frame drawn by the calculator that has not been shown yet
+pub struct Refresh {
+	pub screen: Option<VirtualDM42Screen>,
+}
+
+/// Intensity of a set pixel. Cleared pixels have an intensity of zero.
+pub const SET_PIXEL: u8 = 255;
+
+#[derive(Clone)]
+pub struct VirtualDM42Screen {
+	/// Intensity of each pixel. Only set and cleared pixels are used unless
+	/// grayscale rendering is enabled.
+	pixels: Vec<u8>,
+	grayscale: bool,
+	refresh: Arc<Mutex<Refresh>>,
+}
+
+impl VirtualDM42Screen {
+	/// Creates the simulated screen. The real display is monochrome, so grayscale
+	/// rendering is only enabled if `RSCALC_GRAYSCALE` is set.
+	pub fn new(refresh: Arc<Mutex<Refresh>>) -> Self {
+		VirtualDM42Screen {
+			pixels: vec![0; (WIDTH * HEIGHT) as usize],
+			grayscale: std::env::var_os("RSCALC_GRAYSCALE").is_some(),
+			refresh,
+		}
+	}
+
+	pub fn intensity(&self, x: i32, y: i32) -> u8 {
+		if x < 0 || x >= WIDTH || y < 0 || y >= HEIGHT {
+			return 0;
+		}
+		self.pixels[(y * WIDTH + x) as usize]
+	}
+
+	fn set_intensity(&mut self, x: i32, y: i32, intensity: u8) {
+		if x < 0 || x >= WIDTH || y < 0 || y >= HEIGHT {
+			return;
+		}
+		self.pixels[(y * WIDTH + x) as usize] = intensity;
+	}
+
+	fn pixel(&self, x: i32, y: i32) -> bool {
+		self.intensity(x, y) >= SET_PIXEL / 2
+	}
+
+	fn set_pixel(&mut self, x: i32, y: i32, color: bool) {
+		self.set_intensity(x, y, if color { SET_PIXEL } else { 0 });
+	}
+}
+
+impl Screen for VirtualDM42Screen {
+	fn width(&self) -> i32 {
+		WIDTH
+	}
+
+	fn height(&self) -> i32 {
+		HEIGHT
+	}
+
+	fn clear(&mut self) {
+		for pixel in self.pixels.iter_mut() {
+			*pixel = 0;
+		}
+	}
+
+	fn refresh(&mut self) {
+		self.refresh.lock().unwrap().screen = Some(self.clone());
+	}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		let rect = rect.clipped_to(&self.screen_rect());
+		for y in rect.y..rect.y + rect.h {
+			for x in rect.x..rect.x + rect.w {
+				self.set_pixel(x, y, color);
+			}
+		}
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		for i in 0..width {
+			if bits & (1 << ((width - 1) - i)) != 0 {
+				self.set_pixel(x + i as i32, y, color);
+			}
+		}
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+
+	fn supports_animation(&self) -> bool {
+		true
+	}
+
+	fn supports_grayscale(&self) -> bool {
+		self.grayscale
+	}
+
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		if !self.grayscale {
+			if alpha >= SET_PIXEL / 2 {
+				self.set_pixel(x, y, color);
+			}
+			return;
+		}
+		let current = self.intensity(x, y) as u32;
+		let target = if color { SET_PIXEL as u32 } else { 0 };
+		let alpha = alpha as u32;
+		let blended = (current * (255 - alpha) + target * alpha) / 255;
+		self.set_intensity(x, y, blended as u8);
+	}
+
+	/// Writes the screen to a BMP file named after the current time. Files are
+	/// placed in the directory given by `RSCALC_SCREENSHOT_DIR`, or the current
+	/// directory if it is not set.
+	fn capture(&self) -> Result<()> {
+		let time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_err(|_| Error::WriteFailed)?;
+		let mut path = match std::env::var_os("RSCALC_SCREENSHOT_DIR") {
+			Some(dir) => PathBuf::from(dir),
+			None => PathBuf::new(),
+		};
+		path.push(format!("rscalc-{}.bmp", time.as_millis()));
+
+		let image = encode_bmp(self, &|x, y| self.pixel(x, y));
+		std::fs::write(&path, image).map_err(|_| Error::WriteFailed)
+	}
+}
+
+/// Keys from the window of the simulator, which are already debounced by the
+/// window system
+pub struct VirtualKeyboard {
+	queue: Arc<Mutex<Vec<KeyEvent>>>,
+	event: Arc<Condvar>,
+}
+
+impl VirtualKeyboard {
+	pub fn new(queue: Arc<Mutex<Vec<KeyEvent>>>, event: Arc<Condvar>) -> Self {
+		VirtualKeyboard { queue, event }
+	}
+}
+
+impl KeyboardDriver for VirtualKeyboard {
+	fn has_event(&self) -> bool {
+		self.queue.lock().unwrap().len() != 0
+	}
+
+	fn poll_event(&mut self) -> Option<RawKeyEvent> {
+		let mut queue = self.queue.lock().unwrap();
+		queue.pop().map(RawKeyEvent::from_key_event)
+	}
+
+	fn wait_event(&mut self) -> Option<RawKeyEvent> {
+		let mut queue = self.queue.lock().unwrap();
+		if queue.len() == 0 {
+			queue = self
+				.event
+				.wait_timeout(queue, Duration::from_secs(1))
+				.unwrap()
+				.0;
+		}
+		if queue.len() != 0 {
+			Some(RawKeyEvent::from_key_event(queue.pop().unwrap()))
+		} else {
+			None
+		}
+	}
+
+	fn suspend(&self) {}
+}
@@ -1,5 +1,10 @@
-#[cfg(feature = "dm42")]
+use rscalc_math::error::Result;
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+
+#[cfg(feature = "embedded")]
 use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -48,6 +53,8 @@ pub enum Key {
 	F5,
 	F6,
 	Screenshot,
+	Copy,
+	Paste,
 	ShiftUp,
 	ShiftDown,
 	DoubleRelease,
@@ -57,6 +64,21 @@ pub enum Key {
 pub enum KeyEvent {
 	Press(Key),
 	Release,
+	/// Tap or click at a point on the screen, in the coordinates that the
+	/// calculator draws in
+	Touch(i32, i32),
+}
+
+impl KeyEvent {
+	/// Returns true for presses of keys and for taps, either of which dismisses
+	/// anything that is waiting for a key
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	pub fn is_press(&self) -> bool {
+		match self {
+			KeyEvent::Press(_) | KeyEvent::Touch(_, _) => true,
+			KeyEvent::Release => false,
+		}
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -69,6 +91,65 @@ pub enum AlphaMode {
 pub struct InputMode {
 	pub shift: bool,
 	pub alpha: AlphaMode,
+	/// EXIT, which is also the ON key, is held down and no key has been
+	/// released since
+	pub on_held: bool,
+}
+
+/// Utility action run by pressing a key while holding ON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemAction {
+	Screenshot,
+	SaveState,
+	ToggleTheme,
+	Diagnostics,
+}
+
+impl SystemAction {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			SystemAction::Screenshot => "Screenshot",
+			SystemAction::SaveState => "Save State",
+			SystemAction::ToggleTheme => "Theme",
+			SystemAction::Diagnostics => "Diagnostics",
+		}
+	}
+
+	fn saved_code(&self) -> u32 {
+		match self {
+			SystemAction::Screenshot => 0,
+			SystemAction::SaveState => 1,
+			SystemAction::ToggleTheme => 2,
+			SystemAction::Diagnostics => 3,
+		}
+	}
+
+	fn from_saved_code(code: u32) -> Option<Self> {
+		match code {
+			0 => Some(SystemAction::Screenshot),
+			1 => Some(SystemAction::SaveState),
+			2 => Some(SystemAction::ToggleTheme),
+			3 => Some(SystemAction::Diagnostics),
+			_ => None,
+		}
+	}
+}
+
+/// Keys that run a system action when pressed while holding ON. These are
+/// handled before the user keymap, so they work in every mode and can't be
+/// reassigned.
+pub const SYSTEM_KEYMAP: [(Key, SystemAction); 4] = [
+	(Key::F1, SystemAction::Screenshot),
+	(Key::F2, SystemAction::SaveState),
+	(Key::F3, SystemAction::ToggleTheme),
+	(Key::F4, SystemAction::Diagnostics),
+];
+
+fn system_key_action(key: Key) -> Option<SystemAction> {
+	SYSTEM_KEYMAP
+		.iter()
+		.find(|(system_key, _)| *system_key == key)
+		.map(|(_, action)| *action)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -136,6 +217,10 @@ pub enum InputEvent {
 	Add,
 	Catalog,
 	Screenshot,
+	Copy,
+	Paste,
+	System(SystemAction),
+	Touch(i32, i32),
 }
 
 impl InputEvent {
@@ -216,12 +301,177 @@ impl InputEvent {
 			InputEvent::Add => "+".to_string(),
 			InputEvent::Catalog => "Catalog".to_string(),
 			InputEvent::Screenshot => "Screenshot".to_string(),
+			InputEvent::Copy => "Copy".to_string(),
+			InputEvent::Paste => "Paste".to_string(),
+			InputEvent::System(action) => action.to_str().to_string(),
+			InputEvent::Touch(_, _) => "Tap".to_string(),
 		}
 	}
+	/// Code and payload identifying the event in saved state. Codes must never be
+	/// reused when events are added or removed.
+	fn saved_code(&self) -> (u8, u32) {
+		match self {
+			InputEvent::Character(ch) => (0x00, *ch as u32),
+			InputEvent::FunctionKey(idx, shift) => (0x01, *idx as u32 | ((*shift as u32) << 8)),
+			InputEvent::SigmaPlus => (0x02, 0),
+			InputEvent::SigmaMinus => (0x03, 0),
+			InputEvent::Recip => (0x04, 0),
+			InputEvent::Pow => (0x05, 0),
+			InputEvent::Sqrt => (0x06, 0),
+			InputEvent::Square => (0x07, 0),
+			InputEvent::Log => (0x08, 0),
+			InputEvent::TenX => (0x09, 0),
+			InputEvent::Ln => (0x0a, 0),
+			InputEvent::EX => (0x0b, 0),
+			InputEvent::Xeq => (0x0c, 0),
+			InputEvent::Gto => (0x0d, 0),
+			InputEvent::Sto => (0x0e, 0),
+			InputEvent::Complex => (0x0f, 0),
+			InputEvent::Rcl => (0x10, 0),
+			InputEvent::Percent => (0x11, 0),
+			InputEvent::RotateDown => (0x12, 0),
+			InputEvent::Pi => (0x13, 0),
+			InputEvent::Sin => (0x14, 0),
+			InputEvent::Asin => (0x15, 0),
+			InputEvent::Cos => (0x16, 0),
+			InputEvent::Acos => (0x17, 0),
+			InputEvent::Tan => (0x18, 0),
+			InputEvent::Atan => (0x19, 0),
+			InputEvent::Enter => (0x1a, 0),
+			InputEvent::Swap => (0x1b, 0),
+			InputEvent::Undo => (0x1c, 0),
+			InputEvent::Neg => (0x1d, 0),
+			InputEvent::Modes => (0x1e, 0),
+			InputEvent::E => (0x1f, 0),
+			InputEvent::Disp => (0x20, 0),
+			InputEvent::Backspace => (0x21, 0),
+			InputEvent::Clear => (0x22, 0),
+			InputEvent::Up => (0x23, 0),
+			InputEvent::ShiftUp => (0x24, 0),
+			InputEvent::Solver => (0x25, 0),
+			InputEvent::Integrate => (0x26, 0),
+			InputEvent::Matrix => (0x27, 0),
+			InputEvent::Div => (0x28, 0),
+			InputEvent::Stat => (0x29, 0),
+			InputEvent::Down => (0x2a, 0),
+			InputEvent::ShiftDown => (0x2b, 0),
+			InputEvent::Base => (0x2c, 0),
+			InputEvent::Convert => (0x2d, 0),
+			InputEvent::Logic => (0x2e, 0),
+			InputEvent::Mul => (0x2f, 0),
+			InputEvent::Prob => (0x30, 0),
+			InputEvent::Assign => (0x31, 0),
+			InputEvent::Custom => (0x32, 0),
+			InputEvent::ProgramFunc => (0x33, 0),
+			InputEvent::Sub => (0x34, 0),
+			InputEvent::Print => (0x35, 0),
+			InputEvent::Exit => (0x36, 0),
+			InputEvent::Off => (0x37, 0),
+			InputEvent::Setup => (0x38, 0),
+			InputEvent::Show => (0x39, 0),
+			InputEvent::Run => (0x3a, 0),
+			InputEvent::Program => (0x3b, 0),
+			InputEvent::Add => (0x3c, 0),
+			InputEvent::Catalog => (0x3d, 0),
+			InputEvent::Screenshot => (0x3e, 0),
+			InputEvent::Copy => (0x3f, 0),
+			InputEvent::Paste => (0x40, 0),
+			InputEvent::System(action) => (0x41, action.saved_code()),
+			InputEvent::Touch(x, y) => (0x42, (*x as u16 as u32) | ((*y as u16 as u32) << 16)),
+		}
+	}
+
+	/// Writes the event into saved state
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		let (code, payload) = self.saved_code();
+		output.write_u8(code)?;
+		output.write_u32(payload)
+	}
+
+	/// Reads an event written by `save`. Returns `None` for events that are not
+	/// known to this version.
+	pub fn restore(input: &mut DeserializeInput) -> Result<Option<Self>> {
+		let code = input.read_u8()?;
+		let payload = input.read_u32()?;
+		Ok(match code {
+			0x00 => core::char::from_u32(payload).map(InputEvent::Character),
+			0x01 => Some(InputEvent::FunctionKey(payload as u8, payload & 0x100 != 0)),
+			0x02 => Some(InputEvent::SigmaPlus),
+			0x03 => Some(InputEvent::SigmaMinus),
+			0x04 => Some(InputEvent::Recip),
+			0x05 => Some(InputEvent::Pow),
+			0x06 => Some(InputEvent::Sqrt),
+			0x07 => Some(InputEvent::Square),
+			0x08 => Some(InputEvent::Log),
+			0x09 => Some(InputEvent::TenX),
+			0x0a => Some(InputEvent::Ln),
+			0x0b => Some(InputEvent::EX),
+			0x0c => Some(InputEvent::Xeq),
+			0x0d => Some(InputEvent::Gto),
+			0x0e => Some(InputEvent::Sto),
+			0x0f => Some(InputEvent::Complex),
+			0x10 => Some(InputEvent::Rcl),
+			0x11 => Some(InputEvent::Percent),
+			0x12 => Some(InputEvent::RotateDown),
+			0x13 => Some(InputEvent::Pi),
+			0x14 => Some(InputEvent::Sin),
+			0x15 => Some(InputEvent::Asin),
+			0x16 => Some(InputEvent::Cos),
+			0x17 => Some(InputEvent::Acos),
+			0x18 => Some(InputEvent::Tan),
+			0x19 => Some(InputEvent::Atan),
+			0x1a => Some(InputEvent::Enter),
+			0x1b => Some(InputEvent::Swap),
+			0x1c => Some(InputEvent::Undo),
+			0x1d => Some(InputEvent::Neg),
+			0x1e => Some(InputEvent::Modes),
+			0x1f => Some(InputEvent::E),
+			0x20 => Some(InputEvent::Disp),
+			0x21 => Some(InputEvent::Backspace),
+			0x22 => Some(InputEvent::Clear),
+			0x23 => Some(InputEvent::Up),
+			0x24 => Some(InputEvent::ShiftUp),
+			0x25 => Some(InputEvent::Solver),
+			0x26 => Some(InputEvent::Integrate),
+			0x27 => Some(InputEvent::Matrix),
+			0x28 => Some(InputEvent::Div),
+			0x29 => Some(InputEvent::Stat),
+			0x2a => Some(InputEvent::Down),
+			0x2b => Some(InputEvent::ShiftDown),
+			0x2c => Some(InputEvent::Base),
+			0x2d => Some(InputEvent::Convert),
+			0x2e => Some(InputEvent::Logic),
+			0x2f => Some(InputEvent::Mul),
+			0x30 => Some(InputEvent::Prob),
+			0x31 => Some(InputEvent::Assign),
+			0x32 => Some(InputEvent::Custom),
+			0x33 => Some(InputEvent::ProgramFunc),
+			0x34 => Some(InputEvent::Sub),
+			0x35 => Some(InputEvent::Print),
+			0x36 => Some(InputEvent::Exit),
+			0x37 => Some(InputEvent::Off),
+			0x38 => Some(InputEvent::Setup),
+			0x39 => Some(InputEvent::Show),
+			0x3a => Some(InputEvent::Run),
+			0x3b => Some(InputEvent::Program),
+			0x3c => Some(InputEvent::Add),
+			0x3d => Some(InputEvent::Catalog),
+			0x3e => Some(InputEvent::Screenshot),
+			0x3f => Some(InputEvent::Copy),
+			0x40 => Some(InputEvent::Paste),
+			0x41 => SystemAction::from_saved_code(payload).map(InputEvent::System),
+			0x42 => Some(InputEvent::Touch(
+				payload as u16 as i16 as i32,
+				(payload >> 16) as u16 as i16 as i32,
+			)),
+			_ => None,
+		})
+	}
 }
 
 pub trait InputQueue {
 	fn has_input(&self) -> bool;
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
 	fn pop_raw(&mut self) -> Option<KeyEvent>;
 	fn wait_raw(&mut self) -> Option<KeyEvent>;
 
@@ -229,6 +479,18 @@ pub trait InputQueue {
 		loop {
 			match self.wait_raw() {
 				Some(KeyEvent::Press(key)) => {
+					// The firmware reports a release only once all keys are up, so
+					// a press while ON is still held is a chord
+					if mode.on_held && key != Key::Exit && key != Key::DoubleRelease {
+						if let Some(action) = system_key_action(key) {
+							mode.shift = false;
+							return Some(InputEvent::System(action));
+						}
+						continue;
+					}
+					if key != Key::DoubleRelease {
+						mode.on_held = key == Key::Exit;
+					}
 					let shift = mode.shift;
 					mode.shift = false;
 					match key {
@@ -772,6 +1034,8 @@ pub trait InputQueue {
 						Key::F5 => return Some(InputEvent::FunctionKey(5, shift)),
 						Key::F6 => return Some(InputEvent::FunctionKey(6, shift)),
 						Key::Screenshot => return Some(InputEvent::Screenshot),
+						Key::Copy => return Some(InputEvent::Copy),
+						Key::Paste => return Some(InputEvent::Paste),
 						Key::ShiftUp => match mode.alpha {
 							AlphaMode::UpperAlpha | AlphaMode::LowerAlpha => {
 								if shift {
@@ -809,11 +1073,167 @@ pub trait InputQueue {
 						Key::DoubleRelease => continue,
 					}
 				}
-				Some(KeyEvent::Release) => continue,
+				Some(KeyEvent::Release) => {
+					mode.on_held = false;
+					continue;
+				}
+				Some(KeyEvent::Touch(x, y)) => {
+					mode.on_held = false;
+					return Some(InputEvent::Touch(x, y));
+				}
 				None => return None,
 			}
 		}
 	}
 
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
 	fn suspend(&self);
 }
+
+/// Time after a key is released during which a press of the same key is treated
+/// as contact bounce and ignored
+pub const DEBOUNCE_MS: u32 = 20;
+
+/// Change in the state of a key as reported by a keyboard driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawKeyEvent {
+	/// Key that changed state. Releases may leave this out when the keyboard only
+	/// reports that all keys were released.
+	pub key: Option<Key>,
+	pub pressed: bool,
+	/// Time of the change in milliseconds from any fixed point. Drivers that
+	/// receive keys that have already been debounced leave this out.
+	pub time_ms: Option<u32>,
+	/// Point on the screen that was tapped, for drivers of touch screens and
+	/// pointers. The key is left out for these.
+	pub touch: Option<(i32, i32)>,
+}
+
+impl RawKeyEvent {
+	/// Event for a key that was already debounced by the platform
+	pub fn from_key_event(event: KeyEvent) -> Self {
+		match event {
+			KeyEvent::Press(key) => RawKeyEvent {
+				key: Some(key),
+				pressed: true,
+				time_ms: None,
+				touch: None,
+			},
+			KeyEvent::Release => RawKeyEvent {
+				key: None,
+				pressed: false,
+				time_ms: None,
+				touch: None,
+			},
+			KeyEvent::Touch(x, y) => RawKeyEvent {
+				key: None,
+				pressed: true,
+				time_ms: None,
+				touch: Some((x, y)),
+			},
+		}
+	}
+}
+
+/// Source of raw key events, in the same way that `Screen` is the target of
+/// drawing. Hardware and simulator front ends only report changes in the state
+/// of their keys, and `Keyboard` turns them into input for the calculator.
+pub trait KeyboardDriver {
+	fn has_event(&self) -> bool;
+	fn poll_event(&mut self) -> Option<RawKeyEvent>;
+	/// Waits for the next event. Returns `None` if woken up without one, such as
+	/// to update the clock.
+	fn wait_event(&mut self) -> Option<RawKeyEvent>;
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn suspend(&self);
+}
+
+/// Input queue over a keyboard driver. Presses of a key that follow its release
+/// too closely are dropped as bounce, and presses of a key that is already held
+/// are dropped as well.
+pub struct Keyboard<DriverT: KeyboardDriver> {
+	driver: DriverT,
+	/// Key that was last pressed and whether it is still held
+	last_key: Option<(Key, bool)>,
+	/// Time of the last release
+	last_release: Option<u32>,
+}
+
+impl<DriverT: KeyboardDriver> Keyboard<DriverT> {
+	pub fn new(driver: DriverT) -> Self {
+		Keyboard {
+			driver,
+			last_key: None,
+			last_release: None,
+		}
+	}
+
+	#[cfg(feature = "web")]
+	pub fn driver_mut(&mut self) -> &mut DriverT {
+		&mut self.driver
+	}
+
+	/// Converts a raw event to a key event, or returns `None` if it is bounce
+	fn accept(&mut self, event: RawKeyEvent) -> Option<KeyEvent> {
+		// Taps are not keys, so they don't bounce
+		if let Some((x, y)) = event.touch {
+			return Some(KeyEvent::Touch(x, y));
+		}
+
+		if !event.pressed {
+			if let Some((key, _)) = self.last_key {
+				if event.key.is_none() || event.key == Some(key) {
+					self.last_key = Some((key, false));
+				}
+			}
+			self.last_release = event.time_ms;
+			return Some(KeyEvent::Release);
+		}
+
+		let key = event.key?;
+		if let (Some(time), Some((last_key, held))) = (event.time_ms, self.last_key) {
+			if last_key == key {
+				if held {
+					return None;
+				}
+				if let Some(release) = self.last_release {
+					if time.wrapping_sub(release) < DEBOUNCE_MS {
+						return None;
+					}
+				}
+			}
+		}
+		self.last_key = Some((key, true));
+		Some(KeyEvent::Press(key))
+	}
+}
+
+impl<DriverT: KeyboardDriver> InputQueue for Keyboard<DriverT> {
+	fn has_input(&self) -> bool {
+		self.driver.has_event()
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn pop_raw(&mut self) -> Option<KeyEvent> {
+		while let Some(event) = self.driver.poll_event() {
+			if let Some(event) = self.accept(event) {
+				return Some(event);
+			}
+		}
+		None
+	}
+
+	fn wait_raw(&mut self) -> Option<KeyEvent> {
+		loop {
+			let event = self.driver.wait_event()?;
+			if let Some(event) = self.accept(event) {
+				return Some(event);
+			}
+		}
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn suspend(&self) {
+		self.driver.suspend()
+	}
+}
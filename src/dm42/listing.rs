@@ -0,0 +1,440 @@
+use crate::dm42::flags::FlagOperation;
+use crate::dm42::functions::Function;
+use crate::dm42::graph::ProgramStep;
+use crate::dm42::input::InputEvent;
+use crate::dm42::state::MAX_MEMORY_INDEX_DIGITS;
+use rscalc_math::context::{Context, Location};
+use rscalc_math::error::{Error, Result};
+use rscalc_math::functions::StackFunction;
+use rscalc_math::number::ToNumber;
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+
+#[cfg(not(feature = "embedded"))]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "embedded")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// File that the program is exported to and imported from
+pub const PROGRAM_LISTING_FILE_NAME: &str = "program.prg";
+
+// A listing has one instruction of the program on each line, after the number
+// of the line. The keys of an instruction are written as words separated by
+// spaces: numbers as they were typed, keys and functions by their names, and
+// characters as themselves, or quoted as in 'π' when they could be mistaken
+// for a name. Functions that have no name of their own are written as `#`
+// followed by their saved code in hex. When reading a listing, blank lines are
+// ignored and the line numbers are optional.
+
+/// Names of the keys and functions that can appear in a listing
+struct Mnemonics {
+	inputs: Vec<(String, InputEvent)>,
+	functions: BTreeMap<String, StackFunction>,
+}
+
+impl Mnemonics {
+	fn new() -> Self {
+		let mut inputs = Vec::new();
+		for code in 0x02..=0xff {
+			if let Some(input) = restore_input(code, 0) {
+				match input {
+					InputEvent::System(_) => (),
+					_ => inputs.push((input.to_string(), input)),
+				}
+			}
+		}
+
+		// Functions that take a unit or other kind of argument have it in the
+		// payload of their code. Codes that ignore the payload restore to the
+		// same function for any payload, and are only named once.
+		let context = Context::new();
+		let mut functions = BTreeMap::new();
+		for code in 0..=0x1ff {
+			let base = restore_stack_function(code, 0);
+			let has_payload = base != restore_stack_function(code, 0x7fff);
+			let payloads = if has_payload { 0..0x1000 } else { 0..1 };
+			for payload in payloads {
+				if let Some(func) = restore_stack_function(code, payload) {
+					let name = stack_function_name(&func, &context);
+					if !name.is_empty() {
+						functions.entry(name).or_insert(func);
+					}
+				}
+			}
+		}
+		Mnemonics { inputs, functions }
+	}
+
+	/// Steps given by one word of a listing
+	fn parse_word(&self, word: &str) -> Result<Vec<ProgramStep>> {
+		let mut chars = word.chars();
+		let first = chars.next().ok_or(Error::InvalidEntry)?;
+		let second = chars.next();
+		let single = second.is_none();
+
+		// Quoted character
+		if first == '\'' && word.len() > 2 && word.ends_with('\'') {
+			let mut inner = word[1..word.len() - 1].chars();
+			if let (Some(ch), None) = (inner.next(), inner.next()) {
+				return Ok(single_step(ProgramStep::Input(InputEvent::Character(ch))));
+			}
+		}
+
+		// Number
+		let starts_number = first.is_ascii_digit()
+			|| first == '.'
+			|| first == 'ᴇ'
+			|| (first == '-' && second.is_some_and(|ch| ch.is_ascii_digit() || ch == '.'));
+		if starts_number {
+			return parse_number(word);
+		}
+
+		if let Some((_, input)) = self.inputs.iter().find(|(name, _)| name == word) {
+			return Ok(single_step(ProgramStep::Input(*input)));
+		}
+		if let Some(func) = program_function(word) {
+			return Ok(single_step(ProgramStep::Function(func)));
+		}
+		if let Some(func) = self.functions.get(word) {
+			return Ok(single_step(ProgramStep::Function(Function::Stack(
+				func.clone(),
+			))));
+		}
+		if single {
+			return Ok(single_step(ProgramStep::Input(InputEvent::Character(
+				first,
+			))));
+		}
+		if first == '#' {
+			return Ok(single_step(ProgramStep::Function(Function::Stack(
+				parse_saved_code(&word[1..])?,
+			))));
+		}
+		Err(Error::InvalidEntry)
+	}
+
+	/// Checks that a word reads back as the given steps, so that the listing
+	/// always gives the same program when it is read
+	fn reads_as(&self, word: &str, steps: &[ProgramStep]) -> bool {
+		match self.parse_word(word) {
+			Ok(parsed) => match (saved_steps(&parsed), saved_steps(steps)) {
+				(Ok(parsed), Ok(steps)) => parsed == steps,
+				_ => false,
+			},
+			Err(_) => false,
+		}
+	}
+
+	fn write_step(&self, step: &ProgramStep, words: &mut Vec<String>) -> Result<()> {
+		match step {
+			ProgramStep::Input(InputEvent::Character(ch)) => {
+				let mut word = String::new();
+				word.push(*ch);
+				if ch.is_whitespace() || !self.reads_as(&word, core::slice::from_ref(step)) {
+					word = "'".to_string() + &word + "'";
+				}
+				words.push(word);
+			}
+			ProgramStep::Input(input) => self.write_input(*input, words)?,
+			ProgramStep::Function(Function::Input(input)) => self.write_input(*input, words)?,
+			ProgramStep::Function(Function::Stack(func)) => {
+				let name = stack_function_name(func, &Context::new());
+				if !name.is_empty() && self.reads_as(&name, core::slice::from_ref(step)) {
+					words.push(name);
+				} else {
+					let mut code = Vec::new();
+					func.save(&mut code)?;
+					words.push("#".to_string() + &hex(&code));
+				}
+			}
+			ProgramStep::Function(Function::RecallVariable(location)) => {
+				self.write_input(InputEvent::Rcl, words)?;
+				match location {
+					Location::Variable(ch) => {
+						self.write_step(&ProgramStep::Input(InputEvent::Character(*ch)), words)?
+					}
+					Location::Integer(idx) if *idx < 100 => {
+						let mut word = idx.to_number().to_string();
+						while word.len() < MAX_MEMORY_INDEX_DIGITS {
+							word.insert(0, '0');
+						}
+						words.push(word);
+					}
+					_ => return Err(Error::NotSupported),
+				}
+			}
+			ProgramStep::Function(func) => match program_function_name(func) {
+				Some(name) => words.push(name.to_string()),
+				// Functions that refer to the custom assignments of this
+				// calculator would do something else on another one
+				None => return Err(Error::NotSupported),
+			},
+		}
+		Ok(())
+	}
+
+	fn write_input(&self, input: InputEvent, words: &mut Vec<String>) -> Result<()> {
+		let name = input.to_string();
+		if !self.reads_as(&name, &[ProgramStep::Input(input)]) {
+			return Err(Error::NotSupported);
+		}
+		words.push(name);
+		Ok(())
+	}
+}
+
+/// Writes a program as text that can be shared and read back with
+/// `parse_program_listing`
+pub fn program_listing(steps: &[ProgramStep]) -> Result<String> {
+	let names = Mnemonics::new();
+	let mut lines = Vec::new();
+	let mut idx = 0;
+	while idx < steps.len() {
+		let len = ProgramStep::instruction_len(&steps[idx..]);
+		let instruction = &steps[idx..idx + len];
+		let mut words = Vec::new();
+		let mut key = 0;
+		while key < instruction.len() {
+			let number = if starts_number(&instruction[key]) {
+				ProgramStep::number_len(&instruction[key..])
+			} else {
+				0
+			};
+			if number > 0 {
+				words.push(number_text(&instruction[key..key + number]));
+				key += number;
+			} else {
+				names.write_step(&instruction[key], &mut words)?;
+				key += 1;
+			}
+		}
+		lines.push(words.join(" "));
+		idx += len;
+	}
+
+	let digits = lines.len().to_number().to_string().len().max(2);
+	let mut result = String::new();
+	for (idx, line) in lines.iter().enumerate() {
+		let mut number = (idx + 1).to_number().to_string();
+		while number.len() < digits {
+			number.insert(0, '0');
+		}
+		result += &number;
+		result += " ";
+		result += line;
+		result += "\n";
+	}
+	Ok(result)
+}
+
+/// Reads a program written by `program_listing`
+pub fn parse_program_listing(text: &str) -> Result<Vec<ProgramStep>> {
+	let names = Mnemonics::new();
+	let mut steps = Vec::new();
+	for line in text.lines() {
+		let mut words: Vec<&str> = line.split_whitespace().collect();
+		if words.len() > 1 && words[0].chars().all(|ch| ch.is_ascii_digit()) {
+			words.remove(0);
+		}
+		for word in words {
+			steps.extend(names.parse_word(word)?);
+		}
+	}
+	Ok(steps)
+}
+
+fn single_step(step: ProgramStep) -> Vec<ProgramStep> {
+	vec![step]
+}
+
+fn starts_number(step: &ProgramStep) -> bool {
+	match step {
+		ProgramStep::Input(InputEvent::Character(ch)) => ch.is_ascii_digit() || *ch == '.',
+		ProgramStep::Input(InputEvent::E) => true,
+		_ => false,
+	}
+}
+
+/// Text of a number from the keys that typed it. The sign keys can be pressed
+/// at any time while typing, so they are written where the sign is shown.
+fn number_text(keys: &[ProgramStep]) -> String {
+	let mut mantissa = String::new();
+	let mut exponent: Option<String> = None;
+	let mut negative = false;
+	let mut negative_exponent = false;
+	for key in keys {
+		match key {
+			ProgramStep::Input(InputEvent::Character(ch)) => match &mut exponent {
+				Some(exponent) => exponent.push(*ch),
+				None => mantissa.push(*ch),
+			},
+			ProgramStep::Input(InputEvent::E) if exponent.is_none() => {
+				exponent = Some(String::new());
+			}
+			ProgramStep::Input(InputEvent::Neg) => match exponent {
+				Some(_) => negative_exponent = !negative_exponent,
+				None => negative = !negative,
+			},
+			ProgramStep::Input(InputEvent::Backspace) => match &mut exponent {
+				Some(digits) => {
+					if digits.pop().is_none() {
+						exponent = None;
+						negative_exponent = false;
+					}
+				}
+				None => {
+					mantissa.pop();
+				}
+			},
+			_ => (),
+		}
+	}
+
+	let mut result = String::new();
+	if negative {
+		result.push('-');
+	}
+	result += &mantissa;
+	if let Some(exponent) = exponent {
+		result.push(if !mantissa.is_empty() { 'E' } else { 'ᴇ' });
+		if negative_exponent {
+			result.push('-');
+		}
+		result += &exponent;
+	}
+	result
+}
+
+/// Keys that type a number written by `number_text`
+fn parse_number(word: &str) -> Result<Vec<ProgramStep>> {
+	let (negative, word) = match word.strip_prefix('-') {
+		Some(rest) => (true, rest),
+		None => (false, word),
+	};
+	let (mantissa, exponent) = match word.find(['E', 'ᴇ']) {
+		Some(idx) => {
+			let rest = &word[idx..];
+			let marker = rest.chars().next().map_or(0, |ch| ch.len_utf8());
+			(&word[..idx], Some(&rest[marker..]))
+		}
+		None => (word, None),
+	};
+
+	let mut steps = Vec::new();
+	push_digits(mantissa, &mut steps)?;
+	if negative {
+		steps.push(ProgramStep::Input(InputEvent::Neg));
+	}
+	if let Some(exponent) = exponent {
+		steps.push(ProgramStep::Input(InputEvent::E));
+		let (negative, digits) = match exponent.strip_prefix('-') {
+			Some(rest) => (true, rest),
+			None => (false, exponent),
+		};
+		if digits.contains('.') {
+			return Err(Error::InvalidEntry);
+		}
+		push_digits(digits, &mut steps)?;
+		if negative {
+			steps.push(ProgramStep::Input(InputEvent::Neg));
+		}
+	}
+	Ok(steps)
+}
+
+fn push_digits(digits: &str, steps: &mut Vec<ProgramStep>) -> Result<()> {
+	for ch in digits.chars() {
+		if !ch.is_ascii_digit() && ch != '.' {
+			return Err(Error::InvalidEntry);
+		}
+		steps.push(ProgramStep::Input(InputEvent::Character(ch)));
+	}
+	Ok(())
+}
+
+/// Name of a stack function in a listing. The names shown in menus mark the
+/// mode that is active, which doesn't belong in a listing, and words can't
+/// contain spaces.
+fn stack_function_name(func: &StackFunction, context: &Context) -> String {
+	func.to_string(context)
+		.chars()
+		.filter(|ch| *ch != '▪')
+		.map(|ch| if ch == ' ' { '_' } else { ch })
+		.collect()
+}
+
+/// Names of the functions for programs, which are written the same on every
+/// calculator
+fn program_function_name(func: &Function) -> Option<&'static str> {
+	match func {
+		Function::ProgramInput => Some("Input"),
+		Function::ProgramView => Some("View"),
+		Function::Label => Some("Lbl"),
+		Function::ProgramMenu => Some("Menu"),
+		Function::Flag(operation) => Some(operation.to_str()),
+		_ => None,
+	}
+}
+
+fn program_function(name: &str) -> Option<Function> {
+	let functions = vec![
+		Function::ProgramInput,
+		Function::ProgramView,
+		Function::Label,
+		Function::ProgramMenu,
+		Function::Flag(FlagOperation::Set),
+		Function::Flag(FlagOperation::Clear),
+		Function::Flag(FlagOperation::TestSet),
+		Function::Flag(FlagOperation::TestClear),
+	];
+	functions
+		.into_iter()
+		.find(|func| program_function_name(func) == Some(name))
+}
+
+fn restore_input(code: u8, payload: u32) -> Option<InputEvent> {
+	let mut data = Vec::new();
+	data.write_u8(code).ok()?;
+	data.write_u32(payload).ok()?;
+	InputEvent::restore(&mut DeserializeInput::new(&data)).ok()?
+}
+
+fn restore_stack_function(code: u16, payload: u16) -> Option<StackFunction> {
+	let mut data = Vec::new();
+	data.write_u16(code).ok()?;
+	data.write_u16(payload).ok()?;
+	StackFunction::restore(&mut DeserializeInput::new(&data)).ok()?
+}
+
+fn parse_saved_code(text: &str) -> Result<StackFunction> {
+	if !text.len().is_multiple_of(2) || !text.is_ascii() {
+		return Err(Error::InvalidEntry);
+	}
+	let mut data = Vec::new();
+	for idx in (0..text.len()).step_by(2) {
+		data.push(u8::from_str_radix(&text[idx..idx + 2], 16).map_err(|_| Error::InvalidEntry)?);
+	}
+	StackFunction::restore(&mut DeserializeInput::new(&data))?.ok_or(Error::InvalidEntry)
+}
+
+fn hex(data: &[u8]) -> String {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+	let mut result = String::new();
+	for byte in data {
+		result.push(DIGITS[(byte >> 4) as usize] as char);
+		result.push(DIGITS[(byte & 0xf) as usize] as char);
+	}
+	result
+}
+
+fn saved_steps(steps: &[ProgramStep]) -> Result<Vec<u8>> {
+	let mut data = Vec::new();
+	ProgramStep::save_steps(steps, &mut data)?;
+	Ok(data)
+}
@@ -0,0 +1,238 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Language that menus and messages are shown in
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Language {
+	English,
+	German,
+}
+
+/// Languages in the order they are cycled through. Saved settings refer to a
+/// language by its position, so new languages must be added to the end.
+pub const LANGUAGES: [Language; 2] = [Language::English, Language::German];
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+impl Language {
+	/// Name of the language in that language, so that it can be found by someone
+	/// who can't read the current one
+	pub fn to_str(self) -> &'static str {
+		match self {
+			Language::English => "English",
+			Language::German => "Deutsch",
+		}
+	}
+
+	pub fn index(&self) -> usize {
+		LANGUAGES
+			.iter()
+			.position(|language| language == self)
+			.unwrap_or(0)
+	}
+
+	pub fn from_index(index: usize) -> Option<Language> {
+		LANGUAGES.get(index).copied()
+	}
+
+	/// Table of translations from the English text, or `None` for English
+	fn table(&self) -> Option<&'static [(&'static str, &'static str)]> {
+		match self {
+			Language::English => None,
+			Language::German => Some(GERMAN),
+		}
+	}
+}
+
+/// Returns the active language
+pub fn language() -> Language {
+	Language::from_index(LANGUAGE.load(Ordering::Relaxed) as usize).unwrap_or(Language::English)
+}
+
+/// Sets the active language. Menus that are already open keep the text they were
+/// built with until they are opened again.
+pub fn set_language(language: Language) {
+	LANGUAGE.store(language.index() as u8, Ordering::Relaxed);
+}
+
+/// Translates English text into the active language. The English text is the
+/// key, so text without a translation is shown in English.
+pub fn tr(text: &str) -> &str {
+	match language().table() {
+		Some(table) => table
+			.iter()
+			.find(|(english, _)| *english == text)
+			.map_or(text, |(_, translated)| translated),
+		None => text,
+	}
+}
+
+// The fonts have no umlauts, so they are written out as is usual where they
+// are not available
+const GERMAN: &[(&str, &str)] = &[
+	// Menu titles and items
+	("Settings", "Einstellungen"),
+	("Display", "Anzeige"),
+	("Display >", "Anzeige >"),
+	("Number Format", "Zahlenformat"),
+	("Number Format >", "Zahlenformat >"),
+	("Preferred Units", "Bevorzugte Einheiten"),
+	("Preferred Units >", "Bevorzugte Einheiten >"),
+	("Sounds", "Toene"),
+	("Sounds >", "Toene >"),
+	("Advanced", "Erweitert"),
+	("Advanced >", "Erweitert >"),
+	("Reset to Defaults", "Standardwerte"),
+	("Brightness", "Helligkeit"),
+	("Brightness >", "Helligkeit >"),
+	("Contrast", "Kontrast"),
+	("Auto Dim", "Abdunkeln"),
+	("Auto Save", "Autom. Sichern"),
+	("Display Digits", "Stellen"),
+	("Display Digits >", "Stellen >"),
+	("Alternate Forms", "Alternative Formen"),
+	("Alternate Forms >", "Alternative Formen >"),
+	("Catalog", "Katalog"),
+	("Search", "Suche"),
+	("Language", "Sprache"),
+	// Names of settings
+	("24-hour Clock", "24-Stunden-Uhr"),
+	("Startup", "Start"),
+	("Custom Slots", "Eigene Plaetze"),
+	("Exam Mode", "Pruefungsmodus"),
+	("Font Size", "Schriftgroesse"),
+	("Show Status Bar", "Statusleiste"),
+	("Status Bar Text", "Text der Statusleiste"),
+	("Show Empty Soft Keys", "Leere Softkeys"),
+	("Stack Labels", "Stapelnamen"),
+	("Mode Tags", "Modusmarken"),
+	("Radicals", "Wurzeln"),
+	("Theme", "Design"),
+	("Reduced Motion", "Weniger Bewegung"),
+	("Split Pane", "Geteilte Ansicht"),
+	("Split Size", "Teilung"),
+	("Display Mode", "Anzeigemodus"),
+	("Integer Radix", "Zahlenbasis"),
+	("Angle Mode", "Winkelmodus"),
+	("Digit Separator", "Zifferntrenner"),
+	("Rounding", "Rundung"),
+	("Decimal Point", "Dezimalzeichen"),
+	("Long Integers", "Lange Ganzzahlen"),
+	("Fractions", "Brueche"),
+	("Alternate Display", "Alternative Anzeige"),
+	("Stack Rendering", "Stapeldarstellung"),
+	("Rational Reduction", "Kuerzen"),
+	("Outside Real Domain", "Nicht reell"),
+	("ENTER Key", "ENTER-Taste"),
+	("Integer Division", "Ganzzahldivision"),
+	("Key Click", "Tastenklick"),
+	("Error Buzz", "Fehlerton"),
+	("Alarm Tone", "Weckton"),
+	// Values of settings
+	("On", "Ein"),
+	("Off", "Aus"),
+	("Restore", "Fortsetzen"),
+	("Clear Stack", "Stapel leeren"),
+	("Run Program", "Programm starten"),
+	("Smallest", "Kleinste"),
+	("Small", "Klein"),
+	("Medium", "Mittel"),
+	("Large", "Gross"),
+	("Current Time", "Uhrzeit"),
+	("Free Memory", "Freier Speicher"),
+	("Frame Time", "Bildzeit"),
+	("Battery", "Batterie"),
+	("Light", "Hell"),
+	("Dark", "Dunkel"),
+	("Fraction", "Bruch"),
+	("Fixed", "Fest"),
+	("Scientific", "Wissenschaftlich"),
+	("Engineering", "Technisch"),
+	("Significant", "Signifikant"),
+	("Octal", "Oktal"),
+	("Binary", "Binaer"),
+	("Decimal", "Dezimal"),
+	("Degrees", "Grad"),
+	("Radians", "Bogenmass"),
+	("Gradians", "Gon"),
+	("Half Up", "Kaufmaennisch"),
+	("Half Even", "Gerade"),
+	("To Zero", "Gegen Null"),
+	("Up", "Auf"),
+	("Down", "Ab"),
+	("Period", "Punkt"),
+	("Comma", "Komma"),
+	("Wrap", "Umbrechen"),
+	("Ellipsis", "Auslassung"),
+	("Always", "Immer"),
+	("Bottom", "Unten"),
+	("Left", "Links"),
+	("Cached", "Zwischenspeicher"),
+	("Legacy", "Klassisch"),
+	("Deferred", "Verzoegert"),
+	("Complex", "Komplex"),
+	("Error", "Fehler"),
+	("Terminate", "Beenden"),
+	("Duplicate", "Duplizieren"),
+	("Quot+Rem", "Quot+Rest"),
+	// Pages of the catalog
+	("Alarms", "Wecker"),
+	("Answers", "Ergebnisse"),
+	("Checksums", "Pruefsummen"),
+	("Constants", "Konstanten"),
+	("Coordinates", "Koordinaten"),
+	("Electronics", "Elektronik"),
+	("Equations", "Gleichungen"),
+	("Lists", "Listen"),
+	("Network", "Netzwerk"),
+	("Number Theory", "Zahlentheorie"),
+	("Probability", "Wahrscheinlichkeit"),
+	("Statistics", "Statistik"),
+	("Time", "Zeit"),
+	("Transcendental", "Transzendent"),
+	("Scripts", "Skripte"),
+	("Series", "Reihen"),
+	("Stack", "Stapel"),
+	("Units", "Einheiten"),
+	("User", "Benutzer"),
+	("Variables", "Variablen"),
+	("Vectors", "Vektoren"),
+	// Errors
+	("Out of memory", "Kein Speicher frei"),
+	("Not enough values", "Zu wenige Werte"),
+	("Not a real number", "Keine reelle Zahl"),
+	("Invalid integer", "Ungueltige Ganzzahl"),
+	("Data type mismatch", "Falscher Datentyp"),
+	("Incompatible units", "Einheiten passen nicht"),
+	("Invalid entry", "Ungueltige Eingabe"),
+	("Invalid stack index", "Ungueltige Stapelstelle"),
+	("Value not defined", "Wert nicht definiert"),
+	("Value out of range", "Wert ausserhalb"),
+	("Index out of range", "Index ausserhalb"),
+	("Requires decimal mode", "Nur im Dezimalmodus"),
+	("Requires sized int mode", "Nur mit fester Wortbreite"),
+	("Invalid date", "Ungueltiges Datum"),
+	("Invalid time", "Ungueltige Zeit"),
+	("Corrupt data", "Daten beschaedigt"),
+	("Stack overflow", "Stapelueberlauf"),
+	("Undo buffer empty", "Nichts zum Rueckgaengig"),
+	("Vector too large", "Vektor zu gross"),
+	("Matrix too large", "Matrix zu gross"),
+	("Dimension mismatch", "Dimensionen passen nicht"),
+	("Singular matrix", "Singulaere Matrix"),
+	("Label not found", "Marke nicht gefunden"),
+	("No program recorded", "Kein Programm"),
+	("Locked", "Gesperrt"),
+	("Not supported", "Nicht unterstuetzt"),
+	("Write failed", "Schreiben fehlgeschlagen"),
+	("Unsupported version", "Falsche Version"),
+	("No saved state", "Kein Zustand gespeichert"),
+	("Syntax error", "Syntaxfehler"),
+	("Unknown name", "Unbekannter Name"),
+	("File not found", "Datei nicht gefunden"),
+	("Divide by zero", "Division durch Null"),
+	("Invalid argument", "Ungueltiges Argument"),
+	("Result too large", "Ergebnis zu gross"),
+	("Interrupted", "Abgebrochen"),
+	("No solution found", "Keine Loesung"),
+	("Disabled in exam mode", "Im Pruefungsmodus aus"),
+];
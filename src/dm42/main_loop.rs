@@ -0,0 +1,240 @@
+use crate::dm42::alarm::{ALARM_BEEP_INTERVAL_MS, ALARM_RING_MS};
+use crate::dm42::animation::FRAME_TIME_MS;
+use crate::dm42::feedback::FeedbackEvent;
+use crate::dm42::files::open_file_store;
+use crate::dm42::font::load_custom_fonts;
+use crate::dm42::input::InputQueue;
+use crate::dm42::platform::{platform, LOW_BATTERY_WARNING_MS};
+use crate::dm42::save::{read_saved_state, AutoSave};
+use crate::dm42::screen::Screen;
+use crate::dm42::state::{InputResult, State};
+use crate::dm42::toast::TOAST_TIME_MS;
+use chrono::NaiveDateTime;
+use rscalc_math::time::Now;
+
+fn delay(ms: u32) {
+	platform().delay(ms);
+}
+
+/// Saves the state and turns the calculator off. Failing to save must not
+/// prevent turning off. The startup setting is applied once it is turned back
+/// on.
+fn power_off<ScreenT: Screen, InputT: InputQueue>(
+	state: &mut State,
+	screen: &ScreenT,
+	input: &InputT,
+	auto_save: &mut AutoSave,
+) {
+	auto_save.save(state);
+	input.suspend();
+	state.wake_display();
+	start_up(state, screen);
+}
+
+/// Applies the startup setting, reporting an error from a startup program in
+/// the same way as one from a key
+fn start_up(state: &mut State, screen: &dyn Screen) {
+	if let Err(error) = state.start_up(screen) {
+		state.show_error(error);
+	}
+}
+
+/// Shows each alarm that is due over the whole screen, beeping until a key is
+/// pressed or the alarm has rung for a minute
+fn ring_due_alarms<ScreenT: Screen, InputT: InputQueue>(
+	state: &mut State,
+	screen: &mut ScreenT,
+	input: &mut InputT,
+) {
+	while let Some(alarm) = state.take_due_alarm() {
+		state.show_alarm(alarm);
+		state.render(screen);
+
+		let mut elapsed = 0;
+		while elapsed < ALARM_RING_MS {
+			if elapsed % ALARM_BEEP_INTERVAL_MS == 0 {
+				state.feedback(FeedbackEvent::Alarm);
+			}
+			delay(100);
+			elapsed += 100;
+			if input.pop_raw().is_some_and(|event| event.is_press()) {
+				break;
+			}
+		}
+
+		state.hide_alarm();
+		state.render(screen);
+	}
+}
+
+/// Saves the state and warns over the whole screen when the battery becomes
+/// critically low, so that nothing is lost if it runs out. The warning is shown
+/// once, and again only after the calculator has been on USB power.
+fn warn_low_battery<ScreenT: Screen, InputT: InputQueue>(
+	state: &mut State,
+	screen: &mut ScreenT,
+	input: &mut InputT,
+	warned: &mut bool,
+	auto_save: &mut AutoSave,
+) {
+	let power = match platform().power_status() {
+		Some(power) => power,
+		None => return,
+	};
+	if power.usb {
+		*warned = false;
+	}
+	if *warned || !power.is_critical() {
+		return;
+	}
+	*warned = true;
+
+	let saved = auto_save.save(state);
+	state.show_low_battery_warning(saved);
+	state.render(screen);
+	platform().beep();
+
+	let mut elapsed = 0;
+	while elapsed < LOW_BATTERY_WARNING_MS {
+		delay(100);
+		elapsed += 100;
+		if input.pop_raw().is_some_and(|event| event.is_press()) {
+			break;
+		}
+	}
+
+	state.hide_low_battery_warning();
+	state.render(screen);
+}
+
+/// Shows the message of the state until a key is pressed or it times out
+fn show_toast<ScreenT: Screen, InputT: InputQueue>(
+	state: &mut State,
+	screen: &mut ScreenT,
+	input: &mut InputT,
+) {
+	state.render(screen);
+
+	let mut elapsed = 0;
+	while elapsed < TOAST_TIME_MS {
+		// Fade in the message if animations are enabled
+		let frame_time = if state.animating() {
+			FRAME_TIME_MS
+		} else {
+			100
+		};
+		delay(frame_time);
+		elapsed += frame_time;
+		if state.animating() {
+			state.tick(screen);
+		}
+
+		if input.pop_raw().is_some_and(|event| event.is_press()) {
+			break;
+		}
+	}
+
+	state.hide_toast();
+}
+
+/// Returns true if no key has been pressed for long enough to dim the display
+fn auto_dim_due(state: &State, last_input: Option<NaiveDateTime>) -> bool {
+	match (state.auto_dim_seconds(), last_input, NaiveDateTime::now()) {
+		(0, _, _) => false,
+		(seconds, Some(last_input), Ok(now)) => (now - last_input).num_seconds() >= seconds as i64,
+		_ => false,
+	}
+}
+
+/// Returns true if the calculator has been left alone long enough to turn off
+fn auto_off_due(last_input: Option<NaiveDateTime>) -> bool {
+	match (
+		platform().auto_off_minutes(),
+		last_input,
+		NaiveDateTime::now(),
+	) {
+		(Some(minutes), Some(last_input), Ok(now)) => (now - last_input).num_minutes() >= minutes,
+		_ => false,
+	}
+}
+
+pub fn calc_main<ScreenT: Screen, InputT: InputQueue>(mut screen: ScreenT, mut input: InputT) {
+	load_custom_fonts(&*open_file_store());
+	screen.clear_to_background();
+
+	// Restore the state from the last session. If it can't be read, start over
+	// with a fresh state.
+	let mut state = match read_saved_state() {
+		Some(data) => State::from_saved_state(&data).unwrap_or_else(|_| State::new()),
+		None => State::new(),
+	};
+	state.set_animation_supported(screen.supports_animation());
+	start_up(&mut state, &screen);
+	state.render(&mut screen);
+	rscalc_math::script::set_yield_hook(|| platform().computation_yield());
+	let mut last_input = NaiveDateTime::now().ok();
+	let mut low_battery_warned = false;
+	let mut auto_save = AutoSave::new(&state);
+
+	loop {
+		// Play any active animation until it completes or a key is pressed
+		while state.animating() {
+			if input.has_input() {
+				state.finish_animation();
+				state.render(&mut screen);
+				break;
+			}
+			delay(FRAME_TIME_MS);
+			state.tick(&mut screen);
+		}
+
+		platform().waiting_for_key();
+		if let Some(input_event) = state.wait_for_input(&mut input) {
+			last_input = NaiveDateTime::now().ok();
+			state.wake_display();
+			if state.input_may_be_slow(input_event) {
+				state.show_busy_indicator(&mut screen);
+			}
+			platform().computation_started();
+			let result = state.handle_input(input_event, &screen);
+			platform().computation_finished();
+			match result {
+				Ok(InputResult::Normal) => (),
+				Ok(InputResult::Suspend) => power_off(&mut state, &screen, &input, &mut auto_save),
+				Err(error) => state.show_error(error),
+			}
+			if state.toast().is_some() {
+				show_toast(&mut state, &mut screen, &mut input);
+			}
+			state.render(&mut screen);
+			platform().render_complete();
+			ring_due_alarms(&mut state, &mut screen, &mut input);
+			warn_low_battery(
+				&mut state,
+				&mut screen,
+				&mut input,
+				&mut low_battery_warned,
+				&mut auto_save,
+			);
+			auto_save.update(&state);
+		} else {
+			state.update_header(&mut screen);
+			ring_due_alarms(&mut state, &mut screen, &mut input);
+			warn_low_battery(
+				&mut state,
+				&mut screen,
+				&mut input,
+				&mut low_battery_warned,
+				&mut auto_save,
+			);
+			auto_save.update(&state);
+			if auto_dim_due(&state, last_input) {
+				state.dim_display();
+			}
+			if auto_off_due(last_input) {
+				power_off(&mut state, &screen, &input, &mut auto_save);
+				last_input = NaiveDateTime::now().ok();
+			}
+		}
+	}
+}
@@ -0,0 +1,451 @@
+use crate::dm42::edit::NumberEditor;
+use crate::dm42::screen::{RenderMode, Screen};
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
+use rscalc_layout::string::StringLayout;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::format::Format;
+use rscalc_math::matrix::{self, Matrix};
+use rscalc_math::number::ToNumber;
+use rscalc_math::value::Value;
+use rscalc_math::vector::{self, Vector};
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::ToString;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Most columns shown at once. Wider matrices scroll sideways.
+const MAX_VISIBLE_COLS: usize = 4;
+
+/// Names of the columns of a data set, shown above them
+const DATA_SET_COLUMNS: [&str; 2] = ["x", "y"];
+
+/// Change made by a soft key of the editor
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MatrixEditAction {
+	Left,
+	Right,
+	InsertRow,
+	InsertCol,
+	DeleteRow,
+	DeleteCol,
+}
+
+/// Grid of the elements of a matrix or vector being edited. The elements are
+/// kept as rows while editing, so that rows and columns can be inserted and
+/// deleted, and are made into a value when editing is done.
+pub struct MatrixEditor {
+	cells: Vec<Vec<Value>>,
+	vector: bool,
+	/// Whether this is a data set for statistics, with one point in each row and
+	/// a column for each variable
+	data_set: bool,
+	/// Number of stack entries that the edited value replaces. When it is zero
+	/// the value is pushed as a new value.
+	replaces: usize,
+	row: usize,
+	col: usize,
+	editor: Option<NumberEditor>,
+}
+
+impl MatrixEditor {
+	/// Edits a copy of a matrix or vector. Vectors are edited as a single row.
+	pub fn new(value: &Value) -> Result<Self> {
+		let (cells, vector) = match value {
+			Value::Matrix(matrix) => {
+				let mut cells = Vec::new();
+				for row in 0..matrix.rows() {
+					let mut cols = Vec::new();
+					for col in 0..matrix.cols() {
+						cols.push(matrix.get(row, col)?);
+					}
+					cells.push(cols);
+				}
+				(cells, false)
+			}
+			Value::Vector(vector) => {
+				let mut cols = Vec::new();
+				for idx in 0..vector.len() {
+					cols.push(vector.get(idx)?);
+				}
+				([cols].to_vec(), true)
+			}
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		if cells.is_empty() || cells[0].is_empty() {
+			return Err(Error::DimensionMismatch);
+		}
+		Ok(MatrixEditor {
+			cells,
+			vector,
+			data_set: false,
+			replaces: 1,
+			row: 0,
+			col: 0,
+			editor: None,
+		})
+	}
+
+	/// Edits a new 1×1 matrix holding zero
+	pub fn new_matrix() -> Self {
+		MatrixEditor {
+			cells: [[Value::Number(0.into())].to_vec()].to_vec(),
+			vector: false,
+			data_set: false,
+			replaces: 0,
+			row: 0,
+			col: 0,
+			editor: None,
+		}
+	}
+
+	/// Edits a copy of a data set. A vector is a data set of one variable, with
+	/// one element to a point, and a matrix of two columns holds x and y.
+	pub fn new_data_set(value: &Value) -> Result<Self> {
+		let mut editor = match value {
+			Value::Vector(vector) => {
+				let mut cells = Vec::new();
+				for idx in 0..vector.len() {
+					cells.push([vector.get(idx)?].to_vec());
+				}
+				Self::data_set_from_cells(cells)?
+			}
+			Value::Matrix(matrix) if matrix.cols() == 2 => Self::new(value)?,
+			Value::Matrix(_) => return Err(Error::DimensionMismatch),
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		editor.data_set = true;
+		Ok(editor)
+	}
+
+	/// Edits the data set made by pairing a vector of x values with a vector of
+	/// y values. It replaces both vectors when editing is done.
+	pub fn new_paired_data_set(x_values: &Vector, y_values: &Vector) -> Result<Self> {
+		if x_values.len() != y_values.len() {
+			return Err(Error::DimensionMismatch);
+		}
+		let mut cells = Vec::new();
+		for idx in 0..x_values.len() {
+			cells.push([x_values.get(idx)?, y_values.get(idx)?].to_vec());
+		}
+		let mut editor = Self::data_set_from_cells(cells)?;
+		editor.replaces = 2;
+		Ok(editor)
+	}
+
+	/// Edits a new data set of x and y holding a single point at zero
+	pub fn new_data_set_empty() -> Self {
+		let zero = Value::Number(0.into());
+		let mut editor = Self::new_matrix();
+		editor.cells = [[zero.clone(), zero].to_vec()].to_vec();
+		editor.data_set = true;
+		editor
+	}
+
+	fn data_set_from_cells(cells: Vec<Vec<Value>>) -> Result<Self> {
+		if cells.is_empty() {
+			return Err(Error::DimensionMismatch);
+		}
+		Ok(MatrixEditor {
+			cells,
+			vector: false,
+			data_set: true,
+			replaces: 1,
+			row: 0,
+			col: 0,
+			editor: None,
+		})
+	}
+
+	fn rows(&self) -> usize {
+		self.cells.len()
+	}
+
+	fn cols(&self) -> usize {
+		self.cells[0].len()
+	}
+
+	/// Makes the edited elements into a matrix or vector. A data set of one
+	/// variable is made into a vector.
+	pub fn to_value(&self) -> Result<Value> {
+		if self.vector {
+			let mut vector = Vector::new()?;
+			for value in &self.cells[0] {
+				vector.push(value.clone())?;
+			}
+			Ok(Value::Vector(vector))
+		} else if self.data_set && self.cols() == 1 {
+			let mut vector = Vector::new()?;
+			for cols in &self.cells {
+				vector.push(cols[0].clone())?;
+			}
+			Ok(Value::Vector(vector))
+		} else {
+			let mut matrix = Matrix::new(self.rows(), self.cols())?;
+			for (row, cols) in self.cells.iter().enumerate() {
+				for (col, value) in cols.iter().enumerate() {
+					matrix.set(row, col, value.clone())?;
+				}
+			}
+			Ok(Value::Matrix(matrix))
+		}
+	}
+
+	pub fn replaces(&self) -> usize {
+		self.replaces
+	}
+
+	pub fn editing(&self) -> bool {
+		self.editor.is_some()
+	}
+
+	/// Editor of the number in the selected element, which starts out empty
+	pub fn number_editor(&mut self, format: &Format) -> &mut NumberEditor {
+		self.editor.get_or_insert_with(|| NumberEditor::new(format))
+	}
+
+	pub fn cancel_edit(&mut self) {
+		self.editor = None;
+	}
+
+	/// Puts the number being entered into the selected element
+	pub fn commit_edit(&mut self) {
+		if let Some(editor) = self.editor.take() {
+			self.cells[self.row][self.col] = Value::Number(editor.number());
+		}
+	}
+
+	pub fn selected(&self) -> &Value {
+		&self.cells[self.row][self.col]
+	}
+
+	pub fn set_selected(&mut self, value: Value) {
+		self.cells[self.row][self.col] = value;
+	}
+
+	/// Moves the selection, stopping at the edges. A number being entered is
+	/// put into the element that it was entered for.
+	pub fn move_by(&mut self, rows: isize, cols: isize) {
+		self.commit_edit();
+		let clamp = |pos: usize, delta: isize, len: usize| {
+			(pos as isize + delta).clamp(0, len as isize - 1) as usize
+		};
+		self.row = clamp(self.row, rows, self.rows());
+		self.col = clamp(self.col, cols, self.cols());
+	}
+
+	/// Moves to the next element in reading order, after entering a number
+	pub fn advance(&mut self) {
+		self.commit_edit();
+		if self.col + 1 < self.cols() {
+			self.col += 1;
+		} else if self.row + 1 < self.rows() {
+			self.row += 1;
+			self.col = 0;
+		}
+	}
+
+	pub fn apply(&mut self, action: MatrixEditAction) -> Result<()> {
+		self.commit_edit();
+		let zero = Value::Number(0.into());
+		match action {
+			MatrixEditAction::Left => self.move_by(0, -1),
+			MatrixEditAction::Right => self.move_by(0, 1),
+			MatrixEditAction::InsertRow => {
+				if self.vector {
+					return Err(Error::DimensionMismatch);
+				}
+				self.check_size(self.rows() + 1, self.cols())?;
+				let cols = self.cols();
+				self.cells
+					.insert(self.row + 1, core::iter::repeat_n(zero, cols).collect());
+				self.row += 1;
+			}
+			MatrixEditAction::InsertCol | MatrixEditAction::DeleteCol if self.data_set => {
+				return Err(Error::DimensionMismatch);
+			}
+			MatrixEditAction::InsertCol => {
+				self.check_size(self.rows(), self.cols() + 1)?;
+				for cols in &mut self.cells {
+					cols.insert(self.col + 1, zero.clone());
+				}
+				self.col += 1;
+			}
+			MatrixEditAction::DeleteRow => {
+				if self.rows() == 1 {
+					return Err(Error::DimensionMismatch);
+				}
+				self.cells.remove(self.row);
+				self.row = core::cmp::min(self.row, self.rows() - 1);
+			}
+			MatrixEditAction::DeleteCol => {
+				if self.cols() == 1 {
+					return Err(Error::DimensionMismatch);
+				}
+				for cols in &mut self.cells {
+					cols.remove(self.col);
+				}
+				self.col = core::cmp::min(self.col, self.cols() - 1);
+			}
+		}
+		Ok(())
+	}
+
+	fn check_size(&self, rows: usize, cols: usize) -> Result<()> {
+		if (self.vector && cols > vector::MAX_CAPACITY)
+			|| (self.data_set && cols == 1 && rows > vector::MAX_CAPACITY)
+		{
+			Err(Error::VectorTooLarge)
+		} else if rows * cols > matrix::MAX_CAPACITY {
+			Err(Error::MatrixTooLarge)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Draws the part of the grid around the selected element into `area`. The
+	/// selected element is drawn inverted, showing the number being entered if
+	/// there is one.
+	pub fn render(&self, format: &Format, screen: &mut dyn Screen, area: &Rect) {
+		let metrics = screen.metrics();
+		let title_height = metrics.height(Font::Smallest) + 2;
+		let header_height = if self.data_set {
+			title_height * 2
+		} else {
+			title_height
+		};
+		let line_height = metrics.height(Font::Small) + 2;
+		let label_width = metrics.width(Font::Smallest, "0000");
+		let visible_rows = core::cmp::max(1, (area.h - header_height) / line_height) as usize;
+		let visible_cols = core::cmp::min(self.cols(), MAX_VISIBLE_COLS);
+		let cell_width = (area.w - label_width) / visible_cols as i32;
+
+		// Scroll just far enough to show the selection
+		let top = (self.row + 1).saturating_sub(visible_rows);
+		let left = (self.col + 1).saturating_sub(visible_cols);
+
+		let (title, position) = if self.data_set {
+			let points = if self.rows() == 1 {
+				" point"
+			} else {
+				" points"
+			};
+			(
+				"Data ".to_string() + &self.rows().to_number().to_string() + points,
+				"#".to_string()
+					+ &(self.row + 1).to_number().to_string()
+					+ " " + DATA_SET_COLUMNS[self.col],
+			)
+		} else {
+			let kind = if self.vector { "Vector " } else { "Matrix " };
+			(
+				kind.to_string()
+					+ &self.rows().to_number().to_string()
+					+ "×" + &self.cols().to_number().to_string(),
+				"r".to_string()
+					+ &(self.row + 1).to_number().to_string()
+					+ " c" + &(self.col + 1).to_number().to_string(),
+			)
+		};
+		let header_rect = Rect {
+			x: area.x,
+			y: area.y,
+			w: area.w,
+			h: title_height,
+		};
+
+		let mut renderer = screen.renderer(RenderMode::Normal);
+		renderer.erase(area);
+		Layout::LeftAlign(Box::new(Layout::Text(
+			title,
+			Font::Smallest,
+			TokenType::Label,
+		)))
+		.render(&mut renderer, header_rect.clone(), area);
+		Layout::Text(position, Font::Smallest, TokenType::Label).render(
+			&mut renderer,
+			header_rect,
+			area,
+		);
+		if self.data_set {
+			for (col, name) in DATA_SET_COLUMNS.iter().enumerate().take(self.cols()) {
+				Layout::Text(
+					name.to_string(),
+					Font::Smallest,
+					TokenType::Label,
+				)
+				.render(
+					&mut renderer,
+					Rect {
+						x: area.x + label_width + col as i32 * cell_width,
+						y: area.y + title_height,
+						w: cell_width,
+						h: title_height,
+					},
+					area,
+				);
+			}
+		}
+
+		for row in top..core::cmp::min(self.rows(), top + visible_rows) {
+			let y = area.y + header_height + (row - top) as i32 * line_height;
+			let mut renderer = screen.renderer(RenderMode::Normal);
+			Layout::LeftAlign(Box::new(Layout::Text(
+				(row + 1).to_number().to_string(),
+				Font::Smallest,
+				TokenType::Label,
+			)))
+			.render(
+				&mut renderer,
+				Rect {
+					x: area.x,
+					y,
+					w: label_width,
+					h: line_height,
+				},
+				area,
+			);
+
+			for col in left..core::cmp::min(self.cols(), left + visible_cols) {
+				let selected = row == self.row && col == self.col;
+				let rect = Rect {
+					x: area.x + label_width + (col - left) as i32 * cell_width,
+					y,
+					w: cell_width,
+					h: line_height,
+				};
+				let text = match (&self.editor, selected) {
+					(Some(editor), true) => editor.to_string(format),
+					_ => self.cells[row][col].format(format).to_string(),
+				};
+				let layout = text.ellipsis_layout(
+					Font::Small,
+					TokenType::Text,
+					screen.metrics(),
+					cell_width - 4,
+				);
+				let mut renderer = screen.renderer(if selected {
+					RenderMode::Selected
+				} else {
+					RenderMode::Normal
+				});
+				if selected {
+					renderer.erase(&rect);
+				}
+				layout.render(
+					&mut renderer,
+					Rect {
+						x: rect.x,
+						y: rect.y,
+						w: rect.w - 2,
+						h: rect.h,
+					},
+					area,
+				);
+			}
+		}
+	}
+}
@@ -1,25 +1,34 @@
+use crate::dm42::catalog::stack_effect_preview;
+use crate::dm42::font::bitmap_font;
 use crate::dm42::functions::Function;
-use crate::dm42::screen::{RenderMode, Screen};
-use crate::dm42::state::{State, StatusBarLeftDisplayType};
+use crate::dm42::locale::tr;
+use crate::dm42::platform::platform;
+use crate::dm42::screen::{theme, Color, RenderMode, Screen};
+use crate::dm42::settings::{EnterMode, StartupMode, StatusBarLeftDisplayType};
+use crate::dm42::state::State;
 use core::cell::RefCell;
+use intel_dfp::RoundingMode;
 use rscalc_layout::font::Font;
 use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
-use rscalc_math::format::AlternateFormatMode;
-use rscalc_math::number::Number;
+use rscalc_math::context::IntegerDivision;
+use rscalc_math::format::{
+	AlternateForm, AlternateFormatMode, AlternateValueClass, DecimalPointMode, FormatMode,
+	FractionDisplay, OverflowMode, MAX_DISPLAY_DIGITS,
+};
+use rscalc_math::number::{Number, ToNumber};
 use rscalc_math::storage::{available_bytes, free_bytes, reclaimable_bytes, used_bytes};
+use rscalc_math::unit::AngleUnit;
 
-#[cfg(not(feature = "dm42"))]
-use std::borrow::Cow;
-
-#[cfg(feature = "dm42")]
-use alloc::borrow::Cow;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::boxed::Box;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::string::{String, ToString};
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
 
+/// Narrowest that a column of a menu is made, in pixels
+const MIN_COLUMN_WIDTH: i32 = 100;
+
 #[derive(PartialEq, Eq, Clone)]
 pub enum MenuItemFunction {
 	Action(Function),
@@ -36,6 +45,7 @@ pub enum MenuItemLayout {
 pub struct MenuItem {
 	pub layout: MenuItemLayout,
 	pub function: MenuItemFunction,
+	pub help: Option<&'static str>,
 }
 
 impl MenuItem {
@@ -43,6 +53,11 @@ impl MenuItem {
 		Layout::LeftAlign(Box::new(Layout::Text(text, Font::Small, TokenType::Text)))
 	}
 
+	/// Layout of a setting showing its name and value, both translated
+	pub fn setting_layout(name: &str, value: &str) -> Layout {
+		MenuItem::string_layout(tr(name).to_string() + "   [" + tr(value) + "]")
+	}
+
 	pub fn string_layout_small(text: String) -> Layout {
 		Layout::LeftAlign(Box::new(Layout::Text(
 			text,
@@ -53,15 +68,26 @@ impl MenuItem {
 
 	pub fn static_string_layout(text: &'static str) -> Layout {
 		Layout::LeftAlign(Box::new(Layout::StaticText(
-			text,
+			tr(text),
 			Font::Small,
 			TokenType::Text,
 		)))
 	}
 
+	/// Text of the item if it is a fixed label, for searching the menus it is in
+	pub fn label(&self) -> Option<&'static str> {
+		match &self.layout {
+			MenuItemLayout::Static(Layout::LeftAlign(layout)) => match layout.as_ref() {
+				Layout::StaticText(text, _, _) => Some(text),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
 	pub fn static_string_layout_small(text: &'static str) -> Layout {
 		Layout::LeftAlign(Box::new(Layout::StaticText(
-			text,
+			tr(text),
 			Font::Smallest,
 			TokenType::Text,
 		)))
@@ -71,6 +97,32 @@ impl MenuItem {
 pub struct MenuRenderCache {
 	initial_render: bool,
 	rendered_selection: Option<usize>,
+	rendered_page: Option<usize>,
+	items_per_page: usize,
+	bottom_height: i32,
+	/// Layouts of the dynamic items, along with the state generation and screen
+	/// width that they were built for
+	layouts: Vec<Option<Layout>>,
+	layout_key: Option<(u32, i32)>,
+	item_height: Option<i32>,
+	/// Index and area of each item on the page that was last drawn
+	item_rects: Vec<(usize, Rect)>,
+}
+
+impl MenuRenderCache {
+	fn new() -> Self {
+		MenuRenderCache {
+			initial_render: true,
+			rendered_selection: None,
+			rendered_page: None,
+			items_per_page: 0,
+			bottom_height: 0,
+			layouts: Vec::new(),
+			layout_key: None,
+			item_height: None,
+			item_rects: Vec::new(),
+		}
+	}
 }
 
 pub struct Menu {
@@ -79,21 +131,22 @@ pub struct Menu {
 	bottom: Option<Box<dyn Fn(&State, &dyn Screen) -> Layout>>,
 	selection: usize,
 	columns: usize,
+	show_help: bool,
+	stack_effects: bool,
 	cache: RefCell<MenuRenderCache>,
 }
 
 impl Menu {
 	pub fn new(title: &str, items: Vec<MenuItem>) -> Self {
 		Menu {
-			title: title.to_string(),
+			title: tr(title).to_string(),
 			items,
 			bottom: None,
 			selection: 0,
 			columns: 1,
-			cache: RefCell::new(MenuRenderCache {
-				initial_render: true,
-				rendered_selection: None,
-			}),
+			show_help: false,
+			stack_effects: false,
+			cache: RefCell::new(MenuRenderCache::new()),
 		}
 	}
 
@@ -103,15 +156,14 @@ impl Menu {
 		bottom: Box<dyn Fn(&State, &dyn Screen) -> Layout>,
 	) -> Self {
 		Menu {
-			title: title.to_string(),
+			title: tr(title).to_string(),
 			items,
 			bottom: Some(bottom),
 			selection: 0,
 			columns: 1,
-			cache: RefCell::new(MenuRenderCache {
-				initial_render: true,
-				rendered_selection: None,
-			}),
+			show_help: false,
+			stack_effects: false,
+			cache: RefCell::new(MenuRenderCache::new()),
 		}
 	}
 
@@ -119,6 +171,13 @@ impl Menu {
 		self.columns = cols;
 	}
 
+	/// Number of columns shown, which is fewer than were asked for on screens
+	/// too narrow to fit them
+	fn visible_columns(&self, screen: &dyn Screen) -> usize {
+		let fit = core::cmp::max(screen.width() / MIN_COLUMN_WIDTH, 1) as usize;
+		core::cmp::min(self.columns, fit)
+	}
+
 	pub fn up(&mut self) {
 		self.selection = if self.selection == 0 {
 			self.items.len() - 1
@@ -135,6 +194,68 @@ impl Menu {
 		};
 	}
 
+	fn items_per_page(&self) -> usize {
+		// Page size is determined during rendering, as it depends on the screen
+		// and item layouts. Before the first render, treat the menu as one page.
+		let items_per_page = self.cache.borrow().items_per_page;
+		if items_per_page == 0 {
+			self.items.len()
+		} else {
+			items_per_page
+		}
+	}
+
+	pub fn page_count(&self) -> usize {
+		match self.items_per_page() {
+			0 => 1,
+			items_per_page => self.items.len().div_ceil(items_per_page),
+		}
+	}
+
+	pub fn page(&self) -> usize {
+		self.selection
+			.checked_div(self.items_per_page())
+			.unwrap_or(0)
+	}
+
+	pub fn prev_page(&mut self) {
+		let page_count = self.page_count();
+		if page_count <= 1 {
+			return;
+		}
+		let page = if self.page() == 0 {
+			page_count - 1
+		} else {
+			self.page() - 1
+		};
+		self.selection = page * self.items_per_page();
+	}
+
+	pub fn next_page(&mut self) {
+		let page_count = self.page_count();
+		if page_count <= 1 {
+			return;
+		}
+		let page = if (self.page() + 1) >= page_count {
+			0
+		} else {
+			self.page() + 1
+		};
+		self.selection = page * self.items_per_page();
+	}
+
+	pub fn into_items(self) -> Vec<MenuItem> {
+		self.items
+	}
+
+	pub fn item_count(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn item_function(&self, idx: usize) -> Option<&MenuItemFunction> {
+		self.items.get(idx).map(|item| &item.function)
+	}
+
 	pub fn selected_function(&self) -> MenuItemFunction {
 		self.items[self.selection].function.clone()
 	}
@@ -148,23 +269,181 @@ impl Menu {
 		}
 	}
 
+	pub fn selection(&self) -> usize {
+		self.selection
+	}
+
 	pub fn set_selection(&mut self, idx: usize) {
 		if idx < self.items.len() {
 			self.selection = idx;
 		}
 	}
 
+	/// Item drawn at a point on the screen when the menu was last rendered
+	pub fn item_at(&self, x: i32, y: i32) -> Option<usize> {
+		self.cache
+			.borrow()
+			.item_rects
+			.iter()
+			.find(|(_, rect)| rect.contains(x, y))
+			.map(|(idx, _)| *idx)
+	}
+
+	/// Height of the title bar drawn across the top of menus
+	pub fn title_height(screen: &dyn Screen) -> i32 {
+		screen.metrics().height(Font::Small) + 3
+	}
+
 	pub fn force_refresh(&self) {
 		self.cache.borrow_mut().initial_render = true;
 	}
 
+	/// Shows a preview of what the selected function would do with the values on
+	/// the stack along the bottom of the menu
+	pub fn show_stack_effects(&mut self) {
+		self.stack_effects = true;
+	}
+
+	pub fn toggle_help(&mut self) {
+		if self.show_help {
+			self.hide_help();
+		} else {
+			self.show_help = true;
+		}
+	}
+
+	pub fn hide_help(&mut self) {
+		if self.show_help {
+			// The help popup covers the items, so redraw the whole menu
+			self.show_help = false;
+			self.force_refresh();
+		}
+	}
+
+	fn render_help(&self, help: &str, screen: &mut dyn Screen) {
+		let font = bitmap_font(Font::Small);
+		let text_width = font.width(help);
+		let rect = Rect {
+			x: (screen.width() - (text_width + 16)) / 2,
+			y: (screen.height() - (font.height + 12)) / 2,
+			w: text_width + 16,
+			h: font.height + 12,
+		}
+		.clipped_to(&screen.screen_rect());
+
+		// Draw popup with a border around it
+		let theme = theme();
+		screen.fill(&rect, theme.color(Color::MenuText));
+		let inner_rect = Rect {
+			x: rect.x + 2,
+			y: rect.y + 2,
+			w: rect.w - 4,
+			h: rect.h - 4,
+		};
+		screen.fill(&inner_rect, theme.color(Color::MenuBackground));
+		font.draw(
+			screen,
+			&inner_rect,
+			rect.x + 8,
+			rect.y + 6,
+			help,
+			theme.color(Color::MenuText),
+		);
+	}
+
 	pub fn render(&self, state: &State, screen: &mut dyn Screen) {
+		let top = Self::title_height(screen);
+
+		// Gather layouts for the items, these are needed to determine how many
+		// items fit on a single page of the menu. Dynamic layouts can be slow to
+		// build, so they are kept until the state changes instead of being built
+		// again each time the selection moves.
+		let layout_key = (state.generation(), screen.width());
+		let mut dynamic_layouts = core::mem::take(&mut self.cache.borrow_mut().layouts);
+		if self.cache.borrow().layout_key != Some(layout_key)
+			|| dynamic_layouts.len() != self.items.len()
+		{
+			dynamic_layouts = self
+				.items
+				.iter()
+				.map(|item| match &item.layout {
+					MenuItemLayout::Static(_) => None,
+					MenuItemLayout::Dynamic(func) => Some(func(state, screen)),
+				})
+				.collect();
+			let mut cache = self.cache.borrow_mut();
+			cache.layout_key = Some(layout_key);
+			cache.item_height = None;
+		}
+		let layouts: Vec<&Layout> = self
+			.items
+			.iter()
+			.zip(dynamic_layouts.iter())
+			.filter_map(|(item, dynamic_layout)| match &item.layout {
+				MenuItemLayout::Static(layout) => Some(layout),
+				MenuItemLayout::Dynamic(_) => dynamic_layout.as_ref(),
+			})
+			.collect();
+		let cached_height = self.cache.borrow().item_height;
+		let item_height = match cached_height {
+			Some(height) => height,
+			None => {
+				let height = layouts
+					.iter()
+					.map(|layout| layout.height(screen.metrics()))
+					.max()
+					.unwrap_or(0);
+				self.cache.borrow_mut().item_height = Some(height);
+				height
+			}
+		};
+
+		// The bottom layout is only rendered on initial render, but its height is
+		// needed to determine the page size.
+		let bottom = if self.cache.borrow().initial_render {
+			if let Some(bottom) = &self.bottom {
+				let bottom = bottom(state, screen);
+				self.cache.borrow_mut().bottom_height = bottom.height(screen.metrics());
+				Some(bottom)
+			} else {
+				None
+			}
+		} else {
+			None
+		};
+
+		if self.stack_effects {
+			self.cache.borrow_mut().bottom_height = screen.metrics().height(Font::Small) + 4;
+		}
+
+		// Determine how many items fit on each page. If the entire menu fits on the screen,
+		// the items are split evenly among the columns.
+		let available_height = screen.height() - top - self.cache.borrow().bottom_height;
+		let max_rows = if item_height > 0 {
+			core::cmp::max(available_height / item_height, 1) as usize
+		} else {
+			self.items.len()
+		};
+		let columns = self.visible_columns(screen);
+		let (rows, items_per_page) = if self.items.len() <= max_rows * columns {
+			(self.items.len().div_ceil(columns), self.items.len())
+		} else {
+			(max_rows, max_rows * columns)
+		};
+		self.cache.borrow_mut().items_per_page = items_per_page;
+
+		// Changing pages requires a full redraw of the menu
+		let page = self.page();
+		if self.cache.borrow().rendered_page != Some(page) {
+			self.cache.borrow_mut().initial_render = true;
+		}
+
 		let initial_render = self.cache.borrow().initial_render;
 		let rendered_selection = self.cache.borrow().rendered_selection;
 
 		if initial_render {
 			// On initial render, clear screen and draw title
-			screen.clear();
+			screen.clear_to_background();
 
 			let screen_rect = screen.screen_rect();
 			let mut renderer = screen.renderer(RenderMode::StatusBar);
@@ -183,9 +462,31 @@ impl Menu {
 				&screen_rect,
 			);
 
+			// Draw page indicator if there are multiple pages
+			let page_count = self.page_count();
+			if page_count > 1 {
+				let page_str = "▴▾ ".to_string()
+					+ &Number::Integer((page + 1).into()).to_string()
+					+ "/" + &Number::Integer(page_count.into()).to_string();
+				let width = renderer.metrics().width(Font::Smallest, &page_str);
+				renderer.draw_text(
+					screen_rect.w - width - 4,
+					(renderer.metrics().height(Font::Small)
+						- renderer.metrics().height(Font::Smallest))
+						/ 2,
+					&page_str,
+					Font::Smallest,
+					TokenType::Text,
+					&screen_rect,
+				);
+			}
+
 			// Draw bottom layout if present
-			if let Some(bottom) = &self.bottom {
-				let bottom = bottom(state, screen);
+			let bottom = match bottom {
+				Some(bottom) => Some(bottom),
+				None => self.bottom.as_ref().map(|bottom| bottom(state, screen)),
+			};
+			if let Some(bottom) = bottom {
 				let height = bottom.height(screen.metrics());
 				let rect = Rect {
 					x: 4,
@@ -198,21 +499,17 @@ impl Menu {
 			}
 		}
 
-		let rows = (self.items.len() + self.columns - 1) / self.columns;
-		let col_width = screen.width() / self.columns as i32;
+		let col_width = screen.width() / columns as i32;
 
-		let mut i = 0;
 		let mut row = 0;
-		let top = screen.metrics().height(Font::Small) + 3;
 		let mut x = 0;
 		let mut y = top;
 
-		for item in &self.items {
-			let layout = match &item.layout {
-				MenuItemLayout::Static(layout) => Cow::Borrowed(layout),
-				MenuItemLayout::Dynamic(func) => Cow::Owned(func(state, screen)),
-			};
-
+		let page_start = page * items_per_page;
+		let page_end = core::cmp::min(page_start + items_per_page, self.items.len());
+		let mut item_rects = core::mem::take(&mut self.cache.borrow_mut().item_rects);
+		item_rects.clear();
+		for (i, &layout) in layouts.iter().enumerate().take(page_end).skip(page_start) {
 			let screen_rect = screen.screen_rect();
 			let mut renderer = screen.renderer(if i == self.selection {
 				RenderMode::Selected
@@ -222,6 +519,15 @@ impl Menu {
 
 			// Get height of item
 			let height = layout.height(renderer.metrics());
+			item_rects.push((
+				i,
+				Rect {
+					x,
+					y,
+					w: col_width,
+					h: height,
+				},
+			));
 
 			// Render item if it has been updated
 			if initial_render
@@ -268,7 +574,6 @@ impl Menu {
 				layout.render(&mut renderer, rect.clone(), &rect);
 			}
 
-			i += 1;
 			row += 1;
 			y += height;
 
@@ -279,10 +584,47 @@ impl Menu {
 			}
 		}
 
+		// Work out the preview of the selected function again when it changes
+		if self.stack_effects && (initial_render || rendered_selection != Some(self.selection)) {
+			let preview =
+				stack_effect_preview(state, &self.items[self.selection].function, &*screen);
+			let height = self.cache.borrow().bottom_height;
+			let screen_rect = screen.screen_rect();
+			let mut renderer = screen.renderer(RenderMode::Normal);
+			renderer.erase(&Rect {
+				x: 0,
+				y: screen_rect.h - height,
+				w: screen_rect.w,
+				h: height,
+			});
+			if let Some(preview) = preview {
+				renderer.draw_text(
+					4,
+					screen_rect.h - height + 2,
+					&preview,
+					Font::Small,
+					TokenType::Text,
+					&screen_rect,
+				);
+			}
+		}
+
+		// Draw help for the selected item on top of the menu if requested
+		if self.show_help {
+			if let Some(help) = self.items[self.selection].help {
+				self.render_help(tr(help), screen);
+			}
+		}
+
 		screen.refresh();
 
-		self.cache.borrow_mut().rendered_selection = Some(self.selection);
-		self.cache.borrow_mut().initial_render = false;
+		drop(layouts);
+		let mut cache = self.cache.borrow_mut();
+		cache.layouts = dynamic_layouts;
+		cache.item_rects = item_rects;
+		cache.rendered_selection = Some(self.selection);
+		cache.rendered_page = Some(page);
+		cache.initial_render = false;
 	}
 }
 
@@ -291,16 +633,37 @@ pub fn setup_menu() -> Menu {
 
 	// Create setup menu items
 	items.push(MenuItem {
-		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Display Settings >")),
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Settings >")),
 		function: MenuItemFunction::InMenuAction(Function::SettingsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Diagnostics >")),
+		function: MenuItemFunction::InMenuAction(Function::DiagnosticsMenu),
+		help: None,
 	});
 
-	#[cfg(feature = "dm42")]
 	items.push(MenuItem {
-		layout: MenuItemLayout::Static(MenuItem::static_string_layout("System Settings >")),
-		function: MenuItemFunction::Action(Function::SystemMenu),
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Files >")),
+		function: MenuItemFunction::InMenuAction(Function::FileBrowser),
+		help: None,
 	});
 
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Print >")),
+		function: MenuItemFunction::InMenuAction(Function::PrintMenu),
+		help: None,
+	});
+
+	if platform().has_system_menu() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("System Settings >")),
+			function: MenuItemFunction::Action(Function::SystemMenu),
+			help: None,
+		});
+	}
+
 	// Return the menu object
 	Menu::new_with_bottom(
 		"Setup",
@@ -344,121 +707,592 @@ pub fn setup_menu() -> Menu {
 			))));
 
 			// Add temporary memory available
-			#[cfg(feature = "dm42")]
-			bottom_items.push(Layout::LeftAlign(Box::new(Layout::Text(
-				Number::Integer(crate::dm42::device::sys_free_mem().into()).to_string()
-					+ " bytes temporary memory",
-				Font::Smallest,
-				TokenType::Text,
-			))));
+			if let Some(free) = platform().free_system_memory() {
+				bottom_items.push(Layout::LeftAlign(Box::new(Layout::Text(
+					Number::Integer(free.into()).to_string() + " bytes temporary memory",
+					Font::Smallest,
+					TokenType::Text,
+				))));
+			}
 
 			Layout::Vertical(bottom_items)
 		}),
 	)
 }
 
+pub fn display_digits_menu(state: &State) -> Menu {
+	let mut items = Vec::new();
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("All Digits")),
+		function: MenuItemFunction::Action(Function::DisplayDigits(None)),
+		help: None,
+	});
+	for digits in 0..=MAX_DISPLAY_DIGITS {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::string_layout(
+				Number::Integer(digits.into()).to_string() + " Digits",
+			)),
+			function: MenuItemFunction::Action(Function::DisplayDigits(Some(digits))),
+			help: None,
+		});
+	}
+
+	let mut menu = Menu::new("Display Digits", items);
+	if let Some(digits) = state.context().format().display_digits {
+		menu.set_selection(digits + 1);
+	}
+	menu
+}
+
+/// Menu for choosing which alternate forms are shown for each class of value
+pub fn alternate_forms_menu() -> Menu {
+	let mut items = Vec::new();
+	for class in &AlternateValueClass::ALL {
+		for form in &AlternateForm::ALL {
+			if !class.supports(*form) {
+				continue;
+			}
+			let (class, form) = (*class, *form);
+			items.push(MenuItem {
+				layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+					MenuItem::string_layout(
+						class.to_str().to_string()
+							+ " " + form.to_str() + if state
+							.context()
+							.format()
+							.alt_forms
+							.is_enabled(class, form)
+						{
+							"   [On]"
+						} else {
+							"   [Off]"
+						},
+					)
+				})),
+				function: MenuItemFunction::InMenuAction(Function::AlternateFormToggle(
+					class, form,
+				)),
+				help: None,
+			});
+		}
+	}
+	Menu::new("Alternate Forms", items)
+}
+
+/// Root of the settings menu tree. Settings are grouped into sub-menus by the
+/// part of the calculator they affect.
 pub fn settings_menu() -> Menu {
 	let mut items = Vec::new();
 
-	// Create settings menu items
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Display >")),
+		function: MenuItemFunction::InMenuAction(Function::DisplaySettingsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Number Format >")),
+		function: MenuItemFunction::InMenuAction(Function::NumberFormatSettingsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Preferred Units >")),
+		function: MenuItemFunction::InMenuAction(Function::PreferredUnitsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Sounds >")),
+		function: MenuItemFunction::InMenuAction(Function::SoundSettingsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Language", state.language().to_str())
+		})),
+		function: MenuItemFunction::InMenuAction(Function::LanguageCycle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"24-hour Clock",
+				if state.context().format().time_24_hour {
+					"On"
+				} else {
+					"Off"
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::Time24HourToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Startup", match state.startup_mode() {
+						StartupMode::Restore => "Restore",
+						StartupMode::ClearStack => "Clear Stack",
+						StartupMode::RunProgram => "Run Program",
+					})
+		})),
+		function: MenuItemFunction::InMenuAction(Function::StartupModeCycle),
+		help: Some("What happens when the calculator is turned on. Clear Stack keeps settings and memory, and Run Program runs the recorded program."),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Custom Slots",
+				&Number::Integer(state.custom_slots().into()).to_string(),
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::CustomSlotCountCycle),
+		help: Some("Number of slots in the menu for assigning custom functions"),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Advanced >")),
+		function: MenuItemFunction::InMenuAction(Function::AdvancedSettingsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Exam Mode", if state.exam_mode() { "On" } else { "Off" })
+		})),
+		function: MenuItemFunction::InMenuAction(Function::ExamModeToggle),
+		help: Some("Puts away programs and stored data until exam mode is turned off again"),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Reset to Defaults")),
+		function: MenuItemFunction::Action(Function::ResetToDefaults),
+		help: None,
+	});
+
+	Menu::new("Settings", items)
+}
+
+/// Settings for the appearance of the screen
+pub fn display_settings_menu() -> Menu {
+	let mut items = Vec::new();
+
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Status Bar Text   ".to_string()
-					+ match state.status_bar_left_display() {
-						StatusBarLeftDisplayType::CurrentTime => "[Current Time]",
-						StatusBarLeftDisplayType::FreeMemory => "[Free Memory]",
-					},
+			MenuItem::setting_layout(
+				"Font Size",
+				match state.base_font() {
+					Font::Smallest => "Smallest",
+					Font::Small => "Small",
+					Font::Medium => "Medium",
+					Font::Large => "Large",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::FontSizeToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Show Status Bar",
+				if state.status_bar_enabled() {
+					"On"
+				} else {
+					"Off"
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::StatusBarToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Status Bar Text",
+				match state.status_bar_left_display() {
+					StatusBarLeftDisplayType::CurrentTime => "Current Time",
+					StatusBarLeftDisplayType::FreeMemory => "Free Memory",
+					StatusBarLeftDisplayType::FrameTime => "Frame Time",
+					StatusBarLeftDisplayType::Battery => "Battery",
+				},
 			)
 		})),
 		function: MenuItemFunction::InMenuAction(Function::StatusBarLeftDisplayToggle),
+		help: None,
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"24-hour Clock   ".to_string()
-					+ if state.context().format().time_24_hour {
-						"[On]"
-					} else {
-						"[Off]"
-					},
+			MenuItem::setting_layout(
+				"Show Empty Soft Keys",
+				if state.show_empty_soft_keys() {
+					"On"
+				} else {
+					"Off"
+				},
 			)
 		})),
-		function: MenuItemFunction::InMenuAction(Function::Time24HourToggle),
+		function: MenuItemFunction::InMenuAction(Function::ShowEmptySoftKeyToggle),
+		help: None,
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Stack Labels   ".to_string()
-					+ if state.context().format().stack_xyz {
-						"[x,y,z,4]"
-					} else {
-						"[1,2,3,4]"
-					},
+			MenuItem::setting_layout(
+				"Stack Labels",
+				if state.context().format().stack_xyz {
+					"x,y,z,4"
+				} else {
+					"1,2,3,4"
+				},
 			)
 		})),
 		function: MenuItemFunction::InMenuAction(Function::StackLabelXYZToggle),
+		help: None,
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Show Empty Soft Keys   ".to_string()
-					+ if state.function_keys().show_empty() {
-						"[On]"
-					} else {
-						"[Off]"
-					},
+			MenuItem::setting_layout(
+				"Mode Tags",
+				if state.context().format().mode_tags {
+					"On"
+				} else {
+					"Off"
+				},
 			)
 		})),
-		function: MenuItemFunction::InMenuAction(Function::ShowEmptySoftKeyToggle),
+		function: MenuItemFunction::InMenuAction(Function::ModeTagsToggle),
+		help: Some(
+			"Marks integers shown in another base and results computed in radians or gradians",
+		),
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Show Status Bar   ".to_string()
-					+ if state.status_bar_enabled() {
-						"[On]"
-					} else {
-						"[Off]"
-					},
+			MenuItem::setting_layout(
+				"Radicals",
+				if state.context().format().radicals {
+					"On"
+				} else {
+					"Off"
+				},
 			)
 		})),
-		function: MenuItemFunction::InMenuAction(Function::StatusBarToggle),
+		function: MenuItemFunction::InMenuAction(Function::RadicalsToggle),
+		help: Some("Shows square roots of integers in simplified form, such as 2√2 beside 2.828"),
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Font Size   ".to_string()
-					+ match state.base_font() {
-						Font::Smallest => "[Smallest]",
-						Font::Small => "[Small]",
-						Font::Medium => "[Medium]",
-						Font::Large => "[Large]",
-					},
+			MenuItem::setting_layout("Theme", state.theme().to_str())
+		})),
+		function: MenuItemFunction::InMenuAction(Function::ThemeToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Reduced Motion",
+				if state.reduced_motion() { "On" } else { "Off" },
 			)
 		})),
-		function: MenuItemFunction::InMenuAction(Function::FontSizeToggle),
+		function: MenuItemFunction::InMenuAction(Function::ReducedMotionToggle),
+		help: None,
 	});
 
 	items.push(MenuItem {
 		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
-			MenuItem::string_layout(
-				"Alternate Display   ".to_string()
-					+ match state.context().format().alt_mode {
-						AlternateFormatMode::Smart => "[Smart]",
-						AlternateFormatMode::Bottom => "[Bottom]",
-						AlternateFormatMode::Left => "[Left]",
-					},
+			MenuItem::setting_layout(
+				"Split Pane",
+				match state.split().pane() {
+					Some(pane) => pane.to_str(),
+					None => "Off",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::SplitPaneCycle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Split Size",
+				match state.split().pane_thirds() {
+					1 => "1/3",
+					_ => "2/3",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::SplitPaneResize),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Brightness >")),
+		function: MenuItemFunction::InMenuAction(Function::BrightnessMenu),
+		help: None,
+	});
+
+	Menu::new("Display", items)
+}
+
+/// Settings for how numbers are displayed and entered
+pub fn number_format_settings_menu() -> Menu {
+	let mut items = Vec::new();
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Display Mode",
+				match state.context().format().mode {
+					FormatMode::Normal => "Normal",
+					FormatMode::Rational => "Fraction",
+					FormatMode::Fixed => "Fixed",
+					FormatMode::Scientific => "Scientific",
+					FormatMode::Engineering => "Engineering",
+					FormatMode::Significant => "Significant",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::DisplayModeCycle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Display Digits >")),
+		function: MenuItemFunction::InMenuAction(Function::DisplayDigitsMenu),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Integer Radix",
+				match state.context().format().integer_radix {
+					16 => "Hex",
+					8 => "Octal",
+					2 => "Binary",
+					_ => "Decimal",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::IntegerRadixCycle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Angle Mode",
+				match state.context().angle_mode() {
+					AngleUnit::Degrees => "Degrees",
+					AngleUnit::Radians => "Radians",
+					AngleUnit::Gradians => "Gradians",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::AngleModeCycle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Digit Separator",
+				state
+					.context()
+					.format()
+					.digit_separator
+					.label(state.context().format().decimal_point),
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::DigitSeparatorToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Rounding",
+				match state.context().format().rounding {
+					RoundingMode::NearestAway => "Half Up",
+					RoundingMode::NearestEven => "Half Even",
+					RoundingMode::TowardZero => "To Zero",
+					RoundingMode::Up => "Up",
+					RoundingMode::Down => "Down",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::RoundingModeCycle),
+		help: Some("Rounding of decimal results and of displayed digits"),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Decimal Point",
+				match state.context().format().decimal_point {
+					DecimalPointMode::Period => "Period",
+					DecimalPointMode::Comma => "Comma",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::DecimalPointToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Long Integers",
+				match state.context().format().overflow {
+					OverflowMode::Wrap => "Wrap",
+					OverflowMode::Ellipsis => "Ellipsis",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::OverflowModeToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Fractions",
+				match state.context().format().fraction_display {
+					FractionDisplay::Auto => "Auto",
+					FractionDisplay::Fraction => "Always",
+					FractionDisplay::Decimal => "Decimal",
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::FractionDisplayCycle),
+		help: Some(
+			"Whether fractions too wide to fit are wrapped or shown as decimals marked with ≈",
+		),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Alternate Display",
+				match state.context().format().alt_mode {
+					AlternateFormatMode::Smart => "Smart",
+					AlternateFormatMode::Bottom => "Bottom",
+					AlternateFormatMode::Left => "Left",
+				},
 			)
 		})),
 		function: MenuItemFunction::InMenuAction(Function::AlternateFormatModeToggle),
+		help: None,
 	});
 
-	// Return the menu object
-	Menu::new("Settings", items)
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Alternate Forms >")),
+		function: MenuItemFunction::InMenuAction(Function::AlternateFormsMenu),
+		help: None,
+	});
+
+	Menu::new("Number Format", items)
+}
+
+/// Settings that trade off performance or exactness
+pub fn advanced_settings_menu() -> Menu {
+	let mut items = Vec::new();
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Stack Rendering",
+				if state.stack_render_cache_enabled() {
+					"Cached"
+				} else {
+					"Legacy"
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::StackRenderCacheToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Rational Reduction",
+				if state.context().deferred_reduction() {
+					"Deferred"
+				} else {
+					"Always"
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::DeferredReductionToggle),
+		help: None,
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout(
+				"Outside Real Domain",
+				if state.context().complex_results() {
+					"Complex"
+				} else {
+					"Error"
+				},
+			)
+		})),
+		function: MenuItemFunction::InMenuAction(Function::ComplexResultsToggle),
+		help: Some("Whether functions such as the square root of a negative number give complex results or an error"),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("ENTER Key", match state.enter_mode() {
+						EnterMode::Terminate => "Terminate",
+						EnterMode::Duplicate => "Duplicate",
+					})
+		})),
+		function: MenuItemFunction::InMenuAction(Function::EnterModeToggle),
+		help: Some("Whether ENTER after typing a number only ends entry as in RPL, or also copies the number so that the next number typed replaces the copy as in classic RPN"),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Integer Division", match state.context().integer_division() {
+						IntegerDivision::Rational => "Fraction",
+						IntegerDivision::Decimal => "Decimal",
+						IntegerDivision::QuotientRemainder => "Quot+Rem",
+					})
+		})),
+		function: MenuItemFunction::InMenuAction(Function::IntegerDivisionCycle),
+		help: Some("Result of dividing two integers that don't divide evenly. Quot+Rem gives the quotient followed by the remainder."),
+	});
+
+	items.push(MenuItem {
+		layout: MenuItemLayout::Dynamic(Box::new(|state, _screen| {
+			MenuItem::setting_layout("Auto Save", &auto_save_string(state.auto_save_minutes()))
+		})),
+		function: MenuItemFunction::InMenuAction(Function::AutoSaveCycle),
+		help: Some("Saves the state this often while it is changing, so that little is lost if the calculator crashes or the battery is pulled"),
+	});
+
+	Menu::new("Advanced", items)
+}
+
+fn auto_save_string(minutes: u8) -> String {
+	if minutes == 0 {
+		"Off".to_string()
+	} else {
+		minutes.to_number().to_string() + " min"
+	}
 }
@@ -0,0 +1,266 @@
+use crate::dm42::graph::ProgramStep;
+use crate::dm42::screen::{RenderMode, Screen};
+use crate::dm42::state::State;
+use rscalc_layout::font::{Font, FontMetrics};
+use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
+use rscalc_layout::value::ValueLayout;
+use rscalc_math::constant::Constant;
+use rscalc_math::error::Result;
+use rscalc_math::number::ToNumber;
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::ToString;
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Sizes of the auxiliary pane as a fraction of the stack area, in thirds
+const MIN_PANE_THIRDS: i32 = 1;
+const MAX_PANE_THIRDS: i32 = 2;
+
+/// Content shown in the auxiliary pane of a split screen
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AuxPane {
+	Graph,
+	Statistics,
+	Program,
+	Constants,
+}
+
+impl AuxPane {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			AuxPane::Graph => "Graph",
+			AuxPane::Statistics => "Stats",
+			AuxPane::Program => "Program",
+			AuxPane::Constants => "Constants",
+		}
+	}
+
+	/// Pane that follows this one when cycling, or `None` to close the split
+	fn next(&self) -> Option<AuxPane> {
+		match self {
+			AuxPane::Graph => Some(AuxPane::Statistics),
+			AuxPane::Statistics => Some(AuxPane::Program),
+			AuxPane::Program => Some(AuxPane::Constants),
+			AuxPane::Constants => None,
+		}
+	}
+}
+
+/// Divides the stack area between the stack and an optional auxiliary pane
+/// placed above it
+pub struct SplitLayout {
+	pane: Option<AuxPane>,
+	pane_thirds: i32,
+}
+
+impl SplitLayout {
+	pub fn new() -> Self {
+		SplitLayout {
+			pane: None,
+			pane_thirds: MIN_PANE_THIRDS,
+		}
+	}
+
+	pub fn pane(&self) -> Option<AuxPane> {
+		self.pane
+	}
+
+	/// Opens the split with the first pane, or moves to the next pane. The split
+	/// is closed after the last pane.
+	pub fn cycle_pane(&mut self) {
+		self.pane = match self.pane {
+			Some(pane) => pane.next(),
+			None => Some(AuxPane::Graph),
+		};
+	}
+
+	pub fn pane_thirds(&self) -> i32 {
+		self.pane_thirds
+	}
+
+	/// Cycles through the available pane sizes
+	pub fn resize(&mut self) {
+		self.pane_thirds = if self.pane_thirds >= MAX_PANE_THIRDS {
+			MIN_PANE_THIRDS
+		} else {
+			self.pane_thirds + 1
+		};
+	}
+
+	/// Splits `area` into the area for the stack and the area for the auxiliary
+	/// pane, if the split is open. The pane area includes a separator line at
+	/// its bottom edge.
+	pub fn split(&self, area: &Rect) -> (Rect, Option<Rect>) {
+		if self.pane.is_none() {
+			return (area.clone(), None);
+		}
+
+		let pane_height = (area.h * self.pane_thirds) / 3;
+		let pane = Rect {
+			x: area.x,
+			y: area.y,
+			w: area.w,
+			h: pane_height,
+		};
+		let stack = Rect {
+			x: area.x,
+			y: area.y + pane_height,
+			w: area.w,
+			h: area.h - pane_height,
+		};
+		(stack, Some(pane))
+	}
+}
+
+/// Builds the layout of a text based pane. Returns `None` for the graph pane,
+/// which is drawn directly.
+pub fn pane_layout(
+	pane: AuxPane,
+	state: &State,
+	metrics: &dyn FontMetrics,
+	rect: &Rect,
+) -> Option<Layout> {
+	let width = rect.w - 8;
+	let mut lines = Vec::new();
+	match pane {
+		AuxPane::Graph => return None,
+		AuxPane::Statistics => match state.context().top() {
+			Ok(Value::Vector(vector)) => {
+				lines.push(value_line(
+					"n: ",
+					Ok(Value::Number(vector.len().to_number())),
+					state,
+					metrics,
+					width,
+				));
+				lines.push(value_line("Σ: ", vector.sum(), state, metrics, width));
+				lines.push(value_line("Mean: ", vector.mean(), state, metrics, width));
+			}
+			_ => lines.push(message_line("Vector of data required in X")),
+		},
+		AuxPane::Program => match state.recorded_program() {
+			Some(program) => {
+				// Show as many of the final steps of the program as will fit
+				let available = rect.h - metrics.height(Font::Smallest);
+				let max_lines = core::cmp::max(1, available / metrics.height(Font::Small)) as usize;
+				let start = program.len().saturating_sub(max_lines);
+				for (idx, step) in program.iter().enumerate().skip(start) {
+					let text = match step {
+						ProgramStep::Input(input) => input.to_string(),
+						ProgramStep::Function(func) => func.to_string(state),
+					};
+					lines.push(Layout::LeftAlign(Box::new(Layout::Text(
+						(idx + 1).to_number().to_string() + ": " + &text,
+						Font::Small,
+						TokenType::Text,
+					))));
+				}
+			}
+			None => lines.push(message_line("No program recorded")),
+		},
+		AuxPane::Constants => {
+			for constant in &[Constant::Pi, Constant::SpeedOfLight] {
+				lines.push(value_line(
+					&(constant.to_str().to_string() + " = "),
+					Ok(constant.value()),
+					state,
+					metrics,
+					width,
+				));
+			}
+		}
+	}
+
+	let items = vec![
+		Layout::LeftAlign(Box::new(Layout::StaticText(
+			pane.to_str(),
+			Font::Smallest,
+			TokenType::Label,
+		))),
+		Layout::Vertical(lines),
+	];
+	Some(Layout::Vertical(items))
+}
+
+/// Renders a pane into `rect`, which includes the separator line below the pane
+pub fn render_pane(
+	pane: AuxPane,
+	layout: Option<&Layout>,
+	state: &State,
+	screen: &mut dyn Screen,
+	rect: &Rect,
+) {
+	let content = Rect {
+		x: rect.x,
+		y: rect.y,
+		w: rect.w,
+		h: rect.h - 1,
+	};
+
+	match (pane, layout) {
+		(AuxPane::Graph, _) => match state.graph() {
+			Some(graph) => graph.render_area(state.context().format(), screen, &content),
+			None => render_layout(&message_line("No graph"), screen, &content),
+		},
+		(_, Some(layout)) => render_layout(layout, screen, &content),
+		_ => (),
+	}
+
+	// Separate the pane from the stack below it
+	let mut renderer = screen.renderer(RenderMode::Normal);
+	renderer.fill(
+		&Rect {
+			x: rect.x,
+			y: rect.y + rect.h - 1,
+			w: rect.w,
+			h: 1,
+		},
+		TokenType::Error,
+	);
+}
+
+fn render_layout(layout: &Layout, screen: &mut dyn Screen, rect: &Rect) {
+	let mut renderer = screen.renderer(RenderMode::Normal);
+	renderer.erase(rect);
+	layout.render(
+		&mut renderer,
+		Rect {
+			x: rect.x + 4,
+			y: rect.y,
+			w: rect.w - 8,
+			h: rect.h,
+		},
+		rect,
+	);
+}
+
+fn message_line(text: &'static str) -> Layout {
+	Layout::HorizontalCenter(Box::new(Layout::StaticText(
+		text,
+		Font::Small,
+		TokenType::Label,
+	)))
+}
+
+fn value_line(
+	label: &str,
+	value: Result<Value>,
+	state: &State,
+	metrics: &dyn FontMetrics,
+	width: i32,
+) -> Layout {
+	let label_layout = Layout::Text(label.to_string(), Font::Small, TokenType::Label);
+	let value_width = width - label_layout.width(metrics);
+	let value_layout = match value {
+		Ok(value) => value.layout(state.context().format(), Font::Small, metrics, value_width),
+		Err(error) => Layout::StaticText(error.to_str(), Font::Small, TokenType::Error),
+	};
+	let items = vec![label_layout, value_layout];
+	Layout::LeftAlign(Box::new(Layout::Horizontal(items)))
+}
@@ -0,0 +1,266 @@
+use crate::dm42::feedback::FeedbackEvent;
+use crate::dm42::files::FileStore;
+use crate::dm42::print::PrinterLink;
+use rscalc_math::error::{Error, Result};
+
+#[cfg(all(not(feature = "embedded"), not(target_arch = "wasm32")))]
+use crate::dm42::clipboard;
+
+#[cfg(not(feature = "embedded"))]
+use crate::dm42::files::HostFileStore;
+#[cfg(not(feature = "embedded"))]
+use crate::dm42::print::HostPrinter;
+
+#[cfg(not(feature = "embedded"))]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+// The web page shows the levels by filtering the canvas of the screen
+#[cfg(all(not(feature = "embedded"), target_arch = "wasm32"))]
+extern "C" {
+	fn rscalc_set_display_levels(brightness: u32, contrast: u32);
+}
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::String;
+
+/// State of the power supply of a battery powered platform
+#[derive(Clone, Copy)]
+pub struct PowerStatus {
+	/// Battery voltage in millivolts
+	pub voltage: u32,
+	/// Estimated charge remaining in the battery
+	pub percent: u32,
+	/// True when running from USB power instead of the battery
+	pub usb: bool,
+}
+
+/// Charge in percent at or below which the battery may run out at any moment
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+pub const CRITICAL_BATTERY_PERCENT: u32 = 5;
+
+/// Time the low battery warning is shown for if no key is pressed
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+pub const LOW_BATTERY_WARNING_MS: u32 = 10000;
+
+#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+impl PowerStatus {
+	/// True when running from a battery that is about to run out
+	pub fn is_critical(&self) -> bool {
+		!self.usb && self.percent <= CRITICAL_BATTERY_PERCENT
+	}
+}
+
+/// Services of the hardware that the calculator runs on, other than the screen
+/// and keyboard. Each platform is selected with a cargo feature and provides one
+/// implementation, which is returned by `platform`. The real time clock is given
+/// to the math library with `rscalc_math::time::set_clock`.
+pub trait Platform: Sync {
+	/// Waits for the given number of milliseconds
+	fn delay(&self, ms: u32);
+
+	/// Called after a frame has been drawn in response to input, before waiting
+	/// for the next key
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn render_complete(&self) {}
+
+	/// Called before the calculator blocks waiting for a key. Hardware can lower
+	/// its clock or enter a sleep state until the keyboard wakes it.
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn waiting_for_key(&self) {}
+
+	/// Called before the calculator handles a key, which may start a long
+	/// computation
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn computation_started(&self) {}
+
+	/// Called when the calculator is done handling a key
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn computation_finished(&self) {}
+
+	/// Called regularly while a long computation runs, such as on each loop of a
+	/// script or step of a program. Returns true if the user asked for the
+	/// computation to stop, which it does with `Error::Interrupted`.
+	fn computation_yield(&self) -> bool {
+		false
+	}
+
+	/// Minutes without a key press after which the calculator turns off, or
+	/// `None` if the platform stays on. The state is saved before turning off.
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn auto_off_minutes(&self) -> Option<i64> {
+		None
+	}
+
+	/// Returns true if the minute shown by the clock may have changed since the
+	/// last call. Platforms that can't tell always return true.
+	fn clock_minute_updated(&self) -> bool {
+		true
+	}
+
+	/// Sounds a short beep, if the platform can make sound
+	fn beep(&self) {}
+
+	/// Plays a tone and returns once it has finished. Platforms that can't set
+	/// the pitch of their sound beep instead.
+	fn tone(&self, _frequency_hz: u32, _duration_ms: u32) {
+		self.beep();
+	}
+
+	/// Gives feedback for an event that it is turned on for. Platforms that can
+	/// vibrate may do that instead of playing the tone of the event.
+	fn feedback(&self, event: FeedbackEvent) {
+		let tone = event.tone();
+		self.tone(tone.frequency_hz, tone.duration_ms);
+	}
+
+	/// Sets the brightness and contrast of the display, each from 0 up to
+	/// `DISPLAY_LEVEL_MAX`. Displays that can't change them ignore this.
+	fn set_display_levels(&self, _brightness: u8, _contrast: u8) {}
+
+	/// State of the battery, or `None` if the platform is not battery powered
+	fn power_status(&self) -> Option<PowerStatus> {
+		None
+	}
+
+	/// Memory available outside of the calculator heap, if the platform has a
+	/// separate pool for it
+	fn free_system_memory(&self) -> Option<usize> {
+		None
+	}
+
+	/// Opens the storage for named files
+	fn file_store(&self) -> Box<dyn FileStore>;
+
+	/// Opens the link to the printer, or returns `None` if there is no printer
+	fn printer(&self) -> Option<Box<dyn PrinterLink>> {
+		None
+	}
+
+	/// Copies text to the clipboard of the system the calculator runs on
+	fn copy_to_clipboard(&self, _text: &str) -> Result<()> {
+		Err(Error::NotSupported)
+	}
+
+	/// Reads text from the clipboard of the system the calculator runs on
+	fn paste_from_clipboard(&self) -> Result<String> {
+		Err(Error::NotSupported)
+	}
+
+	/// Returns true if the platform has its own menu of system settings
+	fn has_system_menu(&self) -> bool {
+		false
+	}
+
+	fn show_system_menu(&self) {}
+
+	/// Returns whether times are shown in 24 hour format if this is a setting of
+	/// the platform rather than of the calculator
+	fn time_24_hour(&self) -> Option<bool> {
+		None
+	}
+
+	fn set_time_24_hour(&self, _value: bool) {}
+}
+
+/// Set while the calculator is handling a key on a host
+#[cfg(any(feature = "gtk", feature = "desktop"))]
+static COMPUTING: AtomicBool = AtomicBool::new(false);
+
+#[cfg(not(feature = "embedded"))]
+static INTERRUPT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the running computation to stop. The keyboard of a host runs on its own
+/// thread, and calls this when EXIT is pressed. Returns false if nothing is being
+/// computed, in which case the key should be handled normally.
+#[cfg(any(feature = "gtk", feature = "desktop"))]
+pub fn interrupt_computation() -> bool {
+	if COMPUTING.load(Ordering::Acquire) {
+		INTERRUPT_REQUESTED.store(true, Ordering::Release);
+		true
+	} else {
+		false
+	}
+}
+
+/// Desktop and web builds, which have the standard library
+#[cfg(not(feature = "embedded"))]
+pub struct HostPlatform;
+
+#[cfg(not(feature = "embedded"))]
+impl Platform for HostPlatform {
+	fn delay(&self, ms: u32) {
+		std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn computation_started(&self) {
+		INTERRUPT_REQUESTED.store(false, Ordering::Release);
+		COMPUTING.store(true, Ordering::Release);
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn computation_finished(&self) {
+		COMPUTING.store(false, Ordering::Release);
+	}
+
+	fn computation_yield(&self) -> bool {
+		INTERRUPT_REQUESTED.swap(false, Ordering::AcqRel)
+	}
+
+	fn file_store(&self) -> Box<dyn FileStore> {
+		Box::new(HostFileStore::new())
+	}
+
+	fn printer(&self) -> Option<Box<dyn PrinterLink>> {
+		Some(Box::new(HostPrinter))
+	}
+
+	/// Rings the bell of the terminal that started the calculator
+	#[cfg(not(target_arch = "wasm32"))]
+	fn beep(&self) {
+		use std::io::Write;
+		let mut stdout = std::io::stdout();
+		let _ = stdout.write_all(b"\x07");
+		let _ = stdout.flush();
+	}
+
+	/// The bell of a terminal has no pitch, so it is rung for the length of the
+	/// tone
+	#[cfg(not(target_arch = "wasm32"))]
+	fn tone(&self, _frequency_hz: u32, duration_ms: u32) {
+		self.beep();
+		self.delay(duration_ms);
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	fn set_display_levels(&self, brightness: u8, contrast: u8) {
+		unsafe { rscalc_set_display_levels(brightness as u32, contrast as u32) };
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn copy_to_clipboard(&self, text: &str) -> Result<()> {
+		clipboard::copy(text)
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn paste_from_clipboard(&self) -> Result<String> {
+		clipboard::paste()
+	}
+}
+
+#[cfg(not(feature = "embedded"))]
+pub fn platform() -> &'static dyn Platform {
+	&HostPlatform
+}
+
+#[cfg(feature = "dm42")]
+pub fn platform() -> &'static dyn Platform {
+	&crate::dm42::device::DM42Platform
+}
+
+// Embedded hardware needs its board support alongside `device`, with a feature
+// that enables `embedded` and selects its platform above
+#[cfg(all(feature = "embedded", not(feature = "dm42")))]
+compile_error!("embedded builds need the feature of a board, such as dm42");
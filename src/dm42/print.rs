@@ -0,0 +1,218 @@
+use crate::dm42::functions::Function;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::platform::platform;
+use crate::dm42::state::State;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::number::ToNumber;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Characters on each line of the printer
+pub const PRINTER_COLUMNS: usize = 24;
+
+const LINE_FEED: u8 = 0x0a;
+const ESCAPE: u8 = 0x1b;
+
+/// Escape code that resets the printer to its default modes
+const RESET: u8 = 255;
+
+/// Characters 128 to 159 of the printer, which replace the control characters of
+/// ISO 8859-1 with symbols used by calculators. The rest of the character set
+/// above 159 is the same as ISO 8859-1. The mean symbol x̄ is not a single
+/// character, so it reads back as x.
+const SYMBOLS: [char; 32] = [
+	'∡', 'x', '∇', '√', '∫', 'Σ', '▶', 'π', '∂', '≤', '≥', '≠', 'α', '→', '←', '↓', '↑', 'γ', 'δ',
+	'ε', 'η', 'θ', 'λ', 'ρ', 'σ', 'τ', 'ω', 'Δ', 'Π', 'Ω', '■', '∞',
+];
+
+/// Connection to a printer that accepts the HP 82240 protocol, such as an
+/// infrared printer or a program that shows what would be printed
+pub trait PrinterLink {
+	fn send(&mut self, data: &[u8]) -> Result<()>;
+}
+
+/// Translates a character into the character set of the printer. Characters
+/// that the printer does not have are printed as a block.
+pub fn encode_char(ch: char) -> u8 {
+	match ch as u32 {
+		0x20..=0x7e | 0xa0..=0xff => ch as u8,
+		_ => match ch {
+			'−' => b'-',
+			'μ' => 0xb5,
+			'ℹ' => b'i',
+			'ᴇ' => b'E',
+			'⟪' => 0xab,
+			'⟫' => 0xbb,
+			'₂' => b'2',
+			_ => match SYMBOLS.iter().position(|symbol| *symbol == ch) {
+				Some(idx) => 0x80 + idx as u8,
+				None => 0x80 + 30,
+			},
+		},
+	}
+}
+
+/// Translates a character of the printer back into text
+#[cfg(not(feature = "embedded"))]
+pub fn decode_char(value: u8) -> char {
+	match value {
+		0x80..=0x9f => SYMBOLS[(value - 0x80) as usize],
+		_ => value as char,
+	}
+}
+
+/// Sends lines of text to the printer. Lines longer than the printer are
+/// wrapped. When `right_align` is set each line is pushed to the right edge,
+/// which is how the printer shows numbers.
+fn send_lines(link: &mut dyn PrinterLink, lines: &[String], right_align: bool) -> Result<()> {
+	let mut data = Vec::new();
+	for line in lines {
+		let chars: Vec<u8> = line.chars().map(encode_char).collect();
+		let mut rows: Vec<&[u8]> = chars.chunks(PRINTER_COLUMNS).collect();
+		if rows.is_empty() {
+			rows.push(&[]);
+		}
+		for row in rows {
+			if right_align {
+				data.resize(data.len() + PRINTER_COLUMNS - row.len(), b' ');
+			}
+			data.extend_from_slice(row);
+			data.push(LINE_FEED);
+		}
+	}
+	link.send(&data)
+}
+
+fn open_printer() -> Result<Box<dyn PrinterLink>> {
+	platform().printer().ok_or(Error::NotSupported)
+}
+
+/// Prints the value in x
+pub fn print_x(state: &mut State) -> Result<()> {
+	state.end_edit()?;
+	let context = state.context();
+	let value = context.top()?.format(context.format()).to_string();
+	send_lines(&mut *open_printer()?, &[value], true)
+}
+
+/// Prints the whole stack with the top of the stack last, as it is shown on
+/// the screen
+pub fn print_stack(state: &mut State) -> Result<()> {
+	state.end_edit()?;
+	let context = state.context();
+	let mut lines = Vec::new();
+	for idx in (0..context.stack_len()).rev() {
+		let value = context.entry(idx)?.format(context.format()).to_string();
+		let label = (idx + 1).to_number().to_string() + ":";
+		if label.chars().count() + value.chars().count() < PRINTER_COLUMNS {
+			let padding = PRINTER_COLUMNS - label.chars().count() - value.chars().count();
+			lines.push(label + &" ".repeat(padding) + &value);
+		} else {
+			// Long values are given their own lines below the stack level
+			lines.push(label);
+			lines.push(value);
+		}
+	}
+	if lines.is_empty() {
+		return Err(Error::NotEnoughValues);
+	}
+	send_lines(&mut *open_printer()?, &lines, true)
+}
+
+/// Prints the source of each kept script, with a blank line between them
+pub fn print_programs(state: &mut State) -> Result<()> {
+	state.end_edit()?;
+	if state.scripts().is_empty() {
+		return Err(Error::NoProgram);
+	}
+	let mut lines = Vec::new();
+	for (idx, script) in state.scripts().iter().enumerate() {
+		if idx != 0 {
+			lines.push(String::new());
+		}
+		match script.name() {
+			Some(name) => lines.push(name.to_string() + ":"),
+			None => lines.push("Script ".to_string() + &(idx + 1).to_number().to_string() + ":"),
+		}
+		for line in script.source().lines() {
+			lines.push(line.to_string());
+		}
+	}
+	send_lines(&mut *open_printer()?, &lines, false)
+}
+
+pub fn print_menu() -> Menu {
+	let items = vec![
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Print X")),
+			function: MenuItemFunction::InMenuAction(Function::PrintX),
+			help: None,
+		},
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Print Stack")),
+			function: MenuItemFunction::InMenuAction(Function::PrintStack),
+			help: None,
+		},
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Print Programs")),
+			function: MenuItemFunction::InMenuAction(Function::PrintPrograms),
+			help: Some("Prints the source of each kept script"),
+		},
+		MenuItem {
+			layout: MenuItemLayout::Static(MenuItem::static_string_layout("Reset Printer")),
+			function: MenuItemFunction::InMenuAction(Function::ResetPrinter),
+			help: None,
+		},
+	];
+	Menu::new("Print", items)
+}
+
+pub fn reset_printer() -> Result<()> {
+	open_printer()?.send(&[ESCAPE, RESET])
+}
+
+/// Printer of the simulator, which writes the printed text to the file given by
+/// `RSCALC_PRINTER`, or to standard output if it is not set
+#[cfg(not(feature = "embedded"))]
+pub struct HostPrinter;
+
+#[cfg(not(feature = "embedded"))]
+impl PrinterLink for HostPrinter {
+	fn send(&mut self, data: &[u8]) -> Result<()> {
+		use std::io::Write;
+
+		let mut text = String::new();
+		let mut escape = false;
+		for value in data {
+			// Escape codes change the modes of the printer and are not printed
+			if escape {
+				escape = false;
+			} else if *value == ESCAPE {
+				escape = true;
+			} else {
+				text.push(decode_char(*value));
+			}
+		}
+
+		let result = match std::env::var_os("RSCALC_PRINTER") {
+			Some(path) => std::fs::OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(path)
+				.and_then(|mut file| file.write_all(text.as_bytes())),
+			None => {
+				let stdout = std::io::stdout();
+				let mut out = stdout.lock();
+				out.write_all(text.as_bytes()).and_then(|_| out.flush())
+			}
+		};
+		result.map_err(|_| Error::WriteFailed)
+	}
+}
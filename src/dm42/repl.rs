@@ -0,0 +1,67 @@
+use rscalc_math::context::Context;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::script::Script;
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Runs a command line against the stack. Lines use the same words as scripts,
+/// so `2 3 + sin` pushes two numbers, adds them and takes the sine of the sum.
+fn run_line(context: &mut Context, line: &str) -> Result<()> {
+	let script = Script::parse(line)?;
+	if script.name().is_some() {
+		// User functions are kept by the calculator state, which does not exist
+		// in terminal mode
+		return Err(Error::NotSupported);
+	}
+	script.execute(context)
+}
+
+fn print_stack(context: &Context, out: &mut dyn Write) {
+	for idx in (0..context.stack_len()).rev() {
+		if let Ok(value) = context.entry(idx) {
+			let _ = writeln!(
+				out,
+				"{}: {}",
+				idx + 1,
+				value.format(context.format()).to_string()
+			);
+		}
+	}
+}
+
+/// Runs the calculator in the terminal without a screen. Each line read from
+/// standard input is run as a command line and the stack is printed after it.
+/// Errors are printed to standard error, so that a file of commands can be piped
+/// in for scripting and regression testing with only the stacks as output.
+pub fn run() {
+	let stdin = std::io::stdin();
+	let stdout = std::io::stdout();
+	let interactive = stdin.is_terminal();
+	let mut context = Context::new();
+	let mut out = stdout.lock();
+
+	loop {
+		if interactive {
+			let _ = write!(out, "> ");
+			let _ = out.flush();
+		}
+		let mut line = String::new();
+		match stdin.lock().read_line(&mut line) {
+			Ok(0) | Err(_) => break,
+			Ok(_) => (),
+		}
+		let line = line.trim();
+		if line.is_empty() {
+			continue;
+		}
+		if line.eq_ignore_ascii_case("quit") {
+			break;
+		}
+
+		if let Err(error) = run_line(&mut context, line) {
+			eprintln!("Error: {}", error.to_str());
+		}
+		print_stack(&context, &mut out);
+		let _ = writeln!(out);
+		let _ = out.flush();
+	}
+}
@@ -0,0 +1,195 @@
+use crate::dm42::state::State;
+#[cfg(not(feature = "embedded"))]
+use crate::dm42::transfer::TransferDecoder;
+use chrono::NaiveDateTime;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::time::Now;
+
+#[cfg(feature = "embedded")]
+use crate::dm42::files::{open_file_store, STATE_FILE_NAME};
+
+#[cfg(feature = "embedded")]
+use alloc::string::String;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Saves the state every few minutes while the calculator is in use, so that a
+/// crash or a pulled battery loses little. The state is only written when it
+/// has changed since it was last saved, which keeps down the writes to flash.
+/// A save that fails in the background is not reported, and is only tried
+/// again once the state has changed, so that a full store isn't written to
+/// every few minutes.
+pub struct AutoSave {
+	last_save: Option<NaiveDateTime>,
+	last_hash: u64,
+	/// Hash of the state that last failed to be written
+	failed_hash: Option<u64>,
+}
+
+impl AutoSave {
+	/// Starts timing the saves from the state as it is now, which is taken to
+	/// have been saved already
+	pub fn new(state: &State) -> Self {
+		AutoSave {
+			last_save: NaiveDateTime::now().ok(),
+			last_hash: state.save_state().map_or(0, |data| state_hash(&data)),
+			failed_hash: None,
+		}
+	}
+
+	/// Writes the state, returning true if it was written
+	pub fn save(&mut self, state: &State) -> bool {
+		self.last_save = NaiveDateTime::now().ok();
+		let data = match state.save_state() {
+			Ok(data) => data,
+			Err(_) => return false,
+		};
+		if write_saved_state(&data).is_err() {
+			self.failed_hash = Some(state_hash(&data));
+			return false;
+		}
+		self.last_hash = state_hash(&data);
+		self.failed_hash = None;
+		true
+	}
+
+	/// Writes the state if it has changed and the time between saves has
+	/// passed since it was last written
+	pub fn update(&mut self, state: &State) {
+		let minutes = state.auto_save_minutes();
+		if minutes == 0 {
+			return;
+		}
+		let now = match NaiveDateTime::now() {
+			Ok(now) => now,
+			Err(_) => return,
+		};
+		if let Some(last_save) = self.last_save {
+			if (now - last_save).num_minutes() < minutes as i64 {
+				return;
+			}
+		}
+		self.last_save = Some(now);
+
+		let data = match state.save_state() {
+			Ok(data) => data,
+			Err(_) => return,
+		};
+		let hash = state_hash(&data);
+		if hash == self.last_hash || Some(hash) == self.failed_hash {
+			return;
+		}
+		match write_saved_state(&data) {
+			Ok(()) => {
+				self.last_hash = hash;
+				self.failed_hash = None;
+			}
+			Err(_) => self.failed_hash = Some(hash),
+		}
+	}
+}
+
+/// FNV-1a hash of saved state, which tells whether it has changed without
+/// keeping a copy of the state that was written
+fn state_hash(data: &[u8]) -> u64 {
+	data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+	})
+}
+
+/// Saved state of the simulator is kept in the file given by `RSCALC_STATE_FILE`,
+/// or `rscalc.state` in the current directory if it is not set
+#[cfg(not(feature = "embedded"))]
+fn state_path() -> std::path::PathBuf {
+	match std::env::var_os("RSCALC_STATE_FILE") {
+		Some(path) => std::path::PathBuf::from(path),
+		None => std::path::PathBuf::from("rscalc.state"),
+	}
+}
+
+/// Reads the saved calculator state, if there is one
+#[cfg(not(feature = "embedded"))]
+pub fn read_saved_state() -> Option<Vec<u8>> {
+	std::fs::read(state_path()).ok()
+}
+
+/// Replaces the saved calculator state
+#[cfg(not(feature = "embedded"))]
+pub fn write_saved_state(data: &[u8]) -> Result<()> {
+	std::fs::write(state_path(), data).map_err(|_| Error::WriteFailed)
+}
+
+/// Sends a state transfer to the serial port given by `RSCALC_SERIAL_PORT`
+#[cfg(not(feature = "embedded"))]
+pub fn send_transfer(text: &str) -> Result<()> {
+	let port = std::env::var_os("RSCALC_SERIAL_PORT").ok_or(Error::NotSupported)?;
+	std::fs::OpenOptions::new()
+		.write(true)
+		.open(port)
+		.and_then(|mut port| std::io::Write::write_all(&mut port, text.as_bytes()))
+		.map_err(|_| Error::WriteFailed)
+}
+
+/// Waits for a state transfer on the serial port given by `RSCALC_SERIAL_PORT`
+/// and returns the saved state that was received
+#[cfg(not(feature = "embedded"))]
+pub fn receive_transfer() -> Result<Vec<u8>> {
+	let port = std::env::var_os("RSCALC_SERIAL_PORT").ok_or(Error::NotSupported)?;
+	let mut port = std::fs::File::open(port).map_err(|_| Error::NoSavedState)?;
+	let mut decoder = TransferDecoder::new();
+	let mut buffer = [0; 256];
+	loop {
+		let len = std::io::Read::read(&mut port, &mut buffer).map_err(|_| Error::CorruptData)?;
+		if len == 0 {
+			// The link closed before the end of the transfer
+			return Err(Error::CorruptData);
+		}
+		if let Some(data) = decoder.push(&buffer[..len])? {
+			return Ok(data);
+		}
+	}
+}
+
+/// Reads the script kept in the file given by `RSCALC_SCRIPT_FILE`, or
+/// `rscalc.rpl` in the current directory if it is not set
+#[cfg(not(feature = "embedded"))]
+pub fn read_script() -> Result<String> {
+	let path = match std::env::var_os("RSCALC_SCRIPT_FILE") {
+		Some(path) => std::path::PathBuf::from(path),
+		None => std::path::PathBuf::from("rscalc.rpl"),
+	};
+	std::fs::read_to_string(path).map_err(|_| Error::FileNotFound)
+}
+
+/// Saved state of the device is kept in the file store, which is on the flash
+/// disk, so that it is restored after a reset or a change of batteries
+#[cfg(feature = "embedded")]
+pub fn read_saved_state() -> Option<Vec<u8>> {
+	open_file_store().read(STATE_FILE_NAME).ok()
+}
+
+#[cfg(feature = "embedded")]
+pub fn write_saved_state(data: &[u8]) -> Result<()> {
+	open_file_store().write(STATE_FILE_NAME, data)
+}
+
+// Programs on the device have no serial link, so transfers are only offered by
+// the simulator. These are kept so that a function assigned to a key reports
+// that it can't be used.
+
+#[cfg(feature = "embedded")]
+pub fn send_transfer(_text: &str) -> Result<()> {
+	Err(Error::NotSupported)
+}
+
+#[cfg(feature = "embedded")]
+pub fn receive_transfer() -> Result<Vec<u8>> {
+	Err(Error::NotSupported)
+}
+
+/// Reads the script kept in `rscalc.rpl` in the file store
+#[cfg(feature = "embedded")]
+pub fn read_script() -> Result<String> {
+	let data = open_file_store().read("rscalc.rpl")?;
+	String::from_utf8(data).map_err(|_| Error::CorruptData)
+}
@@ -1,6 +1,97 @@
-use crate::dm42::font::{char_to_idx, SANS_13, SANS_16, SANS_20, SANS_24};
+use crate::dm42::font::{bitmap_font, char_to_idx};
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
 use rscalc_layout::font::{Font, FontMetrics};
 use rscalc_layout::layout::{LayoutRenderer, Rect, TokenType};
+use rscalc_math::error::{Error, Result};
+
+static DARK_THEME: AtomicBool = AtomicBool::new(false);
+
+/// Semantic colors used when rendering. The active theme maps these to the
+/// concrete pixel values of the display.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Color {
+	ContentText,
+	ContentBackground,
+	StackSeparator,
+	StackLabelText,
+	MenuText,
+	MenuBackground,
+	MenuSelectedText,
+	MenuSelectedBackground,
+	StatusBarText,
+	StatusBarBackground,
+	FunctionKeyText,
+	FunctionKeyBackground,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Theme {
+	Light,
+	Dark,
+}
+
+impl Theme {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			Theme::Light => "Light",
+			Theme::Dark => "Dark",
+		}
+	}
+
+	/// Returns the pixel value for a semantic color, where true is a set pixel
+	pub fn color(&self, color: Color) -> bool {
+		match self {
+			Theme::Light => match color {
+				Color::ContentText => true,
+				Color::ContentBackground => false,
+				Color::StackSeparator => true,
+				Color::StackLabelText => true,
+				Color::MenuText => true,
+				Color::MenuBackground => false,
+				Color::MenuSelectedText => false,
+				Color::MenuSelectedBackground => true,
+				Color::StatusBarText => false,
+				Color::StatusBarBackground => true,
+				Color::FunctionKeyText => false,
+				Color::FunctionKeyBackground => true,
+			},
+			Theme::Dark => match color {
+				Color::ContentText => false,
+				Color::ContentBackground => true,
+				Color::StackSeparator => false,
+				Color::StackLabelText => false,
+				Color::MenuText => false,
+				Color::MenuBackground => true,
+				Color::MenuSelectedText => true,
+				Color::MenuSelectedBackground => false,
+				Color::StatusBarText => true,
+				Color::StatusBarBackground => false,
+				Color::FunctionKeyText => true,
+				Color::FunctionKeyBackground => false,
+			},
+		}
+	}
+}
+
+/// Returns the active theme
+pub fn theme() -> Theme {
+	if DARK_THEME.load(Ordering::Relaxed) {
+		Theme::Dark
+	} else {
+		Theme::Light
+	}
+}
+
+/// Sets the active theme. The theme is kept for the life of the program, including
+/// while the calculator is suspended.
+pub fn set_theme(theme: Theme) {
+	DARK_THEME.store(theme == Theme::Dark, Ordering::Relaxed);
+}
+
+/// Coverage given to an empty pixel in the inside corner of a glyph for each
+/// pair of adjacent set pixels that form the corner
+const CORNER_ALPHA: u8 = 80;
 
 pub struct BitmapFont {
 	pub height: i32,
@@ -35,6 +126,12 @@ impl BitmapFont {
 
 			// Decode character into font glyph index
 			if let Some(idx) = char_to_idx(ch) {
+				if screen.supports_grayscale() {
+					self.draw_smoothed_glyph(screen, area, cur_x, y, idx, color);
+					cur_x += self.advance[idx] as i32;
+					continue;
+				}
+
 				// Get width of character and determine the number of bytes per line
 				// the glyph takes in the font data
 				let width = self.width[idx];
@@ -100,6 +197,64 @@ impl BitmapFont {
 		}
 	}
 
+	/// Returns true if the pixel at the given coordinates within a glyph is set.
+	/// Coordinates outside of the glyph are never set.
+	fn glyph_pixel(&self, idx: usize, x: i32, y: i32) -> bool {
+		let width = self.width[idx] as i32;
+		if x < 0 || x >= width || y < 0 || y >= self.height {
+			return false;
+		}
+		let bytes = (width + 7) / 8;
+		let byte = x / 8;
+		let byte_width = core::cmp::min(8, width - byte * 8);
+		let data = self.chars[idx][(y * bytes + byte) as usize];
+		(data >> (byte_width - 1 - (x % 8))) & 1 != 0
+	}
+
+	/// Draws a glyph onto a grayscale screen. Empty pixels in the inside corners
+	/// of the glyph are partially filled, which softens diagonal strokes.
+	fn draw_smoothed_glyph(
+		&self,
+		screen: &mut dyn Screen,
+		area: &Rect,
+		x: i32,
+		y: i32,
+		idx: usize,
+		color: bool,
+	) {
+		let width = self.width[idx] as i32;
+		let top = core::cmp::max(0, area.y - y);
+		let bottom = core::cmp::min(self.height, area.y + area.h - y);
+		let left = core::cmp::max(0, area.x - x);
+		let right = core::cmp::min(width, area.x + area.w - x);
+		for line in top..bottom {
+			for col in left..right {
+				if self.glyph_pixel(idx, col, line) {
+					screen.blend_pixel(x + col, y + line, color, 255);
+					continue;
+				}
+
+				let left_set = self.glyph_pixel(idx, col - 1, line);
+				let right_set = self.glyph_pixel(idx, col + 1, line);
+				let up_set = self.glyph_pixel(idx, col, line - 1);
+				let down_set = self.glyph_pixel(idx, col, line + 1);
+				let corners = [
+					left_set && up_set,
+					up_set && right_set,
+					right_set && down_set,
+					down_set && left_set,
+				]
+				.iter()
+				.filter(|corner| **corner)
+				.count() as u8;
+				if corners > 0 {
+					let alpha = core::cmp::min(2, corners) * CORNER_ALPHA;
+					screen.blend_pixel(x + col, y + line, color, alpha);
+				}
+			}
+		}
+	}
+
 	pub fn width(&self, text: &str) -> i32 {
 		let mut result = 0;
 		let mut extra = 0;
@@ -140,12 +295,34 @@ pub trait Screen {
 	fn clear(&mut self);
 	fn refresh(&mut self);
 
+	/// Updates the rows of the display from `top` up to but not including
+	/// `bottom` with what was drawn. Displays that are updated a line at a time,
+	/// such as memory LCDs and e-ink panels, can send only these rows. Screens
+	/// that can't update part of the display refresh all of it.
+	fn refresh_rows(&mut self, _top: i32, _bottom: i32) {
+		self.refresh();
+	}
+
+	/// Called before anything is drawn for a frame. Double buffered screens can
+	/// prepare the buffer that will be drawn into.
+	fn begin_frame(&mut self) {}
+
+	/// Called when a frame is complete, after it has been refreshed. Double
+	/// buffered screens can present the buffer that was drawn into.
+	fn end_frame(&mut self) {}
+
 	fn fill(&mut self, rect: &Rect, color: bool);
 
 	fn set_pixel(&mut self, x: i32, y: i32, color: bool) {
 		self.fill(&Rect { x, y, w: 1, h: 1 }, color);
 	}
 
+	/// Clears the entire screen to the background color of the active theme
+	fn clear_to_background(&mut self) {
+		let rect = self.screen_rect();
+		self.fill(&rect, theme().color(Color::ContentBackground));
+	}
+
 	fn horizontal_pattern(
 		&mut self,
 		x: i32,
@@ -179,6 +356,274 @@ pub trait Screen {
 	}
 
 	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer;
+
+	/// Returns true if the screen can refresh quickly enough to display animations
+	fn supports_animation(&self) -> bool {
+		false
+	}
+
+	/// Returns true if the screen can display shades between set and cleared
+	/// pixels. Text is drawn with smoothed edges on these screens.
+	fn supports_grayscale(&self) -> bool {
+		false
+	}
+
+	/// Blends `color` into a pixel, where an `alpha` of 255 replaces the pixel
+	/// entirely. Monochrome screens set the pixel if it is at least half covered.
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		if alpha >= 128 {
+			self.set_pixel(x, y, color);
+		}
+	}
+
+	/// Saves the current contents of the screen as an image, for documentation
+	/// and bug reports. Screens that cannot read back their contents or have
+	/// nowhere to store the image return an error.
+	fn capture(&self) -> Result<()> {
+		Err(Error::NotSupported)
+	}
+}
+
+/// Turn of the picture drawn by the calculator relative to the panel it is
+/// shown on, for panels that are mounted at a right angle
+#[cfg(feature = "simulated")]
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Rotation {
+	None,
+	Clockwise,
+	CounterClockwise,
+}
+
+/// Screen that draws onto another screen while tracking the rows that have been
+/// modified. Refreshing only updates the underlying screen if something was drawn
+/// since the last refresh, so frames where nothing changed cost nothing, and then
+/// only the rows that were drawn are refreshed.
+pub struct DamageTrackingScreen<'a> {
+	screen: &'a mut dyn Screen,
+	damage: Option<(i32, i32)>,
+}
+
+impl<'a> DamageTrackingScreen<'a> {
+	pub fn new(screen: &'a mut dyn Screen) -> Self {
+		DamageTrackingScreen {
+			screen,
+			damage: None,
+		}
+	}
+
+	fn add_damage(&mut self, y: i32, h: i32) {
+		let top = core::cmp::max(y, 0);
+		let bottom = core::cmp::min(y + h, self.screen.height());
+		if bottom <= top {
+			return;
+		}
+		self.damage = Some(match self.damage {
+			Some((prev_top, prev_bottom)) => (
+				core::cmp::min(prev_top, top),
+				core::cmp::max(prev_bottom, bottom),
+			),
+			None => (top, bottom),
+		});
+	}
+}
+
+impl<'a> Screen for DamageTrackingScreen<'a> {
+	fn width(&self) -> i32 {
+		self.screen.width()
+	}
+
+	fn height(&self) -> i32 {
+		self.screen.height()
+	}
+
+	fn clear(&mut self) {
+		let height = self.screen.height();
+		self.add_damage(0, height);
+		self.screen.clear();
+	}
+
+	fn refresh(&mut self) {
+		if let Some((top, bottom)) = self.damage.take() {
+			self.screen.refresh_rows(top, bottom);
+		}
+	}
+
+	fn refresh_rows(&mut self, top: i32, bottom: i32) {
+		self.add_damage(top, bottom - top);
+		self.refresh();
+	}
+
+	fn begin_frame(&mut self) {
+		self.screen.begin_frame();
+	}
+
+	fn end_frame(&mut self) {
+		self.screen.end_frame();
+	}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		if rect.w > 0 {
+			self.add_damage(rect.y, rect.h);
+		}
+		self.screen.fill(rect, color);
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		self.add_damage(y, 1);
+		self.screen.draw_bits(x, y, bits, width, color);
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+
+	fn supports_animation(&self) -> bool {
+		self.screen.supports_animation()
+	}
+
+	fn supports_grayscale(&self) -> bool {
+		self.screen.supports_grayscale()
+	}
+
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		self.add_damage(y, 1);
+		self.screen.blend_pixel(x, y, color, alpha);
+	}
+
+	fn capture(&self) -> Result<()> {
+		self.screen.capture()
+	}
+}
+
+/// Screen that turns everything drawn on it by a quarter turn before drawing it
+/// on a panel. Ports with a panel mounted at a right angle wrap the screen of
+/// the panel in this.
+#[cfg(feature = "simulated")]
+pub struct RotatedScreen<S: Screen> {
+	panel: S,
+	rotation: Rotation,
+}
+
+#[cfg(feature = "simulated")]
+impl<S: Screen> RotatedScreen<S> {
+	pub fn new(panel: S, rotation: Rotation) -> Self {
+		RotatedScreen { panel, rotation }
+	}
+
+	pub fn panel(&self) -> &S {
+		&self.panel
+	}
+
+	/// Position on the panel of a pixel of the picture
+	fn panel_point(&self, x: i32, y: i32) -> (i32, i32) {
+		match self.rotation {
+			Rotation::None => (x, y),
+			Rotation::Clockwise => (self.panel.width() - 1 - y, x),
+			Rotation::CounterClockwise => (y, self.panel.height() - 1 - x),
+		}
+	}
+
+	fn panel_rect(&self, rect: &Rect) -> Rect {
+		match self.rotation {
+			Rotation::None => rect.clone(),
+			Rotation::Clockwise => Rect {
+				x: self.panel.width() - rect.y - rect.h,
+				y: rect.x,
+				w: rect.h,
+				h: rect.w,
+			},
+			Rotation::CounterClockwise => Rect {
+				x: rect.y,
+				y: self.panel.height() - rect.x - rect.w,
+				w: rect.h,
+				h: rect.w,
+			},
+		}
+	}
+}
+
+#[cfg(feature = "simulated")]
+impl<S: Screen> Screen for RotatedScreen<S> {
+	fn width(&self) -> i32 {
+		match self.rotation {
+			Rotation::None => self.panel.width(),
+			Rotation::Clockwise | Rotation::CounterClockwise => self.panel.height(),
+		}
+	}
+
+	fn height(&self) -> i32 {
+		match self.rotation {
+			Rotation::None => self.panel.height(),
+			Rotation::Clockwise | Rotation::CounterClockwise => self.panel.width(),
+		}
+	}
+
+	fn clear(&mut self) {
+		self.panel.clear();
+	}
+
+	fn refresh(&mut self) {
+		self.panel.refresh();
+	}
+
+	fn refresh_rows(&mut self, top: i32, bottom: i32) {
+		// Rows of the picture are columns of the panel when it is turned
+		match self.rotation {
+			Rotation::None => self.panel.refresh_rows(top, bottom),
+			Rotation::Clockwise | Rotation::CounterClockwise => self.panel.refresh(),
+		}
+	}
+
+	fn begin_frame(&mut self) {
+		self.panel.begin_frame();
+	}
+
+	fn end_frame(&mut self) {
+		self.panel.end_frame();
+	}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		let rect = self.panel_rect(rect);
+		self.panel.fill(&rect, color);
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		if self.rotation == Rotation::None {
+			self.panel.draw_bits(x, y, bits, width, color);
+			return;
+		}
+		for i in 0..width {
+			if bits & (1 << ((width - 1) - i)) != 0 {
+				let (x, y) = self.panel_point(x + i as i32, y);
+				self.panel.set_pixel(x, y, color);
+			}
+		}
+	}
+
+	fn metrics(&self) -> &dyn FontMetrics {
+		self.panel.metrics()
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+
+	fn supports_animation(&self) -> bool {
+		self.panel.supports_animation()
+	}
+
+	fn supports_grayscale(&self) -> bool {
+		self.panel.supports_grayscale()
+	}
+
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		let (x, y) = self.panel_point(x, y);
+		self.panel.blend_pixel(x, y, color, alpha);
+	}
+
+	fn capture(&self) -> Result<()> {
+		self.panel.capture()
+	}
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -190,53 +635,115 @@ pub enum RenderMode {
 }
 
 impl RenderMode {
-	fn color_for_token(&self, _token_type: TokenType) -> bool {
-		match self {
-			RenderMode::Normal => true,
-			RenderMode::Selected => false,
-			RenderMode::StatusBar => false,
-			RenderMode::FunctionKeys => false,
-		}
+	fn color_for_token(&self, token_type: TokenType) -> bool {
+		let color = match self {
+			RenderMode::Normal => match token_type {
+				TokenType::Label => Color::StackLabelText,
+				TokenType::Separator => Color::StackSeparator,
+				_ => Color::ContentText,
+			},
+			RenderMode::Selected => Color::MenuSelectedText,
+			RenderMode::StatusBar => Color::StatusBarText,
+			RenderMode::FunctionKeys => Color::FunctionKeyText,
+		};
+		theme().color(color)
 	}
 
 	fn color_for_background(&self) -> bool {
-		match self {
-			RenderMode::Normal => false,
-			RenderMode::Selected => true,
-			RenderMode::StatusBar => true,
-			RenderMode::FunctionKeys => true,
-		}
+		let color = match self {
+			RenderMode::Normal => Color::ContentBackground,
+			RenderMode::Selected => Color::MenuSelectedBackground,
+			RenderMode::StatusBar => Color::StatusBarBackground,
+			RenderMode::FunctionKeys => Color::FunctionKeyBackground,
+		};
+		theme().color(color)
 	}
 }
 
+/// Number of measured strings that are remembered
+const TEXT_MEASURE_CACHE_SIZE: usize = 64;
+
+/// Longest string in bytes that is remembered. Longer strings are rare and are
+/// measured each time.
+const TEXT_MEASURE_MAX_LEN: usize = 24;
+
+#[derive(Clone, Copy)]
+struct TextMeasurement {
+	font: *const BitmapFont,
+	len: usize,
+	text: [u8; TEXT_MEASURE_MAX_LEN],
+	width: i32,
+	advance: i32,
+}
+
+/// Width and advance of recently measured strings. Layouts measure the same text
+/// many times while they are laid out and drawn, and most of the text on the
+/// screen is the same from one frame to the next.
+struct TextMeasureCache {
+	busy: AtomicBool,
+	entries: UnsafeCell<[TextMeasurement; TEXT_MEASURE_CACHE_SIZE]>,
+}
+
+// The entries are only accessed while `busy` is held
+unsafe impl Sync for TextMeasureCache {}
+
+static TEXT_MEASURE_CACHE: TextMeasureCache = TextMeasureCache {
+	busy: AtomicBool::new(false),
+	entries: UnsafeCell::new(
+		[TextMeasurement {
+			font: core::ptr::null(),
+			len: 0,
+			text: [0; TEXT_MEASURE_MAX_LEN],
+			width: 0,
+			advance: 0,
+		}; TEXT_MEASURE_CACHE_SIZE],
+	),
+};
+
+/// Returns the width and advance of `text`. Fonts are never freed, so the address
+/// of the font identifies it even when custom fonts are loaded.
+fn measure_text(font: &'static BitmapFont, text: &str) -> (i32, i32) {
+	let bytes = text.as_bytes();
+	if bytes.len() > TEXT_MEASURE_MAX_LEN || TEXT_MEASURE_CACHE.busy.swap(true, Ordering::Acquire) {
+		return (font.width(text), font.advance(text));
+	}
+
+	// Pick the entry from a FNV-1a hash of the font and text
+	let font_ptr = font as *const BitmapFont;
+	let mut hash = 0x811c9dc5u32 ^ font_ptr as usize as u32;
+	for byte in bytes {
+		hash = (hash ^ *byte as u32).wrapping_mul(0x01000193);
+	}
+
+	let entry = unsafe {
+		&mut (*TEXT_MEASURE_CACHE.entries.get())[hash as usize % TEXT_MEASURE_CACHE_SIZE]
+	};
+	if entry.font != font_ptr || &entry.text[..entry.len] != bytes {
+		entry.font = font_ptr;
+		entry.len = bytes.len();
+		entry.text[..bytes.len()].copy_from_slice(bytes);
+		entry.width = font.width(text);
+		entry.advance = font.advance(text);
+	}
+	let result = (entry.width, entry.advance);
+
+	TEXT_MEASURE_CACHE.busy.store(false, Ordering::Release);
+	result
+}
+
 pub struct ScreenFontMetrics;
 
 impl FontMetrics for ScreenFontMetrics {
 	fn width(&self, font: Font, text: &str) -> i32 {
-		match font {
-			Font::Smallest => SANS_13.width(text),
-			Font::Small => SANS_16.width(text),
-			Font::Medium => SANS_20.width(text),
-			Font::Large => SANS_24.width(text),
-		}
+		measure_text(bitmap_font(font), text).0
 	}
 
 	fn advance(&self, font: Font, text: &str) -> i32 {
-		match font {
-			Font::Smallest => SANS_13.advance(text),
-			Font::Small => SANS_16.advance(text),
-			Font::Medium => SANS_20.advance(text),
-			Font::Large => SANS_24.advance(text),
-		}
+		measure_text(bitmap_font(font), text).1
 	}
 
 	fn height(&self, font: Font) -> i32 {
-		match font {
-			Font::Smallest => SANS_13.height,
-			Font::Small => SANS_16.height,
-			Font::Medium => SANS_20.height,
-			Font::Large => SANS_24.height,
-		}
+		bitmap_font(font).height
 	}
 }
 
@@ -295,13 +802,7 @@ impl<'a> LayoutRenderer for ScreenLayoutRenderer<'a> {
 		token_type: TokenType,
 		clip_rect: &Rect,
 	) {
-		let font = match font {
-			Font::Smallest => &SANS_13,
-			Font::Small => &SANS_16,
-			Font::Medium => &SANS_20,
-			Font::Large => &SANS_24,
-		};
-		font.draw(
+		bitmap_font(font).draw(
 			self.screen,
 			clip_rect,
 			x,
@@ -323,3 +824,79 @@ impl<'a> LayoutRenderer for ScreenLayoutRenderer<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{DamageTrackingScreen, RenderMode, Screen, ScreenLayoutRenderer};
+	use rscalc_layout::layout::Rect;
+
+	/// Screen that only records the rows that are refreshed
+	struct RefreshRecorder {
+		refreshed: Vec<(i32, i32)>,
+	}
+
+	impl Screen for RefreshRecorder {
+		fn width(&self) -> i32 {
+			400
+		}
+
+		fn height(&self) -> i32 {
+			240
+		}
+
+		fn clear(&mut self) {}
+
+		fn refresh(&mut self) {
+			self.refreshed.push((0, 240));
+		}
+
+		fn refresh_rows(&mut self, top: i32, bottom: i32) {
+			self.refreshed.push((top, bottom));
+		}
+
+		fn fill(&mut self, _rect: &Rect, _color: bool) {}
+
+		fn draw_bits(&mut self, _x: i32, _y: i32, _bits: u32, _width: u8, _color: bool) {}
+
+		fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+			ScreenLayoutRenderer::new(self, render_mode)
+		}
+	}
+
+	#[test]
+	fn only_damaged_rows_are_refreshed() {
+		let mut recorder = RefreshRecorder {
+			refreshed: Vec::new(),
+		};
+		{
+			let mut screen = DamageTrackingScreen::new(&mut recorder);
+			// Nothing was drawn, so nothing is refreshed
+			screen.refresh();
+
+			screen.fill(
+				&Rect {
+					x: 10,
+					y: 20,
+					w: 30,
+					h: 5,
+				},
+				true,
+			);
+			screen.set_pixel(0, 40, true);
+			screen.refresh();
+
+			// Damage is clipped to the screen
+			screen.fill(
+				&Rect {
+					x: 0,
+					y: 230,
+					w: 400,
+					h: 20,
+				},
+				false,
+			);
+			screen.refresh();
+		}
+		assert_eq!(recorder.refreshed, [(20, 41), (230, 240)]);
+	}
+}
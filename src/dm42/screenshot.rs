@@ -0,0 +1,61 @@
+use crate::dm42::screen::Screen;
+
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+const FILE_HEADER_SIZE: u32 = 14;
+const INFO_HEADER_SIZE: u32 = 40;
+const PALETTE_SIZE: u32 = 8;
+
+/// Encodes the contents of a screen as a monochrome BMP image. `pixel` returns
+/// the state of the pixel at the given coordinates, where set pixels are drawn
+/// in black.
+pub fn encode_bmp(screen: &dyn Screen, pixel: &dyn Fn(i32, i32) -> bool) -> Vec<u8> {
+	let width = screen.width();
+	let height = screen.height();
+
+	// Rows are padded to a multiple of four bytes
+	let row_size = (((width + 31) / 32) * 4) as u32;
+	let data_offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE + PALETTE_SIZE;
+	let file_size = data_offset + row_size * height as u32;
+
+	let mut result = Vec::with_capacity(file_size as usize);
+	result.extend_from_slice(b"BM");
+	result.extend_from_slice(&file_size.to_le_bytes());
+	result.extend_from_slice(&0u32.to_le_bytes());
+	result.extend_from_slice(&data_offset.to_le_bytes());
+
+	result.extend_from_slice(&INFO_HEADER_SIZE.to_le_bytes());
+	result.extend_from_slice(&width.to_le_bytes());
+	result.extend_from_slice(&height.to_le_bytes());
+	result.extend_from_slice(&1u16.to_le_bytes());
+	result.extend_from_slice(&1u16.to_le_bytes());
+	result.extend_from_slice(&0u32.to_le_bytes());
+	result.extend_from_slice(&(row_size * height as u32).to_le_bytes());
+	result.extend_from_slice(&2835i32.to_le_bytes());
+	result.extend_from_slice(&2835i32.to_le_bytes());
+	result.extend_from_slice(&2u32.to_le_bytes());
+	result.extend_from_slice(&0u32.to_le_bytes());
+
+	// Palette entry zero is white and entry one is black
+	result.extend_from_slice(&[0xff, 0xff, 0xff, 0]);
+	result.extend_from_slice(&[0, 0, 0, 0]);
+
+	// Image data is stored from the bottom row up, with the leftmost pixel in the
+	// most significant bit
+	for y in (0..height).rev() {
+		let row_start = result.len();
+		for x in (0..width).step_by(8) {
+			let mut byte = 0;
+			for bit in 0..8 {
+				if x + bit < width && pixel(x + bit, y) {
+					byte |= 0x80 >> bit;
+				}
+			}
+			result.push(byte);
+		}
+		result.resize(row_start + row_size as usize, 0);
+	}
+
+	result
+}
@@ -0,0 +1,621 @@
+use crate::dm42::files::open_file_store;
+use crate::dm42::input::InputEvent;
+use crate::dm42::platform::platform;
+use crate::dm42::screen::{RenderMode, Screen};
+use chrono::{Datelike, NaiveDateTime};
+use intel_dfp::Decimal;
+use num_bigint::BigInt;
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
+use rscalc_math::context::Context;
+use rscalc_math::error::{Error, Result};
+use rscalc_math::modes::ModeScope;
+use rscalc_math::number::{Number, ToNumber};
+use rscalc_math::time::Now;
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Name of the file written and removed by the storage check
+const STORAGE_CHECK_FILE_NAME: &str = "selftest.tmp";
+
+/// Time waited between the readings of the clock check
+const CLOCK_CHECK_DELAY_MS: u32 = 1100;
+
+/// Earliest year that a clock which has been set can show
+const MIN_CLOCK_YEAR: i32 = 2020;
+
+/// Relative error allowed in the results of the math checks
+const MATH_CHECK_TOLERANCE: &str = "1e-30";
+
+/// Number of additions timed by the integer speed check
+const SPEED_CHECK_ITERATIONS: u32 = 20000;
+
+/// Number of keys shown by the keyboard test, most recent first
+const KEY_HISTORY: usize = 4;
+
+/// Sizes in bits of the integers multiplied by the benchmark
+const BENCHMARK_INTEGER_BITS: [u32; 3] = [256, 4096, 32768];
+
+/// Number of times each operation of the benchmark is repeated to time it
+const BENCHMARK_ITERATIONS: u32 = 20;
+
+/// Number of fractions with a common denominator summed to time rational
+/// reduction
+const BENCHMARK_FRACTIONS: i64 = 200;
+
+/// Prime denominator of the summed fractions, so that none of them can be reduced
+const BENCHMARK_DENOMINATOR: i64 = 1009;
+
+/// Number of frames of the calculator screen drawn to time rendering
+const BENCHMARK_FRAMES: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DisplayPattern {
+	Black,
+	White,
+	Checkerboard,
+	HorizontalLines,
+	VerticalLines,
+	Border,
+}
+
+const DISPLAY_PATTERNS: [DisplayPattern; 6] = [
+	DisplayPattern::Black,
+	DisplayPattern::White,
+	DisplayPattern::Checkerboard,
+	DisplayPattern::HorizontalLines,
+	DisplayPattern::VerticalLines,
+	DisplayPattern::Border,
+];
+
+/// Outcome of one of the automatic checks
+pub struct CheckResult {
+	name: &'static str,
+	passed: bool,
+	detail: String,
+}
+
+impl CheckResult {
+	fn new(name: &'static str, result: Result<String>) -> Self {
+		match result {
+			Ok(detail) => CheckResult {
+				name,
+				passed: true,
+				detail,
+			},
+			Err(error) => CheckResult {
+				name,
+				passed: false,
+				detail: error.to_str().to_string(),
+			},
+		}
+	}
+
+	fn check(name: &'static str, passed: bool, detail: String) -> Self {
+		CheckResult {
+			name,
+			passed,
+			detail,
+		}
+	}
+}
+
+/// Average time taken by an operation of the benchmark
+pub struct Timing {
+	name: String,
+	/// Microseconds for each run, or `None` if there is no clock to time with
+	micros: Option<i64>,
+}
+
+impl Timing {
+	fn text(&self) -> String {
+		let time = match self.micros {
+			Some(micros) if micros >= 10000 => (micros / 1000).to_number().to_string() + " ms",
+			Some(micros) => micros.to_number().to_string() + " µs",
+			None => "--".to_string(),
+		};
+		self.name.clone() + ": " + &time
+	}
+}
+
+/// Test of the hardware that takes over the whole screen until it is left
+pub enum SelfTest {
+	/// Fills the screen with each pattern in turn, so that stuck or dead pixels
+	/// stand out
+	Display(usize),
+	/// Shows the name of each key as it is pressed
+	Keyboard {
+		keys: Vec<String>,
+		count: usize,
+		exit_pressed: bool,
+	},
+	/// Results of the clock, storage and math checks
+	Checks(Vec<CheckResult>),
+	/// Times of operations that show the speed of the hardware and firmware.
+	/// Rendering is timed by the state the first time the results are drawn.
+	Benchmark {
+		timings: Vec<Timing>,
+		render: Option<Timing>,
+	},
+}
+
+impl SelfTest {
+	pub fn display() -> Self {
+		SelfTest::Display(0)
+	}
+
+	pub fn keyboard() -> Self {
+		SelfTest::Keyboard {
+			keys: Vec::new(),
+			count: 0,
+			exit_pressed: false,
+		}
+	}
+
+	pub fn checks() -> Self {
+		SelfTest::Checks(run_checks())
+	}
+
+	pub fn benchmark() -> Self {
+		SelfTest::Benchmark {
+			timings: run_benchmark(),
+			render: None,
+		}
+	}
+
+	/// Times drawing frames with `render` if this is a benchmark that hasn't
+	/// timed rendering yet
+	pub fn time_render(&mut self, render: &mut dyn FnMut()) {
+		if let SelfTest::Benchmark { render: result, .. } = self {
+			if result.is_none() {
+				*result = Some(time("Render".to_string(), BENCHMARK_FRAMES, render));
+			}
+		}
+	}
+
+	pub fn needs_render_timing(&self) -> bool {
+		match self {
+			SelfTest::Benchmark { render, .. } => render.is_none(),
+			_ => false,
+		}
+	}
+
+	/// Handles a key press, returning true when the test should be left
+	pub fn handle_input(&mut self, input: InputEvent) -> bool {
+		match self {
+			SelfTest::Display(idx) => match input {
+				InputEvent::Exit | InputEvent::Backspace => true,
+				_ => {
+					*idx += 1;
+					*idx >= DISPLAY_PATTERNS.len()
+				}
+			},
+			SelfTest::Keyboard {
+				keys,
+				count,
+				exit_pressed,
+			} => {
+				// Exit is a key to test like any other, so it must be pressed twice
+				if input == InputEvent::Exit && *exit_pressed {
+					return true;
+				}
+				*exit_pressed = input == InputEvent::Exit;
+				keys.insert(0, input.to_string());
+				keys.truncate(KEY_HISTORY);
+				*count += 1;
+				false
+			}
+			SelfTest::Checks(results) => match input {
+				InputEvent::Enter => {
+					*results = run_checks();
+					false
+				}
+				_ => true,
+			},
+			SelfTest::Benchmark { .. } => match input {
+				InputEvent::Enter => {
+					*self = SelfTest::benchmark();
+					false
+				}
+				_ => true,
+			},
+		}
+	}
+
+	pub fn render(&self, screen: &mut dyn Screen) {
+		match self {
+			SelfTest::Display(idx) => render_pattern(DISPLAY_PATTERNS[*idx], screen),
+			SelfTest::Keyboard { keys, count, .. } => {
+				let mut lines = Vec::new();
+				lines.push(Layout::StaticText(
+					"Keyboard Test",
+					Font::Small,
+					TokenType::Label,
+				));
+				for (idx, key) in keys.iter().enumerate() {
+					let font = if idx == 0 { Font::Large } else { Font::Small };
+					lines.push(Layout::Text(key.clone(), font, TokenType::Text));
+				}
+				lines.push(Layout::Text(
+					"Keys pressed: ".to_string() + &count.to_number().to_string(),
+					Font::Smallest,
+					TokenType::Label,
+				));
+				lines.push(Layout::StaticText(
+					"Press EXIT twice to leave",
+					Font::Smallest,
+					TokenType::Label,
+				));
+				render_lines(lines, screen);
+			}
+			SelfTest::Checks(results) => {
+				let failed = results.iter().filter(|result| !result.passed).count();
+				let mut lines = Vec::new();
+				lines.push(Layout::StaticText(
+					"Self Test",
+					Font::Small,
+					TokenType::Label,
+				));
+				for result in results {
+					let status = if result.passed { "Pass" } else { "FAIL" };
+					lines.push(Layout::Text(
+						result.name.to_string() + ": " + status + ", " + &result.detail,
+						Font::Smallest,
+						if result.passed {
+							TokenType::Text
+						} else {
+							TokenType::Error
+						},
+					));
+				}
+				lines.push(if failed == 0 {
+					Layout::StaticText("All checks passed", Font::Small, TokenType::Text)
+				} else {
+					Layout::Text(
+						failed.to_number().to_string() + " failed",
+						Font::Small,
+						TokenType::Error,
+					)
+				});
+				lines.push(Layout::StaticText(
+					"ENTER to run again, any other key to leave",
+					Font::Smallest,
+					TokenType::Label,
+				));
+				render_lines(lines, screen);
+			}
+			SelfTest::Benchmark { timings, render } => {
+				let mut lines = Vec::new();
+				lines.push(Layout::Text(
+					"Benchmark, version ".to_string() + env!("CARGO_PKG_VERSION"),
+					Font::Small,
+					TokenType::Label,
+				));
+				for timing in timings.iter().chain(render.iter()) {
+					lines.push(Layout::Text(timing.text(), Font::Smallest, TokenType::Text));
+				}
+				lines.push(Layout::StaticText(
+					"ENTER to run again, any other key to leave",
+					Font::Smallest,
+					TokenType::Label,
+				));
+				render_lines(lines, screen);
+			}
+		}
+	}
+}
+
+/// Draws lines centered on the screen, one below the other
+fn render_lines(lines: Vec<Layout>, screen: &mut dyn Screen) {
+	let area = screen.screen_rect();
+	let heights: Vec<i32> = lines
+		.iter()
+		.map(|line| line.height(screen.metrics()) + 4)
+		.collect();
+	let mut y = area.y + core::cmp::max(0, (area.h - heights.iter().sum::<i32>()) / 2);
+	let mut renderer = screen.renderer(RenderMode::Normal);
+	renderer.erase(&area);
+	for (line, height) in lines.into_iter().zip(heights) {
+		Layout::HorizontalCenter(Box::new(line)).render(
+			&mut renderer,
+			Rect {
+				x: area.x,
+				y,
+				w: area.w,
+				h: height,
+			},
+			&area,
+		);
+		y += height;
+	}
+}
+
+/// Draws a test pattern with raw pixel values, so that it does not depend on
+/// the colors of the theme
+fn render_pattern(pattern: DisplayPattern, screen: &mut dyn Screen) {
+	let area = screen.screen_rect();
+	match pattern {
+		DisplayPattern::Black => screen.fill(&area, true),
+		DisplayPattern::White => screen.fill(&area, false),
+		DisplayPattern::Checkerboard => {
+			const SQUARE: i32 = 8;
+			for y in (0..area.h).step_by(SQUARE as usize) {
+				for x in (0..area.w).step_by(SQUARE as usize) {
+					let rect = Rect {
+						x,
+						y,
+						w: SQUARE,
+						h: SQUARE,
+					};
+					screen.fill(&rect, ((x + y) / SQUARE) % 2 == 0);
+				}
+			}
+		}
+		DisplayPattern::HorizontalLines => {
+			for y in 0..area.h {
+				let rect = Rect {
+					x: 0,
+					y,
+					w: area.w,
+					h: 1,
+				};
+				screen.fill(&rect, y % 2 == 0);
+			}
+		}
+		DisplayPattern::VerticalLines => {
+			for x in 0..area.w {
+				let rect = Rect {
+					x,
+					y: 0,
+					w: 1,
+					h: area.h,
+				};
+				screen.fill(&rect, x % 2 == 0);
+			}
+		}
+		DisplayPattern::Border => {
+			screen.fill(&area, false);
+			let edges = [
+				Rect {
+					x: 0,
+					y: 0,
+					w: area.w,
+					h: 1,
+				},
+				Rect {
+					x: 0,
+					y: area.h - 1,
+					w: area.w,
+					h: 1,
+				},
+				Rect {
+					x: 0,
+					y: 0,
+					w: 1,
+					h: area.h,
+				},
+				Rect {
+					x: area.w - 1,
+					y: 0,
+					w: 1,
+					h: area.h,
+				},
+			];
+			for edge in &edges {
+				screen.fill(edge, true);
+			}
+		}
+	}
+}
+
+fn run_checks() -> Vec<CheckResult> {
+	vec![
+		check_clock(),
+		CheckResult::new("Storage", check_storage()),
+		check_decimal(
+			"sqrt 2",
+			Decimal::from(2).sqrt(),
+			"1.414213562373095048801688724209698",
+		),
+		check_decimal(
+			"exp 1",
+			Decimal::from(1).exp(),
+			"2.718281828459045235360287471352662",
+		),
+		check_decimal(
+			"ln 10",
+			Decimal::from(10).ln(),
+			"2.302585092994045684017991454684364",
+		),
+		check_decimal("sin π/6", (Decimal::pi() / Decimal::from(6)).sin(), "0.5"),
+		check_integer(),
+		check_integer_speed(),
+	]
+}
+
+/// The clock must have been set and must move forward
+fn check_clock() -> CheckResult {
+	let first = match NaiveDateTime::now() {
+		Ok(time) => time,
+		Err(error) => return CheckResult::new("Clock", Err(error)),
+	};
+	platform().delay(CLOCK_CHECK_DELAY_MS);
+	let second = match NaiveDateTime::now() {
+		Ok(time) => time,
+		Err(error) => return CheckResult::new("Clock", Err(error)),
+	};
+
+	let detail = first.year().to_number().to_string();
+	if first.year() < MIN_CLOCK_YEAR {
+		CheckResult::check("Clock", false, "not set, year ".to_string() + &detail)
+	} else if second <= first {
+		CheckResult::check("Clock", false, "not running".to_string())
+	} else {
+		CheckResult::check("Clock", true, "running".to_string())
+	}
+}
+
+/// Writes a file holding every byte value, reads it back and removes it
+fn check_storage() -> Result<String> {
+	let data: Vec<u8> = (0..=255).collect();
+	let mut store = open_file_store();
+	store.write(STORAGE_CHECK_FILE_NAME, &data)?;
+	let read = store.read(STORAGE_CHECK_FILE_NAME);
+	store.delete(STORAGE_CHECK_FILE_NAME)?;
+	if read? == data {
+		Ok("read back".to_string())
+	} else {
+		Err(Error::CorruptData)
+	}
+}
+
+fn check_decimal(name: &'static str, value: Decimal, expected: &str) -> CheckResult {
+	let expected = Decimal::from_str(expected);
+	let error = (value.clone() - expected.clone()).abs();
+	let passed = error <= expected.abs() * Decimal::from_str(MATH_CHECK_TOLERANCE);
+	CheckResult::check(name, passed, Number::Decimal(value).to_string())
+}
+
+/// Big integer arithmetic, which does not go through the decimal library
+fn check_integer() -> CheckResult {
+	let value = BigInt::from(2).pow(100u32);
+	let text = value.to_string();
+	CheckResult::check("2^100", text == "1267650600228229401496703205376", text)
+}
+
+/// Times a sum of small integers, which are kept inline, against the same sum
+/// started above 64 bits. Every step of the second sum goes through the big
+/// integer path and allocates, as all integer arithmetic did before small
+/// integers were kept inline.
+fn check_integer_speed() -> CheckResult {
+	let offset = Number::Integer((BigInt::from(1) << 64usize).into());
+	let start = NaiveDateTime::now();
+	let mut small = 0.to_number();
+	for i in 0..SPEED_CHECK_ITERATIONS {
+		small = &small + &i.to_number();
+	}
+	let middle = NaiveDateTime::now();
+	let mut big = offset.clone();
+	for i in 0..SPEED_CHECK_ITERATIONS {
+		big = &big + &i.to_number();
+	}
+	let end = NaiveDateTime::now();
+
+	let (start, middle, end) = match (start, middle, end) {
+		(Ok(start), Ok(middle), Ok(end)) => (start, middle, end),
+		_ => return CheckResult::new("Int speed", Err(Error::ValueNotDefined)),
+	};
+	let small_ms = (middle - start).num_milliseconds();
+	let big_ms = (end - middle).num_milliseconds();
+	CheckResult::check(
+		"Int speed",
+		match (small.to_int(), (&big - &offset).to_int()) {
+			(Ok(small), Ok(big)) => small == big,
+			_ => false,
+		},
+		small_ms.to_number().to_string() + " ms, big " + &big_ms.to_number().to_string() + " ms",
+	)
+}
+
+/// Times big integer multiplication at several sizes and the decimal functions
+/// that most others are built on
+fn run_benchmark() -> Vec<Timing> {
+	let mut timings = Vec::new();
+	for bits in &BENCHMARK_INTEGER_BITS {
+		let value = (BigInt::from(1) << *bits as usize) - BigInt::from(1);
+		let mut offset = 0;
+		let mut product_bits = 0;
+		timings.push(time(
+			"Mul ".to_string() + &bits.to_number().to_string() + " bit",
+			BENCHMARK_ITERATIONS,
+			&mut || {
+				offset += 1;
+				product_bits = (&value * &(&value + BigInt::from(offset))).bits();
+			},
+		));
+	}
+
+	let x = Decimal::from_str("2.5");
+	let mut result = Decimal::zero();
+	timings.push(time("ln".to_string(), BENCHMARK_ITERATIONS, &mut || {
+		result = x.ln();
+	}));
+	timings.push(time("exp".to_string(), BENCHMARK_ITERATIONS, &mut || {
+		result = x.exp();
+	}));
+
+	timings.push(time_fraction_sum("Sum fractions", false));
+	timings.push(time_fraction_sum("Sum deferred", true));
+	timings.push(time_stack_sum("Stack sum", false));
+	timings.push(time_stack_sum("Stack deferred", true));
+	timings
+}
+
+/// Times the same sum done on the stack, as it is when typed in, ending with the
+/// reduction that is done to display the result
+fn time_stack_sum(name: &str, deferred_reduction: bool) -> Timing {
+	let mut context = Context::new();
+	context.set_deferred_reduction(deferred_reduction);
+	let _modes = ModeScope::begin(&context);
+	let denom = BENCHMARK_DENOMINATOR.to_number();
+	let fractions: Vec<Value> = (1..=BENCHMARK_FRACTIONS)
+		.map(|k| Value::Number(&k.to_number() / &denom))
+		.collect();
+	let mut sum = 0.to_number();
+	time(name.to_string(), BENCHMARK_ITERATIONS, &mut || {
+		context.clear_stack();
+		let _ = context.push(Value::Number(0.to_number()));
+		for fraction in &fractions {
+			let _ = context.push(fraction.clone());
+			let _ = context.add();
+		}
+		if let Ok(value) = context.entry(0) {
+			if let Ok(number) = value.real_number() {
+				sum = number.reduced().into_owned();
+			}
+		}
+	})
+}
+
+/// Times a sum of fractions that share a denominator, with the GCD reduction of
+/// each partial sum done right away or deferred
+fn time_fraction_sum(name: &str, deferred_reduction: bool) -> Timing {
+	let mut context = Context::new();
+	context.set_deferred_reduction(deferred_reduction);
+	let _modes = ModeScope::begin(&context);
+	let denom = BENCHMARK_DENOMINATOR.to_number();
+	let fractions: Vec<Number> = (1..=BENCHMARK_FRACTIONS)
+		.map(|k| &k.to_number() / &denom)
+		.collect();
+	let mut sum = 0.to_number();
+	time(name.to_string(), BENCHMARK_ITERATIONS, &mut || {
+		sum = fractions
+			.iter()
+			.fold(0.to_number(), |total, fraction| &total + fraction);
+	})
+}
+
+/// Runs an operation the given number of times, giving the average time of a
+/// run
+fn time(name: String, iterations: u32, op: &mut dyn FnMut()) -> Timing {
+	let start = NaiveDateTime::now();
+	for _ in 0..iterations {
+		op();
+	}
+	let micros = match (start, NaiveDateTime::now()) {
+		(Ok(start), Ok(end)) => (end - start).num_microseconds(),
+		_ => None,
+	};
+	Timing {
+		name,
+		micros: micros.map(|micros| micros / iterations as i64),
+	}
+}
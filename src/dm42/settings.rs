@@ -0,0 +1,261 @@
+use crate::dm42::display::{DisplayControl, AUTO_DIM_SECONDS, DISPLAY_LEVEL_MAX};
+use crate::dm42::feedback::FeedbackEvent;
+use crate::dm42::locale::Language;
+use crate::dm42::screen::Theme;
+use rscalc_layout::font::Font;
+use rscalc_math::context::Context;
+use rscalc_math::error::Result;
+use rscalc_math::storage::{DeserializeInput, SerializeOutput};
+
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum StatusBarLeftDisplayType {
+	CurrentTime,
+	FreeMemory,
+	FrameTime,
+	Battery,
+}
+
+/// What ENTER does after a number has been typed
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum EnterMode {
+	/// ENTER ends entry of the number, as on RPL calculators
+	Terminate,
+	/// ENTER also copies the number into y, and the next number typed replaces
+	/// the copy in x, as on classic RPN calculators
+	Duplicate,
+}
+
+/// What the calculator does when it is turned on
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum StartupMode {
+	/// Continue with the state from before it was turned off
+	Restore,
+	/// Keep the settings, memory and programs, but start with an empty stack
+	ClearStack,
+	/// Restore the state and run the recorded program
+	RunProgram,
+}
+
+/// Choices for the number of slots in the menu for assigning custom functions
+pub const CUSTOM_SLOT_COUNTS: [u8; 5] = [6, 12, 18, 24, 36];
+
+/// Choices for the minutes between automatic saves of the state, with zero
+/// turning them off
+pub const AUTO_SAVE_MINUTES: [u8; 5] = [0, 1, 5, 15, 30];
+
+/// Saved in place of a setting that is now kept by the context, which saves it
+/// with its own modes. Older versions saved the setting itself as 0 or 1.
+const SAVED_WITH_CONTEXT: u8 = 2;
+
+/// Options chosen by the user for the calculator itself. Options that affect
+/// how numbers are formatted, such as the radix, angle mode, display mode and
+/// separators, are kept on the context where the math library can use them.
+/// Both are persisted as part of the saved state.
+#[derive(Clone)]
+pub struct Settings {
+	pub status_bar_left_display: StatusBarLeftDisplayType,
+	pub status_bar_enabled: bool,
+	pub base_font: Font,
+	pub reduced_motion: bool,
+	pub stack_render_cache: bool,
+	pub show_empty_soft_keys: bool,
+	pub theme: Theme,
+	pub enter_mode: EnterMode,
+	pub startup_mode: StartupMode,
+	/// Number of slots shown when assigning custom functions
+	pub custom_slots: u8,
+	pub key_click: bool,
+	pub error_buzz: bool,
+	pub alarm_tone: bool,
+	pub brightness: u8,
+	pub contrast: u8,
+	/// Seconds without a key press before the display is dimmed, or zero to
+	/// keep it at full brightness
+	pub auto_dim_seconds: u16,
+	pub language: Language,
+	/// Minutes between saves of the state while the calculator is in use, or
+	/// zero to only save when it is turned off
+	pub auto_save_minutes: u8,
+}
+
+impl Settings {
+	pub fn new() -> Self {
+		Settings {
+			status_bar_left_display: StatusBarLeftDisplayType::CurrentTime,
+			status_bar_enabled: true,
+			base_font: Font::Large,
+			reduced_motion: false,
+			stack_render_cache: true,
+			show_empty_soft_keys: false,
+			theme: Theme::Light,
+			enter_mode: EnterMode::Terminate,
+			startup_mode: StartupMode::Restore,
+			custom_slots: 18,
+			key_click: false,
+			error_buzz: true,
+			alarm_tone: true,
+			brightness: DisplayControl::Brightness.default_level(),
+			contrast: DisplayControl::Contrast.default_level(),
+			auto_dim_seconds: 0,
+			language: Language::English,
+			auto_save_minutes: 5,
+		}
+	}
+
+	pub fn feedback_enabled(&self, event: FeedbackEvent) -> bool {
+		match event {
+			FeedbackEvent::KeyClick => self.key_click,
+			FeedbackEvent::Error => self.error_buzz,
+			FeedbackEvent::Alarm => self.alarm_tone,
+		}
+	}
+
+	pub fn set_feedback_enabled(&mut self, event: FeedbackEvent, value: bool) {
+		match event {
+			FeedbackEvent::KeyClick => self.key_click = value,
+			FeedbackEvent::Error => self.error_buzz = value,
+			FeedbackEvent::Alarm => self.alarm_tone = value,
+		}
+	}
+
+	/// Writes the settings into saved state. New settings must be added to the
+	/// end so that older versions can still read the rest.
+	pub fn save(&self, output: &mut Vec<u8>) -> Result<()> {
+		output.write_u8(match self.status_bar_left_display {
+			StatusBarLeftDisplayType::CurrentTime => 0,
+			StatusBarLeftDisplayType::FreeMemory => 1,
+			StatusBarLeftDisplayType::FrameTime => 2,
+			StatusBarLeftDisplayType::Battery => 3,
+		})?;
+		output.write_u8(self.status_bar_enabled as u8)?;
+		output.write_u8(match self.base_font {
+			Font::Smallest => 0,
+			Font::Small => 1,
+			Font::Medium => 2,
+			Font::Large => 3,
+		})?;
+		output.write_u8(self.reduced_motion as u8)?;
+		output.write_u8(self.stack_render_cache as u8)?;
+		output.write_u8(self.show_empty_soft_keys as u8)?;
+		output.write_u8(match self.theme {
+			Theme::Light => 0,
+			Theme::Dark => 1,
+		})?;
+		// Deferred reduction and complex results
+		output.write_u8(SAVED_WITH_CONTEXT)?;
+		output.write_u8(SAVED_WITH_CONTEXT)?;
+		output.write_u8(match self.enter_mode {
+			EnterMode::Terminate => 0,
+			EnterMode::Duplicate => 1,
+		})?;
+		output.write_u8(match self.startup_mode {
+			StartupMode::Restore => 0,
+			StartupMode::ClearStack => 1,
+			StartupMode::RunProgram => 2,
+		})?;
+		output.write_u8(self.custom_slots)?;
+		output.write_u8(self.key_click as u8)?;
+		output.write_u8(self.error_buzz as u8)?;
+		output.write_u8(self.alarm_tone as u8)?;
+		output.write_u8(self.brightness)?;
+		output.write_u8(self.contrast)?;
+		output.write_u16(self.auto_dim_seconds)?;
+		output.write_u8(self.language.index() as u8)?;
+		output.write_u8(self.auto_save_minutes)
+	}
+
+	/// Restores settings written by `save`. Settings with values that are not
+	/// known are left unchanged, and settings that older versions saved here but
+	/// that are now kept by the context are given to `context`.
+	pub fn restore(&mut self, input: &mut DeserializeInput, context: &mut Context) -> Result<()> {
+		match input.read_u8()? {
+			0 => self.status_bar_left_display = StatusBarLeftDisplayType::CurrentTime,
+			1 => self.status_bar_left_display = StatusBarLeftDisplayType::FreeMemory,
+			2 => self.status_bar_left_display = StatusBarLeftDisplayType::FrameTime,
+			3 => self.status_bar_left_display = StatusBarLeftDisplayType::Battery,
+			_ => (),
+		}
+		self.status_bar_enabled = input.read_u8()? != 0;
+		match input.read_u8()? {
+			0 => self.base_font = Font::Smallest,
+			1 => self.base_font = Font::Small,
+			2 => self.base_font = Font::Medium,
+			3 => self.base_font = Font::Large,
+			_ => (),
+		}
+		self.reduced_motion = input.read_u8()? != 0;
+		self.stack_render_cache = input.read_u8()? != 0;
+		self.show_empty_soft_keys = input.read_u8()? != 0;
+		match input.read_u8()? {
+			0 => self.theme = Theme::Light,
+			1 => self.theme = Theme::Dark,
+			_ => (),
+		}
+		match input.read_u8()? {
+			SAVED_WITH_CONTEXT => (),
+			value => context.set_deferred_reduction(value != 0),
+		}
+		// Older versions did not save the complex results setting
+		if input.remaining() > 0 {
+			match input.read_u8()? {
+				SAVED_WITH_CONTEXT => (),
+				value => context.set_complex_results(value != 0),
+			}
+		}
+		if input.remaining() > 0 {
+			match input.read_u8()? {
+				0 => self.enter_mode = EnterMode::Terminate,
+				1 => self.enter_mode = EnterMode::Duplicate,
+				_ => (),
+			}
+		}
+		if input.remaining() > 0 {
+			match input.read_u8()? {
+				0 => self.startup_mode = StartupMode::Restore,
+				1 => self.startup_mode = StartupMode::ClearStack,
+				2 => self.startup_mode = StartupMode::RunProgram,
+				_ => (),
+			}
+		}
+		if input.remaining() > 0 {
+			let custom_slots = input.read_u8()?;
+			if CUSTOM_SLOT_COUNTS.contains(&custom_slots) {
+				self.custom_slots = custom_slots;
+			}
+		}
+		if input.remaining() > 0 {
+			self.key_click = input.read_u8()? != 0;
+			self.error_buzz = input.read_u8()? != 0;
+			self.alarm_tone = input.read_u8()? != 0;
+		}
+		if input.remaining() > 0 {
+			let brightness = input.read_u8()?;
+			let contrast = input.read_u8()?;
+			let auto_dim_seconds = input.read_u16()?;
+			if brightness <= DISPLAY_LEVEL_MAX && contrast <= DISPLAY_LEVEL_MAX {
+				self.brightness = brightness;
+				self.contrast = contrast;
+			}
+			if AUTO_DIM_SECONDS.contains(&auto_dim_seconds) {
+				self.auto_dim_seconds = auto_dim_seconds;
+			}
+		}
+		if input.remaining() > 0 {
+			if let Some(language) = Language::from_index(input.read_u8()? as usize) {
+				self.language = language;
+			}
+		}
+		if input.remaining() > 0 {
+			let auto_save_minutes = input.read_u8()?;
+			if AUTO_SAVE_MINUTES.contains(&auto_save_minutes) {
+				self.auto_save_minutes = auto_save_minutes;
+			}
+		}
+		// Some older versions saved a copy of the number format after these, which
+		// is left unread as the context saves the number format itself
+		Ok(())
+	}
+}
@@ -1,21 +1,12 @@
 use crate::dm42::calc_main;
-use crate::dm42::input::{InputQueue, Key, KeyEvent};
-use crate::dm42::screen::{RenderMode, Screen, ScreenLayoutRenderer};
+use crate::dm42::host::{Refresh, VirtualDM42Screen, VirtualKeyboard, HEIGHT, SET_PIXEL, WIDTH};
+use crate::dm42::input::{Key, KeyEvent, Keyboard};
+use crate::dm42::platform::interrupt_computation;
 use gdk_pixbuf::{Colorspace, Pixbuf};
 use glib::source::{timeout_add_local, Continue};
 use gtk::*;
-use rscalc_layout::layout::Rect;
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::Duration;
-
-const WIDTH: i32 = 400;
-const HEIGHT: i32 = 240;
-const WIDTH_BYTES: usize = WIDTH as usize / 8;
-
-pub struct Refresh {
-	screen: Option<VirtualDM42Screen>,
-}
 
 pub struct App {
 	window: Window,
@@ -32,7 +23,10 @@ impl App {
 		let input_queue = Arc::new(Mutex::new(Vec::new()));
 		let input_event = Arc::new(Condvar::new());
 		let screen = VirtualDM42Screen::new(refresh.clone());
-		let input = VirtualInputQueue::new(input_queue.clone(), input_event.clone());
+		let input = Keyboard::new(VirtualKeyboard::new(
+			input_queue.clone(),
+			input_event.clone(),
+		));
 		let content = Content::new(&screen, input_queue, input_event);
 		thread::spawn(move || {
 			calc_main(screen, input);
@@ -53,7 +47,7 @@ impl App {
 		timeout_add_local(33, move || {
 			let mut refresh = timeout_refresh.lock().unwrap();
 			if let Some(screen) = &refresh.screen {
-				let pixbuf = screen.to_pixbuf();
+				let pixbuf = to_pixbuf(screen);
 				content.image.set_from_pixbuf(Some(&pixbuf));
 				refresh.screen = None;
 			}
@@ -83,10 +77,27 @@ impl Content {
 	) -> Content {
 		let container = Box::new(Orientation::Vertical, 0);
 		let image = Image::new();
-		let pixbuf = screen.to_pixbuf();
+		let pixbuf = to_pixbuf(screen);
 		image.set_from_pixbuf(Some(&pixbuf));
 
-		container.pack_start(&image, false, false, 0);
+		// Clicks on the screen are taps. The image is centered in the space given
+		// to it, which can be wider than the screen.
+		let screen_events = EventBox::new();
+		screen_events.add(&image);
+		let touch_input_queue = input_queue.clone();
+		let touch_input_event = input_event.clone();
+		screen_events.connect_button_press_event(move |events, event| {
+			let (x, y) = event.get_position();
+			let x = x as i32 - (events.get_allocated_width() - WIDTH) / 2;
+			let y = y as i32 - (events.get_allocated_height() - HEIGHT) / 2;
+			if x >= 0 && y >= 0 && x < WIDTH && y < HEIGHT {
+				let mut queue = touch_input_queue.lock().unwrap();
+				queue.push(KeyEvent::Touch(x, y));
+				touch_input_event.notify_one();
+			}
+			Inhibit(true)
+		});
+		container.pack_start(&screen_events, false, false, 0);
 
 		let keyboard_top = Grid::new();
 		keyboard_top.set_column_spacing(2);
@@ -110,11 +121,15 @@ impl Content {
 				let button_input_queue = input_queue.clone();
 				let button_input_event = input_event.clone();
 				key.connect_clicked(move |_| {
+					// EXIT stops a running computation instead of being queued
+					if $key == Key::Exit && interrupt_computation() {
+						return;
+					}
 					let mut queue = button_input_queue.lock().unwrap();
 					queue.push(KeyEvent::Press($key));
 					queue.push(KeyEvent::Release);
 					button_input_event.notify_one();
-					});
+				});
 			};
 		}
 
@@ -136,11 +151,15 @@ impl Content {
 				let button_input_queue = input_queue.clone();
 				let button_input_event = input_event.clone();
 				key.connect_clicked(move |_| {
+					// EXIT stops a running computation instead of being queued
+					if $key == Key::Exit && interrupt_computation() {
+						return;
+					}
 					let mut queue = button_input_queue.lock().unwrap();
 					queue.push(KeyEvent::Press($key));
 					queue.push(KeyEvent::Release);
 					button_input_event.notify_one();
-					});
+				});
 				$grid.attach(&key_lbl, $x * 2, $y, $span, 1);
 				$grid.attach(&key, $x * 2, $y + 1, $span, 1);
 				$grid.attach(&a, $x * 2 + $span, $y + 1, $span, 1);
@@ -198,132 +217,15 @@ impl Content {
 	}
 }
 
-#[derive(Clone)]
-pub struct VirtualDM42Screen {
-	bitmap: [u8; WIDTH_BYTES * HEIGHT as usize],
-	refresh: Arc<Mutex<Refresh>>,
-}
-
-impl VirtualDM42Screen {
-	pub fn new(refresh: Arc<Mutex<Refresh>>) -> Self {
-		VirtualDM42Screen {
-			bitmap: [0; WIDTH_BYTES * HEIGHT as usize],
-			refresh,
-		}
-	}
-
-	fn pixel(&self, x: i32, y: i32) -> bool {
-		if x < 0 || x >= WIDTH || y < 0 || y >= HEIGHT {
-			return false;
-		}
-		self.bitmap[y as usize * WIDTH_BYTES + (x as usize / 8)] & (1 << (x & 7)) != 0
-	}
-
-	fn set_pixel(&mut self, x: i32, y: i32, color: bool) {
-		if x < 0 || x >= WIDTH || y < 0 || y >= HEIGHT {
-			return;
-		}
-		if color {
-			self.bitmap[y as usize * WIDTH_BYTES + (x as usize / 8)] |= 1 << (x & 7);
-		} else {
-			self.bitmap[y as usize * WIDTH_BYTES + (x as usize / 8)] &= !(1 << (x & 7));
-		}
-	}
-
-	fn to_pixbuf(&self) -> Pixbuf {
-		let pixbuf = Pixbuf::new(Colorspace::Rgb, false, 8, WIDTH, HEIGHT).unwrap();
-
-		for y in 0..HEIGHT {
-			for x in 0..WIDTH {
-				if self.pixel(x, y) {
-					pixbuf.put_pixel(x as u32, y as u32, 0, 0, 0, 255);
-				} else {
-					pixbuf.put_pixel(x as u32, y as u32, 255, 255, 255, 255);
-				}
-			}
-		}
-
-		pixbuf
-	}
-}
-
-impl Screen for VirtualDM42Screen {
-	fn width(&self) -> i32 {
-		WIDTH
-	}
-
-	fn height(&self) -> i32 {
-		HEIGHT
-	}
+fn to_pixbuf(screen: &VirtualDM42Screen) -> Pixbuf {
+	let pixbuf = Pixbuf::new(Colorspace::Rgb, false, 8, WIDTH, HEIGHT).unwrap();
 
-	fn clear(&mut self) {
-		for i in 0..WIDTH_BYTES * HEIGHT as usize {
-			self.bitmap[i] = 0;
-		}
-	}
-
-	fn refresh(&mut self) {
-		self.refresh.lock().unwrap().screen = Some(self.clone());
-	}
-
-	fn fill(&mut self, rect: &Rect, color: bool) {
-		let rect = rect.clipped_to(&self.screen_rect());
-		for y in rect.y..rect.y + rect.h {
-			for x in rect.x..rect.x + rect.w {
-				self.set_pixel(x, y, color);
-			}
-		}
-	}
-
-	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
-		for i in 0..width {
-			if bits & (1 << ((width - 1) - i)) != 0 {
-				self.set_pixel(x + i as i32, y, color);
-			}
-		}
-	}
-
-	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer {
-		ScreenLayoutRenderer::new(self, render_mode)
-	}
-}
-
-pub struct VirtualInputQueue {
-	queue: Arc<Mutex<Vec<KeyEvent>>>,
-	event: Arc<Condvar>,
-}
-
-impl VirtualInputQueue {
-	fn new(queue: Arc<Mutex<Vec<KeyEvent>>>, event: Arc<Condvar>) -> Self {
-		VirtualInputQueue { queue, event }
-	}
-}
-
-impl InputQueue for VirtualInputQueue {
-	fn has_input(&self) -> bool {
-		self.queue.lock().unwrap().len() != 0
-	}
-
-	fn pop_raw(&mut self) -> Option<KeyEvent> {
-		let mut queue = self.queue.lock().unwrap();
-		queue.pop()
-	}
-
-	fn wait_raw(&mut self) -> Option<KeyEvent> {
-		let mut queue = self.queue.lock().unwrap();
-		if queue.len() == 0 {
-			queue = self
-				.event
-				.wait_timeout(queue, Duration::from_secs(1))
-				.unwrap()
-				.0;
-		}
-		if queue.len() != 0 {
-			Some(queue.pop().unwrap())
-		} else {
-			None
+	for y in 0..HEIGHT {
+		for x in 0..WIDTH {
+			let value = SET_PIXEL - screen.intensity(x, y);
+			pixbuf.put_pixel(x as u32, y as u32, value, value, value, 255);
 		}
 	}
 
-	fn suspend(&self) {}
+	pixbuf
 }
@@ -1,46 +1,113 @@
-use crate::dm42::catalog::{assign_menu, catalog_menu};
-use crate::dm42::edit::NumberEditor;
-use crate::dm42::functions::{Function, FunctionKeyState, FunctionMenu};
-use crate::dm42::input::{AlphaMode, InputEvent, InputMode, InputQueue};
+use crate::dm42::alarm::{Alarm, AlarmList};
+use crate::dm42::animation::{fade_rect, Animation, AnimationType, OffsetScreen};
+use crate::dm42::answers::AnswerHistory;
+use crate::dm42::calendar::Calendar;
+use crate::dm42::catalog::{
+	assign_menu, catalog_menu, catalog_search_menu, custom_banks_menu, program_slots_menu,
+	solver_menu,
+};
+use crate::dm42::currency::{CurrencyRates, CURRENCY_CODES};
+use crate::dm42::diagnostics::SessionStats;
+use crate::dm42::dialog::{ConfirmDialog, DialogAnswer};
+use crate::dm42::display::{
+	dimmed_brightness, DisplayControl, AUTO_DIM_SECONDS, DISPLAY_LEVEL_MAX,
+};
+use crate::dm42::edit::{number_inputs, NumberEditor};
+use crate::dm42::feedback::{play_program_tone, FeedbackEvent};
+use crate::dm42::files::{file_browser_menu, open_file_store, FileKind, STATE_FILE_NAME};
+use crate::dm42::flags::{is_system_flag, FlagOperation, Flags, FLAG_COUNT, FLAG_DIGITS};
+use crate::dm42::functions::{
+	function_key_at, render_function_key_labels, Function, FunctionKeyState, FunctionMenu,
+};
+use crate::dm42::functions::{CustomSlot, ProgramMenuKey};
+use crate::dm42::graph::{Graph, GraphAnalysis, ProgramStep, GRAPH_ANALYSES};
+use crate::dm42::input::{AlphaMode, InputEvent, InputMode, InputQueue, SystemAction};
+use crate::dm42::listing::{parse_program_listing, program_listing, PROGRAM_LISTING_FILE_NAME};
+use crate::dm42::locale::{set_language, tr, Language};
+use crate::dm42::matrix_editor::{MatrixEditAction, MatrixEditor};
 use crate::dm42::menu::{setup_menu, Menu, MenuItemFunction};
-use crate::dm42::screen::{RenderMode, Screen};
+use crate::dm42::pane::{pane_layout, render_pane, AuxPane, SplitLayout};
+use crate::dm42::platform::{platform, PowerStatus};
+use crate::dm42::save::{read_script, write_saved_state};
+use crate::dm42::screen::{set_theme, DamageTrackingScreen, RenderMode, Screen, Theme};
+use crate::dm42::selftest::SelfTest;
+use crate::dm42::settings::{
+	EnterMode, Settings, StartupMode, StatusBarLeftDisplayType, AUTO_SAVE_MINUTES,
+};
+use crate::dm42::table::{function_table_menu, FunctionTable};
+use crate::dm42::tape::{Tape, TapeEntry, MAX_TAPE_OPERANDS, TAPE_FILE_NAME};
+use crate::dm42::toast::{Toast, ToastKind};
 use crate::dm42::unit::unit_menu;
 use chrono::NaiveDateTime;
+use core::convert::TryFrom;
+use intel_dfp::Decimal;
 use rscalc_layout::decimal::DecimalLayout;
-use rscalc_layout::font::Font;
+use rscalc_layout::font::{Font, FontSet};
 use rscalc_layout::layout::{Layout, LayoutRenderer, Rect, TokenType};
 use rscalc_layout::stack::StackRenderer;
 use rscalc_layout::string::StringLayout;
 use rscalc_layout::value::{AlternateLayoutType, ValueLayout};
+use rscalc_math::business::BusinessVariable;
 use rscalc_math::constant::Constant;
 use rscalc_math::context::{Context, Location};
+use rscalc_math::equation::EQUATION_LIBRARY;
 use rscalc_math::error::{Error, Result};
-use rscalc_math::format::{Format, IntegerMode};
-use rscalc_math::number::ToNumber;
-use rscalc_math::storage::available_bytes;
+use rscalc_math::format::{Format, FormatMode, IntegerMode};
+use rscalc_math::histogram::Histogram;
+use rscalc_math::iso8601;
+use rscalc_math::number::{Number, ToNumber};
+use rscalc_math::persist::{read_string, write_string, StateReader, StateWriter};
+use rscalc_math::script::{Script, Series};
+use rscalc_math::solver::{argument_location, solve_equation};
+use rscalc_math::storage::{
+	available_bytes, deserialize_portable, serialize_portable, DeserializeInput, SerializeOutput,
+};
 use rscalc_math::time::{Now, SimpleDateTimeFormat, SimpleDateTimeToString};
 use rscalc_math::unit::AngleUnit;
 use rscalc_math::value::Value;
 
-#[cfg(not(feature = "dm42"))]
+#[cfg(not(feature = "embedded"))]
 use std::cell::RefCell;
-#[cfg(not(feature = "dm42"))]
+#[cfg(not(feature = "embedded"))]
 use std::rc::Rc;
 
-#[cfg(feature = "dm42")]
-use crate::dm42::device::{read_power_voltage, show_system_setup_menu, usb_powered};
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::boxed::Box;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::rc::Rc;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::string::{String, ToString};
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use core::cell::RefCell;
 
-const MAX_MEMORY_INDEX_DIGITS: usize = 2;
+pub const MAX_MEMORY_INDEX_DIGITS: usize = 2;
+
+/// Number of steps after which macro recording ends on its own
+const MAX_MACRO_STEPS: usize = 64;
+
+/// Number of macros that can be running inside each other
+const MAX_MACRO_DEPTH: usize = 8;
+
+/// Keys that pick the menu items after the first ten, which are picked with the
+/// digit keys
+const MENU_LETTER_KEYS: [InputEvent; 12] = [
+	InputEvent::SigmaPlus,
+	InputEvent::Recip,
+	InputEvent::Sqrt,
+	InputEvent::Log,
+	InputEvent::Ln,
+	InputEvent::Xeq,
+	InputEvent::Sto,
+	InputEvent::Rcl,
+	InputEvent::RotateDown,
+	InputEvent::Sin,
+	InputEvent::Cos,
+	InputEvent::Tan,
+];
 
 /// Cached state for rendering the status bar. This is used to optimize the rendering
 /// of the status bar such that it is only drawn when it is updated.
@@ -51,14 +118,78 @@ struct CachedStatusBarState {
 	integer_mode: IntegerMode,
 	angle_mode: AngleUnit,
 	multiple_pages: bool,
+	recording: bool,
+	exam: bool,
+	busy: bool,
+	carry: bool,
+	out_of_range: bool,
+	/// Name of the active bank of custom functions, when there is more than one
+	custom_bank: Option<String>,
 	left_string: String,
+	/// Text being built for the left side, kept to avoid allocating on each update
+	next_left_string: String,
+}
+
+impl CachedStatusBarState {
+	/// Takes the newly built left side text if it differs from what is shown,
+	/// returning true if it changed
+	fn update_left_string(&mut self) -> bool {
+		if self.next_left_string == self.left_string {
+			return false;
+		}
+		self.left_string.clone_from(&self.next_left_string);
+		true
+	}
 }
 
 #[derive(Clone)]
 struct LocationEntryState {
 	name: &'static str,
 	stack: bool,
+	/// The location is the register numbered by the value in the chosen one
+	indirect: bool,
 	value: Vec<u8>,
+	operation: Option<StoreOperation>,
+}
+
+/// Register arithmetic applied when storing, which combines the value in the
+/// register with the value being stored
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum StoreOperation {
+	Add,
+	Sub,
+	Mul,
+	Div,
+}
+
+impl StoreOperation {
+	fn from_input(input: InputEvent) -> Option<Self> {
+		match input {
+			InputEvent::Add => Some(StoreOperation::Add),
+			InputEvent::Sub => Some(StoreOperation::Sub),
+			InputEvent::Mul => Some(StoreOperation::Mul),
+			InputEvent::Div => Some(StoreOperation::Div),
+			_ => None,
+		}
+	}
+
+	fn name(&self) -> &'static str {
+		match self {
+			StoreOperation::Add => "Sto+",
+			StoreOperation::Sub => "Sto−",
+			StoreOperation::Mul => "Sto×",
+			StoreOperation::Div => "Sto÷",
+		}
+	}
+
+	fn apply(&self, register: Value, value: Value) -> Result<Value> {
+		match self {
+			StoreOperation::Add => register + value,
+			StoreOperation::Sub => register - value,
+			StoreOperation::Mul => register * value,
+			StoreOperation::Div => register / value,
+		}
+	}
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -68,178 +199,2448 @@ enum InputState {
 	Recall,
 	Store,
 	Menu,
+	Graph,
+	Lock,
+	TextEntry,
+	Tape,
+	Calendar,
+	SelfTest,
+	MatrixEdit,
+	/// Choosing the variable that a program asks the user for
+	InputLocation,
+	/// A program is waiting for the user before continuing
+	ProgramPause,
+	/// Choosing the flag for a flag operation
+	FlagEntry(FlagOperation),
+	/// Choosing the name of a label in a program
+	Label,
+	/// Choosing the label that a program continues at
+	Goto,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-pub enum StatusBarLeftDisplayType {
-	CurrentTime,
-	FreeMemory,
+/// What a paused program is waiting for
+enum ProgramPrompt {
+	/// A value for a variable. The editor is created once a key is typed, and
+	/// until then the current value is shown.
+	Input(Location, Option<NumberEditor>),
+	/// Any key, while x is shown
+	View,
+}
+
+/// Program that has stopped to ask for something. The steps and the position
+/// to continue from are kept for each level of macros that were running,
+/// innermost first.
+struct ProgramPause {
+	prompt: ProgramPrompt,
+	remaining: Vec<(Vec<ProgramStep>, usize)>,
+}
+
+/// Number of programs that can be kept, one of which is the recorded program
+/// used for graphing
+pub const PROGRAM_SLOTS: usize = 6;
+
+/// Program kept aside while another one is the recorded program
+struct ProgramSlot {
+	name: String,
+	program: Option<Vec<ProgramStep>>,
+	locked: bool,
+}
+
+impl ProgramSlot {
+	fn new(idx: usize) -> Self {
+		ProgramSlot {
+			name: "P".to_string() + &Number::Integer((idx + 1).into()).to_string(),
+			program: None,
+			locked: false,
+		}
+	}
+}
+
+/// What the text being entered is used for
+#[derive(PartialEq, Eq, Clone)]
+enum TextEntryTarget {
+	Script,
+	RenameFile(String),
+	/// Text pushed onto the stack
+	Text,
+	/// Text to find in the names of the functions in the catalog
+	CatalogSearch,
+	RenameCustomBank,
+	RenameProgramSlot,
 }
 
+// Tags of the saved state records written by the calculator itself
+const STATE_RECORD_SETTINGS: u16 = 0x0100;
+const STATE_RECORD_PROGRAM: u16 = 0x0102;
+const STATE_RECORD_SCRIPTS: u16 = 0x0103;
+const STATE_RECORD_ALARMS: u16 = 0x0104;
+const STATE_RECORD_CURRENCIES: u16 = 0x0105;
+const STATE_RECORD_EXAM: u16 = 0x0106;
+const STATE_RECORD_FLAGS: u16 = 0x0107;
+const STATE_RECORD_PROGRAM_SLOTS: u16 = 0x0108;
+
 pub struct State {
 	context: Context,
 	input_mode: InputMode,
 	function_keys: FunctionKeyState,
-	status_bar_left_display: StatusBarLeftDisplayType,
+	settings: Settings,
 	input_state: InputState,
 	location_entry: LocationEntryState,
-	error: Option<Error>,
+	toast: Option<Toast>,
 	menus: Vec<Menu>,
 	editor: Option<NumberEditor>,
-	status_bar_enabled: bool,
-	base_font: Font,
 	stack_renderer: Rc<RefCell<StackRenderer>>,
+	prev_bottom_layouts: Vec<(Layout, Rect)>,
+	bottom_layouts: RefCell<Vec<(Layout, Rect)>>,
 	cached_status_bar_state: CachedStatusBarState,
 	force_refresh: bool,
 	force_render_on_status_update: bool,
+	animation: Option<Animation>,
+	animation_supported: bool,
+	last_frame_time: Option<i64>,
+	program_recording: Option<Vec<ProgramStep>>,
+	recording_step: bool,
+	recorded_program: Option<Vec<ProgramStep>>,
+	/// Programs that can be switched between. The program and lock of the
+	/// active slot are kept in `recorded_program` and `program_locked`.
+	program_slots: Vec<ProgramSlot>,
+	active_program_slot: usize,
+	macro_recording: Option<(CustomSlot, Vec<ProgramStep>)>,
+	macro_depth: usize,
+	/// Number of programs and macros that are running, including the ones that
+	/// have been called by another
+	steps_depth: usize,
+	scripts: Vec<Script>,
+	text_entry: String,
+	text_entry_target: TextEntryTarget,
+	program_locked: bool,
+	program_pause: Option<ProgramPause>,
+	flags: Flags,
+	/// Set when a test in a program fails, so that the next step is skipped
+	skip_step: bool,
+	/// Label that the running program continues at after the current step
+	program_goto: Option<Location>,
+	graph_range: (Decimal, Decimal),
+	graph: Option<Graph>,
+	function_table: Option<FunctionTable>,
+	session_stats: SessionStats,
+	tape: Option<Tape>,
+	answers: AnswerHistory,
+	generation: u32,
+	tape_scroll: usize,
+	calendar: Option<Calendar>,
+	self_test: Option<SelfTest>,
+	matrix_editor: Option<MatrixEditor>,
+	/// Equation whose arguments are on the soft keys of the solver
+	solver: Option<Script>,
+	busy: bool,
+	split: SplitLayout,
+	pane_cache: Option<(Option<Layout>, Rect)>,
+	alarms: AlarmList,
+	ringing_alarm: Option<Alarm>,
+	/// Low battery warning shown over the whole screen, with whether the state
+	/// could be saved
+	low_battery_warning: Option<bool>,
+	dialog: Option<ConfirmDialog>,
+	currencies: CurrencyRates,
+	/// Currency picked by the last key of the currency menu, which the next
+	/// key converts from
+	currency_from: Option<usize>,
+	/// Saved state from before exam mode was entered, which is put back when it
+	/// ends. Its presence is what puts the calculator in exam mode.
+	exam_stash: Option<Vec<u8>>,
+	/// Set while the display is dimmed after no key has been pressed for a while
+	display_dimmed: bool,
+	/// Why the last key typed into the number being entered was refused
+	entry_rejection: Option<Error>,
+}
+
+pub enum InputResult {
+	Normal,
+	Suspend,
+}
+
+pub enum LocationInputResult {
+	Intermediate(InputResult),
+	Finished(Location),
+	Invalid,
+	Exit,
+}
+
+impl LocationEntryState {
+	fn new(name: &'static str) -> Self {
+		LocationEntryState {
+			name,
+			stack: false,
+			indirect: false,
+			value: Vec::new(),
+			operation: None,
+		}
+	}
+
+	fn int_value(&self) -> usize {
+		let mut result = 0;
+		for digit in &self.value {
+			result *= 10;
+			result += *digit as usize;
+		}
+		result
+	}
 }
 
-pub enum InputResult {
-	Normal,
-	Suspend,
-}
+impl State {
+	pub fn new() -> Self {
+		let mut state = Self::new_with_context(Context::new_with_undo());
+		state.tape = Some(Tape::new());
+		state
+	}
+
+	fn new_with_context(mut context: Context) -> Self {
+		let stack_renderer = StackRenderer::new(context.stack_mut());
+
+		let input_mode = InputMode {
+			alpha: AlphaMode::Normal,
+			shift: false,
+			on_held: false,
+		};
+
+		let cached_status_bar_state = CachedStatusBarState {
+			alpha: input_mode.alpha,
+			shift: input_mode.shift,
+			integer_radix: context.format().integer_radix,
+			integer_mode: context.format().integer_mode,
+			angle_mode: *context.angle_mode(),
+			multiple_pages: false,
+			recording: false,
+			exam: false,
+			busy: false,
+			carry: false,
+			out_of_range: false,
+			custom_bank: None,
+			left_string: State::time_string(context.format().time_24_hour),
+			next_left_string: String::new(),
+		};
+
+		State {
+			context,
+			input_mode,
+			function_keys: FunctionKeyState::new(),
+			settings: Settings::new(),
+			input_state: InputState::Normal,
+			location_entry: LocationEntryState::new(""),
+			toast: None,
+			menus: Vec::new(),
+			editor: None,
+			stack_renderer,
+			prev_bottom_layouts: Vec::new(),
+			bottom_layouts: RefCell::new(Vec::new()),
+			cached_status_bar_state,
+			force_refresh: true,
+			force_render_on_status_update: false,
+			animation: None,
+			animation_supported: false,
+			last_frame_time: None,
+			program_recording: None,
+			recording_step: false,
+			recorded_program: None,
+			program_slots: (0..PROGRAM_SLOTS).map(ProgramSlot::new).collect(),
+			active_program_slot: 0,
+			macro_recording: None,
+			macro_depth: 0,
+			steps_depth: 0,
+			scripts: Vec::new(),
+			text_entry: String::new(),
+			text_entry_target: TextEntryTarget::Script,
+			program_locked: false,
+			program_pause: None,
+			flags: Flags::new(),
+			skip_step: false,
+			program_goto: None,
+			graph_range: ((-10).into(), 10.into()),
+			graph: None,
+			function_table: None,
+			session_stats: SessionStats::new(),
+			tape: None,
+			answers: AnswerHistory::new(),
+			generation: 0,
+			tape_scroll: 0,
+			calendar: None,
+			self_test: None,
+			matrix_editor: None,
+			solver: None,
+			busy: false,
+			split: SplitLayout::new(),
+			pane_cache: None,
+			alarms: AlarmList::new(),
+			ringing_alarm: None,
+			low_battery_warning: None,
+			dialog: None,
+			currencies: CurrencyRates::new(),
+			currency_from: None,
+			exam_stash: None,
+			display_dimmed: false,
+			entry_rejection: None,
+		}
+	}
+
+	pub fn context(&self) -> &Context {
+		&self.context
+	}
+
+	pub fn context_mut(&mut self) -> &mut Context {
+		&mut self.context
+	}
+
+	pub fn function_keys(&self) -> &FunctionKeyState {
+		&self.function_keys
+	}
+
+	pub fn function_keys_mut(&mut self) -> &mut FunctionKeyState {
+		&mut self.function_keys
+	}
+
+	pub fn status_bar_left_display(&self) -> &StatusBarLeftDisplayType {
+		&self.settings.status_bar_left_display
+	}
+
+	pub fn set_status_bar_left_display(&mut self, display_type: StatusBarLeftDisplayType) {
+		self.settings.status_bar_left_display = display_type;
+	}
+
+	pub fn custom_function(&self, slot: CustomSlot) -> Option<Function> {
+		self.function_keys.custom_function(slot)
+	}
+
+	pub fn set_custom_function(&mut self, slot: CustomSlot, func: Option<Function>) -> Result<()> {
+		if self.function_keys.is_slot_locked(slot) {
+			return Err(Error::Locked);
+		}
+		self.function_keys.set_custom_function(slot, func);
+		Ok(())
+	}
+
+	/// Assigns the value in x to a custom slot, so that pressing the key pushes
+	/// it. Only numbers, with or without units, can be kept.
+	pub fn assign_custom_value(&mut self, slot: CustomSlot) -> Result<()> {
+		if self.function_keys.is_slot_locked(slot) {
+			return Err(Error::Locked);
+		}
+		self.end_edit()?;
+		let value = self.context.top()?;
+		match value {
+			Value::Number(_) | Value::NumberWithUnit(_, _) | Value::Complex(_) => (),
+			_ => return Err(Error::DataTypeMismatch),
+		}
+		let idx = self.function_keys.store_value(slot, value);
+		self.set_custom_function(slot, Some(Function::PushValue(idx)))
+	}
+
+	pub fn select_custom_bank(&mut self, bank: usize) {
+		self.function_keys.select_custom_bank(bank);
+		self.force_refresh = true;
+	}
+
+	pub fn next_custom_bank(&mut self) {
+		self.function_keys.next_custom_bank();
+		self.force_refresh = true;
+	}
+
+	pub fn new_custom_bank(&mut self) -> Result<()> {
+		self.function_keys.new_custom_bank();
+		self.refresh_custom_banks_menu()
+	}
+
+	pub fn delete_custom_bank(&mut self, bank: usize) -> Result<()> {
+		self.function_keys.delete_custom_bank(bank)?;
+		self.refresh_custom_banks_menu()
+	}
+
+	/// Starts entering a new name for the active bank of custom functions
+	pub fn start_rename_custom_bank(&mut self) -> Result<()> {
+		let bank = self.function_keys.active_custom_bank();
+		let name = self.function_keys.custom_bank_name(bank).to_string();
+		self.begin_alpha_entry(TextEntryTarget::RenameCustomBank, &name, AlphaMode::Normal)
+	}
+
+	/// Replaces the bank menu at the top of the menu stack so that it shows the
+	/// banks as they are now
+	fn refresh_custom_banks_menu(&mut self) -> Result<()> {
+		let selection = match self.menus.pop() {
+			Some(menu) => menu.selection(),
+			None => 0,
+		};
+		let mut menu = custom_banks_menu(self);
+		menu.set_selection(selection.saturating_sub(1));
+		menu.set_selection(selection);
+		self.force_refresh = true;
+		self.show_menu(menu)
+	}
+
+	/// Entry point for user programs to display their own soft key menu
+	pub fn show_program_menu(&mut self, keys: Vec<Option<ProgramMenuKey>>) {
+		self.function_keys.show_program_menu(keys);
+		self.force_refresh = true;
+	}
+
+	/// Shows a soft key for each label in the recorded program, so that a
+	/// program can offer its parts as a menu
+	pub fn show_program_labels_menu(&mut self) -> Result<()> {
+		let program = self.recorded_program.as_ref().ok_or(Error::NoProgram)?;
+		let keys = ProgramStep::labels(program)
+			.into_iter()
+			.map(|target| {
+				Some(ProgramMenuKey {
+					label: target.to_string(),
+					target,
+				})
+			})
+			.collect();
+		self.show_program_menu(keys);
+		Ok(())
+	}
+
+	pub fn status_bar_enabled(&self) -> bool {
+		self.settings.status_bar_enabled
+	}
+
+	pub fn set_status_bar_enabled(&mut self, value: bool) {
+		self.settings.status_bar_enabled = value;
+		self.force_refresh = true;
+	}
+
+	pub fn base_font(&self) -> Font {
+		self.settings.base_font
+	}
+
+	pub fn set_base_font(&mut self, font: Font) {
+		self.settings.base_font = font;
+		self.force_refresh = true;
+		self.stack_renderer.borrow_mut().invalidate_rendering();
+	}
+
+	pub fn stack_render_cache_enabled(&self) -> bool {
+		self.settings.stack_render_cache
+	}
+
+	pub fn set_stack_render_cache_enabled(&mut self, value: bool) {
+		self.settings.stack_render_cache = value;
+		self.stack_renderer.borrow_mut().set_cache_enabled(value);
+		self.last_frame_time = None;
+		self.force_refresh = true;
+	}
+
+	pub fn show_empty_soft_keys(&self) -> bool {
+		self.settings.show_empty_soft_keys
+	}
+
+	pub fn set_show_empty_soft_keys(&mut self, value: bool) {
+		self.settings.show_empty_soft_keys = value;
+		self.function_keys.set_show_empty(value);
+	}
+
+	pub fn theme(&self) -> Theme {
+		self.settings.theme
+	}
+
+	pub fn set_theme(&mut self, theme: Theme) {
+		self.settings.theme = theme;
+		set_theme(theme);
+		self.force_refresh = true;
+	}
+
+	pub fn enter_mode(&self) -> EnterMode {
+		self.settings.enter_mode
+	}
+
+	pub fn set_enter_mode(&mut self, mode: EnterMode) {
+		self.settings.enter_mode = mode;
+	}
+
+	pub fn startup_mode(&self) -> StartupMode {
+		self.settings.startup_mode
+	}
+
+	pub fn set_startup_mode(&mut self, mode: StartupMode) {
+		self.settings.startup_mode = mode;
+	}
+
+	pub fn custom_slots(&self) -> usize {
+		self.settings.custom_slots as usize
+	}
+
+	pub fn set_custom_slots(&mut self, count: u8) {
+		self.settings.custom_slots = count;
+	}
+
+	/// Does what the startup setting asks for when the calculator is turned on
+	pub fn start_up(&mut self, screen: &dyn Screen) -> Result<()> {
+		match self.settings.startup_mode {
+			StartupMode::Restore => Ok(()),
+			StartupMode::ClearStack => {
+				self.editor = None;
+				self.menus.clear();
+				self.input_state = InputState::Normal;
+				self.context.clear_stack();
+				self.force_refresh = true;
+				Ok(())
+			}
+			// Programs are put away in exam mode
+			StartupMode::RunProgram if self.exam_mode() => Ok(()),
+			StartupMode::RunProgram => {
+				let program = self.recorded_program.clone().ok_or(Error::NoProgram)?;
+				self.run_steps(&program, screen)?;
+				self.end_edit()
+			}
+		}
+	}
+
+	/// Applies all of the settings to the parts of the calculator that keep their
+	/// own copy, such as the renderers and global options
+	fn apply_settings(&mut self) {
+		let settings = self.settings.clone();
+		self.set_status_bar_enabled(settings.status_bar_enabled);
+		self.set_base_font(settings.base_font);
+		self.set_stack_render_cache_enabled(settings.stack_render_cache);
+		self.set_show_empty_soft_keys(settings.show_empty_soft_keys);
+		self.set_theme(settings.theme);
+		self.apply_display_levels();
+		self.set_language(settings.language);
+	}
+
+	pub fn language(&self) -> Language {
+		self.settings.language
+	}
+
+	pub fn set_language(&mut self, language: Language) {
+		self.settings.language = language;
+		set_language(language);
+		self.force_refresh = true;
+	}
+
+	pub fn display_level(&self, control: DisplayControl) -> u8 {
+		match control {
+			DisplayControl::Brightness => self.settings.brightness,
+			DisplayControl::Contrast => self.settings.contrast,
+		}
+	}
+
+	pub fn set_display_level(&mut self, control: DisplayControl, level: u8) {
+		let level = core::cmp::min(level, DISPLAY_LEVEL_MAX);
+		match control {
+			DisplayControl::Brightness => self.settings.brightness = level,
+			DisplayControl::Contrast => self.settings.contrast = level,
+		}
+		self.display_dimmed = false;
+		self.apply_display_levels();
+	}
+
+	pub fn auto_dim_seconds(&self) -> u16 {
+		self.settings.auto_dim_seconds
+	}
+
+	pub fn next_auto_dim(&mut self) {
+		let index = AUTO_DIM_SECONDS
+			.iter()
+			.position(|seconds| *seconds == self.settings.auto_dim_seconds)
+			.unwrap_or(0);
+		self.settings.auto_dim_seconds = AUTO_DIM_SECONDS[(index + 1) % AUTO_DIM_SECONDS.len()];
+	}
+
+	pub fn auto_save_minutes(&self) -> u8 {
+		self.settings.auto_save_minutes
+	}
+
+	pub fn next_auto_save(&mut self) {
+		let index = AUTO_SAVE_MINUTES
+			.iter()
+			.position(|minutes| *minutes == self.settings.auto_save_minutes)
+			.unwrap_or(0);
+		self.settings.auto_save_minutes = AUTO_SAVE_MINUTES[(index + 1) % AUTO_SAVE_MINUTES.len()];
+	}
+
+	fn apply_display_levels(&self) {
+		let brightness = if self.display_dimmed {
+			dimmed_brightness(self.settings.brightness)
+		} else {
+			self.settings.brightness
+		};
+		platform().set_display_levels(brightness, self.settings.contrast);
+	}
+
+	/// Dims the display until the next key press
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	pub fn dim_display(&mut self) {
+		if !self.display_dimmed {
+			self.display_dimmed = true;
+			self.apply_display_levels();
+		}
+	}
+
+	/// Brings the display back to its set brightness, if it was dimmed
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	pub fn wake_display(&mut self) {
+		if self.display_dimmed {
+			self.display_dimmed = false;
+			self.apply_display_levels();
+		}
+	}
+
+	pub fn reduced_motion(&self) -> bool {
+		self.settings.reduced_motion
+	}
+
+	pub fn set_reduced_motion(&mut self, value: bool) {
+		self.settings.reduced_motion = value;
+		if value {
+			self.finish_animation();
+		}
+	}
+
+	pub fn set_animation_supported(&mut self, value: bool) {
+		self.animation_supported = value;
+	}
+
+	fn start_animation(&mut self, animation_type: AnimationType) {
+		if self.animation_supported && !self.settings.reduced_motion {
+			self.animation = Some(Animation::new(animation_type));
+		}
+	}
+
+	pub fn animating(&self) -> bool {
+		self.animation.is_some()
+	}
+
+	/// Renders the next frame of the active animation
+	pub fn tick(&mut self, screen: &mut dyn Screen) {
+		if let Some(animation) = &mut self.animation {
+			if !animation.tick() {
+				self.finish_animation();
+			}
+		}
+		self.render(screen);
+	}
+
+	/// Stops the active animation, leaving the screen to render in its final state
+	pub fn finish_animation(&mut self) {
+		if self.animation.take().is_some() {
+			if let Some(menu) = self.menus.last() {
+				menu.force_refresh();
+			}
+			self.force_refresh = true;
+		}
+	}
+
+	pub fn show_error(&mut self, error: Error) {
+		self.input_state = InputState::Normal;
+		self.input_mode.alpha = AlphaMode::Normal;
+		self.show_toast(Toast::error(error));
+		self.feedback(FeedbackEvent::Error);
+	}
+
+	pub fn feedback_enabled(&self, event: FeedbackEvent) -> bool {
+		self.settings.feedback_enabled(event)
+	}
+
+	pub fn set_feedback_enabled(&mut self, event: FeedbackEvent, value: bool) {
+		self.settings.set_feedback_enabled(event, value);
+	}
+
+	/// Gives feedback for an event if it is turned on for it
+	pub fn feedback(&self, event: FeedbackEvent) {
+		if self.feedback_enabled(event) {
+			platform().feedback(event);
+		}
+	}
+
+	/// Plays the tone numbered by x, leaving the stack as it is
+	pub fn play_tone(&self) -> Result<()> {
+		let tone =
+			usize::try_from(&*self.context.top()?.to_int()?).map_err(|_| Error::ValueOutOfRange)?;
+		play_program_tone(tone)
+	}
+
+	/// Shows a message over the bottom of the stack until it is dismissed
+	pub fn show_toast(&mut self, toast: Toast) {
+		self.toast = Some(toast);
+		self.start_animation(AnimationType::FadeIn);
+	}
+
+	pub fn toast(&self) -> Option<&Toast> {
+		self.toast.as_ref()
+	}
+
+	pub fn hide_toast(&mut self) {
+		self.toast = None;
+		self.finish_animation();
+		// Menus only draw what changed, so the part under the message must be
+		// drawn again
+		if let Some(menu) = self.menus.last() {
+			menu.force_refresh();
+		}
+	}
+
+	fn time_string(time_24_hour: bool) -> String {
+		match NaiveDateTime::now() {
+			Ok(now) => now.simple_format(&SimpleDateTimeFormat::status_bar(time_24_hour)),
+			Err(_) => "Unknown time".to_string(),
+		}
+	}
+
+	pub fn program_recording(&self) -> bool {
+		self.program_recording.is_some()
+	}
+
+	/// Starts recording keystrokes into a program, or stops the current recording
+	/// and keeps it as the program used for graphing. A locked program cannot be
+	/// recorded over.
+	pub fn toggle_program_recording(&mut self) -> Result<()> {
+		if let Some(program) = self.program_recording.take() {
+			self.recorded_program = Some(program);
+		} else if self.program_locked && self.recorded_program.is_some() {
+			return Err(Error::Locked);
+		} else {
+			self.check_exam_mode()?;
+			self.program_recording = Some(Vec::new());
+		}
+		Ok(())
+	}
+
+	pub fn macro_recording(&self) -> bool {
+		self.macro_recording.is_some()
+	}
+
+	/// Starts recording a macro that will be assigned to `slot`. Recording ends
+	/// when the program key is pressed or after `MAX_MACRO_STEPS` steps.
+	pub fn start_macro_recording(&mut self, slot: CustomSlot) -> Result<()> {
+		if self.function_keys.is_slot_locked(slot) {
+			return Err(Error::Locked);
+		}
+		self.check_exam_mode()?;
+		self.end_edit()?;
+		self.macro_recording = Some((slot, Vec::new()));
+		Ok(())
+	}
+
+	/// Assigns the macro being recorded to its custom slot
+	fn finish_macro_recording(&mut self) -> Result<()> {
+		if let Some((slot, steps)) = self.macro_recording.take() {
+			let idx = self.function_keys.store_macro(slot, steps);
+			self.set_custom_function(slot, Some(Function::Macro(idx)))?;
+		}
+		Ok(())
+	}
+
+	/// Replays a recorded macro on the current stack
+	pub fn run_macro(&mut self, idx: usize, screen: &dyn Screen) -> Result<()> {
+		let steps = match self.function_keys.macro_steps(idx) {
+			Some(steps) => steps.to_vec(),
+			None => return Err(Error::ValueNotDefined),
+		};
+
+		// Macros can run other macros, but a macro that runs itself would never
+		// finish
+		if self.macro_depth >= MAX_MACRO_DEPTH {
+			return Err(Error::StackOverflow);
+		}
+		self.macro_depth += 1;
+		let result = self.run_steps(&steps, screen);
+		self.macro_depth -= 1;
+		result
+	}
+
+	fn run_steps(&mut self, steps: &[ProgramStep], screen: &dyn Screen) -> Result<()> {
+		self.run_steps_from(steps, 0, screen)
+	}
+
+	fn run_steps_from(
+		&mut self,
+		steps: &[ProgramStep],
+		start: usize,
+		screen: &dyn Screen,
+	) -> Result<()> {
+		self.steps_depth += 1;
+		let result = self.run_each_step(steps, start, screen);
+		self.steps_depth -= 1;
+		result
+	}
+
+	fn run_each_step(
+		&mut self,
+		steps: &[ProgramStep],
+		start: usize,
+		screen: &dyn Screen,
+	) -> Result<()> {
+		let mut idx = start;
+		while let Some(step) = steps.get(idx) {
+			if platform().computation_yield() {
+				return Err(Error::Interrupted);
+			}
+			if self.skip_step {
+				self.skip_step = false;
+				idx += ProgramStep::instruction_len(&steps[idx..]);
+				continue;
+			}
+			idx += 1;
+			match step {
+				ProgramStep::Input(input) => {
+					self.handle_input(*input, screen)?;
+				}
+				ProgramStep::Function(func) => func.execute(self, screen)?,
+			}
+			// Messages from the steps of a program would only be seen after it
+			// finishes, long after the step that showed them
+			self.toast = None;
+
+			// Jumps are to labels in the same list of steps, so that each macro
+			// has its own labels
+			if let Some(label) = self.program_goto.take() {
+				idx = ProgramStep::find_label(steps, &label).ok_or(Error::LabelNotFound)?;
+			}
+
+			// A step that waits for the user stops the program here, and it
+			// continues with the rest of the steps once the user is done
+			if let Some(pause) = &mut self.program_pause {
+				pause.remaining.push((steps.to_vec(), idx));
+				return Ok(());
+			}
+		}
+		Ok(())
+	}
+
+	/// Starts choosing the name of a label. Labels are named like variables and
+	/// numbered registers, and do nothing when they are run.
+	pub fn start_label_entry(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::Label;
+		self.location_entry = LocationEntryState::new("Lbl");
+		Ok(())
+	}
+
+	/// Starts choosing the label to go to. In a running program the steps after
+	/// the label are run next, and from the keyboard the recorded program is
+	/// run from the label.
+	pub fn start_goto_entry(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::Goto;
+		self.location_entry = LocationEntryState::new("Gto");
+		Ok(())
+	}
+
+	fn handle_label_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		let goto = self.input_state == InputState::Goto;
+		// The name of a label is found in the steps of the program, so it can't
+		// be looked up from a register
+		if !goto && input == InputEvent::Rcl {
+			return Err(Error::InvalidEntry);
+		}
+		let label = match self.handle_location_entry(input)? {
+			LocationInputResult::Intermediate(result) => return Ok(result),
+			LocationInputResult::Finished(Location::StackOffset(_))
+			| LocationInputResult::Invalid => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				return Err(Error::InvalidEntry);
+			}
+			LocationInputResult::Finished(label) => label,
+			LocationInputResult::Exit => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				return Ok(InputResult::Normal);
+			}
+		};
+		self.input_state = InputState::Normal;
+		self.input_mode.alpha = AlphaMode::Normal;
+		// While recording, the jump is only kept in the recorded steps
+		if !goto || self.program_recording() || self.macro_recording() {
+			return Ok(InputResult::Normal);
+		}
+		self.goto_label(label, screen)?;
+		Ok(InputResult::Normal)
+	}
+
+	/// Continues at a label. In a running program the steps after the label
+	/// are run next, otherwise the recorded program is run from the label.
+	pub fn goto_label(&mut self, label: Location, screen: &dyn Screen) -> Result<()> {
+		if self.steps_depth > 0 {
+			self.program_goto = Some(label);
+		} else {
+			self.check_exam_mode()?;
+			let program = self.recorded_program.clone().ok_or(Error::NoProgram)?;
+			let start = ProgramStep::find_label(&program, &label).ok_or(Error::LabelNotFound)?;
+			self.run_steps_from(&program, start, screen)?;
+		}
+		Ok(())
+	}
+
+	/// Starts choosing the flag for a flag operation
+	pub fn start_flag_entry(&mut self, operation: FlagOperation) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::FlagEntry(operation);
+		self.location_entry = LocationEntryState::new(operation.to_str());
+		Ok(())
+	}
+
+	/// Reads a flag. System flags show the modes of the calculator using the
+	/// numbers of the HP-42S.
+	pub fn flag(&self, flag: usize) -> Result<bool> {
+		if flag >= FLAG_COUNT {
+			return Err(Error::IndexOutOfRange);
+		}
+		if !is_system_flag(flag) {
+			return Ok(self.flags.get(flag));
+		}
+		let mode = self.context.format().mode;
+		Ok(match flag {
+			40 => mode == FormatMode::Fixed || mode == FormatMode::Normal,
+			41 => mode == FormatMode::Engineering || mode == FormatMode::Normal,
+			42 => *self.context.angle_mode() == AngleUnit::Gradians,
+			43 => *self.context.angle_mode() == AngleUnit::Radians,
+			52 => self.program_recording.is_some(),
+			53 => match &self.program_pause {
+				Some(pause) => match pause.prompt {
+					ProgramPrompt::Input(_, _) => true,
+					ProgramPrompt::View => false,
+				},
+				None => false,
+			},
+			_ => false,
+		})
+	}
+
+	pub fn set_flag(&mut self, flag: usize, value: bool) -> Result<()> {
+		self.flags.set(flag, value)
+	}
+
+	fn apply_flag_operation(&mut self, operation: FlagOperation, flag: usize) -> Result<()> {
+		let result = match operation {
+			FlagOperation::Set => return self.set_flag(flag, true),
+			FlagOperation::Clear => return self.set_flag(flag, false),
+			FlagOperation::TestSet => self.flag(flag)?,
+			FlagOperation::TestClear => !self.flag(flag)?,
+		};
+		// Programs skip the next step when the test fails, and from the keyboard
+		// the answer is shown
+		if self.steps_depth > 0 {
+			self.skip_step = !result;
+		} else {
+			self.show_toast(Toast::message(if result { "Yes" } else { "No" }));
+		}
+		Ok(())
+	}
+
+	fn handle_flag_input(
+		&mut self,
+		operation: FlagOperation,
+		input: InputEvent,
+	) -> Result<InputResult> {
+		let finished = match input {
+			InputEvent::Character(ch @ '0'..='9') => {
+				self.location_entry
+					.value
+					.push(ch as u32 as u8 - '0' as u32 as u8);
+				self.location_entry.value.len() >= FLAG_DIGITS
+			}
+			InputEvent::Enter if !self.location_entry.value.is_empty() => true,
+			InputEvent::Backspace if !self.location_entry.value.is_empty() => {
+				self.location_entry.value.pop();
+				false
+			}
+			InputEvent::Backspace | InputEvent::Exit => {
+				self.input_state = InputState::Normal;
+				return Ok(InputResult::Normal);
+			}
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			_ => {
+				self.input_state = InputState::Normal;
+				return Err(Error::InvalidEntry);
+			}
+		};
+		if finished {
+			self.input_state = InputState::Normal;
+			let flag = self.location_entry.int_value();
+			self.apply_flag_operation(operation, flag)?;
+		}
+		Ok(InputResult::Normal)
+	}
+
+	/// Starts choosing the variable that the program asks the user for
+	pub fn start_program_input(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::InputLocation;
+		self.location_entry = LocationEntryState::new("Input");
+		Ok(())
+	}
+
+	/// Stops the running program to show x until a key is pressed
+	pub fn view_x(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.context.top()?;
+		self.pause_program(ProgramPrompt::View);
+		Ok(())
+	}
+
+	fn pause_program(&mut self, prompt: ProgramPrompt) {
+		self.program_pause = Some(ProgramPause {
+			prompt,
+			remaining: Vec::new(),
+		});
+		self.input_state = InputState::ProgramPause;
+		self.force_refresh = true;
+	}
+
+	/// Runs the rest of a paused program. If it pauses again, the steps that
+	/// were left from before are kept to run after the new ones.
+	fn resume_program(&mut self, screen: &dyn Screen) -> Result<()> {
+		self.input_state = InputState::Normal;
+		self.force_refresh = true;
+		let remaining = match self.program_pause.take() {
+			Some(pause) => pause.remaining,
+			None => return Ok(()),
+		};
+		for (idx, (steps, start)) in remaining.iter().enumerate() {
+			self.run_steps_from(steps, *start, screen)?;
+			if let Some(pause) = &mut self.program_pause {
+				pause.remaining.extend(remaining[idx + 1..].iter().cloned());
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	fn handle_program_pause_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		let pause = match &mut self.program_pause {
+			Some(pause) => pause,
+			None => {
+				self.input_state = InputState::Normal;
+				return Ok(InputResult::Normal);
+			}
+		};
+		match input {
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			InputEvent::Exit => {
+				// Exit stops the program instead of continuing it
+				self.program_pause = None;
+				self.input_state = InputState::Normal;
+				self.force_refresh = true;
+				return Ok(InputResult::Normal);
+			}
+			_ => (),
+		}
+		let (location, editor) = match &mut pause.prompt {
+			ProgramPrompt::Input(location, editor) => (location, editor),
+			ProgramPrompt::View => {
+				self.resume_program(screen)?;
+				return Ok(InputResult::Normal);
+			}
+		};
+		let format = self.context.format();
+		match input {
+			InputEvent::Character(ch @ '0'..='9') | InputEvent::Character(ch @ '.') => editor
+				.get_or_insert_with(|| NumberEditor::new(format))
+				.push_char(ch)?,
+			InputEvent::E => editor
+				.get_or_insert_with(|| NumberEditor::new(format))
+				.exponent()?,
+			InputEvent::Neg => editor
+				.get_or_insert_with(|| NumberEditor::new(format))
+				.neg(),
+			InputEvent::Backspace => {
+				if let Some(current) = editor {
+					if !current.backspace() {
+						*editor = None;
+					}
+				}
+			}
+			InputEvent::Enter | InputEvent::Run => {
+				// Without a new number the variable keeps the value it has, which
+				// the program needs to have been given
+				match editor {
+					Some(editor) => {
+						let value = Value::Number(editor.number());
+						self.context.write(location.clone(), value)?;
+					}
+					None => {
+						self.context.read(location)?;
+					}
+				}
+				self.resume_program(screen)?;
+			}
+			_ => (),
+		}
+		self.force_refresh = true;
+		Ok(InputResult::Normal)
+	}
+
+	fn handle_input_location_input(&mut self, input: InputEvent) -> Result<InputResult> {
+		match self.handle_location_entry(input)? {
+			LocationInputResult::Intermediate(result) => Ok(result),
+			LocationInputResult::Finished(location) => {
+				self.input_mode.alpha = AlphaMode::Normal;
+				self.pause_program(ProgramPrompt::Input(location, None));
+				Ok(InputResult::Normal)
+			}
+			LocationInputResult::Exit => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				Ok(InputResult::Normal)
+			}
+			LocationInputResult::Invalid => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				Err(Error::InvalidEntry)
+			}
+		}
+	}
+
+	pub fn scripts(&self) -> &[Script] {
+		&self.scripts
+	}
+
+	/// Starts entering the text of a script. The soft keys insert the words of
+	/// the language that can't be typed on the keyboard.
+	pub fn start_script_entry(&mut self) -> Result<()> {
+		self.check_exam_mode()?;
+		self.begin_alpha_entry(TextEntryTarget::Script, "", AlphaMode::Normal)?;
+		self.function_keys.show_menu(FunctionMenu::Script);
+		Ok(())
+	}
+
+	/// Starts entering a new name for a file, beginning with its current name
+	pub fn start_rename_file(&mut self, name: &str) -> Result<()> {
+		self.begin_alpha_entry(
+			TextEntryTarget::RenameFile(name.to_string()),
+			name,
+			AlphaMode::LowerAlpha,
+		)
+	}
+
+	/// Starts entering text that is pushed onto the stack when Enter is pressed
+	pub fn start_text_entry(&mut self) -> Result<()> {
+		self.begin_alpha_entry(TextEntryTarget::Text, "", AlphaMode::UpperAlpha)
+	}
+
+	/// Starts entering the text to search the catalog for
+	pub fn start_catalog_search(&mut self) -> Result<()> {
+		self.begin_alpha_entry(TextEntryTarget::CatalogSearch, "", AlphaMode::UpperAlpha)
+	}
+
+	/// Starts entering text with the keys typing letters. Either case can be
+	/// chosen at any time with shift and the arrow keys, and shift and Enter
+	/// switches between letters and the usual functions of the keys.
+	fn begin_alpha_entry(
+		&mut self,
+		target: TextEntryTarget,
+		text: &str,
+		alpha: AlphaMode,
+	) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::TextEntry;
+		self.input_mode.alpha = alpha;
+		self.text_entry = text.to_string();
+		self.text_entry_target = target;
+		Ok(())
+	}
+
+	/// Adds a word to the script being entered, separated from the text around it
+	pub fn insert_script_word(&mut self, word: &str) {
+		if self.input_state != InputState::TextEntry
+			|| self.text_entry_target != TextEntryTarget::Script
+		{
+			return;
+		}
+		if !self.text_entry.is_empty() && !self.text_entry.ends_with(' ') {
+			self.text_entry.push(' ');
+		}
+		self.text_entry += word;
+		self.text_entry.push(' ');
+	}
+
+	fn end_text_entry(&mut self) {
+		self.input_state = InputState::Normal;
+		self.input_mode.alpha = AlphaMode::Normal;
+		if self.text_entry_target == TextEntryTarget::Script {
+			self.function_keys.exit_menu(self.context.format());
+		}
+	}
+
+	/// Uses the entered text for what it was entered for, such as keeping or
+	/// running a script or renaming a file. If the text can't be used, entry
+	/// continues so that it can be corrected.
+	fn finish_text_entry(&mut self) -> Result<()> {
+		match self.text_entry_target.clone() {
+			TextEntryTarget::Script => {
+				let script = Script::parse(&self.text_entry)?;
+				self.end_text_entry();
+				self.keep_or_run_script(script)
+			}
+			TextEntryTarget::RenameFile(name) => {
+				if self.text_entry != name {
+					open_file_store().rename(&name, &self.text_entry)?;
+				}
+				self.end_text_entry();
+				self.show_file_browser()
+			}
+			TextEntryTarget::Text => {
+				self.context
+					.push(Value::Text(self.text_entry.as_bytes().to_vec()))?;
+				self.end_text_entry();
+				Ok(())
+			}
+			TextEntryTarget::RenameCustomBank => {
+				if self.text_entry.is_empty() {
+					return Err(Error::InvalidEntry);
+				}
+				let name = self.text_entry.clone();
+				self.function_keys.set_custom_bank_name(name);
+				self.end_text_entry();
+				self.refresh_custom_banks_menu()
+			}
+			TextEntryTarget::RenameProgramSlot => {
+				if self.text_entry.is_empty() {
+					return Err(Error::InvalidEntry);
+				}
+				self.program_slots[self.active_program_slot].name = self.text_entry.clone();
+				self.end_text_entry();
+				self.refresh_program_slots_menu()
+			}
+			TextEntryTarget::CatalogSearch => match catalog_search_menu(self, &self.text_entry) {
+				Some(menu) => {
+					self.end_text_entry();
+					self.show_menu(menu)
+				}
+				None => {
+					self.show_toast(Toast::message("No matches"));
+					Ok(())
+				}
+			},
+		}
+	}
+
+	/// Program objects are kept so that they can be run later from the catalog,
+	/// and anything else is run right away. Defining a user function that already
+	/// exists replaces it, so that keys assigned to it run the new definition.
+	fn keep_or_run_script(&mut self, script: Script) -> Result<()> {
+		if script.is_program() {
+			let existing = match script.name() {
+				Some(name) => self
+					.scripts
+					.iter()
+					.position(|other| other.name() == Some(name)),
+				None => None,
+			};
+			match existing {
+				Some(idx) => self.scripts[idx] = script,
+				None => self.scripts.push(script),
+			}
+			Ok(())
+		} else {
+			script.execute(&mut self.context)
+		}
+	}
+
+	pub fn load_script(&mut self) -> Result<()> {
+		self.check_exam_mode()?;
+		self.end_edit()?;
+		let script = Script::parse(&read_script()?)?;
+		self.keep_or_run_script(script)
+	}
+
+	pub fn run_script(&mut self, idx: usize) -> Result<()> {
+		self.end_edit()?;
+		let script = match self.scripts.get(idx) {
+			Some(script) => script.clone(),
+			None => return Err(Error::ValueNotDefined),
+		};
+		script.execute(&mut self.context)
+	}
+
+	pub fn show_file_browser(&mut self) -> Result<()> {
+		self.check_exam_mode()?;
+		let names = open_file_store().list()?;
+		self.show_menu(file_browser_menu(names))
+	}
+
+	/// Opens a file according to its kind. Programs are parsed as scripts, and
+	/// saved state replaces the current state.
+	pub fn open_file(&mut self, name: &str) -> Result<()> {
+		self.check_exam_mode()?;
+		self.end_edit()?;
+		let data = open_file_store().read(name)?;
+		match FileKind::of(name) {
+			FileKind::State => self.restore_saved_state(&data),
+			FileKind::Program => {
+				let text = core::str::from_utf8(&data).map_err(|_| Error::CorruptData)?;
+				let script = Script::parse(text)?;
+				self.keep_or_run_script(script)
+			}
+			FileKind::Listing => {
+				let text = core::str::from_utf8(&data).map_err(|_| Error::CorruptData)?;
+				self.import_program(text)
+			}
+			_ => Err(Error::NotSupported),
+		}
+	}
+
+	pub fn delete_file(&mut self, name: &str) -> Result<()> {
+		open_file_store().delete(name)?;
+		self.refresh_file_browser()
+	}
+
+	/// Replaces the file browser at the top of the menu stack so that it shows
+	/// the files as they are now
+	fn refresh_file_browser(&mut self) -> Result<()> {
+		let names = open_file_store().list()?;
+		let selection = match self.menus.pop() {
+			Some(menu) => menu.selection(),
+			None => 0,
+		};
+		// Keep the selection in place, moving up if the last item was deleted
+		let mut menu = file_browser_menu(names);
+		menu.set_selection(selection.saturating_sub(1));
+		menu.set_selection(selection);
+		self.show_menu(menu)
+	}
+
+	pub fn save_state_file(&mut self) -> Result<()> {
+		self.end_edit()?;
+		let data = self.save_state()?;
+		open_file_store().write(STATE_FILE_NAME, &data)?;
+		self.show_toast(Toast::message("Saved"));
+		self.refresh_file_browser()
+	}
+
+	/// Writes each kept script to a program file, named after the user function it
+	/// defines if there is one
+	pub fn save_script_files(&mut self) -> Result<()> {
+		let mut store = open_file_store();
+		for (idx, script) in self.scripts.iter().enumerate() {
+			let name = match script.name() {
+				Some(name) => name.to_string() + ".rpl",
+				None => "script".to_string() + &(idx + 1).to_number().to_string() + ".rpl",
+			};
+			store.write(&name, script.source().as_bytes())?;
+		}
+		self.show_toast(Toast::message("Saved"));
+		self.refresh_file_browser()
+	}
+
+	pub fn split(&self) -> &SplitLayout {
+		&self.split
+	}
+
+	pub fn split_mut(&mut self) -> &mut SplitLayout {
+		self.force_refresh = true;
+		&mut self.split
+	}
+
+	pub fn graph(&self) -> Option<&Graph> {
+		self.graph.as_ref()
+	}
+
+	pub fn function_table(&self) -> Option<&FunctionTable> {
+		self.function_table.as_ref()
+	}
+
+	pub fn recorded_program(&self) -> Option<&[ProgramStep]> {
+		self.recorded_program.as_deref()
+	}
+
+	pub fn session_stats(&self) -> &SessionStats {
+		&self.session_stats
+	}
+
+	pub fn session_stats_mut(&mut self) -> &mut SessionStats {
+		&mut self.session_stats
+	}
+
+	pub fn alarms(&self) -> &[Alarm] {
+		self.alarms.alarms()
+	}
+
+	/// Sets an alarm for the date and time or the time of day in x. With
+	/// `with_message` the time is in y and the message text in x. A time of day
+	/// sets the alarm for the next time the clock reaches it.
+	pub fn set_alarm(&mut self, with_message: bool) -> Result<()> {
+		self.end_edit()?;
+		let now = NaiveDateTime::now()?;
+		let count = if with_message { 2 } else { 1 };
+		let time = match self.context.entry(count - 1)? {
+			Value::DateTime(time) if time > now => time,
+			Value::DateTime(_) => return Err(Error::InvalidTime),
+			Value::Time(time) => {
+				let today = now.date().and_time(time);
+				if today > now {
+					today
+				} else {
+					now.date()
+						.succ_opt()
+						.ok_or(Error::InvalidDate)?
+						.and_time(time)
+				}
+			}
+			_ => return Err(Error::DataTypeMismatch),
+		};
+		let message = if with_message {
+			match self.context.entry(0)? {
+				Value::Text(text) => {
+					Some(String::from_utf8(text).map_err(|_| Error::InvalidArgument)?)
+				}
+				_ => return Err(Error::DataTypeMismatch),
+			}
+		} else {
+			None
+		};
+
+		self.alarms.add(Alarm::new(time, message))?;
+		for _ in 0..count {
+			self.context.pop()?;
+		}
+		Ok(())
+	}
+
+	pub fn cancel_alarm(&mut self, idx: usize) -> Result<()> {
+		self.alarms.remove(idx)
+	}
+
+	/// Removes and returns the earliest alarm if it is due
+	pub fn take_due_alarm(&mut self) -> Option<Alarm> {
+		let now = NaiveDateTime::now().ok()?;
+		self.alarms.take_due(&now)
+	}
+
+	/// Shows an alarm that has gone off over the whole screen
+	pub fn show_alarm(&mut self, alarm: Alarm) {
+		self.ringing_alarm = Some(alarm);
+	}
+
+	pub fn hide_alarm(&mut self) {
+		self.ringing_alarm = None;
+		if let Some(menu) = self.menus.last() {
+			menu.force_refresh();
+		}
+		self.force_refresh = true;
+	}
+
+	/// Warns over the whole screen that the battery is about to run out
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	pub fn show_low_battery_warning(&mut self, state_saved: bool) {
+		self.low_battery_warning = Some(state_saved);
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	pub fn hide_low_battery_warning(&mut self) {
+		self.low_battery_warning = None;
+		if let Some(menu) = self.menus.last() {
+			menu.force_refresh();
+		}
+		self.force_refresh = true;
+	}
+
+	/// Asks before an action that destroys data is carried out
+	pub fn show_dialog(&mut self, dialog: ConfirmDialog) {
+		self.dialog = Some(dialog);
+		self.force_refresh = true;
+	}
+
+	fn hide_dialog(&mut self) {
+		self.dialog = None;
+		if let Some(menu) = self.menus.last() {
+			menu.force_refresh();
+		}
+		self.force_refresh = true;
+	}
+
+	/// Whether actions that destroy data should be confirmed before they are
+	/// carried out. Programs already asked when they were recorded, and steps
+	/// being recorded must run when the program does.
+	pub fn confirms_actions(&self) -> bool {
+		self.steps_depth == 0 && self.program_recording.is_none() && self.macro_recording.is_none()
+	}
+
+	fn answer_dialog(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
+		if input == InputEvent::Off {
+			return Ok(InputResult::Suspend);
+		}
+		match ConfirmDialog::answer(input) {
+			Some(DialogAnswer::Yes) => {
+				if let Some(dialog) = self.dialog.take() {
+					self.hide_dialog();
+					dialog.into_action().execute_confirmed(self, screen)?;
+				}
+			}
+			Some(DialogAnswer::No) => self.hide_dialog(),
+			None => (),
+		}
+		Ok(InputResult::Normal)
+	}
+
+	pub fn answers(&self) -> &AnswerHistory {
+		&self.answers
+	}
+
+	/// Count of the functions that have run, which changes whenever the state
+	/// may have. Menus keep the layouts of their items until it changes.
+	pub fn generation(&self) -> u32 {
+		self.generation
+	}
+
+	pub fn advance_generation(&mut self) {
+		self.generation = self.generation.wrapping_add(1);
+	}
+
+	/// Pushes a result from the answer history back onto the stack
+	pub fn recall_answer(&mut self, idx: usize) -> Result<()> {
+		self.end_edit()?;
+		let value = self.answers.get(idx).ok_or(Error::ValueNotDefined)?.clone();
+		self.context.push(value)
+	}
+
+	/// Shows the tape, starting at the most recent operations
+	pub fn show_tape(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.tape_scroll = 0;
+		self.input_state = InputState::Tape;
+		self.force_refresh = true;
+		self.function_keys.show_menu(FunctionMenu::Tape);
+		Ok(())
+	}
+
+	/// Shows the month of the date in x, or of today if x is not a date
+	pub fn show_calendar(&mut self) -> Result<()> {
+		self.end_edit()?;
+		let today = NaiveDateTime::now().ok().map(|now| now.date());
+		let selected = match self.context.top() {
+			Ok(Value::Date(date)) => date,
+			_ => today.ok_or(Error::InvalidDate)?,
+		};
+		self.calendar = Some(Calendar::new(selected, today));
+		self.input_state = InputState::Calendar;
+		self.force_refresh = true;
+		self.function_keys.show_menu(FunctionMenu::Calendar);
+		Ok(())
+	}
+
+	pub fn move_calendar_months(&mut self, months: i32) -> Result<()> {
+		let calendar = self.calendar.as_mut().ok_or(Error::NotSupported)?;
+		calendar.move_months(months);
+		self.force_refresh = true;
+		Ok(())
+	}
+
+	pub fn select_calendar_today(&mut self) -> Result<()> {
+		let calendar = self.calendar.as_mut().ok_or(Error::NotSupported)?;
+		calendar.select_today();
+		self.force_refresh = true;
+		Ok(())
+	}
+
+	/// Pushes the selected date of the calendar and leaves it
+	pub fn push_calendar_date(&mut self) -> Result<()> {
+		let date = self
+			.calendar
+			.as_ref()
+			.ok_or(Error::NotSupported)?
+			.selected();
+		self.context.push(Value::Date(date))?;
+		self.exit_calendar();
+		Ok(())
+	}
+
+	fn exit_calendar(&mut self) {
+		self.calendar = None;
+		self.input_state = InputState::Normal;
+		self.force_refresh = true;
+		self.function_keys.exit_menu(self.context.format());
+	}
+
+	/// Opens the grid editor on the matrix or vector in x, or on a new matrix if
+	/// x is neither
+	pub fn start_matrix_edit(&mut self) -> Result<()> {
+		self.end_edit()?;
+		let editor = match self.context.top() {
+			Ok(value @ Value::Matrix(_)) | Ok(value @ Value::Vector(_)) => {
+				MatrixEditor::new(&value)?
+			}
+			_ => MatrixEditor::new_matrix(),
+		};
+		self.matrix_editor = Some(editor);
+		self.input_state = InputState::MatrixEdit;
+		self.force_refresh = true;
+		self.function_keys.show_menu(FunctionMenu::MatrixEditor);
+		Ok(())
+	}
+
+	pub fn apply_matrix_edit(&mut self, action: MatrixEditAction) -> Result<()> {
+		let editor = self.matrix_editor.as_mut().ok_or(Error::NotSupported)?;
+		editor.apply(action)?;
+		self.force_refresh = true;
+		Ok(())
+	}
+
+	/// Opens a table of the points of the data set in x, so that single points
+	/// can be corrected, inserted or deleted. A vector of x values in y and a
+	/// vector of y values in x are joined into one data set of x and y. If there
+	/// is no data set, a new one is started.
+	pub fn start_data_edit(&mut self) -> Result<()> {
+		self.end_edit()?;
+		let editor = match (self.context.entry(0), self.context.entry(1)) {
+			(Ok(Value::Vector(y_values)), Ok(Value::Vector(x_values)))
+				if x_values.len() == y_values.len() =>
+			{
+				MatrixEditor::new_paired_data_set(&x_values, &y_values)?
+			}
+			(Ok(value @ Value::Matrix(_)), _) | (Ok(value @ Value::Vector(_)), _) => {
+				MatrixEditor::new_data_set(&value)?
+			}
+			_ => MatrixEditor::new_data_set_empty(),
+		};
+		self.matrix_editor = Some(editor);
+		self.input_state = InputState::MatrixEdit;
+		self.force_refresh = true;
+		self.function_keys.show_menu(FunctionMenu::DataEditor);
+		Ok(())
+	}
+
+	/// Puts the edited matrix on the stack and leaves the editor
+	fn finish_matrix_edit(&mut self) -> Result<()> {
+		let editor = self.matrix_editor.as_ref().ok_or(Error::NotSupported)?;
+		let value = editor.to_value()?;
+		match editor.replaces() {
+			0 => self.context.push(value)?,
+			count => self.context.replace_entries(count, value)?,
+		}
+		self.exit_matrix_edit();
+		Ok(())
+	}
+
+	fn exit_matrix_edit(&mut self) {
+		self.matrix_editor = None;
+		self.input_state = InputState::Normal;
+		self.force_refresh = true;
+		self.function_keys.exit_menu(self.context.format());
+	}
+
+	/// Starts a test of the hardware, which covers the whole screen until it is
+	/// left
+	pub fn start_self_test(&mut self, test: SelfTest) -> Result<()> {
+		self.end_edit()?;
+		self.self_test = Some(test);
+		self.input_state = InputState::SelfTest;
+		Ok(())
+	}
+
+	pub fn export_tape(&mut self) -> Result<()> {
+		let text = match &self.tape {
+			Some(tape) => tape.to_text(self.context.format()),
+			None => return Err(Error::NotSupported),
+		};
+		open_file_store().write(TAPE_FILE_NAME, text.as_bytes())?;
+		self.show_toast(Toast::message("Exported"));
+		Ok(())
+	}
+
+	/// Writes the recorded program as a listing to a file, which can be copied
+	/// off the calculator over USB
+	pub fn export_program(&mut self) -> Result<()> {
+		let text = self.program_listing()?;
+		open_file_store().write(PROGRAM_LISTING_FILE_NAME, text.as_bytes())?;
+		self.show_toast(Toast::message("Exported"));
+		Ok(())
+	}
+
+	pub fn copy_program(&mut self) -> Result<()> {
+		let text = self.program_listing()?;
+		platform().copy_to_clipboard(&text)?;
+		self.show_toast(Toast::message("Copied"));
+		Ok(())
+	}
+
+	pub fn paste_program(&mut self) -> Result<()> {
+		let text = platform().paste_from_clipboard()?;
+		self.import_program(&text)
+	}
+
+	fn program_listing(&mut self) -> Result<String> {
+		self.end_edit()?;
+		match &self.recorded_program {
+			Some(program) => program_listing(program),
+			None => Err(Error::NoProgram),
+		}
+	}
+
+	/// Replaces the recorded program with the one in a listing. A locked
+	/// program is kept, as it is when recording.
+	fn import_program(&mut self, text: &str) -> Result<()> {
+		self.check_exam_mode()?;
+		self.end_edit()?;
+		if self.program_recording.is_some() {
+			return Err(Error::NotSupported);
+		}
+		if self.program_locked && self.recorded_program.is_some() {
+			return Err(Error::Locked);
+		}
+		self.recorded_program = Some(parse_program_listing(text)?);
+		self.show_toast(Toast::message("Program loaded"));
+		Ok(())
+	}
+
+	/// Text of the value in x as it is shown, for copying to other programs. The
+	/// exponent is written with a plain letter so that the text can be read as a
+	/// number elsewhere.
+	pub fn x_text(&mut self) -> Result<String> {
+		self.end_edit()?;
+		let text = self
+			.context
+			.top()?
+			.format(self.context.format())
+			.to_string();
+		Ok(text.replace('ᴇ', "E"))
+	}
+
+	/// Enters a number given as text, such as text pasted from the clipboard, by
+	/// typing it into a new entry as if it was typed on the keyboard. Text that
+	/// is not a number is pushed as a date or time if it is one in ISO 8601
+	/// format, and otherwise nothing is entered.
+	pub fn paste_text(&mut self, text: &str, screen: &dyn Screen) -> Result<()> {
+		match self.input_state {
+			InputState::Normal | InputState::NumberInput => (),
+			_ => return Err(Error::NotSupported),
+		}
+		let inputs = match number_inputs(text, self.context.format()) {
+			Ok(inputs) => inputs,
+			Err(error) => {
+				// Text with date or time separators reports why it is not a valid
+				// date or time, rather than why it is not a number
+				let separated = text
+					.trim()
+					.get(1..)
+					.is_some_and(|rest| rest.contains(['-', ':']));
+				let value = match iso8601::parse(text) {
+					Ok(value) => value,
+					Err(date_error) if separated => return Err(date_error),
+					Err(_) => return Err(error),
+				};
+				self.end_edit()?;
+				return self.context.push(value);
+			}
+		};
+		self.end_edit()?;
+		for input in inputs {
+			self.handle_input(input, screen)?;
+		}
+		Ok(())
+	}
+
+	pub fn program_locked(&self) -> bool {
+		self.program_locked
+	}
+
+	pub fn set_program_locked(&mut self, locked: bool) {
+		self.program_locked = locked;
+	}
+
+	pub fn active_program_slot(&self) -> usize {
+		self.active_program_slot
+	}
+
+	pub fn program_slot_name(&self, slot: usize) -> &str {
+		&self.program_slots[slot].name
+	}
+
+	/// Steps of the program in a slot, which for the active slot is the recorded
+	/// program
+	pub fn program_slot_steps(&self, slot: usize) -> Option<&[ProgramStep]> {
+		if slot == self.active_program_slot {
+			self.recorded_program()
+		} else {
+			self.program_slots[slot].program.as_deref()
+		}
+	}
+
+	/// Makes the program in a slot the recorded program, keeping the current one
+	/// in its own slot. Programs can't be switched while one is being recorded
+	/// or is running.
+	pub fn select_program_slot(&mut self, slot: usize) -> Result<()> {
+		if slot >= PROGRAM_SLOTS {
+			return Err(Error::IndexOutOfRange);
+		}
+		if self.program_recording.is_some() || self.steps_depth > 0 {
+			return Err(Error::NotSupported);
+		}
+		if slot != self.active_program_slot {
+			let current = &mut self.program_slots[self.active_program_slot];
+			current.program = self.recorded_program.take();
+			current.locked = self.program_locked;
+			let selected = &mut self.program_slots[slot];
+			self.recorded_program = selected.program.take();
+			self.program_locked = selected.locked;
+			self.active_program_slot = slot;
+		}
+		self.refresh_program_slots_menu()
+	}
+
+	/// Removes the program in a slot. Locked programs are kept.
+	pub fn clear_program_slot(&mut self, slot: usize) -> Result<()> {
+		if slot >= PROGRAM_SLOTS {
+			return Err(Error::IndexOutOfRange);
+		}
+		if slot == self.active_program_slot {
+			if self.program_recording.is_some() || self.steps_depth > 0 {
+				return Err(Error::NotSupported);
+			}
+			if self.program_locked && self.recorded_program.is_some() {
+				return Err(Error::Locked);
+			}
+			self.recorded_program = None;
+		} else {
+			let other = &mut self.program_slots[slot];
+			if other.locked && other.program.is_some() {
+				return Err(Error::Locked);
+			}
+			other.program = None;
+		}
+		self.refresh_program_slots_menu()
+	}
+
+	/// Starts entering a new name for the active program slot
+	pub fn start_rename_program_slot(&mut self) -> Result<()> {
+		let name = self.program_slots[self.active_program_slot].name.clone();
+		self.begin_alpha_entry(TextEntryTarget::RenameProgramSlot, &name, AlphaMode::Normal)
+	}
+
+	/// Replaces the slot menu at the top of the menu stack so that it shows the
+	/// slots as they are now
+	fn refresh_program_slots_menu(&mut self) -> Result<()> {
+		let selection = match self.menus.pop() {
+			Some(menu) => menu.selection(),
+			None => 0,
+		};
+		let mut menu = program_slots_menu();
+		menu.set_selection(selection.saturating_sub(1));
+		menu.set_selection(selection);
+		self.force_refresh = true;
+		self.show_menu(menu)
+	}
+
+	/// Prompts for a memory location and toggles its lock
+	pub fn start_lock_entry(&mut self) -> Result<()> {
+		self.end_edit()?;
+		self.input_state = InputState::Lock;
+		self.location_entry = LocationEntryState::new("Lock");
+		Ok(())
+	}
+
+	/// Determines the program step recorded for an input. Function keys are
+	/// recorded as the function they currently map to.
+	fn program_step(&self, input: InputEvent) -> Option<ProgramStep> {
+		// What the user does for a program that is waiting is not part of it
+		if self.input_state == InputState::ProgramPause {
+			return None;
+		}
+		match input {
+			InputEvent::FunctionKey(idx, _) => match self.input_state {
+				InputState::Normal | InputState::NumberInput => {
+					self.function_keys.function(idx).map(ProgramStep::Function)
+				}
+				_ => Some(ProgramStep::Input(input)),
+			},
+			InputEvent::Off
+			| InputEvent::Screenshot
+			| InputEvent::System(_)
+			| InputEvent::Touch(_, _) => None,
+			_ => Some(ProgramStep::Input(input)),
+		}
+	}
+
+	/// Creates a separate state for running programs. It shares the modes and
+	/// memory of this state, but has its own stack and no undo history.
+	fn program_evaluator(&self) -> State {
+		let mut evaluator = State::new_with_context(Context::new());
+		evaluator.context.copy_settings_from(&self.context);
+		evaluator.scripts = self.scripts.clone();
+		evaluator.flags = self.flags.clone();
+		evaluator
+	}
+
+	/// Runs a program with `input` as the only value on the stack, returning the
+	/// value left on top of the stack.
+	pub fn run_program(
+		&mut self,
+		program: &[ProgramStep],
+		input: Value,
+		screen: &dyn Screen,
+	) -> Result<Value> {
+		self.editor = None;
+		self.menus.clear();
+		self.input_state = InputState::Normal;
+		self.context.clear_stack();
+		self.context.push(input)?;
+		self.run_steps(program, screen)?;
+		// There is no one to answer a program that is run for its result
+		if self.program_pause.take().is_some() {
+			self.input_state = InputState::Normal;
+			return Err(Error::NotSupported);
+		}
+		self.end_edit()?;
+		self.context.top()
+	}
+
+	/// Sets the range of x values used when graphing
+	pub fn set_graph_range(&mut self, min: Decimal, max: Decimal) -> Result<()> {
+		if min >= max || !min.is_finite() || !max.is_finite() {
+			return Err(Error::ValueOutOfRange);
+		}
+		self.graph_range = (min, max);
+		Ok(())
+	}
+
+	/// Writes the complete calculator state, which includes the stack, memory,
+	/// settings, custom assignments and the recorded program
+	pub fn save_state(&self) -> Result<Vec<u8>> {
+		let mut writer = StateWriter::new();
+		self.context.save_state(&mut writer)?;
+		self.function_keys.save_state(&mut writer)?;
+		writer.record(STATE_RECORD_SETTINGS, |output| self.settings.save(output))?;
+		writer.record(STATE_RECORD_PROGRAM, |output| {
+			match &self.recorded_program {
+				Some(program) => {
+					output.write_u8(1)?;
+					ProgramStep::save_steps(program, output)?;
+				}
+				None => output.write_u8(0)?,
+			}
+			output.write_u8(self.program_locked as u8)?;
+			for value in &[&self.graph_range.0, &self.graph_range.1] {
+				serialize_portable(&Value::Number(Number::Decimal((*value).clone())), output)?;
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_PROGRAM_SLOTS, |output| {
+			output.write_u8(self.active_program_slot as u8)?;
+			output.write_u32(self.program_slots.len() as u32)?;
+			for slot in &self.program_slots {
+				write_string(output, &slot.name)?;
+				match &slot.program {
+					Some(program) => {
+						output.write_u8(1)?;
+						ProgramStep::save_steps(program, output)?;
+					}
+					None => output.write_u8(0)?,
+				}
+				output.write_u8(slot.locked as u8)?;
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_SCRIPTS, |output| {
+			output.write_u32(self.scripts.len() as u32)?;
+			for script in &self.scripts {
+				write_string(output, script.source())?;
+			}
+			Ok(())
+		})?;
+		writer.record(STATE_RECORD_ALARMS, |output| self.alarms.save(output))?;
+		writer.record(STATE_RECORD_CURRENCIES, |output| {
+			self.currencies.save(output)
+		})?;
+		writer.record(STATE_RECORD_FLAGS, |output| self.flags.save(output))?;
+		if let Some(stash) = &self.exam_stash {
+			writer.record(STATE_RECORD_EXAM, |output| output.write(stash))?;
+		}
+		Ok(writer.finish())
+	}
+
+	/// Creates a state from saved state written by `save_state`. Records that are
+	/// not known to this version are skipped. Nothing is changed if the saved
+	/// state can't be read.
+	pub fn from_saved_state(data: &[u8]) -> Result<Self> {
+		let mut reader = StateReader::new(data)?;
+		let mut state = State::new();
+		while let Some((tag, mut input)) = reader.next_record()? {
+			if state.context.restore_state_record(tag, &mut input)?
+				|| state.function_keys.restore_state_record(tag, &mut input)?
+			{
+				continue;
+			}
+			match tag {
+				STATE_RECORD_SETTINGS => state.settings.restore(&mut input, &mut state.context)?,
+				STATE_RECORD_PROGRAM => state.restore_program(&mut input)?,
+				STATE_RECORD_PROGRAM_SLOTS => state.restore_program_slots(&mut input)?,
+				STATE_RECORD_SCRIPTS => state.restore_scripts(&mut input)?,
+				STATE_RECORD_ALARMS => state.alarms.restore(&mut input)?,
+				STATE_RECORD_CURRENCIES => state.currencies.restore(&mut input)?,
+				STATE_RECORD_FLAGS => state.flags.restore(&mut input)?,
+				STATE_RECORD_EXAM => {
+					state.exam_stash = Some(input.read_slice(input.remaining())?.to_vec())
+				}
+				_ => (),
+			}
+		}
+
+		// Settings that are global are only applied once everything has been read
+		// successfully
+		state.apply_settings();
+		Ok(state)
+	}
+
+	pub fn exam_mode(&self) -> bool {
+		self.exam_stash.is_some()
+	}
+
+	/// Fails when in exam mode, for operations that would give access to
+	/// programs or stored data
+	fn check_exam_mode(&self) -> Result<()> {
+		if self.exam_mode() {
+			Err(Error::ExamMode)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Puts away the stack, memory, programs and custom assignments and starts
+	/// over with none, keeping only the settings. Recording programs and opening
+	/// files are disabled until exam mode is left.
+	pub fn enter_exam_mode(&mut self) -> Result<()> {
+		self.check_exam_mode()?;
+		self.end_edit()?;
+		let stash = self.save_state()?;
+		let mut state = State::new();
+		state.settings = self.settings.clone();
+		state.context.copy_settings_from(&self.context);
+		state.animation_supported = self.animation_supported;
+		state.exam_stash = Some(stash);
+		state.apply_settings();
+		*self = state;
+		self.show_toast(Toast::message("Exam mode"));
+		Ok(())
+	}
+
+	/// Ends exam mode, discarding everything done during it and bringing back
+	/// the state from before it started
+	pub fn exit_exam_mode(&mut self) -> Result<()> {
+		let stash = self.exam_stash.take().ok_or(Error::InvalidArgument)?;
+		if let Err(error) = self.restore_saved_state(&stash) {
+			self.exam_stash = Some(stash);
+			return Err(error);
+		}
+		self.show_toast(Toast::message("Exam mode ended"));
+		Ok(())
+	}
+
+	/// Clears all user data and settings, and saves the cleared state so that
+	/// the old one can't come back
+	pub fn reset_to_defaults(&mut self) -> Result<()> {
+		self.check_exam_mode()?;
+		let mut state = State::new();
+		state.animation_supported = self.animation_supported;
+		state.apply_settings();
+		*self = state;
+		write_saved_state(&self.save_state()?)?;
+		self.show_toast(Toast::message("Reset to defaults"));
+		Ok(())
+	}
+
+	/// Replaces the current state with saved state
+	pub fn restore_saved_state(&mut self, data: &[u8]) -> Result<()> {
+		self.check_exam_mode()?;
+		let mut state = State::from_saved_state(data)?;
+		state.animation_supported = self.animation_supported;
+		state.tape = self.tape.take();
+		core::mem::swap(&mut state.answers, &mut self.answers);
+		*self = state;
+		Ok(())
+	}
 
-pub enum LocationInputResult {
-	Intermediate(InputResult),
-	Finished(Location),
-	Invalid,
-	Exit,
-}
+	/// Restores the recorded program and graph range
+	fn restore_program(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		self.recorded_program = if input.read_u8()? != 0 {
+			Some(ProgramStep::restore_steps(input)?)
+		} else {
+			None
+		};
+		self.program_locked = input.read_u8()? != 0;
 
-impl LocationEntryState {
-	fn new(name: &'static str) -> Self {
-		LocationEntryState {
-			name,
-			stack: false,
-			value: Vec::new(),
+		let min = deserialize_portable::<Value>(input)?;
+		let max = deserialize_portable::<Value>(input)?;
+		if let (Value::Number(min), Value::Number(max)) = (min, max) {
+			// Invalid ranges keep the default range
+			let _ =
+				self.set_graph_range(min.to_decimal().into_owned(), max.to_decimal().into_owned());
 		}
+		Ok(())
 	}
 
-	fn int_value(&self) -> usize {
-		let mut result = 0;
-		for digit in &self.value {
-			result *= 10;
-			result += *digit as usize;
+	/// Restores the programs kept in the other slots. The program of the active
+	/// slot is in the program record.
+	fn restore_program_slots(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		let active = input.read_u8()? as usize;
+		let count = input.read_u32()? as usize;
+		for idx in 0..count {
+			let name = read_string(input)?;
+			let program = if input.read_u8()? != 0 {
+				Some(ProgramStep::restore_steps(input)?)
+			} else {
+				None
+			};
+			let locked = input.read_u8()? != 0;
+			if let Some(slot) = self.program_slots.get_mut(idx) {
+				*slot = ProgramSlot {
+					name,
+					program,
+					locked,
+				};
+			}
 		}
-		result
+		if active < PROGRAM_SLOTS {
+			self.active_program_slot = active;
+		}
+		Ok(())
 	}
-}
 
-#[cfg(not(feature = "dm42"))]
-fn clock_minute_updated() -> bool {
-	true
-}
+	/// Restores the stored scripts. Scripts that this version can't parse are
+	/// left out.
+	fn restore_scripts(&mut self, input: &mut DeserializeInput) -> Result<()> {
+		let count = input.read_u32()?;
+		for _ in 0..count {
+			if let Ok(script) = Script::parse(&read_string(input)?) {
+				self.scripts.push(script);
+			}
+		}
+		Ok(())
+	}
 
-#[cfg(feature = "dm42")]
-fn clock_minute_updated() -> bool {
-	crate::dm42::device::rtc_updated()
-}
+	/// Graphs the recorded program over the current graph range
+	pub fn show_graph(&mut self, screen: &dyn Screen) -> Result<()> {
+		let program = match &self.recorded_program {
+			Some(program) => program.clone(),
+			None => return Err(Error::NoProgram),
+		};
+		self.end_edit()?;
 
-impl State {
-	pub fn new() -> Self {
-		let mut context = Context::new_with_undo();
-		let stack_renderer = StackRenderer::new(context.stack_mut());
+		let graph = Graph::new(
+			program,
+			self.graph_range.0.clone(),
+			self.graph_range.1.clone(),
+		);
+		self.open_graph(graph, screen);
+		Ok(())
+	}
 
-		let input_mode = InputMode {
-			alpha: AlphaMode::Normal,
-			shift: false,
+	/// Shows a table of the values of the recorded program over the current graph
+	/// range, with the step between values of x taken from x
+	pub fn show_function_table(&mut self, screen: &dyn Screen) -> Result<()> {
+		let program = match &self.recorded_program {
+			Some(program) => program.clone(),
+			None => return Err(Error::NoProgram),
 		};
+		self.end_edit()?;
+		let step = self.context.top()?.real_number()?.to_decimal().into_owned();
 
-		let cached_status_bar_state = CachedStatusBarState {
-			alpha: input_mode.alpha,
-			shift: input_mode.shift,
-			integer_radix: context.format().integer_radix,
-			integer_mode: context.format().integer_mode,
-			angle_mode: *context.angle_mode(),
-			multiple_pages: false,
-			left_string: State::time_string(context.format().time_24_hour),
+		let table = FunctionTable::new(
+			&program,
+			(&self.graph_range.0, &self.graph_range.1),
+			&step,
+			&mut self.program_evaluator(),
+			screen,
+		)?;
+		let menu = function_table_menu(&table);
+		self.function_table = Some(table);
+		self.show_menu(menu)
+	}
+
+	/// Pushes the value of the function in a row of the table
+	pub fn push_table_value(&mut self, idx: usize) -> Result<()> {
+		let value = match self
+			.function_table
+			.as_ref()
+			.and_then(|table| table.get(idx))
+		{
+			Some((_, Some(value))) => value.clone(),
+			_ => return Err(Error::ValueNotDefined),
 		};
+		self.context.push(Value::Number(Number::Decimal(value)))
+	}
 
-		State {
-			context,
-			input_mode,
-			function_keys: FunctionKeyState::new(),
-			status_bar_left_display: StatusBarLeftDisplayType::CurrentTime,
-			input_state: InputState::Normal,
-			location_entry: LocationEntryState::new(""),
-			error: None,
-			menus: Vec::new(),
-			editor: None,
-			status_bar_enabled: true,
-			base_font: Font::Large,
-			stack_renderer,
-			cached_status_bar_state,
-			force_refresh: true,
-			force_render_on_status_update: false,
+	/// Graphs a user function of one argument over the current graph range
+	/// Returns the user function at `idx` if it takes `arg_count` arguments
+	fn user_function(&self, idx: usize, arg_count: usize) -> Result<Script> {
+		let script = self.scripts.get(idx).ok_or(Error::ValueNotDefined)?;
+		match script.arguments() {
+			Some(args) if args.len() == arg_count => Ok(script.clone()),
+			Some(_) => Err(Error::InvalidEntry),
+			None => Err(Error::ValueNotDefined),
 		}
 	}
 
-	pub fn context(&self) -> &Context {
-		&self.context
+	/// Runs a user function of one argument on each element of the list in x
+	pub fn map_list(&mut self, idx: usize) -> Result<()> {
+		let script = self.user_function(idx, 1)?;
+		self.end_edit()?;
+		script.map(&mut self.context)
 	}
 
-	pub fn context_mut(&mut self) -> &mut Context {
-		&mut self.context
+	/// Combines the elements of the list in x with a user function of two
+	/// arguments
+	pub fn reduce_list(&mut self, idx: usize) -> Result<()> {
+		let script = self.user_function(idx, 2)?;
+		self.end_edit()?;
+		script.reduce(&mut self.context)
 	}
 
-	pub fn function_keys(&self) -> &FunctionKeyState {
-		&self.function_keys
+	/// Sums or multiplies the results of a user function of one argument for each
+	/// index between the limits in y and x
+	pub fn evaluate_series(&mut self, idx: usize, series: Series) -> Result<()> {
+		let script = self.user_function(idx, 1)?;
+		self.end_edit()?;
+		script.series(&mut self.context, series)
 	}
 
-	pub fn function_keys_mut(&mut self) -> &mut FunctionKeyState {
-		&mut self.function_keys
+	pub fn show_user_function_graph(&mut self, idx: usize, screen: &dyn Screen) -> Result<()> {
+		self.user_function(idx, 1)?;
+		self.end_edit()?;
+
+		let graph = Graph::new(
+			[ProgramStep::Function(Function::RunScript(idx))].to_vec(),
+			self.graph_range.0.clone(),
+			self.graph_range.1.clone(),
+		);
+		self.open_graph(graph, screen);
+		Ok(())
 	}
 
-	pub fn status_bar_left_display(&self) -> &StatusBarLeftDisplayType {
-		&self.status_bar_left_display
+	/// Plots a user function of one argument along with the last graph of a
+	/// program, so that the points where they cross can be found
+	pub fn overlay_user_function_graph(&mut self, idx: usize, screen: &dyn Screen) -> Result<()> {
+		self.user_function(idx, 1)?;
+		self.end_edit()?;
+
+		let mut graph = self.graph.take().ok_or(Error::ValueNotDefined)?;
+		let result = graph.set_overlay(
+			[ProgramStep::Function(Function::RunScript(idx))].to_vec(),
+			&mut self.program_evaluator(),
+			screen,
+		);
+		self.graph = Some(graph);
+		result?;
+		self.input_state = InputState::Graph;
+		Ok(())
 	}
 
-	pub fn set_status_bar_left_display(&mut self, display_type: StatusBarLeftDisplayType) {
-		self.status_bar_left_display = display_type;
+	/// Shows the soft keys of the solver for a user function
+	pub fn start_solver(&mut self, idx: usize) -> Result<()> {
+		let script = self.scripts.get(idx).ok_or(Error::ValueNotDefined)?.clone();
+		self.open_solver(script)
 	}
 
-	pub fn custom_function(&self, idx: usize) -> Option<Function> {
-		self.function_keys.custom_function(idx)
+	/// Shows the soft keys of the solver for an equation of the library
+	pub fn start_library_solver(&mut self, idx: usize) -> Result<()> {
+		let equation = EQUATION_LIBRARY.get(idx).ok_or(Error::ValueNotDefined)?;
+		self.open_solver(equation.script()?)
 	}
 
-	pub fn set_custom_function(&mut self, idx: usize, func: Option<Function>) {
-		self.function_keys.set_custom_function(idx, func);
+	/// Shows one soft key for each argument of an equation. The arguments are
+	/// kept in the variables of the same name, so they must be named by a single
+	/// character.
+	fn open_solver(&mut self, script: Script) -> Result<()> {
+		self.end_edit()?;
+		let args = script.arguments().ok_or(Error::ValueNotDefined)?;
+		for arg in args {
+			argument_location(arg)?;
+		}
+		self.function_keys
+			.show_toplevel_menu(FunctionMenu::Solver(args.len()));
+		self.solver = Some(script);
+		Ok(())
 	}
 
-	pub fn status_bar_enabled(&self) -> bool {
-		self.status_bar_enabled
+	/// Name of an argument of the equation on the soft keys of the solver
+	pub fn solver_argument(&self, arg: usize) -> Option<&str> {
+		let script = self.solver.as_ref()?;
+		Some(script.arguments()?.get(arg)?.as_str())
 	}
 
-	pub fn set_status_bar_enabled(&mut self, value: bool) {
-		self.status_bar_enabled = value;
-		self.force_refresh = true;
+	/// Stores the number being entered as the value of an argument of the
+	/// equation on the soft keys of the solver. When no number is being entered
+	/// the equation is solved for the argument instead, and the result is
+	/// stored and pushed onto the stack.
+	pub fn solver_variable(&mut self, arg: usize) -> Result<()> {
+		let script = self.solver.clone().ok_or(Error::ValueNotDefined)?;
+		let name = self
+			.solver_argument(arg)
+			.ok_or(Error::ValueNotDefined)?
+			.to_string();
+		let location = argument_location(&name)?;
+		let value = if self.input_state == InputState::NumberInput {
+			self.end_edit()?;
+			let value = self.context.top()?;
+			self.context.write(location, value.clone())?;
+			value
+		} else {
+			self.end_edit()?;
+			let result = solve_equation(&script, arg, &self.context)?;
+			let value = Value::Number(Number::Decimal(result));
+			self.context.write(location, value.clone())?;
+			self.context.push(value.clone())?;
+			value
+		};
+		let text = name + "=" + &value.format(self.context.format()).to_string();
+		self.show_toast(Toast::message(&text));
+		Ok(())
 	}
 
-	pub fn base_font(&self) -> Font {
-		self.base_font
+	/// Stores the number being entered in a register of the business menu. When
+	/// no number is being entered the variable is found from the others instead,
+	/// and the result is stored and pushed onto the stack.
+	pub fn business_variable(&mut self, variable: BusinessVariable) -> Result<()> {
+		let value = if self.input_state == InputState::NumberInput {
+			self.end_edit()?;
+			let value = self.context.top()?;
+			self.context
+				.business_mut()
+				.set(variable, value.real_number()?.clone())?;
+			value
+		} else {
+			self.end_edit()?;
+			let value = Value::Number(self.context.business_mut().solve(variable)?);
+			self.context.push(value.clone())?;
+			value
+		};
+		let text =
+			variable.to_str().to_string() + "=" + &value.format(self.context.format()).to_string();
+		self.show_toast(Toast::message(&text));
+		Ok(())
 	}
 
-	pub fn set_base_font(&mut self, font: Font) {
-		self.base_font = font;
-		self.force_refresh = true;
-		self.stack_renderer.borrow_mut().invalidate_rendering();
+	pub fn currencies(&self) -> &CurrencyRates {
+		&self.currencies
 	}
 
-	pub fn show_error(&mut self, error: Error) {
-		self.error = Some(error);
-		self.input_state = InputState::Normal;
-		self.input_mode.alpha = AlphaMode::Normal;
+	/// Handles a key of the currency menu. The first key picks the currency
+	/// that x is in, and the next one converts x to its currency.
+	pub fn currency_key(&mut self, currency: usize) -> Result<()> {
+		self.end_edit()?;
+		let text = match self.currency_from {
+			Some(from) if from != currency => {
+				let amount = self.context.top()?.real_number()?.clone();
+				let result = self.currencies.convert(&amount, from, currency)?;
+				self.context.set_top(Value::Number(result))?;
+				CURRENCY_CODES[from].to_string() + "→" + CURRENCY_CODES[currency]
+			}
+			_ => "From ".to_string() + CURRENCY_CODES[currency],
+		};
+		self.currency_from = Some(currency);
+		self.show_toast(Toast::message(&text));
+		Ok(())
 	}
 
-	pub fn hide_error(&mut self) {
-		self.error = None;
+	/// Sets the rate of a currency to x, given as units of the currency per unit
+	/// of the home currency
+	pub fn set_currency_rate(&mut self, currency: usize) -> Result<()> {
+		self.end_edit()?;
+		let rate = self.context.top()?.real_number()?.clone();
+		self.currencies.set_rate(currency, rate.clone())?;
+		let text = "1 ".to_string()
+			+ CURRENCY_CODES[self.currencies.home()]
+			+ " = " + &Value::Number(rate)
+			.format(self.context.format())
+			.to_string()
+			+ " " + CURRENCY_CODES[currency];
+		self.show_toast(Toast::message(&text));
+		Ok(())
 	}
 
-	fn time_string(time_24_hour: bool) -> String {
-		match NaiveDateTime::now() {
-			Ok(now) => now.simple_format(&SimpleDateTimeFormat::status_bar(time_24_hour)),
-			Err(_) => "Unknown time".to_string(),
+	pub fn next_home_currency(&mut self) -> Result<()> {
+		self.currencies.next_home()
+	}
+
+	fn open_graph(&mut self, mut graph: Graph, screen: &dyn Screen) {
+		graph.sample(&mut self.program_evaluator(), screen);
+		graph.move_trace(screen.width() / 2, false, screen);
+		self.graph = Some(graph);
+		self.input_state = InputState::Graph;
+	}
+
+	/// Collects data points for a scatter plot from the stack. Points are taken
+	/// from the rows of a two column matrix in X, or from vectors of x values in
+	/// Y and y values in X.
+	fn scatter_points(&self) -> Result<Vec<(Decimal, Decimal)>> {
+		let real = |value: Value| -> Result<Decimal> {
+			Ok(value.real_number()?.to_decimal().into_owned())
+		};
+
+		let mut points = Vec::new();
+		match self.context.top()? {
+			Value::Matrix(matrix) => {
+				if matrix.cols() != 2 {
+					return Err(Error::DimensionMismatch);
+				}
+				for row in 0..matrix.rows() {
+					points.push((real(matrix.get(row, 0)?)?, real(matrix.get(row, 1)?)?));
+				}
+			}
+			Value::Vector(y_values) => {
+				let x_values = match self.context.entry(1)? {
+					Value::Vector(x_values) => x_values,
+					_ => return Err(Error::DataTypeMismatch),
+				};
+				if x_values.len() != y_values.len() {
+					return Err(Error::DimensionMismatch);
+				}
+				for i in 0..x_values.len() {
+					points.push((real(x_values.get(i)?)?, real(y_values.get(i)?)?));
+				}
+			}
+			_ => return Err(Error::DataTypeMismatch),
 		}
+
+		if points.is_empty() {
+			return Err(Error::NotEnoughValues);
+		}
+		Ok(points)
+	}
+
+	/// Plots the data points on the stack along with their regression line
+	pub fn show_scatter_plot(&mut self, screen: &dyn Screen) -> Result<()> {
+		self.end_edit()?;
+		let graph = Graph::new_scatter(self.scatter_points()?);
+		self.open_graph(graph, screen);
+		Ok(())
+	}
+
+	/// Plots a histogram of the data set in x. If x is a bin count the data set
+	/// is in y, otherwise the bin count is chosen from the size of the data set.
+	pub fn show_histogram(&mut self, screen: &dyn Screen) -> Result<()> {
+		self.end_edit()?;
+		let (values, bins) = match self.context.top()? {
+			value @ Value::Vector(_) | value @ Value::List(_) => {
+				let values = Histogram::data_values(&value)?;
+				let bins = Histogram::default_bins(values.len());
+				(values, bins)
+			}
+			bins => {
+				let bins = u32::try_from(&*bins.to_int()?).map_err(|_| Error::InvalidArgument)?;
+				let values = Histogram::data_values(&self.context.entry(1)?)?;
+				(values, bins as usize)
+			}
+		};
+		let graph = Graph::new_histogram(values, bins)?;
+		self.open_graph(graph, screen);
+		Ok(())
+	}
+
+	/// Leaves the graph view. The graph is kept so that it can be shown in the
+	/// auxiliary pane.
+	fn hide_graph(&mut self) {
+		self.input_state = InputState::Normal;
+		self.cached_status_bar_state.left_string = String::new();
+		self.force_refresh = true;
 	}
 
 	pub fn undo(&mut self) -> Result<()> {
@@ -248,10 +2649,13 @@ impl State {
 
 	pub fn end_edit(&mut self) -> Result<()> {
 		if let Some(editor) = &self.editor {
-			let value = editor.number();
+			let value = Value::Number(editor.number());
 			self.editor = None;
 			self.input_state = InputState::Normal;
-			self.context.push(Value::Number(value))?;
+			match self.settings.enter_mode {
+				EnterMode::Terminate => self.context.push(value)?,
+				EnterMode::Duplicate => self.context.input_value(value)?,
+			}
 		}
 		self.input_mode.alpha = AlphaMode::Normal;
 		Ok(())
@@ -361,6 +2765,7 @@ impl State {
 				self.input_state = InputState::Store;
 				self.location_entry = LocationEntryState::new("Sto");
 			}
+			InputEvent::Gto => self.start_goto_entry()?,
 			InputEvent::Complex => {
 				self.end_edit()?;
 				self.context.complex()?;
@@ -375,8 +2780,15 @@ impl State {
 			}
 			InputEvent::Print => self.context.clear_undo_buffer(),
 			InputEvent::Clear => {
-				self.end_edit()?;
-				self.context.clear_stack();
+				if self.context.stack_len() > 0 && self.confirms_actions() {
+					self.show_dialog(ConfirmDialog::new(
+						"Clear Stack?",
+						"Every value on the stack is removed",
+						Function::ClearStack,
+					));
+				} else {
+					Function::ClearStack.execute(self, screen)?;
+				}
 			}
 			InputEvent::Run => {
 				self.end_edit()?;
@@ -404,14 +2816,21 @@ impl State {
 				self.show_menu(unit_menu())?;
 			}
 			InputEvent::Assign => {
-				self.show_menu(assign_menu())?;
+				let menu = assign_menu(self, None);
+				self.show_menu(menu)?;
 			}
 			InputEvent::Custom => {
 				self.function_keys.show_toplevel_menu(FunctionMenu::Custom);
 			}
+			InputEvent::ProgramFunc => {
+				self.function_keys.show_toplevel_menu(FunctionMenu::Graph);
+			}
 			InputEvent::Catalog => {
 				self.show_menu(catalog_menu(&|page| Function::CatalogPage(page)))?;
 			}
+			InputEvent::Solver => {
+				self.show_menu(solver_menu(self)?)?;
+			}
 			InputEvent::FunctionKey(func, _) => {
 				if let Some(func) = self.function_keys.function(func) {
 					func.execute(self, screen)?;
@@ -424,9 +2843,7 @@ impl State {
 				self.function_keys.next_page();
 			}
 			InputEvent::Setup => {
-				self.end_edit()?;
-				self.input_state = InputState::Menu;
-				self.menus.push(setup_menu());
+				self.show_menu(setup_menu())?;
 			}
 			InputEvent::Undo => {
 				self.end_edit()?;
@@ -450,11 +2867,22 @@ impl State {
 			InputEvent::Character(_) | InputEvent::E => {
 				self.editor = Some(NumberEditor::new(&self.context.format()));
 				self.input_state = InputState::NumberInput;
-				return self.handle_number_input(input, screen);
+				let result = self.handle_number_input(input, screen);
+				// A number that can't start with the key isn't entered at all, so that
+				// the next operation doesn't act on an empty entry
+				if let Some(error) = self.entry_rejection.take() {
+					self.editor = None;
+					self.input_state = InputState::Normal;
+					return Err(error);
+				}
+				return result;
 			}
 			InputEvent::Enter => {
 				self.end_edit()?;
-				self.context.push(self.context.top()?)?;
+				match self.settings.enter_mode {
+					EnterMode::Terminate => self.context.push(self.context.top()?)?,
+					EnterMode::Duplicate => self.context.enter()?,
+				}
 			}
 			InputEvent::Backspace => {
 				self.end_edit()?;
@@ -467,6 +2895,9 @@ impl State {
 			InputEvent::Exit => {
 				self.function_keys.exit_menu(self.context.format());
 			}
+			InputEvent::Show => {
+				self.split_mut().cycle_pane();
+			}
 			_ => return self.handle_common_input(input, screen),
 		}
 		Ok(InputResult::Normal)
@@ -477,6 +2908,7 @@ impl State {
 		input: InputEvent,
 		screen: &dyn Screen,
 	) -> Result<InputResult> {
+		self.entry_rejection = None;
 		let editor = match self.editor.as_mut() {
 			Some(editor) => editor,
 			None => {
@@ -488,20 +2920,36 @@ impl State {
 		match input {
 			InputEvent::Character(ch) => match ch {
 				'0'..='9' | 'A'..='Z' | 'a'..='z' | '.' => {
-					if ch != '.' || self.context.format().integer_mode == IntegerMode::Float {
-						editor.push_char(ch)?;
-					}
+					let float = self.context.format().integer_mode == IntegerMode::Float;
+					let result =
+						if ch.is_ascii_alphabetic() && float && editor.push_si_prefix(ch).is_ok() {
+							// Prefixes are typed in alpha mode, which ends once one has
+							// been entered so that the next operation can be pressed
+							self.input_mode.alpha = AlphaMode::Normal;
+							Ok(())
+						} else if ch != '.' || float {
+							editor.push_char(ch)
+						} else {
+							Err(Error::InvalidEntry)
+						};
+					self.reject_entry(result);
 				}
 				_ => (),
 			},
 			InputEvent::E => {
-				if self.context.format().integer_mode == IntegerMode::Float {
-					editor.exponent();
-				}
+				let result = if self.context.format().integer_mode == IntegerMode::Float {
+					editor.exponent()
+				} else {
+					Err(Error::InvalidEntry)
+				};
+				self.reject_entry(result);
 				self.input_mode.alpha = AlphaMode::Normal;
 			}
 			InputEvent::Enter => {
 				self.end_edit()?;
+				if self.settings.enter_mode == EnterMode::Duplicate {
+					self.context.enter()?;
+				}
 			}
 			InputEvent::Backspace => {
 				if !editor.backspace() {
@@ -521,8 +2969,17 @@ impl State {
 		Ok(InputResult::Normal)
 	}
 
+	/// Shows why a key wasn't accepted next to the number being entered, with the
+	/// error feedback, so that the number can still be finished
+	fn reject_entry(&mut self, result: Result<()>) {
+		if let Err(error) = result {
+			self.entry_rejection = Some(error);
+			self.feedback(FeedbackEvent::Error);
+		}
+	}
+
 	fn handle_recall_input(&mut self, input: InputEvent) -> Result<InputResult> {
-		match self.handle_location_input(input) {
+		match self.handle_location_entry(input)? {
 			LocationInputResult::Intermediate(result) => Ok(result),
 			LocationInputResult::Finished(location) => {
 				self.input_state = InputState::Normal;
@@ -542,32 +2999,235 @@ impl State {
 	}
 
 	fn handle_store_input(&mut self, input: InputEvent) -> Result<InputResult> {
-		match self.handle_location_input(input) {
+		// An arithmetic key before the location selects register arithmetic
+		if self.location_entry.value.is_empty()
+			&& !self.location_entry.stack
+			&& self.location_entry.operation.is_none()
+		{
+			if let Some(operation) = StoreOperation::from_input(input) {
+				self.location_entry.name = operation.name();
+				self.location_entry.operation = Some(operation);
+				return Ok(InputResult::Normal);
+			}
+		}
+
+		match self.handle_location_entry(input)? {
+			LocationInputResult::Intermediate(result) => Ok(result),
+			LocationInputResult::Finished(location) => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				let value = match self.location_entry.operation {
+					Some(operation) => {
+						operation.apply(self.context.read(&location)?, self.context.top()?)?
+					}
+					None => self.context.top()?,
+				};
+				self.context.write(location, value)?;
+				Ok(InputResult::Normal)
+			}
+			LocationInputResult::Exit => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				Ok(InputResult::Normal)
+			}
+			LocationInputResult::Invalid => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				Err(Error::InvalidEntry)
+			}
+		}
+	}
+
+	fn handle_lock_input(&mut self, input: InputEvent) -> Result<InputResult> {
+		match self.handle_location_entry(input)? {
 			LocationInputResult::Intermediate(result) => Ok(result),
 			LocationInputResult::Finished(location) => {
 				self.input_state = InputState::Normal;
 				self.input_mode.alpha = AlphaMode::Normal;
-				self.context.write(location, self.context.top()?)?;
+				let locked = !self.context.is_locked(&location);
+				self.context.set_locked(location, locked)?;
+				Ok(InputResult::Normal)
+			}
+			LocationInputResult::Exit => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
 				Ok(InputResult::Normal)
 			}
-			LocationInputResult::Exit => {
-				self.input_state = InputState::Normal;
-				self.input_mode.alpha = AlphaMode::Normal;
-				Ok(InputResult::Normal)
+			LocationInputResult::Invalid => {
+				self.input_state = InputState::Normal;
+				self.input_mode.alpha = AlphaMode::Normal;
+				Err(Error::InvalidEntry)
+			}
+		}
+	}
+
+	fn handle_text_input(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
+		match input {
+			InputEvent::Character(ch) => self.text_entry.push(ch),
+			InputEvent::Enter => self.finish_text_entry()?,
+			InputEvent::Backspace => {
+				if self.text_entry.pop().is_none() {
+					self.end_text_entry();
+				}
+			}
+			InputEvent::Exit => self.end_text_entry(),
+			InputEvent::FunctionKey(idx, _) => {
+				if let Some(func) = self.function_keys.function(idx) {
+					func.execute(self, screen)?;
+				}
+			}
+			InputEvent::Up => self.function_keys.prev_page(),
+			InputEvent::Down => self.function_keys.next_page(),
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			_ => {
+				// Keys for operations insert the matching word of the language
+				let word = match input {
+					InputEvent::Add => "+",
+					InputEvent::Sub => "-",
+					InputEvent::Mul => "×",
+					InputEvent::Div => "÷",
+					InputEvent::Pow => "^",
+					InputEvent::Neg => "NEG",
+					InputEvent::Recip => "INV",
+					InputEvent::Sqrt => "√",
+					InputEvent::Square => "SQ",
+					InputEvent::Log => "LOG",
+					InputEvent::TenX => "ALOG",
+					InputEvent::Ln => "LN",
+					InputEvent::EX => "EXP",
+					InputEvent::Sin => "SIN",
+					InputEvent::Cos => "COS",
+					InputEvent::Tan => "TAN",
+					InputEvent::Asin => "ASIN",
+					InputEvent::Acos => "ACOS",
+					InputEvent::Atan => "ATAN",
+					InputEvent::Pi => "π",
+					InputEvent::Swap => "SWAP",
+					_ => return Ok(InputResult::Normal),
+				};
+				self.insert_script_word(word);
+			}
+		}
+		Ok(InputResult::Normal)
+	}
+
+	fn handle_graph_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		let mut graph = match self.graph.take() {
+			Some(graph) => graph,
+			None => {
+				self.input_state = InputState::Normal;
+				return Ok(InputResult::Normal);
+			}
+		};
+
+		// Changes to the horizontal range require the program to be evaluated again
+		let mut resample = false;
+		match input {
+			InputEvent::Up => graph.move_trace(-1, false, screen),
+			InputEvent::Down => graph.move_trace(1, false, screen),
+			InputEvent::ShiftUp => graph.move_trace(-1, true, screen),
+			InputEvent::ShiftDown => graph.move_trace(1, true, screen),
+			InputEvent::Add => {
+				graph.zoom(Decimal::from(1) / Decimal::from(2));
+				resample = true;
+			}
+			InputEvent::Sub => {
+				graph.zoom(2.into());
+				resample = true;
+			}
+			InputEvent::Mul => {
+				graph.change_bins(1)?;
+				resample = true;
+			}
+			InputEvent::Div => {
+				graph.change_bins(-1)?;
+				resample = true;
+			}
+			InputEvent::Character(ch) => match ch {
+				'4' => {
+					graph.pan_x(-1);
+					resample = true;
+				}
+				'6' => {
+					graph.pan_x(1);
+					resample = true;
+				}
+				'2' => graph.pan_y(-1),
+				'8' => graph.pan_y(1),
+				'5' => graph.reset_scale(),
+				_ => (),
+			},
+			InputEvent::FunctionKey(idx, _) => {
+				// Find a point near the trace cursor and push its y and x, leaving
+				// x in x. A root is only pushed as x, since its y is zero.
+				let analysis = match (idx as usize)
+					.checked_sub(1)
+					.and_then(|idx| GRAPH_ANALYSES.get(idx))
+				{
+					Some(analysis) => *analysis,
+					None => {
+						self.graph = Some(graph);
+						return Ok(InputResult::Normal);
+					}
+				};
+				let result = graph.analyze(analysis, &mut self.program_evaluator(), screen);
+				self.graph = Some(graph);
+				let (x, y) = result?;
+				if analysis != GraphAnalysis::Root {
+					self.context.push(Value::Number(Number::Decimal(y)))?;
+				}
+				self.context.push(Value::Number(Number::Decimal(x)))?;
+				return Ok(InputResult::Normal);
+			}
+			InputEvent::Enter => {
+				// Place the traced value on the stack and leave the graph
+				let trace_value = graph.trace_value();
+				self.graph = Some(graph);
+				self.hide_graph();
+				return match trace_value {
+					Some(value) => {
+						self.context.push(Value::Number(Number::Decimal(value)))?;
+						Ok(InputResult::Normal)
+					}
+					None => Err(Error::ValueNotDefined),
+				};
 			}
-			LocationInputResult::Invalid => {
-				self.input_state = InputState::Normal;
-				self.input_mode.alpha = AlphaMode::Normal;
-				Err(Error::InvalidEntry)
+			InputEvent::Exit | InputEvent::Backspace => {
+				self.graph = Some(graph);
+				self.hide_graph();
+				return Ok(InputResult::Normal);
+			}
+			InputEvent::Off => {
+				self.graph = Some(graph);
+				return Ok(InputResult::Suspend);
 			}
+			_ => (),
+		}
+
+		if resample {
+			graph.sample(&mut self.program_evaluator(), screen);
 		}
+		self.graph = Some(graph);
+		Ok(InputResult::Normal)
 	}
 
 	fn handle_menu_input(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
 		let menu = self.menus.last_mut().unwrap();
+		if input == InputEvent::Show {
+			menu.toggle_help();
+			return Ok(InputResult::Normal);
+		}
+		menu.hide_help();
+
 		match input {
 			InputEvent::Up => menu.up(),
 			InputEvent::Down => menu.down(),
+			InputEvent::ShiftUp => menu.prev_page(),
+			InputEvent::ShiftDown => menu.next_page(),
 			InputEvent::Enter | InputEvent::Add | InputEvent::Mul => {
 				menu.force_refresh();
 				let function = menu.selected_function();
@@ -627,6 +3287,17 @@ impl State {
 					}
 				}
 			}
+			InputEvent::Clear => {
+				// Toggle the lock on items that can be deleted, protecting them from
+				// accidental deletion
+				menu.force_refresh();
+				let function = menu.selected_function();
+				if let MenuItemFunction::InMenuActionWithDelete(_, action) = function {
+					if let Some(lock) = action.lock_toggle() {
+						lock.execute(self, screen)?;
+					}
+				}
+			}
 			InputEvent::Character(ch) => match ch {
 				'1'..='9' => {
 					self.direct_select_menu_item((ch as u32 - '1' as u32) as usize, screen)?;
@@ -654,42 +3325,588 @@ impl State {
 			InputEvent::Sin => self.direct_select_menu_item(19, screen)?,
 			InputEvent::Cos => self.direct_select_menu_item(20, screen)?,
 			InputEvent::Tan => self.direct_select_menu_item(21, screen)?,
-			InputEvent::Exit => {
-				self.menus.pop();
-				if let Some(menu) = self.menus.last_mut() {
-					menu.force_refresh();
-				} else {
-					self.input_state = InputState::Normal;
-					self.cached_status_bar_state.left_string = String::new();
-					self.force_refresh = true;
-				}
-			}
+			InputEvent::Exit => self.close_menu(),
 			InputEvent::Off => return Ok(InputResult::Suspend),
 			_ => (),
 		}
 		Ok(InputResult::Normal)
 	}
 
-	pub fn handle_input(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
-		if self.error.is_some() {
-			self.error = None;
+	pub fn handle_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		// Screenshots are taken before anything else so that error messages can
+		// be captured as well
+		if input == InputEvent::Screenshot {
+			screen.capture()?;
+			return Ok(InputResult::Normal);
+		}
+		if let InputEvent::System(action) = input {
+			self.run_system_action(action, screen)?;
+			return Ok(InputResult::Normal);
+		}
+		if let InputEvent::Touch(x, y) = input {
+			return self.handle_touch(x, y, screen);
+		}
+
+		// A question about an action takes all of the input until it is answered
+		if self.dialog.is_some() {
+			self.toast = None;
+			return self.answer_dialog(input, screen);
+		}
+
+		if self.toast.is_some() {
+			self.hide_toast();
 			return match input {
 				InputEvent::Off => Ok(InputResult::Suspend),
 				_ => Ok(InputResult::Normal),
 			};
 		}
 
+		// Pasted text is typed with the keys of its digits, so they are recorded
+		// while recording a program instead of the paste itself
+		match input {
+			InputEvent::Copy => {
+				let text = self.x_text()?;
+				platform().copy_to_clipboard(&text)?;
+				self.show_toast(Toast::message("Copied"));
+				return Ok(InputResult::Normal);
+			}
+			InputEvent::Paste => {
+				let text = platform().paste_from_clipboard()?;
+				self.paste_text(&text, screen)?;
+				return Ok(InputResult::Normal);
+			}
+			_ => (),
+		}
+
+		if input == InputEvent::Program && self.input_state != InputState::Graph {
+			self.end_edit()?;
+			if self.macro_recording.is_some() {
+				self.finish_macro_recording()?;
+			} else {
+				self.toggle_program_recording()?;
+			}
+			return Ok(InputResult::Normal);
+		}
+
+		// While recording a program or macro, successful steps are appended to it.
+		// Inputs generated by a step that is being recorded are part of that step.
+		if (self.program_recording.is_some() || self.macro_recording.is_some())
+			&& !self.recording_step
+		{
+			let step = self.program_step(input);
+			self.recording_step = true;
+			let result = self.dispatch_input(input, screen);
+			self.recording_step = false;
+			if let (Some(step), Ok(_)) = (step, &result) {
+				if let Some(program) = &mut self.program_recording {
+					program.push(step.clone());
+				}
+				if let Some((_, steps)) = &mut self.macro_recording {
+					steps.push(step);
+					if steps.len() >= MAX_MACRO_STEPS {
+						self.finish_macro_recording()?;
+					}
+				}
+			}
+			if result.is_ok() {
+				self.session_stats.record_operation();
+			}
+			return result;
+		}
+
+		let result = self.dispatch_input(input, screen);
+		if result.is_ok() {
+			self.session_stats.record_operation();
+		}
+		result
+	}
+
+	/// Handles a tap at a point on the screen as the keys that do the same thing,
+	/// so that what it does is recorded in programs like any other input
+	fn handle_touch(&mut self, x: i32, y: i32, screen: &dyn Screen) -> Result<InputResult> {
+		// A tap dismisses a message in the same way as a key
+		if self.dialog.is_none() && self.toast.is_some() {
+			self.hide_toast();
+			return Ok(InputResult::Normal);
+		}
+		for input in self.touch_inputs(x, y, screen) {
+			self.handle_input(input, screen)?;
+		}
+		Ok(InputResult::Normal)
+	}
+
+	/// Keys for a tap. Taps on the labels of the soft keys press them, taps on a
+	/// menu item pick it, and taps on a stack entry recall a copy of it to x. A
+	/// tap on the title of a menu with more than one page goes to the next page.
+	fn touch_inputs(&self, x: i32, y: i32, screen: &dyn Screen) -> Vec<InputEvent> {
+		let mut inputs = Vec::new();
+		let function_key = function_key_at(x, y, screen);
+		if self.dialog.is_some() {
+			if let Some(key) = function_key {
+				inputs.push(InputEvent::FunctionKey(key, false));
+			}
+			return inputs;
+		}
+
+		match self.input_state {
+			InputState::Menu => {
+				if let Some(menu) = self.menus.last() {
+					if let Some(idx) = menu.item_at(x, y) {
+						Self::menu_item_inputs(menu, idx, &mut inputs);
+					} else if y < Menu::title_height(screen) && menu.page_count() > 1 {
+						inputs.push(InputEvent::ShiftDown);
+					}
+				}
+			}
+			InputState::Graph | InputState::SelfTest => (),
+			_ => match function_key {
+				Some(key) if self.function_keys.height(screen) > 0 => {
+					inputs.push(InputEvent::FunctionKey(key, false));
+				}
+				_ => {
+					if self.input_state != InputState::Normal
+						&& self.input_state != InputState::NumberInput
+					{
+						return inputs;
+					}
+					let entry = self
+						.stack_renderer
+						.borrow()
+						.entry_at(x, y, screen.metrics());
+					if let Some(idx) = entry {
+						// The number being typed is entered before the recall, which
+						// moves the entry up the stack
+						let offset = if self.input_state == InputState::NumberInput {
+							idx + 1
+						} else {
+							idx
+						};
+						inputs.push(InputEvent::Rcl);
+						inputs.push(InputEvent::Character('.'));
+						for ch in (offset + 1).to_number().to_string().chars() {
+							inputs.push(InputEvent::Character(ch));
+						}
+						inputs.push(InputEvent::Enter);
+					}
+				}
+			},
+		}
+		inputs
+	}
+
+	/// Keys that pick a menu item with the same effect as its number key. Items
+	/// past those with keys are reached by moving the selection to them.
+	fn menu_item_inputs(menu: &Menu, idx: usize, inputs: &mut Vec<InputEvent>) {
+		match idx {
+			0..=8 => inputs.push(InputEvent::Character(
+				core::char::from_digit(idx as u32 + 1, 10).unwrap(),
+			)),
+			9 => inputs.push(InputEvent::Character('0')),
+			_ if idx - 10 < MENU_LETTER_KEYS.len() => inputs.push(MENU_LETTER_KEYS[idx - 10]),
+			_ => {
+				let count = menu.item_count();
+				let forward = (idx + count - menu.selection()) % count;
+				if forward <= count / 2 {
+					inputs.extend(core::iter::repeat_n(InputEvent::Down, forward));
+				} else {
+					inputs.extend(core::iter::repeat_n(InputEvent::Up, count - forward));
+				}
+				match menu.item_function(idx) {
+					Some(MenuItemFunction::ConversionAction(_, _, _)) | None => (),
+					Some(_) => inputs.push(InputEvent::Enter),
+				}
+			}
+		}
+	}
+
+	/// Returns true if the input can start a computation that may take long enough
+	/// that the busy indicator should be shown while it runs.
+	pub fn input_may_be_slow(&self, input: InputEvent) -> bool {
+		match self.input_state {
+			InputState::Normal | InputState::NumberInput => (),
+			_ => return false,
+		}
+		matches!(
+			input,
+			InputEvent::FunctionKey(_, _)
+				| InputEvent::SigmaPlus
+				| InputEvent::SigmaMinus
+				| InputEvent::Recip
+				| InputEvent::Pow
+				| InputEvent::Sqrt
+				| InputEvent::Square
+				| InputEvent::Log
+				| InputEvent::TenX
+				| InputEvent::Ln
+				| InputEvent::EX
+				| InputEvent::Percent
+				| InputEvent::Sin
+				| InputEvent::Asin
+				| InputEvent::Cos
+				| InputEvent::Acos
+				| InputEvent::Tan
+				| InputEvent::Atan
+				| InputEvent::Div
+				| InputEvent::Mul
+				| InputEvent::Sub
+				| InputEvent::Add
+				| InputEvent::Run
+		)
+	}
+
+	/// Shows the busy indicator in the status bar immediately. It stays onscreen
+	/// until the status bar is next rendered after the input has been handled.
+	pub fn show_busy_indicator(&mut self, screen: &mut dyn Screen) {
+		if self.settings.status_bar_enabled {
+			self.busy = true;
+			self.render_status_bar_frame(screen);
+			self.busy = false;
+		}
+	}
+
+	/// Runs an action of the system keymap. These are not recorded in programs
+	/// and work whatever the calculator is doing.
+	fn run_system_action(&mut self, action: SystemAction, screen: &dyn Screen) -> Result<()> {
+		let function = match action {
+			SystemAction::Screenshot => Function::Screenshot,
+			SystemAction::SaveState => Function::SaveState,
+			SystemAction::ToggleTheme => Function::ThemeToggle,
+			SystemAction::Diagnostics => Function::DiagnosticsMenu,
+		};
+		function.execute(self, screen)?;
+		if action == SystemAction::SaveState {
+			self.show_toast(Toast::message("State saved"));
+		}
+		Ok(())
+	}
+
+	fn dispatch_input(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
+		match self.tape_operation(input) {
+			Some(operation) => self.dispatch_operation(operation, input, screen),
+			None => self.dispatch_input_to_state(input, screen),
+		}
+	}
+
+	/// Label of the operation performed by `input`, if it is one that is recorded
+	/// on the tape and in the answer history
+	fn tape_operation(&self, input: InputEvent) -> Option<String> {
+		match self.input_state {
+			InputState::Normal | InputState::NumberInput => (),
+			_ => return None,
+		}
+		match input {
+			InputEvent::FunctionKey(idx, _) => match self.function_keys.function(idx) {
+				Some(func @ Function::Stack(_))
+				| Some(func @ Function::Macro(_))
+				| Some(func @ Function::RunScript(_)) => Some(func.to_string(self)),
+				_ => None,
+			},
+			InputEvent::SigmaPlus
+			| InputEvent::SigmaMinus
+			| InputEvent::Recip
+			| InputEvent::Pow
+			| InputEvent::Sqrt
+			| InputEvent::Square
+			| InputEvent::Log
+			| InputEvent::TenX
+			| InputEvent::Ln
+			| InputEvent::EX
+			| InputEvent::Percent
+			| InputEvent::Sin
+			| InputEvent::Asin
+			| InputEvent::Cos
+			| InputEvent::Acos
+			| InputEvent::Tan
+			| InputEvent::Atan
+			| InputEvent::Div
+			| InputEvent::Mul
+			| InputEvent::Sub
+			| InputEvent::Add => Some(input.to_string()),
+			_ => None,
+		}
+	}
+
+	/// Performs an operation and records it on the tape along with its operands
+	/// and result. The result is also kept in the answer history.
+	fn dispatch_operation(
+		&mut self,
+		operation: String,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		// Enter the number being edited first so that it is recorded as an operand
+		self.end_edit()?;
+		let depth = self.context.stack_len();
+		let mut operands = Vec::new();
+		for idx in (0..core::cmp::min(depth, MAX_TAPE_OPERANDS)).rev() {
+			operands.push(self.context.entry(idx)?);
+		}
+
+		let result = self.dispatch_input_to_state(input, screen)?;
+
+		// Operations replace the values they take from the stack with their result
+		let consumed = core::cmp::min(
+			(depth + 1).saturating_sub(self.context.stack_len()),
+			operands.len(),
+		);
+		operands.drain(..operands.len() - consumed);
+		let entry = TapeEntry {
+			operation,
+			operands,
+			result: self.context.top().ok(),
+		};
+		if let Some(result) = &entry.result {
+			self.answers.push(result.clone());
+		}
+		if let Some(tape) = &mut self.tape {
+			tape.push(entry);
+		}
+		Ok(result)
+	}
+
+	fn dispatch_input_to_state(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
 		match self.input_state {
 			InputState::Normal => self.handle_normal_input(input, screen),
 			InputState::NumberInput => self.handle_number_input(input, screen),
 			InputState::Recall => self.handle_recall_input(input),
 			InputState::Store => self.handle_store_input(input),
+			InputState::Lock => self.handle_lock_input(input),
 			InputState::Menu => self.handle_menu_input(input, screen),
+			InputState::Graph => self.handle_graph_input(input, screen),
+			InputState::TextEntry => self.handle_text_input(input, screen),
+			InputState::Tape => self.handle_tape_input(input, screen),
+			InputState::Calendar => self.handle_calendar_input(input, screen),
+			InputState::SelfTest => self.handle_self_test_input(input),
+			InputState::MatrixEdit => self.handle_matrix_edit_input(input, screen),
+			InputState::InputLocation => self.handle_input_location_input(input),
+			InputState::ProgramPause => self.handle_program_pause_input(input, screen),
+			InputState::FlagEntry(operation) => self.handle_flag_input(operation, input),
+			InputState::Label | InputState::Goto => self.handle_label_input(input, screen),
+		}
+	}
+
+	fn handle_tape_input(&mut self, input: InputEvent, screen: &dyn Screen) -> Result<InputResult> {
+		let page = core::cmp::max(1, self.tape_visible_lines(screen));
+		let line_count = self.tape_line_count();
+		match input {
+			InputEvent::Up => self.tape_scroll += 1,
+			InputEvent::Down => self.tape_scroll = self.tape_scroll.saturating_sub(1),
+			InputEvent::ShiftUp => self.tape_scroll += page,
+			InputEvent::ShiftDown => self.tape_scroll = self.tape_scroll.saturating_sub(page),
+			InputEvent::FunctionKey(idx, _) => {
+				if let Some(func) = self.function_keys.function(idx) {
+					func.execute(self, screen)?;
+				}
+			}
+			InputEvent::Exit | InputEvent::Backspace | InputEvent::Enter => {
+				self.input_state = InputState::Normal;
+				self.force_refresh = true;
+				self.function_keys.exit_menu(self.context.format());
+			}
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			_ => (),
+		}
+		self.tape_scroll = core::cmp::min(self.tape_scroll, line_count.saturating_sub(page));
+		Ok(InputResult::Normal)
+	}
+
+	fn handle_calendar_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		let calendar = match &mut self.calendar {
+			Some(calendar) => calendar,
+			None => {
+				self.exit_calendar();
+				return Ok(InputResult::Normal);
+			}
+		};
+		match input {
+			InputEvent::Up => calendar.move_months(-1),
+			InputEvent::Down => calendar.move_months(1),
+			InputEvent::ShiftUp => calendar.move_months(-12),
+			InputEvent::ShiftDown => calendar.move_months(12),
+			// The number keys move the selection like a keypad
+			InputEvent::Character('4') => calendar.move_days(-1),
+			InputEvent::Character('6') => calendar.move_days(1),
+			InputEvent::Character('8') => calendar.move_days(-7),
+			InputEvent::Character('2') => calendar.move_days(7),
+			InputEvent::FunctionKey(idx, _) => {
+				if let Some(func) = self.function_keys.function(idx) {
+					func.execute(self, screen)?;
+				}
+			}
+			InputEvent::Enter => self.push_calendar_date()?,
+			InputEvent::Exit | InputEvent::Backspace => self.exit_calendar(),
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			_ => (),
+		}
+		self.force_refresh = true;
+		Ok(InputResult::Normal)
+	}
+
+	fn handle_matrix_edit_input(
+		&mut self,
+		input: InputEvent,
+		screen: &dyn Screen,
+	) -> Result<InputResult> {
+		let float = self.context.format().integer_mode == IntegerMode::Float;
+		let format = self.context.format().clone();
+		let editor = match &mut self.matrix_editor {
+			Some(editor) => editor,
+			None => {
+				self.exit_matrix_edit();
+				return Ok(InputResult::Normal);
+			}
+		};
+		self.force_refresh = true;
+		match input {
+			InputEvent::Character(ch) => match ch {
+				'0'..='9' | 'A'..='Z' | 'a'..='z' | '.' if ch != '.' || float => {
+					editor.number_editor(&format).push_char(ch)?;
+				}
+				_ => (),
+			},
+			InputEvent::E => {
+				if float {
+					editor.number_editor(&format).exponent()?;
+				}
+				self.input_mode.alpha = AlphaMode::Normal;
+			}
+			InputEvent::Neg => {
+				if editor.editing() {
+					editor.number_editor(&format).neg();
+				} else {
+					let value = (-editor.selected().clone())?;
+					editor.set_selected(value);
+				}
+			}
+			InputEvent::Backspace => {
+				if !editor.editing() {
+					editor.set_selected(Value::Number(0.into()));
+				} else if !editor.number_editor(&format).backspace() {
+					editor.cancel_edit();
+				}
+			}
+			InputEvent::Enter => {
+				if editor.editing() {
+					editor.advance();
+				} else {
+					self.finish_matrix_edit()?;
+				}
+			}
+			InputEvent::Up => editor.move_by(-1, 0),
+			InputEvent::Down => editor.move_by(1, 0),
+			InputEvent::ShiftUp => editor.move_by(0, -1),
+			InputEvent::ShiftDown => editor.move_by(0, 1),
+			InputEvent::FunctionKey(idx, _) => {
+				if let Some(func) = self.function_keys.function(idx) {
+					func.execute(self, screen)?;
+				}
+			}
+			InputEvent::Exit => {
+				if editor.editing() {
+					editor.cancel_edit();
+				} else {
+					self.exit_matrix_edit();
+				}
+			}
+			InputEvent::Off => return Ok(InputResult::Suspend),
+			_ => (),
+		}
+		Ok(InputResult::Normal)
+	}
+
+	fn handle_self_test_input(&mut self, input: InputEvent) -> Result<InputResult> {
+		if input == InputEvent::Off {
+			return Ok(InputResult::Suspend);
+		}
+		let done = match &mut self.self_test {
+			Some(test) => test.handle_input(input),
+			None => true,
+		};
+		if done {
+			self.self_test = None;
+			self.input_state = InputState::Menu;
+			self.force_refresh = true;
+			if let Some(menu) = self.menus.last() {
+				menu.force_refresh();
+			} else {
+				self.input_state = InputState::Normal;
+			}
+		}
+		Ok(InputResult::Normal)
+	}
+
+	/// Area of the screen that the tape is shown in, between the status bar and
+	/// the soft keys
+	fn tape_area(&self, screen: &dyn Screen) -> Rect {
+		let top = self.status_bar_size(screen);
+		Rect {
+			x: 0,
+			y: top,
+			w: screen.width(),
+			h: screen.height() - top - self.function_keys.height(screen),
+		}
+	}
+
+	/// Number of lines of the tape that fit below its title
+	fn tape_visible_lines(&self, screen: &dyn Screen) -> usize {
+		let metrics = screen.metrics();
+		let available = self.tape_area(screen).h - metrics.height(Font::Smallest);
+		core::cmp::max(0, available / metrics.height(Font::Small)) as usize
+	}
+
+	fn tape_line_count(&self) -> usize {
+		match &self.tape {
+			Some(tape) => tape.lines(self.context.format()).len(),
+			None => 0,
+		}
+	}
+
+	/// Handles a key of a location entry. When the entry is indirect, the
+	/// location that is finished is the register numbered by the value in the
+	/// location that was chosen.
+	fn handle_location_entry(&mut self, input: InputEvent) -> Result<LocationInputResult> {
+		match self.handle_location_input(input) {
+			LocationInputResult::Finished(location) if self.location_entry.indirect => {
+				match self.indirect_location(&location) {
+					Ok(location) => Ok(LocationInputResult::Finished(location)),
+					Err(error) => {
+						self.input_state = InputState::Normal;
+						self.input_mode.alpha = AlphaMode::Normal;
+						Err(error)
+					}
+				}
+			}
+			result => Ok(result),
 		}
 	}
 
+	fn indirect_location(&self, location: &Location) -> Result<Location> {
+		let value = self.context.read(location)?;
+		let index = usize::try_from(&*value.to_int()?).map_err(|_| Error::ValueOutOfRange)?;
+		Ok(Location::Integer(index))
+	}
+
 	fn handle_location_input(&mut self, input: InputEvent) -> LocationInputResult {
 		match input {
+			// Rcl before the location makes the entry indirect
+			InputEvent::Rcl
+				if self.location_entry.value.is_empty() && !self.location_entry.stack =>
+			{
+				self.location_entry.indirect = !self.location_entry.indirect;
+				LocationInputResult::Intermediate(InputResult::Normal)
+			}
 			InputEvent::Character(ch) => match ch {
 				'0'..='9' => {
 					self.location_entry
@@ -785,6 +4002,10 @@ impl State {
 		let integer_mode = self.context.format().integer_mode;
 		let angle_mode = *self.context.angle_mode();
 		let multiple_pages = self.function_keys.multiple_pages();
+		let recording = self.program_recording() || self.macro_recording();
+		let busy = self.busy;
+		let carry = self.context.carry();
+		let out_of_range = self.context.out_of_range();
 
 		// Check for alpha mode updates
 		if alpha != self.cached_status_bar_state.alpha {
@@ -816,37 +4037,119 @@ impl State {
 			changed = true;
 		}
 
-		if multiple_pages != self.cached_status_bar_state.multiple_pages {
-			self.cached_status_bar_state.multiple_pages = multiple_pages;
+		if multiple_pages != self.cached_status_bar_state.multiple_pages {
+			self.cached_status_bar_state.multiple_pages = multiple_pages;
+			changed = true;
+		}
+
+		if recording != self.cached_status_bar_state.recording {
+			self.cached_status_bar_state.recording = recording;
+			changed = true;
+		}
+
+		let exam = self.exam_mode();
+		if exam != self.cached_status_bar_state.exam {
+			self.cached_status_bar_state.exam = exam;
+			changed = true;
+		}
+
+		if busy != self.cached_status_bar_state.busy {
+			self.cached_status_bar_state.busy = busy;
+			changed = true;
+		}
+
+		let bank = if self.function_keys.custom_bank_count() > 1 {
+			Some(
+				self.function_keys
+					.custom_bank_name(self.function_keys.active_custom_bank()),
+			)
+		} else {
+			None
+		};
+		if bank != self.cached_status_bar_state.custom_bank.as_deref() {
+			self.cached_status_bar_state.custom_bank = bank.map(|name| name.to_string());
+			changed = true;
+		}
+
+		if carry != self.cached_status_bar_state.carry
+			|| out_of_range != self.cached_status_bar_state.out_of_range
+		{
+			self.cached_status_bar_state.carry = carry;
+			self.cached_status_bar_state.out_of_range = out_of_range;
 			changed = true;
 		}
 
-		match self.status_bar_left_display {
+		match self.settings.status_bar_left_display {
 			StatusBarLeftDisplayType::CurrentTime => {
 				// Check for time updates
-				if clock_minute_updated() || self.cached_status_bar_state.left_string.len() == 0 {
+				if platform().clock_minute_updated()
+					|| self.cached_status_bar_state.left_string.is_empty()
+				{
 					let time_string = State::time_string(self.context.format().time_24_hour);
 					self.cached_status_bar_state.left_string = time_string;
 					changed = true;
 				}
 			}
 			StatusBarLeftDisplayType::FreeMemory => {
-				let free_memory = available_bytes().to_number().to_string() + " bytes free";
-				if free_memory != self.cached_status_bar_state.left_string {
-					self.cached_status_bar_state.left_string = free_memory;
-					changed = true;
+				let text = &mut self.cached_status_bar_state.next_left_string;
+				text.clear();
+				Format::new().write_count(text, available_bytes() as u64);
+				text.push_str(" bytes free");
+				changed |= self.cached_status_bar_state.update_left_string();
+			}
+			StatusBarLeftDisplayType::FrameTime => {
+				// Show the time taken by the previous frame along with the stack
+				// rendering path that was used
+				let text = &mut self.cached_status_bar_state.next_left_string;
+				text.clear();
+				text.push_str("Frame ");
+				match self.last_frame_time {
+					Some(ms) => Format::new().write_count(text, ms as u64),
+					None => text.push_str("--"),
+				}
+				text.push_str(if self.settings.stack_render_cache {
+					" ms (Cached)"
+				} else {
+					" ms (Legacy)"
+				});
+				changed |= self.cached_status_bar_state.update_left_string();
+			}
+			StatusBarLeftDisplayType::Battery => {
+				let text = &mut self.cached_status_bar_state.next_left_string;
+				text.clear();
+				match platform().power_status() {
+					Some(power) => {
+						if power.usb {
+							text.push_str("USB ");
+						} else {
+							Format::new().write_count(text, power.percent as u64);
+							text.push_str("% ");
+						}
+						Format::new().write_count(text, (power.voltage / 1000) as u64);
+						let centivolts = (power.voltage % 1000) / 10;
+						text.push('.');
+						text.push((b'0' + (centivolts / 10) as u8) as char);
+						text.push((b'0' + (centivolts % 10) as u8) as char);
+						text.push_str(" V");
+					}
+					None => text.push_str("No battery"),
 				}
+				changed |= self.cached_status_bar_state.update_left_string();
 			}
 		}
 
 		changed
 	}
 
-	#[cfg(feature = "dm42")]
-	fn draw_battery_indicator(&self, renderer: &mut dyn LayoutRenderer, x: &mut i32) {
+	fn draw_battery_indicator(
+		&self,
+		renderer: &mut dyn LayoutRenderer,
+		x: &mut i32,
+		power: PowerStatus,
+	) {
 		// Determine how many bars are present inside the battery indicator
-		let usb = usb_powered();
-		let voltage = read_power_voltage();
+		let usb = power.usb;
+		let voltage = power.voltage;
 		let mut fill = 5 - ((2940 - voltage as i32) / 150);
 		if fill < 0 {
 			fill = 0;
@@ -932,7 +4235,7 @@ impl State {
 	}
 
 	fn draw_status_bar(&self, screen: &mut dyn Screen) {
-		if !self.status_bar_enabled && !self.input_mode.shift {
+		if !self.settings.status_bar_enabled && !self.input_mode.shift {
 			return;
 		}
 
@@ -958,8 +4261,9 @@ impl State {
 
 		let mut x = screen_width - 4;
 
-		#[cfg(feature = "dm42")]
-		self.draw_battery_indicator(&mut renderer, &mut x);
+		if let Some(power) = platform().power_status() {
+			self.draw_battery_indicator(&mut renderer, &mut x, power);
+		}
 
 		// Render alpha mode indicator
 		match self.cached_status_bar_state.alpha {
@@ -993,6 +4297,13 @@ impl State {
 
 		// Render integer radix indicator
 		match self.cached_status_bar_state.integer_radix {
+			2 => self.draw_status_bar_indicator(
+				&mut renderer,
+				&mut x,
+				"Bin",
+				Font::Smallest,
+				&status_bar_rect,
+			),
 			8 => self.draw_status_bar_indicator(
 				&mut renderer,
 				&mut x,
@@ -1034,6 +4345,26 @@ impl State {
 					Font::Smallest,
 					&status_bar_rect,
 				);
+
+				// Carry and out of range flags, using the annunciators of the HP-16C
+				if self.cached_status_bar_state.carry {
+					self.draw_status_bar_indicator(
+						&mut renderer,
+						&mut x,
+						"C",
+						Font::Smallest,
+						&status_bar_rect,
+					);
+				}
+				if self.cached_status_bar_state.out_of_range {
+					self.draw_status_bar_indicator(
+						&mut renderer,
+						&mut x,
+						"G",
+						Font::Smallest,
+						&status_bar_rect,
+					);
+				}
 			}
 		}
 
@@ -1056,6 +4387,51 @@ impl State {
 			),
 		}
 
+		// Render program recording indicator
+		if self.cached_status_bar_state.recording {
+			self.draw_status_bar_indicator(
+				&mut renderer,
+				&mut x,
+				"Prgm",
+				Font::Smallest,
+				&status_bar_rect,
+			);
+		}
+
+		// Render exam mode indicator, which stays visible while programs and
+		// stored data are unavailable
+		if self.cached_status_bar_state.exam {
+			self.draw_status_bar_indicator(
+				&mut renderer,
+				&mut x,
+				"Exam",
+				Font::Smallest,
+				&status_bar_rect,
+			);
+		}
+
+		// Render the name of the active custom bank
+		if let Some(bank) = &self.cached_status_bar_state.custom_bank {
+			self.draw_status_bar_indicator(
+				&mut renderer,
+				&mut x,
+				bank,
+				Font::Smallest,
+				&status_bar_rect,
+			);
+		}
+
+		// Render busy indicator while a computation is running
+		if self.cached_status_bar_state.busy {
+			self.draw_status_bar_indicator(
+				&mut renderer,
+				&mut x,
+				"Busy",
+				Font::Smallest,
+				&status_bar_rect,
+			);
+		}
+
 		// Render menu page indicator
 		if self.cached_status_bar_state.multiple_pages {
 			self.draw_status_bar_indicator(
@@ -1082,8 +4458,8 @@ impl State {
 		}
 	}
 
-	fn status_bar_size(&self, screen: &mut dyn Screen) -> i32 {
-		if self.status_bar_enabled || self.input_mode.shift {
+	fn status_bar_size(&self, screen: &dyn Screen) -> i32 {
+		if self.settings.status_bar_enabled || self.input_mode.shift {
 			screen.metrics().height(Font::Smallest) + 1
 		} else {
 			0
@@ -1111,10 +4487,22 @@ impl State {
 			h: rect.h,
 		};
 
+		// Skip drawing if the same layout is already onscreen at this position. Fading
+		// animations draw over the layout, so it must be redrawn while one is active.
+		let entry = (layout, rect);
+		if !self.force_refresh
+			&& self.animation.is_none()
+			&& self.prev_bottom_layouts.contains(&entry)
+		{
+			self.bottom_layouts.borrow_mut().push(entry);
+			return;
+		}
+		let (layout, rect) = entry;
+
 		let screen_width = screen.width();
 		let mut renderer = screen.renderer(RenderMode::Normal);
 		renderer.erase(&clip_rect);
-		layout.render(&mut renderer, rect, &clip_rect);
+		layout.render(&mut renderer, rect.clone(), &clip_rect);
 
 		// Render a line to separate the error from the stack area
 		renderer.fill(
@@ -1126,20 +4514,69 @@ impl State {
 			},
 			TokenType::Error,
 		);
+		self.bottom_layouts.borrow_mut().push((layout, rect));
 	}
 
-	fn render_error(&self, error: &Error, screen: &mut dyn Screen, stack_area: &mut Rect) {
-		let mut items = Vec::new();
-		items.push(Layout::StaticText(
-			error.to_str(),
+	fn toast_layout(toast: &Toast, screen: &dyn Screen) -> Layout {
+		let token_type = match toast.kind() {
+			ToastKind::Message => TokenType::Text,
+			ToastKind::Error => TokenType::Error,
+		};
+		toast.text().to_string().ellipsis_layout(
 			Font::Large,
-			TokenType::Error,
-		));
-		items.push(Layout::HorizontalSpace(4));
+			token_type,
+			screen.metrics(),
+			screen.width() - 4,
+		)
+	}
+
+	fn render_toast(&self, toast: &Toast, screen: &mut dyn Screen, stack_area: &mut Rect) {
+		let items = vec![
+			State::toast_layout(toast, screen),
+			Layout::HorizontalSpace(4),
+		];
 		let layout = Layout::Horizontal(items);
 		self.render_stack_bottom_layout(layout, screen, stack_area);
 	}
 
+	/// Draws the message over the bottom of `area`, for views that are shown in
+	/// place of the stack
+	fn render_toast_overlay(&self, screen: &mut dyn Screen, area: &Rect) {
+		let toast = match &self.toast {
+			Some(toast) => toast,
+			None => return,
+		};
+		let layout = Layout::HorizontalCenter(Box::new(State::toast_layout(toast, screen)));
+		let height = layout.height(screen.metrics()) + 1;
+		let rect = Rect {
+			x: area.x,
+			y: area.y + area.h - height,
+			w: area.w,
+			h: height,
+		};
+		let mut renderer = screen.renderer(RenderMode::Normal);
+		renderer.erase(&rect);
+		renderer.fill(
+			&Rect {
+				x: rect.x,
+				y: rect.y,
+				w: rect.w,
+				h: 1,
+			},
+			TokenType::Error,
+		);
+		layout.render(
+			&mut renderer,
+			Rect {
+				x: rect.x,
+				y: rect.y + 1,
+				w: rect.w,
+				h: height - 1,
+			},
+			area,
+		);
+	}
+
 	fn render_number_editor(
 		&self,
 		editor: &NumberEditor,
@@ -1148,13 +4585,23 @@ impl State {
 	) {
 		// Show an editor prompt to the left
 		let prompt_layout = Layout::StaticText("⋙ ", Font::Small, TokenType::Label);
+		self.render_number_editor_with_prompt(editor, prompt_layout, screen, stack_area);
+	}
+
+	fn render_number_editor_with_prompt(
+		&self,
+		editor: &NumberEditor,
+		prompt_layout: Layout,
+		screen: &mut dyn Screen,
+		stack_area: &mut Rect,
+	) {
 		let prompt_width = prompt_layout.width(screen.metrics());
 
 		// Currently editing number, format editor text
 		let edit_str = editor.to_string(self.context.format());
 		let layout = if let Some(layout) = edit_str.double_line_layout(
-			self.base_font,
-			self.base_font.smaller(),
+			self.settings.base_font,
+			self.settings.base_font.smaller(),
 			editor.token_type(),
 			screen.metrics(),
 			screen.width() - prompt_width - 8,
@@ -1170,7 +4617,7 @@ impl State {
 				self.context.format(),
 				"",
 				"",
-				self.base_font,
+				self.settings.base_font,
 				screen.metrics(),
 				screen.width() - prompt_width - 8,
 			));
@@ -1182,13 +4629,25 @@ impl State {
 		let (layout, alt_layout) = Value::Number(editor.number()).add_alternate_layout(
 			layout,
 			self.context.format(),
-			self.base_font.smaller().smaller(),
+			self.settings.base_font.smaller().smaller(),
 			screen.metrics(),
 			screen.width() - prompt_width - 8,
 			true,
 			false,
 		);
 
+		// A key that was refused is explained after the prompt until the next key
+		let prompt_layout = match self.entry_rejection {
+			Some(error) => {
+				let prompt_items = vec![
+					prompt_layout,
+					Layout::StaticText(tr(error.to_str()), Font::Smallest, TokenType::Label),
+				];
+				Layout::Horizontal(prompt_items)
+			}
+			None => prompt_layout,
+		};
+
 		let mut items = Vec::new();
 		items.push(match alt_layout {
 			AlternateLayoutType::Left => prompt_layout,
@@ -1198,6 +4657,85 @@ impl State {
 		self.render_stack_bottom_layout(Layout::Horizontal(items), screen, stack_area);
 	}
 
+	/// Shows what a paused program is waiting for below the stack. A program
+	/// asking for a variable shows its name and the number being typed, or the
+	/// value it has until a number is typed.
+	fn render_program_prompt(&self, screen: &mut dyn Screen, stack_area: &mut Rect) {
+		let pause = match &self.program_pause {
+			Some(pause) => pause,
+			None => return,
+		};
+		let (prompt, value) = match &pause.prompt {
+			ProgramPrompt::Input(location, Some(editor)) => {
+				let prompt =
+					Layout::Text(location.to_string() + "? ", Font::Small, TokenType::Label);
+				self.render_number_editor_with_prompt(editor, prompt, screen, stack_area);
+				return;
+			}
+			ProgramPrompt::Input(location, None) => (
+				location.to_string() + "? ",
+				self.context.read(location).ok(),
+			),
+			ProgramPrompt::View => ("x: ".to_string(), self.context.top().ok()),
+		};
+		let prompt_layout = Layout::Text(prompt, Font::Small, TokenType::Label);
+		let width = screen.width() - prompt_layout.width(screen.metrics()) - 8;
+		let layout = match value {
+			Some(value) => value.layout(
+				self.context.format(),
+				self.settings.base_font,
+				screen.metrics(),
+				width,
+			),
+			None => Layout::StaticText("?", self.settings.base_font, TokenType::Label),
+		};
+		let items = vec![Layout::LeftAlign(Box::new(prompt_layout)), layout];
+		self.render_stack_bottom_layout(Layout::Horizontal(items), screen, stack_area);
+	}
+
+	fn render_text_entry(&self, screen: &mut dyn Screen, stack_area: &mut Rect) {
+		let prompt = match self.text_entry_target {
+			TextEntryTarget::Script => "« ",
+			TextEntryTarget::RenameFile(_) => "Name: ",
+			TextEntryTarget::Text => "\"",
+			TextEntryTarget::CatalogSearch => "Find: ",
+			TextEntryTarget::RenameCustomBank => "Bank: ",
+			TextEntryTarget::RenameProgramSlot => "Slot: ",
+		};
+		let prompt_layout = Layout::StaticText(prompt, Font::Small, TokenType::Label);
+		let width = screen.width() - prompt_layout.width(screen.metrics()) - 8;
+		let layout = match self.text_entry.double_line_layout(
+			self.settings.base_font,
+			self.settings.base_font.smaller(),
+			TokenType::Text,
+			screen.metrics(),
+			width,
+			Some(self.text_entry.len()),
+		) {
+			Some(layout) => layout,
+			None => {
+				// Long scripts show the end of the text, which is where it is
+				// being entered
+				let mut text = self.text_entry.clone();
+				while !text.is_empty()
+					&& screen
+						.metrics()
+						.width(Font::Small, &("…".to_string() + &text))
+						> width
+				{
+					text.remove(0);
+				}
+				Layout::Horizontal(vec![
+					Layout::Text("…".to_string() + &text, Font::Small, TokenType::Text),
+					Layout::EditCursor(Font::Small),
+				])
+			}
+		};
+
+		let items = vec![Layout::LeftAlign(Box::new(prompt_layout)), layout];
+		self.render_stack_bottom_layout(Layout::Horizontal(items), screen, stack_area);
+	}
+
 	fn render_location_edit(&self, screen: &mut dyn Screen, stack_area: &mut Rect) {
 		let mut items = Vec::new();
 		// Show use of location
@@ -1207,6 +4745,10 @@ impl State {
 			TokenType::Keyword,
 		));
 
+		if self.location_entry.indirect {
+			items.push(Layout::StaticText("Ind ", Font::Large, TokenType::Keyword));
+		}
+
 		// If this is a stack access, display "Stack"
 		if self.location_entry.stack {
 			items.push(Layout::StaticText(
@@ -1231,9 +4773,87 @@ impl State {
 	}
 
 	pub fn render(&mut self, screen: &mut dyn Screen) {
+		let mut screen = DamageTrackingScreen::new(screen);
+		screen.begin_frame();
+		if self.settings.status_bar_left_display != StatusBarLeftDisplayType::FrameTime {
+			self.render_frame(&mut screen);
+			screen.end_frame();
+			return;
+		}
+
+		// Measure the time taken to render the frame for display in the status bar
+		let start = NaiveDateTime::now();
+		self.render_frame(&mut screen);
+		if let (Ok(start), Ok(end)) = (start, NaiveDateTime::now()) {
+			self.last_frame_time = Some((end - start).num_milliseconds());
+		}
+		screen.end_frame();
+	}
+
+	/// Draws the status bar by itself as a frame, refreshing only its rows
+	fn render_status_bar_frame(&mut self, screen: &mut dyn Screen) {
+		let mut screen = DamageTrackingScreen::new(screen);
+		screen.begin_frame();
+		self.update_status_bar_state();
+		self.draw_status_bar(&mut screen);
+		screen.refresh();
+		screen.end_frame();
+	}
+
+	fn render_frame(&mut self, screen: &mut dyn Screen) {
+		if let Some(alarm) = &self.ringing_alarm {
+			self.render_alarm(alarm, screen);
+			screen.refresh();
+			return;
+		}
+		if let Some(state_saved) = self.low_battery_warning {
+			self.render_low_battery_warning(state_saved, screen);
+			screen.refresh();
+			return;
+		}
+		if let Some(dialog) = &self.dialog {
+			Self::render_dialog(dialog, screen);
+			screen.refresh();
+			return;
+		}
+
+		// The benchmark times drawing the calculator screen before showing its
+		// results over it
+		if let (InputState::SelfTest, Some(mut test)) = (self.input_state, self.self_test.take()) {
+			if test.needs_render_timing() {
+				self.input_state = InputState::Normal;
+				test.time_render(&mut || self.render_frame(screen));
+				self.input_state = InputState::SelfTest;
+			}
+			self.self_test = Some(test);
+		}
+
+		if let (InputState::SelfTest, Some(test)) = (self.input_state, &self.self_test) {
+			test.render(screen);
+			screen.refresh();
+			return;
+		}
+
+		if self.input_state == InputState::Graph {
+			if let Some(graph) = &self.graph {
+				graph.render(self.context.format(), screen);
+				return;
+			}
+		}
+
 		if self.input_state == InputState::Menu {
 			if let Some(menu) = self.menus.last() {
-				menu.render(self, screen);
+				match &self.animation {
+					Some(animation) if animation.animation_type() == AnimationType::MenuSlideIn => {
+						// Slide the menu in from the right edge of the screen
+						let x_offset = screen.width() - animation.progress(screen.width());
+						menu.force_refresh();
+						menu.render(self, &mut OffsetScreen::new(screen, x_offset));
+					}
+					_ => menu.render(self, screen),
+				}
+				let screen_rect = screen.screen_rect();
+				self.render_toast_overlay(screen, &screen_rect);
 				return;
 			}
 		}
@@ -1261,9 +4881,66 @@ impl State {
 			h: screen.height() - self.status_bar_size(screen) - self.function_keys.height(screen),
 		};
 
-		// If there is an error, display the message
-		if let Some(error) = &self.error {
-			self.render_error(error, screen, &mut stack_area);
+		// The tape replaces the stack while it is being viewed
+		if self.input_state == InputState::Tape {
+			self.render_tape(screen, &stack_area);
+			self.render_toast_overlay(screen, &stack_area);
+			screen.refresh();
+			self.force_refresh = false;
+			self.force_render_on_status_update = false;
+			return;
+		}
+
+		// As does the matrix editor
+		if let (InputState::MatrixEdit, Some(editor)) = (self.input_state, &self.matrix_editor) {
+			editor.render(self.context.format(), screen, &stack_area);
+			self.render_toast_overlay(screen, &stack_area);
+			screen.refresh();
+			self.force_refresh = false;
+			self.force_render_on_status_update = false;
+			return;
+		}
+
+		// And the calendar
+		if let (InputState::Calendar, Some(calendar)) = (self.input_state, &self.calendar) {
+			calendar.render(screen, &stack_area);
+			self.render_toast_overlay(screen, &stack_area);
+			screen.refresh();
+			self.force_refresh = false;
+			self.force_render_on_status_update = false;
+			return;
+		}
+
+		// Give the top of the stack area to the auxiliary pane if the split is open
+		let (split_stack_area, pane_area) = self.split.split(&stack_area);
+		stack_area = split_stack_area;
+		if let (Some(pane), Some(pane_area)) = (self.split.pane(), pane_area) {
+			self.render_aux_pane(pane, pane_area, screen);
+		} else {
+			self.pane_cache = None;
+		}
+
+		// Layouts below the stack from the previous frame are kept so that unchanged
+		// ones are not drawn again
+		self.prev_bottom_layouts = self.bottom_layouts.replace(Vec::new());
+
+		// If there is a message, display it below the stack
+		if let Some(toast) = &self.toast {
+			let toast_bottom = stack_area.y + stack_area.h;
+			self.render_toast(toast, screen, &mut stack_area);
+
+			if let Some(animation) = &self.animation {
+				if animation.animation_type() == AnimationType::FadeIn {
+					let toast_top = stack_area.y + stack_area.h;
+					let toast_rect = Rect {
+						x: 0,
+						y: toast_top,
+						w: screen.width(),
+						h: toast_bottom - toast_top,
+					};
+					fade_rect(screen, &toast_rect, animation.progress(16), 16);
+				}
+			}
 		}
 
 		// If there is an active editor present, render it
@@ -1275,24 +4952,39 @@ impl State {
 					stack_label_offset = 1;
 				}
 			}
-			InputState::Recall | InputState::Store => {
-				self.render_location_edit(screen, &mut stack_area)
-			}
+			InputState::Recall
+			| InputState::Store
+			| InputState::Lock
+			| InputState::InputLocation
+			| InputState::FlagEntry(_)
+			| InputState::Label
+			| InputState::Goto => self.render_location_edit(screen, &mut stack_area),
+			InputState::TextEntry => self.render_text_entry(screen, &mut stack_area),
+			InputState::ProgramPause => self.render_program_prompt(screen, &mut stack_area),
 			_ => (),
 		}
 
-		// Render the stack
+		// Render the stack. While a number is being typed the stack usually stays the
+		// same, and only the editor has to be drawn for each key.
 		if self.force_refresh {
 			self.stack_renderer.borrow_mut().force_refresh();
 		}
-		self.stack_renderer.borrow_mut().render(
-			self.context.stack(),
-			&mut screen.renderer(RenderMode::Normal),
-			self.context.format(),
-			self.base_font,
-			stack_area,
-			stack_label_offset,
-		);
+		let stack_current = self.input_state == InputState::NumberInput
+			&& self.animation.is_none()
+			&& self
+				.stack_renderer
+				.borrow()
+				.is_current(&stack_area, stack_label_offset);
+		if !stack_current {
+			self.stack_renderer.borrow_mut().render(
+				self.context.stack(),
+				&mut screen.renderer(RenderMode::Normal),
+				self.context.format(),
+				&FontSet::new(self.settings.base_font),
+				stack_area,
+				stack_label_offset,
+			);
+		}
 
 		// Refresh the LCD contents
 		screen.refresh();
@@ -1300,14 +4992,191 @@ impl State {
 		self.force_render_on_status_update = false;
 	}
 
+	/// Draws the title of the tape and the lines of the tape that are in view.
+	/// Operation markers are on the left and values are aligned to the right.
+	/// Draws an alarm that has gone off, with its time and message, over the
+	/// whole screen
+	fn render_alarm(&self, alarm: &Alarm, screen: &mut dyn Screen) {
+		let area = screen.screen_rect();
+		let mut lines = Vec::new();
+		lines.push(Layout::StaticText("Alarm", Font::Large, TokenType::Label));
+		lines.push(Layout::Text(
+			alarm.time_string(self.context.format().time_24_hour),
+			Font::Small,
+			TokenType::Text,
+		));
+		if let Some(message) = alarm.message() {
+			lines.push(message.to_string().ellipsis_layout(
+				Font::Large,
+				TokenType::Text,
+				screen.metrics(),
+				area.w - 16,
+			));
+		}
+		lines.push(Layout::StaticText(
+			"Press any key",
+			Font::Smallest,
+			TokenType::Label,
+		));
+		Self::render_full_screen_lines(lines, screen);
+	}
+
+	/// Draws the warning shown when the battery is critically low
+	fn render_low_battery_warning(&self, state_saved: bool, screen: &mut dyn Screen) {
+		let mut lines = Vec::new();
+		lines.push(Layout::StaticText(
+			"Battery Low",
+			Font::Large,
+			TokenType::Label,
+		));
+		lines.push(Layout::StaticText(
+			if state_saved {
+				"State saved"
+			} else {
+				"State could not be saved"
+			},
+			Font::Small,
+			TokenType::Text,
+		));
+		lines.push(Layout::StaticText(
+			"Replace the battery or connect USB",
+			Font::Smallest,
+			TokenType::Label,
+		));
+		Self::render_full_screen_lines(lines, screen);
+	}
+
+	/// Draws a confirmation dialog over the whole screen, with the answers on the
+	/// soft keys
+	fn render_dialog(dialog: &ConfirmDialog, screen: &mut dyn Screen) {
+		let lines = vec![
+			Layout::StaticText(dialog.title(), Font::Large, TokenType::Label),
+			Layout::StaticText(dialog.message(), Font::Small, TokenType::Text),
+		];
+		Self::render_full_screen_lines(lines, screen);
+		render_function_key_labels(&ConfirmDialog::key_labels(), screen);
+	}
+
+	/// Draws lines centered over the whole screen, replacing everything on it
+	fn render_full_screen_lines(lines: Vec<Layout>, screen: &mut dyn Screen) {
+		let area = screen.screen_rect();
+		let heights: Vec<i32> = lines
+			.iter()
+			.map(|line| line.height(screen.metrics()) + 8)
+			.collect();
+		let mut y = area.y + (area.h - heights.iter().sum::<i32>()) / 2;
+		let mut renderer = screen.renderer(RenderMode::Normal);
+		renderer.erase(&area);
+		for (line, height) in lines.into_iter().zip(heights) {
+			Layout::HorizontalCenter(Box::new(line)).render(
+				&mut renderer,
+				Rect {
+					x: area.x,
+					y,
+					w: area.w,
+					h: height,
+				},
+				&area,
+			);
+			y += height;
+		}
+	}
+
+	fn render_tape(&self, screen: &mut dyn Screen, area: &Rect) {
+		let lines = match &self.tape {
+			Some(tape) => tape.lines(self.context.format()),
+			None => Vec::new(),
+		};
+		let end = lines.len().saturating_sub(self.tape_scroll);
+		let start = end.saturating_sub(self.tape_visible_lines(screen));
+
+		let title = Layout::HorizontalCenter(Box::new(Layout::StaticText(
+			"Tape",
+			Font::Smallest,
+			TokenType::Label,
+		)));
+		let title_height = screen.metrics().height(Font::Smallest);
+		let line_height = screen.metrics().height(Font::Small);
+		let mut line_layouts = Vec::new();
+		for (marker, value) in &lines[start..end] {
+			let marker = Layout::Text(marker.clone(), Font::Small, TokenType::Keyword);
+			let value = Layout::Text(value.clone(), Font::Small, TokenType::Text);
+			let value_width = value.width(screen.metrics());
+			line_layouts.push((marker, value, value_width));
+		}
+
+		let mut renderer = screen.renderer(RenderMode::Normal);
+		renderer.erase(area);
+		title.render(
+			&mut renderer,
+			Rect {
+				x: area.x,
+				y: area.y,
+				w: area.w,
+				h: title_height,
+			},
+			area,
+		);
+		if lines.is_empty() {
+			let message = Layout::HorizontalCenter(Box::new(Layout::StaticText(
+				"No operations recorded",
+				Font::Small,
+				TokenType::Label,
+			)));
+			message.render(
+				&mut renderer,
+				Rect {
+					x: area.x,
+					y: area.y + title_height,
+					w: area.w,
+					h: line_height,
+				},
+				area,
+			);
+		}
+		for (idx, (marker, value, value_width)) in line_layouts.iter().enumerate() {
+			let y = area.y + title_height + idx as i32 * line_height;
+			marker.render(
+				&mut renderer,
+				Rect {
+					x: area.x + 4,
+					y,
+					w: area.w - 8,
+					h: line_height,
+				},
+				area,
+			);
+			value.render(
+				&mut renderer,
+				Rect {
+					x: area.x + area.w - 4 - value_width,
+					y,
+					w: *value_width,
+					h: line_height,
+				},
+				area,
+			);
+		}
+	}
+
+	/// Draws the auxiliary pane if its contents or position have changed
+	fn render_aux_pane(&mut self, pane: AuxPane, area: Rect, screen: &mut dyn Screen) {
+		let layout = pane_layout(pane, self, screen.metrics(), &area);
+		if let Some((prev_layout, prev_area)) = &self.pane_cache {
+			if !self.force_refresh && prev_area == &area && prev_layout == &layout {
+				return;
+			}
+		}
+		render_pane(pane, layout.as_ref(), self, screen, &area);
+		self.pane_cache = Some((layout, area));
+	}
+
 	pub fn update_header(&mut self, screen: &mut dyn Screen) {
 		if self.force_render_on_status_update {
 			self.render(screen);
-		} else if self.input_state != InputState::Menu {
+		} else if self.input_state != InputState::Menu && self.input_state != InputState::Graph {
 			// When specifically updating the header, always render the header
-			self.update_status_bar_state();
-			self.draw_status_bar(screen);
-			screen.refresh();
+			self.render_status_bar_frame(screen);
 		}
 	}
 
@@ -1335,22 +5204,117 @@ impl State {
 
 	pub fn show_menu(&mut self, menu: Menu) -> Result<()> {
 		self.end_edit()?;
+		if self.input_state != InputState::Menu {
+			self.start_animation(AnimationType::MenuSlideIn);
+		}
 		self.menus.push(menu);
 		self.input_state = InputState::Menu;
 		Ok(())
 	}
 
+	/// Closes the menu on top, going back to the one below it if there is one
+	/// Replaces the open menu with another, keeping the same item selected. Used
+	/// when the text of the open menu changes.
+	pub fn replace_menu(&mut self, mut menu: Menu) {
+		if let Some(old) = self.menus.pop() {
+			menu.set_selection(old.selection());
+		}
+		self.menus.push(menu);
+		self.input_state = InputState::Menu;
+		self.force_refresh = true;
+	}
+
+	pub fn close_menu(&mut self) {
+		self.menus.pop();
+		if let Some(menu) = self.menus.last_mut() {
+			menu.force_refresh();
+		} else {
+			self.input_state = InputState::Normal;
+			self.cached_status_bar_state.left_string = String::new();
+			self.force_refresh = true;
+		}
+	}
+
 	pub fn show_system_setup_menu(&mut self) {
-		#[cfg(feature = "dm42")]
-		show_system_setup_menu();
+		platform().show_system_menu();
 	}
 
 	pub fn wait_for_input<InputT: InputQueue>(&mut self, input: &mut InputT) -> Option<InputEvent> {
 		let prev_shift = self.input_mode.shift;
 		let result = input.wait(&mut self.input_mode);
-		if !self.status_bar_enabled && prev_shift != self.input_mode.shift {
+		if !self.settings.status_bar_enabled && prev_shift != self.input_mode.shift {
 			self.force_render_on_status_update = true;
 		}
+		if result.is_some() {
+			self.feedback(FeedbackEvent::KeyClick);
+		}
 		result
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::State;
+	use crate::dm42::functions::Function;
+	use crate::dm42::golden::RecordingScreen;
+	use crate::dm42::graph::ProgramStep;
+	use crate::dm42::input::InputEvent;
+	use rscalc_math::format::{DecimalPointMode, FormatMode};
+	use rscalc_math::unit::AngleUnit;
+
+	#[test]
+	fn number_format_is_restored_from_the_context() {
+		let screen = RecordingScreen::new(400, 240);
+		let mut state = State::new();
+		Function::AngleModeCycle
+			.execute(&mut state, &screen)
+			.unwrap();
+		Function::DecimalPointToggle
+			.execute(&mut state, &screen)
+			.unwrap();
+		Function::IntegerRadixCycle
+			.execute(&mut state, &screen)
+			.unwrap();
+		Function::DisplayModeCycle
+			.execute(&mut state, &screen)
+			.unwrap();
+		assert!(*state.context().angle_mode() == AngleUnit::Radians);
+		assert_eq!(
+			state.context().format().decimal_point,
+			DecimalPointMode::Comma
+		);
+
+		let restored = State::from_saved_state(&state.save_state().unwrap()).unwrap();
+		let format = restored.context().format();
+		assert!(*restored.context().angle_mode() == AngleUnit::Radians);
+		assert_eq!(format.decimal_point, DecimalPointMode::Comma);
+		assert_eq!(format.integer_radix, 16);
+		assert_eq!(format.mode, FormatMode::Fixed);
+	}
+
+	#[test]
+	fn program_menu_runs_the_chosen_label() {
+		let screen = RecordingScreen::new(400, 240);
+		let mut state = State::new();
+		state.recorded_program = Some(
+			[
+				ProgramStep::Function(Function::Label),
+				ProgramStep::Input(InputEvent::Character('A')),
+				ProgramStep::Input(InputEvent::Character('1')),
+				ProgramStep::Input(InputEvent::Character('2')),
+				ProgramStep::Function(Function::Label),
+				ProgramStep::Input(InputEvent::Character('B')),
+				ProgramStep::Input(InputEvent::Character('3')),
+			]
+			.to_vec(),
+		);
+		Function::ProgramMenu.execute(&mut state, &screen).unwrap();
+		assert!(state.function_keys().function(3).is_none());
+
+		let key = state.function_keys().function(2).unwrap();
+		assert_eq!(key.to_string(&state), "B");
+		key.execute(&mut state, &screen).unwrap();
+		state.handle_input(InputEvent::Enter, &screen).unwrap();
+		assert_eq!(state.context().stack().top().unwrap().to_string(), "3");
+	}
+}
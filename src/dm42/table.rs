@@ -0,0 +1,121 @@
+use crate::dm42::functions::Function;
+use crate::dm42::graph::ProgramStep;
+use crate::dm42::menu::{Menu, MenuItem, MenuItemFunction, MenuItemLayout};
+use crate::dm42::screen::Screen;
+use crate::dm42::state::State;
+use intel_dfp::Decimal;
+use rscalc_layout::font::Font;
+use rscalc_layout::layout::{Layout, TokenType};
+use rscalc_math::error::{Error, Result};
+use rscalc_math::number::Number;
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::boxed::Box;
+#[cfg(feature = "embedded")]
+use alloc::string::ToString;
+#[cfg(feature = "embedded")]
+use alloc::vec;
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Most rows in a table. A step that would need more is refused rather than
+/// running the program for a long time.
+pub const MAX_TABLE_ROWS: usize = 200;
+
+/// Values of a recorded program y=f(x) for x going from the start to the end of
+/// a range in equal steps
+pub struct FunctionTable {
+	rows: Vec<(Decimal, Option<Decimal>)>,
+}
+
+impl FunctionTable {
+	/// Runs the program for each x in the range. Values where the program fails
+	/// or doesn't give a number are left undefined.
+	pub fn new(
+		program: &[ProgramStep],
+		range: (&Decimal, &Decimal),
+		step: &Decimal,
+		evaluator: &mut State,
+		screen: &dyn Screen,
+	) -> Result<Self> {
+		let (start, end) = range;
+		if !step.is_finite() || step <= &Decimal::zero() {
+			return Err(Error::InvalidArgument);
+		}
+		let count = &(&(end - start) / step) + &1.into();
+		if count > (MAX_TABLE_ROWS as u64).into() {
+			return Err(Error::ValueOutOfRange);
+		}
+
+		let mut rows = Vec::new();
+		let mut row: u64 = 0;
+		loop {
+			let x = start + &(step * &row.into());
+			if &x > end {
+				break;
+			}
+			let y = match evaluator.run_program(
+				program,
+				Value::Number(Number::Decimal(x.clone())),
+				screen,
+			) {
+				Ok(Value::Number(result)) | Ok(Value::NumberWithUnit(result, _)) => {
+					Some(result.to_decimal().into_owned())
+				}
+				_ => None,
+			};
+			rows.push((x, y.filter(|y| y.is_finite())));
+			row += 1;
+		}
+		Ok(FunctionTable { rows })
+	}
+
+	/// Values of x and of the function, from the start of the range
+	pub fn rows(&self) -> &[(Decimal, Option<Decimal>)] {
+		&self.rows
+	}
+
+	/// Value of x and of the function for the row at `idx`
+	pub fn get(&self, idx: usize) -> Option<&(Decimal, Option<Decimal>)> {
+		self.rows.get(idx)
+	}
+}
+
+/// Menu with a row for each value of the table, showing x and f(x) in columns.
+/// Choosing a row pushes its value of f(x).
+pub fn function_table_menu(table: &FunctionTable) -> Menu {
+	let mut items = Vec::new();
+	for idx in 0..table.rows().len() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, screen| {
+				let (x, y) = match state.function_table().and_then(|table| table.get(idx)) {
+					Some(row) => row,
+					None => return MenuItem::static_string_layout(""),
+				};
+				let format = state.context().format();
+				let x = format
+					.format_number(&Number::Decimal(x.clone()))
+					.to_string();
+				let y = match y {
+					Some(y) => format
+						.format_number(&Number::Decimal(y.clone()))
+						.to_string(),
+					None => "undefined".to_string(),
+				};
+				// Line up the values of f(x) in a column halfway across the screen
+				let column = screen.width() / 2 - 8;
+				let space = core::cmp::max(8, column - screen.metrics().width(Font::Small, &x));
+				let columns = vec![
+					Layout::Text(x, Font::Small, TokenType::Text),
+					Layout::HorizontalSpace(space),
+					Layout::Text(y, Font::Small, TokenType::Text),
+				];
+				Layout::LeftAlign(Box::new(Layout::Horizontal(columns)))
+			})),
+			function: MenuItemFunction::Action(Function::TableValue(idx)),
+			help: None,
+		});
+	}
+	Menu::new("x   f(x)", items)
+}
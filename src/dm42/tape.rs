@@ -0,0 +1,82 @@
+use rscalc_math::format::Format;
+use rscalc_math::value::Value;
+
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "embedded")]
+use alloc::vec::Vec;
+
+/// Number of operations kept on the tape. When the tape is full the oldest
+/// operations are discarded.
+const MAX_TAPE_ENTRIES: usize = 500;
+
+/// Most operands that are recorded for a single operation
+pub const MAX_TAPE_OPERANDS: usize = 3;
+
+/// File that the tape is exported to
+pub const TAPE_FILE_NAME: &str = "tape.txt";
+
+/// An operation on the tape along with the values it took from the stack and
+/// the value it left on top of the stack
+pub struct TapeEntry {
+	pub operation: String,
+	pub operands: Vec<Value>,
+	pub result: Option<Value>,
+}
+
+/// History of the operations of the session, like the paper tape of a printing
+/// calculator. Operations can only be added to the tape.
+pub struct Tape {
+	entries: Vec<TapeEntry>,
+}
+
+impl Tape {
+	pub fn new() -> Self {
+		Tape {
+			entries: Vec::new(),
+		}
+	}
+
+	pub fn push(&mut self, entry: TapeEntry) {
+		if self.entries.len() >= MAX_TAPE_ENTRIES {
+			self.entries.remove(0);
+		}
+		self.entries.push(entry);
+	}
+
+	/// Lines of the tape as a marker on the left and a value on the right. The
+	/// operands of an operation are on their own lines, followed by a line with
+	/// the operation and its result.
+	pub fn lines(&self, format: &Format) -> Vec<(String, String)> {
+		let mut lines = Vec::new();
+		for entry in &self.entries {
+			for operand in &entry.operands {
+				lines.push((String::new(), operand.format(format).to_string()));
+			}
+			let result = match &entry.result {
+				Some(result) => "= ".to_string() + &result.format(format).to_string(),
+				None => String::new(),
+			};
+			lines.push((entry.operation.clone(), result));
+		}
+		lines
+	}
+
+	/// Text of the tape for export, with a blank line after each operation
+	pub fn to_text(&self, format: &Format) -> String {
+		let mut text = String::new();
+		for entry in &self.entries {
+			for operand in &entry.operands {
+				text += &operand.format(format).to_string();
+				text.push('\n');
+			}
+			text += &entry.operation;
+			if let Some(result) = &entry.result {
+				text += " = ";
+				text += &result.format(format).to_string();
+			}
+			text += "\n\n";
+		}
+		text
+	}
+}
@@ -0,0 +1,47 @@
+use crate::dm42::locale::tr;
+use rscalc_math::error::Error;
+
+#[cfg(feature = "embedded")]
+use alloc::string::{String, ToString};
+
+/// Time that a message is shown for before it is dismissed by itself
+pub const TOAST_TIME_MS: u32 = 3000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+	/// Confirms that something happened
+	Message,
+	Error,
+}
+
+/// Transient message shown over the bottom of the stack. The key press that
+/// dismisses it is not handled otherwise, so that keys typed before the message
+/// was seen do not act on an unexpected state.
+pub struct Toast {
+	text: String,
+	kind: ToastKind,
+}
+
+impl Toast {
+	pub fn message(text: &str) -> Self {
+		Toast {
+			text: tr(text).to_string(),
+			kind: ToastKind::Message,
+		}
+	}
+
+	pub fn error(error: Error) -> Self {
+		Toast {
+			text: tr(error.to_str()).to_string(),
+			kind: ToastKind::Error,
+		}
+	}
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	pub fn kind(&self) -> ToastKind {
+		self.kind
+	}
+}
@@ -0,0 +1,268 @@
+#[cfg(not(feature = "embedded"))]
+use rscalc_math::error::{Error, Result};
+
+#[cfg(feature = "embedded")]
+use alloc::string::String;
+
+/// Saved state is transferred over a serial link as lines of ASCII text. The
+/// transfer starts with a header line giving the total length in hex:
+///
+/// `#RSCALC-STATE 0000012c`
+///
+/// Followed by data lines of up to 32 bytes each. A data line is a colon, the
+/// offset of the data as 8 hex digits, the data bytes, and a checksum byte, with
+/// bytes written as 2 hex digits. The checksum is chosen so that the sum of all
+/// the bytes on the line, including the offset, is zero.
+///
+/// `:000000200102dd`
+///
+/// The transfer ends with the line `#END`. Any text before the header is ignored,
+/// so that noise on the link does not prevent a transfer.
+const TRANSFER_HEADER: &str = "#RSCALC-STATE ";
+const TRANSFER_END: &str = "#END";
+const TRANSFER_LINE_BYTES: usize = 32;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn push_hex(text: &mut String, value: u32, digits: usize) {
+	for i in (0..digits).rev() {
+		text.push(HEX_DIGITS[((value >> (i * 4)) & 0xf) as usize] as char);
+	}
+}
+
+#[cfg(not(feature = "embedded"))]
+fn parse_hex(text: &str) -> Result<u32> {
+	if text.is_empty() || text.len() > 8 {
+		return Err(Error::CorruptData);
+	}
+	let mut result = 0;
+	for ch in text.chars() {
+		result = (result << 4) | ch.to_digit(16).ok_or(Error::CorruptData)?;
+	}
+	Ok(result)
+}
+
+/// Encodes saved state for sending over a serial link
+pub fn encode_transfer(data: &[u8]) -> String {
+	let mut text = String::new();
+	text.push_str(TRANSFER_HEADER);
+	push_hex(&mut text, data.len() as u32, 8);
+	text.push_str("\r\n");
+
+	for (idx, chunk) in data.chunks(TRANSFER_LINE_BYTES).enumerate() {
+		let offset = (idx * TRANSFER_LINE_BYTES) as u32;
+		text.push(':');
+		push_hex(&mut text, offset, 8);
+		let mut sum = offset
+			.to_le_bytes()
+			.iter()
+			.fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+		for byte in chunk {
+			push_hex(&mut text, *byte as u32, 2);
+			sum = sum.wrapping_add(*byte);
+		}
+		push_hex(&mut text, sum.wrapping_neg() as u32, 2);
+		text.push_str("\r\n");
+	}
+
+	text.push_str(TRANSFER_END);
+	text.push_str("\r\n");
+	text
+}
+
+/// Longest line that is kept while waiting for its end. Data lines are much
+/// shorter, so a longer line is noise before the header or a damaged transfer.
+#[cfg(not(feature = "embedded"))]
+const MAX_LINE_LEN: usize = 128;
+
+/// Decodes saved state as it is received over a serial link. Text is given to
+/// the decoder as it is read, which may end partway through a line, so each
+/// line is only decoded once its end has been received.
+#[cfg(not(feature = "embedded"))]
+pub struct TransferDecoder {
+	/// Text of the line whose end has not been received
+	line: Vec<u8>,
+	/// Length of the saved state, once the header has been received
+	len: Option<usize>,
+	data: Vec<u8>,
+}
+
+#[cfg(not(feature = "embedded"))]
+impl TransferDecoder {
+	pub fn new() -> Self {
+		TransferDecoder {
+			line: Vec::new(),
+			len: None,
+			data: Vec::new(),
+		}
+	}
+
+	/// Decodes the lines ended by the received text. Returns the saved state
+	/// once the end of the transfer has been received, or `None` if the
+	/// receiver should wait for more text.
+	pub fn push(&mut self, text: &[u8]) -> Result<Option<Vec<u8>>> {
+		for byte in text {
+			if *byte != b'\n' {
+				if self.line.len() >= MAX_LINE_LEN {
+					if self.len.is_some() {
+						return Err(Error::CorruptData);
+					}
+					self.line.clear();
+				}
+				self.line.push(*byte);
+				continue;
+			}
+
+			let line = core::mem::take(&mut self.line);
+			if let Some(data) = self.decode_line(&line)? {
+				return Ok(Some(data));
+			}
+		}
+		Ok(None)
+	}
+
+	fn decode_line(&mut self, line: &[u8]) -> Result<Option<Vec<u8>>> {
+		let len = match (self.len, core::str::from_utf8(line)) {
+			(Some(len), Ok(_)) => len,
+			(Some(_), Err(_)) => return Err(Error::CorruptData),
+			(None, Ok(line)) => {
+				// Anything before the header is ignored
+				let line = line.trim();
+				if let Some(len) = line.strip_prefix(TRANSFER_HEADER) {
+					self.len = Some(parse_hex(len)? as usize);
+				}
+				return Ok(None);
+			}
+			(None, Err(_)) => return Ok(None),
+		};
+
+		let line = core::str::from_utf8(line)
+			.map_err(|_| Error::CorruptData)?
+			.trim();
+		if line == TRANSFER_END {
+			if self.data.len() != len {
+				return Err(Error::CorruptData);
+			}
+			return Ok(Some(core::mem::take(&mut self.data)));
+		}
+		if line.is_empty() {
+			return Ok(None);
+		}
+		if !line.starts_with(':') || !line.is_ascii() || line.len() < 11 || line.len() % 2 == 0 {
+			return Err(Error::CorruptData);
+		}
+
+		// Data must arrive in order without gaps
+		let offset = parse_hex(&line[1..9])?;
+		if offset as usize != self.data.len() {
+			return Err(Error::CorruptData);
+		}
+		let mut sum = offset
+			.to_le_bytes()
+			.iter()
+			.fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+		let mut bytes = Vec::new();
+		for idx in (9..line.len()).step_by(2) {
+			let byte = parse_hex(&line[idx..idx + 2])? as u8;
+			sum = sum.wrapping_add(byte);
+			bytes.push(byte);
+		}
+		if sum != 0 {
+			return Err(Error::CorruptData);
+		}
+
+		// The last byte is the checksum
+		bytes.pop();
+		if self.data.len() + bytes.len() > len {
+			return Err(Error::CorruptData);
+		}
+		self.data.extend_from_slice(&bytes);
+		Ok(None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn saved_state(len: usize) -> Vec<u8> {
+		(0..len).map(|idx| (idx * 31 + 7) as u8).collect()
+	}
+
+	/// Feeds the text to a decoder in chunks of the given size, as reads from a
+	/// serial port return it
+	fn decode_in_chunks(text: &[u8], chunk: usize) -> Result<Option<Vec<u8>>> {
+		let mut decoder = TransferDecoder::new();
+		for part in text.chunks(chunk) {
+			if let Some(data) = decoder.push(part)? {
+				return Ok(Some(data));
+			}
+		}
+		Ok(None)
+	}
+
+	#[test]
+	fn transfer_round_trips_in_any_chunks() {
+		for len in [0, 1, 31, 32, 33, 1000].iter() {
+			let data = saved_state(*len);
+			let text = encode_transfer(&data);
+			for chunk in [1, 2, 7, 13, 64, 256, text.len()].iter() {
+				assert_eq!(
+					decode_in_chunks(text.as_bytes(), *chunk),
+					Ok(Some(data.clone())),
+					"{} bytes in chunks of {}",
+					len,
+					chunk
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn unfinished_transfer_waits_for_more() {
+		let text = encode_transfer(&saved_state(500));
+		let mut decoder = TransferDecoder::new();
+		// Ends partway through a data line
+		let split = text.len() / 2 + 5;
+		assert_eq!(decoder.push(&text.as_bytes()[..split]), Ok(None));
+		assert_eq!(
+			decoder.push(&text.as_bytes()[split..]),
+			Ok(Some(saved_state(500)))
+		);
+	}
+
+	#[test]
+	fn noise_before_header_is_ignored() {
+		let mut text = b"\x00\xff garbage\r\n#RSCALC-STAT\n".to_vec();
+		text.extend_from_slice(&[b'x'; 300]);
+		text.extend_from_slice(b"\r\n");
+		text.extend_from_slice(encode_transfer(&saved_state(40)).as_bytes());
+		assert_eq!(decode_in_chunks(&text, 256), Ok(Some(saved_state(40))));
+	}
+
+	#[test]
+	fn damaged_transfer_is_refused() {
+		let text = encode_transfer(&saved_state(100));
+
+		// A changed data byte makes the checksum of its line wrong
+		let mut damaged = text.clone().into_bytes();
+		let byte = text.find(":00000020").unwrap() + 10;
+		damaged[byte] = if damaged[byte] == b'0' { b'1' } else { b'0' };
+		assert_eq!(decode_in_chunks(&damaged, 256), Err(Error::CorruptData));
+
+		// A missing line leaves a gap in the data
+		let mut lines: Vec<&str> = text.split("\r\n").collect();
+		lines.remove(2);
+		assert_eq!(
+			decode_in_chunks(lines.join("\r\n").as_bytes(), 256),
+			Err(Error::CorruptData)
+		);
+
+		// The end can't come before all of the data
+		let short = text.replacen(":00000060", "#END\r\n:00000060", 1);
+		assert_eq!(
+			decode_in_chunks(short.as_bytes(), 256),
+			Err(Error::CorruptData)
+		);
+	}
+}
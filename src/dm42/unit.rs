@@ -8,11 +8,11 @@ use rscalc_layout::value::ValueLayout;
 use rscalc_math::functions::StackFunction;
 use rscalc_math::unit::{Unit, UnitType};
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::boxed::Box;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::string::ToString;
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 use alloc::vec::Vec;
 
 fn value_layout() -> Box<dyn Fn(&State, &dyn Screen) -> Layout> {
@@ -34,6 +34,30 @@ fn value_layout() -> Box<dyn Fn(&State, &dyn Screen) -> Layout> {
 	})
 }
 
+pub fn preferred_units_menu(state: &State) -> Menu {
+	let mut items = Vec::new();
+	for i in 0..state.context().preferred_units().len() {
+		items.push(MenuItem {
+			layout: MenuItemLayout::Dynamic(Box::new(move |state, _screen| {
+				if let Some(preferred) = state.context().preferred_units().get(i) {
+					MenuItem::string_layout(
+						preferred.unit_type().to_str().to_string()
+							+ "   [" + match preferred.target() {
+							Some(unit) => unit.to_str(),
+							None => "Off",
+						} + "]",
+					)
+				} else {
+					MenuItem::static_string_layout("")
+				}
+			})),
+			function: MenuItemFunction::InMenuAction(Function::PreferredUnitCycle(i)),
+			help: None,
+		});
+	}
+	Menu::new("Preferred Units", items)
+}
+
 pub fn unit_menu() -> Menu {
 	let mut items = Vec::new();
 	for item in &[
@@ -55,8 +79,14 @@ pub fn unit_menu() -> Menu {
 				Function::UnitMenu(item.1),
 				Function::Stack(StackFunction::ClearUnits),
 			),
+			help: None,
 		});
 	}
+	items.push(MenuItem {
+		layout: MenuItemLayout::Static(MenuItem::static_string_layout("Currency")),
+		function: MenuItemFunction::Action(Function::CurrencyMenu),
+		help: Some("Pick the currency of x, then the currency to convert it to"),
+	});
 	let mut menu = Menu::new_with_bottom("Units", items, value_layout());
 	menu.set_columns(3);
 	menu
@@ -77,10 +107,12 @@ pub fn unit_menu_of_type(unit_type: UnitType) -> Menu {
 						unit.to_str(),
 					)),
 					function,
+					help: None,
 				}),
 				_ => items.push(MenuItem {
 					layout: MenuItemLayout::Static(MenuItem::static_string_layout(unit.to_str())),
 					function,
+					help: None,
 				}),
 			}
 		} else {
@@ -95,6 +127,7 @@ pub fn unit_menu_of_type(unit_type: UnitType) -> Menu {
 							Function::Stack(StackFunction::AddInvUnitSquared(*unit)),
 							Function::Stack(StackFunction::ConvertToUnit(*unit)),
 						),
+						help: None,
 					});
 				}
 				UnitType::Volume => {
@@ -107,6 +140,7 @@ pub fn unit_menu_of_type(unit_type: UnitType) -> Menu {
 							Function::Stack(StackFunction::AddInvUnitCubed(*unit)),
 							Function::Stack(StackFunction::ConvertToUnit(*unit)),
 						),
+						help: None,
 					});
 				}
 				_ => unreachable!(),
@@ -142,6 +176,7 @@ pub fn unit_catalog_menu(title: &str, func: &dyn Fn(UnitType) -> Function) -> Me
 		items.push(MenuItem {
 			layout: MenuItemLayout::Static(MenuItem::static_string_layout(item.0)),
 			function: MenuItemFunction::InMenuAction(func(item.1)),
+			help: None,
 		});
 	}
 	let mut menu = Menu::new(title, items);
@@ -166,12 +201,14 @@ pub fn unit_catalog_menu_of_type(
 						prefix.to_string() + unit.to_str(),
 					)),
 					function,
+					help: None,
 				}),
 				_ => items.push(MenuItem {
 					layout: MenuItemLayout::Static(MenuItem::string_layout(
 						prefix.to_string() + unit.to_str(),
 					)),
 					function,
+					help: None,
 				}),
 			}
 		} else {
@@ -182,6 +219,7 @@ pub fn unit_catalog_menu_of_type(
 							prefix.to_string() + unit.to_str() + "²",
 						)),
 						function: MenuItemFunction::Action(squared_func(*unit)),
+						help: None,
 					});
 				}
 				UnitType::Volume => {
@@ -190,6 +228,7 @@ pub fn unit_catalog_menu_of_type(
 							prefix.to_string() + unit.to_str() + "³",
 						)),
 						function: MenuItemFunction::Action(cubed_func(*unit)),
+						help: None,
 					});
 				}
 				_ => unreachable!(),
@@ -0,0 +1,434 @@
+use crate::dm42::alarm::ALARM_RING_MS;
+use crate::dm42::animation::FRAME_TIME_MS;
+use crate::dm42::files::open_file_store;
+use crate::dm42::font::load_custom_fonts;
+use crate::dm42::input::{InputQueue, Key, KeyEvent, Keyboard, KeyboardDriver, RawKeyEvent};
+use crate::dm42::save::{read_saved_state, AutoSave};
+use crate::dm42::screen::{RenderMode, Screen, ScreenLayoutRenderer};
+use crate::dm42::state::{InputResult, State};
+use crate::dm42::toast::TOAST_TIME_MS;
+use rscalc_layout::layout::Rect;
+use rscalc_math::error::Error;
+
+const WIDTH: i32 = 400;
+const HEIGHT: i32 = 240;
+
+/// Time between updates of the clock in the status bar
+const HEADER_UPDATE_MS: u32 = 1000;
+
+/// Keys in the order of their codes in the page. The keypad of the page sends the
+/// index of the key that was clicked or touched.
+const KEYS: [Key; 43] = [
+	Key::Sigma,
+	Key::Recip,
+	Key::Sqrt,
+	Key::Log,
+	Key::Ln,
+	Key::Xeq,
+	Key::Sto,
+	Key::Rcl,
+	Key::RotateDown,
+	Key::Sin,
+	Key::Cos,
+	Key::Tan,
+	Key::Enter,
+	Key::Swap,
+	Key::Neg,
+	Key::E,
+	Key::Backspace,
+	Key::Up,
+	Key::Seven,
+	Key::Eight,
+	Key::Nine,
+	Key::Div,
+	Key::Down,
+	Key::Four,
+	Key::Five,
+	Key::Six,
+	Key::Mul,
+	Key::Shift,
+	Key::One,
+	Key::Two,
+	Key::Three,
+	Key::Sub,
+	Key::Exit,
+	Key::Zero,
+	Key::Dot,
+	Key::Run,
+	Key::Add,
+	Key::F1,
+	Key::F2,
+	Key::F3,
+	Key::F4,
+	Key::F5,
+	Key::F6,
+];
+
+/// Screen drawn into an RGBA buffer that the page copies to a canvas
+struct CanvasScreen {
+	pixels: Vec<u8>,
+	updated: bool,
+}
+
+impl CanvasScreen {
+	fn new() -> Self {
+		CanvasScreen {
+			pixels: vec![0xff; (WIDTH * HEIGHT * 4) as usize],
+			updated: true,
+		}
+	}
+
+	fn set_intensity(&mut self, x: i32, y: i32, intensity: u8) {
+		if !(0..WIDTH).contains(&x) || !(0..HEIGHT).contains(&y) {
+			return;
+		}
+		let offset = ((y * WIDTH + x) * 4) as usize;
+		let value = 255 - intensity;
+		self.pixels[offset] = value;
+		self.pixels[offset + 1] = value;
+		self.pixels[offset + 2] = value;
+	}
+
+	fn intensity(&self, x: i32, y: i32) -> u8 {
+		if !(0..WIDTH).contains(&x) || !(0..HEIGHT).contains(&y) {
+			return 0;
+		}
+		255 - self.pixels[((y * WIDTH + x) * 4) as usize]
+	}
+}
+
+impl Screen for CanvasScreen {
+	fn width(&self) -> i32 {
+		WIDTH
+	}
+
+	fn height(&self) -> i32 {
+		HEIGHT
+	}
+
+	fn clear(&mut self) {
+		for pixel in self.pixels.iter_mut() {
+			*pixel = 0xff;
+		}
+	}
+
+	fn refresh(&mut self) {
+		self.updated = true;
+	}
+
+	fn fill(&mut self, rect: &Rect, color: bool) {
+		let rect = rect.clipped_to(&self.screen_rect());
+		for y in rect.y..rect.y + rect.h {
+			for x in rect.x..rect.x + rect.w {
+				self.set_intensity(x, y, if color { 255 } else { 0 });
+			}
+		}
+	}
+
+	fn draw_bits(&mut self, x: i32, y: i32, bits: u32, width: u8, color: bool) {
+		for i in 0..width {
+			if bits & (1 << ((width - 1) - i)) != 0 {
+				self.set_intensity(x + i as i32, y, if color { 255 } else { 0 });
+			}
+		}
+	}
+
+	fn renderer(&mut self, render_mode: RenderMode) -> ScreenLayoutRenderer<'_> {
+		ScreenLayoutRenderer::new(self, render_mode)
+	}
+
+	fn supports_animation(&self) -> bool {
+		true
+	}
+
+	fn supports_grayscale(&self) -> bool {
+		true
+	}
+
+	fn blend_pixel(&mut self, x: i32, y: i32, color: bool, alpha: u8) {
+		let current = self.intensity(x, y) as u32;
+		let target = if color { 255 } else { 0 };
+		let alpha = alpha as u32;
+		let blended = (current * (255 - alpha) + target * alpha) / 255;
+		self.set_intensity(x, y, blended as u8);
+	}
+}
+
+/// Keys pressed on the page that have not been handled yet. The page never
+/// blocks, so waiting for input returns immediately when there is none.
+struct PageKeyboard {
+	queue: Vec<KeyEvent>,
+}
+
+impl KeyboardDriver for PageKeyboard {
+	fn has_event(&self) -> bool {
+		!self.queue.is_empty()
+	}
+
+	fn poll_event(&mut self) -> Option<RawKeyEvent> {
+		if !self.queue.is_empty() {
+			Some(RawKeyEvent::from_key_event(self.queue.remove(0)))
+		} else {
+			None
+		}
+	}
+
+	fn wait_event(&mut self) -> Option<RawKeyEvent> {
+		self.poll_event()
+	}
+
+	#[cfg(any(feature = "embedded", feature = "gtk", feature = "desktop"))]
+	fn suspend(&self) {}
+}
+
+/// Calculator driven by calls from the page. A browser can't block waiting for
+/// input, so instead of running the main loop, each key press and each frame
+/// timer tick is handled as it arrives.
+struct PageCalculator {
+	state: State,
+	screen: CanvasScreen,
+	input: Keyboard<PageKeyboard>,
+	/// Time the current message has been shown
+	toast_time: Option<u32>,
+	/// Time the alarm that has gone off has been shown
+	alarm_time: Option<u32>,
+	header_time: u32,
+	auto_save: AutoSave,
+	/// Text passed between the page and the calculator for the clipboard
+	text: Vec<u8>,
+}
+
+impl PageCalculator {
+	fn new() -> Self {
+		load_custom_fonts(&*open_file_store());
+		let mut screen = CanvasScreen::new();
+		screen.clear_to_background();
+		let mut state = match read_saved_state() {
+			Some(data) => State::from_saved_state(&data).unwrap_or_else(|_| State::new()),
+			None => State::new(),
+		};
+		state.set_animation_supported(screen.supports_animation());
+		if let Err(error) = state.start_up(&screen) {
+			state.show_error(error);
+		}
+		state.render(&mut screen);
+		let auto_save = AutoSave::new(&state);
+		PageCalculator {
+			state,
+			screen,
+			input: Keyboard::new(PageKeyboard { queue: Vec::new() }),
+			toast_time: None,
+			alarm_time: None,
+			header_time: 0,
+			auto_save,
+			text: Vec::new(),
+		}
+	}
+
+	/// Handles a press of a key, or a tap on the screen, which is handled in the
+	/// same way
+	fn press(&mut self, event: KeyEvent) {
+		// A key press dismisses an alarm that has gone off
+		if self.alarm_time.is_some() {
+			self.dismiss_alarm();
+			return;
+		}
+
+		// A key press dismisses a message and is otherwise ignored
+		if self.toast_time.is_some() {
+			self.toast_time = None;
+			self.state.hide_toast();
+			self.state.render(&mut self.screen);
+			return;
+		}
+
+		if self.state.animating() {
+			self.state.finish_animation();
+			self.state.render(&mut self.screen);
+		}
+
+		let queue = &mut self.input.driver_mut().queue;
+		queue.push(event);
+		if let KeyEvent::Press(_) = event {
+			queue.push(KeyEvent::Release);
+		}
+		while self.input.has_input() && self.toast_time.is_none() {
+			let input_event = match self.state.wait_for_input(&mut self.input) {
+				Some(input_event) => input_event,
+				None => continue,
+			};
+			if self.state.input_may_be_slow(input_event) {
+				self.state.show_busy_indicator(&mut self.screen);
+			}
+			match self.state.handle_input(input_event, &self.screen) {
+				Ok(InputResult::Normal) => (),
+				Ok(InputResult::Suspend) => {
+					self.auto_save.save(&self.state);
+				}
+				Err(error) => self.state.show_error(error),
+			}
+			if self.state.toast().is_some() {
+				self.toast_time = Some(0);
+			}
+			self.state.render(&mut self.screen);
+		}
+		self.input.driver_mut().queue.clear();
+
+		// Keys such as shift only change the status bar
+		self.state.update_header(&mut self.screen);
+	}
+
+	/// Enters the number in the text buffer, which the page fills with the text
+	/// pasted into it
+	fn paste(&mut self) {
+		if self.toast_time.is_some() {
+			self.toast_time = None;
+			self.state.hide_toast();
+		}
+		let result = match core::str::from_utf8(&self.text) {
+			Ok(text) => self.state.paste_text(text, &self.screen),
+			Err(_) => Err(Error::InvalidEntry),
+		};
+		if let Err(error) = result {
+			self.state.show_error(error);
+		}
+		if self.state.toast().is_some() {
+			self.toast_time = Some(0);
+		}
+		self.state.render(&mut self.screen);
+		self.state.update_header(&mut self.screen);
+	}
+
+	fn dismiss_alarm(&mut self) {
+		self.alarm_time = None;
+		self.state.hide_alarm();
+		self.state.render(&mut self.screen);
+	}
+
+	fn tick(&mut self) {
+		if let Some(time) = self.alarm_time {
+			let time = time + FRAME_TIME_MS;
+			if time >= ALARM_RING_MS {
+				self.dismiss_alarm();
+			} else {
+				self.alarm_time = Some(time);
+			}
+			return;
+		}
+
+		if let Some(time) = self.toast_time {
+			let time = time + FRAME_TIME_MS;
+			if self.state.animating() {
+				self.state.tick(&mut self.screen);
+			}
+			if time >= TOAST_TIME_MS {
+				self.toast_time = None;
+				self.state.hide_toast();
+				self.state.render(&mut self.screen);
+			} else {
+				self.toast_time = Some(time);
+			}
+			return;
+		}
+
+		if self.state.animating() {
+			self.state.tick(&mut self.screen);
+			return;
+		}
+
+		self.header_time += FRAME_TIME_MS;
+		if self.header_time >= HEADER_UPDATE_MS {
+			self.header_time = 0;
+			self.state.update_header(&mut self.screen);
+			self.auto_save.update(&self.state);
+			if let Some(alarm) = self.state.take_due_alarm() {
+				self.state.show_alarm(alarm);
+				self.state.render(&mut self.screen);
+				self.alarm_time = Some(0);
+			}
+		}
+	}
+}
+
+// The page runs the calculator on its only thread, so the calculator is only
+// accessed from the calls below and never concurrently
+static mut CALCULATOR: Option<PageCalculator> = None;
+
+fn calculator() -> &'static mut PageCalculator {
+	unsafe { (*core::ptr::addr_of_mut!(CALCULATOR)).get_or_insert_with(PageCalculator::new) }
+}
+
+/// Starts the calculator and draws the first frame
+#[no_mangle]
+pub extern "C" fn rscalc_start() {
+	calculator();
+}
+
+/// Handles a press of the key with the given index in `KEYS`
+#[no_mangle]
+pub extern "C" fn rscalc_key(code: u32) {
+	if let Some(key) = KEYS.get(code as usize) {
+		calculator().press(KeyEvent::Press(*key));
+	}
+}
+
+/// Handles a tap or click on the canvas at a point in screen pixels
+#[no_mangle]
+pub extern "C" fn rscalc_touch(x: i32, y: i32) {
+	if x >= 0 && y >= 0 && x < WIDTH && y < HEIGHT {
+		calculator().press(KeyEvent::Touch(x, y));
+	}
+}
+
+/// Advances animations and timers. The page calls this every `FRAME_TIME_MS`.
+#[no_mangle]
+pub extern "C" fn rscalc_tick() {
+	calculator().tick();
+}
+
+/// Returns true if a new frame was drawn since the last call
+#[no_mangle]
+pub extern "C" fn rscalc_frame_updated() -> bool {
+	let screen = &mut calculator().screen;
+	let updated = screen.updated;
+	screen.updated = false;
+	updated
+}
+
+/// Places the value in x in the text buffer for copying to the clipboard, and
+/// returns its length in bytes. The length is zero if there is no value.
+#[no_mangle]
+pub extern "C" fn rscalc_copy() -> u32 {
+	let calculator = calculator();
+	calculator.text = match calculator.state.x_text() {
+		Ok(text) => text.into_bytes(),
+		Err(_) => Vec::new(),
+	};
+	calculator.text.len() as u32
+}
+
+/// Resizes the text buffer so that the page can write pasted text into it
+#[no_mangle]
+pub extern "C" fn rscalc_text_buffer(len: u32) -> *mut u8 {
+	let text = &mut calculator().text;
+	text.resize(len as usize, 0);
+	text.as_mut_ptr()
+}
+
+/// Address of the text buffer in the memory of the module
+#[no_mangle]
+pub extern "C" fn rscalc_text() -> *const u8 {
+	calculator().text.as_ptr()
+}
+
+/// Enters the number in the text buffer as if it was typed
+#[no_mangle]
+pub extern "C" fn rscalc_paste() {
+	calculator().paste();
+}
+
+/// Address of the RGBA pixels of the screen in the memory of the module
+#[no_mangle]
+pub extern "C" fn rscalc_frame() -> *const u8 {
+	calculator().screen.pixels.as_ptr()
+}
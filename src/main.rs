@@ -2,25 +2,56 @@
 #![feature(assoc_char_funcs)]
 #![feature(c_variadic)]
 #![feature(slice_fill)]
-#![cfg_attr(feature = "dm42", no_std)]
-#![cfg_attr(feature = "dm42", no_main)]
+#![cfg_attr(feature = "embedded", no_std)]
+#![cfg_attr(feature = "embedded", no_main)]
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 extern crate alloc;
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 #[macro_use]
 extern crate lazy_static;
 
-#[cfg(feature = "dm42")]
+#[cfg(feature = "embedded")]
 mod dm42;
 #[cfg(feature = "simulated")]
 mod dm42;
 
 fn main() {
-    #[cfg(feature = "dm42")]
-    dm42::device::program_main();
+	#[cfg(feature = "dm42")]
+	dm42::device::program_main();
 
-    #[cfg(feature = "simulated")]
-    dm42::simulated::App::run();
+	// The terminal mode runs the calculator without a window
+	#[cfg(feature = "simulated")]
+	if std::env::args().nth(1).as_deref() == Some("--repl") {
+		dm42::repl::run();
+		return;
+	}
+
+	// Compares renders against the golden images, or updates them
+	#[cfg(feature = "simulated")]
+	match std::env::args().nth(1).as_deref() {
+		Some("--golden") => std::process::exit(if dm42::golden::run(false) { 0 } else { 1 }),
+		Some("--update-golden") => {
+			dm42::golden::run(true);
+			std::process::exit(0)
+		}
+		// Checks arithmetic properties and presses random keys, optionally
+		// starting from a seed printed by an earlier failure
+		Some("--fuzz") => {
+			let seed = std::env::args().nth(2).and_then(|seed| seed.parse().ok());
+			std::process::exit(if dm42::fuzz::run(seed.unwrap_or(1)) {
+				0
+			} else {
+				1
+			})
+		}
+		_ => (),
+	}
+
+	#[cfg(feature = "desktop")]
+	dm42::desktop::run();
+
+	#[cfg(all(feature = "gtk", not(feature = "desktop")))]
+	dm42::simulated::App::run();
 }
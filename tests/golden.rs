@@ -0,0 +1,19 @@
+//! Runs the golden image checks of the simulator. They run in a process of
+//! their own, as some scenes show how much memory is in use, which would
+//! change with the tests running beside them.
+#![cfg(feature = "simulated")]
+
+use std::process::Command;
+
+#[test]
+fn renders_match_golden_images() {
+	let output = Command::new(env!("CARGO_BIN_EXE_rscalc"))
+		.arg("--golden")
+		.output()
+		.expect("failed to run the simulator");
+	assert!(
+		output.status.success(),
+		"renders differ from the golden images:\n{}",
+		String::from_utf8_lossy(&output.stdout)
+	);
+}
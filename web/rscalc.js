@@ -0,0 +1,139 @@
+// Keys of the on-screen keypad. The code of a key is its index in the list of
+// keys in src/dm42/web.rs, and the class sets its width in the keypad.
+const KEYPAD = [
+	[["F1", 37, "function"], ["F2", 38, "function"], ["F3", 39, "function"],
+		["F4", 40, "function"], ["F5", 41, "function"], ["F6", 42, "function"]],
+	[["Σ+", 0], ["1/x", 1], ["√x", 2], ["LOG", 3], ["LN", 4], ["XEQ", 5]],
+	[["STO", 6], ["RCL", 7], ["R↓", 8], ["SIN", 9], ["COS", 10], ["TAN", 11]],
+	[["ENTER", 12, "wide"], ["x≷y", 13], ["+/-", 14], ["E", 15], ["←", 16]],
+	[["▲", 17, "row"], ["7", 18, "row"], ["8", 19, "row"], ["9", 20, "row"], ["÷", 21, "row"]],
+	[["▼", 22, "row"], ["4", 23, "row"], ["5", 24, "row"], ["6", 25, "row"], ["×", 26, "row"]],
+	[["SHIFT", 27, "row shift"], ["1", 28, "row"], ["2", 29, "row"], ["3", 30, "row"],
+		["-", 31, "row"]],
+	[["EXIT", 32, "row"], ["0", 33, "row"], [".", 34, "row"], ["R/S", 35, "row"],
+		["+", 36, "row"]],
+];
+
+// Keys of the desktop keyboard, mapped as in the minifb version. Letters press
+// the key that types them in alpha mode.
+const KEYBOARD = {
+	"0": 33, "1": 28, "2": 29, "3": 30, "4": 23, "5": 24, "6": 25, "7": 18,
+	"8": 19, "9": 20, ".": 34, "+": 36, "-": 31, "*": 26, "/": 21,
+	"Enter": 12, "Backspace": 16, "Escape": 32, "Tab": 27, "ArrowUp": 17,
+	"ArrowDown": 22, "F1": 37, "F2": 38, "F3": 39, "F4": 40, "F5": 41,
+	"F6": 42, " ": 35,
+	"a": 0, "b": 1, "c": 2, "d": 3, "e": 4, "f": 5, "g": 6, "h": 7, "i": 8,
+	"j": 9, "k": 10, "l": 11, "m": 13, "n": 14, "o": 15, "p": 18, "q": 19,
+	"r": 20, "s": 21, "t": 23, "u": 24, "v": 25, "w": 26, "x": 28, "y": 29,
+	"z": 30,
+};
+
+const WIDTH = 400;
+const HEIGHT = 240;
+const FRAME_TIME_MS = 33;
+const DISPLAY_LEVEL_MAX = 10;
+
+async function start() {
+	const imports = {
+		env: {
+			// Local time in milliseconds since the epoch
+			rscalc_local_time_ms: () =>
+				Date.now() - new Date().getTimezoneOffset() * 60 * 1000,
+			// Levels go up to DISPLAY_LEVEL_MAX. The middle contrast leaves the
+			// image as it is, and the lowest brightness still shows it faintly.
+			rscalc_set_display_levels: (brightness, contrast) => {
+				const screen = document.getElementById("screen");
+				screen.style.filter =
+					`brightness(${0.1 + 0.9 * brightness / DISPLAY_LEVEL_MAX}) ` +
+					`contrast(${0.5 + contrast / DISPLAY_LEVEL_MAX})`;
+			},
+		},
+	};
+	const response = await fetch("rscalc.wasm");
+	const module = await WebAssembly.instantiate(await response.arrayBuffer(), imports);
+	const calc = module.instance.exports;
+
+	const canvas = document.getElementById("screen");
+	const context = canvas.getContext("2d");
+	const image = context.createImageData(WIDTH, HEIGHT);
+
+	function draw() {
+		if (!calc.rscalc_frame_updated()) {
+			return;
+		}
+		// The memory can grow while the calculator runs, so view it each frame
+		const pixels = new Uint8Array(calc.memory.buffer, calc.rscalc_frame(),
+			WIDTH * HEIGHT * 4);
+		image.data.set(pixels);
+		context.putImageData(image, 0, 0);
+	}
+
+	function press(code) {
+		calc.rscalc_key(code);
+		draw();
+	}
+
+	// Taps on the screen are given in the pixels of the calculator, whatever
+	// size the canvas is shown at
+	canvas.addEventListener("pointerdown", (event) => {
+		event.preventDefault();
+		const rect = canvas.getBoundingClientRect();
+		const x = Math.floor((event.clientX - rect.left) * WIDTH / rect.width);
+		const y = Math.floor((event.clientY - rect.top) * HEIGHT / rect.height);
+		calc.rscalc_touch(x, y);
+		draw();
+	});
+
+	const keypad = document.getElementById("keypad");
+	for (const row of KEYPAD) {
+		for (const [label, code, kind] of row) {
+			const button = document.createElement("button");
+			button.textContent = label;
+			if (kind) {
+				button.className = kind;
+			}
+			button.addEventListener("pointerdown", (event) => {
+				event.preventDefault();
+				press(code);
+			});
+			keypad.appendChild(button);
+		}
+	}
+
+	// Copying from the page copies x, and pasted text is entered as a number
+	document.addEventListener("copy", (event) => {
+		const len = calc.rscalc_copy();
+		if (len == 0) {
+			return;
+		}
+		const text = new Uint8Array(calc.memory.buffer, calc.rscalc_text(), len);
+		event.clipboardData.setData("text/plain", new TextDecoder().decode(text));
+		event.preventDefault();
+	});
+	document.addEventListener("paste", (event) => {
+		const data = new TextEncoder().encode(event.clipboardData.getData("text/plain"));
+		const buffer = calc.rscalc_text_buffer(data.length);
+		new Uint8Array(calc.memory.buffer, buffer, data.length).set(data);
+		calc.rscalc_paste();
+		event.preventDefault();
+		draw();
+	});
+
+	document.addEventListener("keydown", (event) => {
+		const code = KEYBOARD[event.key.length == 1 ? event.key.toLowerCase() : event.key];
+		if (code === undefined || event.ctrlKey || event.metaKey || event.altKey) {
+			return;
+		}
+		event.preventDefault();
+		press(code);
+	});
+
+	calc.rscalc_start();
+	draw();
+	setInterval(() => {
+		calc.rscalc_tick();
+		draw();
+	}, FRAME_TIME_MS);
+}
+
+start();